@@ -18,6 +18,7 @@ use bevy::asset::ron;
 use bevy::log::{Level, LogPlugin};
 use bevy::prelude::*;
 use bevy::DefaultPlugins;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use clap::{Parser, ValueEnum};
 use lightyear::prelude::client::ClientConfig;
@@ -265,6 +266,7 @@ fn client_app(settings: Settings, net_config: client::NetConfig) -> (App, Client
     }));
     if settings.client.inspector {
         app.add_plugins(WorldInspectorPlugin::new());
+        app.add_systems(Update, misprediction_overlay);
     }
     let client_config = client::ClientConfig {
         shared: shared_config(Mode::Separate),
@@ -274,6 +276,37 @@ fn client_app(settings: Settings, net_config: client::NetConfig) -> (App, Client
     (app, client_config)
 }
 
+/// Display the misprediction statistics of each predicted component
+fn misprediction_overlay(mut contexts: EguiContexts, stats: Option<Res<client::PredictionStats>>) {
+    let Some(stats) = stats else {
+        return;
+    };
+    egui::Window::new("Mispredictions").show(contexts.ctx_mut(), |ui| {
+        egui::Grid::new("mispredictions")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("component");
+                ui.label("checks");
+                ui.label("rate");
+                ui.label("avg magnitude");
+                ui.label("max magnitude");
+                ui.end_row();
+                for (name, stats) in stats.all() {
+                    ui.label(name);
+                    ui.label(stats.checks.to_string());
+                    ui.label(format!("{:.1}%", stats.misprediction_rate() * 100.0));
+                    ui.label(
+                        stats
+                            .average_magnitude()
+                            .map_or("-".to_string(), |magnitude| format!("{magnitude:.3}")),
+                    );
+                    ui.label(format!("{:.3}", stats.max_magnitude));
+                    ui.end_row();
+                }
+            });
+    });
+}
+
 /// Build the server app with the `ServerPlugins` added.
 #[cfg(not(target_family = "wasm"))]
 fn server_app(
@@ -156,6 +156,9 @@ pub fn build_server_netcode_config(
         transport: transport_config,
         conditioner,
         compression: shared.compression,
+        relay: None,
+        socket: Default::default(),
+        liveness: None,
     };
     server::NetConfig::Netcode {
         config: netcode_config,
@@ -212,6 +215,7 @@ pub fn get_server_net_configs(settings: &Settings) -> Vec<server::NetConfig> {
                 &settings.shared,
                 server::ServerTransport::WebSocketServer {
                     server_addr: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), *local_port),
+                    tls: None,
                 },
             ),
             ServerTransports::Steam {
@@ -258,6 +262,9 @@ pub fn build_client_netcode_config(
         transport: transport_config,
         conditioner,
         compression: shared.compression,
+        relay: None,
+        socket: Default::default(),
+        liveness: None,
     };
     client::NetConfig::Netcode {
         auth,
@@ -300,7 +307,11 @@ pub fn get_client_net_config(settings: &Settings, client_id: u64) -> client::Net
             server_addr,
             settings.client.conditioner.as_ref(),
             &settings.shared,
-            client::ClientTransport::WebSocketClient { server_addr },
+            client::ClientTransport::WebSocketClient {
+                server_addr,
+                tls: None,
+                proxy: None,
+            },
         ),
         #[cfg(not(target_family = "wasm"))]
         ClientTransports::Steam { app_id } => client::NetConfig::Steam {
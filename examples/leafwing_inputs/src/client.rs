@@ -5,10 +5,11 @@ use bevy_xpbd_2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 use lightyear::prelude::client::*;
 use lightyear::prelude::*;
+use lightyear::utils::bevy_xpbd_2d::PhysicsReplicationSet;
 
 use crate::protocol::*;
 use crate::shared;
-use crate::shared::{color_from_id, shared_movement_behaviour, FixedSet};
+use crate::shared::{color_from_id, shared_movement_behaviour};
 
 pub struct ExampleClientPlugin;
 
@@ -29,7 +30,10 @@ impl Plugin for ExampleClientPlugin {
                 .before(PredictionSet::SpawnPrediction),
         );
         // all actions related-system that can be rolled back should be in FixedUpdate schedule
-        app.add_systems(FixedUpdate, player_movement.in_set(FixedSet::Main));
+        app.add_systems(
+            FixedUpdate,
+            player_movement.in_set(PhysicsReplicationSet::Main),
+        );
         app.add_systems(
             Update,
             (
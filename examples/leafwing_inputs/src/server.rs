@@ -6,10 +6,11 @@ use leafwing_input_manager::prelude::*;
 use lightyear::prelude::client::{Confirmed, Predicted};
 use lightyear::prelude::server::*;
 use lightyear::prelude::*;
+use lightyear::utils::bevy_xpbd_2d::PhysicsReplicationSet;
 
 use crate::protocol::*;
 use crate::shared;
-use crate::shared::{color_from_id, shared_movement_behaviour, FixedSet};
+use crate::shared::{color_from_id, shared_movement_behaviour};
 
 // Plugin for server-specific logic
 pub struct ExampleServerPlugin {
@@ -34,7 +35,7 @@ impl Plugin for ExampleServerPlugin {
             replicate_players.in_set(ServerReplicationSet::ClientReplication),
         );
         // the physics/FixedUpdates systems that consume inputs should be run in this set
-        app.add_systems(FixedUpdate, movement.in_set(FixedSet::Main));
+        app.add_systems(FixedUpdate, movement.in_set(PhysicsReplicationSet::Main));
     }
 }
 
@@ -59,7 +59,13 @@ impl Plugin for ExampleClientPlugin {
             )
                 .run_if(in_state(AppState::Game)),
         );
-        app.add_systems(Update, (lobby::lobby_ui, lobby::receive_start_game_message));
+        app.add_systems(
+            Update,
+            (
+                lobby::lobby_ui,
+                lobby::receive_start_game_message.run_if(resource_exists::<ClientConnection>),
+            ),
+        );
         app.add_systems(OnEnter(NetworkingState::Disconnected), on_disconnect);
     }
 }
@@ -101,8 +107,12 @@ fn on_disconnect(
     entities: Query<Entity, (Without<Window>, Without<Camera2d>)>,
     mut config: ResMut<ClientConfig>,
     settings: Res<Settings>,
-    connection: Res<ClientConnection>,
+    connection: Option<Res<ClientConnection>>,
 ) {
+    // the client never tried to connect
+    let Some(connection) = connection else {
+        return;
+    };
     let existing_client_id = connection.id();
 
     for entity in entities.iter() {
@@ -235,7 +245,8 @@ mod lobby {
         mut commands: Commands,
         mut contexts: EguiContexts,
         mut lobby_table: ResMut<LobbyTable>,
-        mut connection_manager: ResMut<ConnectionManager>,
+        // the connection manager only exists once the client started connecting
+        mut connection_manager: Option<ResMut<ConnectionManager>>,
         settings: Res<Settings>,
         config: ResMut<ClientConfig>,
         lobbies: Option<Res<Lobbies>>,
@@ -303,18 +314,29 @@ mod lobby {
                                                             // find the host of the game
                                                             let host = lobby_table.get_host();
                                                             // send a message to join the game
-                                                            let _ = connection_manager
-                                                                .send_message::<Channel1, _>(
-                                                                    &StartGame { lobby_id, host },
-                                                                );
+                                                            if let Some(connection_manager) =
+                                                                connection_manager.as_mut()
+                                                            {
+                                                                let _ = connection_manager
+                                                                    .send_message::<Channel1, _>(
+                                                                        &StartGame {
+                                                                            lobby_id,
+                                                                            host,
+                                                                        },
+                                                                    );
+                                                            }
                                                         }
                                                     } else {
                                                         if ui.button("Join Lobby").clicked() {
-                                                            connection_manager
-                                                                .send_message::<Channel1, _>(
-                                                                    &JoinLobby { lobby_id },
-                                                                )
-                                                                .unwrap();
+                                                            if let Some(connection_manager) =
+                                                                connection_manager.as_mut()
+                                                            {
+                                                                connection_manager
+                                                                    .send_message::<Channel1, _>(
+                                                                        &JoinLobby { lobby_id },
+                                                                    )
+                                                                    .unwrap();
+                                                            }
                                                             next_app_state.set(AppState::Lobby {
                                                                 joined_lobby: Some(lobby_id),
                                                             });
@@ -383,6 +405,9 @@ mod lobby {
                         let _ = ui.button("Connecting");
                     }
                     NetworkingState::Connected => {
+                        let Some(connection_manager) = connection_manager.as_mut() else {
+                            return;
+                        };
                         match app_state.get() {
                             AppState::Lobby { joined_lobby } => {
                                 if let Some(lobby_id) = joined_lobby {
@@ -101,7 +101,6 @@ mod game {
     /// to join the lobby list)
     pub(crate) fn handle_connections(
         mut connections: EventReader<ConnectEvent>,
-        server: ResMut<ConnectionManager>,
         mut commands: Commands,
     ) {
         for connection in connections.read() {
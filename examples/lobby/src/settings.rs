@@ -157,6 +157,9 @@ pub fn build_server_netcode_config(
         transport: transport_config,
         conditioner,
         compression: shared.compression,
+        relay: None,
+        socket: Default::default(),
+        liveness: None,
     };
     server::NetConfig::Netcode {
         config: netcode_config,
@@ -213,6 +216,7 @@ pub fn get_server_net_configs(settings: &Settings) -> Vec<server::NetConfig> {
                 &settings.shared,
                 TransportConfig::WebSocketServer {
                     server_addr: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), *local_port),
+                    tls: None,
                 },
             ),
             ServerTransports::Steam {
@@ -288,6 +292,7 @@ pub fn get_host_server_net_configs(settings: &Settings) -> Vec<server::NetConfig
                 &settings.shared,
                 TransportConfig::WebSocketServer {
                     server_addr: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), *local_port),
+                    tls: None,
                 },
             ),
             ServerTransports::Steam {
@@ -334,6 +339,9 @@ pub fn build_client_netcode_config(
         transport: transport_config,
         conditioner,
         compression: shared.compression,
+        relay: None,
+        socket: Default::default(),
+        liveness: None,
     };
     client::NetConfig::Netcode {
         auth,
@@ -376,7 +384,11 @@ pub fn get_client_net_config(settings: &Settings) -> client::NetConfig {
             server_addr,
             settings.client.conditioner.as_ref(),
             &settings.shared,
-            TransportConfig::WebSocketClient { server_addr },
+            TransportConfig::WebSocketClient {
+                server_addr,
+                tls: None,
+                proxy: None,
+            },
         ),
         #[cfg(not(target_family = "wasm"))]
         ClientTransports::Steam { app_id } => client::NetConfig::Steam {
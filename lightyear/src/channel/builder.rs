@@ -2,29 +2,42 @@
 use bevy::prelude::TypePath;
 use bevy::reflect::Reflect;
 use bevy::utils::Duration;
+use governor::Quota;
 
 use lightyear_macros::ChannelInternal;
 
+use crate::channel::receivers::nack::NackReceiver;
 use crate::channel::receivers::ordered_reliable::OrderedReliableReceiver;
 use crate::channel::receivers::sequenced_reliable::SequencedReliableReceiver;
 use crate::channel::receivers::sequenced_unreliable::SequencedUnreliableReceiver;
+use crate::channel::receivers::stream::StreamReceiver;
 use crate::channel::receivers::tick_unreliable::TickUnreliableReceiver;
 use crate::channel::receivers::unordered_reliable::UnorderedReliableReceiver;
 use crate::channel::receivers::unordered_unreliable::UnorderedUnreliableReceiver;
 use crate::channel::receivers::ChannelReceiver;
 use crate::channel::senders::reliable::ReliableSender;
+use crate::channel::senders::sequenced_reliable::SequencedReliableSender;
 use crate::channel::senders::sequenced_unreliable::SequencedUnreliableSender;
+use crate::channel::senders::stream::StreamSender;
 use crate::channel::senders::tick_unreliable::TickUnreliableSender;
 use crate::channel::senders::unordered_unreliable::UnorderedUnreliableSender;
 use crate::channel::senders::unordered_unreliable_with_acks::UnorderedUnreliableWithAcksSender;
-use crate::channel::senders::ChannelSender;
+use crate::channel::senders::{ChannelSend, ChannelSender};
+use crate::channel::stats::ChannelStatistics;
+use crate::packet::packet::FRAGMENT_SIZE;
 use crate::prelude::ChannelKind;
+use crate::transport::Delivery;
 
 /// A ChannelContainer is a struct that implements the [`Channel`] trait
+/// Minimum value of [`ChannelSettings::fragment_threshold`]
+const MIN_FRAGMENT_THRESHOLD: usize = 64;
+
 pub struct ChannelContainer {
     pub setting: ChannelSettings,
     pub(crate) receiver: ChannelReceiver,
     pub(crate) sender: ChannelSender,
+    /// Number of messages and bytes sent and received on the channel
+    pub(crate) stats: ChannelStatistics,
 }
 
 /// A `Channel` is an abstraction for a way to send messages over the network
@@ -74,20 +87,35 @@ impl ChannelBuilder {
 
 impl ChannelContainer {
     pub fn new(settings: ChannelSettings) -> Self {
-        let receiver: ChannelReceiver;
+        let mut receiver: ChannelReceiver;
         let sender: ChannelSender;
         let settings_clone = settings.clone();
         match settings.mode {
             ChannelMode::UnorderedUnreliableWithAcks => {
-                receiver = UnorderedUnreliableReceiver::new().into();
+                let mut unordered_receiver = UnorderedUnreliableReceiver::new();
+                if let Some(window) = settings.dedup_window {
+                    unordered_receiver = unordered_receiver.with_dedup_window(window);
+                }
+                receiver = unordered_receiver.into();
                 sender = UnorderedUnreliableWithAcksSender::new().into();
             }
             ChannelMode::UnorderedUnreliable => {
-                receiver = UnorderedUnreliableReceiver::new().into();
-                sender = UnorderedUnreliableSender::new().into();
+                let mut unordered_receiver = UnorderedUnreliableReceiver::new();
+                let mut unordered_sender = UnorderedUnreliableSender::new();
+                if let Some(window) = settings.dedup_window {
+                    unordered_receiver = unordered_receiver.with_dedup_window(window);
+                    // the messages need an id to be deduplicated
+                    unordered_sender = unordered_sender.with_message_ids();
+                }
+                receiver = unordered_receiver.into();
+                sender = unordered_sender.into();
             }
             ChannelMode::SequencedUnreliable => {
-                receiver = SequencedUnreliableReceiver::new().into();
+                let mut sequenced_receiver = SequencedUnreliableReceiver::new();
+                if let Some(window) = settings.dedup_window {
+                    sequenced_receiver = sequenced_receiver.with_dedup_window(window);
+                }
+                receiver = sequenced_receiver.into();
                 sender = SequencedUnreliableSender::new().into();
             }
             ChannelMode::UnorderedReliable(reliable_settings) => {
@@ -96,7 +124,7 @@ impl ChannelContainer {
             }
             ChannelMode::SequencedReliable(reliable_settings) => {
                 receiver = SequencedReliableReceiver::new().into();
-                sender = ReliableSender::new(reliable_settings).into();
+                sender = SequencedReliableSender::new(reliable_settings).into();
             }
             ChannelMode::OrderedReliable(reliable_settings) => {
                 receiver = OrderedReliableReceiver::new().into();
@@ -106,12 +134,43 @@ impl ChannelContainer {
                 receiver = TickUnreliableReceiver::new().into();
                 sender = TickUnreliableSender::new().into();
             }
+            ChannelMode::Stream(stream_settings) => {
+                receiver = StreamReceiver::new(stream_settings.max_transfer_size as usize)
+                    .with_max_concurrent_transfers(
+                        stream_settings.max_concurrent_transfers as usize,
+                    )
+                    .with_max_buffered_bytes(stream_settings.max_buffered_bytes as usize)
+                    .into();
+                sender = StreamSender::new(stream_settings).into();
+            }
         }
-        Self {
+        if let Some(reliable_settings) = settings_clone.mode.reliable_settings() {
+            if matches!(
+                reliable_settings.resend_strategy,
+                ResendStrategy::Nack { .. }
+            ) {
+                receiver = NackReceiver::new(receiver, reliable_settings.clone()).into();
+            }
+        }
+        let mut container = Self {
             setting: settings_clone,
             receiver,
             sender,
-        }
+            stats: ChannelStatistics::default(),
+        };
+        container.set_fragment_size(FRAGMENT_SIZE);
+        container
+    }
+
+    /// Set the size of the fragments of the channel, given the maximum fragment size allowed by the packet size
+    pub(crate) fn set_fragment_size(&mut self, max_fragment_size: usize) {
+        let fragment_size = self
+            .setting
+            .fragment_threshold
+            .map_or(max_fragment_size, |threshold| {
+                threshold.clamp(MIN_FRAGMENT_THRESHOLD, max_fragment_size)
+            });
+        self.sender.set_fragment_size(fragment_size);
     }
 }
 
@@ -122,6 +181,39 @@ pub struct ChannelSettings {
     pub direction: ChannelDirection,
     /// Sets the priority of the channel. The final priority of a message will be `MessagePriority * ChannelPriority`
     pub priority: f32,
+    /// If set, the receiver keeps track of the last `dedup_window` message ids that it received,
+    /// and discards the messages that it already received (for example because the packet was
+    /// duplicated by the network).
+    ///
+    /// This only applies to the unreliable modes [`ChannelMode::UnorderedUnreliable`],
+    /// [`ChannelMode::UnorderedUnreliableWithAcks`] and [`ChannelMode::SequencedUnreliable`];
+    /// reliable channels always deliver each message only once.
+    ///
+    /// `Some(0)` disables the deduplication, like `None`.
+    pub dedup_window: Option<u16>,
+    /// How the packets of this channel are sent on transports that support both unreliable datagrams
+    /// and reliable streams (WebTransport). Other transports ignore this setting.
+    ///
+    /// [`Delivery::Stream`] is a good fit for bulky reliable channels, since the packets don't need to
+    /// be resent and are not limited by the datagram congestion window.
+    pub delivery: Delivery,
+    /// Optional bandwidth budget (in bytes) for the messages of this channel.
+    ///
+    /// Once the budget is exhausted, the remaining messages of the channel are not sent, so that
+    /// they cannot crowd out the messages of other channels. Unreliable messages are dropped,
+    /// reliable messages will be resent later.
+    /// The budget is enforced even if the global bandwidth cap is disabled.
+    pub send_budget: Option<Quota>,
+    /// If false, the packets that only contain messages of channels with `compress` disabled are
+    /// never compressed, even if packet compression is enabled in the `PacketConfig`.
+    ///
+    /// This is useful for channels whose content is already compressed (for example audio).
+    pub compress: bool,
+    /// If set, the messages bigger than this number of bytes are split into fragments of at most this size.
+    ///
+    /// By default (or if the threshold is too big) the fragments are as big as the packet size of the connection allows.
+    /// The threshold is at least 64 bytes, and a message can be split into at most 255 fragments.
+    pub fragment_threshold: Option<usize>,
 }
 
 impl Default for ChannelSettings {
@@ -130,6 +222,11 @@ impl Default for ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             direction: ChannelDirection::Bidirectional,
             priority: 1.0,
+            dedup_window: None,
+            delivery: Delivery::Datagram,
+            send_budget: None,
+            compress: true,
+            fragment_threshold: None,
         }
     }
 }
@@ -149,13 +246,29 @@ pub enum ChannelMode {
     /// Messages may arrive out-of-order, but we make sure (with retries, acks) that the message
     /// will arrive
     UnorderedReliable(ReliableSettings),
-    /// Same as unordered reliable, but the messages are sequenced (only the newest message is accepted)
+    /// Same as unordered reliable, but the messages are sequenced (only the newest message is accepted).
+    ///
+    /// The newest message is guaranteed to arrive, but older messages that have been superseded
+    /// by a newer one are not resent.
     SequencedReliable(ReliableSettings),
     /// Messages will arrive in the correct order at the destination
     OrderedReliable(ReliableSettings),
-    /// Inputs from the client are associated with the current tick on the client.
+    /// Messages from the client are associated with the current tick on the client.
     /// The server will buffer them and only receive them on the same tick.
+    ///
+    /// This is useful for messages that must be processed by the server at the exact tick
+    /// at which the client sent them (for example command frames in a lockstep game).
+    /// The messages are unreliable, and the messages that arrive after their tick are dropped.
+    /// Only use this mode for [`ChannelDirection::ClientToServer`] channels, because the server
+    /// runs behind the client.
     TickBuffered,
+    /// Designed for large payloads (map data, replays, etc.) that are several megabytes big.
+    ///
+    /// Each message is a transfer that is split into chunks, which are sent reliably at a capped rate
+    /// so that the transfer doesn't starve the other channels.
+    /// The progress of the transfers is reported with [`TransferProgress`](crate::channel::stream::TransferProgress)
+    /// events on both ends, and an ongoing transfer can be aborted.
+    Stream(StreamSettings),
 }
 
 impl ChannelMode {
@@ -168,6 +281,18 @@ impl ChannelMode {
             ChannelMode::SequencedReliable(_) => true,
             ChannelMode::OrderedReliable(_) => true,
             ChannelMode::TickBuffered => false,
+            ChannelMode::Stream(_) => true,
+        }
+    }
+
+    /// The reliability settings of the channel, if it is reliable
+    pub(crate) fn reliable_settings(&self) -> Option<&ReliableSettings> {
+        match self {
+            ChannelMode::UnorderedReliable(settings)
+            | ChannelMode::SequencedReliable(settings)
+            | ChannelMode::OrderedReliable(settings) => Some(settings),
+            ChannelMode::Stream(settings) => Some(&settings.reliable_settings),
+            _ => None,
         }
     }
 
@@ -181,6 +306,7 @@ impl ChannelMode {
             ChannelMode::SequencedReliable(_) => true,
             ChannelMode::OrderedReliable(_) => true,
             ChannelMode::TickBuffered => false,
+            ChannelMode::Stream(_) => true,
         }
     }
 }
@@ -199,6 +325,27 @@ pub struct ReliableSettings {
     pub rtt_resend_factor: f32,
     /// Minimum duration to wait before resending a packet if it has not been acked
     pub rtt_resend_min_delay: Duration,
+    /// How we decide when a message that has not been acked should be resent
+    pub resend_strategy: ResendStrategy,
+    /// If true, the messages of the client that were not acked when the connection was lost are
+    /// sent again after the client reconnects.
+    ///
+    /// The server might receive the same message twice if the connection was lost before the ack
+    /// of the message was received.
+    pub persist_outbox: bool,
+    /// If set, the messages that are not acked after this duration stop being retransmitted,
+    /// and a [`MessageExpiredEvent`](crate::shared::events::components::MessageExpiredEvent) is emitted.
+    ///
+    /// The duration can also be set per message, for example with
+    /// [`send_message_with_ttl`](crate::client::connection::ConnectionManager::send_message_with_ttl)
+    pub max_age: Option<Duration>,
+    /// If set, at most this number of fragments are sent every time the packets are sent.
+    ///
+    /// This paces the sending of very large messages, so that they don't use all the outgoing bandwidth
+    /// at once and cause packet loss for the other channels.
+    /// The progress of the fragmented messages is reported with
+    /// [`FragmentProgressEvent`](crate::shared::events::components::FragmentProgressEvent).
+    pub max_fragments_per_send: Option<usize>,
 }
 
 impl Default for ReliableSettings {
@@ -206,17 +353,132 @@ impl Default for ReliableSettings {
         Self {
             rtt_resend_factor: 1.5,
             rtt_resend_min_delay: Duration::default(),
+            resend_strategy: ResendStrategy::default(),
+            persist_outbox: false,
+            max_age: None,
+            max_fragments_per_send: None,
         }
     }
 }
 
 impl ReliableSettings {
-    pub(crate) fn resend_delay(&self, rtt: Duration) -> Duration {
-        let delay = rtt.mul_f32(self.rtt_resend_factor);
+    pub fn with_resend_strategy(mut self, resend_strategy: ResendStrategy) -> Self {
+        self.resend_strategy = resend_strategy;
+        self
+    }
+
+    pub fn with_persist_outbox(mut self, persist_outbox: bool) -> Self {
+        self.persist_outbox = persist_outbox;
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn with_max_fragments_per_send(mut self, max_fragments_per_send: usize) -> Self {
+        self.max_fragments_per_send = Some(max_fragments_per_send);
+        self
+    }
+
+    /// Duration to wait before resending a message that has not been acked
+    ///
+    /// `rtt` is the smoothed round-trip time and `rtt_variance` is the variation of the round-trip time
+    pub(crate) fn resend_delay(&self, rtt: Duration, rtt_variance: Duration) -> Duration {
+        let delay = match self.resend_strategy {
+            ResendStrategy::Fixed(delay) => delay,
+            ResendStrategy::RttVariance { k } => rtt + rtt_variance.mul_f32(k),
+            ResendStrategy::RttFactor | ResendStrategy::ImmediateOnNack => {
+                rtt.mul_f32(self.rtt_resend_factor)
+            }
+            ResendStrategy::Nack { fallback_delay } => fallback_delay,
+        };
         std::cmp::max(delay, self.rtt_resend_min_delay)
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamSettings {
+    /// Reliability settings used for the chunks of the transfers
+    pub reliable_settings: ReliableSettings,
+    /// Maximum number of bytes per second that are sent on the channel
+    pub send_rate: u32,
+    /// Maximum size (in bytes) of a transfer. The receiver rejects bigger transfers.
+    pub max_transfer_size: u32,
+    /// Maximum number of transfers that the receiver reassembles at the same time.
+    /// The receiver rejects the chunks of new transfers beyond that.
+    pub max_concurrent_transfers: u32,
+    /// Maximum number of bytes that the receiver buffers for the transfers that are being reassembled.
+    pub max_buffered_bytes: u32,
+}
+
+impl Default for StreamSettings {
+    fn default() -> Self {
+        Self {
+            reliable_settings: ReliableSettings::default(),
+            send_rate: 256 * 1024,
+            max_transfer_size: 64 * 1024 * 1024,
+            max_concurrent_transfers: 16,
+            max_buffered_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl StreamSettings {
+    pub fn with_send_rate(mut self, send_rate: u32) -> Self {
+        self.send_rate = send_rate;
+        self
+    }
+
+    pub fn with_max_transfer_size(mut self, max_transfer_size: u32) -> Self {
+        self.max_transfer_size = max_transfer_size;
+        self
+    }
+
+    pub fn with_max_concurrent_transfers(mut self, max_concurrent_transfers: u32) -> Self {
+        self.max_concurrent_transfers = max_concurrent_transfers;
+        self
+    }
+
+    pub fn with_max_buffered_bytes(mut self, max_buffered_bytes: u32) -> Self {
+        self.max_buffered_bytes = max_buffered_bytes;
+        self
+    }
+}
+
+/// Strategy used by reliable channels to decide when a message should be resent.
+///
+/// In all cases the delay before resending is at least [`ReliableSettings::rtt_resend_min_delay`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ResendStrategy {
+    /// Resend the message after `rtt * rtt_resend_factor`
+    #[default]
+    RttFactor,
+    /// Resend the message after a fixed interval, regardless of the network conditions
+    Fixed(Duration),
+    /// Resend the message after `SRTT + k * RTTVAR`, similarly to the TCP retransmission
+    /// timeout ([RFC 6298](https://datatracker.ietf.org/doc/html/rfc6298)).
+    ///
+    /// This adapts to the jitter of the connection; TCP uses `k = 4.0`
+    RttVariance { k: f32 },
+    /// Resend the message as soon as the remote acks more recent packets, but not the packet
+    /// that contained the message.
+    ///
+    /// If no such packet is received, the message is resent after `rtt * rtt_resend_factor`
+    ImmediateOnNack,
+    /// The receiver keeps track of the gaps in the message ids that it receives, and requests the
+    /// missing messages with a negative acknowledgement (NACK). The sender only resends the messages
+    /// that were requested, instead of resending every unacked message on a timer.
+    ///
+    /// This cuts redundant retransmissions on low-loss links for high-frequency channels.
+    /// The receiver repeats a NACK every `rtt * rtt_resend_factor` until it receives the message.
+    ///
+    /// The messages are still resent after `fallback_delay`, in case the most recent messages
+    /// were lost (the receiver cannot detect a gap) or for fragmented messages.
+    Nack { fallback_delay: Duration },
+}
+
 /// Default channel to replicate entity actions.
 /// This is an Unordered Reliable channel.
 /// (SpawnEntity, DespawnEntity, InsertComponent, RemoveComponent)
@@ -228,11 +490,42 @@ pub struct EntityActionsChannel;
 /// This is a Sequenced Unreliable channel
 pub struct EntityUpdatesChannel;
 
+/// Channel used to send the [snapshot of the replicated world](crate::server::config::LateJoinReplication::Snapshot)
+/// to the clients that connect while entities are already replicated.
+/// This is a Stream channel, so that the snapshot is not limited by the maximum number of fragments of a message.
+#[derive(ChannelInternal)]
+pub struct WorldSnapshotChannel;
+
 /// Default channel to send pings. This is a Sequenced Unreliable channel, because
 /// there is no point in getting older pings.
 #[derive(ChannelInternal)]
 pub struct PingChannel;
 
+/// Internal channel used to request the messages that are missing on channels that use
+/// [`ResendStrategy::Nack`]. This is an Unordered Unreliable channel.
+#[derive(ChannelInternal)]
+pub struct NackChannel;
+
+/// Internal channel used to agree on the network ids of the channels that are registered at runtime.
+/// This is an Ordered Reliable channel.
+#[derive(ChannelInternal)]
+pub struct ChannelSyncChannel;
+
+/// Channel used to replicate the [`ServerStats`](crate::server::stats::ServerStats) to the admin clients.
+/// This is a Sequenced Unreliable channel, because only the latest stats are relevant.
+#[derive(ChannelInternal)]
+pub struct ServerStatsChannel;
+
+/// Channel used by the [inspector](crate::server::inspector) clients to request the snapshots of the server world,
+/// and by the server to send them. This is an Ordered Reliable channel.
+#[derive(ChannelInternal)]
+pub struct InspectorChannel;
+
+/// Channel used to send the events scheduled with the [`EventScheduler`](crate::shared::scheduled_events::EventScheduler).
+/// This is an Unordered Reliable channel.
+#[derive(ChannelInternal)]
+pub struct ScheduledEventChannel;
+
 #[derive(ChannelInternal)]
 /// Default channel to send inputs from client to server. This is a Sequenced Unreliable channel.
 pub struct InputChannel;
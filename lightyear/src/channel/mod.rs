@@ -3,3 +3,5 @@
 pub mod builder;
 pub(crate) mod receivers;
 pub(crate) mod senders;
+pub mod stats;
+pub mod stream;
@@ -0,0 +1,62 @@
+use std::collections::VecDeque;
+
+use crate::packet::message::MessageId;
+
+/// Keeps a rolling window of the most recently received [`MessageId`]s, so that
+/// duplicated packets don't deliver the same message twice on unreliable channels
+#[derive(Debug)]
+pub(crate) struct MessageDeduplicator {
+    /// Maximum number of message ids to keep track of
+    window: usize,
+    /// The most recently received message ids, from oldest to newest
+    recent_message_ids: VecDeque<MessageId>,
+}
+
+impl MessageDeduplicator {
+    pub(crate) fn new(window: u16) -> Self {
+        Self {
+            window: window as usize,
+            recent_message_ids: VecDeque::with_capacity(window as usize),
+        }
+    }
+
+    /// Returns true if the message was already received recently.
+    /// Otherwise, the message id gets recorded in the window.
+    ///
+    /// Messages without ids cannot be deduplicated
+    pub(crate) fn is_duplicate(&mut self, message_id: Option<MessageId>) -> bool {
+        let Some(message_id) = message_id else {
+            return false;
+        };
+        if self.recent_message_ids.contains(&message_id) {
+            return true;
+        }
+        if self.recent_message_ids.len() >= self.window {
+            self.recent_message_ids.pop_front();
+        }
+        self.recent_message_ids.push_back(message_id);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_window() {
+        let mut dedup = MessageDeduplicator::new(2);
+        assert!(!dedup.is_duplicate(None));
+        assert!(!dedup.is_duplicate(None));
+
+        assert!(!dedup.is_duplicate(Some(MessageId(0))));
+        assert!(dedup.is_duplicate(Some(MessageId(0))));
+        assert!(!dedup.is_duplicate(Some(MessageId(1))));
+        assert!(dedup.is_duplicate(Some(MessageId(0))));
+
+        // message 0 falls out of the window
+        assert!(!dedup.is_duplicate(Some(MessageId(2))));
+        assert!(dedup.is_duplicate(Some(MessageId(1))));
+        assert!(!dedup.is_duplicate(Some(MessageId(0))));
+    }
+}
@@ -5,7 +5,6 @@ use bytes::Bytes;
 use tracing::trace;
 
 use crate::packet::message::{FragmentData, MessageId, SingleData};
-use crate::packet::packet::FRAGMENT_SIZE;
 use crate::shared::time_manager::WrappedTime;
 
 /// `FragmentReceiver` is used to reconstruct fragmented messages
@@ -45,7 +44,7 @@ impl FragmentReceiver {
         // completed the fragmented message!
         if let Some(payload) = fragment_message.receive_fragment(
             fragment.fragment_id as usize,
-            fragment.bytes.as_ref(),
+            fragment.bytes,
             current_time,
         )? {
             self.fragment_messages.remove(&fragment.message_id);
@@ -67,8 +66,10 @@ pub struct FragmentConstructor {
     num_fragments: usize,
     num_received_fragments: usize,
     received: Vec<bool>,
-    // bytes: Bytes,
-    bytes: Vec<u8>,
+    /// The fragments received so far, indexed by fragment index.
+    /// The fragment size depends on the packet size used by the sender, so we cannot
+    /// write the fragments directly at their final offset.
+    fragments: Vec<Bytes>,
 
     last_received: Option<WrappedTime>,
 }
@@ -79,7 +80,7 @@ impl FragmentConstructor {
             num_fragments,
             num_received_fragments: 0,
             received: vec![false; num_fragments],
-            bytes: vec![0; num_fragments * FRAGMENT_SIZE],
+            fragments: vec![Bytes::new(); num_fragments],
             last_received: None,
         }
     }
@@ -87,31 +88,22 @@ impl FragmentConstructor {
     pub fn receive_fragment(
         &mut self,
         fragment_index: usize,
-        bytes: &[u8],
+        bytes: Bytes,
         received_time: Option<WrappedTime>,
     ) -> Result<Option<Bytes>> {
         self.last_received = received_time;
 
-        let is_last_fragment = fragment_index == self.num_fragments - 1;
         // TODO: check sizes?
 
         if !self.received[fragment_index] {
             self.received[fragment_index] = true;
             self.num_received_fragments += 1;
-
-            if is_last_fragment {
-                let len = (self.num_fragments - 1) * FRAGMENT_SIZE + bytes.len();
-                self.bytes.resize(len, 0);
-            }
-
-            let start = fragment_index * FRAGMENT_SIZE;
-            let end = start + bytes.len();
-            self.bytes[start..end].copy_from_slice(bytes);
+            self.fragments[fragment_index] = bytes;
         }
 
         if self.num_received_fragments == self.num_fragments {
             trace!("Received all fragments!");
-            let payload = std::mem::take(&mut self.bytes);
+            let payload = std::mem::take(&mut self.fragments).concat();
             return Ok(Some(payload.into()));
         }
 
@@ -122,6 +114,7 @@ impl FragmentConstructor {
 #[cfg(test)]
 mod tests {
     use crate::channel::senders::fragment_sender::FragmentSender;
+    use crate::packet::packet::FRAGMENT_SIZE;
 
     use super::*;
 
@@ -5,9 +5,15 @@ use crate::packet::message::SingleData;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
 
+/// Discard duplicate messages on unreliable channels
+pub(crate) mod dedup;
+
 /// Utilities to receive a Message from multiple fragment packets
 pub(crate) mod fragment_receiver;
 
+/// Request the missing messages of a reliable channel with negative acknowledgements
+pub(crate) mod nack;
+
 /// Receive messages in an Ordered Reliable manner
 pub(crate) mod ordered_reliable;
 
@@ -17,6 +23,9 @@ pub(crate) mod sequenced_reliable;
 /// Receive messages in an Sequenced Unreliable manner
 pub(crate) mod sequenced_unreliable;
 
+/// Reassemble the transfers of a Stream channel
+pub(crate) mod stream;
+
 pub(crate) mod tick_unreliable;
 
 /// Receive messages in an Unordered Reliable manner
@@ -36,6 +45,11 @@ pub trait ChannelReceive {
 
     /// Reads a message from the internal buffer to get its content
     fn read_message(&mut self) -> Option<SingleData>;
+
+    /// Number of messages that were discarded because a more recent message was already received
+    fn dropped_messages(&self) -> u64 {
+        0
+    }
 }
 
 /// This enum contains the various types of receivers available
@@ -47,4 +61,6 @@ pub enum ChannelReceiver {
     SequencedReliable(sequenced_reliable::SequencedReliableReceiver),
     UnorderedReliable(unordered_reliable::UnorderedReliableReceiver),
     TickUnreliable(tick_unreliable::TickUnreliableReceiver),
+    Stream(stream::StreamReceiver),
+    Nack(nack::NackReceiver),
 }
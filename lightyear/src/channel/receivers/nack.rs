@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+
+use anyhow::bail;
+use bevy::utils::Duration;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::channel::builder::ReliableSettings;
+use crate::channel::receivers::{ChannelReceive, ChannelReceiver};
+use crate::packet::message::{MessageContainer, MessageId, SingleData};
+use crate::protocol::registry::NetId;
+use crate::shared::tick_manager::TickManager;
+use crate::shared::time_manager::{TimeManager, WrappedTime};
+
+/// Maximum number of missing message ids that we keep track of
+const MAX_MISSING_MESSAGES: usize = 512;
+
+/// Maximum number of message ids that are requested in a single [`NackMessage`]
+const MAX_NACKS_PER_MESSAGE: usize = 256;
+
+/// Control message sent by the receiver to request the messages that it is missing on a channel
+#[derive(Debug, PartialEq)]
+pub(crate) struct NackMessage {
+    pub(crate) channel_id: NetId,
+    pub(crate) message_ids: Vec<MessageId>,
+}
+
+impl NackMessage {
+    pub(crate) fn to_bytes(&self) -> Bytes {
+        let mut bytes = BytesMut::with_capacity(2 + 2 * self.message_ids.len());
+        bytes.put_u16_le(self.channel_id);
+        for message_id in &self.message_ids {
+            bytes.put_u16_le(message_id.0);
+        }
+        bytes.freeze()
+    }
+
+    pub(crate) fn from_bytes(mut bytes: Bytes) -> anyhow::Result<Self> {
+        if bytes.remaining() < 2 || bytes.remaining() % 2 != 0 {
+            bail!("invalid nack message");
+        }
+        let channel_id = bytes.get_u16_le();
+        let mut message_ids = Vec::with_capacity(bytes.remaining() / 2);
+        while bytes.has_remaining() {
+            message_ids.push(MessageId(bytes.get_u16_le()));
+        }
+        Ok(Self {
+            channel_id,
+            message_ids,
+        })
+    }
+}
+
+/// Wraps a reliable receiver to keep track of the gaps in the message ids that are received,
+/// so that the missing messages can be requested from the sender with a [`NackMessage`]
+pub struct NackReceiver {
+    receiver: Box<ChannelReceiver>,
+    reliable_settings: ReliableSettings,
+    /// Id of the next message that we expect to receive
+    next_message_id: MessageId,
+    /// Messages that are older than `next_message_id` but have not been received yet,
+    /// along with the last time that they were requested.
+    /// They are kept in the order in which the gaps were detected (the message ids wrap around)
+    missing_messages: VecDeque<(MessageId, Option<WrappedTime>)>,
+    current_time: WrappedTime,
+    current_rtt: Duration,
+}
+
+impl NackReceiver {
+    pub(crate) fn new(receiver: ChannelReceiver, reliable_settings: ReliableSettings) -> Self {
+        Self {
+            receiver: Box::new(receiver),
+            reliable_settings,
+            next_message_id: MessageId(0),
+            missing_messages: VecDeque::new(),
+            current_time: WrappedTime::default(),
+            current_rtt: Duration::default(),
+        }
+    }
+
+    pub(crate) fn set_rtt(&mut self, rtt: Duration) {
+        self.current_rtt = rtt;
+    }
+
+    /// Returns the receiver that is wrapped by the NACK tracking
+    pub(crate) fn inner_mut(&mut self) -> &mut ChannelReceiver {
+        &mut self.receiver
+    }
+
+    /// Collect the ids of the missing messages that should be requested.
+    ///
+    /// A message is requested again if it is still missing `rtt * rtt_resend_factor` after the previous request.
+    pub(crate) fn collect_nacks(&mut self) -> Vec<MessageId> {
+        let nack_delay = std::cmp::max(
+            self.current_rtt
+                .mul_f32(self.reliable_settings.rtt_resend_factor),
+            self.reliable_settings.rtt_resend_min_delay,
+        );
+        let nack_delay = chrono::Duration::from_std(nack_delay).unwrap();
+        let current_time = self.current_time;
+        self.missing_messages
+            .iter_mut()
+            .filter(|(_, last_nack)| {
+                last_nack.map_or(true, |last_nack| current_time - last_nack > nack_delay)
+            })
+            .take(MAX_NACKS_PER_MESSAGE)
+            .map(|(message_id, last_nack)| {
+                *last_nack = Some(current_time);
+                *message_id
+            })
+            .collect()
+    }
+
+    fn track_message_id(&mut self, message_id: MessageId) {
+        let gap = message_id - self.next_message_id;
+        if gap < 0 {
+            self.missing_messages.retain(|(id, _)| *id != message_id);
+            return;
+        }
+        // all the messages between the next expected message and this one are missing
+        let gap = std::cmp::min(gap as usize, MAX_MISSING_MESSAGES);
+        for i in 0..gap {
+            self.missing_messages
+                .push_back((message_id - (gap - i) as u16, None));
+        }
+        while self.missing_messages.len() > MAX_MISSING_MESSAGES {
+            self.missing_messages.pop_front();
+        }
+        self.next_message_id = message_id + MessageId(1);
+    }
+}
+
+impl ChannelReceive for NackReceiver {
+    fn update(&mut self, time_manager: &TimeManager, tick_manager: &TickManager) {
+        self.current_time = time_manager.current_time();
+        self.receiver.update(time_manager, tick_manager);
+    }
+
+    fn buffer_recv(&mut self, message: MessageContainer) -> anyhow::Result<()> {
+        if let Some(message_id) = message.message_id() {
+            self.track_message_id(message_id);
+        }
+        self.receiver.buffer_recv(message)
+    }
+
+    fn read_message(&mut self) -> Option<SingleData> {
+        self.receiver.read_message()
+    }
+
+    fn dropped_messages(&self) -> u64 {
+        self.receiver.dropped_messages()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::channel::receivers::unordered_reliable::UnorderedReliableReceiver;
+
+    use super::*;
+
+    fn receive(receiver: &mut NackReceiver, message_id: u16) {
+        receiver
+            .buffer_recv(
+                SingleData::new(Some(MessageId(message_id)), Bytes::from_static(b"a"), 1.0).into(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_nack_receiver_tracks_gaps() {
+        let mut receiver = NackReceiver::new(
+            UnorderedReliableReceiver::new().into(),
+            ReliableSettings {
+                rtt_resend_factor: 1.0,
+                ..Default::default()
+            },
+        );
+        receiver.set_rtt(Duration::from_millis(100));
+
+        receive(&mut receiver, 0);
+        assert!(receiver.collect_nacks().is_empty());
+
+        // messages 1 and 2 are missing
+        receive(&mut receiver, 3);
+        assert_eq!(receiver.collect_nacks(), vec![MessageId(1), MessageId(2)]);
+        // the messages are not requested again before the rtt has elapsed
+        assert!(receiver.collect_nacks().is_empty());
+
+        receive(&mut receiver, 1);
+        receiver.current_time += Duration::from_millis(150);
+        assert_eq!(receiver.collect_nacks(), vec![MessageId(2)]);
+
+        // the messages are still read from the inner receiver
+        assert_eq!(std::iter::from_fn(|| receiver.read_message()).count(), 3);
+    }
+
+    /// The missing messages are requested in order, and the oldest ones are evicted first,
+    /// even when the message ids wrap around
+    #[test]
+    fn test_nack_receiver_message_id_wrapping() {
+        let mut receiver = NackReceiver::new(
+            UnorderedReliableReceiver::new().into(),
+            ReliableSettings::default(),
+        );
+        receiver.next_message_id = MessageId(65533);
+
+        // messages 65533, 65534, 65535 and 0 are missing
+        receive(&mut receiver, 1);
+        assert_eq!(
+            receiver.collect_nacks(),
+            vec![
+                MessageId(65533),
+                MessageId(65534),
+                MessageId(65535),
+                MessageId(0)
+            ]
+        );
+
+        // a big gap evicts the oldest missing messages
+        receive(&mut receiver, 1 + MAX_MISSING_MESSAGES as u16);
+        receiver.current_time += Duration::from_secs(1);
+        let nacks = receiver.collect_nacks();
+        assert_eq!(nacks.len(), MAX_NACKS_PER_MESSAGE);
+        // 65533, 65534 and 65535 were evicted
+        assert_eq!(nacks[..3], [MessageId(0), MessageId(2), MessageId(3)]);
+    }
+
+    #[test]
+    fn test_nack_message_serialization() -> anyhow::Result<()> {
+        let message = NackMessage {
+            channel_id: 3,
+            message_ids: vec![MessageId(1), MessageId(65535)],
+        };
+        assert_eq!(NackMessage::from_bytes(message.to_bytes())?, message);
+        assert!(NackMessage::from_bytes(Bytes::from_static(&[0, 0, 1])).is_err());
+        Ok(())
+    }
+}
@@ -17,7 +17,13 @@ pub struct SequencedReliableReceiver {
     recv_message_buffer: BTreeMap<MessageId, SingleData>,
     /// Highest message id received so far
     most_recent_message_id: MessageId,
+    /// Id of the last message that was returned by [`read_message`](ChannelReceive::read_message).
+    /// The sender might resend a message that we already received if the ack was lost, so we
+    /// need to make sure that we don't return it twice.
+    last_read_message_id: Option<MessageId>,
     fragment_receiver: FragmentReceiver,
+    /// Number of messages that were older than the most recent message received
+    dropped_messages: u64,
 }
 
 impl SequencedReliableReceiver {
@@ -25,7 +31,9 @@ impl SequencedReliableReceiver {
         Self {
             recv_message_buffer: BTreeMap::new(),
             most_recent_message_id: MessageId(0),
+            last_read_message_id: None,
             fragment_receiver: FragmentReceiver::new(),
+            dropped_messages: 0,
         }
     }
 }
@@ -39,8 +47,16 @@ impl ChannelReceive for SequencedReliableReceiver {
             .message_id()
             .ok_or_else(|| anyhow!("message id not found"))?;
 
+        // the message was already read (the sender resent it because the ack was lost)
+        if self
+            .last_read_message_id
+            .is_some_and(|last_read| message_id <= last_read)
+        {
+            return Ok(());
+        }
         // if the message is too old, ignore it
         if message_id < self.most_recent_message_id {
+            self.dropped_messages += 1;
             return Ok(());
         }
 
@@ -71,10 +87,16 @@ impl ChannelReceive for SequencedReliableReceiver {
         loop {
             let (message_id, message) = self.recv_message_buffer.pop_first()?;
             if message_id >= self.most_recent_message_id {
+                self.last_read_message_id = Some(message_id);
                 return Some(message);
             }
+            self.dropped_messages += 1;
         }
     }
+
+    fn dropped_messages(&self) -> u64 {
+        self.dropped_messages
+    }
 }
 
 #[cfg(test)]
@@ -118,6 +140,10 @@ mod tests {
         receiver.buffer_recv(single1.clone().into())?;
         assert_eq!(receiver.recv_message_buffer.len(), 0);
         assert_eq!(receiver.read_message(), None);
+
+        // message 1 is resent because the ack was lost: it doesn't get read twice
+        receiver.buffer_recv(single2.clone().into())?;
+        assert_eq!(receiver.read_message(), None);
         Ok(())
     }
 }
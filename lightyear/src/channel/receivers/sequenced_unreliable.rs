@@ -1,7 +1,9 @@
 use std::collections::VecDeque;
 
 use anyhow::anyhow;
+use tracing::trace;
 
+use crate::channel::receivers::dedup::MessageDeduplicator;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
 use crate::channel::receivers::ChannelReceive;
 use crate::packet::message::{MessageContainer, MessageId, SingleData};
@@ -19,6 +21,10 @@ pub struct SequencedUnreliableReceiver {
     most_recent_message_id: MessageId,
     fragment_receiver: FragmentReceiver,
     current_time: WrappedTime,
+    /// Optional window of recently received message ids used to discard duplicates
+    dedup: Option<MessageDeduplicator>,
+    /// Number of messages that were older than the most recent message received
+    dropped_messages: u64,
 }
 
 impl SequencedUnreliableReceiver {
@@ -29,8 +35,29 @@ impl SequencedUnreliableReceiver {
             fragment_receiver: FragmentReceiver::new(),
             // TODO: starting at 0 time could be dangerous, because the first update will bring it to time_manager time ?
             current_time: WrappedTime::default(),
+            dedup: None,
+            dropped_messages: 0,
         }
     }
+
+    /// Discard messages whose id was among the last `window` message ids received
+    /// (a window of 0 disables the deduplication)
+    pub(crate) fn with_dedup_window(mut self, window: u16) -> Self {
+        self.dedup = (window > 0).then(|| MessageDeduplicator::new(window));
+        self
+    }
+
+    fn push_message(&mut self, data: SingleData) {
+        if self
+            .dedup
+            .as_mut()
+            .is_some_and(|dedup| dedup.is_duplicate(data.id))
+        {
+            trace!(?data.id, "discarding duplicate message");
+            return;
+        }
+        self.recv_message_buffer.push_back(data);
+    }
 }
 
 impl ChannelReceive for SequencedUnreliableReceiver {
@@ -48,6 +75,7 @@ impl ChannelReceive for SequencedUnreliableReceiver {
 
         // if the message is too old, ignore it
         if message_id < self.most_recent_message_id {
+            self.dropped_messages += 1;
             return Ok(());
         }
 
@@ -58,13 +86,13 @@ impl ChannelReceive for SequencedUnreliableReceiver {
 
         // add the message to the buffer
         match message {
-            MessageContainer::Single(data) => self.recv_message_buffer.push_back(data),
+            MessageContainer::Single(data) => self.push_message(data),
             MessageContainer::Fragment(data) => {
                 if let Some(single_data) = self
                     .fragment_receiver
                     .receive_fragment(data, Some(self.current_time))?
                 {
-                    self.recv_message_buffer.push_back(single_data);
+                    self.push_message(single_data);
                 }
             }
         }
@@ -74,6 +102,10 @@ impl ChannelReceive for SequencedUnreliableReceiver {
         self.recv_message_buffer.pop_front()
         // TODO: naia does a more optimized version by return a Vec<Message> instead of Option<Message>
     }
+
+    fn dropped_messages(&self) -> u64 {
+        self.dropped_messages
+    }
 }
 
 #[cfg(test)]
@@ -114,6 +146,7 @@ mod tests {
         // we don't add it to the buffer since we have read a more recent message.
         assert_eq!(receiver.recv_message_buffer.len(), 0);
         assert_eq!(receiver.read_message(), None);
+        assert_eq!(receiver.dropped_messages(), 2);
 
         // receive a later message
         single3.id = Some(MessageId(2));
@@ -125,4 +158,19 @@ mod tests {
         assert_eq!(receiver.recv_message_buffer.len(), 0);
         Ok(())
     }
+
+    #[test]
+    fn test_sequenced_unreliable_receiver_dedup() -> anyhow::Result<()> {
+        let mut receiver = SequencedUnreliableReceiver::new().with_dedup_window(4);
+
+        let mut single = SingleData::new(None, Bytes::from("hello"), 1.0);
+        single.id = Some(MessageId(1));
+
+        // the most recent message gets duplicated: it only gets read once
+        receiver.buffer_recv(single.clone().into())?;
+        receiver.buffer_recv(single.clone().into())?;
+        assert_eq!(receiver.read_message(), Some(single.clone()));
+        assert_eq!(receiver.read_message(), None);
+        Ok(())
+    }
 }
@@ -0,0 +1,298 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::bail;
+use bytes::BytesMut;
+use tracing::trace;
+
+use crate::channel::receivers::unordered_reliable::UnorderedReliableReceiver;
+use crate::channel::receivers::ChannelReceive;
+use crate::channel::stream::{
+    push_progress, Chunk, TransferDirection, TransferId, TransferProgress, TransferStatus,
+    ABORTED_TRANSFERS_HISTORY,
+};
+use crate::packet::message::{MessageContainer, MessageId, SingleData};
+use crate::shared::tick_manager::TickManager;
+use crate::shared::time_manager::TimeManager;
+
+/// Default maximum number of transfers that are reassembled at the same time
+const DEFAULT_MAX_CONCURRENT_TRANSFERS: usize = 16;
+
+/// A transfer that is being reassembled
+struct IncomingTransfer {
+    /// Bytes of the transfer; the buffer grows as the chunks arrive
+    data: BytesMut,
+    total_bytes: usize,
+    received_bytes: usize,
+}
+
+/// Stream receiver: receives the chunks of each transfer reliably, and returns the transfer
+/// once all its chunks have been received
+pub struct StreamReceiver {
+    /// Receives the chunks, and discards the duplicates
+    receiver: UnorderedReliableReceiver,
+    /// Maximum size of a transfer that we accept
+    max_transfer_size: usize,
+    /// Maximum number of transfers that we reassemble at the same time
+    max_concurrent_transfers: usize,
+    /// Maximum number of bytes buffered for the transfers that are being reassembled
+    max_buffered_bytes: usize,
+    /// Number of bytes currently buffered for the transfers that are being reassembled
+    buffered_bytes: usize,
+    transfers: HashMap<TransferId, IncomingTransfer>,
+    /// Most recent transfers that were aborted by the sender
+    aborted_transfers: VecDeque<TransferId>,
+    /// Transfers that were fully received
+    recv_message_buffer: VecDeque<SingleData>,
+    /// Progress updates that have not been read yet
+    progress: Vec<TransferProgress>,
+}
+
+impl StreamReceiver {
+    pub fn new(max_transfer_size: usize) -> Self {
+        Self {
+            receiver: UnorderedReliableReceiver::new(),
+            max_transfer_size,
+            max_concurrent_transfers: DEFAULT_MAX_CONCURRENT_TRANSFERS,
+            max_buffered_bytes: max_transfer_size,
+            buffered_bytes: 0,
+            transfers: HashMap::new(),
+            aborted_transfers: VecDeque::new(),
+            recv_message_buffer: VecDeque::new(),
+            progress: Vec::new(),
+        }
+    }
+
+    pub(crate) fn with_max_concurrent_transfers(mut self, max_concurrent_transfers: usize) -> Self {
+        self.max_concurrent_transfers = max_concurrent_transfers;
+        self
+    }
+
+    pub(crate) fn with_max_buffered_bytes(mut self, max_buffered_bytes: usize) -> Self {
+        self.max_buffered_bytes = max_buffered_bytes;
+        self
+    }
+
+    /// Drain the progress updates of the transfers
+    pub(crate) fn drain_progress(&mut self) -> Vec<TransferProgress> {
+        std::mem::take(&mut self.progress)
+    }
+
+    fn receive_chunk(&mut self, single_data: SingleData) -> anyhow::Result<()> {
+        match Chunk::from_bytes(single_data.bytes)? {
+            Chunk::Data {
+                transfer_id,
+                total_bytes,
+                offset,
+                payload,
+            } => {
+                if self.aborted_transfers.contains(&transfer_id) {
+                    return Ok(());
+                }
+                if total_bytes > self.max_transfer_size {
+                    bail!("transfer of {total_bytes} bytes exceeds the maximum transfer size");
+                }
+                if offset + payload.len() > total_bytes {
+                    bail!("stream chunk is out of the bounds of the transfer");
+                }
+                if !self.transfers.contains_key(&transfer_id)
+                    && self.transfers.len() >= self.max_concurrent_transfers
+                {
+                    bail!("too many concurrent stream transfers");
+                }
+                let transfer =
+                    self.transfers
+                        .entry(transfer_id)
+                        .or_insert_with(|| IncomingTransfer {
+                            data: BytesMut::new(),
+                            total_bytes,
+                            received_bytes: 0,
+                        });
+                if transfer.total_bytes != total_bytes {
+                    bail!("stream chunks of the same transfer have different sizes");
+                }
+                let end = offset + payload.len();
+                if end > transfer.data.len() {
+                    let growth = end - transfer.data.len();
+                    if self.buffered_bytes + growth > self.max_buffered_bytes {
+                        bail!("stream transfers exceed the maximum number of buffered bytes");
+                    }
+                    self.buffered_bytes += growth;
+                    transfer.data.resize(end, 0);
+                }
+                transfer.data[offset..end].copy_from_slice(&payload);
+                transfer.received_bytes += payload.len();
+                let mut progress = TransferProgress {
+                    transfer_id,
+                    direction: TransferDirection::Receive,
+                    bytes_transferred: transfer.received_bytes,
+                    total_bytes,
+                    status: TransferStatus::InProgress,
+                };
+                if transfer.received_bytes >= total_bytes {
+                    trace!(?transfer_id, "transfer received");
+                    let transfer = self.transfers.remove(&transfer_id).unwrap();
+                    self.buffered_bytes -= transfer.data.len();
+                    self.recv_message_buffer.push_back(SingleData {
+                        id: Some(MessageId(transfer_id.0)),
+                        tick: single_data.tick,
+                        bytes: transfer.data.freeze(),
+                        priority: single_data.priority,
+                    });
+                    progress.status = TransferStatus::Completed;
+                }
+                push_progress(&mut self.progress, progress);
+            }
+            Chunk::Abort { transfer_id } => {
+                trace!(?transfer_id, "transfer aborted by the sender");
+                if self.aborted_transfers.len() == ABORTED_TRANSFERS_HISTORY {
+                    self.aborted_transfers.pop_front();
+                }
+                self.aborted_transfers.push_back(transfer_id);
+                if let Some(transfer) = self.transfers.remove(&transfer_id) {
+                    self.buffered_bytes -= transfer.data.len();
+                    push_progress(
+                        &mut self.progress,
+                        TransferProgress {
+                            transfer_id,
+                            direction: TransferDirection::Receive,
+                            bytes_transferred: transfer.received_bytes,
+                            total_bytes: transfer.total_bytes,
+                            status: TransferStatus::Aborted,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ChannelReceive for StreamReceiver {
+    fn update(&mut self, time_manager: &TimeManager, tick_manager: &TickManager) {
+        self.receiver.update(time_manager, tick_manager);
+    }
+
+    /// Queues a received chunk, and reassemble the transfer if it is complete
+    fn buffer_recv(&mut self, message: MessageContainer) -> anyhow::Result<()> {
+        self.receiver.buffer_recv(message)?;
+        while let Some(single_data) = self.receiver.read_message() {
+            self.receive_chunk(single_data)?;
+        }
+        Ok(())
+    }
+
+    fn read_message(&mut self) -> Option<SingleData> {
+        self.recv_message_buffer.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    fn data_chunk(message_id: u16, offset: usize, payload: &'static [u8]) -> MessageContainer {
+        let chunk = Chunk::Data {
+            transfer_id: TransferId(0),
+            total_bytes: 10,
+            offset,
+            payload: Bytes::from_static(payload),
+        };
+        SingleData::new(Some(MessageId(message_id)), chunk.to_bytes(), 1.0).into()
+    }
+
+    #[test]
+    fn test_stream_receiver_reassembly() -> anyhow::Result<()> {
+        let mut receiver = StreamReceiver::new(100);
+
+        // the chunks can arrive out of order
+        receiver.buffer_recv(data_chunk(1, 5, b"world"))?;
+        assert!(receiver.read_message().is_none());
+        // duplicate chunks are ignored
+        receiver.buffer_recv(data_chunk(1, 5, b"world"))?;
+        assert_eq!(
+            receiver.drain_progress(),
+            vec![TransferProgress {
+                transfer_id: TransferId(0),
+                direction: TransferDirection::Receive,
+                bytes_transferred: 5,
+                total_bytes: 10,
+                status: TransferStatus::InProgress,
+            }]
+        );
+
+        receiver.buffer_recv(data_chunk(0, 0, b"hello"))?;
+        let message = receiver.read_message().unwrap();
+        assert_eq!(message.bytes, Bytes::from_static(b"helloworld"));
+        assert_eq!(message.id, Some(MessageId(0)));
+        assert_eq!(
+            receiver.drain_progress().last().unwrap().status,
+            TransferStatus::Completed
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_receiver_abort() -> anyhow::Result<()> {
+        let mut receiver = StreamReceiver::new(100);
+        receiver.buffer_recv(data_chunk(0, 0, b"hello"))?;
+        let abort = Chunk::Abort {
+            transfer_id: TransferId(0),
+        };
+        receiver.buffer_recv(SingleData::new(Some(MessageId(1)), abort.to_bytes(), 1.0).into())?;
+        assert_eq!(
+            receiver.drain_progress().last().unwrap().status,
+            TransferStatus::Aborted
+        );
+
+        // chunks that arrive after the abort are discarded
+        receiver.buffer_recv(data_chunk(2, 5, b"world"))?;
+        assert!(receiver.read_message().is_none());
+        assert!(receiver.drain_progress().is_empty());
+
+        // transfers that are too big are rejected
+        let mut receiver = StreamReceiver::new(5);
+        assert!(receiver.buffer_recv(data_chunk(0, 0, b"hello")).is_err());
+        Ok(())
+    }
+
+    /// The first chunk of a transfer of 10 bytes
+    fn first_chunk(message_id: u16, transfer_id: u16) -> MessageContainer {
+        let chunk = Chunk::Data {
+            transfer_id: TransferId(transfer_id),
+            total_bytes: 10,
+            offset: 0,
+            payload: Bytes::from_static(b"hello"),
+        };
+        SingleData::new(Some(MessageId(message_id)), chunk.to_bytes(), 1.0).into()
+    }
+
+    #[test]
+    fn test_stream_receiver_limits() -> anyhow::Result<()> {
+        // the buffer of a transfer only grows with the chunks that are received
+        let mut receiver = StreamReceiver::new(100);
+        receiver.buffer_recv(first_chunk(0, 0))?;
+        assert_eq!(receiver.buffered_bytes, 5);
+
+        // opening too many transfers at the same time is rejected
+        let mut receiver = StreamReceiver::new(100).with_max_concurrent_transfers(2);
+        receiver.buffer_recv(first_chunk(0, 0))?;
+        receiver.buffer_recv(first_chunk(1, 1))?;
+        assert!(receiver.buffer_recv(first_chunk(2, 2)).is_err());
+        // the chunks of the transfers in progress are still accepted
+        receiver.buffer_recv(data_chunk(3, 5, b"world"))?;
+        assert_eq!(
+            receiver.read_message().unwrap().bytes,
+            Bytes::from_static(b"helloworld")
+        );
+        // a new transfer can start once a transfer is completed
+        receiver.buffer_recv(first_chunk(4, 2))?;
+
+        // buffering too many bytes is rejected
+        let mut receiver = StreamReceiver::new(100).with_max_buffered_bytes(8);
+        receiver.buffer_recv(first_chunk(0, 0))?;
+        assert!(receiver.buffer_recv(first_chunk(1, 1)).is_err());
+        Ok(())
+    }
+}
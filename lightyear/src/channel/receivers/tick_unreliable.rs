@@ -1,4 +1,3 @@
-//! NOTE: This does not work anymore since we don't serialize the tick in SingleData anymore!.
 use anyhow::Context;
 
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
@@ -19,6 +18,8 @@ pub struct TickUnreliableReceiver {
     fragment_receiver: FragmentReceiver,
     current_time: WrappedTime,
     current_tick: Tick,
+    /// Number of messages that arrived after their tick
+    dropped_messages: u64,
 }
 
 // REQUIREMENTS:
@@ -35,6 +36,7 @@ impl TickUnreliableReceiver {
             fragment_receiver: FragmentReceiver::new(),
             current_time: WrappedTime::default(),
             current_tick: Tick(0),
+            dropped_messages: 0,
         }
     }
 }
@@ -45,6 +47,7 @@ impl TickUnreliableReceiver {
         // message is too old
         if tick < self.current_tick {
             // TODO: send message to client to speedup?
+            self.dropped_messages += 1;
         } else {
             // TODO: send message to client to slow down if too far ahead
             self.recv_message_buffer.add_item(tick, data);
@@ -83,6 +86,10 @@ impl ChannelReceive for TickUnreliableReceiver {
             .map(|(_, data)| data)
         // TODO: naia does a more optimized version by return a Vec<Message> instead of Option<Message>
     }
+
+    fn dropped_messages(&self) -> u64 {
+        self.dropped_messages
+    }
 }
 
 #[cfg(test)]
@@ -120,6 +127,7 @@ mod tests {
         single2.tick = Some(Tick(60000));
         receiver.buffer_recv(single2.clone().into())?;
         assert_eq!(receiver.recv_message_buffer.len(), 0);
+        assert_eq!(receiver.dropped_messages(), 1);
 
         // receive message for a future tick: it gets added to the buffer
         single2.tick = Some(Tick(2));
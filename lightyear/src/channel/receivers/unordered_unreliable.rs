@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
-use tracing::info;
+use tracing::{info, trace};
 
+use crate::channel::receivers::dedup::MessageDeduplicator;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
 use crate::channel::receivers::ChannelReceive;
 use crate::packet::message::{MessageContainer, SingleData};
@@ -13,6 +14,8 @@ pub struct UnorderedUnreliableReceiver {
     recv_message_buffer: VecDeque<SingleData>,
     fragment_receiver: FragmentReceiver,
     current_time: WrappedTime,
+    /// Optional window of recently received message ids used to discard duplicates
+    dedup: Option<MessageDeduplicator>,
 }
 
 impl UnorderedUnreliableReceiver {
@@ -21,8 +24,28 @@ impl UnorderedUnreliableReceiver {
             recv_message_buffer: VecDeque::new(),
             fragment_receiver: FragmentReceiver::new(),
             current_time: WrappedTime::default(),
+            dedup: None,
         }
     }
+
+    /// Discard messages whose id was among the last `window` message ids received
+    /// (a window of 0 disables the deduplication)
+    pub(crate) fn with_dedup_window(mut self, window: u16) -> Self {
+        self.dedup = (window > 0).then(|| MessageDeduplicator::new(window));
+        self
+    }
+
+    fn push_message(&mut self, data: SingleData) {
+        if self
+            .dedup
+            .as_mut()
+            .is_some_and(|dedup| dedup.is_duplicate(data.id))
+        {
+            trace!(?data.id, "discarding duplicate message");
+            return;
+        }
+        self.recv_message_buffer.push_back(data);
+    }
 }
 
 impl ChannelReceive for UnorderedUnreliableReceiver {
@@ -34,13 +57,13 @@ impl ChannelReceive for UnorderedUnreliableReceiver {
 
     fn buffer_recv(&mut self, message: MessageContainer) -> anyhow::Result<()> {
         match message {
-            MessageContainer::Single(data) => self.recv_message_buffer.push_back(data),
+            MessageContainer::Single(data) => self.push_message(data),
             MessageContainer::Fragment(fragment) => {
                 if let Some(data) = self
                     .fragment_receiver
                     .receive_fragment(fragment, Some(self.current_time))?
                 {
-                    self.recv_message_buffer.push_back(data);
+                    self.push_message(data);
                 }
             }
         }
@@ -93,4 +116,39 @@ mod tests {
         assert_eq!(receiver.read_message(), Some(single1.clone()));
         Ok(())
     }
+
+    #[test]
+    fn test_unordered_unreliable_receiver_dedup() -> anyhow::Result<()> {
+        let mut receiver = UnorderedUnreliableReceiver::new().with_dedup_window(4);
+
+        let mut single = SingleData::new(None, Bytes::from("hello"), 1.0);
+
+        // messages without ids cannot be deduplicated
+        receiver.buffer_recv(single.clone().into())?;
+        receiver.buffer_recv(single.clone().into())?;
+        assert_eq!(receiver.recv_message_buffer.len(), 2);
+        receiver.recv_message_buffer.clear();
+
+        // a duplicated message only gets read once
+        single.id = Some(MessageId(1));
+        receiver.buffer_recv(single.clone().into())?;
+        receiver.buffer_recv(single.clone().into())?;
+        assert_eq!(receiver.read_message(), Some(single.clone()));
+        assert_eq!(receiver.read_message(), None);
+        Ok(())
+    }
+
+    /// A dedup window of 0 is the same as no deduplication
+    #[test]
+    fn test_unordered_unreliable_receiver_dedup_window_zero() -> anyhow::Result<()> {
+        let mut receiver = UnorderedUnreliableReceiver::new().with_dedup_window(0);
+        assert!(receiver.dedup.is_none());
+
+        let mut single = SingleData::new(None, Bytes::from("hello"), 1.0);
+        single.id = Some(MessageId(1));
+        receiver.buffer_recv(single.clone().into())?;
+        receiver.buffer_recv(single.clone().into())?;
+        assert_eq!(receiver.recv_message_buffer.len(), 2);
+        Ok(())
+    }
 }
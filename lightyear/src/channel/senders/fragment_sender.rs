@@ -12,7 +12,7 @@ pub(crate) struct FragmentSender {
 impl FragmentSender {
     pub fn new() -> Self {
         Self {
-            // TODO: make this overridable?
+            // updated with `set_fragment_size` once the packet size of the connection is known
             fragment_size: FRAGMENT_SIZE,
         }
     }
@@ -23,10 +23,10 @@ impl FragmentSender {
         fragment_bytes: Bytes,
         priority: f32,
     ) -> Vec<FragmentData> {
-        if fragment_bytes.len() <= FRAGMENT_SIZE {
+        if fragment_bytes.len() <= self.fragment_size {
             panic!(
                 "Message size must be at least {} to need to be fragmented",
-                FRAGMENT_SIZE
+                self.fragment_size
             );
         }
         let chunks = fragment_bytes.chunks(self.fragment_size);
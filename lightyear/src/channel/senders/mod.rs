@@ -1,18 +1,21 @@
 use std::collections::VecDeque;
 
+use bevy::utils::Duration;
 use bytes::Bytes;
 use crossbeam_channel::Receiver;
 use enum_dispatch::enum_dispatch;
 
-use crate::packet::message::{FragmentData, MessageAck, MessageId, SingleData};
+use crate::packet::message::{FragmentData, FragmentProgress, MessageAck, MessageId, SingleData};
 use crate::shared::ping::manager::PingManager;
-use crate::shared::tick_manager::TickManager;
+use crate::shared::tick_manager::{Tick, TickManager};
 use crate::shared::time_manager::TimeManager;
 
 pub(crate) mod fragment_ack_receiver;
 pub(crate) mod fragment_sender;
 pub(crate) mod reliable;
+pub(crate) mod sequenced_reliable;
 pub(crate) mod sequenced_unreliable;
+pub(crate) mod stream;
 pub(crate) mod tick_unreliable;
 pub(crate) mod unordered_unreliable;
 pub(crate) mod unordered_unreliable_with_acks;
@@ -38,6 +41,18 @@ pub trait ChannelSend {
     /// Returns the MessageId of the message that was queued, if there is one
     fn buffer_send(&mut self, message: Bytes, priority: f32) -> Option<MessageId>;
 
+    /// Queues a message to be transmitted, which stops being retransmitted if it is not acked within `ttl`.
+    ///
+    /// Only reliable channels retransmit messages, so the `ttl` is ignored for the other channels.
+    fn buffer_send_with_ttl(
+        &mut self,
+        message: Bytes,
+        priority: f32,
+        _ttl: Duration,
+    ) -> Option<MessageId> {
+        self.buffer_send(message, priority)
+    }
+
     /// Reads from the buffer of messages to send to prepare a list of Packets
     /// that can be sent over the network for this channel
     fn send_packet(&mut self) -> (VecDeque<SingleData>, VecDeque<FragmentData>);
@@ -50,11 +65,64 @@ pub trait ChannelSend {
     /// Called when we receive acknowledgement that a Message has been received
     fn notify_message_delivered(&mut self, message_ack: &MessageAck);
 
+    /// Called when the packet that contained a Message was probably lost: the remote has acked
+    /// more recent packets, but not this one
+    fn notify_message_lost(&mut self, _message_ack: &MessageAck) {}
+
+    /// Called when the receiver requested a Message that it is missing, with a negative acknowledgement
+    fn notify_message_nacked(&mut self, _message_id: MessageId) {}
+
     /// Returns true if there are messages in the buffer that are ready to be sent
     fn has_messages_to_send(&self) -> bool;
 
+    /// Number of messages that are waiting to be sent, or to be acked on reliable channels
+    fn send_queue_depth(&self) -> usize;
+
+    /// Number of times a message was sent again because it was not acked in time
+    fn retransmissions(&self) -> u64 {
+        0
+    }
+
+    /// Drain the ids of the messages that expired before being acked
+    fn drain_expired_messages(&mut self) -> Vec<MessageId> {
+        vec![]
+    }
+
+    /// Drain the progress updates of the fragmented messages since the last call
+    fn drain_fragment_progress(&mut self) -> Vec<FragmentProgress> {
+        vec![]
+    }
+
+    /// Stop sending the message `message_id` if it hasn't been acked yet.
+    ///
+    /// Returns true if the message was cancelled. Only reliable channels keep the messages
+    /// after they are sent, so the other channels never cancel messages.
+    fn cancel_message(&mut self, _message_id: MessageId) -> bool {
+        false
+    }
+
+    /// Remove the messages that haven't been acked yet, along with their priority,
+    /// so that they can be sent again on a new connection.
+    ///
+    /// Only reliable channels with [`ReliableSettings::persist_outbox`](crate::channel::builder::ReliableSettings::persist_outbox)
+    /// return messages.
+    fn take_outbox(&mut self) -> Vec<(Bytes, f32)> {
+        vec![]
+    }
+
     /// Create a new receiver that will receive a message id when a sent message is acked
     fn subscribe_acks(&mut self) -> Receiver<MessageId>;
+
+    /// Set the tick that is associated with the messages that are buffered from now on.
+    ///
+    /// Only [`ChannelMode::TickBuffered`](crate::channel::builder::ChannelMode::TickBuffered) channels
+    /// send the tick with the messages.
+    fn set_tick(&mut self, _tick: Tick) {}
+
+    /// Set the maximum size of a fragment. Messages bigger than that will be fragmented.
+    ///
+    /// This is updated when the packet size of the connection changes (for example after path MTU discovery).
+    fn set_fragment_size(&mut self, fragment_size: usize);
 }
 
 /// Enum dispatch lets us derive ChannelSend on each enum variant
@@ -64,5 +132,7 @@ pub enum ChannelSender {
     UnorderedUnreliable(unordered_unreliable::UnorderedUnreliableSender),
     SequencedUnreliable(sequenced_unreliable::SequencedUnreliableSender),
     Reliable(reliable::ReliableSender),
+    SequencedReliable(sequenced_reliable::SequencedReliableSender),
     TickUnreliable(tick_unreliable::TickUnreliableSender),
+    Stream(stream::StreamSender),
 }
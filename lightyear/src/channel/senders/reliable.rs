@@ -1,15 +1,15 @@
 use std::collections::VecDeque;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use bevy::utils::Duration;
 use bytes::Bytes;
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 use tracing::{info, trace};
 
-use crate::channel::builder::ReliableSettings;
+use crate::channel::builder::{ReliableSettings, ResendStrategy};
 use crate::channel::senders::fragment_sender::FragmentSender;
 use crate::channel::senders::ChannelSend;
-use crate::packet::message::{FragmentData, MessageAck, MessageId, SingleData};
+use crate::packet::message::{FragmentData, FragmentProgress, MessageAck, MessageId, SingleData};
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::{TimeManager, WrappedTime};
@@ -35,6 +35,8 @@ pub struct UnackedMessageWithPriority {
     pub unacked_message: UnackedMessage,
     pub base_priority: f32,
     pub accumulated_priority: f32,
+    /// Time after which the message stops being retransmitted
+    pub expires_at: Option<WrappedTime>,
 }
 
 /// A sender that makes sure to resend messages until it receives an ack
@@ -44,6 +46,11 @@ pub struct ReliableSender {
     // TODO: maybe optimize by using a RingBuffer
     /// Ordered map of the messages that haven't been acked yet
     unacked_messages: BTreeMap<MessageId, UnackedMessageWithPriority>,
+    /// Messages that were dropped (because they expired or were cancelled) before being acked, with their priority.
+    ///
+    /// An empty message is sent once in their place so that ordered receivers don't wait for them forever
+    /// (empty messages are discarded by the receiver). It is only sent again if it was lost.
+    dropped_messages: HashMap<MessageId, f32>,
     /// Message id to use for the next message to be sent
     next_send_message_id: MessageId,
 
@@ -58,9 +65,18 @@ pub struct ReliableSender {
 
     /// Used to split a message into fragments if the message is too big
     fragment_sender: FragmentSender,
+    /// Channels to notify when a message has been fully acked
+    ack_senders: Vec<Sender<MessageId>>,
 
     current_rtt: Duration,
+    current_rtt_variance: Duration,
     current_time: WrappedTime,
+    /// Number of messages or fragments that were sent again
+    retransmissions: u64,
+    /// Messages that expired before being acked
+    expired_messages: Vec<MessageId>,
+    /// Progress updates of the fragmented messages that haven't been drained yet
+    fragment_progress: Vec<FragmentProgress>,
 }
 
 impl ReliableSender {
@@ -68,15 +84,100 @@ impl ReliableSender {
         Self {
             reliable_settings,
             unacked_messages: Default::default(),
+            dropped_messages: Default::default(),
             next_send_message_id: MessageId(0),
             single_messages_to_send: Default::default(),
             fragmented_messages_to_send: Default::default(),
             message_ids_to_send: Default::default(),
             fragment_sender: FragmentSender::new(),
+            ack_senders: Vec::new(),
             current_rtt: Duration::default(),
+            current_rtt_variance: Duration::default(),
+            retransmissions: 0,
+            expired_messages: Vec::new(),
+            fragment_progress: Vec::new(),
             current_time: WrappedTime::default(),
         }
     }
+
+    /// Stop resending all the unacked messages, apart from `message_id`
+    pub(crate) fn discard_unacked_messages_except(&mut self, message_id: MessageId) {
+        self.unacked_messages.retain(|id, _| *id == message_id);
+    }
+
+    /// Stop resending the unacked message `message_id`
+    pub(crate) fn discard_unacked_message(&mut self, message_id: MessageId) {
+        self.unacked_messages.remove(&message_id);
+    }
+
+    /// Stop retransmitting the messages that expired.
+    fn expire_messages(&mut self) {
+        let current_time = self.current_time;
+        let expired = self
+            .unacked_messages
+            .iter()
+            .filter(|(_, unacked_message)| {
+                unacked_message
+                    .expires_at
+                    .is_some_and(|expires_at| expires_at <= current_time)
+            })
+            .map(|(message_id, _)| *message_id)
+            .collect::<Vec<_>>();
+        for message_id in expired {
+            trace!(?message_id, "Message expired before being acked");
+            self.drop_message(message_id);
+            self.expired_messages.push(message_id);
+        }
+    }
+
+    /// Remove the message from the unacked messages, and send an empty message in its place
+    fn drop_message(&mut self, message_id: MessageId) -> bool {
+        let Some(unacked_message) = self.unacked_messages.remove(&message_id) else {
+            return false;
+        };
+        self.dropped_messages
+            .insert(message_id, unacked_message.base_priority);
+        self.send_empty_message(message_id);
+        true
+    }
+
+    /// Queue the empty message that replaces a dropped message
+    fn send_empty_message(&mut self, message_id: MessageId) {
+        let Some(priority) = self.dropped_messages.get(&message_id) else {
+            return;
+        };
+        let message_info = MessageAck {
+            message_id,
+            fragment_id: None,
+        };
+        if self.message_ids_to_send.insert(message_info) {
+            self.single_messages_to_send.push_back(SingleData::new(
+                Some(message_id),
+                Bytes::new(),
+                *priority,
+            ));
+        }
+    }
+
+    /// Record the progress of a fragmented message, keeping only the latest update of each message
+    fn push_fragment_progress(&mut self, update: FragmentProgress) {
+        if let Some(pending) = self
+            .fragment_progress
+            .iter_mut()
+            .find(|p| p.message_id == update.message_id)
+        {
+            *pending = update;
+        } else {
+            self.fragment_progress.push(update);
+        }
+    }
+
+    /// Notify the subscribers that the message was fully acked
+    fn notify_ack(&self, message_id: MessageId) {
+        for sender in &self.ack_senders {
+            let _ = sender.send(message_id);
+        }
+    }
 }
 
 // Stragegy:
@@ -92,6 +193,8 @@ impl ChannelSend for ReliableSender {
     fn update(&mut self, time_manager: &TimeManager, ping_manager: &PingManager, _: &TickManager) {
         self.current_time = time_manager.current_time();
         self.current_rtt = ping_manager.rtt();
+        // the jitter is half of the standard deviation of the rtt
+        self.current_rtt_variance = ping_manager.jitter() * 2;
     }
 
     /// Add a new message to the buffer of messages to be sent.
@@ -124,6 +227,10 @@ impl ChannelSend for ReliableSender {
             // store with 0.0 accumulated priority because priority gets accumulated when we collect the messages
             // for sending (even the first time the message is sent)
             accumulated_priority: 0.0,
+            expires_at: self
+                .reliable_settings
+                .max_age
+                .map(|max_age| self.current_time + max_age),
         };
         self.unacked_messages
             .insert(message_id, unacked_message_with_priority);
@@ -131,6 +238,19 @@ impl ChannelSend for ReliableSender {
         Some(message_id)
     }
 
+    fn buffer_send_with_ttl(
+        &mut self,
+        message: Bytes,
+        priority: f32,
+        ttl: Duration,
+    ) -> Option<MessageId> {
+        let message_id = self.buffer_send(message, priority)?;
+        if let Some(unacked_message) = self.unacked_messages.get_mut(&message_id) {
+            unacked_message.expires_at = Some(self.current_time + ttl);
+        }
+        Some(message_id)
+    }
+
     /// Take messages from the buffer of messages to be sent, and build a list of packets
     /// to be sent
     /// The messages to be sent need to have been collected prior to this point.
@@ -162,10 +282,13 @@ impl ChannelSend for ReliableSender {
     /// Either because they have never been sent, or because they need to be resent
     /// Needs to be called before [`ReliableSender::send_packet`]
     fn collect_messages_to_send(&mut self) {
+        self.expire_messages();
         // resend delay is based on the rtt
-        let resend_delay =
-            chrono::Duration::from_std(self.reliable_settings.resend_delay(self.current_rtt))
-                .unwrap();
+        let resend_delay = chrono::Duration::from_std(
+            self.reliable_settings
+                .resend_delay(self.current_rtt, self.current_rtt_variance),
+        )
+        .unwrap();
         let should_send = |last_sent: &Option<WrappedTime>| -> bool {
             match last_sent {
                 // send it the message has never been sent
@@ -175,6 +298,12 @@ impl ChannelSend for ReliableSender {
             }
         };
 
+        // number of fragments that can still be sent during this send interval
+        let mut fragment_allowance = self
+            .reliable_settings
+            .max_fragments_per_send
+            .unwrap_or(usize::MAX);
+
         // Iterate through all unacked messages, oldest message ids first
         for (message_id, unacked_message_with_priority) in self.unacked_messages.iter_mut() {
             // accumulate the priority for all messages (including the ones that were just added, since we set the accumulated priority to 0.0)
@@ -206,16 +335,23 @@ impl ChannelSend for ReliableSender {
                             );
                             self.single_messages_to_send.push_back(message);
                             self.message_ids_to_send.insert(message_info);
+                            if last_sent.is_some() {
+                                self.retransmissions += 1;
+                            }
                             *last_sent = Some(self.current_time);
                         }
                     }
                 }
                 UnackedMessage::Fragmented(fragment_acks) => {
                     // only send the fragments that haven't been acked and should be resent
+                    // the fragments that are over the allowance will be sent during the next send intervals
                     fragment_acks
                         .iter_mut()
                         .filter(|f| !f.acked && should_send(&f.last_sent))
                         .for_each(|f| {
+                            if fragment_allowance == 0 {
+                                return;
+                            }
                             // TODO: need a mechanism like message_ids_to_send? (message/fragmnet_id) to send?
                             let message_info = MessageAck {
                                 message_id: *message_id,
@@ -225,6 +361,10 @@ impl ChannelSend for ReliableSender {
                                 let message = f.data.clone();
                                 self.fragmented_messages_to_send.push_back(message);
                                 self.message_ids_to_send.insert(message_info);
+                                fragment_allowance -= 1;
+                                if f.last_sent.is_some() {
+                                    self.retransmissions += 1;
+                                }
                                 f.last_sent = Some(self.current_time);
                             }
                         })
@@ -234,6 +374,17 @@ impl ChannelSend for ReliableSender {
     }
 
     fn notify_message_delivered(&mut self, message_ack: &MessageAck) {
+        // the content of the messages that expired or were cancelled might not have been received,
+        // so they are not notified as acked.
+        // (the acks of the fragments that were sent before the message was dropped are ignored)
+        if message_ack.fragment_id.is_none()
+            && self
+                .dropped_messages
+                .remove(&message_ack.message_id)
+                .is_some()
+        {
+            return;
+        }
         if let Some(unacked_message) = self.unacked_messages.get_mut(&message_ack.message_id) {
             match &mut unacked_message.unacked_message {
                 UnackedMessage::Single { .. } => {
@@ -243,6 +394,7 @@ impl ChannelSend for ReliableSender {
                         )
                     }
                     self.unacked_messages.remove(&message_ack.message_id);
+                    self.notify_ack(message_ack.message_id);
                 }
                 UnackedMessage::Fragmented(fragment_acks) => {
                     let Some(fragment_id) = message_ack.fragment_id else {
@@ -250,10 +402,16 @@ impl ChannelSend for ReliableSender {
                     };
                     if !fragment_acks[fragment_id as usize].acked {
                         fragment_acks[fragment_id as usize].acked = true;
-                        // TODO: use a variable to keep track of this?
+                        let progress = FragmentProgress {
+                            message_id: message_ack.message_id,
+                            fragments_acked: fragment_acks.iter().filter(|f| f.acked).count(),
+                            num_fragments: fragment_acks.len(),
+                        };
+                        self.push_fragment_progress(progress);
                         // all fragments were acked
-                        if fragment_acks.iter().all(|f| f.acked) {
+                        if progress.is_complete() {
                             self.unacked_messages.remove(&message_ack.message_id);
+                            self.notify_ack(message_ack.message_id);
                         }
                     }
                 }
@@ -261,12 +419,123 @@ impl ChannelSend for ReliableSender {
         }
     }
 
+    fn notify_message_lost(&mut self, message_ack: &MessageAck) {
+        // the empty message that replaces a dropped message is only sent again if it was lost
+        if message_ack.fragment_id.is_none()
+            && self.dropped_messages.contains_key(&message_ack.message_id)
+        {
+            trace!(?message_ack, "Empty message was lost, sending it again");
+            self.send_empty_message(message_ack.message_id);
+            return;
+        }
+        if self.reliable_settings.resend_strategy != ResendStrategy::ImmediateOnNack {
+            return;
+        }
+        let Some(unacked_message) = self.unacked_messages.get_mut(&message_ack.message_id) else {
+            return;
+        };
+        trace!(?message_ack, "Message was lost, resending it immediately");
+        // reset the last time the message was sent, so that it is resent during the next collection
+        match (
+            &mut unacked_message.unacked_message,
+            message_ack.fragment_id,
+        ) {
+            (UnackedMessage::Single { last_sent, .. }, None) => {
+                *last_sent = None;
+            }
+            (UnackedMessage::Fragmented(fragment_acks), Some(fragment_id)) => {
+                if let Some(fragment_ack) = fragment_acks.get_mut(fragment_id as usize) {
+                    fragment_ack.last_sent = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn notify_message_nacked(&mut self, message_id: MessageId) {
+        if self.dropped_messages.contains_key(&message_id) {
+            trace!(?message_id, "Empty message was nacked, sending it again");
+            self.send_empty_message(message_id);
+            return;
+        }
+        let Some(unacked_message) = self.unacked_messages.get_mut(&message_id) else {
+            return;
+        };
+        trace!(?message_id, "Message was nacked, resending it");
+        // reset the last time the message was sent, so that it is resent during the next collection
+        match &mut unacked_message.unacked_message {
+            UnackedMessage::Single { last_sent, .. } => {
+                *last_sent = None;
+            }
+            UnackedMessage::Fragmented(fragment_acks) => {
+                fragment_acks
+                    .iter_mut()
+                    .filter(|f| !f.acked)
+                    .for_each(|f| f.last_sent = None);
+            }
+        }
+    }
+
     fn has_messages_to_send(&self) -> bool {
         !self.single_messages_to_send.is_empty() || !self.fragmented_messages_to_send.is_empty()
     }
 
+    fn send_queue_depth(&self) -> usize {
+        self.unacked_messages.len()
+    }
+
+    fn retransmissions(&self) -> u64 {
+        self.retransmissions
+    }
+
+    fn drain_expired_messages(&mut self) -> Vec<MessageId> {
+        std::mem::take(&mut self.expired_messages)
+    }
+
+    fn drain_fragment_progress(&mut self) -> Vec<FragmentProgress> {
+        std::mem::take(&mut self.fragment_progress)
+    }
+
+    fn cancel_message(&mut self, message_id: MessageId) -> bool {
+        if !self.drop_message(message_id) {
+            return false;
+        }
+        trace!(?message_id, "Message cancelled before being acked");
+        true
+    }
+
+    fn take_outbox(&mut self) -> Vec<(Bytes, f32)> {
+        if !self.reliable_settings.persist_outbox {
+            return vec![];
+        }
+        self.single_messages_to_send.clear();
+        self.fragmented_messages_to_send.clear();
+        self.message_ids_to_send.clear();
+        self.dropped_messages.clear();
+        std::mem::take(&mut self.unacked_messages)
+            .into_values()
+            .map(|message| {
+                let bytes = match message.unacked_message {
+                    UnackedMessage::Single { bytes, .. } => bytes,
+                    // re-assemble the fragmented message
+                    UnackedMessage::Fragmented(fragment_acks) => fragment_acks
+                        .into_iter()
+                        .flat_map(|fragment_ack| fragment_ack.data.bytes)
+                        .collect(),
+                };
+                (bytes, message.base_priority)
+            })
+            .collect()
+    }
+
     fn subscribe_acks(&mut self) -> Receiver<MessageId> {
-        todo!()
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.ack_senders.push(sender);
+        receiver
+    }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
     }
 }
 
@@ -285,6 +554,7 @@ mod tests {
         let mut sender = ReliableSender::new(ReliableSettings {
             rtt_resend_factor: 1.5,
             rtt_resend_min_delay: Duration::from_millis(100),
+            ..Default::default()
         });
         sender.current_rtt = Duration::from_millis(100);
         sender.current_time = WrappedTime::new(0);
@@ -324,4 +594,206 @@ mod tests {
         // this time there are no new messages to send
         assert_eq!(sender.single_messages_to_send.len(), 1);
     }
+
+    #[test]
+    fn test_resend_strategy() {
+        let settings = ReliableSettings {
+            rtt_resend_min_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let rtt = Duration::from_secs(1);
+        let rtt_variance = Duration::from_millis(250);
+        assert_eq!(
+            settings.resend_delay(rtt, rtt_variance),
+            Duration::from_millis(1500)
+        );
+        let settings = settings.with_resend_strategy(ResendStrategy::RttVariance { k: 4.0 });
+        assert_eq!(
+            settings.resend_delay(rtt, rtt_variance),
+            Duration::from_secs(2)
+        );
+        let settings =
+            settings.with_resend_strategy(ResendStrategy::Fixed(Duration::from_millis(5)));
+        // the minimum delay still applies
+        assert_eq!(
+            settings.resend_delay(rtt, rtt_variance),
+            Duration::from_millis(10)
+        );
+    }
+
+    #[test]
+    fn test_reliable_sender_resend_on_nack() {
+        let mut sender = ReliableSender::new(
+            ReliableSettings::default().with_resend_strategy(ResendStrategy::ImmediateOnNack),
+        );
+        sender.current_rtt = Duration::from_millis(100);
+        sender.current_time = WrappedTime::new(0);
+        let message_id = sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        sender.collect_messages_to_send();
+        sender.send_packet();
+
+        // the resend delay has not elapsed yet
+        sender.current_time += Duration::from_millis(10);
+        sender.collect_messages_to_send();
+        assert!(!sender.has_messages_to_send());
+
+        // the packet was lost: the message is resent immediately
+        sender.notify_message_lost(&MessageAck {
+            message_id,
+            fragment_id: None,
+        });
+        sender.collect_messages_to_send();
+        assert_eq!(sender.single_messages_to_send.len(), 1);
+    }
+
+    #[test]
+    fn test_reliable_sender_fragment_pacing() {
+        let mut sender =
+            ReliableSender::new(ReliableSettings::default().with_max_fragments_per_send(2));
+        sender.set_fragment_size(10);
+        sender.current_rtt = Duration::from_millis(100);
+        sender.current_time = WrappedTime::new(0);
+        // the message is split into 5 fragments
+        let message_id = sender.buffer_send(Bytes::from(vec![1; 45]), 1.0).unwrap();
+
+        // only 2 fragments are sent per send interval
+        for expected in [2, 2, 1, 0] {
+            sender.collect_messages_to_send();
+            let (_, fragments) = sender.send_packet();
+            assert_eq!(fragments.len(), expected);
+        }
+
+        for fragment_id in 0..5 {
+            sender.notify_message_delivered(&MessageAck {
+                message_id,
+                fragment_id: Some(fragment_id),
+            });
+        }
+        // only the latest progress update is kept
+        assert_eq!(
+            sender.drain_fragment_progress(),
+            vec![FragmentProgress {
+                message_id,
+                fragments_acked: 5,
+                num_fragments: 5,
+            }]
+        );
+        assert!(sender.unacked_messages.is_empty());
+    }
+
+    #[test]
+    fn test_reliable_sender_subscribe_acks() {
+        let mut sender = ReliableSender::new(ReliableSettings::default());
+        let receiver = sender.subscribe_acks();
+
+        let message_id = sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        let ack = MessageAck {
+            message_id,
+            fragment_id: None,
+        };
+        sender.notify_message_delivered(&ack);
+        assert_eq!(receiver.try_recv(), Ok(message_id));
+        // duplicate acks are only notified once
+        sender.notify_message_delivered(&ack);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_take_outbox() {
+        let mut sender = ReliableSender::new(ReliableSettings::default());
+        sender.buffer_send(Bytes::from("hello"), 1.0);
+        // the outbox is only persisted if the setting is enabled
+        assert!(sender.take_outbox().is_empty());
+        assert_eq!(sender.unacked_messages.len(), 1);
+
+        let mut sender = ReliableSender::new(ReliableSettings::default().with_persist_outbox(true));
+        sender.set_fragment_size(2);
+        sender.buffer_send(Bytes::from("a"), 1.0);
+        sender.buffer_send(Bytes::from("hello"), 2.0);
+        sender.buffer_send(Bytes::from("b"), 1.0);
+        sender.collect_messages_to_send();
+        sender.notify_message_delivered(&MessageAck {
+            message_id: MessageId(0),
+            fragment_id: None,
+        });
+
+        // the unacked messages are returned in order, and the fragmented message is re-assembled
+        assert_eq!(
+            sender.take_outbox(),
+            vec![(Bytes::from("hello"), 2.0), (Bytes::from("b"), 1.0)]
+        );
+        assert_eq!(sender.send_queue_depth(), 0);
+        assert!(!sender.has_messages_to_send());
+    }
+
+    #[test]
+    fn test_message_expiration() {
+        let mut sender = ReliableSender::new(
+            ReliableSettings::default().with_max_age(Duration::from_millis(100)),
+        );
+        sender.current_time = WrappedTime::new(0);
+        sender.buffer_send(Bytes::from("a"), 1.0);
+        sender.buffer_send_with_ttl(Bytes::from("b"), 1.0, Duration::from_millis(300));
+        sender.collect_messages_to_send();
+        sender.send_packet();
+        assert!(sender.drain_expired_messages().is_empty());
+
+        // the first message expires: it is replaced by an empty message
+        sender.current_time += Duration::from_millis(200);
+        sender.collect_messages_to_send();
+        assert_eq!(sender.drain_expired_messages(), vec![MessageId(0)]);
+        let (single_messages, _) = sender.send_packet();
+        let message = single_messages.front().unwrap();
+        assert_eq!(message.id, Some(MessageId(0)));
+        assert!(message.bytes.is_empty());
+
+        // the message with a custom ttl expires later
+        sender.current_time += Duration::from_millis(200);
+        sender.collect_messages_to_send();
+        assert_eq!(sender.drain_expired_messages(), vec![MessageId(1)]);
+    }
+
+    #[test]
+    fn test_cancel_message() {
+        let mut sender = ReliableSender::new(ReliableSettings::default());
+        let receiver = sender.subscribe_acks();
+        sender.set_fragment_size(2);
+        sender.buffer_send(Bytes::from("hello"), 1.0);
+        sender.buffer_send(Bytes::from("b"), 1.0);
+        sender.collect_messages_to_send();
+        sender.send_packet();
+
+        // the fragmented message is cancelled after its fragments were sent
+        assert!(sender.cancel_message(MessageId(0)));
+        assert!(!sender.cancel_message(MessageId(0)));
+        // the ack of a fragment that was sent before the cancellation is ignored
+        sender.notify_message_delivered(&MessageAck {
+            message_id: MessageId(0),
+            fragment_id: Some(0),
+        });
+
+        // an empty message is sent instead, so that ordered receivers are not blocked
+        sender.current_time += Duration::from_secs(1);
+        sender.collect_messages_to_send();
+        let (single_messages, fragmented_messages) = sender.send_packet();
+        let message = single_messages.front().unwrap();
+        assert_eq!(message.id, Some(MessageId(0)));
+        assert!(message.bytes.is_empty());
+        assert!(fragmented_messages.is_empty());
+
+        // the cancelled message is not notified as acked
+        sender.notify_message_delivered(&MessageAck {
+            message_id: MessageId(0),
+            fragment_id: None,
+        });
+        assert!(receiver.try_recv().is_err());
+        // messages that were already acked cannot be cancelled
+        sender.notify_message_delivered(&MessageAck {
+            message_id: MessageId(1),
+            fragment_id: None,
+        });
+        assert_eq!(receiver.try_recv(), Ok(MessageId(1)));
+        assert!(!sender.cancel_message(MessageId(1)));
+        assert_eq!(sender.send_queue_depth(), 0);
+    }
 }
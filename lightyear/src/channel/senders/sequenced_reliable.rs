@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+
+use bevy::utils::Duration;
+use bytes::Bytes;
+use crossbeam_channel::Receiver;
+
+use crate::channel::builder::ReliableSettings;
+use crate::channel::senders::reliable::ReliableSender;
+use crate::channel::senders::ChannelSend;
+use crate::packet::message::{FragmentData, FragmentProgress, MessageAck, MessageId, SingleData};
+use crate::shared::ping::manager::PingManager;
+use crate::shared::tick_manager::TickManager;
+use crate::shared::time_manager::TimeManager;
+
+/// A sender that makes sure that the most recent message is received.
+///
+/// The receiver ignores all the messages that are older than the most recent one it received,
+/// so when a new message is buffered we stop resending the older messages that are still unacked.
+/// Those superseded messages will never be notified as acked.
+pub struct SequencedReliableSender {
+    sender: ReliableSender,
+}
+
+impl SequencedReliableSender {
+    pub(crate) fn new(reliable_settings: ReliableSettings) -> Self {
+        Self {
+            sender: ReliableSender::new(reliable_settings),
+        }
+    }
+}
+
+impl ChannelSend for SequencedReliableSender {
+    fn update(
+        &mut self,
+        time_manager: &TimeManager,
+        ping_manager: &PingManager,
+        tick_manager: &TickManager,
+    ) {
+        self.sender.update(time_manager, ping_manager, tick_manager);
+    }
+
+    /// Add a new message to the buffer of messages to be sent.
+    /// This is a client-facing function, to be called when you want to send a message
+    fn buffer_send(&mut self, message: Bytes, priority: f32) -> Option<MessageId> {
+        let message_id = self.sender.buffer_send(message, priority)?;
+        self.sender.discard_unacked_messages_except(message_id);
+        Some(message_id)
+    }
+
+    fn send_packet(&mut self) -> (VecDeque<SingleData>, VecDeque<FragmentData>) {
+        self.sender.send_packet()
+    }
+
+    fn collect_messages_to_send(&mut self) {
+        self.sender.collect_messages_to_send();
+    }
+
+    fn notify_message_delivered(&mut self, message_ack: &MessageAck) {
+        self.sender.notify_message_delivered(message_ack);
+    }
+
+    fn notify_message_lost(&mut self, message_ack: &MessageAck) {
+        self.sender.notify_message_lost(message_ack);
+    }
+
+    fn notify_message_nacked(&mut self, message_id: MessageId) {
+        self.sender.notify_message_nacked(message_id);
+    }
+
+    fn has_messages_to_send(&self) -> bool {
+        self.sender.has_messages_to_send()
+    }
+
+    fn send_queue_depth(&self) -> usize {
+        self.sender.send_queue_depth()
+    }
+
+    fn retransmissions(&self) -> u64 {
+        self.sender.retransmissions()
+    }
+
+    fn buffer_send_with_ttl(
+        &mut self,
+        message: Bytes,
+        priority: f32,
+        ttl: Duration,
+    ) -> Option<MessageId> {
+        self.sender.buffer_send_with_ttl(message, priority, ttl)
+    }
+
+    fn drain_expired_messages(&mut self) -> Vec<MessageId> {
+        self.sender.drain_expired_messages()
+    }
+
+    fn drain_fragment_progress(&mut self) -> Vec<FragmentProgress> {
+        self.sender.drain_fragment_progress()
+    }
+
+    fn cancel_message(&mut self, message_id: MessageId) -> bool {
+        self.sender.cancel_message(message_id)
+    }
+
+    fn take_outbox(&mut self) -> Vec<(Bytes, f32)> {
+        self.sender.take_outbox()
+    }
+
+    fn subscribe_acks(&mut self) -> Receiver<MessageId> {
+        self.sender.subscribe_acks()
+    }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.sender.set_fragment_size(fragment_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequenced_reliable_sender_discards_superseded_messages() {
+        let mut sender = SequencedReliableSender::new(ReliableSettings::default());
+        let acks = sender.subscribe_acks();
+
+        sender.buffer_send(Bytes::from("hello"), 1.0);
+        sender.collect_messages_to_send();
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+
+        // a newer message is buffered before the first one is acked: only the newer one is sent
+        let message_id = sender.buffer_send(Bytes::from("world"), 1.0).unwrap();
+        assert_eq!(message_id, MessageId(1));
+        sender.buffer_send(Bytes::from("!"), 1.0);
+        sender.collect_messages_to_send();
+        let (single, _) = sender.send_packet();
+        assert_eq!(
+            single,
+            VecDeque::from([SingleData::new(Some(MessageId(2)), Bytes::from("!"), 1.0)])
+        );
+
+        // a late ack for a superseded message is ignored
+        sender.notify_message_delivered(&MessageAck {
+            message_id: MessageId(0),
+            fragment_id: None,
+        });
+        assert!(acks.try_recv().is_err());
+        sender.notify_message_delivered(&MessageAck {
+            message_id: MessageId(2),
+            fragment_id: None,
+        });
+        assert_eq!(acks.try_recv(), Ok(MessageId(2)));
+    }
+}
@@ -79,9 +79,17 @@ impl ChannelSend for SequencedUnreliableSender {
         !self.single_messages_to_send.is_empty() || !self.fragmented_messages_to_send.is_empty()
     }
 
+    fn send_queue_depth(&self) -> usize {
+        self.single_messages_to_send.len() + self.fragmented_messages_to_send.len()
+    }
+
     fn subscribe_acks(&mut self) -> Receiver<MessageId> {
         unreachable!()
     }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
 }
 
 #[cfg(test)]
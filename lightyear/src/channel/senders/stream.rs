@@ -0,0 +1,437 @@
+use std::collections::{HashMap, VecDeque};
+
+use bytes::Bytes;
+use crossbeam_channel::{Receiver, Sender};
+use tracing::{error, trace};
+
+use crate::channel::builder::StreamSettings;
+use crate::channel::senders::reliable::ReliableSender;
+use crate::channel::senders::ChannelSend;
+use crate::channel::stream::{
+    push_progress, Chunk, TransferDirection, TransferId, TransferProgress, TransferStatus,
+    ABORTED_TRANSFERS_HISTORY, CHUNK_HEADER_SIZE,
+};
+use crate::packet::message::{FragmentData, MessageAck, MessageId, SingleData};
+use crate::packet::packet::FRAGMENT_SIZE;
+use crate::shared::ping::manager::PingManager;
+use crate::shared::tick_manager::TickManager;
+use crate::shared::time_manager::TimeManager;
+
+/// Maximum number of chunks that can be sent without being acked
+const MAX_CHUNKS_IN_FLIGHT: usize = 1024;
+
+/// A transfer that has not been fully acked yet
+struct OutgoingTransfer {
+    id: TransferId,
+    data: Bytes,
+    priority: f32,
+    /// Number of bytes that have already been split into chunks
+    sent_bytes: usize,
+    acked_bytes: usize,
+    /// True if at least one chunk was sent (empty transfers are still sent as one chunk)
+    started: bool,
+}
+
+impl OutgoingTransfer {
+    fn has_unsent_chunks(&self) -> bool {
+        !self.started || self.sent_bytes < self.data.len()
+    }
+
+    fn progress(&self, status: TransferStatus) -> TransferProgress {
+        TransferProgress {
+            transfer_id: self.id,
+            direction: TransferDirection::Send,
+            bytes_transferred: self.acked_bytes,
+            total_bytes: self.data.len(),
+            status,
+        }
+    }
+}
+
+/// A sender that splits each message into chunks that are sent reliably at a capped rate.
+///
+/// The transfers are sent one after the other; the [`MessageId`] returned when a message is buffered
+/// is the id of the transfer.
+pub struct StreamSender {
+    /// Sends the chunks reliably
+    sender: ReliableSender,
+    /// Maximum number of bytes sent per second
+    send_rate: u32,
+    /// Number of bytes that can be sent right now
+    allowance: f32,
+    fragment_size: usize,
+    next_transfer_id: TransferId,
+    transfers: VecDeque<OutgoingTransfer>,
+    /// Most recent started transfers that were aborted. The receiver ignores the chunks of these transfers,
+    /// so their ids are not re-used
+    aborted_transfers: VecDeque<TransferId>,
+    /// Transfer and payload size of the chunks that have not been acked yet
+    unacked_chunks: HashMap<MessageId, (TransferId, usize)>,
+    /// Progress updates that have not been read yet
+    progress: Vec<TransferProgress>,
+    /// Channels to notify when a transfer has been fully acked
+    ack_senders: Vec<Sender<MessageId>>,
+}
+
+impl StreamSender {
+    pub(crate) fn new(settings: StreamSettings) -> Self {
+        Self {
+            sender: ReliableSender::new(settings.reliable_settings),
+            send_rate: settings.send_rate,
+            allowance: 0.0,
+            fragment_size: FRAGMENT_SIZE,
+            next_transfer_id: TransferId(0),
+            transfers: VecDeque::new(),
+            aborted_transfers: VecDeque::new(),
+            unacked_chunks: HashMap::new(),
+            progress: Vec::new(),
+            ack_senders: Vec::new(),
+        }
+    }
+
+    /// Abort an ongoing transfer. The chunks of the transfer are not sent anymore,
+    /// and the receiver discards the bytes it already received.
+    ///
+    /// Returns false if the transfer was not found (for example because it was already completed)
+    pub(crate) fn abort(&mut self, transfer_id: TransferId) -> bool {
+        let Some(index) = self.transfers.iter().position(|t| t.id == transfer_id) else {
+            return false;
+        };
+        let transfer = self.transfers.remove(index).unwrap();
+        trace!(?transfer_id, "aborting transfer");
+        let sender = &mut self.sender;
+        self.unacked_chunks.retain(|message_id, (id, _)| {
+            if *id == transfer_id {
+                sender.discard_unacked_message(*message_id);
+                false
+            } else {
+                true
+            }
+        });
+        // notify the receiver, which might have received some chunks already
+        if transfer.started {
+            self.sender
+                .buffer_send(Chunk::Abort { transfer_id }.to_bytes(), transfer.priority);
+            if self.aborted_transfers.len() == ABORTED_TRANSFERS_HISTORY {
+                self.aborted_transfers.pop_front();
+            }
+            self.aborted_transfers.push_back(transfer_id);
+        }
+        push_progress(
+            &mut self.progress,
+            transfer.progress(TransferStatus::Aborted),
+        );
+        true
+    }
+
+    /// Abort all the ongoing transfers
+    pub(crate) fn abort_all(&mut self) {
+        let transfer_ids: Vec<_> = self.transfers.iter().map(|t| t.id).collect();
+        for transfer_id in transfer_ids {
+            self.abort(transfer_id);
+        }
+    }
+
+    /// Drain the progress updates of the transfers
+    pub(crate) fn drain_progress(&mut self) -> Vec<TransferProgress> {
+        std::mem::take(&mut self.progress)
+    }
+
+    /// Find the next transfer id that is not used by a transfer in flight or by a recently aborted transfer,
+    /// so that the receiver doesn't mix up two transfers once the ids wrap around.
+    ///
+    /// Returns None if every id is in use
+    fn next_free_transfer_id(&mut self) -> Option<TransferId> {
+        for _ in 0..=u16::MAX {
+            let transfer_id = self.next_transfer_id;
+            self.next_transfer_id = TransferId(transfer_id.0.wrapping_add(1));
+            if !self.transfers.iter().any(|t| t.id == transfer_id)
+                && !self.aborted_transfers.contains(&transfer_id)
+            {
+                return Some(transfer_id);
+            }
+        }
+        None
+    }
+
+    /// Maximum number of bytes of the transfer in a chunk, so that the chunk doesn't need to be fragmented
+    fn chunk_payload_size(&self) -> usize {
+        self.fragment_size - CHUNK_HEADER_SIZE
+    }
+}
+
+impl ChannelSend for StreamSender {
+    fn update(
+        &mut self,
+        time_manager: &TimeManager,
+        ping_manager: &PingManager,
+        tick_manager: &TickManager,
+    ) {
+        self.sender.update(time_manager, ping_manager, tick_manager);
+        // the allowance doesn't accumulate while there is nothing to send, so that
+        // new transfers are not sent in a single burst
+        if !self.transfers.iter().any(|t| t.has_unsent_chunks()) {
+            self.allowance = 0.0;
+            return;
+        }
+        // we can send up to one second worth of data at once (and at least one chunk)
+        let max_allowance = (self.send_rate as f32).max(self.fragment_size as f32);
+        self.allowance = (self.allowance
+            + self.send_rate as f32 * time_manager.delta().as_secs_f32())
+        .min(max_allowance);
+    }
+
+    /// Add a new transfer to the buffer of transfers to be sent.
+    ///
+    /// Returns the id of the transfer
+    fn buffer_send(&mut self, message: Bytes, priority: f32) -> Option<MessageId> {
+        if message.len() > u32::MAX as usize {
+            error!("message is too big to be sent on a stream channel");
+            return None;
+        }
+        let Some(transfer_id) = self.next_free_transfer_id() else {
+            error!("too many transfers in flight on the stream channel");
+            return None;
+        };
+        let transfer = OutgoingTransfer {
+            id: transfer_id,
+            data: message,
+            priority,
+            sent_bytes: 0,
+            acked_bytes: 0,
+            started: false,
+        };
+        push_progress(
+            &mut self.progress,
+            transfer.progress(TransferStatus::InProgress),
+        );
+        self.transfers.push_back(transfer);
+        Some(MessageId(transfer_id.0))
+    }
+
+    fn send_packet(&mut self) -> (VecDeque<SingleData>, VecDeque<FragmentData>) {
+        self.sender.send_packet()
+    }
+
+    /// Split the transfers into chunks, as long as the send rate allows it
+    fn collect_messages_to_send(&mut self) {
+        let payload_size = self.chunk_payload_size();
+        while self.unacked_chunks.len() < MAX_CHUNKS_IN_FLIGHT {
+            let Some(transfer) = self.transfers.iter_mut().find(|t| t.has_unsent_chunks()) else {
+                break;
+            };
+            let chunk_size = payload_size.min(transfer.data.len() - transfer.sent_bytes);
+            if self.allowance < chunk_size as f32 {
+                break;
+            }
+            let chunk = Chunk::Data {
+                transfer_id: transfer.id,
+                total_bytes: transfer.data.len(),
+                offset: transfer.sent_bytes,
+                payload: transfer
+                    .data
+                    .slice(transfer.sent_bytes..transfer.sent_bytes + chunk_size),
+            };
+            let Some(message_id) = self.sender.buffer_send(chunk.to_bytes(), transfer.priority)
+            else {
+                break;
+            };
+            self.unacked_chunks
+                .insert(message_id, (transfer.id, chunk_size));
+            transfer.sent_bytes += chunk_size;
+            transfer.started = true;
+            self.allowance -= chunk_size as f32;
+        }
+        self.sender.collect_messages_to_send();
+    }
+
+    fn notify_message_delivered(&mut self, message_ack: &MessageAck) {
+        self.sender.notify_message_delivered(message_ack);
+        let Some((transfer_id, chunk_size)) = self.unacked_chunks.remove(&message_ack.message_id)
+        else {
+            return;
+        };
+        let Some(index) = self.transfers.iter().position(|t| t.id == transfer_id) else {
+            return;
+        };
+        let transfer = &mut self.transfers[index];
+        transfer.acked_bytes += chunk_size;
+        if transfer.acked_bytes < transfer.data.len() {
+            push_progress(
+                &mut self.progress,
+                transfer.progress(TransferStatus::InProgress),
+            );
+            return;
+        }
+        let transfer = self.transfers.remove(index).unwrap();
+        trace!(?transfer_id, "transfer completed");
+        push_progress(
+            &mut self.progress,
+            transfer.progress(TransferStatus::Completed),
+        );
+        for sender in &self.ack_senders {
+            let _ = sender.send(MessageId(transfer_id.0));
+        }
+    }
+
+    fn notify_message_lost(&mut self, message_ack: &MessageAck) {
+        self.sender.notify_message_lost(message_ack);
+    }
+
+    fn notify_message_nacked(&mut self, message_id: MessageId) {
+        self.sender.notify_message_nacked(message_id);
+    }
+
+    fn has_messages_to_send(&self) -> bool {
+        self.sender.has_messages_to_send()
+    }
+
+    fn send_queue_depth(&self) -> usize {
+        self.transfers.len() + self.sender.send_queue_depth()
+    }
+
+    fn retransmissions(&self) -> u64 {
+        self.sender.retransmissions()
+    }
+
+    /// The subscribers are notified with the id of the transfer once it has been fully acked
+    fn subscribe_acks(&mut self) -> Receiver<MessageId> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.ack_senders.push(sender);
+        receiver
+    }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_size = fragment_size;
+        self.sender.set_fragment_size(fragment_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::Duration;
+
+    use crate::shared::tick_manager::TickConfig;
+
+    use super::*;
+
+    fn update(sender: &mut StreamSender, delta: Duration) {
+        let mut time_manager = TimeManager::default();
+        time_manager.update(delta);
+        sender.update(
+            &time_manager,
+            &PingManager::new(Default::default()),
+            &TickManager::from_config(TickConfig::new(Duration::from_millis(10))),
+        );
+    }
+
+    fn ack(sender: &mut StreamSender, single_data: &SingleData) {
+        sender.notify_message_delivered(&MessageAck {
+            message_id: single_data.id.unwrap(),
+            fragment_id: None,
+        });
+    }
+
+    #[test]
+    fn test_stream_sender_rate_limit() {
+        let mut sender = StreamSender::new(StreamSettings::default().with_send_rate(1000));
+        sender.set_fragment_size(100 + CHUNK_HEADER_SIZE);
+        let acks = sender.subscribe_acks();
+
+        let transfer_id = sender.buffer_send(Bytes::from(vec![1; 250]), 1.0).unwrap();
+        assert_eq!(transfer_id, MessageId(0));
+
+        // no allowance yet
+        sender.collect_messages_to_send();
+        assert!(!sender.has_messages_to_send());
+
+        // 200 bytes of allowance: 2 chunks can be sent
+        update(&mut sender, Duration::from_millis(200));
+        sender.collect_messages_to_send();
+        let (single, fragments) = sender.send_packet();
+        assert_eq!(single.len(), 2);
+        assert!(fragments.is_empty());
+        assert!(single
+            .iter()
+            .all(|data| data.bytes.len() == 100 + CHUNK_HEADER_SIZE));
+
+        ack(&mut sender, &single[0]);
+        assert_eq!(
+            sender.drain_progress(),
+            vec![TransferProgress {
+                transfer_id: TransferId(0),
+                direction: TransferDirection::Send,
+                bytes_transferred: 100,
+                total_bytes: 250,
+                status: TransferStatus::InProgress,
+            }]
+        );
+        ack(&mut sender, &single[1]);
+
+        // the last chunk only needs 50 bytes of allowance
+        update(&mut sender, Duration::from_millis(50));
+        sender.collect_messages_to_send();
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].bytes.len(), 50 + CHUNK_HEADER_SIZE);
+        ack(&mut sender, &single[0]);
+        assert_eq!(
+            sender.drain_progress().last().unwrap().status,
+            TransferStatus::Completed
+        );
+        assert_eq!(acks.try_recv(), Ok(transfer_id));
+    }
+
+    #[test]
+    fn test_stream_sender_abort() {
+        let mut sender = StreamSender::new(StreamSettings::default().with_send_rate(100));
+        sender.set_fragment_size(100 + CHUNK_HEADER_SIZE);
+        sender.buffer_send(Bytes::from(vec![1; 250]), 1.0);
+        update(&mut sender, Duration::from_secs(1));
+        sender.collect_messages_to_send();
+        sender.send_packet();
+
+        assert!(sender.abort(TransferId(0)));
+        assert!(!sender.abort(TransferId(0)));
+        assert_eq!(
+            sender.drain_progress().last().unwrap().status,
+            TransferStatus::Aborted
+        );
+
+        // only the abort chunk is sent
+        update(&mut sender, Duration::from_secs(1));
+        sender.collect_messages_to_send();
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+        assert_eq!(
+            Chunk::from_bytes(single[0].bytes.clone()).unwrap(),
+            Chunk::Abort {
+                transfer_id: TransferId(0)
+            }
+        );
+    }
+
+    #[test]
+    fn test_stream_sender_transfer_id_wrap() {
+        let mut sender = StreamSender::new(StreamSettings::default().with_send_rate(1000));
+        sender.set_fragment_size(100 + CHUNK_HEADER_SIZE);
+        // transfer 0 is still in flight, transfer 1 was aborted after being started
+        sender.buffer_send(Bytes::from(vec![1; 250]), 1.0);
+        sender.buffer_send(Bytes::from(vec![1; 50]), 2.0);
+        update(&mut sender, Duration::from_secs(1));
+        sender.collect_messages_to_send();
+        sender.send_packet();
+        assert!(sender.abort(TransferId(1)));
+
+        // the ids that are in use are skipped when the ids wrap around
+        sender.next_transfer_id = TransferId(u16::MAX);
+        assert_eq!(
+            sender.buffer_send(Bytes::from(vec![1; 10]), 1.0),
+            Some(MessageId(u16::MAX))
+        );
+        assert_eq!(
+            sender.buffer_send(Bytes::from(vec![1; 10]), 1.0),
+            Some(MessageId(2))
+        );
+    }
+}
@@ -43,6 +43,10 @@ impl ChannelSend for TickUnreliableSender {
         self.current_tick = tick_manager.tick();
     }
 
+    fn set_tick(&mut self, tick: Tick) {
+        self.current_tick = tick;
+    }
+
     /// Add a new message to the buffer of messages to be sent.
     /// This is a client-facing function, to be called when you want to send a message
     fn buffer_send(&mut self, message: Bytes, priority: f32) -> Option<MessageId> {
@@ -86,9 +90,17 @@ impl ChannelSend for TickUnreliableSender {
         !self.single_messages_to_send.is_empty() || !self.fragmented_messages_to_send.is_empty()
     }
 
+    fn send_queue_depth(&self) -> usize {
+        self.single_messages_to_send.len() + self.fragmented_messages_to_send.len()
+    }
+
     fn subscribe_acks(&mut self) -> Receiver<MessageId> {
         unreachable!()
     }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
 }
 
 #[cfg(test)]
@@ -22,6 +22,8 @@ pub struct UnorderedUnreliableSender {
     next_send_fragmented_message_id: MessageId,
     /// Used to split a message into fragments if the message is too big
     fragment_sender: FragmentSender,
+    /// If true, single messages also get an id, so that the receiver can discard duplicates
+    with_message_ids: bool,
 }
 
 impl UnorderedUnreliableSender {
@@ -31,8 +33,15 @@ impl UnorderedUnreliableSender {
             fragmented_messages_to_send: VecDeque::new(),
             next_send_fragmented_message_id: MessageId::default(),
             fragment_sender: FragmentSender::new(),
+            with_message_ids: false,
         }
     }
+
+    /// Include a message id in every message (and not only in the fragmented messages)
+    pub(crate) fn with_message_ids(mut self) -> Self {
+        self.with_message_ids = true;
+        self
+    }
 }
 
 impl ChannelSend for UnorderedUnreliableSender {
@@ -52,6 +61,12 @@ impl ChannelSend for UnorderedUnreliableSender {
             }
             self.next_send_fragmented_message_id += 1;
             Some(self.next_send_fragmented_message_id - 1)
+        } else if self.with_message_ids {
+            let message_id = self.next_send_fragmented_message_id;
+            let single_data = SingleData::new(Some(message_id), message, priority);
+            self.single_messages_to_send.push_back(single_data);
+            self.next_send_fragmented_message_id += 1;
+            Some(message_id)
         } else {
             let single_data = SingleData::new(None, message, priority);
             self.single_messages_to_send.push_back(single_data);
@@ -80,9 +95,17 @@ impl ChannelSend for UnorderedUnreliableSender {
         !self.single_messages_to_send.is_empty() || !self.fragmented_messages_to_send.is_empty()
     }
 
+    fn send_queue_depth(&self) -> usize {
+        self.single_messages_to_send.len() + self.fragmented_messages_to_send.len()
+    }
+
     fn subscribe_acks(&mut self) -> Receiver<MessageId> {
         unreachable!()
     }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
 }
 
 #[cfg(test)]
@@ -119,12 +119,20 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
         !self.single_messages_to_send.is_empty() || !self.fragmented_messages_to_send.is_empty()
     }
 
+    fn send_queue_depth(&self) -> usize {
+        self.single_messages_to_send.len() + self.fragmented_messages_to_send.len()
+    }
+
     /// Create a new receiver that will receive a message id when a message is acked
     fn subscribe_acks(&mut self) -> Receiver<MessageId> {
         let (sender, receiver) = crossbeam_channel::unbounded();
         self.ack_senders.push(sender);
         receiver
     }
+
+    fn set_fragment_size(&mut self, fragment_size: usize) {
+        self.fragment_sender.fragment_size = fragment_size;
+    }
 }
 
 #[cfg(test)]
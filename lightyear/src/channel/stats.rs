@@ -0,0 +1,159 @@
+//! Statistics about the messages sent and received on each channel of each connection
+//!
+//! They are available in the [`ChannelStats`] resource, which is updated every frame after the packets are sent.
+//! They can be used to tune the [`ReliableSettings`](crate::channel::builder::ReliableSettings)
+//! of a channel, or to understand why a channel is backing up.
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy::utils::HashMap;
+
+use crate::channel::builder::Channel;
+#[cfg(feature = "client")]
+use crate::connection::client::NetClient;
+#[cfg(feature = "client")]
+use crate::prelude::client;
+#[cfg(feature = "server")]
+use crate::prelude::server;
+use crate::prelude::{ChannelKind, ClientId};
+
+/// Statistics of a single channel of a connection
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChannelStatistics {
+    /// Number of messages buffered to be sent on the channel
+    pub messages_sent: u64,
+    pub bytes_sent: u64,
+    /// Number of messages read from the channel
+    pub messages_received: u64,
+    pub bytes_received: u64,
+    /// Number of times a message (or a fragment of a message) was sent again because it was not acked in time
+    pub retransmissions: u64,
+    /// Number of messages discarded because a more recent message was already received (on sequenced channels)
+    pub messages_dropped: u64,
+    /// Number of messages that are waiting to be sent, or to be acked on reliable channels
+    pub send_queue_depth: usize,
+}
+
+/// Resource containing the [`ChannelStatistics`] of every channel of every connection.
+///
+/// On the server, there is one entry per connected client.
+/// On the client, there is a single entry for the connection to the server, identified by the [`ClientId`] of the client.
+#[derive(Resource, Debug, Default)]
+pub struct ChannelStats {
+    connections: HashMap<ClientId, HashMap<ChannelKind, ChannelStatistics>>,
+}
+
+impl ChannelStats {
+    /// Statistics of the channel `C` on the connection with `client_id`
+    pub fn get<C: Channel>(&self, client_id: ClientId) -> Option<&ChannelStatistics> {
+        self.connections
+            .get(&client_id)?
+            .get(&ChannelKind::of::<C>())
+    }
+
+    /// Statistics of all the channels of the connection with `client_id`
+    pub fn connection(
+        &self,
+        client_id: ClientId,
+    ) -> Option<&HashMap<ChannelKind, ChannelStatistics>> {
+        self.connections.get(&client_id)
+    }
+
+    /// Iterate over the statistics of all the connections
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&ClientId, &HashMap<ChannelKind, ChannelStatistics>)> {
+        self.connections.iter()
+    }
+}
+
+#[cfg(feature = "client")]
+pub(crate) fn update_client_channel_stats(
+    connection_manager: Res<client::ConnectionManager>,
+    netclient: Res<client::ClientConnection>,
+    mut channel_stats: ResMut<ChannelStats>,
+) {
+    let client_id = netclient.id();
+    channel_stats.connections.retain(|id, _| *id == client_id);
+    let stats = channel_stats.connections.entry(client_id).or_default();
+    stats.clear();
+    stats.extend(connection_manager.message_manager.channel_stats());
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn update_server_channel_stats(
+    connection_manager: Res<server::ConnectionManager>,
+    mut channel_stats: ResMut<ChannelStats>,
+) {
+    channel_stats
+        .connections
+        .retain(|client_id, _| connection_manager.connections.contains_key(client_id));
+    for (client_id, connection) in connection_manager.connections.iter() {
+        let stats = channel_stats.connections.entry(*client_id).or_default();
+        stats.clear();
+        stats.extend(connection.message_manager.channel_stats());
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::utils::Duration;
+
+    use super::*;
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::{LinkConditionerConfig, SharedConfig, TickConfig};
+    use crate::tests::protocol::{Channel1, Message1};
+    use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+    #[test]
+    fn test_channel_stats() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper.init();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        for _ in 0..3 {
+            stepper
+                .server_app
+                .world
+                .resource_mut::<server::ConnectionManager>()
+                .send_message::<Channel1, _>(client_id, &Message1("a".to_string()))
+                .unwrap();
+        }
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+
+        let server_stats = *stepper
+            .server_app
+            .world
+            .resource::<ChannelStats>()
+            .get::<Channel1>(client_id)
+            .unwrap();
+        assert_eq!(server_stats.messages_sent, 3);
+        assert!(server_stats.bytes_sent > 0);
+        assert_eq!(server_stats.messages_received, 0);
+
+        let client_stats = *stepper
+            .client_app
+            .world
+            .resource::<ChannelStats>()
+            .get::<Channel1>(client_id)
+            .unwrap();
+        assert_eq!(client_stats.messages_received, 3);
+        assert_eq!(client_stats.bytes_received, server_stats.bytes_sent);
+        assert_eq!(client_stats.messages_sent, 0);
+    }
+}
@@ -0,0 +1,165 @@
+//! Types used to track the transfers of a [`ChannelMode::Stream`](crate::channel::builder::ChannelMode::Stream) channel
+//!
+//! Each message sent on a stream channel is a transfer, which is split into chunks.
+//! Every chunk carries a small header so that the receiver can reassemble the transfer:
+//! - the kind of the chunk (data or abort)
+//! - the id of the transfer
+//! - for data chunks: the total size of the transfer and the offset of the chunk in the transfer
+use anyhow::{anyhow, bail};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// Identifies a transfer on a [`ChannelMode::Stream`](crate::channel::builder::ChannelMode::Stream) channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TransferId(pub u16);
+
+/// Whether the transfer is sent or received by the local peer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferDirection {
+    Send,
+    Receive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferStatus {
+    InProgress,
+    /// All the bytes of the transfer were acked (sender) or received (receiver)
+    Completed,
+    /// The transfer was aborted by the sender
+    Aborted,
+}
+
+/// Progress of a transfer on a [`ChannelMode::Stream`](crate::channel::builder::ChannelMode::Stream) channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub transfer_id: TransferId,
+    pub direction: TransferDirection,
+    /// Number of bytes that were acked by the remote (sender) or received (receiver)
+    pub bytes_transferred: usize,
+    pub total_bytes: usize,
+    pub status: TransferStatus,
+}
+
+impl TransferProgress {
+    /// Fraction of the transfer that is done, between 0.0 and 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+        self.bytes_transferred as f32 / self.total_bytes as f32
+    }
+}
+
+/// Add a progress update to the list of updates that haven't been read yet.
+///
+/// Successive updates of a transfer that is in progress are coalesced, so that only the latest one is kept.
+pub(crate) fn push_progress(progress: &mut Vec<TransferProgress>, update: TransferProgress) {
+    if let Some(pending) = progress.iter_mut().find(|p| {
+        p.transfer_id == update.transfer_id
+            && p.direction == update.direction
+            && p.status == TransferStatus::InProgress
+    }) {
+        *pending = update;
+    } else {
+        progress.push(update);
+    }
+}
+
+/// Number of aborted transfers that are remembered, so that the receiver can ignore the chunks that arrive
+/// after the abort, and the sender doesn't re-use their ids too early
+pub(crate) const ABORTED_TRANSFERS_HISTORY: usize = 64;
+
+const DATA_CHUNK: u8 = 0;
+const ABORT_CHUNK: u8 = 1;
+
+/// Size of the header of a data chunk
+pub(crate) const CHUNK_HEADER_SIZE: usize = 11;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Chunk {
+    Data {
+        transfer_id: TransferId,
+        total_bytes: usize,
+        offset: usize,
+        payload: Bytes,
+    },
+    Abort {
+        transfer_id: TransferId,
+    },
+}
+
+impl Chunk {
+    pub(crate) fn to_bytes(&self) -> Bytes {
+        match self {
+            Chunk::Data {
+                transfer_id,
+                total_bytes,
+                offset,
+                payload,
+            } => {
+                let mut bytes = BytesMut::with_capacity(CHUNK_HEADER_SIZE + payload.len());
+                bytes.put_u8(DATA_CHUNK);
+                bytes.put_u16_le(transfer_id.0);
+                bytes.put_u32_le(*total_bytes as u32);
+                bytes.put_u32_le(*offset as u32);
+                bytes.put_slice(payload);
+                bytes.freeze()
+            }
+            Chunk::Abort { transfer_id } => {
+                let mut bytes = BytesMut::with_capacity(3);
+                bytes.put_u8(ABORT_CHUNK);
+                bytes.put_u16_le(transfer_id.0);
+                bytes.freeze()
+            }
+        }
+    }
+
+    pub(crate) fn from_bytes(mut bytes: Bytes) -> anyhow::Result<Self> {
+        if bytes.remaining() < 3 {
+            bail!("stream chunk is too short");
+        }
+        let kind = bytes.get_u8();
+        let transfer_id = TransferId(bytes.get_u16_le());
+        match kind {
+            DATA_CHUNK => {
+                if bytes.remaining() < CHUNK_HEADER_SIZE - 3 {
+                    bail!("stream chunk is too short");
+                }
+                let total_bytes = bytes.get_u32_le() as usize;
+                let offset = bytes.get_u32_le() as usize;
+                Ok(Chunk::Data {
+                    transfer_id,
+                    total_bytes,
+                    offset,
+                    payload: bytes,
+                })
+            }
+            ABORT_CHUNK => Ok(Chunk::Abort { transfer_id }),
+            _ => Err(anyhow!("invalid stream chunk kind: {kind}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_serialization() -> anyhow::Result<()> {
+        let chunk = Chunk::Data {
+            transfer_id: TransferId(3),
+            total_bytes: 100,
+            offset: 20,
+            payload: Bytes::from_static(b"hello"),
+        };
+        let bytes = chunk.to_bytes();
+        assert_eq!(bytes.len(), CHUNK_HEADER_SIZE + 5);
+        assert_eq!(Chunk::from_bytes(bytes)?, chunk);
+
+        let chunk = Chunk::Abort {
+            transfer_id: TransferId(3),
+        };
+        assert_eq!(Chunk::from_bytes(chunk.to_bytes())?, chunk);
+        assert!(Chunk::from_bytes(Bytes::from_static(&[2, 0, 0])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+/*! Reduce the work done by the client while the game is running in the background
+
+When the game window is unfocused or minimized, the player is not looking at the game so there is no need
+to apply every replication update as soon as it arrives, or to interpolate entities every frame.
+
+While the [`BackgroundMode`] is enabled:
+- packets are still received and acked, and the ping/sync systems keep running, so the connection stays healthy
+- replication messages are buffered and applied to the [`World`](bevy::prelude::World) at most once every
+  [`BackgroundConfig::replication_interval`]. No update is lost, they are simply applied in batches.
+- the interpolation of the [`Interpolated`](crate::client::interpolation::Interpolated) entities is paused
+
+lightyear doesn't know about windows, so you need to enable the [`BackgroundMode`] yourself, for example:
+```rust
+# use bevy::prelude::*;
+# use bevy::window::WindowFocused;
+# use lightyear::prelude::client::BackgroundMode;
+fn toggle_background_mode(
+    mut events: EventReader<WindowFocused>,
+    mut background: ResMut<BackgroundMode>,
+) {
+    for event in events.read() {
+        background.set_enabled(!event.focused);
+    }
+}
+```
+*/
+use bevy::prelude::{Res, Resource};
+use bevy::reflect::Reflect;
+use bevy::utils::Duration;
+
+use crate::shared::time_manager::WrappedTime;
+
+/// Configuration of the client's behaviour while the [`BackgroundMode`] is enabled
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct BackgroundConfig {
+    /// Minimum duration between two applications of the replication messages to the World
+    pub replication_interval: Duration,
+}
+
+impl Default for BackgroundConfig {
+    fn default() -> Self {
+        Self {
+            replication_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+impl BackgroundConfig {
+    pub fn with_replication_interval(mut self, replication_interval: Duration) -> Self {
+        self.replication_interval = replication_interval;
+        self
+    }
+}
+
+/// Resource to indicate that the client is running in the background (window unfocused or minimized)
+#[derive(Resource, Debug, Default)]
+pub struct BackgroundMode {
+    enabled: bool,
+    /// Last time the replication messages were applied to the World while in background mode
+    last_replication: Option<WrappedTime>,
+}
+
+impl BackgroundMode {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.last_replication = None;
+    }
+
+    /// Returns true if the replication messages should be applied to the World now
+    pub(crate) fn should_apply_replication(
+        &mut self,
+        now: WrappedTime,
+        config: &BackgroundConfig,
+    ) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        if self
+            .last_replication
+            .is_some_and(|last| now < last + config.replication_interval)
+        {
+            return false;
+        }
+        self.last_replication = Some(now);
+        true
+    }
+}
+
+/// Run condition that returns true if the client is not running in the background
+pub(crate) fn is_in_foreground(background: Option<Res<BackgroundMode>>) -> bool {
+    background.map_or(true, |background| !background.is_enabled())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replication_throttling() {
+        let config =
+            BackgroundConfig::default().with_replication_interval(Duration::from_millis(100));
+        let mut background = BackgroundMode::default();
+        let mut now = WrappedTime::default();
+
+        // in the foreground, replication is always applied
+        assert!(background.should_apply_replication(now, &config));
+        assert!(background.should_apply_replication(now, &config));
+
+        background.set_enabled(true);
+        assert!(background.should_apply_replication(now, &config));
+        now += Duration::from_millis(50);
+        assert!(!background.should_apply_replication(now, &config));
+        now += Duration::from_millis(50);
+        assert!(background.should_apply_replication(now, &config));
+        assert!(!background.should_apply_replication(now, &config));
+
+        background.set_enabled(false);
+        assert!(background.should_apply_replication(now, &config));
+    }
+}
@@ -3,17 +3,24 @@ Defines components that are used for the client-side prediction and interpolatio
 */
 use std::fmt::Debug;
 
-use bevy::prelude::{Component, Entity};
+#[cfg(feature = "client")]
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Component, Entity, Res};
 use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
 
-use crate::prelude::{Message, Tick};
+#[cfg(feature = "client")]
+use crate::client::interpolation::resource::InterpolationManager;
+#[cfg(feature = "client")]
+use crate::client::prediction::resource::PredictionManager;
+use crate::prelude::{ComponentRegistry, Message, Tick};
 
 /// Marks an entity that directly applies the replication updates from the remote
 ///
 /// In general, when an entity is replicated from the server to the client, multiple entities can be created on the client:
 /// - an entity that simply contains the replicated components. It will have the marker component [`Confirmed`]
-/// - an entity that is in the future compared to the confirmed entity, and does prediction with rollback. It will have the marker component [`Predicted`](crate::client::prediction::Predicted)
-/// - an entity that is in the past compared to the confirmed entity and interpolates between multiple server updates. It will have the marker component [`Interpolated`](crate::client::interpolation::Interpolated)
+/// - an entity that is in the future compared to the confirmed entity, and does prediction with rollback. It will have the marker component [`Predicted`]
+/// - an entity that is in the past compared to the confirmed entity and interpolates between multiple server updates. It will have the marker component [`Interpolated`]
 #[derive(Component, Reflect, Default)]
 pub struct Confirmed {
     /// The corresponding Predicted entity
@@ -25,6 +32,40 @@ pub struct Confirmed {
     pub tick: Tick,
 }
 
+/// Marks an entity that is being predicted by the client
+#[derive(Component, Debug, Reflect)]
+pub struct Predicted {
+    // This is an option because we could spawn pre-predicted entities on the client that exist before we receive
+    // the corresponding confirmed entity
+    pub confirmed_entity: Option<Entity>,
+}
+
+/// Marker component for an entity that is being interpolated by the client
+#[derive(Component, Debug, Reflect)]
+pub struct Interpolated {
+    // TODO: maybe here add an interpolation function?
+    pub confirmed_entity: Entity,
+    // TODO: add config about despawn behaviour here:
+    //  - despawn immediately all components
+    //  - leave the entity alive until the confirmed entity catches up to it and then it gets removed.
+    //    - or do this only for certain components (audio, animation, particles..) -> mode on PredictedComponent
+}
+
+/// Marks an entity that was spawned on the client before being spawned on the server; the client entity
+/// is matched with the server entity that has the same hash
+#[derive(
+    Component, Serialize, Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, Reflect,
+)]
+#[component(storage = "SparseSet")]
+pub struct PreSpawnedPlayerObject {
+    /// The hash that will identify the spawned entity
+    /// By default, if the hash is not set, it will be generated from the entity's archetype (list of components) and spawn tick
+    /// Otherwise you can manually set it to a value that will be the same on both the client and server
+    pub hash: Option<u64>,
+    //
+    // pub conflict_resolution: ConflictResolution,
+}
+
 pub trait SyncComponent: Component + Clone + PartialEq + Message {}
 impl<T> SyncComponent for T where T: Component + Clone + PartialEq + Message {}
 
@@ -67,3 +108,61 @@ pub enum ComponentSyncMode {
     /// The component is not copied from the Confirmed entity to the interpolated/predicted entity
     None,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Defines which local entity the entities referenced by a component (via [`MapEntities`](bevy::ecs::entity::MapEntities))
+/// resolve to, when the component is copied from the [`Confirmed`] entity to the predicted/interpolated entity
+///
+/// By default, the references of a predicted component resolve to the Predicted entities,
+/// and the references of an interpolated component resolve to the Interpolated entities.
+pub enum MapEntitiesTarget {
+    /// Keep referencing the Confirmed entities
+    Confirmed,
+    /// Reference the Predicted counterparts of the entities
+    Predicted,
+    /// Reference the Interpolated counterparts of the entities
+    Interpolated,
+}
+
+/// Maps the entities referenced in a component from the Confirmed entities to their local counterparts
+/// (as defined by the [`MapEntitiesTarget`] of the component)
+#[cfg(feature = "client")]
+#[derive(SystemParam)]
+pub(crate) struct SyncEntityMapper<'w> {
+    component_registry: Res<'w, ComponentRegistry>,
+    prediction_manager: Option<Res<'w, PredictionManager>>,
+    interpolation_manager: Option<Res<'w, InterpolationManager>>,
+}
+
+#[cfg(feature = "client")]
+impl SyncEntityMapper<'_> {
+    /// Map the entities of a component that is copied to a predicted entity
+    pub(crate) fn map_to_predicted<C: Component>(&self, component: &mut C) {
+        self.map_entities(component, MapEntitiesTarget::Predicted);
+    }
+
+    /// Map the entities of a component that is copied to an interpolated entity
+    pub(crate) fn map_to_interpolated<C: Component>(&self, component: &mut C) {
+        self.map_entities(component, MapEntitiesTarget::Interpolated);
+    }
+
+    fn map_entities<C: Component>(&self, component: &mut C, default_target: MapEntitiesTarget) {
+        let registry = self.component_registry.as_ref();
+        match registry
+            .map_entities_target::<C>()
+            .unwrap_or(default_target)
+        {
+            MapEntitiesTarget::Confirmed => {}
+            MapEntitiesTarget::Predicted => {
+                if let Some(manager) = &self.prediction_manager {
+                    manager.map_entities(component, registry);
+                }
+            }
+            MapEntitiesTarget::Interpolated => {
+                if let Some(manager) = &self.interpolation_manager {
+                    manager.map_entities(component, registry);
+                }
+            }
+        }
+    }
+}
@@ -5,13 +5,22 @@ use bevy::reflect::Reflect;
 use governor::Quota;
 use nonzero_ext::nonzero;
 
+use crate::client::background::BackgroundConfig;
+use crate::client::fallback::ConnectionFallback;
 use crate::client::input::InputConfig;
 use crate::client::interpolation::plugin::InterpolationConfig;
 use crate::client::prediction::plugin::PredictionConfig;
+use crate::client::reconnect::ReconnectConfig;
 use crate::client::sync::SyncConfig;
+use crate::client::transition::SyncTransitionConfig;
 use crate::connection::client::NetConfig;
+use crate::packet::compression::PacketCompression;
+use crate::packet::mtu::MtuConfig;
+use crate::packet::pacer::PacingConfig;
 use crate::shared::config::{Mode, SharedConfig};
 use crate::shared::ping::manager::PingConfig;
+use crate::shared::replication::UnknownEntityUpdatePolicy;
+use crate::transport::io::IoDiagnosticsConfig;
 
 #[derive(Clone, Reflect)]
 /// Config related to the netcode protocol (abstraction of a connection over raw UDP-like transport)
@@ -55,6 +64,17 @@ pub struct PacketConfig {
     pub send_bandwidth_cap: Quota,
     /// If false, there is no bandwidth cap and all messages are sent as soon as possible
     pub bandwidth_cap_enabled: bool,
+    /// If set, the packets sent to the server are paced so that they are spread over time
+    /// instead of being sent in a single burst
+    pub send_pacing: Option<PacingConfig>,
+    /// If set, and if the transport supports it (UDP), the maximum packet size that can reach
+    /// the server is discovered by sending probes. Otherwise a fixed packet size is used.
+    pub mtu_discovery: Option<MtuConfig>,
+    /// Algorithm used to compress the packets after they are assembled.
+    ///
+    /// The packets are only compressed once the remote announced that it supports the algorithm,
+    /// and channels can opt out with [`ChannelSettings::compress`](crate::prelude::ChannelSettings::compress).
+    pub compression: PacketCompression,
 }
 
 impl Default for PacketConfig {
@@ -63,6 +83,9 @@ impl Default for PacketConfig {
             // 56 KB/s bandwidth cap
             send_bandwidth_cap: Quota::per_second(nonzero!(56000u32)),
             bandwidth_cap_enabled: false,
+            send_pacing: None,
+            mtu_discovery: None,
+            compression: PacketCompression::None,
         }
     }
 }
@@ -83,6 +106,51 @@ impl PacketConfig {
         self.bandwidth_cap_enabled = true;
         self
     }
+
+    /// Pace the packets sent to the server so that at most `bytes_per_second` are sent per second
+    pub fn with_send_pacing(mut self, bytes_per_second: u32) -> Self {
+        self.send_pacing = Some(PacingConfig::new(bytes_per_second));
+        self
+    }
+
+    /// Enable path MTU discovery, so that the packet size adapts to the network path
+    pub fn with_mtu_discovery(mut self, mtu_config: MtuConfig) -> Self {
+        self.mtu_discovery = Some(mtu_config);
+        self
+    }
+
+    /// Compress the packets with the given algorithm, if the remote supports it
+    pub fn with_compression(mut self, compression: PacketCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+}
+
+/// Configuration related to replication
+#[derive(Clone, Debug, Default, Reflect)]
+pub struct ReplicationConfig {
+    /// What to do with the component updates received from the server for an entity that doesn't exist
+    pub unknown_entity_updates: UnknownEntityUpdatePolicy,
+    /// If true, the replicated entities are kept when the client disconnects. When the client reconnects,
+    /// the entities that the server sends again are re-attached to the existing local entities instead of
+    /// being spawned again, and the others are despawned.
+    ///
+    /// This requires the server to send the world in a single snapshot
+    /// ([`LateJoinReplication::Snapshot`](crate::server::config::LateJoinReplication::Snapshot)), and to keep the
+    /// same entities across restarts (see [`ServerReplication`](crate::server::persistence::ServerReplication)).
+    pub reattach_entities: bool,
+}
+
+impl ReplicationConfig {
+    pub fn with_unknown_entity_updates(mut self, policy: UnknownEntityUpdatePolicy) -> Self {
+        self.unknown_entity_updates = policy;
+        self
+    }
+
+    pub fn with_reattach_entities(mut self, reattach_entities: bool) -> Self {
+        self.reattach_entities = reattach_entities;
+        self
+    }
 }
 
 /// The configuration object that lets you create a `ClientPlugin` with the desired settings.
@@ -105,9 +173,22 @@ pub struct ClientConfig {
     pub shared: SharedConfig,
     pub packet: PacketConfig,
     pub net: NetConfig,
+    /// Connection configs that are tried in order if the connection attempt with `net` fails
+    #[reflect(ignore)]
+    pub fallback: ConnectionFallback,
+    /// If set, the client automatically tries to reconnect when the connection is lost
+    #[reflect(ignore)]
+    pub reconnect: Option<ReconnectConfig>,
     pub input: InputConfig,
     pub ping: PingConfig,
     pub sync: SyncConfig,
     pub prediction: PredictionConfig,
     pub interpolation: InterpolationConfig,
+    pub replication: ReplicationConfig,
+    /// If set, the visual state is blended when an entity switches between prediction and interpolation.
+    /// Otherwise the entity snaps to the state of the new driver
+    pub sync_transition: Option<SyncTransitionConfig>,
+    pub background: BackgroundConfig,
+    /// Debugging options for the packets sent and received (requires the `pcap` feature)
+    pub io_diagnostics: IoDiagnosticsConfig,
 }
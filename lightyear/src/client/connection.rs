@@ -1,5 +1,5 @@
 //! Specify how a Client sends/receives messages with a Server
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::entity::{EntityHashMap, MapEntities};
 use bevy::prelude::{Component, Entity, Local, Mut, Resource, World};
@@ -9,24 +9,32 @@ use bytes::Bytes;
 use serde::Serialize;
 use tracing::{debug, error, info, trace, trace_span, warn};
 
-use crate::channel::builder::{EntityUpdatesChannel, PingChannel};
+use crate::channel::builder::{
+    ChannelSettings, ChannelSyncChannel, EntityUpdatesChannel, PingChannel,
+};
 use bitcode::encoding::Fixed;
 
 use crate::channel::senders::ChannelSend;
-use crate::client::config::PacketConfig;
+use crate::channel::stream::TransferId;
+use crate::client::config::{PacketConfig, ReplicationConfig};
 use crate::client::message::ClientMessage;
 use crate::client::replication::send::ReplicateCache;
 use crate::client::sync::SyncConfig;
 use crate::inputs::native::input_buffer::InputBuffer;
+use crate::packet::compression::PacketCompression;
+use crate::packet::message::{MessageHandle, MessageId};
 use crate::packet::message_manager::MessageManager;
+use crate::packet::pacer::PacketPacer;
 use crate::packet::packet::Packet;
 use crate::packet::packet_manager::{Payload, PACKET_BUFFER_CAPACITY};
 use crate::prelude::{Channel, ChannelKind, ClientId, Message, ReplicationGroup, TargetEntity};
-use crate::protocol::channel::ChannelRegistry;
-use crate::protocol::component::{ComponentNetId, ComponentRegistry};
+use crate::protocol::channel::{
+    ChannelId, ChannelRegistration, ChannelRegistry, MAX_PENDING_CHANNEL_MESSAGES,
+};
+use crate::protocol::component::{ComponentNetId, ComponentRegistry, ProtocolVersion};
 use crate::protocol::message::MessageRegistry;
 use crate::protocol::registry::NetId;
-use crate::protocol::BitSerializable;
+use crate::protocol::{registries_hash, BitSerializable, DeniedReason};
 use crate::serialize::bitcode::reader::BufferPool;
 use crate::serialize::bitcode::writer::BitcodeWriter;
 use crate::serialize::reader::ReadBuffer;
@@ -41,12 +49,14 @@ use crate::shared::replication::components::{ReplicationGroupId, ReplicationTarg
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
-use crate::shared::replication::{ReplicationMessage, ReplicationSend};
+use crate::shared::replication::{ReplicationMessage, ReplicationSend, UnknownEntityUpdateStats};
 use crate::shared::replication::{ReplicationMessageData, ReplicationPeer, ReplicationReceive};
 use crate::shared::sets::{ClientMarker, ServerMarker};
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
+#[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+use crate::transport::pcap::PacketCapture;
 
 use super::sync::SyncManager;
 
@@ -54,6 +64,9 @@ use super::sync::SyncManager;
 ///
 /// This is the main [`Resource`] to use to interact with the server (send inputs, messages, etc.)
 ///
+/// The resource is only inserted when the client starts connecting, so systems that can run before
+/// that (for example in a menu) should use `Option<Res<ConnectionManager>>`.
+///
 /// ```rust,ignore
 /// # use bevy::prelude::*;
 /// # use lightyear::client::connection::ConnectionManager as ClientConnectionManager;
@@ -87,10 +100,25 @@ pub struct ConnectionManager {
     pub(crate) received_messages: HashMap<NetId, Vec<Bytes>>,
     writer: BitcodeWriter,
     pub(crate) reader_pool: BufferPool,
+    /// Spreads the packet sends over time, if pacing is enabled
+    pub(crate) pacer: Option<PacketPacer>,
+    /// Writes the packets sent and received to a file, if packet capture is enabled
+    #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+    pub(crate) packet_capture: Option<PacketCapture>,
+    /// Network ids announced by the server for channels that were not registered on the client yet
+    announced_channels: HashMap<String, ChannelId>,
+    /// Messages sent on channels registered at runtime, waiting for the server to announce their network id
+    /// (at most [`MAX_PENDING_CHANNEL_MESSAGES`] per channel)
+    pending_channel_messages: HashMap<ChannelKind, Vec<(RawData, NetworkTarget, Option<Duration>)>>,
+    /// Compression algorithm that the client would like to use for the packets sent to the server
+    packet_compression: PacketCompression,
+    /// Reason why the server refused the connection, if it did
+    pub(crate) denied_reason: Option<DeniedReason>,
     // TODO: maybe don't do any replication until connection is synced?
 }
 
 impl ConnectionManager {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         component_registry: &ComponentRegistry,
         message_registry: &MessageRegistry,
@@ -99,7 +127,10 @@ impl ConnectionManager {
         sync_config: SyncConfig,
         ping_config: PingConfig,
         input_delay_ticks: u16,
-    ) -> Self {
+        replication_config: ReplicationConfig,
+    ) -> Result<Self> {
+        let pacer = packet_config.send_pacing.map(PacketPacer::new);
+        let packet_compression = packet_config.compression;
         // create the message manager and the channels
         let mut message_manager = MessageManager::new(channel_registry, packet_config.into());
         // get the acks-tracker for entity updates
@@ -114,8 +145,9 @@ impl ConnectionManager {
             message_manager.get_replication_update_send_receiver();
         let replication_sender =
             ReplicationSender::new(update_acks_tracker, replication_update_send_receiver);
-        let replication_receiver = ReplicationReceiver::new();
-        Self {
+        let mut replication_receiver = ReplicationReceiver::new();
+        replication_receiver.unknown_entity_policy = replication_config.unknown_entity_updates;
+        let mut connection_manager = Self {
             component_registry: component_registry.clone(),
             message_registry: message_registry.clone(),
             message_manager,
@@ -129,7 +161,35 @@ impl ConnectionManager {
             writer: BitcodeWriter::with_capacity(PACKET_BUFFER_CAPACITY),
             // TODO: it looks like we don't really need the pool this case, we can just keep re-using the same buffer
             reader_pool: BufferPool::new(1),
-        }
+            pacer,
+            #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+            packet_capture: None,
+            announced_channels: HashMap::default(),
+            pending_channel_messages: HashMap::default(),
+            packet_compression,
+            denied_reason: None,
+        };
+        // the server can refuse the connection if it was built with a different protocol
+        connection_manager
+            .send_protocol_hash(registries_hash(
+                component_registry,
+                message_registry,
+                channel_registry,
+            ))
+            .context("could not buffer the protocol hash")?;
+        // the server needs to know our protocol version to migrate the components that changed
+        connection_manager
+            .send_protocol_version(component_registry.protocol_version())
+            .context("could not buffer the protocol version")?;
+        // the server only compresses its packets once it knows that we can decompress them
+        connection_manager
+            .send_supported_compressions()
+            .context("could not buffer the supported compressions")?;
+        // the server only replicates the dynamic components that we registered too
+        connection_manager
+            .send_dynamic_components(component_registry)
+            .context("could not buffer the dynamic components")?;
+        Ok(connection_manager)
     }
 
     #[doc(hidden)]
@@ -138,6 +198,35 @@ impl ConnectionManager {
         self.sync_manager.is_synced()
     }
 
+    /// By how many ticks (including the fractional part) the client's prediction tick is ahead of
+    /// the estimated current server tick.
+    ///
+    /// Returns `None` if the client is not synced with the server yet.
+    pub fn prediction_tick_offset(
+        &self,
+        tick_manager: &TickManager,
+        time_manager: &TimeManager,
+    ) -> Option<f32> {
+        self.sync_manager
+            .prediction_tick_offset(tick_manager, time_manager)
+    }
+
+    /// By how many ticks (including the fractional part) the inputs sent now will arrive on the server
+    /// before the server simulates the tick they were produced for.
+    ///
+    /// This is the achieved margin controlled by [`SyncConfig`](crate::client::sync::SyncConfig):
+    /// the fractional part corresponds to the `tick_phase_offset`.
+    ///
+    /// Returns `None` if the client is not synced with the server yet.
+    pub fn input_arrival_margin(
+        &self,
+        tick_manager: &TickManager,
+        time_manager: &TimeManager,
+    ) -> Option<f32> {
+        self.sync_manager
+            .input_arrival_margin(tick_manager, time_manager, &self.ping_manager)
+    }
+
     /// Returns true if we received a new server packet on this frame
     pub(crate) fn received_new_server_tick(&self) -> bool {
         self.sync_manager.duration_since_latest_received_server_tick == Duration::default()
@@ -168,6 +257,86 @@ impl ConnectionManager {
         Ok(())
     }
 
+    fn send_protocol_version(&mut self, version: ProtocolVersion) -> Result<()> {
+        self.writer.start_write();
+        ClientMessage::ProtocolVersion(version).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    fn send_protocol_hash(&mut self, hash: u64) -> Result<()> {
+        self.writer.start_write();
+        ClientMessage::ProtocolHash(hash).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    fn send_supported_compressions(&mut self) -> Result<()> {
+        self.writer.start_write();
+        ClientMessage::SupportedCompressions(PacketCompression::supported())
+            .encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    /// Announce the type paths of the dynamic components registered on the client
+    pub(crate) fn send_dynamic_components(
+        &mut self,
+        component_registry: &ComponentRegistry,
+    ) -> Result<()> {
+        let type_paths: Vec<String> = component_registry
+            .dynamic_components()
+            .map(|(_, dynamic)| dynamic.type_path.clone())
+            .collect();
+        if type_paths.is_empty() {
+            return Ok(());
+        }
+        self.writer.start_write();
+        ClientMessage::DynamicComponents(type_paths).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    /// Acknowledge the network ids that the server assigned to our dynamic components,
+    /// so that the server can start replicating them
+    fn send_dynamic_component_ids_ack(&mut self, net_ids: Vec<ComponentNetId>) -> Result<()> {
+        self.writer.start_write();
+        ClientMessage::DynamicComponentIdsAck(net_ids).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    /// Ask the server to send again the entities for which we received updates without having them
+    fn send_resend_requests(&mut self) -> Result<()> {
+        let entities = self.replication_receiver.drain_resend_requests();
+        if entities.is_empty() {
+            return Ok(());
+        }
+        self.writer.start_write();
+        ClientMessage::ResendEntities(entities).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    /// Algorithm used to compress the packets sent to the server.
+    ///
+    /// This is [`PacketCompression::None`] until the server announced that it supports the configured algorithm.
+    pub fn packet_compression(&self) -> PacketCompression {
+        self.message_manager.compression()
+    }
+
     fn send_pong(&mut self, pong: Pong) -> Result<()> {
         self.writer.start_write();
         ClientMessage::Pong(pong).encode(&mut self.writer)?;
@@ -177,11 +346,127 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Number of component updates received from the server for entities that didn't exist locally
+    pub fn unknown_entity_update_stats(&self) -> UnknownEntityUpdateStats {
+        self.replication_receiver.unknown_entity_stats
+    }
+
+    /// Returns true if the channel `C` can be used, which is the case for all the channels except
+    /// the ones registered at runtime whose network id was not announced by the server yet
+    pub fn is_channel_registered<C: Channel>(&self) -> bool {
+        self.message_manager
+            .channels
+            .contains_key(&ChannelKind::of::<C>())
+    }
+
+    /// Register a channel while the client is running.
+    ///
+    /// The channel can be used once the server announced its network id.
+    pub(crate) fn register_channel<C: Channel>(&mut self, settings: ChannelSettings) {
+        self.message_manager
+            .channel_registry
+            .add_runtime_channel::<C>(settings, false);
+        if let Some(net_id) = self.announced_channels.remove(C::name()) {
+            if let Err(e) = self.bind_channel(C::name(), net_id) {
+                error!("could not register channel {}: {e:?}", C::name());
+            }
+        }
+    }
+
+    /// Assign the network id announced by the server to a channel registered at runtime,
+    /// and send the messages that were waiting for it
+    fn bind_channel(&mut self, name: &str, net_id: ChannelId) -> Result<()> {
+        let Some(kind) = self
+            .message_manager
+            .channel_registry
+            .bind_channel(name, net_id)?
+        else {
+            // the channel will be bound once it is registered on the client
+            self.announced_channels.insert(name.to_string(), net_id);
+            return Ok(());
+        };
+        self.message_manager.add_channel(kind)?;
+        self.writer.start_write();
+        ClientMessage::ChannelRegistrationAck(net_id).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        if let Some(pending_messages) = self.pending_channel_messages.remove(&kind) {
+            pending_messages
+                .into_iter()
+                .try_for_each(|(message, target, ttl)| {
+                    self.buffer_message_with_ttl(message, kind, target, ttl)
+                        .map(|_| ())
+                })?;
+        }
+        Ok(())
+    }
+
     /// Send a message to the server
     pub fn send_message<C: Channel, M: Message>(&mut self, message: &M) -> Result<()> {
         self.send_message_to_target::<C, M>(message, NetworkTarget::None)
     }
 
+    /// Send a message to the server on the reliable channel `C`.
+    ///
+    /// If the message is not acked within `ttl`, it stops being retransmitted and a
+    /// [`MessageExpiredEvent`](crate::client::events::MessageExpiredEvent) is emitted.
+    pub fn send_message_with_ttl<C: Channel, M: Message>(
+        &mut self,
+        message: &M,
+        ttl: Duration,
+    ) -> Result<()> {
+        let message_bytes = self.message_registry.serialize(message, &mut self.writer)?;
+        self.buffer_message_with_ttl(
+            message_bytes,
+            ChannelKind::of::<C>(),
+            NetworkTarget::None,
+            Some(ttl),
+        )
+        .map(|_| ())
+    }
+
+    /// Send a message to the server, and get a [`MessageHandle`] that can be used to know when the
+    /// server received the message.
+    ///
+    /// The channel `C` must track acks (reliable channels or [`ChannelMode::UnorderedUnreliableWithAcks`](crate::channel::builder::ChannelMode::UnorderedUnreliableWithAcks)).
+    /// A [`MessageAckedEvent`](crate::client::events::MessageAckedEvent) is emitted when the message is acked,
+    /// or you can poll [`is_message_acked`](Self::is_message_acked).
+    pub fn send_message_with_handle<C: Channel, M: Message>(
+        &mut self,
+        message: &M,
+    ) -> Result<MessageHandle> {
+        let channel = ChannelKind::of::<C>();
+        self.message_manager.watch_acks(channel)?;
+        if self.message_manager.channel_registry.is_unbound(&channel) {
+            return Err(anyhow!("the channel is not registered on the server yet"));
+        }
+        let message_bytes = self.message_registry.serialize(message, &mut self.writer)?;
+        let message_id = self
+            .buffer_message_with_ttl(message_bytes, channel, NetworkTarget::None, None)?
+            .context("the channel did not assign a message id")?;
+        let handle = MessageHandle {
+            channel,
+            message_id,
+        };
+        self.message_manager.track_ack(handle);
+        Ok(handle)
+    }
+
+    /// Returns true if the server received the message sent with [`send_message_with_handle`](Self::send_message_with_handle)
+    pub fn is_message_acked(&self, handle: MessageHandle) -> bool {
+        self.message_manager.is_acked(handle)
+    }
+
+    /// Cancel a message sent with [`send_message_with_handle`](Self::send_message_with_handle), so that
+    /// it is not retransmitted anymore.
+    ///
+    /// Returns true if the message was cancelled, and false if it was already acked.
+    /// The message might still be received by the server if it was sent before being cancelled.
+    pub fn cancel_message(&mut self, handle: MessageHandle) -> Result<bool> {
+        self.message_manager.cancel_message(handle)
+    }
+
     /// Send a message to the server, the message should be re-broadcasted according to the `target`
     pub fn send_message_to_target<C: Channel, M: Message>(
         &mut self,
@@ -191,6 +476,21 @@ impl ConnectionManager {
         self.erased_send_message_to_target(message, ChannelKind::of::<C>(), target)
     }
 
+    /// Abort a transfer that is being sent to the server on the
+    /// [`ChannelMode::Stream`](crate::channel::builder::ChannelMode::Stream) channel `C`
+    ///
+    /// The id of the transfer is provided by the [`TransferProgressEvent`](crate::client::events::TransferProgressEvent)s
+    pub fn abort_transfer<C: Channel>(&mut self, transfer_id: TransferId) -> Result<()> {
+        self.message_manager
+            .abort_transfer(ChannelKind::of::<C>(), Some(transfer_id))
+    }
+
+    /// Abort all the transfers that are being sent to the server on the stream channel `C`
+    pub fn abort_transfers<C: Channel>(&mut self) -> Result<()> {
+        self.message_manager
+            .abort_transfer(ChannelKind::of::<C>(), None)
+    }
+
     fn erased_send_message_to_target<M: Message>(
         &mut self,
         message: &M,
@@ -207,6 +507,29 @@ impl ConnectionManager {
         channel: ChannelKind,
         target: NetworkTarget,
     ) -> Result<()> {
+        self.buffer_message_with_ttl(message, channel, target, None)
+            .map(|_| ())
+    }
+
+    /// Returns the id of the message, if the channel assigns one
+    fn buffer_message_with_ttl(
+        &mut self,
+        message: RawData,
+        channel: ChannelKind,
+        target: NetworkTarget,
+        ttl: Option<Duration>,
+    ) -> Result<Option<MessageId>> {
+        if self.message_manager.channel_registry.is_unbound(&channel) {
+            // the server didn't announce the network id of the channel yet
+            let pending_messages = self.pending_channel_messages.entry(channel).or_default();
+            if pending_messages.len() >= MAX_PENDING_CHANNEL_MESSAGES {
+                return Err(anyhow!(
+                    "too many messages are waiting for the server to announce the channel"
+                ));
+            }
+            pending_messages.push((message, target, ttl));
+            return Ok(None);
+        }
         // TODO: i know channel names never change so i should be able to get them as static
         // TODO: just have a channel registry enum as well?
         let channel_name = self
@@ -221,8 +544,12 @@ impl ConnectionManager {
         // TODO: doesn't this serialize the bytes twice?
         let message_bytes = self.writer.finish_write().to_vec();
         // message.emit_send_logs(&channel_name);
-        self.message_manager.buffer_send(message_bytes, channel)?;
-        Ok(())
+        match ttl {
+            Some(ttl) => self
+                .message_manager
+                .buffer_send_with_ttl(message_bytes, channel, ttl),
+            None => self.message_manager.buffer_send(message_bytes, channel),
+        }
     }
 
     pub(crate) fn buffer_replication_messages(
@@ -240,7 +567,7 @@ impl ConnectionManager {
         // }
 
         self.replication_sender
-            .finalize(tick)
+            .finalize(tick, bevy_tick)
             .into_iter()
             .try_for_each(|(channel, group_id, message_data, priority)| {
                 let should_track_ack = matches!(message_data, ReplicationMessageData::Updates(_));
@@ -270,18 +597,21 @@ impl ConnectionManager {
                 if should_track_ack {
                     self.replication_sender
                         .updates_message_id_to_group_id
-                        .insert(message_id, (group_id, bevy_tick));
+                        .insert(message_id, (group_id, bevy_tick, tick));
                 }
                 Ok(())
             })
     }
 
     /// Send packets that are ready to be sent
+    ///
+    /// Returns the payloads that should be sent as datagrams, and the payloads that should be
+    /// sent on a reliable stream (for channels that use [`Delivery::Stream`](crate::transport::Delivery::Stream))
     pub(crate) fn send_packets(
         &mut self,
         time_manager: &TimeManager,
         tick_manager: &TickManager,
-    ) -> Result<Vec<Payload>> {
+    ) -> Result<(Vec<Payload>, Vec<Payload>)> {
         // update the ping manager with the actual send time
         // TODO: issues here: we would like to send the ping/pong messages immediately, otherwise the recorded current time is incorrect
         //   - can give infinity priority to this channel?
@@ -307,11 +637,14 @@ impl ConnectionManager {
                     Ok::<(), anyhow::Error>(())
                 })?;
         }
-        let payloads = self.message_manager.send_packets(tick_manager.tick());
+        let payloads = self.message_manager.send_packets(tick_manager.tick())?;
+        let stream_payloads = self
+            .message_manager
+            .send_stream_packets(tick_manager.tick())?;
 
         // update the replication sender about which messages were actually sent, and accumulate priority
         self.replication_sender.recv_send_notification();
-        payloads
+        Ok((payloads, stream_payloads))
     }
 
     pub(crate) fn receive(
@@ -320,8 +653,11 @@ impl ConnectionManager {
         world: &mut World,
         time_manager: &TimeManager,
         tick_manager: &TickManager,
+        // if false, the replication messages stay buffered until a later call
+        apply_replication: bool,
     ) {
         let _span = trace_span!("receive").entered();
+        let mut corrections = vec![];
         for (channel_kind, messages) in self.message_manager.read_messages() {
             let channel_name = self
                 .message_manager
@@ -382,6 +718,55 @@ impl ConnectionManager {
                                         time = ?pong.pong_sent_time,
                                         "Updated server pong generation")
                         }
+                        ServerMessage::ChannelRegistration(ChannelRegistration {
+                            name,
+                            net_id,
+                        }) => {
+                            if let Err(e) = self.bind_channel(&name, net_id) {
+                                error!("could not register channel {name}: {e:?}");
+                            }
+                        }
+                        ServerMessage::SupportedCompressions(supported) => {
+                            let compression = self.packet_compression.negotiate(supported);
+                            debug!(?compression, "negotiated packet compression");
+                            if let Err(e) = self.message_manager.set_compression(compression) {
+                                error!("could not negotiate the packet compression: {e:?}");
+                            }
+                        }
+                        ServerMessage::ConnectionDenied(reason) => {
+                            error!(?reason, "the server refused the connection");
+                            self.denied_reason = Some(reason);
+                        }
+                        ServerMessage::ComponentCorrection(correction) => {
+                            corrections.push(correction);
+                        }
+                        ServerMessage::DynamicComponentIds(ids) => {
+                            let net_ids = world
+                                .resource_mut::<ComponentRegistry>()
+                                .set_remote_dynamic_net_ids(&ids);
+                            if let Err(e) = self.send_dynamic_component_ids_ack(net_ids) {
+                                error!("could not acknowledge the dynamic component ids: {e:?}");
+                            }
+                        }
+                        ServerMessage::GroupSendIntervals(send_intervals) => {
+                            for (group_id, send_interval) in send_intervals {
+                                debug!(
+                                    ?group_id,
+                                    ?send_interval,
+                                    "updated the send interval of the replication group"
+                                );
+                                match send_interval {
+                                    Some(interval) => {
+                                        self.sync_manager
+                                            .group_send_intervals
+                                            .insert(group_id, Duration::from_micros(interval));
+                                    }
+                                    None => {
+                                        self.sync_manager.group_send_intervals.remove(&group_id);
+                                    }
+                                }
+                            }
+                        }
                     }
 
                     // return the buffer to the pool
@@ -390,11 +775,28 @@ impl ConnectionManager {
             }
         }
 
+        // the server didn't accept some of the values of our entities
+        if !corrections.is_empty() {
+            world.resource_scope(|world, component_registry: Mut<ComponentRegistry>| {
+                for correction in corrections {
+                    self.replication_receiver.apply_component_correction(
+                        world,
+                        component_registry.as_ref(),
+                        correction.entity,
+                        &correction.component,
+                        &mut self.events,
+                    );
+                }
+            });
+        }
+
         // NOTE: we run this outside of is_empty() because we could have received an update for a future tick that we can
         //  now apply. Also we can read from out buffers even if we didn't receive any messages.
         //
         // Check if we have any replication messages we can apply to the World (and emit events)
-        if self.sync_manager.is_synced() {
+        if self.sync_manager.is_synced() && apply_replication {
+            self.replication_receiver
+                .apply_world_reset(world, &mut self.events);
             for (group, replication_list) in
                 self.replication_receiver.read_messages(tick_manager.tick())
             {
@@ -416,6 +818,9 @@ impl ConnectionManager {
                         });
                 })
             }
+            if let Err(e) = self.send_resend_requests() {
+                error!("could not request the resend of the unknown entities: {e:?}");
+            }
         }
     }
 
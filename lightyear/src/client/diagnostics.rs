@@ -2,7 +2,9 @@ use bevy::app::{App, Plugin, PostUpdate};
 use bevy::diagnostic::Diagnostics;
 use bevy::prelude::{not, Condition, IntoSystemConfigs, Real, Res, ResMut, Time};
 
+use crate::client::interpolation::diagnostics::InterpolationDiagnosticsPlugin;
 use crate::client::networking::is_disconnected;
+use crate::client::prediction::diagnostics::PredictionDiagnosticsPlugin;
 use crate::connection::client::{ClientConnection, NetClient};
 use crate::prelude::SharedConfig;
 use crate::transport::io::IoDiagnosticsPlugin;
@@ -21,7 +23,11 @@ fn io_diagnostics_system(
 }
 impl Plugin for ClientDiagnosticsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(IoDiagnosticsPlugin);
+        app.add_plugins((
+            IoDiagnosticsPlugin,
+            PredictionDiagnosticsPlugin,
+            InterpolationDiagnosticsPlugin,
+        ));
         app.add_systems(
             PostUpdate,
             io_diagnostics_system.run_if(not(
@@ -0,0 +1,197 @@
+//! Handles the entities that are disabled by the server with [`ReplicateDisabled`].
+//!
+//! Instead of despawning an entity that is temporarily removed from play (which would also
+//! despawn any client-side state attached to it, such as audio or VFX), the server can insert the
+//! [`ReplicateDisabled`] component on the entity. The client then inserts the marker registered with
+//! [`AppDisableExt::register_disabled_marker`] on the confirmed entity and on its predicted/interpolated
+//! entities, and removes it once the server removes [`ReplicateDisabled`].
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+
+use crate::client::components::Confirmed;
+use crate::client::interpolation::plugin::InterpolationSet;
+use crate::client::prediction::plugin::PredictionSet;
+use crate::shared::replication::components::ReplicateDisabled;
+
+/// Functions used to insert/remove the user's disabled marker on an entity
+#[derive(Resource, Clone, Copy)]
+struct DisabledMarker {
+    insert: fn(&mut EntityCommands),
+    remove: fn(&mut EntityCommands),
+}
+
+fn insert_marker<M: Component + Default>(entity: &mut EntityCommands) {
+    entity.insert(M::default());
+}
+
+fn remove_marker<M: Component>(entity: &mut EntityCommands) {
+    entity.remove::<M>();
+}
+
+pub trait AppDisableExt {
+    /// Register the marker component `M` that will be inserted on the client entities
+    /// that are disabled by the server with [`ReplicateDisabled`].
+    fn register_disabled_marker<M: Component + Default>(&mut self);
+}
+
+impl AppDisableExt for App {
+    fn register_disabled_marker<M: Component + Default>(&mut self) {
+        self.world.insert_resource(DisabledMarker {
+            insert: insert_marker::<M>,
+            remove: remove_marker::<M>,
+        });
+    }
+}
+
+pub(crate) struct DisablePlugin;
+
+impl Plugin for DisablePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ReplicateDisabled>();
+        app.add_systems(
+            PreUpdate,
+            (insert_disabled_marker, remove_disabled_marker)
+                // run after the predicted/interpolated entities are spawned
+                .after(PredictionSet::SpawnPrediction)
+                .after(InterpolationSet::SpawnInterpolation)
+                .run_if(resource_exists::<DisabledMarker>),
+        );
+    }
+}
+
+/// The entity, along with its predicted and interpolated entities
+fn with_sync_entities(
+    entity: Entity,
+    confirmed: Option<&Confirmed>,
+) -> impl Iterator<Item = Entity> + '_ {
+    std::iter::once(entity).chain(confirmed.into_iter().flat_map(|confirmed| {
+        confirmed
+            .predicted
+            .into_iter()
+            .chain(confirmed.interpolated)
+    }))
+}
+
+/// Insert the disabled marker on the entities that were just disabled, and on the
+/// predicted/interpolated entities that were spawned for a disabled entity
+fn insert_disabled_marker(
+    mut commands: Commands,
+    marker: Res<DisabledMarker>,
+    query: Query<
+        (Entity, Option<&Confirmed>),
+        (
+            With<ReplicateDisabled>,
+            Or<(Added<ReplicateDisabled>, Changed<Confirmed>)>,
+        ),
+    >,
+) {
+    for (entity, confirmed) in query.iter() {
+        trace!(?entity, "disable entity");
+        for entity in with_sync_entities(entity, confirmed) {
+            if let Some(mut entity_commands) = commands.get_entity(entity) {
+                (marker.insert)(&mut entity_commands);
+            }
+        }
+    }
+}
+
+/// Remove the disabled marker when the entity is enabled again
+fn remove_disabled_marker(
+    mut commands: Commands,
+    marker: Res<DisabledMarker>,
+    mut removed: RemovedComponents<ReplicateDisabled>,
+    confirmed_query: Query<&Confirmed>,
+) {
+    for entity in removed.read() {
+        trace!(?entity, "enable entity");
+        for entity in with_sync_entities(entity, confirmed_query.get(entity).ok()) {
+            if let Some(mut entity_commands) = commands.get_entity(entity) {
+                (marker.remove)(&mut entity_commands);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::default;
+
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{client, NetworkTarget};
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    #[derive(Component, Default)]
+    struct Disabled;
+
+    #[test]
+    fn test_disable_entity() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.register_disabled_marker::<Disabled>();
+
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((
+                Component1(0.0),
+                Replicate {
+                    sync: SyncTarget {
+                        prediction: NetworkTarget::All,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ))
+            .id();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        let confirmed_entity = *stepper
+            .client_app
+            .world
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        let predicted_entity = stepper
+            .client_app
+            .world
+            .get::<Confirmed>(confirmed_entity)
+            .unwrap()
+            .predicted
+            .expect("entity should be predicted");
+        assert!(stepper
+            .client_app
+            .world
+            .get::<Disabled>(confirmed_entity)
+            .is_none());
+
+        // disable the entity on the server: the client entities are not despawned but get the marker
+        stepper
+            .server_app
+            .world
+            .entity_mut(server_entity)
+            .insert(ReplicateDisabled);
+        stepper.frame_step();
+        stepper.frame_step();
+        for entity in [confirmed_entity, predicted_entity] {
+            assert!(stepper.client_app.world.get::<Disabled>(entity).is_some());
+        }
+
+        // enable the entity again
+        stepper
+            .server_app
+            .world
+            .entity_mut(server_entity)
+            .remove::<ReplicateDisabled>();
+        stepper.frame_step();
+        stepper.frame_step();
+        for entity in [confirmed_entity, predicted_entity] {
+            assert!(stepper.client_app.world.get_entity(entity).is_some());
+            assert!(stepper.client_app.world.get::<Disabled>(entity).is_none());
+        }
+    }
+}
@@ -13,13 +13,18 @@
 //! }
 //! ```
 
-use bevy::app::{App, Plugin, PreUpdate};
-use bevy::prelude::{Component, Event, Events, IntoSystemConfigs};
+use bevy::app::{App, Plugin};
+use bevy::prelude::{
+    Component, Entity, Event, EventReader, EventWriter, Events, IntoSystemConfigs, ResMut,
+};
 
 use crate::client::connection::ConnectionManager;
 use crate::prelude::ClientId;
+use crate::protocol::DeniedReason;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::events::plugin::EventsPlugin;
 use crate::shared::events::systems::push_component_events;
+use crate::shared::replication::DespawnReason;
 use crate::shared::sets::{ClientMarker, InternalMainSet};
 
 /// Plugin that handles generating bevy [`Events`] related to networking and replication
@@ -28,21 +33,135 @@ pub struct ClientEventsPlugin;
 
 impl Plugin for ClientEventsPlugin {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         app
             // EVENTS
             .add_event::<ConnectEvent>()
             .add_event::<DisconnectEvent>()
+            .add_event::<TransferProgressEvent>()
+            .add_event::<MessageExpiredEvent>()
+            .add_event::<FragmentProgressEvent>()
+            .add_event::<MessageAckedEvent>()
+            .add_event::<UnknownEntityUpdateEvent>()
+            .add_event::<EntityEnteredInterest>()
+            .add_event::<EntityLeftInterest>()
+            // SYSTEMS
+            .add_systems(
+                schedules.receive,
+                (
+                    emit_transfer_progress,
+                    emit_expired_messages,
+                    emit_fragment_progress,
+                    emit_acked_messages,
+                    emit_unknown_entity_updates,
+                )
+                    .in_set(InternalMainSet::<ClientMarker>::EmitEvents),
+            )
+            .add_systems(
+                schedules.receive,
+                emit_interest_events.after(InternalMainSet::<ClientMarker>::EmitEvents),
+            )
             // PLUGIN
             .add_plugins(EventsPlugin::<ConnectionManager>::default());
     }
 }
 
+/// Send the progress updates of the transfers on stream channels as bevy [`Events`]
+fn emit_transfer_progress(
+    mut connection: ResMut<ConnectionManager>,
+    mut events: EventWriter<TransferProgressEvent>,
+) {
+    events.send_batch(
+        connection
+            .message_manager
+            .drain_transfer_progress()
+            .into_iter()
+            .map(|(channel, progress)| TransferProgressEvent::new(channel, progress, ())),
+    );
+}
+
+/// Send the messages that expired before being acked as bevy [`Events`]
+fn emit_expired_messages(
+    mut connection: ResMut<ConnectionManager>,
+    mut events: EventWriter<MessageExpiredEvent>,
+) {
+    events.send_batch(
+        connection
+            .message_manager
+            .drain_expired_messages()
+            .into_iter()
+            .map(|(channel, message_id)| MessageExpiredEvent::new(channel, message_id, ())),
+    );
+}
+
+/// Send the progress updates of the fragmented messages as bevy [`Events`]
+fn emit_fragment_progress(
+    mut connection: ResMut<ConnectionManager>,
+    mut events: EventWriter<FragmentProgressEvent>,
+) {
+    events.send_batch(
+        connection
+            .message_manager
+            .drain_fragment_progress()
+            .into_iter()
+            .map(|(channel, progress)| FragmentProgressEvent::new(channel, progress, ())),
+    );
+}
+
+/// Send the acks of the messages that were sent with a [`MessageHandle`](crate::packet::message::MessageHandle) as bevy [`Events`]
+fn emit_acked_messages(
+    mut connection: ResMut<ConnectionManager>,
+    mut events: EventWriter<MessageAckedEvent>,
+) {
+    events.send_batch(
+        connection
+            .message_manager
+            .drain_acked_messages()
+            .into_iter()
+            .map(|handle| MessageAckedEvent::new(handle, ())),
+    );
+}
+
+/// Send the updates received for entities that don't exist locally as bevy [`Events`]
+fn emit_unknown_entity_updates(
+    mut connection: ResMut<ConnectionManager>,
+    mut events: EventWriter<UnknownEntityUpdateEvent>,
+) {
+    events.send_batch(
+        connection
+            .replication_receiver
+            .drain_unknown_entity_events()
+            .into_iter()
+            .map(|(remote_entity, tick)| UnknownEntityUpdateEvent::new(remote_entity, tick, ())),
+    );
+}
+
+/// Send the entities that entered or left the interest of the client as bevy [`Events`]
+fn emit_interest_events(
+    mut spawn_events: EventReader<EntitySpawnEvent>,
+    mut despawn_events: EventReader<EntityDespawnEvent>,
+    mut entered_events: EventWriter<EntityEnteredInterest>,
+    mut left_events: EventWriter<EntityLeftInterest>,
+) {
+    entered_events.send_batch(spawn_events.read().map(|event| EntityEnteredInterest {
+        entity: event.entity(),
+    }));
+    left_events.send_batch(
+        despawn_events
+            .read()
+            .filter(|event| event.reason() == Some(DespawnReason::LOST_VISIBILITY))
+            .map(|event| EntityLeftInterest {
+                entity: event.entity(),
+            }),
+    );
+}
+
 pub(crate) fn emit_replication_events<C: Component>(app: &mut App) {
     app.add_event::<ComponentUpdateEvent<C>>();
     app.add_event::<ComponentInsertEvent<C>>();
     app.add_event::<ComponentRemoveEvent<C>>();
     app.add_systems(
-        PreUpdate,
+        NetworkScheduleConfig::get(app).receive,
         push_component_events::<C, ConnectionManager>
             .in_set(InternalMainSet::<ClientMarker>::EmitEvents),
     );
@@ -65,8 +184,27 @@ impl ConnectEvent {
 }
 
 /// Bevy [`Event`] emitted on the client on the frame where the connection is disconnected
-#[derive(Event, Default)]
-pub struct DisconnectEvent;
+#[derive(Event, Default, Debug)]
+pub struct DisconnectEvent {
+    /// Set if the server refused the connection
+    pub reason: Option<DeniedReason>,
+}
+
+/// Bevy [`Event`] emitted on the client when a replicated entity is spawned locally, either because it was
+/// just spawned on the server or because it became visible to the client
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct EntityEnteredInterest {
+    /// The local entity
+    pub entity: Entity,
+}
+
+/// Bevy [`Event`] emitted on the client when a replicated entity is despawned locally because the client
+/// lost visibility of it (the entity still exists on the server)
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct EntityLeftInterest {
+    /// The local entity
+    pub entity: Entity,
+}
 
 /// Bevy [`Event`] emitted on the client to indicate the user input for the tick
 pub type InputEvent<I> = crate::shared::events::components::InputEvent<I, ()>;
@@ -82,3 +220,13 @@ pub type ComponentInsertEvent<C> = crate::shared::events::components::ComponentI
 pub type ComponentRemoveEvent<C> = crate::shared::events::components::ComponentRemoveEvent<C, ()>;
 /// Bevy [`Event`] emitted on the client when a (non-replication) message is received
 pub type MessageEvent<M> = crate::shared::events::components::MessageEvent<M, ()>;
+/// Bevy [`Event`] emitted on the client when the progress of a transfer on a stream channel is updated
+pub type TransferProgressEvent = crate::shared::events::components::TransferProgressEvent<()>;
+/// Bevy [`Event`] emitted on the client when a message sent to the server expired before being acked
+pub type MessageExpiredEvent = crate::shared::events::components::MessageExpiredEvent<()>;
+/// Bevy [`Event`] emitted on the client when a fragment of a message sent to the server is acked
+pub type FragmentProgressEvent = crate::shared::events::components::FragmentProgressEvent<()>;
+/// Bevy [`Event`] emitted on the client when the server received a message sent with a [`MessageHandle`](crate::packet::message::MessageHandle)
+pub type MessageAckedEvent = crate::shared::events::components::MessageAckedEvent<()>;
+/// Bevy [`Event`] emitted on the client when component updates are received for an entity that doesn't exist locally
+pub type UnknownEntityUpdateEvent = crate::shared::events::components::UnknownEntityUpdateEvent<()>;
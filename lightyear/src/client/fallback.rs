@@ -0,0 +1,186 @@
+/*! Try multiple connection configurations in order until the client manages to connect
+
+The [`ClientConnection`](crate::connection::client::ClientConnection) is rebuilt from [`ClientConfig::net`] every time
+the client starts connecting, so [`ClientConfig::net`] can be modified between two connection attempts to use a
+completely different transport.
+
+The [`ConnectionFallback`] policy does this automatically: if the connection attempt with [`ClientConfig::net`] fails,
+the client tries to connect again with each of the [`ConnectionFallback::net_configs`] in order,
+for example to fall back from WebTransport to WebSocket if the UDP path to the server is blocked.
+
+Once a connection is established, the client keeps using the config that worked until it gets disconnected.
+After the disconnection (or if all the configs failed), [`ClientConfig::net`] is restored so that the next connection
+attempt starts again with the first config.
+*/
+use bevy::prelude::*;
+use tracing::{error, info};
+
+use crate::client::config::ClientConfig;
+use crate::client::networking::NetworkingState;
+use crate::connection::client::NetConfig;
+
+/// Connection configurations that are tried in order if the connection attempt with
+/// [`ClientConfig::net`] fails
+#[derive(Clone, Default)]
+pub struct ConnectionFallback {
+    pub net_configs: Vec<NetConfig>,
+}
+
+impl ConnectionFallback {
+    /// Add a connection config that will be tried if all the previous ones failed
+    pub fn with_net_config(mut self, net_config: NetConfig) -> Self {
+        self.net_configs.push(net_config);
+        self
+    }
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct FallbackState {
+    /// True if the client is trying to connect
+    attempting: bool,
+    /// True if the current connection attempt succeeded
+    connected: bool,
+    /// Index of the next fallback config to try
+    next: usize,
+    /// The [`ClientConfig::net`] provided by the user, while a fallback config is being used
+    original: Option<NetConfig>,
+}
+
+impl FallbackState {
+    /// The connection attempt was cancelled by the user, we should not try the fallback configs
+    pub(crate) fn cancel(&mut self) {
+        self.attempting = false;
+    }
+
+    fn restore(&mut self, config: &mut ClientConfig) {
+        self.next = 0;
+        if let Some(original) = self.original.take() {
+            config.net = original;
+        }
+    }
+}
+
+pub(crate) fn on_connecting(mut state: ResMut<FallbackState>) {
+    state.attempting = true;
+    state.connected = false;
+}
+
+pub(crate) fn on_connected(mut state: ResMut<FallbackState>) {
+    state.connected = true;
+}
+
+/// If the connection attempt failed, try to connect with the next fallback config
+pub(crate) fn on_disconnected(
+    mut state: ResMut<FallbackState>,
+    mut config: ResMut<ClientConfig>,
+    mut next_state: ResMut<NextState<NetworkingState>>,
+) {
+    if !std::mem::take(&mut state.attempting) {
+        return;
+    }
+    if state.connected {
+        state.restore(&mut config);
+        return;
+    }
+    let Some(fallback) = config.fallback.net_configs.get(state.next).cloned() else {
+        if !config.fallback.net_configs.is_empty() {
+            error!("Could not connect with any of the fallback connection configs");
+        }
+        state.restore(&mut config);
+        return;
+    };
+    info!(
+        "Connection attempt failed, trying fallback connection config #{}",
+        state.next
+    );
+    state.next += 1;
+    let original = std::mem::replace(&mut config.net, fallback);
+    state.original.get_or_insert(original);
+    next_state.set(NetworkingState::Connecting);
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::utils::Duration;
+
+    use crate::client::config::NetcodeConfig;
+    use crate::prelude::client::{
+        Authentication, InterpolationConfig, PredictionConfig, SyncConfig,
+    };
+    use crate::prelude::{LinkConditionerConfig, SharedConfig, TickConfig};
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    #[test]
+    fn test_connection_fallback() {
+        let tick_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..Default::default()
+            },
+            SyncConfig::default().speedup_factor(1.0),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::from_millis(0),
+                incoming_jitter: Duration::from_millis(0),
+                incoming_loss: 0.0,
+            },
+            tick_duration,
+        );
+        // the first config uses the wrong protocol id, so the connection attempt fails
+        let mut config = stepper.client_app.world.resource_mut::<ClientConfig>();
+        let working_config = config.net.clone();
+        let NetConfig::Netcode {
+            auth:
+                Authentication::Manual {
+                    server_addr,
+                    private_key,
+                    client_id,
+                    ..
+                },
+            io,
+            ..
+        } = working_config.clone()
+        else {
+            unreachable!()
+        };
+        config.net = NetConfig::Netcode {
+            auth: Authentication::Manual {
+                server_addr,
+                protocol_id: 1,
+                private_key,
+                client_id,
+            },
+            config: NetcodeConfig {
+                client_timeout_secs: 1,
+                ..Default::default()
+            },
+            io,
+        };
+        config.fallback = ConnectionFallback::default().with_net_config(working_config);
+        stepper.init();
+        for _ in 0..200 {
+            stepper.frame_step();
+        }
+
+        // the client connected with the fallback config
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .resource::<State<NetworkingState>>()
+                .get(),
+            &NetworkingState::Connected
+        );
+        assert!(matches!(
+            stepper.client_app.world.resource::<ClientConfig>().net,
+            NetConfig::Netcode {
+                auth: Authentication::Manual { protocol_id: 0, .. },
+                ..
+            }
+        ));
+    }
+}
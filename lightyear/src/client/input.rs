@@ -40,6 +40,23 @@
 //! - handle inputs in your game logic in systems that run in the `FixedUpdate` schedule. These systems
 //! will read the inputs using the [`InputEvent`] event.
 //!
+//! If the framerate is higher than the tickrate, you can instead call [`add_frame_input`](InputManager::add_frame_input)
+//! every frame. The inputs of all the frames since the last tick are then combined into the input of the next tick
+//! with the [`CoalesceFn`] of the [`InputPlugin`](crate::prelude::InputPlugin), so that the inputs that only lasted
+//! for a few frames (for example a fast tap of a button) are not lost:
+//! ```rust
+//! # use bevy::prelude::*;
+//! # use serde::{Deserialize, Serialize};
+//! # use lightyear::prelude::InputPlugin;
+//! # #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+//! # struct MyInput(u8);
+//! # let mut app = App::new();
+//! // the input for the tick contains all the buttons that were pressed during one of the frames
+//! app.add_plugins(
+//!     InputPlugin::<MyInput>::default().with_coalesce_fn(|buttons, frame| MyInput(buttons.0 | frame.0)),
+//! );
+//! ```
+//!
 //! NOTE: I would advise to activate the `leafwing` feature to handle inputs via the `input_leafwing` module, instead.
 //! That module is more up-to-date and has more features.
 //! This module is kept for simplicity but might get removed in the future.
@@ -61,8 +78,10 @@ use crate::connection::client::NetClient;
 use crate::inputs::native::input_buffer::InputBuffer;
 use crate::inputs::native::{InputMessage, UserAction};
 use crate::prelude::client::ClientConnection;
-use crate::prelude::{server, AppMessageExt, ChannelDirection, SharedConfig, Tick, TickManager};
-use crate::shared::config::Mode;
+use crate::prelude::{AppMessageExt, ChannelDirection, SharedConfig, Tick, TickManager};
+use crate::shared::config::{Mode, NetworkScheduleConfig};
+use crate::shared::input::latest_input;
+pub use crate::shared::input::CoalesceFn;
 use crate::shared::sets::{ClientMarker, InternalMainSet};
 use crate::shared::tick_manager::TickEvent;
 
@@ -82,12 +101,24 @@ pub struct InputConfig {
 #[derive(Debug, Resource)]
 pub struct InputManager<A> {
     pub(crate) input_buffer: InputBuffer<A>,
+    /// Input combined from the frames since the last tick
+    pending_input: Option<A>,
+    /// Input added during the last frame, used for the ticks that run without a new frame input
+    last_frame_input: Option<A>,
+    /// True if a tick ran since the last frame input was added. The last frame input is then cleared
+    /// at the end of the frame, so that an input that is not added anymore is not repeated on the next frames
+    last_frame_input_consumed: bool,
+    pub(crate) coalesce_fn: CoalesceFn<A>,
 }
 
 impl<A> Default for InputManager<A> {
     fn default() -> Self {
         Self {
             input_buffer: InputBuffer::default(),
+            pending_input: None,
+            last_frame_input: None,
+            last_frame_input_consumed: false,
+            coalesce_fn: latest_input,
         }
     }
 }
@@ -103,6 +134,40 @@ impl<A: UserAction> InputManager<A> {
     pub fn add_input(&mut self, input: A, tick: Tick) {
         self.input_buffer.set(tick, Some(input));
     }
+
+    /// Add the user action of the current frame. It is buffered at the next tick, combined with the actions
+    /// of the other frames since the last tick by the [`CoalesceFn`].
+    ///
+    /// The ticks that run before a new frame action is added re-use the action of the last frame,
+    /// until the end of the frame in which they ran.
+    pub fn add_frame_input(&mut self, input: A) {
+        self.pending_input = Some(match self.pending_input.take() {
+            Some(pending) => (self.coalesce_fn)(pending, input.clone()),
+            None => input.clone(),
+        });
+        self.last_frame_input = Some(input);
+        self.last_frame_input_consumed = false;
+    }
+
+    /// Buffer the input combined from the frames since the last tick for the given tick
+    fn buffer_frame_input(&mut self, tick: Tick) {
+        if let Some(input) = self
+            .pending_input
+            .take()
+            .or_else(|| self.last_frame_input.clone())
+        {
+            self.add_input(input, tick);
+            self.last_frame_input_consumed = true;
+        }
+    }
+
+    /// Clear the last frame input if a tick already used it
+    fn clear_frame_input(&mut self) {
+        if self.last_frame_input_consumed {
+            self.last_frame_input = None;
+            self.last_frame_input_consumed = false;
+        }
+    }
 }
 
 impl Default for InputConfig {
@@ -115,16 +180,23 @@ impl Default for InputConfig {
 
 pub struct InputPlugin<A> {
     config: InputConfig,
-    _marker: std::marker::PhantomData<A>,
+    coalesce_fn: CoalesceFn<A>,
 }
 
 impl<A: UserAction> InputPlugin<A> {
     fn new(config: InputConfig) -> Self {
         Self {
             config,
-            _marker: std::marker::PhantomData,
+            coalesce_fn: latest_input,
         }
     }
+
+    /// Set the function that combines the inputs added with [`add_frame_input`](InputManager::add_frame_input)
+    /// during the frames between two ticks. By default, the input of the last frame is used.
+    pub fn with_coalesce_fn(mut self, coalesce_fn: CoalesceFn<A>) -> Self {
+        self.coalesce_fn = coalesce_fn;
+        self
+    }
 }
 
 impl<A: UserAction> Default for InputPlugin<A> {
@@ -142,10 +214,14 @@ pub struct CurrentInput<A: UserAction> {
 
 impl<A: UserAction> Plugin for InputPlugin<A> {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         // REGISTRATION
         app.register_type::<InputConfig>();
         // RESOURCES
-        app.init_resource::<InputManager<A>>();
+        app.insert_resource(InputManager::<A> {
+            coalesce_fn: self.coalesce_fn,
+            ..Default::default()
+        });
         // EVENT
         app.add_event::<InputEvent<A>>();
         // SETS
@@ -160,7 +236,7 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
         );
         app.configure_sets(FixedPostUpdate, InputSystemSet::ClearInputEvent);
         app.configure_sets(
-            PostUpdate,
+            schedules.send,
             (
                 SyncSet,
                 // handle tick events from sync before sending the message
@@ -181,6 +257,13 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
         );
         // SYSTEMS
 
+        app.add_systems(
+            FixedPreUpdate,
+            buffer_frame_input::<A>
+                .before(InputSystemSet::BufferInputs)
+                .run_if(not(is_in_rollback)),
+        );
+        app.add_systems(PostUpdate, clear_frame_input::<A>);
         // Host server mode only!
         app.add_systems(
             FixedPreUpdate,
@@ -199,7 +282,7 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
             clear_input_events::<A>.in_set(InputSystemSet::ClearInputEvent),
         );
         app.add_systems(
-            PostUpdate,
+            schedules.send,
             (
                 receive_tick_events::<A>.in_set(InputSystemSet::ReceiveTickEvents),
                 prepare_input_message::<A>.in_set(InputSystemSet::SendInputMessage),
@@ -231,6 +314,19 @@ pub enum InputSystemSet {
     SendInputMessage,
 }
 
+/// Buffer the inputs added with [`add_frame_input`](InputManager::add_frame_input) for the current tick
+fn buffer_frame_input<A: UserAction>(
+    tick_manager: Res<TickManager>,
+    mut input_manager: ResMut<InputManager<A>>,
+) {
+    input_manager.buffer_frame_input(tick_manager.tick());
+}
+
+/// Stop repeating the input of the last frame once it was buffered, if no new frame input was added
+fn clear_frame_input<A: UserAction>(mut input_manager: ResMut<InputManager<A>>) {
+    input_manager.clear_frame_input();
+}
+
 /// System that clears the input events.
 /// It is necessary because events are cleared every frame, but we want to clear every tick instead
 fn clear_input_events<A: UserAction>(mut input_events: EventReader<InputEvent<A>>) {
@@ -39,12 +39,30 @@
 //!   For instance, let's say you have a system in the `FixedUpdate` schedule that reacts on a button press when the button was `JustPressed`.
 //!   If we have 2 frames with no FixedUpdate in between (because the framerate is high compared to the tickrate), then on the second frame
 //!   the button won't be `JustPressed` anymore (it will simply be `Pressed`) so your system might not react correctly to it.
+//! - similarly, if a button is pressed and released between two ticks, the tick only sees the released button.
+//!   You can choose how the values of an action during the frames between two ticks are combined with
+//!   [`LeafwingInputConfig::with_coalescing`]:
+//! ```rust
+//! # use bevy::prelude::*;
+//! # use leafwing_input_manager::Actionlike;
+//! # use serde::{Deserialize, Serialize};
+//! # use lightyear::prelude::client::{InputCoalescing, LeafwingInputConfig};
+//! # #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash, Reflect, Actionlike)]
+//! # enum PlayerActions {
+//! #     Jump,
+//! #     Move,
+//! # }
+//! let config = LeafwingInputConfig::<PlayerActions>::default()
+//!     // a fast tap of the button between two ticks is still seen by the next tick
+//!     .with_coalescing(PlayerActions::Jump, InputCoalescing::AnyPressed)
+//!     .with_coalescing(PlayerActions::Move, InputCoalescing::Average);
+//! ```
 //!
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use bevy::prelude::*;
-use bevy::utils::{HashMap, HashSet};
+use bevy::utils::{Duration, HashMap, HashSet};
 use leafwing_input_manager::plugin::InputManagerSystem;
 use leafwing_input_manager::prelude::*;
 use tracing::{error, trace};
@@ -52,15 +70,18 @@ use tracing::{error, trace};
 use crate::channel::builder::InputChannel;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
+use crate::client::events::MessageEvent;
 use crate::client::prediction::plugin::{is_in_rollback, PredictionSet};
 use crate::client::prediction::rollback::{Rollback, RollbackState};
 use crate::client::prediction::Predicted;
 use crate::client::sync::{client_is_synced, SyncSet};
 use crate::inputs::leafwing::input_buffer::{
-    ActionDiff, ActionDiffBuffer, ActionDiffEvent, InputBuffer, InputMessage, InputTarget,
+    ActionDiff, ActionDiffBuffer, ActionDiffEvent, InputBuffer, InputMessage, InputStateRequest,
+    InputTarget,
 };
 use crate::inputs::leafwing::LeafwingUserAction;
-use crate::prelude::{Mode, SharedConfig, TickManager};
+use crate::prelude::{Mode, SharedConfig, Tick, TickManager};
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::replication::components::PrePredicted;
 use crate::shared::sets::{ClientMarker, FixedUpdateSet, InternalMainSet};
 use crate::shared::tick_manager::TickEvent;
@@ -90,8 +111,23 @@ impl<A> Default for ToggleActions<A> {
     }
 }
 
+/// How the values of an action during the frames that run between two ticks are combined into the
+/// value of the action for the next tick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+pub enum InputCoalescing {
+    /// Use the value of the action during the last frame before the tick
+    #[default]
+    Latest,
+    /// The action is pressed for the tick if it was pressed during any of the frames, so that
+    /// a fast tap of a button between two ticks is not lost
+    AnyPressed,
+    /// Use the average of the values (and axis pairs) of the action over the frames, where the frames in which
+    /// the action is released count as 0. The action is pressed if it was pressed during any of the frames.
+    Average,
+}
+
 // TODO: the resource should have a generic param, but not the user-facing config struct
-#[derive(Debug, Copy, Clone, Resource)]
+#[derive(Debug, Clone, Resource)]
 pub struct LeafwingInputConfig<A> {
     // TODO: right now the input-delay causes the client timeline to be more in the past than it should be
     //  I'm not sure if we can have different input_delay_ticks per ActionType
@@ -110,6 +146,16 @@ pub struct LeafwingInputConfig<A> {
     /// which would break the input handling on the server.
     /// Turn this on if you want to optimize the bandwidth that the client sends to the server.
     pub send_diffs_only: bool,
+    /// Interval at which the full [`ActionState`]s are sent to the server, in addition to the diffs.
+    ///
+    /// If some diffs are lost, the server's [`ActionState`] can differ from the client's (for example a button
+    /// can stay pressed); the full states let the server correct it.
+    /// The server also requests the full states as soon as it notices that some diffs are missing.
+    /// If `None`, the full states are only sent when the server requests them.
+    pub full_state_sync_interval: Option<Duration>,
+    /// How the frames between two ticks are combined for each action. The actions that are not present
+    /// use [`InputCoalescing::Latest`].
+    pub coalescing: HashMap<A, InputCoalescing>,
     // TODO: add an option where we send all diffs vs send only just-pressed diffs
     pub(crate) _marker: PhantomData<A>,
 }
@@ -120,6 +166,8 @@ impl<A> Default for LeafwingInputConfig<A> {
             // input_delay_ticks: 0,
             packet_redundancy: 10,
             send_diffs_only: true,
+            full_state_sync_interval: Some(Duration::from_secs(1)),
+            coalescing: HashMap::default(),
             _marker: PhantomData,
         }
     }
@@ -130,6 +178,181 @@ impl<A> LeafwingInputConfig<A> {
     //     self.input_delay_ticks = tick;
     //     self
     // }
+
+    pub fn with_full_state_sync_interval(mut self, interval: Option<Duration>) -> Self {
+        self.full_state_sync_interval = interval;
+        self
+    }
+
+    /// Choose how the values of `action` during the frames between two ticks are combined
+    pub fn with_coalescing(mut self, action: A, coalescing: InputCoalescing) -> Self
+    where
+        A: LeafwingUserAction,
+    {
+        self.coalescing.insert(action, coalescing);
+        self
+    }
+}
+
+/// Keeps track of when the full [`ActionState`]s were last sent to the server
+#[derive(Resource, Debug)]
+struct FullStateSync<A> {
+    last_sync: Option<Tick>,
+    /// The server requested the full states because some diffs were lost
+    requested: bool,
+    _marker: PhantomData<A>,
+}
+
+impl<A> Default for FullStateSync<A> {
+    fn default() -> Self {
+        Self {
+            last_sync: None,
+            requested: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A> FullStateSync<A> {
+    /// Returns true if the full states should be included in the input message sent at `tick`
+    fn should_sync(&mut self, tick: Tick, interval_ticks: Option<i16>) -> bool {
+        let periodic = interval_ticks.is_some_and(|interval| {
+            self.last_sync.map_or(true, |last_sync| {
+                !(0..interval).contains(&(tick - last_sync))
+            })
+        });
+        if !periodic && !self.requested {
+            return false;
+        }
+        self.last_sync = Some(tick);
+        self.requested = false;
+        true
+    }
+}
+
+/// Values of an action accumulated over the frames since the last tick where it was pressed
+#[derive(Debug, Default)]
+struct ActionAccumulator {
+    value: f32,
+    axis_pair: Option<Vec2>,
+}
+
+/// Actions accumulated for one [`ActionState`] over the frames since the last tick
+#[derive(Debug)]
+struct FrameActions<A> {
+    num_frames: u32,
+    actions: HashMap<A, ActionAccumulator>,
+}
+
+impl<A> Default for FrameActions<A> {
+    fn default() -> Self {
+        Self {
+            num_frames: 0,
+            actions: HashMap::default(),
+        }
+    }
+}
+
+/// Tracks the actions that use an [`InputCoalescing`] other than [`InputCoalescing::Latest`].
+///
+/// The [`ActionState`]s are identified by their entity, or `None` for the global [`ActionState`].
+#[derive(Resource, Debug)]
+struct CoalescedActions<A: LeafwingUserAction> {
+    frames: HashMap<Option<Entity>, FrameActions<A>>,
+    /// The [`ActionState`]s that were modified by the coalescing at the last tick, with their value before
+    /// the coalescing. The coalesced values only apply to that tick.
+    coalesced: HashMap<Option<Entity>, (ActionState<A>, ActionState<A>)>,
+}
+
+impl<A: LeafwingUserAction> Default for CoalescedActions<A> {
+    fn default() -> Self {
+        Self {
+            frames: HashMap::default(),
+            coalesced: HashMap::default(),
+        }
+    }
+}
+
+impl<A: LeafwingUserAction> CoalescedActions<A> {
+    /// Record the values of the actions during a frame.
+    ///
+    /// Returns the diffs that restore the values of the actions that were coalesced at the last tick.
+    fn accumulate(
+        &mut self,
+        owner: Option<Entity>,
+        action_state: &ActionState<A>,
+        config: &LeafwingInputConfig<A>,
+    ) -> Vec<ActionDiff<A>> {
+        let diffs = self
+            .coalesced
+            .remove(&owner)
+            .map_or(vec![], |(coalesced, _)| {
+                ActionDiff::between(&coalesced, action_state)
+            });
+        let frames = self.frames.entry(owner).or_default();
+        frames.num_frames += 1;
+        for (action, coalescing) in config.coalescing.iter() {
+            if *coalescing == InputCoalescing::Latest || !action_state.pressed(action) {
+                continue;
+            }
+            let accumulator = frames.actions.entry(*action).or_default();
+            accumulator.value += action_state.value(action);
+            if let Some(axis_pair) = action_state.axis_pair(action) {
+                accumulator.axis_pair =
+                    Some(accumulator.axis_pair.unwrap_or_default() + axis_pair.xy());
+            }
+        }
+        diffs
+    }
+
+    /// Combine the values of the actions during the frames since the last tick, and apply them to the
+    /// [`ActionState`] of the current tick.
+    ///
+    /// Returns the diffs that were applied to the [`ActionState`].
+    fn coalesce(
+        &mut self,
+        owner: Option<Entity>,
+        action_state: &mut ActionState<A>,
+        config: &LeafwingInputConfig<A>,
+    ) -> Vec<ActionDiff<A>> {
+        let Some(frames) = self.frames.remove(&owner) else {
+            // no frame ran since the last tick: the coalesced values don't apply to this tick
+            let Some((_, raw)) = self.coalesced.remove(&owner) else {
+                return vec![];
+            };
+            let diffs = ActionDiff::between(action_state, &raw);
+            *action_state = raw;
+            return diffs;
+        };
+        let mut target = action_state.clone();
+        let num_frames = frames.num_frames.max(1) as f32;
+        for (action, accumulator) in frames.actions {
+            match config.coalescing.get(&action) {
+                Some(InputCoalescing::AnyPressed) if !target.pressed(&action) => {
+                    ActionDiff::Pressed { action }.apply(&mut target);
+                }
+                Some(InputCoalescing::Average) => match accumulator.axis_pair {
+                    Some(axis_pair) => ActionDiff::AxisPairChanged {
+                        action,
+                        axis_pair: axis_pair / num_frames,
+                    }
+                    .apply(&mut target),
+                    None => ActionDiff::ValueChanged {
+                        action,
+                        value: accumulator.value / num_frames,
+                    }
+                    .apply(&mut target),
+                },
+                _ => {}
+            }
+        }
+        let diffs = ActionDiff::between(action_state, &target);
+        if !diffs.is_empty() {
+            let raw = std::mem::replace(action_state, target.clone());
+            self.coalesced.insert(owner, (target, raw));
+        }
+        diffs
+    }
 }
 
 /// Adds a plugin to handle inputs using the LeafwingInputManager
@@ -174,7 +397,10 @@ impl<A: LeafwingUserAction + TypePath> Plugin for LeafwingInputPlugin<A>
         // RESOURCES
         app.insert_resource(self.config.clone());
         app.init_resource::<ToggleActions<A>>();
+        app.init_resource::<FullStateSync<A>>();
+        app.init_resource::<CoalescedActions<A>>();
 
+        let schedules = NetworkScheduleConfig::get(app);
         // in host-server mode, we don't need to handle inputs in any way, because the player's entity
         // is spawned with `InputBuffer` and the client is in the same timeline as the server
         let should_run = run_if_enabled::<A>.and_then(not(SharedConfig::is_host_server_condition));
@@ -186,12 +412,19 @@ impl<A: LeafwingUserAction + TypePath> Plugin for LeafwingInputPlugin<A>
         app.init_resource::<Events<ActionDiffEvent<A>>>();
         // SETS
         // app.configure_sets(PreUpdate, InputManagerSystem::Tick.run_if(should_tick::<A>));
+        // update the ActionState from the user's inputs after receiving the replicated ActionState from the server,
+        // otherwise the local inputs could be overwritten before we generate the diffs
+        app.configure_sets(
+            schedules.receive,
+            (InputManagerSystem::Tick, InputManagerSystem::Update)
+                .after(InternalMainSet::<ClientMarker>::Receive),
+        );
         app.configure_sets(
             FixedPreUpdate,
             InputSystemSet::BufferClientInputs.run_if(should_run.clone()),
         );
         app.configure_sets(
-            PostUpdate,
+            schedules.send,
             // we send inputs only every send_interval
             (
                 SyncSet,
@@ -220,6 +453,9 @@ impl<A: LeafwingUserAction + TypePath> Plugin for LeafwingInputPlugin<A>
                 add_action_state_buffer::<A>
                     .run_if(should_run.clone())
                     .after(PredictionSet::SpawnPrediction),
+                accumulate_action_states::<A>
+                    .run_if(should_run.clone())
+                    .after(generate_action_diffs::<A>),
             ),
         );
         // NOTE: we do not tick the ActionState during FixedUpdate
@@ -231,6 +467,7 @@ impl<A: LeafwingUserAction + TypePath> Plugin for LeafwingInputPlugin<A>
             FixedPreUpdate,
             (
                 (
+                    coalesce_action_states::<A>,
                     (write_action_diffs::<A>, buffer_action_state::<A>),
                     // get the action-state corresponding to the current tick (which we need to get from the buffer
                     //  because it was added to the buffer input_delay ticks ago)
@@ -266,7 +503,7 @@ impl<A: LeafwingUserAction + TypePath> Plugin for LeafwingInputPlugin<A>
         // - one thing to understand is that if we have F1 TA ( frame 1 starts, and then we run one FixedUpdate schedule)
         //   we want to add the input value computed during F1 to the buffer for tick TA, because the tick will use this value
         app.add_systems(
-            PostUpdate,
+            schedules.send,
             prepare_input_message::<A>.in_set(InputSystemSet::SendInputMessage),
         );
 
@@ -280,9 +517,10 @@ impl<A: LeafwingUserAction + TypePath> Plugin for LeafwingInputPlugin<A>
         //   for example if I have F1 TA F2 TB TC F3, we set the value after TA and after TC
         //   'set' will apply SameAsPrecedent for TB.
         app.add_systems(
-            PostUpdate,
+            schedules.send,
             (
-                receive_tick_events::<A>.in_set(InputSystemSet::ReceiveTickEvents),
+                (receive_tick_events::<A>, receive_state_requests::<A>)
+                    .in_set(InputSystemSet::ReceiveTickEvents),
                 clean_buffers::<A>.in_set(InputSystemSet::CleanUp),
                 add_action_state_buffer_added_input_map::<A>.run_if(should_run.clone()),
                 toggle_actions::<A>,
@@ -507,6 +745,65 @@ fn get_rollback_action_state<A: LeafwingUserAction>(
     }
 }
 
+/// Record the values of the actions that are coalesced over the frames between two ticks
+fn accumulate_action_states<A: LeafwingUserAction>(
+    config: Res<LeafwingInputConfig<A>>,
+    mut coalesced_actions: ResMut<CoalescedActions<A>>,
+    global_action_state: Option<Res<ActionState<A>>>,
+    action_state_query: Query<(Entity, &ActionState<A>), With<InputMap<A>>>,
+    mut action_diffs: EventWriter<ActionDiffEvent<A>>,
+) {
+    if config.coalescing.is_empty() {
+        return;
+    }
+    let action_state_iter = action_state_query
+        .iter()
+        .map(|(entity, action_state)| (Some(entity), action_state))
+        .chain(
+            global_action_state
+                .as_ref()
+                .map(|action_state| (None, action_state.as_ref())),
+        );
+    for (owner, action_state) in action_state_iter {
+        let diffs = coalesced_actions.accumulate(owner, action_state, &config);
+        if !diffs.is_empty() {
+            action_diffs.send(ActionDiffEvent {
+                owner,
+                action_diff: diffs,
+            });
+        }
+    }
+}
+
+/// Apply the values of the actions coalesced over the frames since the last tick to the ActionStates,
+/// before they are written to the buffers
+fn coalesce_action_states<A: LeafwingUserAction>(
+    config: Res<LeafwingInputConfig<A>>,
+    mut coalesced_actions: ResMut<CoalescedActions<A>>,
+    global_action_state: Option<ResMut<ActionState<A>>>,
+    mut action_state_query: Query<(Entity, &mut ActionState<A>), With<InputBuffer<A>>>,
+    mut action_diffs: EventWriter<ActionDiffEvent<A>>,
+) {
+    if config.coalescing.is_empty() {
+        return;
+    }
+    let mut coalesce = |owner: Option<Entity>, action_state: &mut ActionState<A>| {
+        let diffs = coalesced_actions.coalesce(owner, action_state, &config);
+        if !diffs.is_empty() {
+            action_diffs.send(ActionDiffEvent {
+                owner,
+                action_diff: diffs,
+            });
+        }
+    };
+    for (entity, mut action_state) in action_state_query.iter_mut() {
+        coalesce(Some(entity), action_state.as_mut());
+    }
+    if let Some(mut action_state) = global_action_state {
+        coalesce(None, action_state.as_mut());
+    }
+}
+
 /// Read the action-diffs and store them in a buffer.
 ///
 /// NOTE: we have an ActionState buffer used for rollbacks,
@@ -577,15 +874,21 @@ fn clean_buffers<A: LeafwingUserAction>(
 
 /// Send a message to the server containing the ActionDiffs for the last few ticks
 /// Also clear the ActionDiffBuffers and InputBuffers
+///
+/// Periodically (or when the server requested it), the message also contains the full ActionStates.
 fn prepare_input_message<A: LeafwingUserAction>(
     mut connection: ResMut<ConnectionManager>,
     config: Res<ClientConfig>,
+    input_config: Res<LeafwingInputConfig<A>>,
     tick_manager: Res<TickManager>,
+    mut full_state_sync: ResMut<FullStateSync<A>>,
     global_action_diff_buffer: Option<Res<ActionDiffBuffer<A>>>,
+    global_input_buffer: Res<InputBuffer<A>>,
     action_diff_buffer_query: Query<
         (
             Entity,
             &ActionDiffBuffer<A>,
+            Option<&InputBuffer<A>>,
             Option<&Predicted>,
             Option<&PrePredicted>,
         ),
@@ -607,8 +910,25 @@ fn prepare_input_message<A: LeafwingUserAction>(
     .unwrap();
     let redundancy = config.input.packet_redundancy;
     let message_len = redundancy * num_tick;
+    let interval_ticks = input_config.full_state_sync_interval.map(|interval| {
+        (interval.as_nanos() / config.shared.tick.tick_duration.as_nanos())
+            .clamp(1, i16::MAX as u128) as i16
+    });
+    let send_states = full_state_sync.should_sync(tick, interval_ticks);
     let mut message = InputMessage::<A>::new(tick);
-    for (entity, action_diff_buffer, predicted, pre_predicted) in action_diff_buffer_query.iter() {
+    let add_state = |message: &mut InputMessage<A>,
+                     input_buffer: Option<&InputBuffer<A>>,
+                     target: InputTarget| {
+        if let Some(state) = input_buffer
+            .filter(|_| send_states)
+            .and_then(|buffer| buffer.get(tick))
+        {
+            message.states.push((target, state.clone()));
+        }
+    };
+    for (entity, action_diff_buffer, input_buffer, predicted, pre_predicted) in
+        action_diff_buffer_query.iter()
+    {
         debug!(
             ?tick,
             ?entity,
@@ -640,6 +960,11 @@ fn prepare_input_message<A: LeafwingUserAction>(
                 message_len,
                 InputTarget::PrePredictedEntity(entity),
             );
+            add_state(
+                &mut message,
+                input_buffer,
+                InputTarget::PrePredictedEntity(entity),
+            );
         } else {
             // 1. if the entity is confirmed, we need to convert the entity to the server's entity
             // 2. if the entity is predicted, we need to first convert the entity to confirmed, and then from confirmed to remote
@@ -657,6 +982,11 @@ fn prepare_input_message<A: LeafwingUserAction>(
                         message_len,
                         InputTarget::Entity(server_entity),
                     );
+                    add_state(
+                        &mut message,
+                        input_buffer,
+                        InputTarget::Entity(server_entity),
+                    );
                 }
             } else {
                 // TODO: entity is not predicted or not confirmed? also need to do the conversion, no?
@@ -667,6 +997,11 @@ fn prepare_input_message<A: LeafwingUserAction>(
 
     if let Some(action_diff_buffer) = global_action_diff_buffer {
         action_diff_buffer.add_to_message(&mut message, tick, message_len, InputTarget::Global);
+        add_state(
+            &mut message,
+            Some(&*global_input_buffer),
+            InputTarget::Global,
+        );
     }
 
     // all inputs are absent
@@ -691,6 +1026,17 @@ fn prepare_input_message<A: LeafwingUserAction>(
     //  maybe at interpolation_tick(), since it's before any latest server update we receive?
 }
 
+/// The server noticed that some diffs were lost: send the full ActionStates in the next input message
+fn receive_state_requests<A: LeafwingUserAction>(
+    mut requests: EventReader<MessageEvent<InputStateRequest<A>>>,
+    mut full_state_sync: ResMut<FullStateSync<A>>,
+) {
+    if requests.read().count() > 0 {
+        debug!(action = ?A::short_type_path(), "the server requested the full action states");
+        full_state_sync.requested = true;
+    }
+}
+
 fn receive_tick_events<A: LeafwingUserAction>(
     mut tick_events: EventReader<TickEvent>,
     mut global_action_diff_buffer: Option<ResMut<ActionDiffBuffer<A>>>,
@@ -959,8 +1305,14 @@ mod tests {
     use super::*;
 
     fn setup() -> (BevyStepper, Entity, Entity) {
+        setup_with_config(Duration::from_millis(10), LeafwingInputConfig::default())
+    }
+
+    fn setup_with_config(
+        tick_duration: Duration,
+        config: LeafwingInputConfig<LeafwingInput1>,
+    ) -> (BevyStepper, Entity, Entity) {
         let frame_duration = Duration::from_millis(10);
-        let tick_duration = Duration::from_millis(10);
         let shared_config = SharedConfig {
             tick: TickConfig::new(tick_duration),
             ..Default::default()
@@ -985,7 +1337,7 @@ mod tests {
         {
             stepper
                 .client_app
-                .add_plugins(crate::prelude::LeafwingInputPlugin::<LeafwingInput1>::default());
+                .add_plugins(crate::prelude::LeafwingInputPlugin::<LeafwingInput1> { config });
             stepper
                 .client_app
                 .add_plugins(crate::prelude::LeafwingInputPlugin::<LeafwingInput2>::default());
@@ -1075,4 +1427,56 @@ mod tests {
             assert_eq!(event.owner, Some(client_entity));
         }
     }
+
+    #[test]
+    fn test_coalesce_button_tap() {
+        // the ticks are 3 times longer than the frames
+        let (mut stepper, _, client_entity) = setup_with_config(
+            Duration::from_millis(30),
+            LeafwingInputConfig::default()
+                .with_coalescing(LeafwingInput1::Jump, InputCoalescing::AnyPressed),
+        );
+        // wait for a frame that runs a tick
+        let tick = stepper.client_tick();
+        while stepper.client_tick() == tick {
+            stepper.frame_step();
+        }
+        let start_tick = stepper.client_tick();
+
+        // press and release the jump button before the next tick
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyA);
+        stepper.frame_step();
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .release(KeyCode::KeyA);
+
+        // the old ticks are removed from the InputBuffer, so we check each tick when it runs
+        let mut ticks_pressed = vec![];
+        for _ in 0..6 {
+            stepper.frame_step();
+            let tick = stepper.client_tick();
+            let pressed = stepper
+                .client_app
+                .world
+                .entity(client_entity)
+                .get::<InputBuffer<LeafwingInput1>>()
+                .unwrap()
+                .get(tick)
+                .is_some_and(|action_state| action_state.pressed(&LeafwingInput1::Jump));
+            if tick != start_tick && ticks_pressed.last().map_or(true, |(last, _)| *last != tick) {
+                ticks_pressed.push((tick, pressed));
+            }
+        }
+        // the tap is seen by the first tick after the press, and only by that tick
+        assert_eq!(
+            ticks_pressed[..2],
+            [(start_tick + 1, true), (start_tick + 2, false)]
+        );
+    }
 }
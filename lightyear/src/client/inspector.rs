@@ -0,0 +1,70 @@
+/*! Inspect the world of a server from a client
+
+The [`InspectorClientPlugin`] turns a client into an inspector of the [server world](crate::server::inspector):
+the client presents the admin token when it connects, and the latest [`InspectorSnapshot`] received from the
+server is inserted as a resource.
+*/
+use bevy::prelude::*;
+use tracing::error;
+
+use crate::channel::builder::InspectorChannel;
+use crate::client::connection::ConnectionManager;
+use crate::client::events::{ConnectEvent, MessageEvent};
+use crate::client::networking::is_connected;
+use crate::server::inspector::{InspectorRequest, InspectorSnapshot};
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::sets::{ClientMarker, InternalMainSet};
+
+/// Plugin that requests the [`InspectorSnapshot`]s of the server world when the client connects
+pub struct InspectorClientPlugin {
+    admin_token: String,
+}
+
+impl InspectorClientPlugin {
+    /// `admin_token` must match the [`InspectorConfig::admin_token`](crate::server::inspector::InspectorConfig::admin_token) of the server
+    pub fn new(admin_token: impl Into<String>) -> Self {
+        Self {
+            admin_token: admin_token.into(),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct InspectorToken(String);
+
+impl Plugin for InspectorClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InspectorToken(self.admin_token.clone()));
+        app.add_systems(
+            NetworkScheduleConfig::get(app).receive,
+            (request_inspection, receive_inspector_snapshots)
+                .after(InternalMainSet::<ClientMarker>::EmitEvents)
+                .run_if(is_connected),
+        );
+    }
+}
+
+/// Present the admin token to the server when the client connects
+fn request_inspection(
+    token: Res<InspectorToken>,
+    mut connect_events: EventReader<ConnectEvent>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    for _ in connect_events.read() {
+        if let Err(e) = connection_manager.send_message::<InspectorChannel, _>(&InspectorRequest {
+            admin_token: token.0.clone(),
+        }) {
+            error!(?e, "could not send the inspector request");
+        }
+    }
+}
+
+/// Insert the latest snapshot of the server world
+fn receive_inspector_snapshots(
+    mut commands: Commands,
+    mut snapshots: EventReader<MessageEvent<InspectorSnapshot>>,
+) {
+    if let Some(event) = snapshots.read().last() {
+        commands.insert_resource(event.message.clone());
+    }
+}
@@ -0,0 +1,349 @@
+//! Diagnostics about the interpolation buffers of each interpolated entity
+//!
+//! An interpolated entity buffers the server updates that are more recent than the interpolation time.
+//! If the buffer is often empty (underflow) the entity has nothing to interpolate towards and stutters;
+//! if it is consistently much deeper than needed (overflow) the entity is shown later than necessary.
+//! Both usually mean that the [`InterpolationDelay`](crate::client::interpolation::plugin::InterpolationDelay)
+//! does not match the network conditions, so the [`InterpolationDiagnosticsPlugin`] emits an
+//! [`InterpolationBufferWarning`] with a suggested delay when it happens.
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::diagnostic::{Diagnostic, DiagnosticMeasurement, DiagnosticPath, DiagnosticsStore};
+use bevy::prelude::{Component, Event, EventWriter, Local, Query, Res, ResMut, Time};
+use bevy::utils::{Duration, Instant};
+use tracing::warn;
+
+use crate::client::components::SyncComponent;
+use crate::client::config::ClientConfig;
+use crate::client::interpolation::interpolate::InterpolateStatus;
+use crate::client::interpolation::interpolation_history::ConfirmedHistory;
+use crate::prelude::TickManager;
+
+/// Depth and age of the interpolation buffer of an interpolated entity, updated every frame.
+///
+/// If the entity has several interpolated components, the stats of the most starved buffer are used.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct InterpolationBufferStats {
+    /// Number of buffered server updates that are more recent than the interpolation time
+    pub depth: usize,
+    /// How far ahead of the interpolation time the most recent buffered update is
+    pub age: Duration,
+    /// How far the interpolation time is past the most recent server update, when the buffer is empty
+    pub overrun: Duration,
+    recorded: bool,
+}
+
+impl InterpolationBufferStats {
+    /// The buffer is empty: the entity has no server update to interpolate towards
+    pub fn is_underflowing(&self) -> bool {
+        self.depth == 0
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn record(&mut self, depth: usize, age: Duration, overrun: Duration) {
+        if !self.recorded {
+            *self = Self {
+                depth,
+                age,
+                overrun,
+                recorded: true,
+            };
+            return;
+        }
+        self.depth = self.depth.min(depth);
+        self.age = self.age.min(age);
+        self.overrun = self.overrun.max(overrun);
+    }
+}
+
+/// Which way the interpolation buffers are misbehaving
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationBufferIssue {
+    /// The buffers are often empty: the interpolation delay is too small
+    Underflow,
+    /// The buffers consistently hold more than the interpolation delay: the interpolation delay is too big
+    Overflow,
+}
+
+/// Bevy [`Event`] emitted on the client when the interpolation buffers underflow or overflow during more than
+/// [`InterpolationDiagnosticsPlugin::WARNING_RATIO`] of the frames of the last [`InterpolationDiagnosticsPlugin::WINDOW`]
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct InterpolationBufferWarning {
+    pub issue: InterpolationBufferIssue,
+    /// The interpolation delay currently in use
+    pub delay: Duration,
+    /// An interpolation delay that would have kept the buffers between empty and the delay during the window
+    pub suggested_delay: Duration,
+}
+
+/// Update the [`InterpolationBufferStats`] with the buffer of the component `C`
+pub(crate) fn update_interpolation_buffer_stats<C: SyncComponent>(
+    tick_manager: Res<TickManager>,
+    mut query: Query<(
+        &mut InterpolationBufferStats,
+        &InterpolateStatus<C>,
+        &ConfirmedHistory<C>,
+    )>,
+) {
+    let tick_duration = tick_manager.config.tick_duration;
+    let ticks_to_duration = |ticks: f32| tick_duration.mul_f32(ticks.max(0.0));
+    for (mut stats, status, history) in query.iter_mut() {
+        let depth = history.buffer.len() + status.end.is_some() as usize;
+        let newest_tick = history
+            .buffer
+            .heap
+            .iter()
+            .map(|item| item.key)
+            .chain(status.end.as_ref().map(|(tick, _)| *tick))
+            .max();
+        let (age, overrun) = match (newest_tick, &status.start) {
+            (Some(newest_tick), _) => (
+                ticks_to_duration(
+                    (newest_tick - status.current_tick) as f32 - status.current_overstep,
+                ),
+                Duration::ZERO,
+            ),
+            (None, Some((start_tick, _))) => (
+                Duration::ZERO,
+                ticks_to_duration(
+                    (status.current_tick - *start_tick) as f32 + status.current_overstep,
+                ),
+            ),
+            (None, None) => (Duration::ZERO, Duration::ZERO),
+        };
+        stats.record(depth, age, overrun);
+    }
+}
+
+/// Reset the [`InterpolationBufferStats`] before the buffers of the components are measured
+pub(crate) fn reset_interpolation_buffer_stats(mut query: Query<&mut InterpolationBufferStats>) {
+    for mut stats in query.iter_mut() {
+        stats.reset();
+    }
+}
+
+/// Accumulates the state of the interpolation buffers over a window
+#[derive(Debug, Default)]
+struct BufferMonitor {
+    elapsed: Duration,
+    frames: u32,
+    underflow_frames: u32,
+    overflow_frames: u32,
+    max_overrun: Duration,
+    min_age: Option<Duration>,
+}
+
+impl BufferMonitor {
+    /// Record the stats of all the interpolated entities for one frame
+    fn record<'a>(
+        &mut self,
+        stats: impl Iterator<Item = &'a InterpolationBufferStats>,
+        delay: Duration,
+    ) {
+        let mut recorded = false;
+        let mut underflow = false;
+        let mut min_age: Option<Duration> = None;
+        for stats in stats.filter(|stats| stats.recorded) {
+            recorded = true;
+            if stats.is_underflowing() {
+                underflow = true;
+                self.max_overrun = self.max_overrun.max(stats.overrun);
+            } else {
+                min_age = Some(min_age.map_or(stats.age, |age| age.min(stats.age)));
+            }
+        }
+        if !recorded {
+            return;
+        }
+        self.frames += 1;
+        if underflow {
+            self.underflow_frames += 1;
+        } else if let Some(age) = min_age {
+            if age > delay {
+                self.overflow_frames += 1;
+            }
+            self.min_age = Some(self.min_age.map_or(age, |min_age| min_age.min(age)));
+        }
+    }
+
+    /// Check if the buffers misbehaved during the window
+    fn evaluate(&self, delay: Duration) -> Option<InterpolationBufferWarning> {
+        if self.frames == 0 {
+            return None;
+        }
+        let ratio = |frames: u32| frames as f32 / self.frames as f32;
+        if ratio(self.underflow_frames) > InterpolationDiagnosticsPlugin::WARNING_RATIO {
+            return Some(InterpolationBufferWarning {
+                issue: InterpolationBufferIssue::Underflow,
+                delay,
+                suggested_delay: delay + self.max_overrun,
+            });
+        }
+        if ratio(self.overflow_frames) > InterpolationDiagnosticsPlugin::WARNING_RATIO {
+            // reducing the delay reduces the age of the buffers by the same amount
+            let excess = self.min_age.unwrap_or_default().saturating_sub(delay);
+            return Some(InterpolationBufferWarning {
+                issue: InterpolationBufferIssue::Overflow,
+                delay,
+                suggested_delay: delay.saturating_sub(excess),
+            });
+        }
+        None
+    }
+}
+
+/// Publishes the [`InterpolationBufferStats`] as bevy diagnostics, and emits an [`InterpolationBufferWarning`]
+/// when the buffers consistently underflow or overflow.
+///
+/// The diagnostics `interpolation/buffer depth` and `interpolation/buffer age` (in milliseconds) contain the
+/// smallest depth and age over all the interpolated entities.
+pub struct InterpolationDiagnosticsPlugin;
+
+impl InterpolationDiagnosticsPlugin {
+    /// Max diagnostic history length.
+    pub const DIAGNOSTIC_HISTORY_LEN: usize = 60;
+    /// Duration over which the state of the buffers is accumulated before deciding to emit a warning
+    pub const WINDOW: Duration = Duration::from_secs(2);
+    /// Fraction of the frames of the window where the buffers need to underflow (or overflow) to emit a warning
+    pub const WARNING_RATIO: f32 = 0.2;
+
+    pub const BUFFER_DEPTH: DiagnosticPath =
+        DiagnosticPath::const_new("interpolation/buffer depth");
+    pub const BUFFER_AGE: DiagnosticPath = DiagnosticPath::const_new("interpolation/buffer age");
+
+    fn add_measurement(store: &mut DiagnosticsStore, path: &DiagnosticPath, value: f64) {
+        if let Some(diagnostic) = store.get_mut(path) {
+            diagnostic.add_measurement(DiagnosticMeasurement {
+                time: Instant::now(),
+                value,
+            });
+        }
+    }
+}
+
+fn interpolation_diagnostics_system(
+    config: Res<ClientConfig>,
+    time: Res<Time>,
+    query: Query<&InterpolationBufferStats>,
+    mut store: ResMut<DiagnosticsStore>,
+    mut warnings: EventWriter<InterpolationBufferWarning>,
+    mut monitor: Local<BufferMonitor>,
+) {
+    let delay = config
+        .interpolation
+        .delay
+        .to_duration(config.shared.server_send_interval);
+    let recorded = || query.iter().filter(|stats| stats.recorded);
+    if let Some(depth) = recorded().map(|stats| stats.depth).min() {
+        InterpolationDiagnosticsPlugin::add_measurement(
+            &mut store,
+            &InterpolationDiagnosticsPlugin::BUFFER_DEPTH,
+            depth as f64,
+        );
+    }
+    if let Some(age) = recorded().map(|stats| stats.age).min() {
+        InterpolationDiagnosticsPlugin::add_measurement(
+            &mut store,
+            &InterpolationDiagnosticsPlugin::BUFFER_AGE,
+            age.as_secs_f64() * 1000.0,
+        );
+    }
+
+    monitor.record(query.iter(), delay);
+    monitor.elapsed += time.delta();
+    if monitor.elapsed < InterpolationDiagnosticsPlugin::WINDOW {
+        return;
+    }
+    if let Some(warning) = monitor.evaluate(delay) {
+        warn!(
+            issue = ?warning.issue,
+            delay = ?warning.delay,
+            suggested_delay = ?warning.suggested_delay,
+            "The interpolation buffers are misbehaving: consider changing the interpolation delay"
+        );
+        warnings.send(warning);
+    }
+    *monitor = BufferMonitor::default();
+}
+
+impl Plugin for InterpolationDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DiagnosticsStore>();
+        app.add_event::<InterpolationBufferWarning>();
+        let mut store = app.world.resource_mut::<DiagnosticsStore>();
+        for path in [Self::BUFFER_DEPTH, Self::BUFFER_AGE] {
+            store.add(Diagnostic::new(path).with_max_history_length(Self::DIAGNOSTIC_HISTORY_LEN));
+        }
+        app.add_systems(PostUpdate, interpolation_diagnostics_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(depth: usize, age_ms: u64, overrun_ms: u64) -> InterpolationBufferStats {
+        let mut stats = InterpolationBufferStats::default();
+        stats.record(
+            depth,
+            Duration::from_millis(age_ms),
+            Duration::from_millis(overrun_ms),
+        );
+        stats
+    }
+
+    #[test]
+    fn test_buffer_stats_use_most_starved_component() {
+        let mut stats = stats(3, 80, 0);
+        stats.record(0, Duration::ZERO, Duration::from_millis(20));
+        assert!(stats.is_underflowing());
+        assert_eq!(stats.overrun, Duration::from_millis(20));
+        stats.reset();
+        assert!(!stats.recorded);
+    }
+
+    #[test]
+    fn test_buffer_monitor() {
+        let delay = Duration::from_millis(100);
+
+        // the buffers are healthy
+        let mut monitor = BufferMonitor::default();
+        for _ in 0..10 {
+            monitor.record([stats(2, 60, 0), stats(3, 90, 0)].iter(), delay);
+        }
+        monitor.record([stats(0, 0, 10)].iter(), delay);
+        assert_eq!(monitor.evaluate(delay), None);
+
+        // the buffers underflow in half of the frames
+        let mut monitor = BufferMonitor::default();
+        for i in 0..10 {
+            let overrun = if i % 2 == 0 { 30 } else { 10 };
+            monitor.record([stats(2, 60, 0), stats(0, 0, overrun)].iter(), delay);
+            monitor.record([stats(2, 60, 0)].iter(), delay);
+        }
+        assert_eq!(
+            monitor.evaluate(delay),
+            Some(InterpolationBufferWarning {
+                issue: InterpolationBufferIssue::Underflow,
+                delay,
+                suggested_delay: Duration::from_millis(130),
+            })
+        );
+
+        // the buffers always hold more than the delay
+        let mut monitor = BufferMonitor::default();
+        for _ in 0..10 {
+            monitor.record([stats(5, 150, 0), stats(6, 180, 0)].iter(), delay);
+        }
+        assert_eq!(
+            monitor.evaluate(delay),
+            Some(InterpolationBufferWarning {
+                issue: InterpolationBufferIssue::Overflow,
+                delay,
+                suggested_delay: Duration::from_millis(50),
+            })
+        );
+    }
+}
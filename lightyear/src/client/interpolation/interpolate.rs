@@ -60,23 +60,21 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
         Option<&mut C>,
         &mut InterpolateStatus<C>,
         &mut ConfirmedHistory<C>,
+        Option<&Interpolated>,
     )>,
 ) {
     let kind = std::any::type_name::<C>();
 
-    // how many ticks between each interpolation (add 1 to roughly take the ceil)
-    let send_interval_delta_tick = (SEND_INTERVAL_TICK_FACTOR
-        * config.shared.server_send_interval.as_secs_f32()
-        / config.shared.tick.tick_duration.as_secs_f32()) as i16
-        + 1;
-
     let current_interpolate_tick = connection
         .sync_manager
         .interpolation_tick(tick_manager.as_ref());
     let current_interpolate_overstep = connection
         .sync_manager
         .interpolation_overstep(tick_manager.as_ref());
-    for (entity, component, mut status, mut history) in query.iter_mut() {
+    for (entity, component, mut status, mut history, interpolated) in query.iter_mut() {
+        // how many ticks between each interpolation
+        let send_interval_delta_tick =
+            send_interval_delta_tick(&config, Some(connection.as_ref()), interpolated);
         let mut start = status.start.take();
         let mut end = status.end.take();
 
@@ -215,18 +213,17 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
 pub(crate) fn insert_interpolated_component<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
     config: Res<ClientConfig>,
+    connection: Option<Res<ConnectionManager>>,
     tick_manager: Res<TickManager>,
     mut commands: Commands,
-    mut query: Query<(Entity, &InterpolateStatus<C>), Without<C>>,
+    mut query: Query<(Entity, &InterpolateStatus<C>, Option<&Interpolated>), Without<C>>,
 ) {
     let tick = tick_manager.tick();
-    // how many ticks between each interpolation update (add 1 to roughly take the ceil)
-    // TODO: use something more precise, with the interpolation overstep?
-    let send_interval_delta_tick = (SEND_INTERVAL_TICK_FACTOR
-        * config.shared.server_send_interval.as_secs_f32()
-        / config.shared.tick.tick_duration.as_secs_f32()) as i16
-        + 1;
-    for (entity, status) in query.iter_mut() {
+    for (entity, status, interpolated) in query.iter_mut() {
+        // how many ticks between each interpolation update
+        // TODO: use something more precise, with the interpolation overstep?
+        let send_interval_delta_tick =
+            send_interval_delta_tick(&config, connection.as_deref(), interpolated);
         trace!("checking if we need to insert the component on the Interpolated entity");
         let mut entity_commands = commands.entity(entity);
         // NOTE: it is possible that we reach start_tick when end_tick is not set
@@ -251,6 +248,28 @@ pub(crate) fn insert_interpolated_component<C: SyncComponent>(
     }
 }
 
+/// Number of ticks between two updates of the replication group of the interpolated entity
+/// (add 1 to roughly take the ceil)
+fn send_interval_delta_tick(
+    config: &ClientConfig,
+    connection: Option<&ConnectionManager>,
+    interpolated: Option<&Interpolated>,
+) -> i16 {
+    let send_interval = connection.map_or(config.shared.server_send_interval, |connection| {
+        let group_id = interpolated.and_then(|interpolated| {
+            connection
+                .replication_receiver
+                .get_replication_group_id(interpolated.confirmed_entity)
+        });
+        connection
+            .sync_manager
+            .group_send_interval(group_id, config.shared.server_send_interval)
+    });
+    (SEND_INTERVAL_TICK_FACTOR * send_interval.as_secs_f32()
+        / config.shared.tick.tick_duration.as_secs_f32()) as i16
+        + 1
+}
+
 /// Update the component value on the Interpolate entity
 pub(crate) fn interpolate<C: Component + Clone>(
     component_registry: Res<ComponentRegistry>,
@@ -1,16 +1,16 @@
 use std::ops::Deref;
 
 use bevy::prelude::{
-    Commands, Component, DetectChanges, Entity, Query, Ref, Res, ResMut, With, Without,
+    Commands, Component, DetectChanges, Entity, Has, Query, Ref, Res, ResMut, With, Without,
 };
 use tracing::{debug, trace};
 
 use crate::client::components::Confirmed;
-use crate::client::components::{ComponentSyncMode, SyncComponent};
+use crate::client::components::{ComponentSyncMode, SyncComponent, SyncEntityMapper};
 use crate::client::connection::ConnectionManager;
 use crate::client::interpolation::interpolate::InterpolateStatus;
-use crate::client::interpolation::resource::InterpolationManager;
 use crate::client::interpolation::Interpolated;
+use crate::client::transition::PreviousSyncEntity;
 use crate::prelude::{ComponentRegistry, TickManager};
 use crate::shared::tick_manager::Tick;
 use crate::utils::ready_buffer::ReadyBuffer;
@@ -79,11 +79,14 @@ impl<C: SyncComponent> ConfirmedHistory<C> {
 /// that we want to interpolate between entities that have the `Confirmed` component
 pub(crate) fn add_component_history<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
-    manager: Res<InterpolationManager>,
+    entity_mapper: SyncEntityMapper,
     tick_manager: Res<TickManager>,
     mut commands: Commands,
     connection: Res<ConnectionManager>,
-    interpolated_entities: Query<Entity, (Without<ConfirmedHistory<C>>, With<Interpolated>)>,
+    interpolated_entities: Query<
+        (Entity, Has<PreviousSyncEntity>),
+        (Without<ConfirmedHistory<C>>, With<Interpolated>),
+    >,
     confirmed_entities: Query<(&Confirmed, Ref<C>)>,
 ) {
     let current_tick = connection
@@ -94,8 +97,9 @@ pub(crate) fn add_component_history<C: SyncComponent>(
         .interpolation_overstep(tick_manager.as_ref());
     for (confirmed_entity, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed_entity.interpolated {
-            if let Ok(interpolated_entity) = interpolated_entities.get(p) {
-                if confirmed_component.is_added() {
+            if let Ok((interpolated_entity, is_switch)) = interpolated_entities.get(p) {
+                // the interpolated entity could also replace a predicted entity
+                if confirmed_component.is_added() || is_switch {
                     // safety: we know the entity exists
                     let mut interpolated_entity_mut =
                         commands.get_entity(interpolated_entity).unwrap();
@@ -103,10 +107,14 @@ pub(crate) fn add_component_history<C: SyncComponent>(
                     let history = ConfirmedHistory::<C>::new();
                     // map any entities from confirmed to interpolated
                     let mut new_component = confirmed_component.deref().clone();
-                    manager.map_entities(&mut new_component, component_registry.as_ref());
+                    entity_mapper.map_to_interpolated(&mut new_component);
                     match component_registry.interpolation_mode::<C>() {
                         ComponentSyncMode::Full => {
                             trace!(?interpolated_entity, tick=?tick_manager.tick(),  "spawn interpolation history");
+                            if is_switch {
+                                // the entity is already visible, so we keep showing it until we can interpolate
+                                interpolated_entity_mut.insert(new_component.clone());
+                            }
                             interpolated_entity_mut.insert((
                                 // NOTE: we probably do NOT want to insert the component right away, instead we want to wait until we have two updates
                                 //  we can interpolate between. Otherwise it will look jarring if send_interval is low. (because the entity will
@@ -136,7 +144,7 @@ pub(crate) fn add_component_history<C: SyncComponent>(
 /// When we receive a server update for an interpolated component, we need to store it in the confirmed history,
 pub(crate) fn apply_confirmed_update_mode_full<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
-    manager: Res<InterpolationManager>,
+    entity_mapper: SyncEntityMapper,
     mut interpolated_entities: Query<
         &mut ConfirmedHistory<C>,
         (With<Interpolated>, Without<Confirmed>),
@@ -162,7 +170,7 @@ pub(crate) fn apply_confirmed_update_mode_full<C: SyncComponent>(
 
                     // map any entities from confirmed to predicted
                     let mut component = confirmed_component.deref().clone();
-                    manager.map_entities(&mut component, component_registry.as_ref());
+                    entity_mapper.map_to_interpolated(&mut component);
                     trace!(?kind, tick = ?tick, "adding confirmed update to history");
                     // update the history at the value that the entity currently is
                     history.buffer.add_item(tick, component);
@@ -177,7 +185,7 @@ pub(crate) fn apply_confirmed_update_mode_full<C: SyncComponent>(
 /// When we receive a server update for a simple component, we just update the entity directly
 pub(crate) fn apply_confirmed_update_mode_simple<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
-    manager: Res<InterpolationManager>,
+    entity_mapper: SyncEntityMapper,
     mut interpolated_entities: Query<&mut C, (With<Interpolated>, Without<Confirmed>)>,
     confirmed_entities: Query<(Entity, &Confirmed, Ref<C>)>,
 ) {
@@ -188,7 +196,7 @@ pub(crate) fn apply_confirmed_update_mode_simple<C: SyncComponent>(
                     // for sync-components, we just match the confirmed component
                     // map any entities from confirmed to interpolated first
                     let mut component = confirmed_component.deref().clone();
-                    manager.map_entities(&mut component, component_registry.as_ref());
+                    entity_mapper.map_to_interpolated(&mut component);
                     *interpolated_component = component;
                 }
             }
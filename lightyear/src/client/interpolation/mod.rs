@@ -4,6 +4,7 @@ use std::ops::{Add, Mul};
 use bevy::prelude::{Added, Commands, Component, Entity, Query, Reflect, Res, ResMut};
 use tracing::trace;
 
+pub use crate::client::components::Interpolated;
 pub use interpolate::InterpolateStatus;
 pub use interpolation_history::ConfirmedHistory;
 pub use plugin::{add_interpolation_systems, add_prepare_interpolation_systems};
@@ -16,10 +17,11 @@ use crate::client::interpolation::resource::InterpolationManager;
 use crate::shared::replication::components::ShouldBeInterpolated;
 
 mod despawn;
+pub mod diagnostics;
 mod interpolate;
 pub mod interpolation_history;
 pub mod plugin;
-mod resource;
+pub(crate) mod resource;
 mod spawn;
 mod visual_interpolation;
 
@@ -34,14 +36,3 @@ where
         start * (1.0 - t) + other * t
     }
 }
-
-/// Marker component for an entity that is being interpolated by the client
-#[derive(Component, Debug, Reflect)]
-pub struct Interpolated {
-    // TODO: maybe here add an interpolation function?
-    pub confirmed_entity: Entity,
-    // TODO: add config about despawn behaviour here:
-    //  - despawn immediately all components
-    //  - leave the entity alive until the confirmed entity catches up to it and then it gets removed.
-    //    - or do this only for certain components (audio, animation, particles..) -> mode on PredictedComponent
-}
@@ -3,9 +3,13 @@ use std::marker::PhantomData;
 use bevy::prelude::*;
 use bevy::utils::Duration;
 
+use crate::client::background::is_in_foreground;
 use crate::client::components::{ComponentSyncMode, SyncComponent, SyncMetadata};
 use crate::client::config::ClientConfig;
 use crate::client::interpolation::despawn::{despawn_interpolated, removed_components};
+use crate::client::interpolation::diagnostics::{
+    reset_interpolation_buffer_stats, update_interpolation_buffer_stats,
+};
 use crate::client::interpolation::interpolate::{
     insert_interpolated_component, interpolate, update_interpolate_status,
 };
@@ -146,6 +150,9 @@ pub fn add_prepare_interpolation_systems<C: SyncComponent>(
                 (
                     apply_confirmed_update_mode_full::<C>,
                     update_interpolate_status::<C>.run_if(client_is_synced),
+                    update_interpolation_buffer_stats::<C>
+                        .after(reset_interpolation_buffer_stats)
+                        .run_if(client_is_synced),
                     // TODO: that means we could insert the component twice, here and then in interpolate...
                     //  need to optimize this
                     insert_interpolated_component::<C>,
@@ -203,12 +210,18 @@ impl Plugin for InterpolationPlugin {
             Update,
             InterpolationSet::All.run_if(should_run_interpolation),
         );
+        // the visual interpolation is paused while the client is in the background
+        app.configure_sets(
+            Update,
+            InterpolationSet::Interpolate.run_if(is_in_foreground),
+        );
         // SYSTEMS
         app.add_systems(
             Update,
             (
                 spawn_interpolated_entity.in_set(InterpolationSet::SpawnInterpolation),
                 despawn_interpolated.in_set(InterpolationSet::Despawn),
+                reset_interpolation_buffer_stats.in_set(InterpolationSet::PrepareInterpolation),
             ),
         );
     }
@@ -1,11 +1,15 @@
-use bevy::prelude::{Added, Commands, Entity, Query, Res, ResMut};
-use tracing::trace;
+use bevy::prelude::{Added, Commands, Entity, Query, Ref, Res, ResMut};
+use tracing::{debug, trace};
 
 use crate::client::components::Confirmed;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
+use crate::client::interpolation::diagnostics::InterpolationBufferStats;
 use crate::client::interpolation::resource::InterpolationManager;
 use crate::client::interpolation::Interpolated;
+use crate::client::prediction::resource::PredictionManager;
+use crate::client::prediction::Predicted;
+use crate::client::transition::{is_switch, PreviousSyncEntity};
 use crate::prelude::Tick;
 use crate::shared::replication::components::ShouldBeInterpolated;
 
@@ -14,11 +18,18 @@ pub fn spawn_interpolated_entity(
     config: Res<ClientConfig>,
     connection: Res<ConnectionManager>,
     mut manager: ResMut<InterpolationManager>,
+    mut prediction_manager: Option<ResMut<PredictionManager>>,
     mut commands: Commands,
+    predicted_entities: Query<Ref<Predicted>>,
     mut confirmed_entities: Query<(Entity, Option<&mut Confirmed>), Added<ShouldBeInterpolated>>,
 ) {
     for (confirmed_entity, confirmed) in confirmed_entities.iter_mut() {
-        let interpolated = commands.spawn(Interpolated { confirmed_entity }).id();
+        let interpolated = commands
+            .spawn((
+                Interpolated { confirmed_entity },
+                InterpolationBufferStats::default(),
+            ))
+            .id();
 
         // update the entity mapping
         manager
@@ -31,6 +42,28 @@ pub fn spawn_interpolated_entity(
         // safety: we know the entity exists
         let mut confirmed_entity_mut = commands.get_entity(confirmed_entity).unwrap();
         if let Some(mut confirmed) = confirmed {
+            // the entity was predicted and is now interpolated: the interpolated entity replaces the predicted entity
+            if let Some(predicted) = confirmed
+                .predicted
+                .filter(|p| is_switch(&predicted_entities, *p))
+            {
+                debug!(
+                    ?confirmed_entity,
+                    ?predicted,
+                    "Switching from prediction to interpolation"
+                );
+                confirmed.predicted = None;
+                if let Some(prediction_manager) = prediction_manager.as_mut() {
+                    prediction_manager
+                        .predicted_entity_map
+                        .get_mut()
+                        .confirmed_to_predicted
+                        .remove(&confirmed_entity);
+                }
+                commands
+                    .entity(interpolated)
+                    .insert(PreviousSyncEntity(predicted));
+            }
             confirmed.interpolated = Some(interpolated);
         } else {
             // get the confirmed tick for the entity
@@ -178,7 +178,7 @@ pub(crate) fn restore_from_visual_interpolation<C: SyncComponent>(
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use approx::assert_relative_eq;
     use bevy::prelude::*;
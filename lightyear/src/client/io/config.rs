@@ -1,7 +1,7 @@
 use crate::client::io::transport::{ClientTransportBuilder, ClientTransportBuilderEnum};
 use crate::client::io::{Io, IoContext};
 use crate::prelude::CompressionConfig;
-use crate::transport::config::SharedIoConfig;
+use crate::transport::config::{LivenessConfig, SharedIoConfig, SocketConfig};
 use crate::transport::dummy::DummyIo;
 use crate::transport::error::{Error, Result};
 use crate::transport::io::{BaseIo, IoStats};
@@ -12,10 +12,15 @@ use crate::transport::middleware::compression::zstd::compression::ZstdCompressor
 use crate::transport::middleware::compression::zstd::decompression::ZstdDecompressor;
 use crate::transport::middleware::conditioner::LinkConditioner;
 use crate::transport::middleware::{PacketReceiverWrapper, PacketSenderWrapper};
+#[cfg(feature = "websocket")]
+use crate::transport::proxy::ProxyConfig;
+use crate::transport::relay;
 #[cfg(not(target_family = "wasm"))]
 use crate::transport::udp::UdpSocketBuilder;
 #[cfg(feature = "websocket")]
 use crate::transport::websocket::client::WebSocketClientSocketBuilder;
+#[cfg(feature = "websocket")]
+use crate::transport::websocket::WebSocketClientTls;
 #[cfg(feature = "webtransport")]
 use crate::transport::webtransport::client::WebTransportClientSocketBuilder;
 use crate::transport::{BoxedReceiver, Transport, LOCAL_SOCKET};
@@ -41,7 +46,14 @@ pub enum ClientTransport {
     },
     /// Use [`WebSocket`](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket) as a transport
     #[cfg(feature = "websocket")]
-    WebSocketClient { server_addr: SocketAddr },
+    WebSocketClient {
+        server_addr: SocketAddr,
+        /// If provided, connect to the server with a secure WebSocket (`wss://`) instead of a plain `ws://` WebSocket
+        tls: Option<WebSocketClientTls>,
+        /// If provided, the connection is tunneled through a SOCKS5 or HTTP proxy.
+        /// This is ignored on wasm, where the browser's proxy settings are used
+        proxy: Option<ProxyConfig>,
+    },
     /// Use a crossbeam_channel as a transport. This is useful for testing.
     /// This is mostly for clients.
     LocalChannel {
@@ -53,11 +65,54 @@ pub enum ClientTransport {
 }
 
 impl ClientTransport {
-    pub(super) fn build(self) -> ClientTransportBuilderEnum {
+    /// Update the digest of the server certificate that is accepted by a WebTransport client
+    /// (for example after the server rotated its certificate).
+    ///
+    /// The new digest is used the next time the client connects. It only matters on wasm, where the
+    /// browser validates the server certificate against the digest; the native client does not validate
+    /// the server certificate.
+    ///
+    /// Lightyear does not send the new digest to the clients: the app has to get it from the server
+    /// itself (for example from the [`CertificateRotatedEvent`](crate::server::events::CertificateRotatedEvent),
+    /// sent in a message while the client is still connected, or served over HTTP with the connect token)
+    /// and call this function before reconnecting.
+    #[cfg(feature = "webtransport")]
+    pub fn set_certificate_digest(&mut self, digest: impl Into<String>) {
+        #[cfg(target_family = "wasm")]
+        if let ClientTransport::WebTransportClient {
+            certificate_digest, ..
+        } = self
+        {
+            *certificate_digest = digest.into().replace(':', "");
+        }
+        #[cfg(not(target_family = "wasm"))]
+        let _ = digest;
+    }
+
+    #[cfg_attr(target_family = "wasm", allow(unused_variables))]
+    /// Returns true if the transport can check the liveness of the connection itself
+    fn has_native_liveness(&self) -> bool {
+        match self {
+            #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+            ClientTransport::WebTransportClient { .. } => true,
+            #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
+            ClientTransport::WebSocketClient { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub(super) fn build(
+        self,
+        socket_config: SocketConfig,
+        liveness: Option<LivenessConfig>,
+    ) -> ClientTransportBuilderEnum {
         match self {
             #[cfg(not(target_family = "wasm"))]
             ClientTransport::UdpSocket(addr) => {
-                ClientTransportBuilderEnum::UdpSocket(UdpSocketBuilder { local_addr: addr })
+                ClientTransportBuilderEnum::UdpSocket(UdpSocketBuilder {
+                    local_addr: addr,
+                    config: socket_config,
+                })
             }
             #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
             ClientTransport::WebTransportClient {
@@ -66,6 +121,7 @@ impl ClientTransport {
             } => ClientTransportBuilderEnum::WebTransportClient(WebTransportClientSocketBuilder {
                 client_addr,
                 server_addr,
+                liveness,
             }),
             #[cfg(all(feature = "webtransport", target_family = "wasm"))]
             ClientTransport::WebTransportClient {
@@ -78,11 +134,16 @@ impl ClientTransport {
                 certificate_digest,
             }),
             #[cfg(feature = "websocket")]
-            ClientTransport::WebSocketClient { server_addr } => {
-                ClientTransportBuilderEnum::WebSocketClient(WebSocketClientSocketBuilder {
-                    server_addr,
-                })
-            }
+            ClientTransport::WebSocketClient {
+                server_addr,
+                tls,
+                proxy,
+            } => ClientTransportBuilderEnum::WebSocketClient(WebSocketClientSocketBuilder {
+                server_addr,
+                tls,
+                proxy,
+                liveness,
+            }),
             ClientTransport::LocalChannel { recv, send } => {
                 ClientTransportBuilderEnum::LocalChannel(LocalChannelBuilder { recv, send })
             }
@@ -106,10 +167,17 @@ impl Default for ClientTransport {
 
 impl SharedIoConfig<ClientTransport> {
     pub fn connect(self) -> Result<Io> {
-        let (transport, state, io_rx, network_tx) = self.transport.build().connect()?;
+        let native_liveness = self.liveness.is_some() && self.transport.has_native_liveness();
+        let (transport, state, io_rx, network_tx) =
+            self.transport.build(self.socket, self.liveness).connect()?;
         let local_addr = transport.local_addr();
+        let packet_sizing = transport.packet_sizing();
+        let (sender, receiver) = transport.split();
         #[allow(unused_mut)]
-        let (mut sender, receiver) = transport.split();
+        let (mut sender, receiver) = match self.relay {
+            Some(relay_config) => relay::wrap_client(relay_config, sender, receiver),
+            None => (sender, receiver),
+        };
         #[allow(unused_mut)]
         let mut receiver: BoxedReceiver = if let Some(conditioner_config) = self.conditioner {
             let conditioner = LinkConditioner::new(conditioner_config);
@@ -133,6 +201,8 @@ impl SharedIoConfig<ClientTransport> {
             receiver,
             state,
             stats: IoStats::default(),
+            packet_sizing,
+            native_liveness,
             context: IoContext {
                 event_sender: network_tx,
                 event_receiver: io_rx,
@@ -1,6 +1,6 @@
 //! Defines the [`ClientMessage`] enum used to send messages from the client to the server
 use anyhow::Context;
-use bevy::prelude::{App, EventWriter, IntoSystemConfigs, PreUpdate, Res, ResMut, Resource};
+use bevy::prelude::{App, Entity, EventWriter, IntoSystemConfigs, Res, ResMut, Resource};
 use bevy::utils::HashMap;
 use bytes::Bytes;
 use tracing::{error, info_span, trace};
@@ -8,17 +8,24 @@ use tracing::{error, info_span, trace};
 use bitcode::encoding::Fixed;
 use bitcode::{Decode, Encode};
 
+#[cfg(feature = "client")]
 use crate::client::connection::ConnectionManager;
+#[cfg(feature = "client")]
 use crate::client::events::MessageEvent;
+#[cfg(feature = "client")]
 use crate::client::networking::is_connected;
+use crate::packet::compression::SupportedCompressions;
 use crate::packet::message::SingleData;
 use crate::prelude::{ChannelDirection, ChannelKind, Message};
+use crate::protocol::channel::ChannelId;
+use crate::protocol::component::{ComponentNetId, ProtocolVersion};
 use crate::protocol::message::{MessageKind, MessageRegistry};
 use crate::protocol::registry::NetId;
 use crate::protocol::BitSerializable;
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::serialize::RawData;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::ping::message::{Ping, Pong, SyncMessage};
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::{ReplicationMessage, ReplicationMessageData};
@@ -38,9 +45,28 @@ pub(crate) enum ClientMessage {
     // the sync messages can be added to packets that have other messages
     Ping(Ping),
     Pong(Pong),
+    /// The client received the network id of a channel that was registered at runtime
+    ChannelRegistrationAck(ChannelId),
+    /// Version of the protocol used by the client, sent when the client connects
+    ProtocolVersion(ProtocolVersion),
+    /// Compression algorithms that the client can decompress, sent when the client connects
+    SupportedCompressions(SupportedCompressions),
+    /// Hash of the components, messages and channels registered on the client, sent when the client connects
+    ProtocolHash(u64),
+    /// Type paths of the dynamic components registered on the client, sent when the client connects and
+    /// whenever it registers a new dynamic component
+    DynamicComponents(Vec<String>),
+    /// The client received the network ids of its dynamic components, which can now be replicated to it
+    DynamicComponentIdsAck(Vec<ComponentNetId>),
+    /// Server entities for which the client received updates without having them, and that the server
+    /// must send again (see [`UnknownEntityUpdatePolicy::RequestResend`])
+    ///
+    /// [`UnknownEntityUpdatePolicy::RequestResend`]: crate::shared::replication::UnknownEntityUpdatePolicy::RequestResend
+    ResendEntities(#[bitcode(with_serde)] Vec<Entity>),
 }
 
 /// Read the message received from the server and emit the MessageEvent event
+#[cfg(feature = "client")]
 fn read_message<M: Message>(
     message_registry: Res<MessageRegistry>,
     mut connection: ResMut<ConnectionManager>,
@@ -75,10 +101,11 @@ fn read_message<M: Message>(
 }
 
 /// Register a message that can be sent from server to client
+#[cfg(feature = "client")]
 pub(crate) fn add_server_to_client_message<M: Message>(app: &mut App) {
     app.add_event::<MessageEvent<M>>();
     app.add_systems(
-        PreUpdate,
+        NetworkScheduleConfig::get(app).receive,
         read_message::<M>
             .in_set(InternalMainSet::<ClientMarker>::EmitEvents)
             .run_if(is_connected),
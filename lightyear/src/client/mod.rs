@@ -1,30 +1,63 @@
 /*! Modules related to the client
+
+Without the `client` feature, only the [`components`] and the messages that are needed to define the protocol are available.
 */
 
+#[cfg(feature = "client")]
+pub mod background;
+
 pub mod components;
 
+#[cfg(feature = "client")]
 pub mod config;
 
+#[cfg(feature = "client")]
+pub mod disable;
+
+#[cfg(feature = "client")]
 pub mod connection;
 
+#[cfg(feature = "client")]
 pub mod events;
 
+#[cfg(feature = "client")]
+pub mod fallback;
+
+#[cfg(feature = "client")]
 pub mod input;
 
+#[cfg(feature = "client")]
+pub mod inspector;
+
+#[cfg(feature = "client")]
 pub mod interpolation;
 
+#[cfg(feature = "client")]
 pub mod plugin;
 
+#[cfg(feature = "client")]
 pub mod prediction;
 
+#[cfg(feature = "client")]
+pub mod reconnect;
+
+#[cfg(feature = "client")]
 pub mod sync;
 
+#[cfg(feature = "client")]
+pub mod transition;
+
+#[cfg(feature = "client")]
 mod diagnostics;
+#[cfg(feature = "client")]
 mod easings;
 #[cfg_attr(docsrs, doc(cfg(feature = "leafwing")))]
-#[cfg(feature = "leafwing")]
+#[cfg(all(feature = "client", feature = "leafwing"))]
 pub mod input_leafwing;
+#[cfg(feature = "client")]
 pub(crate) mod io;
 pub(crate) mod message;
+#[cfg(feature = "client")]
 pub(crate) mod networking;
+#[cfg(feature = "client")]
 pub mod replication;
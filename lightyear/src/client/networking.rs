@@ -6,37 +6,46 @@ use async_channel::TryRecvError;
 use bevy::ecs::system::{Command, RunSystemOnce, SystemChangeTick, SystemParam, SystemState};
 use bevy::prelude::ResMut;
 use bevy::prelude::*;
-use tracing::{error, trace};
+use tracing::{debug, error, trace};
 
+use crate::channel::stats::{update_client_channel_stats, ChannelStats};
+use crate::client::background::BackgroundMode;
 use crate::client::components::Confirmed;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
 use crate::client::events::{ConnectEvent, DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent};
+use crate::client::fallback::{self, FallbackState};
 use crate::client::interpolation::Interpolated;
 use crate::client::io::ClientIoEvent;
+use crate::client::prediction::plugin::is_in_rollback;
+use crate::client::prediction::rollback::Rollback;
 use crate::client::prediction::Predicted;
+use crate::client::reconnect::{self, ReconnectState};
 use crate::client::sync::SyncSet;
-use crate::connection::client::{ClientConnection, NetClient, NetConfig};
-use crate::connection::server::{IoConfig, ServerConnections};
+use crate::connection::client::{ClientConnection, IoConfig, NetClient, NetConfig};
 use crate::prelude::{
-    ChannelRegistry, MainSet, MessageRegistry, SharedConfig, TickManager, TimeManager,
+    ChannelRegistry, FixedUpdateSet, MainSet, MessageRegistry, SharedConfig, TickManager,
+    TimeManager,
 };
 use crate::protocol::component::ComponentRegistry;
-use crate::server::clients::ControlledEntities;
-use crate::server::networking::is_started;
-use crate::shared::config::Mode;
+#[cfg(feature = "server")]
+use crate::server::clients::client_entity_bundle;
+use crate::shared::config::{Mode, NetworkScheduleConfig};
 use crate::shared::events::connection::{IterEntityDespawnEvent, IterEntitySpawnEvent};
 use crate::shared::replication::components::Replicated;
-use crate::shared::sets::{ClientMarker, InternalMainSet};
+use crate::shared::sets::{ClientMarker, InternalMainSet, InternalReplicationSet};
 use crate::shared::tick_manager::TickEvent;
 use crate::shared::time_manager::is_client_ready_to_send;
+use crate::shared::timings::{add_set_timing, TimedPhase};
 use crate::transport::io::IoState;
+use crate::transport::Delivery;
 
 #[derive(Default)]
 pub(crate) struct ClientNetworkingPlugin;
 
 impl Plugin for ClientNetworkingPlugin {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         app
             // REFLECTION
             .register_type::<IoConfig>()
@@ -44,9 +53,11 @@ impl Plugin for ClientNetworkingPlugin {
             .init_state::<NetworkingState>()
             // RESOURCE
             .init_resource::<HostServerMetadata>()
+            .init_resource::<BackgroundMode>()
+            .init_resource::<ChannelStats>()
             // SYSTEM SETS
             .configure_sets(
-                PreUpdate,
+                schedules.receive,
                 (
                     InternalMainSet::<ClientMarker>::Receive.in_set(MainSet::Receive),
                     InternalMainSet::<ClientMarker>::EmitEvents.in_set(MainSet::EmitEvents),
@@ -57,7 +68,7 @@ impl Plugin for ClientNetworkingPlugin {
                     )),
             )
             .configure_sets(
-                PostUpdate,
+                schedules.send,
                 // run sync before send because some send systems need to know if the client is synced
                 // we don't send packets every frame, but on a timer instead
                 (
@@ -72,7 +83,7 @@ impl Plugin for ClientNetworkingPlugin {
                     .chain(),
             )
             .configure_sets(
-                PostUpdate,
+                schedules.send,
                 // send packets is when we call the actual `send` system, it's inside
                 // the `Send` system-sets so that we can run it less frequently than every frame
                 InternalMainSet::<ClientMarker>::SendPackets
@@ -81,7 +92,7 @@ impl Plugin for ClientNetworkingPlugin {
             )
             // SYSTEMS
             .add_systems(
-                PreUpdate,
+                schedules.receive,
                 listen_io_state
                     // we are running the listen_io_state in a different set because it can impact the run_condition for the
                     // Receive system set
@@ -91,35 +102,78 @@ impl Plugin for ClientNetworkingPlugin {
                     )),
             )
             .add_systems(
-                PreUpdate,
+                schedules.receive,
                 (listen_io_state, receive).in_set(InternalMainSet::<ClientMarker>::Receive),
             )
             .add_systems(
-                PostUpdate,
+                schedules.send,
                 (
                     send.in_set(InternalMainSet::<ClientMarker>::SendPackets),
+                    send_paced_packets
+                        .after(InternalMainSet::<ClientMarker>::Send)
+                        .run_if(not(
+                            SharedConfig::is_host_server_condition.or_else(is_disconnected)
+                        )),
                     // TODO: update virtual time with Time<Real> so we have more accurate time at Send time.
                     sync_update.in_set(SyncSet),
+                    update_client_channel_stats
+                        .after(InternalMainSet::<ClientMarker>::Send)
+                        .run_if(not(SharedConfig::is_host_server_condition).and_then(is_connected)),
+                ),
+            )
+            .add_systems(
+                FixedFirst,
+                update_send_tick.after(FixedUpdateSet::TickUpdate).run_if(
+                    not(SharedConfig::is_host_server_condition)
+                        .and_then(is_connected)
+                        .and_then(not(resource_exists::<Rollback>).or_else(not(is_in_rollback))),
                 ),
             );
 
+        // TIMINGS
+        add_set_timing::<ClientMarker>(
+            app,
+            schedules.receive,
+            InternalMainSet::<ClientMarker>::Receive,
+            TimedPhase::Receive,
+        );
+        add_set_timing::<ClientMarker>(
+            app,
+            schedules.receive,
+            InternalMainSet::<ClientMarker>::EmitEvents,
+            TimedPhase::EmitEvents,
+        );
+        add_set_timing::<ClientMarker>(
+            app,
+            schedules.send,
+            InternalReplicationSet::<ClientMarker>::All,
+            TimedPhase::ReplicationBuffer,
+        );
+        add_set_timing::<ClientMarker>(
+            app,
+            schedules.send,
+            InternalMainSet::<ClientMarker>::Send,
+            TimedPhase::Send,
+        );
+
         // STARTUP
-        // TODO: update all systems that need these to only run when needed, so that we don't have to create
-        //  a ConnectionManager or a NetConfig at startup
-        // Create a new `ClientConnection` and `ConnectionManager` at startup, so that systems
-        // that depend on these resources do not panic
-        app.world.run_system_once(rebuild_client_connection);
+        // The `ClientConnection` and `ConnectionManager` resources are only created when the client
+        // starts connecting (see `connect`); until then, all the systems that depend on them don't run.
 
         // CONNECTING
-        app.add_systems(OnEnter(NetworkingState::Connecting), connect);
+        app.init_resource::<FallbackState>();
+        app.init_resource::<ReconnectState>();
+        reconnect::add_events(app);
+        app.add_systems(
+            OnEnter(NetworkingState::Connecting),
+            (connect, fallback::on_connecting, reconnect::on_connecting),
+        );
+        app.add_systems(PreUpdate, reconnect::reconnect);
 
         // CONNECTED
         app.add_systems(
             OnEnter(NetworkingState::Connected),
-            (
-                on_connect,
-                on_connect_host_server.run_if(SharedConfig::is_host_server_condition),
-            ),
+            (on_connect, fallback::on_connected, reconnect::on_connected),
         );
 
         // DISCONNECTED
@@ -127,9 +181,24 @@ impl Plugin for ClientNetworkingPlugin {
             OnEnter(NetworkingState::Disconnected),
             (
                 on_disconnect,
-                on_disconnect_host_server.run_if(SharedConfig::is_host_server_condition),
+                fallback::on_disconnected,
+                reconnect::on_disconnected
+                    .after(fallback::on_disconnected)
+                    .before(on_disconnect),
             ),
         );
+
+        // HOST-SERVER
+        #[cfg(feature = "server")]
+        app.add_systems(
+            OnEnter(NetworkingState::Connected),
+            on_connect_host_server.run_if(SharedConfig::is_host_server_condition),
+        );
+        #[cfg(feature = "server")]
+        app.add_systems(
+            OnEnter(NetworkingState::Disconnected),
+            on_disconnect_host_server.run_if(SharedConfig::is_host_server_condition),
+        );
     }
 }
 
@@ -190,12 +259,20 @@ pub(crate) fn receive(world: &mut World) {
 
                                                         // RECV PACKETS: buffer packets into message managers
                                                         while let Some(packet) = netclient.recv() {
+                                                            #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+                                                            if let Some(capture) = connection.packet_capture.as_mut() {
+                                                                capture.capture_received(tick_manager.tick(), Some(netclient.id()), &packet);
+                                                            }
                                                             connection
                                                                 .recv_packet(packet, tick_manager.as_ref())
                                                                 .unwrap();
                                                         }
                                                         // RECEIVE: receive packets from message managers
-                                                        connection.receive(world, time_manager.as_ref(), tick_manager.as_ref());
+                                                        let background_config = world.resource::<ClientConfig>().background;
+                                                        let apply_replication = world
+                                                            .resource_mut::<BackgroundMode>()
+                                                            .should_apply_replication(time_manager.current_time(), &background_config);
+                                                        connection.receive(world, time_manager.as_ref(), tick_manager.as_ref(), apply_replication);
                                                     });
                                             });
                                         });
@@ -208,6 +285,13 @@ pub(crate) fn receive(world: &mut World) {
     trace!("client finished recv");
 }
 
+/// Tag the messages sent on the tick-buffered channels with the tick during which they are buffered
+fn update_send_tick(tick_manager: Res<TickManager>, mut connection: ResMut<ConnectionManager>) {
+    connection
+        .message_manager
+        .set_send_tick(tick_manager.tick());
+}
+
 pub(crate) fn send(
     mut netcode: ResMut<ClientConnection>,
     system_change_tick: SystemChangeTick,
@@ -223,9 +307,28 @@ pub(crate) fn send(
             error!("Error preparing replicate send: {}", e);
         });
     // SEND_PACKETS: send buffered packets to io
-    let packet_bytes = connection
+    let (packet_bytes, stream_packet_bytes) = connection
         .send_packets(time_manager.as_ref(), tick_manager.as_ref())
         .unwrap();
+    #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+    if let Some(capture) = connection.packet_capture.as_mut() {
+        for packet_byte in packet_bytes.iter().chain(stream_packet_bytes.iter()) {
+            capture.capture_sent(tick_manager.tick(), Some(netcode.id()), packet_byte);
+        }
+    }
+    // streams have their own flow control, so they are not paced
+    for packet_byte in stream_packet_bytes {
+        let _ = netcode
+            .send_with_delivery(packet_byte.as_slice(), Delivery::Stream)
+            .map_err(|e| {
+                error!("Error sending packet: {}", e);
+            });
+    }
+    // with pacing, the packets are sent progressively in `send_paced_packets`
+    if let Some(pacer) = connection.pacer.as_mut() {
+        pacer.push(packet_bytes);
+        return;
+    }
     for packet_byte in packet_bytes {
         let _ = netcode.send(packet_byte.as_slice()).map_err(|e| {
             error!("Error sending packet: {}", e);
@@ -236,12 +339,32 @@ pub(crate) fn send(
     // client.connection.clear();
 }
 
+/// Send the packets that were queued by the pacer, within the bandwidth limit.
+///
+/// This runs every frame (and not only when the client is ready to send) so that the packets
+/// are spread over time.
+pub(crate) fn send_paced_packets(
+    mut netcode: ResMut<ClientConnection>,
+    time_manager: Res<TimeManager>,
+    mut connection: ResMut<ConnectionManager>,
+) {
+    let Some(pacer) = connection.pacer.as_mut() else {
+        return;
+    };
+    for packet_byte in pacer.drain_ready(time_manager.delta()) {
+        let _ = netcode.send(packet_byte.as_slice()).map_err(|e| {
+            error!("Error sending packet: {}", e);
+        });
+    }
+}
+
 /// Update the sync manager.
 /// We run this at PostUpdate because:
 /// - client prediction time is computed from ticks, which haven't been updated yet at PreUpdate
 /// - server prediction time is computed from time, which has been updated via delta
 /// Also server sends the tick after FixedUpdate, so it makes sense that we would compare to the client tick after FixedUpdate
 /// So instead we update the sync manager at PostUpdate, after both ticks/time have been updated
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn sync_update(
     config: Res<ClientConfig>,
     netclient: Res<ClientConnection>,
@@ -249,6 +372,7 @@ pub(crate) fn sync_update(
     mut time_manager: ResMut<TimeManager>,
     mut tick_manager: ResMut<TickManager>,
     mut virtual_time: ResMut<Time<Virtual>>,
+    mut fixed_time: ResMut<Time<Fixed>>,
     mut tick_events: EventWriter<TickEvent>,
 ) {
     let connection = connection.into_inner();
@@ -272,6 +396,9 @@ pub(crate) fn sync_update(
         ) {
             tick_events.send(tick_event);
         }
+        if let Some(discard) = connection.sync_manager.take_overstep_discard() {
+            fixed_time.discard_overstep(discard);
+        }
         let relative_speed = time_manager.get_relative_speed();
         virtual_time.set_relative_speed(relative_speed);
     }
@@ -344,6 +471,7 @@ fn on_connect(mut connect_event_writer: EventWriter<ConnectEvent>, netcode: Res<
 }
 
 /// Same as on-connect, but only runs if we are in host-server mode
+#[cfg(feature = "server")]
 fn on_connect_host_server(
     mut commands: Commands,
     netcode: Res<ClientConnection>,
@@ -351,7 +479,7 @@ fn on_connect_host_server(
     mut server_connect_event_writer: ResMut<Events<crate::server::events::ConnectEvent>>,
 ) {
     // spawn an entity for the client
-    let client_entity = commands.spawn(ControlledEntities::default()).id();
+    let client_entity = commands.spawn(client_entity_bundle(netcode.id())).id();
     error!("send connect event to server");
     server_connect_event_writer.send(crate::server::events::ConnectEvent {
         client_id: netcode.id(),
@@ -363,17 +491,25 @@ fn on_connect_host_server(
 /// System that runs when we enter the Disconnected state
 /// Updates the DisconnectEvent events
 fn on_disconnect(
-    mut connection_manager: ResMut<ConnectionManager>,
+    connection_manager: Option<ResMut<ConnectionManager>>,
     mut disconnect_event_writer: EventWriter<DisconnectEvent>,
-    mut netcode: ResMut<ClientConnection>,
+    netcode: Option<ResMut<ClientConnection>>,
+    config: Res<ClientConfig>,
     mut commands: Commands,
     received_entities: Query<Entity, Or<(With<Replicated>, With<Predicted>, With<Interpolated>)>>,
 ) {
+    // the client never tried to connect (this runs when entering the initial `Disconnected` state)
+    let (Some(mut connection_manager), Some(mut netcode)) = (connection_manager, netcode) else {
+        return;
+    };
     info!("Running OnDisconnect schedule");
-    // despawn any entities that were spawned from replication
-    received_entities
-        .iter()
-        .for_each(|e| commands.entity(e).despawn_recursive());
+    // despawn any entities that were spawned from replication, unless they can be re-attached
+    // when we reconnect
+    if !config.replication.reattach_entities {
+        received_entities
+            .iter()
+            .for_each(|e| commands.entity(e).despawn_recursive());
+    }
 
     // set synced to false
     connection_manager.sync_manager.synced = false;
@@ -383,15 +519,20 @@ fn on_disconnect(
 
     // no need to update the io state, because we will recreate a new `ClientConnection`
     // for the next connection attempt
-    disconnect_event_writer.send(DisconnectEvent);
-    // TODO: remove ClientConnection and ConnectionManager resources?
+    disconnect_event_writer.send(DisconnectEvent {
+        reason: connection_manager.denied_reason.take(),
+    });
 }
 
+#[cfg(feature = "server")]
 fn on_disconnect_host_server(
-    netcode: Res<ClientConnection>,
+    netcode: Option<Res<ClientConnection>>,
     mut metadata: ResMut<HostServerMetadata>,
     mut server_disconnect_event_writer: ResMut<Events<crate::server::events::DisconnectEvent>>,
 ) {
+    let Some(netcode) = netcode else {
+        return;
+    };
     let client_id = netcode.id();
     if let Some(client_entity) = std::mem::take(&mut metadata.client_entity) {
         server_disconnect_event_writer.send(crate::server::events::DisconnectEvent {
@@ -436,12 +577,16 @@ pub(crate) fn is_disconnected(netclient: Option<Res<ClientConnection>>) -> bool
 
 /// This runs only when we enter the [`Connecting`](NetworkingState::Connecting) state.
 ///
-/// We rebuild the [`ClientConnection`] by using the latest [`ClientConfig`].
+/// We (re)build the [`ClientConnection`] by using the latest [`ClientConfig`].
+/// The resources are not created at startup, so that a client that never connects (for example while
+/// it's in a menu) doesn't hold a half-initialized connection.
 /// This has several benefits:
 /// - the client connection's internal time is up-to-date (otherwise it might not be, since we don't call `update` while disconnected)
 /// - we can take into account any changes to the client config
-fn rebuild_client_connection(world: &mut World) {
+fn rebuild_client_connection(world: &mut World) -> Result<()> {
     let client_config = world.resource::<ClientConfig>().clone();
+    // the packets must not be fragmented by the network to discover the path MTU
+    let dont_fragment = client_config.packet.mtu_discovery.is_some();
     // if client_config.shared.mode == Mode::HostServer {
     //     assert!(
     //         matches!(client_config.net, NetConfig::Local { .. }),
@@ -449,8 +594,32 @@ fn rebuild_client_connection(world: &mut World) {
     //     );
     // }
 
+    // keep the messages that were not acked on the previous connection, for the channels that persist their outbox
+    let outbox = world
+        .get_resource_mut::<ConnectionManager>()
+        .map(|mut previous| previous.message_manager.take_outbox())
+        .unwrap_or_default();
+    // keep the entity mapping of the previous connection, so that the entities can be re-attached
+    let previous_entity_map = client_config
+        .replication
+        .reattach_entities
+        .then(|| {
+            world
+                .get_resource_mut::<ConnectionManager>()
+                .map(|mut previous| {
+                    std::mem::take(&mut previous.replication_receiver.remote_entity_map)
+                })
+        })
+        .flatten();
+
+    // the server will announce the network ids of the dynamic components again
+    world
+        .resource_mut::<ComponentRegistry>()
+        .clear_remote_dynamic_net_ids();
+
     // insert a new connection manager (to reset sync, priority, message numbers, etc.)
-    let connection_manager = ConnectionManager::new(
+    #[allow(unused_mut)]
+    let mut connection_manager = ConnectionManager::new(
         world.resource::<ComponentRegistry>(),
         world.resource::<MessageRegistry>(),
         world.resource::<ChannelRegistry>(),
@@ -458,14 +627,44 @@ fn rebuild_client_connection(world: &mut World) {
         client_config.sync,
         client_config.ping,
         client_config.prediction.input_delay_ticks,
-    );
+        client_config.replication,
+    )?;
+    #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+    {
+        connection_manager.packet_capture = client_config.io_diagnostics.build_packet_capture();
+    }
+    #[cfg(not(all(feature = "pcap", not(target_family = "wasm"))))]
+    client_config.io_diagnostics.warn_if_packet_capture();
+    if !outbox.is_empty() {
+        debug!(
+            "Re-sending {} messages that were not acked before the disconnection",
+            outbox.len()
+        );
+    }
+    for (channel_kind, message, priority) in outbox {
+        let _ = connection_manager
+            .message_manager
+            .buffer_send_with_priority(message.into(), channel_kind, priority)
+            .inspect_err(|e| error!("Could not re-send message after reconnecting: {:?}", e));
+    }
+    if let Some(entity_map) = previous_entity_map {
+        connection_manager.replication_receiver.reattach(entity_map);
+    }
     world.insert_resource(connection_manager);
 
     // drop the previous client connection to make sure we release any resources before creating the new one
     world.remove_resource::<ClientConnection>();
     // insert the new client connection
-    let client_connection = client_config.net.build_client();
+    let mut net_config = client_config.net;
+    match &mut net_config {
+        NetConfig::Netcode { io, .. } | NetConfig::Punched { io, .. } => {
+            io.socket.dont_fragment = dont_fragment;
+        }
+        _ => {}
+    }
+    let client_connection = net_config.build_client()?;
     world.insert_resource(client_connection);
+    Ok(())
 }
 
 // TODO: the design where the user has to call world.connect_client() is better because the user can handle the Error however they want!
@@ -486,13 +685,27 @@ fn connect(world: &mut World) {
     // - this allows us to take into account any changes to the client config (when building a
     // new client connection and connection manager, which want to do because we need to reset
     // the internal time, sync, priority, message numbers, etc.)
-    rebuild_client_connection(world);
+    if let Err(e) = rebuild_client_connection(world) {
+        error!("Error rebuilding the client connection: {:?}", e);
+        return;
+    }
     let _ = world
         .resource_mut::<ClientConnection>()
         .connect()
         .inspect_err(|e| {
             error!("Error connecting client: {}", e);
         });
+    // the packet size depends on the transport used by the connection
+    let packet_sizing = world
+        .resource::<ClientConnection>()
+        .io()
+        .map(|io| io.packet_sizing)
+        .unwrap_or_default();
+    let mtu_config = world.resource::<ClientConfig>().packet.mtu_discovery;
+    world
+        .resource_mut::<ConnectionManager>()
+        .message_manager
+        .set_packet_sizing(packet_sizing, mtu_config);
     let config = world.resource::<ClientConfig>();
 
     if world.resource::<ClientConnection>().state() == NetworkingState::Connected
@@ -533,6 +746,16 @@ impl ClientCommands for Commands<'_, '_> {
     }
 
     fn disconnect_client(&mut self) {
+        // the disconnection is requested by the user: do not try the fallback connection configs
+        // or to reconnect
+        self.add(|world: &mut World| {
+            if let Some(mut fallback) = world.get_resource_mut::<FallbackState>() {
+                fallback.cancel();
+            }
+            if let Some(mut reconnect) = world.get_resource_mut::<ReconnectState>() {
+                reconnect.cancel();
+            }
+        });
         self.insert_resource(NextState::<NetworkingState>(Some(
             NetworkingState::Disconnected,
         )));
@@ -19,7 +19,7 @@ use crate::client::prediction::plugin::PredictionPlugin;
 use crate::client::replication::{
     receive::ClientReplicationReceivePlugin, send::ClientReplicationSendPlugin,
 };
-use crate::prelude::server::{ServerConfig, ServerPlugins};
+use crate::client::transition::SyncTransitionPlugin;
 use crate::shared::config::Mode;
 use crate::shared::plugin::SharedPlugin;
 
@@ -38,6 +38,7 @@ use super::config::ClientConfig;
 ///   disabled if you don't need client to server replication.
 /// - [`PredictionPlugin`]: Handles the client-prediction systems. This can be disabled if you don't need it.
 /// - [`InterpolationPlugin`]: Handles the interpolation systems. This can be disabled if you don't need it.
+/// - [`SyncTransitionPlugin`]: Handles entities that switch between prediction and interpolation.
 pub struct ClientPlugins {
     pub config: ClientConfig,
 }
@@ -64,6 +65,7 @@ impl PluginGroup for ClientPlugins {
             .add(ClientReplicationSendPlugin { tick_interval })
             .add(PredictionPlugin)
             .add(InterpolationPlugin::new(interpolation_config))
+            .add(SyncTransitionPlugin)
     }
 }
 
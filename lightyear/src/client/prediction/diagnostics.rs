@@ -0,0 +1,292 @@
+//! Statistics about the mispredictions of each predicted component
+//!
+//! Every time the client receives a server update for a predicted entity, the predicted value at that tick
+//! is compared with the confirmed value. Tracking how often (and by how much) each component diverges helps
+//! find which gameplay systems are not simulated identically on the client and the server.
+//!
+//! The number of rollbacks and the number of ticks that they re-simulate are also tracked, to help tune prediction.
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::diagnostic::{Diagnostic, DiagnosticMeasurement, DiagnosticPath, DiagnosticsStore};
+use bevy::prelude::{Component, Local, Real, Res, ResMut, Resource, Time};
+use bevy::utils::{HashMap, Instant};
+use parking_lot::RwLock;
+
+use crate::protocol::component::ComponentKind;
+
+/// Misprediction statistics for a single component
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ComponentPredictionStats {
+    /// Number of times the predicted value was compared with the confirmed value
+    pub checks: u64,
+    /// Number of times the predicted value did not match the confirmed value
+    pub mispredictions: u64,
+    /// Number of mispredictions whose magnitude was measured
+    pub measured_mispredictions: u64,
+    /// Sum of the magnitudes of the measured mispredictions
+    pub total_magnitude: f64,
+    /// Largest magnitude of a misprediction
+    pub max_magnitude: f32,
+}
+
+impl ComponentPredictionStats {
+    /// Fraction of the checks that resulted in a misprediction
+    pub fn misprediction_rate(&self) -> f64 {
+        if self.checks == 0 {
+            return 0.0;
+        }
+        self.mispredictions as f64 / self.checks as f64
+    }
+
+    /// Average magnitude of the mispredictions.
+    ///
+    /// Returns `None` if no misprediction metric was registered for the component
+    /// (see [`add_misprediction_metric`](crate::protocol::component::ComponentRegistration::add_misprediction_metric))
+    pub fn average_magnitude(&self) -> Option<f64> {
+        if self.measured_mispredictions == 0 {
+            return None;
+        }
+        Some(self.total_magnitude / self.measured_mispredictions as f64)
+    }
+
+    fn record(&mut self, mispredicted: bool, magnitude: Option<f32>) {
+        self.checks += 1;
+        if !mispredicted {
+            return;
+        }
+        self.mispredictions += 1;
+        if let Some(magnitude) = magnitude {
+            self.measured_mispredictions += 1;
+            self.total_magnitude += magnitude as f64;
+            self.max_magnitude = self.max_magnitude.max(magnitude);
+        }
+    }
+}
+
+/// Statistics about the rollbacks
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RollbackStats {
+    /// Number of rollbacks
+    pub rollbacks: u64,
+    /// Total number of ticks that were re-simulated
+    pub ticks_resimulated: u64,
+    /// Largest number of ticks re-simulated in a single rollback
+    pub max_depth: u16,
+}
+
+impl RollbackStats {
+    /// Average number of ticks re-simulated per rollback
+    pub fn average_depth(&self) -> f64 {
+        if self.rollbacks == 0 {
+            return 0.0;
+        }
+        self.ticks_resimulated as f64 / self.rollbacks as f64
+    }
+}
+
+/// Resource that contains the misprediction statistics of every predicted component
+#[derive(Resource, Debug, Default)]
+pub struct PredictionStats {
+    /// We use a RwLock because the rollback checks of the different components run in parallel
+    components: RwLock<HashMap<ComponentKind, (&'static str, ComponentPredictionStats)>>,
+    rollbacks: RwLock<RollbackStats>,
+}
+
+impl PredictionStats {
+    /// Get the statistics for the component `C`
+    pub fn get<C: Component>(&self) -> Option<ComponentPredictionStats> {
+        self.components
+            .read()
+            .get(&ComponentKind::of::<C>())
+            .map(|(_, stats)| *stats)
+    }
+
+    /// Get the statistics of all the components, with the name of the component
+    pub fn all(&self) -> Vec<(&'static str, ComponentPredictionStats)> {
+        let mut stats: Vec<_> = self.components.read().values().copied().collect();
+        stats.sort_by_key(|(name, _)| *name);
+        stats
+    }
+
+    /// Get the statistics about the rollbacks
+    pub fn rollbacks(&self) -> RollbackStats {
+        *self.rollbacks.read()
+    }
+
+    /// Reset all the statistics
+    pub fn reset(&self) {
+        self.components.write().clear();
+        *self.rollbacks.write() = RollbackStats::default();
+    }
+
+    /// Record that a rollback re-simulated `ticks` ticks
+    pub(crate) fn record_rollback(&self, ticks: u16) {
+        let mut stats = self.rollbacks.write();
+        stats.rollbacks += 1;
+        stats.ticks_resimulated += ticks as u64;
+        stats.max_depth = stats.max_depth.max(ticks);
+    }
+
+    /// Record the result of a comparison between the predicted and the confirmed value of `C`
+    pub(crate) fn record<C: Component>(&self, mispredicted: bool, magnitude: Option<f32>) {
+        self.components
+            .write()
+            .entry(ComponentKind::of::<C>())
+            .or_insert_with(|| (std::any::type_name::<C>(), Default::default()))
+            .1
+            .record(mispredicted, magnitude);
+    }
+}
+
+/// Publishes the [`PredictionStats`] as bevy diagnostics.
+///
+/// For each predicted component, the diagnostics `prediction/{component}/misprediction rate` and
+/// `prediction/{component}/average misprediction magnitude` are computed over the last frame.
+/// The diagnostics [`ROLLBACKS_PER_SECOND`](Self::ROLLBACKS_PER_SECOND) and
+/// [`AVERAGE_ROLLBACK_DEPTH`](Self::AVERAGE_ROLLBACK_DEPTH) track the rollbacks.
+pub struct PredictionDiagnosticsPlugin;
+
+impl PredictionDiagnosticsPlugin {
+    /// Number of rollbacks per second
+    pub const ROLLBACKS_PER_SECOND: DiagnosticPath =
+        DiagnosticPath::const_new("prediction/rollbacks per second");
+    /// Average number of ticks re-simulated by the rollbacks of the last frame
+    pub const AVERAGE_ROLLBACK_DEPTH: DiagnosticPath =
+        DiagnosticPath::const_new("prediction/average rollback depth");
+
+    /// Max diagnostic history length.
+    pub const DIAGNOSTIC_HISTORY_LEN: usize = 60;
+
+    /// Path of the diagnostic that contains the misprediction rate of a component
+    pub fn misprediction_rate_path(component: &str) -> DiagnosticPath {
+        DiagnosticPath::from_components(["prediction", component, "misprediction rate"])
+    }
+
+    /// Path of the diagnostic that contains the average misprediction magnitude of a component
+    pub fn average_magnitude_path(component: &str) -> DiagnosticPath {
+        DiagnosticPath::from_components([
+            "prediction",
+            component,
+            "average misprediction magnitude",
+        ])
+    }
+
+    fn add_measurement(store: &mut DiagnosticsStore, path: DiagnosticPath, value: f64) {
+        if store.get(&path).is_none() {
+            store.add(
+                Diagnostic::new(path.clone()).with_max_history_length(Self::DIAGNOSTIC_HISTORY_LEN),
+            );
+        }
+        if let Some(diagnostic) = store.get_mut(&path) {
+            diagnostic.add_measurement(DiagnosticMeasurement {
+                time: Instant::now(),
+                value,
+            });
+        }
+    }
+}
+
+fn prediction_diagnostics_system(
+    stats: Res<PredictionStats>,
+    mut store: ResMut<DiagnosticsStore>,
+    time: Res<Time<Real>>,
+    // statistics at the time of the previous measurement
+    mut previous: Local<HashMap<&'static str, ComponentPredictionStats>>,
+    mut previous_rollbacks: Local<RollbackStats>,
+) {
+    let rollbacks = stats.rollbacks();
+    let new_rollbacks = rollbacks
+        .rollbacks
+        .saturating_sub(previous_rollbacks.rollbacks);
+    let delta = time.delta_seconds_f64();
+    if delta > 0.0 {
+        PredictionDiagnosticsPlugin::add_measurement(
+            &mut store,
+            PredictionDiagnosticsPlugin::ROLLBACKS_PER_SECOND,
+            new_rollbacks as f64 / delta,
+        );
+    }
+    if new_rollbacks > 0 {
+        let ticks = rollbacks
+            .ticks_resimulated
+            .saturating_sub(previous_rollbacks.ticks_resimulated);
+        PredictionDiagnosticsPlugin::add_measurement(
+            &mut store,
+            PredictionDiagnosticsPlugin::AVERAGE_ROLLBACK_DEPTH,
+            ticks as f64 / new_rollbacks as f64,
+        );
+    }
+    *previous_rollbacks = rollbacks;
+
+    for (name, stats) in stats.all() {
+        let previous = previous.entry(name).or_default();
+        let checks = stats.checks.saturating_sub(previous.checks);
+        if checks > 0 {
+            let mispredictions = stats.mispredictions.saturating_sub(previous.mispredictions);
+            PredictionDiagnosticsPlugin::add_measurement(
+                &mut store,
+                PredictionDiagnosticsPlugin::misprediction_rate_path(name),
+                mispredictions as f64 / checks as f64,
+            );
+        }
+        let measured = stats
+            .measured_mispredictions
+            .saturating_sub(previous.measured_mispredictions);
+        if measured > 0 {
+            PredictionDiagnosticsPlugin::add_measurement(
+                &mut store,
+                PredictionDiagnosticsPlugin::average_magnitude_path(name),
+                (stats.total_magnitude - previous.total_magnitude) / measured as f64,
+            );
+        }
+        *previous = stats;
+    }
+}
+
+impl Plugin for PredictionDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DiagnosticsStore>();
+        app.init_resource::<Time<Real>>();
+        app.init_resource::<PredictionStats>();
+        app.add_systems(PostUpdate, prediction_diagnostics_system);
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::tests::protocol::{Component1, Component2};
+
+    #[test]
+    fn test_prediction_stats() {
+        let stats = PredictionStats::default();
+        stats.record::<Component1>(false, None);
+        stats.record::<Component1>(true, Some(1.0));
+        stats.record::<Component1>(true, Some(3.0));
+        stats.record::<Component1>(false, None);
+        stats.record::<Component2>(true, None);
+
+        let component_1 = stats.get::<Component1>().unwrap();
+        assert_eq!(component_1.checks, 4);
+        assert_eq!(component_1.mispredictions, 2);
+        assert_eq!(component_1.misprediction_rate(), 0.5);
+        assert_eq!(component_1.average_magnitude(), Some(2.0));
+        assert_eq!(component_1.max_magnitude, 3.0);
+
+        // no metric function for Component2
+        let component_2 = stats.get::<Component2>().unwrap();
+        assert_eq!(component_2.misprediction_rate(), 1.0);
+        assert_eq!(component_2.average_magnitude(), None);
+        assert_eq!(stats.all().len(), 2);
+
+        stats.record_rollback(2);
+        stats.record_rollback(4);
+        let rollbacks = stats.rollbacks();
+        assert_eq!(rollbacks.rollbacks, 2);
+        assert_eq!(rollbacks.max_depth, 4);
+        assert_eq!(rollbacks.average_depth(), 3.0);
+
+        stats.reset();
+        assert!(stats.get::<Component1>().is_none());
+        assert_eq!(stats.rollbacks(), RollbackStats::default());
+    }
+}
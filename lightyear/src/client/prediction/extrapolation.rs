@@ -0,0 +1,162 @@
+//! Prediction of the remote entities.
+//!
+//! By default the entities that are not controlled by the client (other players, NPCs) are interpolated, so they are
+//! displayed in the past. Some games (fighting, racing, etc.) need every entity to be on the predicted timeline;
+//! the server can predict them on all clients by setting [`SyncTarget::prediction`](crate::prelude::server::SyncTarget)
+//! to [`NetworkTarget::All`](crate::prelude::NetworkTarget::All).
+//!
+//! The client does not have the inputs of the remote entities, so they need to be predicted forward from their
+//! last confirmed state in a different way. [`AppExtrapolationExt::add_extrapolation`] registers a function that
+//! advances a component every tick using another replicated component (for example the position using the velocity).
+//! It is only applied to the predicted entities that don't have the [`Controlled`] marker, and it also runs during
+//! rollback so that the remote entities are re-simulated forward from the confirmed state.
+//!
+//! The extrapolation runs in `FixedPostUpdate`, in [`PredictionSet::Extrapolate`]: after the `FixedUpdate` systems
+//! of the tick (so it advances the state produced by the simulation of the tick), and before the prediction
+//! history is updated in [`PredictionSet::UpdateHistory`] (so the extrapolated value is the one stored for the tick).
+use std::time::Duration;
+
+use bevy::prelude::{
+    App, Component, FixedPostUpdate, IntoSystemConfigs, Query, Res, Resource, With, Without,
+};
+
+use tracing::warn;
+
+use crate::client::components::SyncComponent;
+use crate::client::config::ClientConfig;
+use crate::client::prediction::plugin::PredictionSet;
+use crate::client::prediction::rollback::SkipRollback;
+use crate::client::prediction::Predicted;
+use crate::prelude::TickManager;
+use crate::shared::replication::components::Controlled;
+
+/// Function that advances the component `C` by `delta` using the component `V`
+/// (for example `|position, velocity, delta| position.0 += velocity.0 * delta.as_secs_f32()`)
+pub type ExtrapolateFn<C, V> = fn(&mut C, &V, Duration);
+
+#[derive(Resource)]
+struct Extrapolation<C, V> {
+    extrapolate: ExtrapolateFn<C, V>,
+}
+
+pub trait AppExtrapolationExt {
+    /// Predict the component `C` of the remote predicted entities (without the [`Controlled`] marker) forward
+    /// every tick by using the `extrapolate` function and the value of the component `V`.
+    ///
+    /// The extrapolation only happens on the client: this must be called after adding the
+    /// [`ClientPlugins`](crate::client::plugin::ClientPlugins), and it does nothing (apart from
+    /// logging a warning) on an app without a [`ClientConfig`].
+    fn add_extrapolation<C: SyncComponent, V: Component>(
+        &mut self,
+        extrapolate: ExtrapolateFn<C, V>,
+    );
+}
+
+impl AppExtrapolationExt for App {
+    fn add_extrapolation<C: SyncComponent, V: Component>(
+        &mut self,
+        extrapolate: ExtrapolateFn<C, V>,
+    ) {
+        // the prediction only happens on the client
+        if self.world.get_resource::<ClientConfig>().is_none() {
+            warn!(
+                "add_extrapolation::<{}, {}> has no effect because the app has no ClientConfig. \
+                It must be called on the client app, after adding the ClientPlugins",
+                std::any::type_name::<C>(),
+                std::any::type_name::<V>()
+            );
+            return;
+        }
+        self.insert_resource(Extrapolation { extrapolate });
+        self.add_systems(
+            FixedPostUpdate,
+            extrapolate_remote_entities::<C, V>.in_set(PredictionSet::Extrapolate),
+        );
+    }
+}
+
+/// Advance the remote predicted entities by one tick
+#[allow(clippy::type_complexity)]
+fn extrapolate_remote_entities<C: SyncComponent, V: Component>(
+    extrapolation: Res<Extrapolation<C, V>>,
+    tick_manager: Res<TickManager>,
+    mut query: Query<(&mut C, &V), (With<Predicted>, Without<Controlled>, Without<SkipRollback>)>,
+) {
+    // use the tick duration instead of the `Time` resource, which is not the fixed time during rollback
+    let delta = tick_manager.config.tick_duration;
+    for (mut component, value) in query.iter_mut() {
+        (extrapolation.extrapolate)(&mut component, value, delta);
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::prelude::client::*;
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    #[derive(Component)]
+    struct Velocity(f32);
+
+    fn spawn_predicted(stepper: &mut BevyStepper) -> Entity {
+        let confirmed = stepper.client_app.world.spawn(Confirmed::default()).id();
+        let predicted = stepper
+            .client_app
+            .world
+            .spawn((
+                Predicted {
+                    confirmed_entity: Some(confirmed),
+                },
+                Component1(0.0),
+                Velocity(100.0),
+            ))
+            .id();
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .get_mut::<Confirmed>()
+            .unwrap()
+            .predicted = Some(predicted);
+        predicted
+    }
+
+    /// Test that the remote predicted entities are extrapolated every tick,
+    /// but not the entities controlled by the client
+    #[test]
+    fn test_extrapolation() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .add_extrapolation::<Component1, Velocity>(|component, velocity, delta| {
+                component.0 += velocity.0 * delta.as_secs_f32();
+            });
+        let remote = spawn_predicted(&mut stepper);
+        let controlled = spawn_predicted(&mut stepper);
+        stepper
+            .client_app
+            .world
+            .entity_mut(controlled)
+            .insert(Controlled);
+
+        // the tick duration is 10ms
+        stepper.frame_step();
+        stepper.frame_step();
+        let remote_value = stepper
+            .client_app
+            .world
+            .get::<Component1>(remote)
+            .unwrap()
+            .0;
+        assert!((remote_value - 2.0).abs() < 1e-4);
+        assert_eq!(
+            stepper.client_app.world.get::<Component1>(controlled),
+            Some(&Component1(0.0))
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Sync the hierarchy of the confirmed entities to the predicted entities
+use bevy::prelude::{BuildChildren, Commands, Parent, Query, Res, With};
+use tracing::trace;
+
+use crate::client::components::Confirmed;
+use crate::client::config::ClientConfig;
+use crate::client::prediction::Predicted;
+use crate::shared::replication::hierarchy::ParentSync;
+
+/// Returns true if the hierarchy of the confirmed entities should be synced to the predicted entities
+/// (see [`PredictionConfig::predict_hierarchy`](crate::client::prediction::plugin::PredictionConfig::predict_hierarchy))
+pub(crate) fn should_predict_hierarchy(config: Res<ClientConfig>) -> bool {
+    config.prediction.predict_hierarchy
+}
+
+/// Attach each predicted entity to the predicted entity of its confirmed parent.
+///
+/// If the confirmed parent is not predicted, the predicted child is not attached to any parent:
+/// the predicted entities are never attached to a confirmed entity.
+pub(crate) fn sync_predicted_hierarchy(
+    mut commands: Commands,
+    confirmed_query: Query<(&Confirmed, &ParentSync)>,
+    parent_query: Query<&Confirmed>,
+    predicted_query: Query<Option<&Parent>, With<Predicted>>,
+) {
+    for (confirmed, parent_sync) in confirmed_query.iter() {
+        let Some(predicted) = confirmed.predicted else {
+            continue;
+        };
+        let Ok(parent) = predicted_query.get(predicted) else {
+            continue;
+        };
+        let predicted_parent = parent_sync
+            .parent()
+            .and_then(|confirmed_parent| parent_query.get(confirmed_parent).ok())
+            .and_then(|confirmed_parent| confirmed_parent.predicted)
+            .filter(|predicted_parent| predicted_query.contains(*predicted_parent));
+        match predicted_parent {
+            Some(predicted_parent) => {
+                if parent.map_or(true, |parent| parent.get() != predicted_parent) {
+                    trace!(
+                        ?predicted,
+                        ?predicted_parent,
+                        "attach the predicted entity to its predicted parent"
+                    );
+                    commands.entity(predicted).set_parent(predicted_parent);
+                }
+            }
+            None => {
+                // only detach the entity from the parents that we attached it to
+                if parent.is_some_and(|parent| predicted_query.contains(parent.get())) {
+                    commands.entity(predicted).remove_parent();
+                }
+            }
+        }
+    }
+}
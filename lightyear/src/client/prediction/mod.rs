@@ -4,18 +4,17 @@ use std::fmt::Debug;
 
 pub(crate) mod correction;
 pub(crate) mod despawn;
+pub mod diagnostics;
+pub mod extrapolation;
+mod hierarchy;
 pub mod plugin;
 mod pre_prediction;
 pub mod predicted_history;
 pub mod prespawn;
 pub(crate) mod resource;
+pub mod resource_history;
 pub(crate) mod rollback;
 pub mod spawn;
+pub mod visual_correction;
 
-/// Marks an entity that is being predicted by the client
-#[derive(Component, Debug, Reflect)]
-pub struct Predicted {
-    // This is an option because we could spawn pre-predicted entities on the client that exist before we receive
-    // the corresponding confirmed entity
-    pub confirmed_entity: Option<Entity>,
-}
+pub use crate::client::components::Predicted;
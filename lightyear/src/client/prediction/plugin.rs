@@ -17,6 +17,7 @@ use crate::client::prediction::despawn::{
     despawn_confirmed, remove_component_for_despawn_predicted, remove_despawn_marker,
     restore_components_if_despawn_rolled_back, PredictionDespawnMarker,
 };
+use crate::client::prediction::diagnostics::PredictionStats;
 use crate::client::prediction::predicted_history::{
     add_prespawned_component_history, update_prediction_history,
 };
@@ -29,17 +30,19 @@ use crate::client::sync::client_is_synced;
 use crate::connection::client::{ClientConnection, NetClient};
 use crate::prelude::{PreSpawnedPlayerObject, SharedConfig};
 use crate::shared::sets::{ClientMarker, InternalMainSet};
+use crate::shared::timings::{add_set_timing, TimedPhase};
 
+use super::hierarchy::{should_predict_hierarchy, sync_predicted_hierarchy};
 use super::pre_prediction::{PrePredictionPlugin, PrePredictionSet};
 use super::predicted_history::{add_component_history, apply_confirmed_update};
 use super::rollback::{
     check_rollback, increment_rollback_tick, prepare_rollback, prepare_rollback_prespawn,
-    run_rollback, Rollback, RollbackState,
+    run_rollback, Rollback, RollbackEnd, RollbackGroup, RollbackStart, RollbackState, SkipRollback,
 };
 use super::spawn::spawn_predicted_entity;
 
 /// Configuration to specify how the prediction plugin should behave
-#[derive(Debug, Clone, Copy, Default, Reflect)]
+#[derive(Debug, Clone, Copy, Reflect)]
 pub struct PredictionConfig {
     /// If true, we always rollback whenever we receive a server update, instead of checking
     /// ff the confirmed state matches the predicted state history
@@ -54,6 +57,30 @@ pub struct PredictionConfig {
     /// (i.e. if the client is 10 ticks head and correction_ticks is 1.0, then the correction will be done over 10 ticks)
     // Number of ticks it will take to visually update the Predicted state to the new Corrected state
     pub correction_ticks_factor: f32,
+    /// Maximum number of ticks that we can roll back.
+    ///
+    /// The histories that are not bounded by the confirmed state (for example the history of the resources
+    /// registered with [`add_resource_rollback`](crate::client::prediction::resource_history::AppResourceRollbackExt::add_resource_rollback))
+    /// only keep the values of the last `max_rollback_ticks` ticks.
+    pub max_rollback_ticks: u16,
+    /// If true, the predicted entities are attached to the predicted entity of their confirmed parent
+    /// (replicated with [`ParentSync`](crate::shared::replication::hierarchy::ParentSync)), so that the
+    /// predicted children are simulated and rolled back along with their predicted parent.
+    ///
+    /// The predicted entities whose confirmed parent is not predicted are not attached to any parent.
+    pub predict_hierarchy: bool,
+}
+
+impl Default for PredictionConfig {
+    fn default() -> Self {
+        Self {
+            always_rollback: false,
+            input_delay_ticks: 0,
+            correction_ticks_factor: 0.0,
+            max_rollback_ticks: 100,
+            predict_hierarchy: false,
+        }
+    }
 }
 
 impl PredictionConfig {
@@ -73,6 +100,18 @@ impl PredictionConfig {
         self.correction_ticks_factor = factor;
         self
     }
+
+    /// Update the maximum number of ticks that we can roll back
+    pub fn with_max_rollback_ticks(mut self, ticks: u16) -> Self {
+        self.max_rollback_ticks = ticks;
+        self
+    }
+
+    /// Attach the predicted entities to the predicted entity of their confirmed parent
+    pub fn with_predict_hierarchy(mut self, predict_hierarchy: bool) -> Self {
+        self.predict_hierarchy = predict_hierarchy;
+        self
+    }
 }
 
 /// Plugin that enables client-side prediction
@@ -99,6 +138,8 @@ pub enum PredictionSet {
     // NOTE: no need to add RollbackFlush because running a schedule (which we do for rollback) will flush all commands at the end of each run
 
     // FixedPostUpdate Sets
+    /// Predict the remote entities forward (see [`AppExtrapolationExt`](crate::client::prediction::extrapolation::AppExtrapolationExt))
+    Extrapolate,
     /// Increment the rollback tick after the main fixed-update physics loop has run
     IncrementRollbackTick,
     /// Set to deal with predicted/confirmed entities getting despawned
@@ -110,6 +151,12 @@ pub enum PredictionSet {
     // PostUpdate Sets
     /// Visually interpolate the predicted components to the corrected state
     VisualCorrection,
+    /// Add the [`VisualCorrection`](crate::client::prediction::visual_correction::VisualCorrection) offsets
+    /// to the `Transform`, right before the transforms are propagated
+    ApplyVisualCorrection,
+    /// Remove the [`VisualCorrection`](crate::client::prediction::visual_correction::VisualCorrection) offsets
+    /// from the `Transform`, right after the transforms are propagated
+    RemoveVisualCorrection,
 
     /// General set encompassing all other system sets
     All,
@@ -204,12 +251,18 @@ impl Plugin for PredictionPlugin {
             .register_type::<PreSpawnedPlayerObject>()
             .register_type::<Rollback>()
             .register_type::<RollbackState>()
+            .register_type::<RollbackGroup>()
+            .register_type::<SkipRollback>()
             .register_type::<PredictionDespawnMarker>()
             .register_type::<PredictionConfig>();
 
         // RESOURCES
         app.init_resource::<PredictionManager>();
         app.insert_resource(Rollback::new(RollbackState::Default));
+        app.init_resource::<PredictionStats>();
+
+        // EVENTS
+        app.add_event::<RollbackStart>().add_event::<RollbackEnd>();
 
         // PreUpdate systems:
         // 1. Receive confirmed entities, add Confirmed and Predicted components
@@ -252,10 +305,20 @@ impl Plugin for PredictionPlugin {
                     despawn_confirmed,
                 )
                     .in_set(PredictionSet::SpawnPrediction),
+                sync_predicted_hierarchy
+                    .run_if(should_predict_hierarchy)
+                    .in_set(PredictionSet::SpawnHistory),
                 run_rollback.in_set(PredictionSet::Rollback),
             ),
         );
 
+        add_set_timing::<ClientMarker>(
+            app,
+            PreUpdate,
+            PredictionSet::Rollback,
+            TimedPhase::Rollback,
+        );
+
         // FixedUpdate systems
         // 1. Update client tick (don't run in rollback)
         // 2. Run main physics/game fixed-update loop
@@ -264,6 +327,7 @@ impl Plugin for PredictionPlugin {
         app.configure_sets(
             FixedPostUpdate,
             (
+                PredictionSet::Extrapolate,
                 PredictionSet::EntityDespawn,
                 // for prespawned entities that could be spawned during FixedUpdate, we want to add the history
                 // right away to avoid rollbacks
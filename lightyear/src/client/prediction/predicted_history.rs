@@ -3,15 +3,17 @@
 use std::ops::Deref;
 
 use bevy::prelude::{
-    Commands, Component, DetectChanges, Entity, Or, Query, Ref, RemovedComponents, Res, ResMut,
-    With, Without,
+    Commands, Component, DetectChanges, Entity, Has, Or, Query, Ref, RemovedComponents, Res,
+    ResMut, With, Without,
 };
 use tracing::{debug, error, info, trace};
 
-use crate::client::components::{ComponentSyncMode, Confirmed, SyncComponent, SyncMetadata};
-use crate::client::prediction::resource::PredictionManager;
-use crate::client::prediction::rollback::{Rollback, RollbackState};
+use crate::client::components::{
+    ComponentSyncMode, Confirmed, SyncComponent, SyncEntityMapper, SyncMetadata,
+};
+use crate::client::prediction::rollback::{Rollback, RollbackState, SkipRollback};
 use crate::client::prediction::Predicted;
+use crate::client::transition::PreviousSyncEntity;
 use crate::prelude::{ComponentRegistry, PreSpawnedPlayerObject, ShouldBePredicted, TickManager};
 use crate::shared::tick_manager::Tick;
 use crate::utils::ready_buffer::ReadyBuffer;
@@ -95,11 +97,11 @@ impl<C: SyncComponent> PredictionHistory<C> {
 #[allow(clippy::type_complexity)]
 pub(crate) fn add_component_history<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
-    manager: Res<PredictionManager>,
+    entity_mapper: SyncEntityMapper,
     mut commands: Commands,
     tick_manager: Res<TickManager>,
     predicted_entities: Query<
-        (Entity, Option<Ref<C>>),
+        (Entity, Option<Ref<C>>, Has<PreviousSyncEntity>),
         (
             Without<PredictionHistory<C>>,
             // for all types of predicted entities, we want to add the component history to enable them to be rolled-back
@@ -112,7 +114,9 @@ pub(crate) fn add_component_history<C: SyncComponent>(
     let tick = tick_manager.tick();
     for (confirmed_entity, confirmed, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed.predicted {
-            if let Ok((predicted_entity, predicted_component)) = predicted_entities.get(p) {
+            if let Ok((predicted_entity, predicted_component, is_switch)) =
+                predicted_entities.get(p)
+            {
                 // if component got added on predicted side, add history
                 add_history::<C>(
                     component_registry.as_ref(),
@@ -122,25 +126,25 @@ pub(crate) fn add_component_history<C: SyncComponent>(
                     &mut commands,
                 );
 
-                // if component got added on confirmed side
+                // if component got added on confirmed side (or if the predicted entity replaces an interpolated entity)
                 // - full: sync component and add history
                 // - simple/once: sync component
                 if let Some(confirmed_component) = confirmed_component {
-                    if confirmed_component.is_added() {
+                    if confirmed_component.is_added() || is_switch {
                         trace!(?kind, "Component added on confirmed side");
                         // safety: we know the entity exists
                         let mut predicted_entity_mut =
                             commands.get_entity(predicted_entity).unwrap();
                         // map any entities from confirmed to predicted
                         let mut new_component = confirmed_component.deref().clone();
-                        manager.map_entities(&mut new_component, component_registry.as_ref());
+                        entity_mapper.map_to_predicted(&mut new_component);
                         match component_registry.prediction_mode::<C>() {
                             ComponentSyncMode::Full => {
                                 // insert history, it will be quickly filled by a rollback (since it starts empty before the current client tick)
                                 // or will it? because the component just got spawned anyway..
                                 // TODO: then there's no need to add the component here, since it's going to get added during rollback anyway?
                                 let mut history = PredictionHistory::<C>::default();
-                                history.add_update(tick, confirmed_component.deref().clone());
+                                history.add_update(tick, new_component.clone());
                                 predicted_entity_mut.insert((new_component, history));
                             }
                             ComponentSyncMode::Simple => {
@@ -221,9 +225,10 @@ fn add_history<C: SyncComponent>(
 
 /// If ComponentSyncMode::Full, we store every update on the predicted entity in the PredictionHistory
 pub(crate) fn update_prediction_history<T: SyncComponent>(
-    mut query: Query<(Ref<T>, &mut PredictionHistory<T>)>,
+    // the entities that are not part of the current rollback keep their history
+    mut query: Query<(Ref<T>, &mut PredictionHistory<T>), Without<SkipRollback>>,
     mut removed_component: RemovedComponents<T>,
-    mut removed_entities: Query<&mut PredictionHistory<T>, Without<T>>,
+    mut removed_entities: Query<&mut PredictionHistory<T>, (Without<T>, Without<SkipRollback>)>,
     tick_manager: Res<TickManager>,
     rollback: Res<Rollback>,
 ) {
@@ -249,7 +254,7 @@ pub(crate) fn update_prediction_history<T: SyncComponent>(
 pub(crate) fn apply_confirmed_update<C: SyncComponent>(
     mut commands: Commands,
     component_registry: Res<ComponentRegistry>,
-    manager: Res<PredictionManager>,
+    entity_mapper: SyncEntityMapper,
     mut predicted_entities: Query<
         &mut C,
         (
@@ -272,7 +277,7 @@ pub(crate) fn apply_confirmed_update<C: SyncComponent>(
                     );
                     // map any entities from confirmed to predicted
                     let mut component = confirmed_component.deref().clone();
-                    manager.map_entities(&mut component, component_registry.as_ref());
+                    entity_mapper.map_to_predicted(&mut component);
                     *predicted_component = component;
                 }
             }
@@ -288,7 +293,7 @@ pub(crate) fn apply_confirmed_update<C: SyncComponent>(
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use super::*;
     use crate::tests::protocol::*;
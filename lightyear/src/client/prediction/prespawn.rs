@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, info, trace, warn};
 
 use crate::client::components::Confirmed;
+pub use crate::client::components::PreSpawnedPlayerObject;
 use crate::client::connection::ConnectionManager;
 use crate::client::events::ComponentInsertEvent;
 use crate::client::networking::{is_connected, NetworkingState};
@@ -18,15 +19,12 @@ use crate::client::prediction::Predicted;
 use crate::client::replication::send::ReplicateToServer;
 use crate::client::sync::client_is_synced;
 use crate::prelude::client::PredictionSet;
-use crate::prelude::server::ControlledBy;
 use crate::prelude::{
     ComponentRegistry, ParentSync, ReplicateHierarchy, Replicated, Replicating, ReplicationTarget,
     ShouldBePredicted, TargetEntity, Tick, TickManager, VisibilityMode,
 };
 use crate::protocol::component::ComponentKind;
-use crate::server::prediction::compute_hash;
-use crate::server::replication::send::SyncTarget;
-use crate::server::visibility::immediate::ReplicateVisibility;
+use crate::server::prediction::{compute_hash, ignored_components};
 use crate::shared::replication::components::DespawnTracker;
 use crate::shared::sets::{ClientMarker, InternalReplicationSet};
 
@@ -148,6 +146,8 @@ impl PreSpawnedPlayerObjectPlugin {
                         // NOTE: we cannot call hash() multiple times because the components in the archetype
                         //  might get iterated in any order!
                         //  Instead we will get the sorted list of types to hash first, sorted by type_id
+                        // ignore some book-keeping components
+                        let ignored_components = ignored_components();
                         let mut kinds_to_hash = entity_ref
                             .archetype()
                             .components()
@@ -155,23 +155,7 @@ impl PreSpawnedPlayerObjectPlugin {
                                 if let Some(type_id) =
                                     world.components().get_info(component_id).unwrap().type_id()
                                 {
-                                    // ignore some book-keeping components
-                                    if type_id != TypeId::of::<VisibilityMode>()
-                                        && type_id != TypeId::of::<ReplicationTarget>()
-                                        && type_id != TypeId::of::<SyncTarget>()
-                                        && type_id != TypeId::of::<ControlledBy>()
-                                        && type_id != TypeId::of::<Replicating>()
-                                        && type_id != TypeId::of::<Replicated>()
-                                        && type_id != TypeId::of::<ReplicateToServer>()
-                                        && type_id != TypeId::of::<ReplicateVisibility>()
-                                        && type_id != TypeId::of::<VisibilityMode>()
-                                        && type_id != TypeId::of::<TargetEntity>()
-                                        && type_id != TypeId::of::<ReplicateHierarchy>()
-                                        && type_id != TypeId::of::<PreSpawnedPlayerObject>()
-                                        && type_id != TypeId::of::<ShouldBePredicted>()
-                                        && type_id != TypeId::of::<DespawnTracker>()
-                                        && type_id != TypeId::of::<ParentSync>()
-                                    {
+                                    if !ignored_components.contains(&type_id) {
                                         return component_registry.kind_map.net_id(&ComponentKind::from(type_id)).copied();
                                     }
                                 }
@@ -368,19 +352,6 @@ impl PreSpawnedPlayerObjectPlugin {
     }
 }
 
-#[derive(
-    Component, Serialize, Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, Reflect,
-)]
-#[component(storage = "SparseSet")]
-pub struct PreSpawnedPlayerObject {
-    /// The hash that will identify the spawned entity
-    /// By default, if the hash is not set, it will be generated from the entity's archetype (list of components) and spawn tick
-    /// Otherwise you can manually set it to a value that will be the same on both the client and server
-    pub hash: Option<u64>,
-    //
-    // pub conflict_resolution: ConflictResolution,
-}
-
 // pub enum ClientNoMatchHandling {
 //     /// If we don't get any server-entity that matches this prespawned player object, then we despawn it on the client
 //     /// Once we are sure that we won't get any more server updates for that entity
@@ -466,7 +437,7 @@ pub struct PreSpawnedPlayerObject {
 //     },
 // }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use crate::client::prediction::predicted_history::{ComponentState, PredictionHistory};
     use bevy::prelude::Entity;
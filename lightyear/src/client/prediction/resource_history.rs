@@ -0,0 +1,370 @@
+//! Rollback of resources that are part of the predicted simulation.
+//!
+//! Some simulations (for example physics engines) store part of their state in a resource instead of in the components of the predicted entities. Such a resource is not replicated, but it still needs
+//! to be reset to its state at the rollback tick before the ticks are re-simulated.
+//! The same applies to resources that are read by the predicted systems, such as a deterministic RNG or a match clock:
+//! if they are not restored, the re-simulated ticks diverge from the original simulation.
+//!
+//! [`AppResourceRollbackExt::add_resource_rollback`] stores the history of the resource for every tick (including
+//! whether the resource existed), and restores it when a rollback is triggered.
+use std::collections::VecDeque;
+
+use bevy::prelude::{
+    App, Commands, DetectChanges, FixedPostUpdate, IntoSystemConfigs, PreUpdate, Query, Res,
+    ResMut, Resource,
+};
+use tracing::{debug, trace, warn};
+
+use crate::client::components::Confirmed;
+use crate::client::config::ClientConfig;
+use crate::client::prediction::plugin::PredictionSet;
+use crate::client::prediction::predicted_history::ComponentState;
+use crate::client::prediction::rollback::Rollback;
+use crate::prelude::{Tick, TickManager};
+
+/// Stores the value of the resource `R` at the ticks where it changed or got removed
+///
+/// (we don't use a [`ReadyBuffer`](crate::utils::ready_buffer::ReadyBuffer) because the resource
+/// does not need to implement `PartialEq`. The values are always added in increasing tick order)
+#[derive(Resource, Debug)]
+pub(crate) struct ResourceHistory<R> {
+    buffer: VecDeque<(Tick, ComponentState<R>)>,
+}
+
+impl<R> Default for ResourceHistory<R> {
+    fn default() -> Self {
+        Self {
+            buffer: VecDeque::new(),
+        }
+    }
+}
+
+impl<R: Clone> ResourceHistory<R> {
+    /// Add to the buffer the value of the resource at the given tick
+    pub(crate) fn add_update(&mut self, tick: Tick, resource: R) {
+        self.add_state(tick, ComponentState::Updated(resource));
+    }
+
+    /// Add to the buffer that the resource got removed at the given tick
+    pub(crate) fn add_remove(&mut self, tick: Tick) {
+        self.add_state(tick, ComponentState::Removed);
+    }
+
+    fn add_state(&mut self, tick: Tick, state: ComponentState<R>) {
+        // overwrite any value that is more recent or equal (for example when re-simulating a tick)
+        while self.buffer.back().is_some_and(|(t, _)| *t >= tick) {
+            self.buffer.pop_back();
+        }
+        self.buffer.push_back((tick, state));
+    }
+
+    /// Clear the history of values strictly older than the specified tick,
+    /// and return the most recent value that is older or equal to the specified tick.
+    /// NOTE: That value is kept in the buffer, because the history only contains
+    /// the ticks where the resource changed
+    pub(crate) fn pop_until_tick(&mut self, tick: Tick) -> Option<ComponentState<R>> {
+        let mut value = None;
+        while self.buffer.front().is_some_and(|(t, _)| *t <= tick) {
+            value = self.buffer.pop_front();
+        }
+        value.map(|(t, state)| {
+            self.buffer.push_front((t, state.clone()));
+            state
+        })
+    }
+
+    /// Remove all the values that are strictly more recent than the specified tick
+    pub(crate) fn clear_after_tick(&mut self, tick: Tick) {
+        while self.buffer.back().is_some_and(|(t, _)| *t > tick) {
+            self.buffer.pop_back();
+        }
+    }
+}
+
+pub trait AppResourceRollbackExt {
+    /// Roll back the resource `R` along with the predicted components.
+    ///
+    /// The value of the resource is stored at the end of every tick, and when a rollback is
+    /// triggered it gets reset to its value at the rollback tick before re-simulating the ticks.
+    /// This is only needed for resources that hold state of the predicted simulation (e.g. the
+    /// internal state of a physics engine).
+    ///
+    /// The rollback only happens on the client: this must be called after adding the
+    /// [`ClientPlugins`](crate::client::plugin::ClientPlugins), and it does nothing (apart from
+    /// logging a warning) on an app without a [`ClientConfig`].
+    fn add_resource_rollback<R: Resource + Clone>(&mut self);
+}
+
+impl AppResourceRollbackExt for App {
+    fn add_resource_rollback<R: Resource + Clone>(&mut self) {
+        // the rollback only happens on the client
+        if self.world.get_resource::<ClientConfig>().is_none() {
+            warn!(
+                "add_resource_rollback::<{}> has no effect because the app has no ClientConfig. \
+                It must be called on the client app, after adding the ClientPlugins",
+                std::any::type_name::<R>()
+            );
+            return;
+        }
+        self.init_resource::<ResourceHistory<R>>();
+        self.add_systems(
+            PreUpdate,
+            prepare_resource_rollback::<R>.in_set(PredictionSet::PrepareRollback),
+        );
+        self.add_systems(
+            FixedPostUpdate,
+            update_resource_history::<R>.in_set(PredictionSet::UpdateHistory),
+        );
+    }
+}
+
+/// Store the value of the resource in the history if it changed during this tick.
+///
+/// Also remove the values that are older than the oldest tick that we could roll back to: the oldest
+/// confirmed tick of the predicted entities, and at most [`PredictionConfig::max_rollback_ticks`] ticks ago
+/// (the confirmed tick of an entity that is not updated by the server does not advance).
+///
+/// [`PredictionConfig::max_rollback_ticks`]: crate::client::prediction::plugin::PredictionConfig::max_rollback_ticks
+pub(crate) fn update_resource_history<R: Resource + Clone>(
+    resource: Option<Res<R>>,
+    mut history: ResMut<ResourceHistory<R>>,
+    confirmed_query: Query<&Confirmed>,
+    config: Res<ClientConfig>,
+    tick_manager: Res<TickManager>,
+    rollback: Res<Rollback>,
+) {
+    // tick for which we will record the history (either the current client tick or the current rollback tick)
+    let tick = tick_manager.tick_or_rollback_tick(rollback.as_ref());
+    // we can never roll back further than the maximum rollback window
+    let mut oldest_tick = tick - config.prediction.max_rollback_ticks;
+    // or to a tick older than the oldest confirmed tick of the predicted entities
+    if let Some(oldest_confirmed_tick) = confirmed_query
+        .iter()
+        .filter(|confirmed| confirmed.predicted.is_some())
+        .map(|confirmed| confirmed.tick)
+        .min()
+    {
+        oldest_tick = oldest_tick.max(oldest_confirmed_tick);
+    }
+    history.pop_until_tick(oldest_tick);
+    let last_removed = history
+        .buffer
+        .back()
+        .map(|(_, state)| matches!(state, ComponentState::Removed));
+    match resource {
+        // change detection works even when running the schedule for rollback
+        Some(resource) if resource.is_changed() || last_removed != Some(false) => {
+            trace!(
+                ?tick,
+                "update resource history for {:?}",
+                std::any::type_name::<R>()
+            );
+            history.add_update(tick, resource.clone());
+        }
+        None if last_removed != Some(true) => {
+            trace!(
+                ?tick,
+                "resource {:?} got removed",
+                std::any::type_name::<R>()
+            );
+            history.add_remove(tick);
+        }
+        _ => {}
+    }
+}
+
+/// Reset the resource to its value at the start of the rollback.
+/// The resource is re-inserted if it existed at that tick, and removed if it didn't.
+pub(crate) fn prepare_resource_rollback<R: Resource + Clone>(
+    mut commands: Commands,
+    resource: Option<ResMut<R>>,
+    mut history: ResMut<ResourceHistory<R>>,
+    rollback: Res<Rollback>,
+) {
+    let Some(rollback_tick) = rollback.get_rollback_tick() else {
+        return;
+    };
+    // we restore the state at the end of tick `rollback_tick - 1`, i.e. the state of the confirmed entities
+    let tick = rollback_tick - 1;
+    history.clear_after_tick(tick);
+    match (history.pop_until_tick(tick), resource) {
+        (None, _) => {
+            debug!(
+                ?tick,
+                "No history to roll back the resource {:?}",
+                std::any::type_name::<R>()
+            );
+        }
+        (Some(ComponentState::Updated(value)), Some(mut resource)) => {
+            *resource = value;
+        }
+        (Some(ComponentState::Updated(value)), None) => {
+            debug!(
+                "Re-inserting resource {:?} that was removed after the rollback tick",
+                std::any::type_name::<R>()
+            );
+            commands.insert_resource(value);
+        }
+        (Some(ComponentState::Removed), Some(_)) => {
+            debug!(
+                "Removing resource {:?} that did not exist at the rollback tick",
+                std::any::type_name::<R>()
+            );
+            commands.remove_resource::<R>();
+        }
+        (Some(ComponentState::Removed), None) => {}
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::*;
+    use bevy::utils::HashMap;
+
+    use crate::client::prediction::rollback::test_utils::received_confirmed_update;
+    use crate::prelude::client::*;
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    #[derive(Resource, Clone, Debug, Default, PartialEq)]
+    struct Counter(f32);
+
+    fn increment(mut counter: ResMut<Counter>, query: Query<&Component1, With<Predicted>>) {
+        for component in query.iter() {
+            counter.0 += component.0;
+        }
+    }
+
+    /// Test that:
+    /// - the resource history gets updated every tick
+    /// - on rollback, the resource is restored to its value at the rollback tick and the ticks are re-simulated
+    #[test]
+    fn test_resource_rollback() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<Counter>();
+        stepper.client_app.add_resource_rollback::<Counter>();
+        stepper.client_app.add_systems(FixedUpdate, increment);
+
+        // add predicted/confirmed entities
+        let confirmed = stepper
+            .client_app
+            .world
+            .spawn((Confirmed::default(), Component1(1.0)))
+            .id();
+        let predicted = stepper
+            .client_app
+            .world
+            .spawn(Predicted {
+                confirmed_entity: Some(confirmed),
+            })
+            .id();
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .get_mut::<Confirmed>()
+            .unwrap()
+            .predicted = Some(predicted);
+        // store the value of the resource at the end of each tick
+        let mut values = HashMap::new();
+        for _ in 0..5 {
+            stepper.frame_step();
+            values.insert(
+                stepper.client_tick(),
+                stepper.client_app.world.resource::<Counter>().0,
+            );
+        }
+
+        // the confirmed entity got updated 2 ticks ago: the resource gets restored to its value at that tick,
+        // then 2 ticks get re-simulated with the new value of the component, and we advance by 1 tick
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world
+            .get_mut::<Component1>(confirmed)
+            .unwrap()
+            .0 = 10.0;
+        received_confirmed_update(&mut stepper, confirmed, tick - 2);
+        stepper.frame_step();
+        assert_eq!(
+            stepper.client_app.world.resource::<Counter>(),
+            &Counter(values[&(tick - 2)] + 30.0)
+        );
+    }
+
+    /// Test that if the resource did not exist at the rollback tick, it gets removed during the rollback
+    #[test]
+    fn test_resource_rollback_removed() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.add_resource_rollback::<Counter>();
+
+        // add predicted/confirmed entities
+        let confirmed = stepper
+            .client_app
+            .world
+            .spawn((Confirmed::default(), Component1(1.0)))
+            .id();
+        let predicted = stepper
+            .client_app
+            .world
+            .spawn(Predicted {
+                confirmed_entity: Some(confirmed),
+            })
+            .id();
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .get_mut::<Confirmed>()
+            .unwrap()
+            .predicted = Some(predicted);
+        stepper.frame_step();
+        stepper.frame_step();
+        let tick = stepper.client_tick();
+
+        // the resource gets inserted after the tick that we will roll back to
+        stepper.client_app.world.insert_resource(Counter(1.0));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        stepper
+            .client_app
+            .world
+            .get_mut::<Component1>(confirmed)
+            .unwrap()
+            .0 = 10.0;
+        received_confirmed_update(&mut stepper, confirmed, tick);
+        stepper.frame_step();
+        assert!(stepper.client_app.world.get_resource::<Counter>().is_none());
+    }
+
+    /// Test that the history is bounded by the maximum rollback window even if there are no predicted entities
+    #[test]
+    fn test_resource_history_max_rollback_ticks() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ClientConfig>()
+            .prediction
+            .max_rollback_ticks = 5;
+        stepper.client_app.init_resource::<Counter>();
+        stepper.client_app.add_resource_rollback::<Counter>();
+        stepper
+            .client_app
+            .add_systems(FixedUpdate, |mut counter: ResMut<Counter>| counter.0 += 1.0);
+
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        // the values of the last 5 ticks, plus the value at the oldest tick that we can roll back to
+        let history = stepper
+            .client_app
+            .world
+            .resource::<ResourceHistory<Counter>>();
+        assert_eq!(history.buffer.len(), 6);
+    }
+}
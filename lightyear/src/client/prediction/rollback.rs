@@ -6,17 +6,19 @@ use bevy::app::FixedMain;
 use bevy::ecs::entity::EntityHashSet;
 use bevy::ecs::reflect::ReflectResource;
 use bevy::prelude::{
-    Commands, DespawnRecursiveExt, DetectChanges, Entity, Query, Ref, Res, ResMut, Resource, With,
-    Without, World,
+    Commands, Component, DespawnRecursiveExt, DetectChanges, Entity, Event, Or, Query, Ref, Res,
+    ResMut, Resource, With, Without, World,
 };
 use bevy::reflect::Reflect;
+use bevy::utils::HashSet;
 use parking_lot::RwLock;
 use tracing::{debug, error, trace, trace_span};
 
-use crate::client::components::{ComponentSyncMode, Confirmed, SyncComponent};
+use crate::client::components::{ComponentSyncMode, Confirmed, SyncComponent, SyncEntityMapper};
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
 use crate::client::prediction::correction::Correction;
+use crate::client::prediction::diagnostics::PredictionStats;
 use crate::client::prediction::predicted_history::ComponentState;
 use crate::client::prediction::resource::PredictionManager;
 use crate::prelude::client::SyncMetadata;
@@ -34,11 +36,50 @@ pub struct Rollback {
     /// We use a RwLock because we want to be able to update this value from multiple systems
     /// in parallel.
     pub state: RwLock<RollbackState>,
-    // pub rollback_groups: EntityHashMap<ReplicationGroupId, RollbackState>,
+    #[reflect(ignore)]
+    /// The [`RollbackGroup`]s that had a misprediction and need to be rolled back.
+    /// (`None` is the group of the predicted entities that don't have a [`RollbackGroup`])
+    mispredicted_groups: RwLock<HashSet<Option<RollbackGroup>>>,
+}
+
+/// Component to split the predicted entities into groups that are rolled back independently.
+///
+/// By default, when a misprediction is detected on any predicted entity, all predicted entities
+/// are rolled back and re-simulated. If the predicted entities are split into [`RollbackGroup`]s (for example
+/// one group per player), only the groups that had a misprediction are rolled back; the entities of the
+/// other groups get the [`SkipRollback`] marker during the rollback.
+/// Predicted entities without a [`RollbackGroup`] are part of the same default group.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct RollbackGroup(pub u32);
+
+/// Bevy [`Event`] emitted on the client right before the ticks are re-simulated during a rollback
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct RollbackStart {
+    /// First tick that will be re-simulated
+    pub rollback_tick: Tick,
+    /// Current tick of the client; the last tick that will be re-simulated
+    pub current_tick: Tick,
 }
 
+/// Bevy [`Event`] emitted on the client after a rollback is complete
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct RollbackEnd {
+    /// Number of ticks that were re-simulated
+    pub ticks_resimulated: u16,
+}
+
+/// Marker component added on the predicted entities that are not part of the current rollback
+/// (because their [`RollbackGroup`] did not have any misprediction).
+///
+/// Systems that run in `FixedUpdate` should ignore the entities that have this component, to avoid
+/// re-simulating them.
+/// For example: `Query<&mut Position, (With<Predicted>, Without<SkipRollback>)>`
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct SkipRollback;
+
 /// Resource that will track whether we should do rollback or not
-/// (We have this as a resource because if any predicted entity needs to be rolled-back; we should roll back all predicted entities)
+/// (We have this as a resource because if any predicted entity needs to be rolled-back; we should roll back all predicted entities
+/// of the same [`RollbackGroup`])
 #[derive(Debug, Default, Reflect)]
 pub enum RollbackState {
     /// We are not in a rollback state
@@ -57,6 +98,7 @@ impl Rollback {
     pub(crate) fn new(state: RollbackState) -> Self {
         Self {
             state: RwLock::new(state),
+            mispredicted_groups: Default::default(),
         }
     }
     /// Returns true if we are currently in a rollback state
@@ -88,6 +130,20 @@ impl Rollback {
     /// Set the rollback state back to non-rollback
     pub(crate) fn set_non_rollback(&self) {
         *self.state.write().deref_mut() = RollbackState::Default;
+        self.mispredicted_groups.write().clear();
+    }
+
+    /// Record that there was a misprediction for an entity of the given [`RollbackGroup`]
+    pub(crate) fn add_mispredicted_group(&self, group: Option<RollbackGroup>) {
+        self.mispredicted_groups.write().insert(group);
+    }
+
+    /// Returns true if the entities of the given [`RollbackGroup`] should be rolled back
+    ///
+    /// All groups are rolled back if the rollback was not caused by a misprediction in a specific group.
+    pub fn should_rollback_group(&self, group: Option<&RollbackGroup>) -> bool {
+        let groups = self.mispredicted_groups.read();
+        groups.is_empty() || groups.contains(&group.copied())
     }
 
     /// Set the rollback state to `ShouldRollback` with the given tick
@@ -107,10 +163,15 @@ pub(crate) fn check_rollback<C: SyncComponent>(
     tick_manager: Res<TickManager>,
     connection: Res<ConnectionManager>,
     // We also snap the value of the component to the server state if we are in rollback
-    mut predicted_query: Query<&mut PredictionHistory<C>, (With<Predicted>, Without<Confirmed>)>,
+    mut predicted_query: Query<
+        (&mut PredictionHistory<C>, Option<&RollbackGroup>),
+        (With<Predicted>, Without<Confirmed>),
+    >,
     // We use Option<> because the predicted component could have been removed while it still exists in Confirmed
     confirmed_query: Query<(Entity, Option<&C>, Ref<Confirmed>)>,
     rollback: Res<Rollback>,
+    stats: Res<PredictionStats>,
+    entity_mapper: SyncEntityMapper,
 ) {
     // TODO: can just enable bevy spans?
     let _span = trace_span!("client rollback check");
@@ -141,7 +202,7 @@ pub(crate) fn check_rollback<C: SyncComponent>(
         let Some(p) = confirmed.predicted else {
             continue;
         };
-        let Ok(mut predicted_history) = predicted_query.get_mut(p) else {
+        let Ok((mut predicted_history, group)) = predicted_query.get_mut(p) else {
             debug!(
                 "Predicted entity {:?} was not found when checking rollback for {:?}",
                 confirmed.predicted,
@@ -165,26 +226,47 @@ pub(crate) fn check_rollback<C: SyncComponent>(
             continue;
         }
 
-        // 3.a We are still not sure if we should do rollback. Compare history against confirmed
+        // 3. Compare history against confirmed
         // We rollback if there's no history (newly added predicted entity, or if there is a mismatch)
-        if !rollback.is_rollback() {
-            let history_value = predicted_history.pop_until_tick(tick);
-            let predicted_exist = history_value.is_some();
-            let confirmed_exist = confirmed_component.is_some();
-            let should_rollback = match confirmed_component {
-                // TODO: history-value should not be empty here; should we panic if it is?
-                // confirm does not exist. rollback if history value is not Removed
-                None => history_value.map_or(false, |history_value| {
-                    history_value != ComponentState::Removed
-                }),
-                // confirm exist. rollback if history value is different
-                Some(c) => history_value.map_or(true, |history_value| match history_value {
+        // NOTE: we do the comparison even if we already know that we should do a rollback (because of
+        //  another entity/component) to record the misprediction statistics. The history will be cleared
+        //  when preparing the rollback anyway.
+        let history_value = predicted_history.pop_until_tick(tick);
+        let predicted_exist = history_value.is_some();
+        let confirmed_exist = confirmed_component.is_some();
+        let mut magnitude = None;
+        let should_rollback = match confirmed_component {
+            // TODO: history-value should not be empty here; should we panic if it is?
+            // confirm does not exist. rollback if history value is not Removed
+            None => history_value.as_ref().map_or(false, |history_value| {
+                *history_value != ComponentState::Removed
+            }),
+            // confirm exist. rollback if history value is different
+            Some(c) => history_value
+                .as_ref()
+                .map_or(true, |history_value| match history_value {
                     ComponentState::Updated(history_value) => {
-                        component_registry.should_rollback(&history_value, c)
+                        // the history references the predicted entities, so we compare it with the mapped confirmed value
+                        let mut c = c.clone();
+                        entity_mapper.map_to_predicted(&mut c);
+                        let should_rollback = component_registry.should_rollback(history_value, &c);
+                        if should_rollback {
+                            magnitude =
+                                component_registry.misprediction_magnitude(history_value, &c);
+                        }
+                        should_rollback
                     }
                     ComponentState::Removed => true,
                 }),
-            };
+        };
+        // there is no prediction to compare with for newly added predicted entities
+        if predicted_exist {
+            stats.record::<C>(should_rollback, magnitude);
+        }
+        if should_rollback {
+            rollback.add_mispredicted_group(group.copied());
+        }
+        if !rollback.is_rollback() {
             if should_rollback {
                 debug!(
                    ?predicted_exist, ?confirmed_exist,
@@ -196,7 +278,7 @@ pub(crate) fn check_rollback<C: SyncComponent>(
                 rollback.set_rollback_tick(tick + 1);
             }
         } else {
-            // 3.b We already know we should do rollback (because of another entity/component), start the rollback
+            // We already know we should do rollback (because of another entity/component), start the rollback
             trace!(
                    "Rollback check: should roll back for component between predicted and confirmed on tick {:?} for component {:?}. Current tick: {:?}",
                    tick, kind, current_tick
@@ -205,6 +287,22 @@ pub(crate) fn check_rollback<C: SyncComponent>(
     }
 }
 
+/// Number of ticks over which the correction of the component `C` is applied
+pub(crate) fn correction_ticks<C: SyncComponent>(
+    component_registry: &ComponentRegistry,
+    config: &ClientConfig,
+    current_tick: Tick,
+    rollback_tick: Tick,
+) -> i16 {
+    component_registry.correction_ticks::<C>().map_or_else(
+        || {
+            ((current_tick - rollback_tick) as f32 * config.prediction.correction_ticks_factor)
+                .round() as i16
+        },
+        |ticks| ticks.min(i16::MAX as u16) as i16,
+    )
+}
+
 /// If there is a mismatch, prepare rollback for all components
 #[allow(clippy::type_complexity)]
 #[allow(clippy::too_many_arguments)]
@@ -222,6 +320,7 @@ pub(crate) fn prepare_rollback<C: SyncComponent>(
             Option<&mut C>,
             &mut PredictionHistory<C>,
             Option<&mut Correction<C>>,
+            Option<&RollbackGroup>,
         ),
         (
             With<Predicted>,
@@ -231,6 +330,7 @@ pub(crate) fn prepare_rollback<C: SyncComponent>(
     >,
     confirmed_query: Query<(Entity, Option<&C>, Ref<Confirmed>)>,
     rollback: Res<Rollback>,
+    entity_mapper: SyncEntityMapper,
 ) {
     let kind = std::any::type_name::<C>();
 
@@ -255,8 +355,13 @@ pub(crate) fn prepare_rollback<C: SyncComponent>(
         };
 
         // 1. Get the predicted entity, and it's history
-        let Ok((predicted_entity, predicted_component, mut predicted_history, mut correction)) =
-            predicted_query.get_mut(p)
+        let Ok((
+            predicted_entity,
+            predicted_component,
+            mut predicted_history,
+            mut correction,
+            group,
+        )) = predicted_query.get_mut(p)
         else {
             debug!(
                 "Predicted entity {:?} was not found when preparing rollback for {:?}",
@@ -265,6 +370,10 @@ pub(crate) fn prepare_rollback<C: SyncComponent>(
             );
             continue;
         };
+        // the entity is not part of the rollback: keep its current state and history
+        if !rollback.should_rollback_group(group) {
+            continue;
+        }
 
         // 2. we need to clear the history so we can write a new one
         predicted_history.clear();
@@ -284,6 +393,9 @@ pub(crate) fn prepare_rollback<C: SyncComponent>(
             }
             // confirm exist, update or insert on predicted
             Some(c) => {
+                // map any entities from confirmed to predicted
+                let mut c = c.clone();
+                entity_mapper.map_to_predicted(&mut c);
                 predicted_history
                     .buffer
                     .add_item(rollback_tick, ComponentState::Updated(c.clone()));
@@ -299,9 +411,12 @@ pub(crate) fn prepare_rollback<C: SyncComponent>(
                         // }
 
                         // insert the Correction information only if the component exists on both confirmed and predicted
-                        let correction_ticks = ((current_tick - rollback_tick) as f32
-                            * config.prediction.correction_ticks_factor)
-                            .round() as i16;
+                        let correction_ticks = correction_ticks::<C>(
+                            &component_registry,
+                            &config,
+                            current_tick,
+                            rollback_tick,
+                        );
 
                         // no need to add the Correction if the correction is instant
                         if correction_ticks != 0 && component_registry.has_correction::<C>() {
@@ -330,7 +445,7 @@ pub(crate) fn prepare_rollback<C: SyncComponent>(
                         }
 
                         // update the component to the corrected value
-                        *predicted_component = c.clone();
+                        *predicted_component = c;
                     }
                 };
             }
@@ -366,6 +481,7 @@ pub(crate) fn prepare_rollback_prespawn<C: SyncComponent>(
             Option<&mut C>,
             &mut PredictionHistory<C>,
             Option<&mut Correction<C>>,
+            Option<&RollbackGroup>,
         ),
         (
             With<PreSpawnedPlayerObject>,
@@ -411,10 +527,12 @@ pub(crate) fn prepare_rollback_prespawn<C: SyncComponent>(
         }
     });
 
-    for (prespawned_entity, predicted_component, mut predicted_history, mut correction) in
+    for (prespawned_entity, predicted_component, mut predicted_history, mut correction, group) in
         predicted_query.iter_mut()
     {
-        if entities_to_despawn.contains(&prespawned_entity) {
+        if entities_to_despawn.contains(&prespawned_entity)
+            || !rollback.should_rollback_group(group)
+        {
             continue;
         }
 
@@ -433,9 +551,12 @@ pub(crate) fn prepare_rollback_prespawn<C: SyncComponent>(
                     // TODO: do we need to do a correction in this case?
 
                     // insert the Correction information only if the component exists on both confirmed and predicted
-                    let correction_ticks = ((current_tick - rollback_tick) as f32
-                        * config.prediction.correction_ticks_factor)
-                        .round() as i16;
+                    let correction_ticks = correction_ticks::<C>(
+                        &component_registry,
+                        &config,
+                        current_tick,
+                        rollback_tick,
+                    );
 
                     // no need to add the Correction if the correction is instant
                     if correction_ticks != 0 && component_registry.has_correction::<C>() {
@@ -502,6 +623,25 @@ pub(crate) fn run_rollback(world: &mut World) {
         "Rollback between {:?} and {:?}",
         current_rollback_tick, current_tick
     );
+    world.send_event(RollbackStart {
+        rollback_tick: current_rollback_tick,
+        current_tick,
+    });
+
+    // mark the predicted entities that are not part of the rollback
+    let mut query = world.query_filtered::<
+        (Entity, Option<&RollbackGroup>),
+        Or<(With<Predicted>, With<PreSpawnedPlayerObject>)>,
+    >();
+    let rollback = world.resource::<Rollback>();
+    let skipped_entities: Vec<Entity> = query
+        .iter(world)
+        .filter(|(_, group)| !rollback.should_rollback_group(*group))
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in &skipped_entities {
+        world.entity_mut(*entity).insert(SkipRollback);
+    }
 
     // run the physics fixed update schedule (which should contain ALL predicted/rollback components)
     for i in 0..num_rollback_ticks {
@@ -510,6 +650,17 @@ pub(crate) fn run_rollback(world: &mut World) {
         world.run_schedule(FixedMain)
     }
     debug!("Finished rollback. Current tick: {:?}", current_tick);
+    let ticks_resimulated = num_rollback_ticks.max(0) as u16;
+    world
+        .resource::<PredictionStats>()
+        .record_rollback(ticks_resimulated);
+    world.send_event(RollbackEnd { ticks_resimulated });
+
+    for entity in skipped_entities {
+        if let Some(mut entity_mut) = world.get_entity_mut(entity) {
+            entity_mut.remove::<SkipRollback>();
+        }
+    }
 
     // revert the state of Rollback for the next frame
     let rollback = world.get_resource_mut::<Rollback>().unwrap();
@@ -521,8 +672,8 @@ pub(crate) fn increment_rollback_tick(rollback: Res<Rollback>) {
     rollback.increment_rollback_tick();
 }
 
-#[cfg(test)]
-pub(super) mod test_utils {
+#[cfg(all(test, feature = "client", feature = "server"))]
+pub(crate) mod test_utils {
     use crate::client::components::Confirmed;
     use crate::client::connection::ConnectionManager;
     use crate::prelude::Tick;
@@ -531,7 +682,7 @@ pub(super) mod test_utils {
     use std::time::Duration;
 
     /// Helper function to simulate that we received a server message
-    pub(super) fn received_confirmed_update(
+    pub(crate) fn received_confirmed_update(
         stepper: &mut BevyStepper,
         confirmed: Entity,
         tick: Tick,
@@ -552,7 +703,7 @@ pub(super) mod test_utils {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod unit_tests {
     use super::test_utils::*;
     use super::*;
@@ -705,10 +856,88 @@ mod unit_tests {
             .resource::<Rollback>()
             .is_rollback());
     }
+
+    /// Check that the mispredictions are recorded in the PredictionStats
+    #[test]
+    fn test_misprediction_stats() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ComponentRegistry>()
+            .set_misprediction_metric::<Component1>(|predicted, confirmed| {
+                (predicted.0 - confirmed.0).abs()
+            });
+        let confirmed = stepper.client_app.world.spawn(Confirmed::default()).id();
+        let predicted = stepper
+            .client_app
+            .world
+            .spawn(Predicted {
+                confirmed_entity: Some(confirmed),
+            })
+            .id();
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .get_mut::<Confirmed>()
+            .unwrap()
+            .predicted = Some(predicted);
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .insert(Component1(1.0));
+        stepper.frame_step();
+
+        // misprediction
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .get_mut::<Component1>()
+            .unwrap()
+            .0 = 3.0;
+        received_confirmed_update(&mut stepper, confirmed, tick);
+        stepper
+            .client_app
+            .world
+            .run_system_once(check_rollback::<Component1>);
+
+        // correct prediction
+        stepper
+            .client_app
+            .world
+            .resource::<Rollback>()
+            .set_non_rollback();
+        stepper
+            .client_app
+            .world
+            .entity_mut(predicted)
+            .get_mut::<PredictionHistory<Component1>>()
+            .unwrap()
+            .add_update(tick, Component1(3.0));
+        received_confirmed_update(&mut stepper, confirmed, tick);
+        stepper
+            .client_app
+            .world
+            .run_system_once(check_rollback::<Component1>);
+
+        let stats = stepper
+            .client_app
+            .world
+            .resource::<PredictionStats>()
+            .get::<Component1>()
+            .unwrap();
+        assert_eq!(stats.checks, 2);
+        assert_eq!(stats.mispredictions, 1);
+        assert_eq!(stats.average_magnitude(), Some(2.0));
+    }
 }
 
 /// More general integration tests for rollback
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod integration_tests {
     use super::test_utils::*;
 
@@ -930,4 +1159,188 @@ mod integration_tests {
             .0 = 4.0;
         Ok(())
     }
+
+    fn increment_not_skipped(
+        mut query: Query<&mut Component1, (With<Predicted>, Without<SkipRollback>)>,
+    ) {
+        for mut component in query.iter_mut() {
+            component.0 += 1.0;
+        }
+    }
+
+    /// Test that:
+    /// - a misprediction on an entity only rolls back the entities of its RollbackGroup
+    /// - the entities of other groups get the SkipRollback marker during the rollback,
+    ///   and are not re-simulated
+    #[test]
+    fn test_partial_rollback() -> anyhow::Result<()> {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .add_systems(FixedUpdate, increment_not_skipped);
+        let mut spawn = |group: u32| {
+            let confirmed = stepper
+                .client_app
+                .world
+                .spawn((Confirmed::default(), Component1(0.0)))
+                .id();
+            let predicted = stepper
+                .client_app
+                .world
+                .spawn((
+                    Predicted {
+                        confirmed_entity: Some(confirmed),
+                    },
+                    RollbackGroup(group),
+                ))
+                .id();
+            stepper
+                .client_app
+                .world
+                .entity_mut(confirmed)
+                .get_mut::<Confirmed>()
+                .unwrap()
+                .predicted = Some(predicted);
+            (confirmed, predicted)
+        };
+        let (confirmed_a, predicted_a) = spawn(1);
+        let (_, predicted_b) = spawn(2);
+        for _ in 0..3 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper.client_app.world.get::<Component1>(predicted_a),
+            Some(&Component1(3.0))
+        );
+        assert_eq!(
+            stepper.client_app.world.get::<Component1>(predicted_b),
+            Some(&Component1(3.0))
+        );
+
+        // create a misprediction for the entity of group 1
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world
+            .get_mut::<Component1>(confirmed_a)
+            .unwrap()
+            .0 = -10.0;
+        received_confirmed_update(&mut stepper, confirmed_a, tick - 1);
+        stepper.frame_step();
+
+        // group 1 rolled back 1 tick and advanced by 1 tick
+        assert_eq!(
+            stepper.client_app.world.get::<Component1>(predicted_a),
+            Some(&Component1(-8.0))
+        );
+        // group 2 was not rolled back or re-simulated
+        assert_eq!(
+            stepper.client_app.world.get::<Component1>(predicted_b),
+            Some(&Component1(4.0))
+        );
+        assert!(stepper
+            .client_app
+            .world
+            .get::<SkipRollback>(predicted_b)
+            .is_none());
+        Ok(())
+    }
+
+    /// Test that the RollbackStart/RollbackEnd events are emitted and that the rollback is
+    /// recorded in the PredictionStats
+    #[test]
+    fn test_rollback_events() -> anyhow::Result<()> {
+        let (mut stepper, confirmed, _) = setup();
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .insert(Component1(0.0));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world
+            .get_mut::<Component1>(confirmed)
+            .unwrap()
+            .0 = -10.0;
+        received_confirmed_update(&mut stepper, confirmed, tick - 1);
+        stepper.frame_step();
+
+        let starts: Vec<_> = stepper
+            .client_app
+            .world
+            .resource_mut::<Events<RollbackStart>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            starts,
+            vec![RollbackStart {
+                rollback_tick: tick,
+                current_tick: tick,
+            }]
+        );
+        let ends: Vec<_> = stepper
+            .client_app
+            .world
+            .resource_mut::<Events<RollbackEnd>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            ends,
+            vec![RollbackEnd {
+                ticks_resimulated: 1
+            }]
+        );
+        let stats = stepper
+            .client_app
+            .world
+            .resource::<PredictionStats>()
+            .rollbacks();
+        assert_eq!(stats.rollbacks, 1);
+        assert_eq!(stats.ticks_resimulated, 1);
+        Ok(())
+    }
+
+    /// Test that the number of correction ticks registered for a component is used
+    /// instead of the correction ticks factor
+    #[test]
+    fn test_correction_ticks() -> anyhow::Result<()> {
+        let (mut stepper, confirmed, predicted) = setup();
+        {
+            let mut registry = stepper.client_app.world.resource_mut::<ComponentRegistry>();
+            registry.set_linear_correction::<Component1>();
+            registry.set_correction_ticks::<Component1>(4);
+        }
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .insert(Component1(0.0));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world
+            .get_mut::<Component1>(confirmed)
+            .unwrap()
+            .0 = -10.0;
+        received_confirmed_update(&mut stepper, confirmed, tick - 1);
+        stepper.frame_step();
+
+        // the simulation uses the corrected value, and the visual value is corrected over 4 ticks
+        let correction = stepper
+            .client_app
+            .world
+            .get::<Correction<Component1>>(predicted)
+            .unwrap();
+        assert_eq!(correction.original_tick, tick);
+        assert_eq!(correction.final_correction_tick, tick + 4);
+        assert_eq!(correction.current_correction, Some(Component1(-8.0)));
+        Ok(())
+    }
 }
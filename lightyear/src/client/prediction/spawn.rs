@@ -6,11 +6,14 @@ use crate::client::components::Confirmed;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
 use crate::client::events::ComponentInsertEvent;
+use crate::client::interpolation::resource::InterpolationManager;
+use crate::client::interpolation::Interpolated;
 use crate::client::prediction::resource::PredictionManager;
 use crate::client::prediction::Predicted;
+use crate::client::transition::{is_switch, PreviousSyncEntity};
 use crate::connection::client::ClientConnection;
 use crate::prelude::{ShouldBePredicted, Tick};
-use crate::shared::replication::components::PrePredicted;
+use crate::shared::replication::components::{PrePredicted, ShouldBeInterpolated};
 
 /// Spawn a predicted entity for each confirmed entity that has the `ShouldBePredicted` component added
 /// The `Confirmed` entity could already exist because we share the Confirmed component for prediction and interpolation.
@@ -19,6 +22,8 @@ use crate::shared::replication::components::PrePredicted;
 pub(crate) fn spawn_predicted_entity(
     connection: Res<ConnectionManager>,
     mut manager: ResMut<PredictionManager>,
+    mut interpolation_manager: Option<ResMut<InterpolationManager>>,
+    interpolated_entities: Query<Ref<Interpolated>>,
     mut commands: Commands,
 
     // TODO: instead of listening to the ComponentInsertEvent, should we just directly query on Added<ShouldBePredicted>?
@@ -62,6 +67,29 @@ pub(crate) fn spawn_predicted_entity(
         let mut confirmed_entity_mut = commands.entity(confirmed_entity);
         confirmed_entity_mut.remove::<ShouldBePredicted>();
         if let Some(mut confirmed) = confirmed {
+            // the entity was interpolated and is now predicted: the predicted entity replaces the interpolated entity
+            if let Some(interpolated) = confirmed
+                .interpolated
+                .filter(|i| is_switch(&interpolated_entities, *i))
+            {
+                debug!(
+                    ?confirmed_entity,
+                    ?interpolated,
+                    "Switching from interpolation to prediction"
+                );
+                confirmed.interpolated = None;
+                if let Some(interpolation_manager) = interpolation_manager.as_mut() {
+                    interpolation_manager
+                        .interpolated_entity_map
+                        .get_mut()
+                        .confirmed_to_interpolated
+                        .remove(&confirmed_entity);
+                }
+                confirmed_entity_mut.remove::<ShouldBeInterpolated>();
+                commands
+                    .entity(predicted_entity)
+                    .insert(PreviousSyncEntity(interpolated));
+            }
             confirmed.predicted = Some(predicted_entity);
         } else {
             // TODO: this is the same as the current tick no? or maybe not because we could have received updates before the spawn
@@ -0,0 +1,452 @@
+//! Smooth the rollback corrections on the rendered [`Transform`].
+//!
+//! When a rollback corrects a predicted entity, the simulation immediately uses the corrected state, which looks
+//! like a teleport if the misprediction was large. [`Correction`](crate::client::prediction::correction::Correction)
+//! smooths the simulated component itself; the [`CorrectionPlugin`] instead keeps the simulation snapped to the
+//! corrected state and only offsets the rendered [`Transform`]:
+//! - when a rollback corrects the component `C` of a predicted entity, the difference between the rendered value
+//!   before and after the rollback is stored in a [`VisualCorrection<C>`] component
+//! - the offset decays to zero over the number of ticks set with
+//!   [`add_correction_ticks`](crate::prelude::ComponentRegistration::add_correction_ticks) (or computed from
+//!   [`PredictionConfig::correction_ticks_factor`](crate::client::prediction::plugin::PredictionConfig))
+//! - in `PostUpdate`, the offset is added to the [`Transform`] before the transforms are propagated, so that the
+//!   [`GlobalTransform`] of the entity and of its children are offset, and it is removed right after the propagation
+//!   so that the [`Transform`] matches the simulation again
+//!
+//! The [`Transform`] must be updated from `C` (for example from a `Position` component) before
+//! [`PredictionSet::ApplyVisualCorrection`], and `C` should not have a correction function as well, otherwise
+//! the correction is smoothed twice.
+//! ```rust
+//! # use bevy::prelude::*;
+//! # use serde::{Deserialize, Serialize};
+//! # use lightyear::prelude::client::{CorrectionPlugin, PredictionSet};
+//! # #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Deref)]
+//! # struct Position(Vec2);
+//! # fn sync_transform(mut query: Query<(&Position, &mut Transform)>) {
+//! #     for (position, mut transform) in query.iter_mut() {
+//! #         transform.translation = position.extend(0.0);
+//! #     }
+//! # }
+//! # let mut app = App::new();
+//! app.add_plugins(CorrectionPlugin::<Position>::new(|position| {
+//!     Transform::from_translation(position.extend(0.0))
+//! }));
+//! app.add_systems(PostUpdate, sync_transform.before(PredictionSet::ApplyVisualCorrection));
+//! ```
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use tracing::trace;
+
+use crate::client::components::SyncComponent;
+use crate::client::config::ClientConfig;
+use crate::client::easings::ease_out_quad;
+use crate::client::interpolation::plugin::InterpolationSet;
+use crate::client::prediction::plugin::PredictionSet;
+use crate::client::prediction::rollback::{correction_ticks, prepare_rollback, Rollback};
+use crate::client::prediction::Predicted;
+use crate::prelude::{ComponentRegistry, Tick, TickManager, TimeManager};
+
+/// The visual corrections smaller than this are ignored
+const MIN_CORRECTION: f32 = 1e-4;
+
+/// Plugin that smooths the rollback corrections of the component `C` on the rendered [`Transform`]
+/// of the predicted entities
+pub struct CorrectionPlugin<C> {
+    /// How the component is rendered (only the translation and rotation are used)
+    to_transform: fn(&C) -> Transform,
+}
+
+impl<C> CorrectionPlugin<C> {
+    pub fn new(to_transform: fn(&C) -> Transform) -> Self {
+        Self { to_transform }
+    }
+}
+
+/// Visual offset added to the rendered [`Transform`] of a predicted entity after a rollback corrected its component `C`
+///
+/// The offset is equal to `translation` and `rotation` at `start_tick`, and decays to zero at `end_tick`,
+/// at which point the component is removed.
+#[derive(Component, Debug, Clone, PartialEq)]
+#[component(storage = "SparseSet")]
+pub struct VisualCorrection<C: Component> {
+    /// Translation offset at the start of the correction
+    pub translation: Vec3,
+    /// Rotation offset at the start of the correction
+    pub rotation: Quat,
+    /// Tick at which the correction started
+    pub start_tick: Tick,
+    /// Tick at which the offset reaches zero
+    pub end_tick: Tick,
+    /// Offset that was rendered during the latest frame
+    current: (Vec3, Quat),
+    /// True if the offset is currently added to the [`Transform`]
+    applied: bool,
+    _marker: std::marker::PhantomData<C>,
+}
+
+impl<C: Component> VisualCorrection<C> {
+    /// Offset at the given tick, `overstep` being the fraction of the tick that elapsed
+    /// (returns `None` if the correction is over)
+    pub fn offset(&self, tick: Tick, overstep: f32) -> Option<(Vec3, Quat)> {
+        let t = ((tick - self.start_tick) as f32 + overstep)
+            / (self.end_tick - self.start_tick).max(1) as f32;
+        if t >= 1.0 {
+            return None;
+        }
+        let remaining = 1.0 - ease_out_quad(t.max(0.0));
+        Some((
+            self.translation * remaining,
+            Quat::IDENTITY.slerp(self.rotation, remaining),
+        ))
+    }
+}
+
+/// The rendered transforms of the predicted entities before the rollback
+#[derive(Resource)]
+struct PreRollbackTransforms<C> {
+    to_transform: fn(&C) -> Transform,
+    transforms: EntityHashMap<Transform>,
+    start_tick: Tick,
+    end_tick: Tick,
+}
+
+impl<C: SyncComponent> Plugin for CorrectionPlugin<C> {
+    fn build(&self, app: &mut App) {
+        // the correction only happens on the client
+        if app.world.get_resource::<ClientConfig>().is_none() {
+            warn!(
+                "CorrectionPlugin::<{}> has no effect because the app has no ClientConfig. \
+                It must be added to the client app, after the ClientPlugins",
+                std::any::type_name::<C>()
+            );
+            return;
+        }
+        app.insert_resource(PreRollbackTransforms::<C> {
+            to_transform: self.to_transform,
+            transforms: EntityHashMap::default(),
+            start_tick: Tick(0),
+            end_tick: Tick(0),
+        });
+        // SETS
+        app.configure_sets(
+            PostUpdate,
+            (
+                PredictionSet::ApplyVisualCorrection
+                    .after(PredictionSet::VisualCorrection)
+                    .after(InterpolationSet::VisualInterpolation)
+                    .before(TransformSystem::TransformPropagate),
+                PredictionSet::RemoveVisualCorrection.after(TransformSystem::TransformPropagate),
+            )
+                .in_set(PredictionSet::All),
+        );
+        // SYSTEMS
+        app.add_systems(
+            PreUpdate,
+            (
+                store_pre_rollback_transforms::<C>
+                    .in_set(PredictionSet::PrepareRollback)
+                    .before(prepare_rollback::<C>),
+                start_visual_correction::<C>
+                    .after(PredictionSet::Rollback)
+                    .in_set(PredictionSet::All),
+            ),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                apply_visual_correction::<C>.in_set(PredictionSet::ApplyVisualCorrection),
+                remove_visual_correction::<C>.in_set(PredictionSet::RemoveVisualCorrection),
+            ),
+        );
+    }
+}
+
+/// Store the rendered value of the component before it gets corrected by the rollback
+fn store_pre_rollback_transforms<C: SyncComponent>(
+    component_registry: Res<ComponentRegistry>,
+    config: Res<ClientConfig>,
+    tick_manager: Res<TickManager>,
+    rollback: Res<Rollback>,
+    mut pre_rollback: ResMut<PreRollbackTransforms<C>>,
+    query: Query<(Entity, &C), With<Predicted>>,
+) {
+    let Some(rollback_tick) = rollback.get_rollback_tick() else {
+        return;
+    };
+    let current_tick = tick_manager.tick();
+    let ticks = correction_ticks::<C>(&component_registry, &config, current_tick, rollback_tick);
+    // the correction is instant
+    if ticks <= 0 {
+        return;
+    }
+    pre_rollback.start_tick = current_tick;
+    pre_rollback.end_tick = current_tick + ticks;
+    let to_transform = pre_rollback.to_transform;
+    pre_rollback.transforms = query
+        .iter()
+        .map(|(entity, component)| (entity, to_transform(component)))
+        .collect();
+}
+
+/// Once the rollback is done, add the difference between the rendered values before and after the rollback
+/// to the visual offset of the entity
+fn start_visual_correction<C: SyncComponent>(
+    mut commands: Commands,
+    mut pre_rollback: ResMut<PreRollbackTransforms<C>>,
+    mut query: Query<(&C, Option<&mut VisualCorrection<C>>), With<Predicted>>,
+) {
+    if pre_rollback.transforms.is_empty() {
+        return;
+    }
+    let (start_tick, end_tick) = (pre_rollback.start_tick, pre_rollback.end_tick);
+    let to_transform = pre_rollback.to_transform;
+    for (entity, before) in pre_rollback.transforms.drain() {
+        let Ok((component, correction)) = query.get_mut(entity) else {
+            continue;
+        };
+        let after = to_transform(component);
+        // keep rendering the entity where it was rendered during the previous frame
+        let (current_translation, current_rotation) = correction
+            .as_ref()
+            .map_or((Vec3::ZERO, Quat::IDENTITY), |correction| {
+                correction.current
+            });
+        let translation = current_translation + before.translation - after.translation;
+        let rotation = (current_rotation * before.rotation * after.rotation.inverse()).normalize();
+        if translation.length() < MIN_CORRECTION
+            && rotation.angle_between(Quat::IDENTITY) < MIN_CORRECTION
+        {
+            if correction.is_some() {
+                commands.entity(entity).remove::<VisualCorrection<C>>();
+            }
+            continue;
+        }
+        trace!(
+            ?entity,
+            ?translation,
+            ?rotation,
+            "Visual correction for {:?}",
+            std::any::type_name::<C>()
+        );
+        match correction {
+            Some(mut correction) => {
+                correction.translation = translation;
+                correction.rotation = rotation;
+                correction.start_tick = start_tick;
+                correction.end_tick = end_tick;
+            }
+            None => {
+                commands.entity(entity).insert(VisualCorrection::<C> {
+                    translation,
+                    rotation,
+                    start_tick,
+                    end_tick,
+                    current: (Vec3::ZERO, Quat::IDENTITY),
+                    applied: false,
+                    _marker: std::marker::PhantomData,
+                });
+            }
+        }
+    }
+}
+
+/// Add the visual offset to the [`Transform`] before it is propagated
+fn apply_visual_correction<C: SyncComponent>(
+    mut commands: Commands,
+    tick_manager: Res<TickManager>,
+    time_manager: Res<TimeManager>,
+    mut query: Query<(Entity, &mut Transform, &mut VisualCorrection<C>)>,
+) {
+    let tick = tick_manager.tick();
+    let overstep = time_manager.overstep();
+    for (entity, mut transform, mut correction) in query.iter_mut() {
+        let Some((translation, rotation)) = correction.offset(tick, overstep) else {
+            commands.entity(entity).remove::<VisualCorrection<C>>();
+            continue;
+        };
+        transform.translation += translation;
+        transform.rotation = rotation * transform.rotation;
+        correction.current = (translation, rotation);
+        correction.applied = true;
+    }
+}
+
+/// Remove the visual offset from the [`Transform`] once it has been propagated, so that the [`Transform`]
+/// matches the simulation again
+fn remove_visual_correction<C: SyncComponent>(
+    mut query: Query<(&mut Transform, &mut VisualCorrection<C>)>,
+) {
+    for (mut transform, mut correction) in query.iter_mut() {
+        if !std::mem::take(&mut correction.applied) {
+            continue;
+        }
+        let (translation, rotation) = correction.current;
+        // the change is detected, so that the transform is propagated again on the next frame
+        transform.translation -= translation;
+        transform.rotation = rotation.inverse() * transform.rotation;
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::*;
+    use bevy::transform::TransformPlugin;
+    use bevy::utils::Duration;
+
+    use crate::client::prediction::rollback::test_utils::received_confirmed_update;
+    use crate::prelude::client::*;
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    fn increment_component(mut query: Query<&mut Component1, With<Predicted>>) {
+        for mut component in query.iter_mut() {
+            component.0 += 1.0;
+        }
+    }
+
+    fn sync_transform(mut query: Query<(&Component1, &mut Transform)>) {
+        for (component, mut transform) in query.iter_mut() {
+            transform.translation.x = component.0;
+        }
+    }
+
+    fn rendered_x(stepper: &BevyStepper, entity: Entity) -> f32 {
+        stepper
+            .client_app
+            .world
+            .get::<GlobalTransform>(entity)
+            .unwrap()
+            .translation()
+            .x
+    }
+
+    /// Test that:
+    /// - after a rollback, the simulation uses the corrected value but the rendered transform keeps the previous prediction
+    /// - the visual offset decays over the correction ticks, and is removed from the `Transform` after propagation
+    #[test]
+    fn test_visual_correction() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default().speedup_factor(1.0),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper.client_app.add_plugins((
+            TransformPlugin,
+            CorrectionPlugin::<Component1>::new(|component| {
+                Transform::from_xyz(component.0, 0.0, 0.0)
+            }),
+        ));
+        stepper.init();
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ComponentRegistry>()
+            .set_correction_ticks::<Component1>(4);
+        stepper
+            .client_app
+            .add_systems(FixedUpdate, increment_component);
+        stepper.client_app.add_systems(
+            PostUpdate,
+            sync_transform.before(PredictionSet::ApplyVisualCorrection),
+        );
+
+        let confirmed = stepper
+            .client_app
+            .world
+            .spawn((Confirmed::default(), Component1(0.0)))
+            .id();
+        let predicted = stepper
+            .client_app
+            .world
+            .spawn((
+                Predicted {
+                    confirmed_entity: Some(confirmed),
+                },
+                Component1(0.0),
+                Transform::default(),
+                GlobalTransform::default(),
+            ))
+            .id();
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .get_mut::<Confirmed>()
+            .unwrap()
+            .predicted = Some(predicted);
+        stepper.frame_step();
+        stepper.frame_step();
+        let rendered_before = rendered_x(&stepper, predicted);
+
+        // the server corrects the entity by -10.0
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world
+            .get_mut::<Component1>(confirmed)
+            .unwrap()
+            .0 = -10.0;
+        received_confirmed_update(&mut stepper, confirmed, tick - 1);
+        stepper.frame_step();
+
+        let simulated = stepper
+            .client_app
+            .world
+            .get::<Component1>(predicted)
+            .unwrap()
+            .0;
+        let correction = stepper
+            .client_app
+            .world
+            .get::<VisualCorrection<Component1>>(predicted)
+            .unwrap();
+        assert_eq!(correction.start_tick, tick);
+        assert_eq!(correction.end_tick, tick + 4);
+        // the transform matches the simulation after the propagation
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .get::<Transform>(predicted)
+                .unwrap()
+                .translation
+                .x,
+            simulated
+        );
+        // the entity is rendered between the previous prediction and the corrected value
+        let rendered = rendered_x(&stepper, predicted);
+        assert!(rendered > simulated && rendered <= rendered_before + 1.0);
+
+        // the offset decays to zero
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .client_app
+            .world
+            .get::<VisualCorrection<Component1>>(predicted)
+            .is_none());
+        let simulated = stepper
+            .client_app
+            .world
+            .get::<Component1>(predicted)
+            .unwrap()
+            .0;
+        assert_eq!(rendered_x(&stepper, predicted), simulated);
+    }
+}
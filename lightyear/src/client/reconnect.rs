@@ -0,0 +1,337 @@
+/*! Automatically reconnect to the server when the connection is lost
+
+If [`ClientConfig::reconnect`] is set and the connection drops unexpectedly (because of a timeout or an io error),
+the client tries to connect again with an exponential backoff between the attempts, using the same [`ClientConfig`].
+
+The disconnections that are expected do not trigger a reconnection:
+- the user called [`disconnect_client`](crate::client::networking::ClientCommands::disconnect_client)
+- the server closed the connection (for example because it was stopped), or refused it
+- the client never managed to connect (see [`ConnectionFallback`](crate::client::fallback::ConnectionFallback) instead)
+
+Connect tokens usually expire, so a new one can be provided for each attempt with [`ReconnectConfig::with_auth`].
+
+Each phase of the reconnection emits a distinct event, so that the UI can display the progress:
+```rust
+# use bevy::prelude::*;
+# use lightyear::prelude::client::{ReconnectFailedEvent, ReconnectedEvent, ReconnectingEvent};
+fn reconnect_screen(
+    mut reconnecting: EventReader<ReconnectingEvent>,
+    mut reconnected: EventReader<ReconnectedEvent>,
+    mut failed: EventReader<ReconnectFailedEvent>,
+) {
+    for event in reconnecting.read() {
+        info!("Connection lost, reconnecting in {:?} (attempt #{})", event.delay, event.attempt);
+    }
+    for event in reconnected.read() {
+        info!("Reconnected after {} attempts", event.attempts);
+    }
+    for event in failed.read() {
+        info!("Could not reconnect after {} attempts", event.attempts);
+    }
+}
+```
+*/
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use tracing::{info, warn};
+
+use crate::client::config::ClientConfig;
+use crate::client::connection::ConnectionManager;
+use crate::client::networking::NetworkingState;
+use crate::connection::client::{Authentication, ClientConnection, NetClient, NetConfig};
+
+/// Provides the [`Authentication`] used for a reconnection attempt.
+///
+/// It is called every frame once the backoff delay has elapsed, until it returns `Some`; this
+/// lets the user request a new connect token asynchronously.
+pub type ReconnectAuthFn = Arc<dyn Fn(&mut World) -> Option<Authentication> + Send + Sync>;
+
+/// Configuration of the automatic reconnection
+#[derive(Clone)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnection attempt
+    pub initial_delay: Duration,
+    /// Maximum delay between two reconnection attempts
+    pub max_delay: Duration,
+    /// Factor by which the delay is multiplied after each failed attempt
+    pub backoff_factor: f32,
+    /// Number of attempts after which the client gives up. If None, the client never gives up
+    pub max_attempts: Option<u32>,
+    /// If set, provides the authentication used for each attempt. Otherwise the authentication
+    /// of [`ClientConfig::net`] is reused
+    pub auth: Option<ReconnectAuthFn>,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            backoff_factor: 2.0,
+            max_attempts: Some(10),
+            auth: None,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    pub fn with_initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn with_backoff_factor(mut self, backoff_factor: f32) -> Self {
+        self.backoff_factor = backoff_factor;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: Option<u32>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Provide the authentication used for each reconnection attempt, for example to request
+    /// a new connect token from the backend.
+    ///
+    /// Only the [`NetConfig::Netcode`] and [`NetConfig::Punched`] connections use an [`Authentication`];
+    /// it is ignored (with a warning) for the other connections.
+    pub fn with_auth(
+        mut self,
+        auth: impl Fn(&mut World) -> Option<Authentication> + Send + Sync + 'static,
+    ) -> Self {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    /// Delay before the reconnection attempt number `attempt` (starting at 1)
+    fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let factor = (self.backoff_factor as f64).powi(exponent);
+        // compute the delay in f64 so that it cannot overflow the Duration after many attempts
+        let delay = self.initial_delay.as_secs_f64() * factor;
+        if delay.is_nan() || delay >= self.max_delay.as_secs_f64() {
+            return self.max_delay;
+        }
+        Duration::from_secs_f64(delay.max(0.0)).min(self.max_delay)
+    }
+}
+
+/// Bevy [`Event`] emitted on the client when the connection was lost and a reconnection attempt is scheduled
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ReconnectingEvent {
+    /// Number of the attempt (starting at 1)
+    pub attempt: u32,
+    /// Delay before the attempt starts
+    pub delay: Duration,
+}
+
+/// Bevy [`Event`] emitted on the client when a reconnection attempt starts
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ReconnectAttemptEvent {
+    /// Number of the attempt (starting at 1)
+    pub attempt: u32,
+}
+
+/// Bevy [`Event`] emitted on the client when the client managed to reconnect
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ReconnectedEvent {
+    /// Number of attempts that were needed
+    pub attempts: u32,
+}
+
+/// Bevy [`Event`] emitted on the client when the client gave up reconnecting
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ReconnectFailedEvent {
+    /// Number of attempts that failed
+    pub attempts: u32,
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct ReconnectState {
+    /// True if the client is connected
+    connected: bool,
+    /// True if the user requested the disconnection
+    cancelled: bool,
+    /// Number of the current reconnection attempt (0 if the client is not reconnecting)
+    attempt: u32,
+    /// Time left before the next reconnection attempt
+    backoff: Option<Timer>,
+}
+
+impl ReconnectState {
+    /// The disconnection was requested by the user, we should not try to reconnect
+    pub(crate) fn cancel(&mut self) {
+        self.cancelled = true;
+        self.reset();
+    }
+
+    fn reset(&mut self) {
+        self.attempt = 0;
+        self.backoff = None;
+    }
+}
+
+pub(crate) fn add_events(app: &mut App) {
+    app.add_event::<ReconnectingEvent>()
+        .add_event::<ReconnectAttemptEvent>()
+        .add_event::<ReconnectedEvent>()
+        .add_event::<ReconnectFailedEvent>();
+}
+
+pub(crate) fn on_connecting(mut state: ResMut<ReconnectState>) {
+    state.cancelled = false;
+    state.backoff = None;
+}
+
+pub(crate) fn on_connected(
+    mut state: ResMut<ReconnectState>,
+    mut events: EventWriter<ReconnectedEvent>,
+) {
+    state.connected = true;
+    if state.attempt > 0 {
+        info!("Reconnected after {} attempts", state.attempt);
+        events.send(ReconnectedEvent {
+            attempts: state.attempt,
+        });
+        state.reset();
+    }
+}
+
+/// If the connection dropped unexpectedly, schedule a reconnection attempt
+///
+/// This must run before the [`ClientConnection`] is disconnected (which loses the cause of the disconnection)
+pub(crate) fn on_disconnected(
+    mut state: ResMut<ReconnectState>,
+    config: Res<ClientConfig>,
+    next_state: Res<NextState<NetworkingState>>,
+    connection_manager: Option<Res<ConnectionManager>>,
+    netclient: Option<Res<ClientConnection>>,
+    mut reconnecting_events: EventWriter<ReconnectingEvent>,
+    mut failed_events: EventWriter<ReconnectFailedEvent>,
+) {
+    let was_connected = std::mem::take(&mut state.connected);
+    let Some(reconnect) = config.reconnect.as_ref() else {
+        return;
+    };
+    if std::mem::take(&mut state.cancelled) {
+        state.reset();
+        return;
+    }
+    // the client never managed to connect
+    if !was_connected && state.attempt == 0 {
+        return;
+    }
+    // the fallback connection configs are being tried
+    if next_state.0.is_some() {
+        return;
+    }
+    let refused = connection_manager.is_some_and(|manager| manager.denied_reason.is_some());
+    let closed_by_server = netclient.is_some_and(|netclient| netclient.closed_by_server());
+    if refused || closed_by_server {
+        if state.attempt > 0 {
+            failed_events.send(ReconnectFailedEvent {
+                attempts: state.attempt,
+            });
+        }
+        state.reset();
+        return;
+    }
+    if reconnect
+        .max_attempts
+        .is_some_and(|max_attempts| state.attempt >= max_attempts)
+    {
+        info!("Could not reconnect after {} attempts", state.attempt);
+        failed_events.send(ReconnectFailedEvent {
+            attempts: state.attempt,
+        });
+        state.reset();
+        return;
+    }
+    state.attempt += 1;
+    let delay = reconnect.delay(state.attempt);
+    info!(
+        "Connection lost, reconnection attempt #{} in {:?}",
+        state.attempt, delay
+    );
+    state.backoff = Some(Timer::new(delay, TimerMode::Once));
+    reconnecting_events.send(ReconnectingEvent {
+        attempt: state.attempt,
+        delay,
+    });
+}
+
+/// Start the reconnection attempt once the backoff delay has elapsed
+pub(crate) fn reconnect(world: &mut World) {
+    let delta = world.resource::<Time<Real>>().delta();
+    let mut state = world.resource_mut::<ReconnectState>();
+    let Some(backoff) = state.backoff.as_mut() else {
+        return;
+    };
+    if !backoff.tick(delta).finished() {
+        return;
+    }
+    let attempt = state.attempt;
+    let auth = world
+        .resource::<ClientConfig>()
+        .reconnect
+        .as_ref()
+        .and_then(|reconnect| reconnect.auth.clone());
+    if let Some(auth) = auth {
+        if matches!(
+            world.resource::<ClientConfig>().net,
+            NetConfig::Netcode { .. } | NetConfig::Punched { .. }
+        ) {
+            // wait until the authentication is available
+            let Some(auth) = auth(world) else {
+                return;
+            };
+            if let NetConfig::Netcode { auth: previous, .. }
+            | NetConfig::Punched { auth: previous, .. } =
+                &mut world.resource_mut::<ClientConfig>().net
+            {
+                *previous = auth;
+            }
+        } else {
+            warn!("ReconnectConfig::auth is ignored because the connection doesn't use an Authentication");
+        }
+    }
+    world.resource_mut::<ReconnectState>().backoff = None;
+    world.send_event(ReconnectAttemptEvent { attempt });
+    world
+        .resource_mut::<NextState<NetworkingState>>()
+        .set(NetworkingState::Connecting);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_delay() {
+        let config = ReconnectConfig::default()
+            .with_initial_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(500));
+        assert_eq!(config.delay(1), Duration::from_millis(100));
+        assert_eq!(config.delay(2), Duration::from_millis(200));
+        assert_eq!(config.delay(3), Duration::from_millis(400));
+        assert_eq!(config.delay(4), Duration::from_millis(500));
+    }
+
+    /// The delay saturates at the maximum delay instead of overflowing after many attempts
+    #[test]
+    fn test_reconnect_delay_many_attempts() {
+        let config = ReconnectConfig::default()
+            .with_initial_delay(Duration::from_millis(500))
+            .with_max_delay(Duration::from_secs(10));
+        assert_eq!(config.delay(67), Duration::from_secs(10));
+        assert_eq!(config.delay(2000), Duration::from_secs(10));
+        assert_eq!(config.delay(u32::MAX), Duration::from_secs(10));
+    }
+}
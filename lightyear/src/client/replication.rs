@@ -3,12 +3,16 @@ use bevy::prelude::*;
 use bevy::utils::Duration;
 
 use crate::client::connection::ConnectionManager;
+use crate::client::disable::DisablePlugin;
 use crate::client::networking::is_connected;
 use crate::client::sync::client_is_synced;
 use crate::prelude::SharedConfig;
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::replication::entity_map::PrePlacedEntities;
 use crate::shared::replication::plugin::receive::ReplicationReceivePlugin;
 use crate::shared::replication::plugin::send::ReplicationSendPlugin;
-use crate::shared::sets::{ClientMarker, InternalReplicationSet};
+use crate::shared::replication::systems;
+use crate::shared::sets::{ClientMarker, InternalMainSet, InternalReplicationSet};
 
 pub(crate) mod receive {
     use super::*;
@@ -19,10 +23,22 @@ pub(crate) mod receive {
 
     impl Plugin for ClientReplicationReceivePlugin {
         fn build(&self, app: &mut App) {
+            let schedules = NetworkScheduleConfig::get(app);
             // PLUGIN
             app.add_plugins(ReplicationReceivePlugin::<ConnectionManager>::new(
                 self.tick_interval,
-            ));
+            ))
+            .add_plugins(DisablePlugin);
+
+            // RESOURCES
+            // only the client links the replicated entities to its pre-placed entities
+            app.init_resource::<PrePlacedEntities>();
+            // SYSTEMS
+            app.add_systems(
+                schedules.receive,
+                systems::update_pre_placed_entities
+                    .before(InternalMainSet::<ClientMarker>::Receive),
+            );
 
             // TODO: currently we only support pre-spawned entities spawned during the FixedUpdate schedule
             // // SYSTEM SETS
@@ -33,7 +49,7 @@ pub(crate) mod receive {
             // )
 
             app.configure_sets(
-                PostUpdate,
+                schedules.send,
                 // only replicate entities once client is synced
                 // NOTE: we need is_synced, and not connected. Otherwise the ticks associated with the messages might be incorrect
                 //  and the message might be ignored by the server
@@ -54,21 +70,18 @@ pub(crate) mod send {
     use crate::client::interpolation::Interpolated;
     use crate::client::prediction::Predicted;
     use crate::connection::client::ClientConnection;
-    use crate::connection::server::ServerConnections;
     use crate::prelude::client::NetClient;
-    use crate::prelude::server::{ControlledBy, ServerConfig, ServerReplicationSet};
     use crate::prelude::{
         ClientId, ComponentRegistry, DisabledComponent, OverrideTargetComponent, PrePredicted,
-        ReplicateHierarchy, ReplicateOnceComponent, Replicated, ReplicationGroup,
+        ReplicateHierarchy, ReplicateIf, ReplicateOnceComponent, Replicated, ReplicationGroup,
         ShouldBePredicted, TargetEntity, VisibilityMode,
     };
-    use crate::server::events::EntitySpawnEvent;
-    use crate::server::replication::send::SyncTarget;
-    use crate::server::visibility::immediate::{ClientVisibility, ReplicateVisibility};
     use crate::shared::replication::components::{
-        Controlled, DespawnTracker, Replicating, ReplicationTarget, ShouldBeInterpolated,
+        Controlled, DespawnTracker, RefreshComponent, RejectedUpdates, Replicating,
+        ReplicationTarget, ShouldBeInterpolated,
     };
     use crate::shared::replication::network_target::NetworkTarget;
+    use crate::shared::replication::systems::remove_refresh_markers;
     use crate::shared::replication::{systems, ReplicationSend};
     use crate::shared::sets::ServerMarker;
     use bevy::ecs::entity::Entities;
@@ -81,6 +94,7 @@ pub(crate) mod send {
 
     impl Plugin for ClientReplicationSendPlugin {
         fn build(&self, app: &mut App) {
+            let schedules = NetworkScheduleConfig::get(app);
             app
                 // REFLECTION
                 .register_type::<Replicate>()
@@ -90,7 +104,7 @@ pub(crate) mod send {
                 ))
                 // SETS
                 .configure_sets(
-                    PostUpdate,
+                    schedules.send,
                     // only replicate entities once client is synced
                     // NOTE: we need is_synced, and not connected. Otherwise the ticks associated with the messages might be incorrect
                     //  and the message might be ignored by the server
@@ -104,7 +118,7 @@ pub(crate) mod send {
                 )
                 // SYSTEMS
                 .add_systems(
-                    PostUpdate,
+                    schedules.send,
                     (
                         // NOTE: we need to run `send_entity_despawn` once per frame (and not once per send_interval)
                         //  because the RemovedComponents Events are present only for 1 frame and we might miss them if we don't run this every frame
@@ -119,8 +133,10 @@ pub(crate) mod send {
                         ),
                         handle_replicating_add
                             .in_set(InternalReplicationSet::<ClientMarker>::AfterBuffer),
-                        add_replicated_component_host_server
-                            .run_if(SharedConfig::is_host_server_condition),
+                        add_replicated_component_host_server.run_if(
+                            SharedConfig::is_host_server_condition
+                                .and_then(resource_exists::<ClientConnection>),
+                        ),
                     ),
                 );
         }
@@ -289,9 +305,11 @@ pub(crate) mod send {
                 ?entity,
                 "send entity despawn because ReplicationToServerTarget was removed"
             );
-            sender
-                .replication_sender
-                .prepare_entity_despawn(entity, group.group_id(Some(entity)));
+            sender.replication_sender.prepare_entity_despawn(
+                entity,
+                group.group_id(Some(entity)),
+                None,
+            );
         });
 
         // Despawn entities when the entity gets despawned on local world
@@ -305,6 +323,7 @@ pub(crate) mod send {
                 sender.replication_sender.prepare_entity_despawn(
                     entity,
                     replicate_cache.replication_group.group_id(Some(entity)),
+                    None,
                 );
             }
         }
@@ -327,20 +346,34 @@ pub(crate) mod send {
                 &ReplicationGroup,
                 Has<DisabledComponent<C>>,
                 Has<ReplicateOnceComponent<C>>,
+                Has<RefreshComponent<C>>,
+                Option<&ReplicateIf<C>>,
             ),
             With<Replicating>,
         >,
         system_bevy_ticks: SystemChangeTick,
         mut sender: ResMut<ConnectionManager>,
+        mut rejected_updates: Local<RejectedUpdates>,
     ) {
         let kind = registry.net_id::<C>();
+        rejected_updates.retain(|entity| query.contains(entity));
         query.iter().for_each(
-            |(entity, component, target, group, disabled, replicate_once)| {
+            |(
+                entity,
+                component,
+                target,
+                group,
+                disabled,
+                replicate_once,
+                refresh,
+                replicate_if,
+            )| {
                 // do not replicate components that are disabled
                 if disabled {
                     return;
                 }
                 let (mut insert, mut update) = (false, false);
+                let change_tick = component.last_changed();
 
                 // send a component_insert for components that were newly added
                 // or if we start replicating the entity
@@ -349,13 +382,24 @@ pub(crate) mod send {
                     insert = true;
                 } else {
                     // do not send updates for these components, only inserts/removes
-                    if replicate_once {
+                    // (unless the component was explicitly refreshed)
+                    if replicate_once && !refresh {
                         trace!(?entity,
                         "not replicating updates for {:?} because it is marked as replicate_once",
                         kind
                     );
                         return;
                     }
+                    // do not send updates that are rejected by the replication predicate,
+                    // except for the first rejected value
+                    if !refresh {
+                        let rejected = replicate_if.is_some_and(|replicate_if| {
+                            !replicate_if.should_replicate(component.as_ref())
+                        });
+                        if !rejected_updates.should_send(entity, rejected, change_tick) {
+                            return;
+                        }
+                    }
                     // otherwise send an update for all components that changed since the
                     // last update we have ack-ed
                     update = true;
@@ -384,12 +428,10 @@ pub(crate) mod send {
 
                         // send the update for all changes newer than the last ack bevy tick for the group
                         if collect_changes_since_this_tick.map_or(true, |c| {
-                            component
-                                .last_changed()
-                                .is_newer_than(c, system_bevy_ticks.this_run())
+                            change_tick.is_newer_than(c, system_bevy_ticks.this_run())
                         }) {
                             trace!(
-                                change_tick = ?component.last_changed(),
+                                ?change_tick,
                                 ?collect_changes_since_this_tick,
                                 current_tick = ?system_bevy_ticks.this_run(),
                                 "prepare entity update changed check"
@@ -439,7 +481,7 @@ pub(crate) mod send {
 
     pub(crate) fn register_replicate_component_send<C: Component>(app: &mut App) {
         app.add_systems(
-            PostUpdate,
+            NetworkScheduleConfig::get(app).send,
             (
                 // NOTE: we need to run `send_component_removed` once per frame (and not once per send_interval)
                 //  because the RemovedComponents Events are present only for 1 frame and we might miss them if we don't run this every frame
@@ -450,11 +492,13 @@ pub(crate) mod send {
                 //  and use up all the bandwidth
                 send_component_update::<C>
                     .in_set(InternalReplicationSet::<ClientMarker>::BufferComponentUpdates),
+                remove_refresh_markers::<C>
+                    .in_set(InternalReplicationSet::<ClientMarker>::AfterBuffer),
             ),
         );
     }
 
-    #[cfg(test)]
+    #[cfg(all(test, feature = "client", feature = "server"))]
     mod tests {
         use crate::prelude::{client, server, ClientId};
         use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
@@ -502,10 +546,11 @@ pub(crate) mod commands {
     use bevy::prelude::{Entity, World};
 
     fn despawn_without_replication(entity: Entity, world: &mut World) {
-        let mut sender = world.resource_mut::<ConnectionManager>();
         // remove the entity from the cache of entities that are being replicated
         // so that if it gets despawned, the despawn won't be replicated
-        sender.replicate_component_cache.remove(&entity);
+        if let Some(mut sender) = world.get_resource_mut::<ConnectionManager>() {
+            sender.replicate_component_cache.remove(&entity);
+        }
         world.despawn(entity);
     }
 
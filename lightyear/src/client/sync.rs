@@ -1,7 +1,7 @@
 /*! Handles syncing the time between the client and the server
 */
 use bevy::prelude::{Reflect, Res, SystemSet};
-use bevy::utils::Duration;
+use bevy::utils::{Duration, HashMap};
 use chrono::Duration as ChronoDuration;
 use tracing::{debug, info, trace};
 
@@ -9,6 +9,7 @@ use crate::client::connection::ConnectionManager;
 use crate::client::interpolation::plugin::InterpolationDelay;
 use crate::packet::packet::PacketId;
 use crate::shared::ping::manager::PingManager;
+use crate::shared::replication::components::ReplicationGroupId;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::tick_manager::{Tick, TickEvent};
 use crate::shared::time_manager::{TimeManager, WrappedTime};
@@ -51,6 +52,17 @@ pub struct SyncConfig {
     // TODO: instead of constant speedup_factor, the speedup should be linear w.r.t the offset
     /// By how much should we speed up the simulation to make ticks stay in sync with server?
     pub speedup_factor: f32,
+    /// Fraction of a tick by which the client prediction time is deliberately offset ahead of the
+    /// time at which the server needs the client's inputs.
+    ///
+    /// This can be used to pipeline the client and server simulations: for example with an offset of 0.5,
+    /// the inputs for tick T are produced and sent half a tick earlier than strictly necessary, so
+    /// that they arrive on the server in the middle of the server's previous tick instead of right at the edge.
+    /// The achieved offset can be measured with [`ConnectionManager::input_arrival_margin`].
+    ///
+    /// The precision with which the offset is maintained is given by `error_margin`, so it should be set
+    /// to a value smaller than the offset.
+    pub tick_phase_offset: f32,
 
     // Integration
     server_time_estimate_smoothing: f32,
@@ -65,6 +77,7 @@ impl Default for SyncConfig {
             error_margin: 0.5,
             max_error_margin: 5.0,
             speedup_factor: 1.05,
+            tick_phase_offset: 0.0,
             // server_time_estimate_smoothing: 0.0,
             server_time_estimate_smoothing: 0.2,
         }
@@ -76,6 +89,11 @@ impl SyncConfig {
         self.speedup_factor = speedup_factor;
         self
     }
+
+    pub fn tick_phase_offset(mut self, tick_phase_offset: f32) -> Self {
+        self.tick_phase_offset = tick_phase_offset;
+        self
+    }
 }
 
 #[derive(Default)]
@@ -116,6 +134,12 @@ pub struct SyncManager {
     /// The Tick associated with the 'server_tick_generation' (it might not be the same as latest_received_server_tick
     /// because we update the generation only from pong messages)
     pub(crate) server_pong_tick: Tick,
+    /// Amount of overstep that must be discarded from the fixed timestep to snap the prediction time
+    /// to its objective with sub-tick precision
+    pub(crate) overstep_discard: Option<Duration>,
+    /// Send intervals of the replication groups that the server announced to send less often than its
+    /// send interval
+    pub(crate) group_send_intervals: HashMap<ReplicationGroupId, Duration>,
 }
 
 // TODO: split into PredictionTime Manager, InterpolationTime Manager
@@ -135,6 +159,8 @@ impl SyncManager {
             new_latest_received_server_tick: false,
             server_pong_generation: 0,
             server_pong_tick: Tick(0),
+            overstep_discard: None,
+            group_send_intervals: HashMap::default(),
         }
     }
 
@@ -217,10 +243,29 @@ impl SyncManager {
         }
     }
 
+    /// The interval between the updates of the replication group: the configured `server_send_interval`,
+    /// unless the server announced that the group is sent less often.
+    ///
+    /// The interpolation time only depends on the configured `server_send_interval`.
+    pub(crate) fn group_send_interval(
+        &self,
+        group_id: Option<ReplicationGroupId>,
+        configured: Duration,
+    ) -> Duration {
+        group_id
+            .and_then(|group_id| self.group_send_intervals.get(&group_id))
+            .map_or(configured, |interval| (*interval).max(configured))
+    }
+
     pub(crate) fn is_synced(&self) -> bool {
         self.synced
     }
 
+    /// Returns the amount of overstep that must be discarded from the fixed timestep, if any
+    pub(crate) fn take_overstep_discard(&mut self) -> Option<Duration> {
+        self.overstep_discard.take()
+    }
+
     /// Compute the current client time; we will make sure that the client tick is ahead of the server tick
     /// Even if it is wrapped around.
     /// (i.e. if client tick is 1, and server tick is 65535, we act as if the client tick was 65537)
@@ -270,6 +315,41 @@ impl SyncManager {
         self.server_time_estimate
     }
 
+    /// By how many ticks (including the fractional part) the client prediction time is ahead of
+    /// the estimated current server time
+    pub(crate) fn prediction_tick_offset(
+        &self,
+        tick_manager: &TickManager,
+        time_manager: &TimeManager,
+    ) -> Option<f32> {
+        if !self.is_synced() {
+            return None;
+        }
+        let offset =
+            self.current_prediction_time(tick_manager, time_manager) - self.server_time_estimate();
+        Some(Self::to_ticks(offset, tick_manager.config.tick_duration))
+    }
+
+    /// By how many ticks (including the fractional part) the inputs we send now will arrive on the server
+    /// before the server needs them
+    pub(crate) fn input_arrival_margin(
+        &self,
+        tick_manager: &TickManager,
+        time_manager: &TimeManager,
+        ping_manager: &PingManager,
+    ) -> Option<f32> {
+        if !self.is_synced() {
+            return None;
+        }
+        let margin = self.current_prediction_time(tick_manager, time_manager)
+            - self.predicted_server_receive_time(ping_manager.rtt());
+        Some(Self::to_ticks(margin, tick_manager.config.tick_duration))
+    }
+
+    fn to_ticks(duration: ChronoDuration, tick_duration: Duration) -> f32 {
+        duration.num_nanoseconds().unwrap_or_default() as f32 / tick_duration.as_nanos() as f32
+    }
+
     fn server_latest_tick_generation(&self) -> u16 {
         // check if the latest_server_tick has crossed a generation compared to the latest pong tick
         if self.latest_received_server_tick.unwrap().0 < self.server_pong_tick.0 {
@@ -328,9 +408,11 @@ impl SyncManager {
         // TODO: do we need to make sure that the client time is ahead of the server time?
         //  we might have some weird interpolation issues if this is not the case
         let input_delay = tick_duration * input_delay_ticks as u32;
+        let phase_offset = tick_duration.mul_f32(self.config.tick_phase_offset);
         ChronoDuration::nanoseconds(
             jitter.as_nanos() as i64 * self.config.jitter_multiple_margin as i64
                 + tick_duration.as_nanos() as i64 * self.config.tick_margin as i64
+                + phase_offset.as_nanos() as i64
                 - input_delay.as_nanos() as i64,
         )
     }
@@ -565,6 +647,16 @@ impl SyncManager {
         let client_ideal_tick =
             Tick((client_ideal_time.elapsed.as_nanos() / tick_duration.as_nanos()) as u16);
 
+        // sub-tick control: the remainder of the ideal time should end up in the overstep.
+        // We can only discard overstep, so if the current overstep is too small we let the
+        // speed adjustments catch up
+        let ideal_overstep = client_ideal_time.tick_overstep(tick_duration);
+        let current_overstep = time_manager.overstep();
+        if current_overstep > ideal_overstep {
+            self.overstep_discard = Some(tick_duration.mul_f32(current_overstep - ideal_overstep));
+            time_manager.update_overstep(ideal_overstep);
+        }
+
         let delta_tick = client_ideal_tick - tick_manager.tick();
         // Update client ticks
         if rtt != Duration::default() {
@@ -586,7 +678,7 @@ impl SyncManager {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use bevy::prelude::*;
     use bevy::utils::Duration;
@@ -698,4 +790,52 @@ mod tests {
             &Component1(1.0)
         );
     }
+
+    fn input_arrival_margin(tick_phase_offset: f32) -> f32 {
+        let frame_duration = Duration::from_millis(10);
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        };
+        let link_conditioner = LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(20),
+            incoming_jitter: Duration::from_millis(0),
+            incoming_loss: 0.0,
+        };
+        let sync_config = SyncConfig {
+            error_margin: 0.1,
+            ..default()
+        }
+        .tick_phase_offset(tick_phase_offset);
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            sync_config,
+            client::PredictionConfig::default(),
+            client::InterpolationConfig::default(),
+            link_conditioner,
+            frame_duration,
+        );
+        stepper.init();
+        for _ in 0..200 {
+            stepper.frame_step();
+        }
+        let world = &stepper.client_app.world;
+        let connection = world.resource::<client::ConnectionManager>();
+        let tick_manager = world.resource::<TickManager>();
+        let time_manager = world.resource::<TimeManager>();
+        assert!(connection
+            .prediction_tick_offset(tick_manager, time_manager)
+            .is_some_and(|offset| offset > 0.0));
+        connection
+            .input_arrival_margin(tick_manager, time_manager)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tick_phase_offset() {
+        let margin = input_arrival_margin(0.0);
+        let offset_margin = input_arrival_margin(0.5);
+        assert!((offset_margin - margin - 0.5).abs() < 0.2);
+    }
 }
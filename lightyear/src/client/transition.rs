@@ -0,0 +1,332 @@
+/*! Smooth the visual transition when an entity switches between interpolation and prediction
+
+When the [`SyncTarget`](crate::prelude::server::SyncTarget) of an entity is updated on the server so that a client
+starts predicting an entity that it was interpolating (or the other way around), the client spawns a new
+[`Predicted`] (or [`Interpolated`]) entity that replaces the previous one, which is despawned.
+
+The two entities usually don't have the same state at the time of the switch (the interpolated entity lags behind the
+server, while the predicted entity is ahead of the server), so the entity would visually snap to a new position.
+If [`ClientConfig::sync_transition`] is set, the visual state of the previous entity is frozen at the time of the switch
+and the components of the new entity are visually blended from that frozen state to the state computed by the new
+driver (prediction or interpolation) over [`SyncTransitionConfig::duration`].
+
+Like the prediction correction, the blending only affects the visual state: the component's true value is restored at
+the start of every frame. The blending is done with the interpolation function registered for the component, so only
+components that have an interpolation function are blended; the other components snap to their new value.
+*/
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::utils::Duration;
+use tracing::debug;
+
+use crate::client::components::SyncComponent;
+use crate::client::config::ClientConfig;
+use crate::client::easings::ease_out_quad;
+use crate::client::interpolation::Interpolated;
+use crate::client::prediction::Predicted;
+use crate::prelude::client::{InterpolationSet, PredictionSet};
+use crate::prelude::ComponentRegistry;
+
+/// Configuration of the visual transition when an entity switches between interpolation and prediction
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct SyncTransitionConfig {
+    /// Duration over which the visual state is blended from the previous entity to the new entity
+    pub duration: Duration,
+}
+
+impl Default for SyncTransitionConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_millis(200),
+        }
+    }
+}
+
+impl SyncTransitionConfig {
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum SyncTransitionSet {
+    // PreUpdate Sets
+    /// Restore the true component values
+    Restore,
+    // PostUpdate Sets
+    /// Freeze the visual state of the entities that are being replaced
+    Start,
+    /// Despawn the entities that were replaced
+    DespawnPrevious,
+    /// Blend the visual state from the frozen state to the new driver's state
+    Blend,
+}
+
+/// Marker added on a new [`Predicted`] or [`Interpolated`] entity that replaces the entity `0`
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PreviousSyncEntity(pub(crate) Entity);
+
+/// Visual transition of the component `C` from the state of the entity that was replaced
+#[derive(Component, Debug)]
+#[component(storage = "SparseSet")]
+pub struct SyncTransition<C: Component> {
+    /// Visual value of the component on the previous entity at the time of the switch
+    pub start: C,
+    /// Time (elapsed since the start of the app) at which the switch happened
+    pub started_at: Duration,
+    /// True value of the component, that will be restored at the start of the next frame
+    pub current: Option<C>,
+}
+
+pub(crate) struct SyncTransitionPlugin;
+
+impl Plugin for SyncTransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(
+            PreUpdate,
+            // restore the true value before we check for rollbacks
+            SyncTransitionSet::Restore.before(PredictionSet::CheckRollback),
+        );
+        app.configure_sets(
+            PostUpdate,
+            (
+                SyncTransitionSet::Start,
+                SyncTransitionSet::DespawnPrevious,
+                SyncTransitionSet::Blend
+                    .after(PredictionSet::VisualCorrection)
+                    .after(InterpolationSet::VisualInterpolation)
+                    .before(TransformSystem::TransformPropagate),
+            )
+                .chain(),
+        );
+        app.add_systems(
+            PostUpdate,
+            despawn_previous_sync_entity.in_set(SyncTransitionSet::DespawnPrevious),
+        );
+    }
+}
+
+/// Add the systems that handle the visual transition of the component `C`
+pub(crate) fn add_sync_transition_systems<C: SyncComponent>(app: &mut App) {
+    app.add_systems(
+        PreUpdate,
+        restore_sync_transition::<C>.in_set(SyncTransitionSet::Restore),
+    );
+    app.add_systems(
+        PostUpdate,
+        (
+            start_sync_transition::<C>.in_set(SyncTransitionSet::Start),
+            blend_sync_transition::<C>.in_set(SyncTransitionSet::Blend),
+        ),
+    );
+}
+
+/// Returns true if the entity was already predicted or interpolated before this frame, in which case the
+/// new [`Predicted`] or [`Interpolated`] entity replaces it
+pub(crate) fn is_switch<T: Component>(query: &Query<Ref<T>>, previous: Entity) -> bool {
+    query.get(previous).is_ok_and(|marker| !marker.is_added())
+}
+
+/// Freeze the visual state of the entity that is being replaced
+fn start_sync_transition<C: SyncComponent>(
+    config: Res<ClientConfig>,
+    component_registry: Res<ComponentRegistry>,
+    time: Res<Time>,
+    mut commands: Commands,
+    new_entities: Query<(Entity, &PreviousSyncEntity)>,
+    components: Query<&C, Or<(With<Predicted>, With<Interpolated>)>>,
+) {
+    if config.sync_transition.is_none() || !component_registry.has_interpolation_fn::<C>() {
+        return;
+    }
+    for (entity, previous) in new_entities.iter() {
+        if let Ok(start) = components.get(previous.0) {
+            debug!(?entity, previous = ?previous.0, "starting sync transition for {:?}", std::any::type_name::<C>());
+            commands.entity(entity).insert(SyncTransition {
+                start: start.clone(),
+                started_at: time.elapsed(),
+                current: None,
+            });
+        }
+    }
+}
+
+fn despawn_previous_sync_entity(
+    mut commands: Commands,
+    new_entities: Query<(Entity, &PreviousSyncEntity)>,
+) {
+    for (entity, previous) in new_entities.iter() {
+        if let Some(previous_entity) = commands.get_entity(previous.0) {
+            previous_entity.despawn_recursive();
+        }
+        commands.entity(entity).remove::<PreviousSyncEntity>();
+    }
+}
+
+/// Visually blend the component from the frozen state to the state computed by the new driver
+fn blend_sync_transition<C: SyncComponent>(
+    config: Res<ClientConfig>,
+    component_registry: Res<ComponentRegistry>,
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut C, &mut SyncTransition<C>)>,
+) {
+    let duration = config.sync_transition.unwrap_or_default().duration;
+    for (entity, mut component, mut transition) in query.iter_mut() {
+        let elapsed = time.elapsed().saturating_sub(transition.started_at);
+        if elapsed >= duration {
+            debug!(
+                ?entity,
+                "sync transition is over for {:?}",
+                std::any::type_name::<C>()
+            );
+            commands.entity(entity).remove::<SyncTransition<C>>();
+            continue;
+        }
+        let t = ease_out_quad(elapsed.as_secs_f32() / duration.as_secs_f32());
+        let visual = component_registry.interpolate(&transition.start, component.as_ref(), t);
+        transition.current = Some(std::mem::replace(
+            component.bypass_change_detection(),
+            visual,
+        ));
+    }
+}
+
+/// At the start of the frame, restore the true value of the component
+fn restore_sync_transition<C: SyncComponent>(mut query: Query<(&mut C, &mut SyncTransition<C>)>) {
+    for (mut component, mut transition) in query.iter_mut() {
+        if let Some(current) = transition.current.take() {
+            *component.bypass_change_detection() = current;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::default;
+
+    use crate::client::components::Confirmed;
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{client, NetworkTarget};
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    #[test]
+    fn test_switch_interpolation_to_prediction() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ClientConfig>()
+            .sync_transition = Some(SyncTransitionConfig::default());
+
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((
+                Component1(0.0),
+                Replicate {
+                    sync: SyncTarget {
+                        interpolation: NetworkTarget::All,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ))
+            .id();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        let confirmed_entity = *stepper
+            .client_app
+            .world
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        let interpolated_entity = stepper
+            .client_app
+            .world
+            .get::<Confirmed>(confirmed_entity)
+            .unwrap()
+            .interpolated
+            .expect("entity should be interpolated");
+
+        // switch the entity to prediction, while the component changes on the server
+        stepper.server_app.world.entity_mut(server_entity).insert((
+            Component1(10.0),
+            SyncTarget {
+                prediction: NetworkTarget::All,
+                ..default()
+            },
+        ));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let confirmed = stepper
+            .client_app
+            .world
+            .get::<Confirmed>(confirmed_entity)
+            .unwrap();
+        assert!(confirmed.interpolated.is_none());
+        let predicted_entity = confirmed.predicted.expect("entity should be predicted");
+        // the interpolated entity was replaced by the predicted entity
+        assert!(stepper
+            .client_app
+            .world
+            .get_entity(interpolated_entity)
+            .is_none());
+        assert!(stepper
+            .client_app
+            .world
+            .get::<Component1>(predicted_entity)
+            .is_some());
+        // the predicted entity is displayed with the frozen value of the interpolated entity
+        let transition = stepper
+            .client_app
+            .world
+            .get::<SyncTransition<Component1>>(predicted_entity)
+            .expect("the predicted entity should be transitioning");
+        assert_eq!(transition.start, Component1(0.0));
+        assert_eq!(transition.current, Some(Component1(10.0)));
+        assert_eq!(
+            stepper.client_app.world.get::<Component1>(predicted_entity),
+            Some(&Component1(0.0))
+        );
+
+        // the visual value is then blended towards the predicted value
+        let mut previous_visual = 0.0;
+        for _ in 0..2 {
+            stepper.frame_step();
+            let visual = stepper
+                .client_app
+                .world
+                .get::<Component1>(predicted_entity)
+                .unwrap()
+                .0;
+            assert!(
+                visual > previous_visual && visual < 10.0,
+                "visual value: {visual}"
+            );
+            previous_visual = visual;
+        }
+
+        // once the transition is over, the component keeps its predicted value
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .client_app
+            .world
+            .get::<SyncTransition<Component1>>(predicted_entity)
+            .is_none());
+        assert_eq!(
+            stepper.client_app.world.get::<Component1>(predicted_entity),
+            Some(&Component1(10.0))
+        );
+    }
+}
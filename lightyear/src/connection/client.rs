@@ -2,7 +2,7 @@ use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::{NextState, Reflect, ResMut, Resource};
 use enum_dispatch::enum_dispatch;
@@ -20,6 +20,7 @@ use crate::packet::packet::Packet;
 use crate::prelude::client::ClientTransport;
 use crate::prelude::{generate_key, Key, LinkConditionerConfig};
 use crate::transport::config::SharedIoConfig;
+use crate::transport::{Delivery, LOCAL_SOCKET};
 
 // TODO: add diagnostics methods?
 #[enum_dispatch]
@@ -44,6 +45,17 @@ pub trait NetClient: Send + Sync {
     /// Send a packet to the server
     fn send(&mut self, buf: &[u8]) -> Result<()>;
 
+    /// Send a packet to the server, using the given [`Delivery`] method if the transport supports it
+    fn send_with_delivery(&mut self, buf: &[u8], delivery: Delivery) -> Result<()> {
+        let _ = delivery;
+        self.send(buf)
+    }
+
+    /// Returns true if the server closed the connection (as opposed to the connection being lost)
+    fn closed_by_server(&self) -> bool {
+        false
+    }
+
     /// Get the id of the client
     fn id(&self) -> ClientId;
 
@@ -63,6 +75,9 @@ pub(crate) enum NetClientDispatch {
     #[cfg(all(feature = "steam", not(target_family = "wasm")))]
     Steam(super::steam::client::Client),
     Local(super::local::client::Client),
+    Punched(super::punch::client::Client),
+    #[cfg(any(test, feature = "test-utils"))]
+    Mock(super::mock::client::MockClient),
 }
 
 /// Resource that holds a [`NetClient`] instance.
@@ -95,6 +110,36 @@ pub enum NetConfig {
     Local {
         id: u64,
     },
+    /// Netcode connection to a server that is behind a NAT.
+    ///
+    /// The address of the server is obtained from the [`RendezvousServer`](crate::prelude::RendezvousServer)
+    /// at `rendezvous_addr`; the server must have registered with the same `room_code`.
+    /// (With `Authentication::Manual`, the `server_addr` is replaced by the address provided by the rendezvous server)
+    Punched {
+        #[reflect(ignore, default = "default_rendezvous_addr")]
+        rendezvous_addr: SocketAddr,
+        room_code: String,
+        #[reflect(ignore)]
+        auth: Authentication,
+        config: NetcodeConfig,
+        #[reflect(ignore)]
+        io: IoConfig,
+    },
+    /// Connection scripted by the test, that doesn't use any sockets
+    #[cfg(any(test, feature = "test-utils"))]
+    Mock {
+        #[reflect(ignore, default = "default_mock_connection")]
+        connection: super::mock::client::MockClientConnection,
+    },
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+fn default_mock_connection() -> super::mock::client::MockClientConnection {
+    super::mock::client::MockClientConnection::new(ClientId::Local(0))
+}
+
+fn default_rendezvous_addr() -> SocketAddr {
+    LOCAL_SOCKET
 }
 
 impl Default for NetConfig {
@@ -108,8 +153,8 @@ impl Default for NetConfig {
 }
 
 impl NetConfig {
-    pub fn build_client(self) -> ClientConnection {
-        match self {
+    pub fn build_client(self) -> Result<ClientConnection> {
+        Ok(match self {
             NetConfig::Netcode {
                 auth,
                 config,
@@ -149,7 +194,25 @@ impl NetConfig {
                     client: NetClientDispatch::Local(client),
                 }
             }
-        }
+            NetConfig::Punched {
+                rendezvous_addr,
+                room_code,
+                auth,
+                config,
+                io,
+            } => {
+                let client =
+                    super::punch::client::Client::new(rendezvous_addr, room_code, auth, config, io)
+                        .context("could not create punched client")?;
+                ClientConnection {
+                    client: NetClientDispatch::Punched(client),
+                }
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            NetConfig::Mock { connection } => ClientConnection {
+                client: NetClientDispatch::Mock(super::mock::client::MockClient::new(connection)),
+            },
+        })
     }
 }
 
@@ -178,6 +241,14 @@ impl NetClient for ClientConnection {
         self.client.send(buf)
     }
 
+    fn send_with_delivery(&mut self, buf: &[u8], delivery: Delivery) -> Result<()> {
+        self.client.send_with_delivery(buf, delivery)
+    }
+
+    fn closed_by_server(&self) -> bool {
+        self.client.closed_by_server()
+    }
+
     fn id(&self) -> ClientId {
         self.client.id()
     }
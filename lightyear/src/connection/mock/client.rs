@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use bevy::utils::Duration;
+use parking_lot::Mutex;
+use tracing::error;
+
+use crate::client::io::config::ClientTransport;
+use crate::client::io::{ClientIoEvent, ClientIoEventReceiver, Io};
+use crate::client::networking::NetworkingState;
+use crate::connection::client::{IoConfig, NetClient};
+use crate::packet::packet::Packet;
+use crate::prelude::ClientId;
+use crate::serialize::bitcode::reader::BitcodeReader;
+use crate::serialize::reader::ReadBuffer;
+use crate::transport::LOCAL_SOCKET;
+
+#[derive(Debug)]
+struct MockClientState {
+    id: ClientId,
+    state: NetworkingState,
+    manual_connect: bool,
+    timeout: Option<Duration>,
+    elapsed: Duration,
+    last_received: Duration,
+    connect_error: Option<String>,
+    send_error: Option<String>,
+    io_events: Option<async_channel::Sender<ClientIoEvent>>,
+    received: VecDeque<Vec<u8>>,
+    sent: Vec<Vec<u8>>,
+}
+
+/// [`NetClient`] whose behaviour is scripted by the test.
+///
+/// All the clones of a `MockClientConnection` share the same connection.
+#[derive(Debug, Clone)]
+pub struct MockClientConnection {
+    inner: Arc<Mutex<MockClientState>>,
+}
+
+impl MockClientConnection {
+    /// By default the connection is established as soon as the client connects
+    pub fn new(id: ClientId) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(MockClientState {
+                id,
+                state: NetworkingState::Disconnected,
+                manual_connect: false,
+                timeout: None,
+                elapsed: Duration::ZERO,
+                last_received: Duration::ZERO,
+                connect_error: None,
+                send_error: None,
+                io_events: None,
+                received: VecDeque::new(),
+                sent: Vec::new(),
+            })),
+        }
+    }
+
+    /// The client stays in [`NetworkingState::Connecting`] until [`accept`](Self::accept) is called
+    pub fn with_manual_connect(self) -> Self {
+        self.inner.lock().manual_connect = true;
+        self
+    }
+
+    /// The client disconnects if it doesn't receive any packet for `timeout`
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.inner.lock().timeout = Some(timeout);
+        self
+    }
+
+    /// Finish establishing a connection started with [`with_manual_connect`](Self::with_manual_connect)
+    pub fn accept(&self) {
+        let mut inner = self.inner.lock();
+        if inner.state == NetworkingState::Connecting {
+            inner.state = NetworkingState::Connected;
+            inner.last_received = inner.elapsed;
+        }
+    }
+
+    /// The next call to `connect` fails with this error
+    pub fn fail_next_connect(&self, error: impl Into<String>) {
+        self.inner.lock().connect_error = Some(error.into());
+    }
+
+    /// The next packet sent fails with this error
+    pub fn fail_next_send(&self, error: impl Into<String>) {
+        self.inner.lock().send_error = Some(error.into());
+    }
+
+    /// The io of the client fails with this error, which disconnects the client.
+    ///
+    /// Does nothing if the client is not connecting or connected.
+    pub fn fail_io(&self, error: impl Into<String>) {
+        if let Some(events) = self.inner.lock().io_events.as_ref() {
+            let _ = events.try_send(ClientIoEvent::Disconnected(
+                std::io::Error::other(error.into()).into(),
+            ));
+        }
+    }
+
+    /// Disconnect the client as if the server closed the connection
+    pub fn disconnect_from_server(&self) {
+        self.inner.lock().state = NetworkingState::Disconnected;
+    }
+
+    /// Queue a packet (encoded with [`Packet::encode`]) that will be received by the client
+    pub fn push_received(&self, packet: impl Into<Vec<u8>>) {
+        let mut inner = self.inner.lock();
+        inner.received.push_back(packet.into());
+        inner.last_received = inner.elapsed;
+    }
+
+    /// Take the packets sent by the client since the last call
+    pub fn take_sent(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.inner.lock().sent)
+    }
+
+    /// Total time elapsed in the updates of the connection
+    pub fn elapsed(&self) -> Duration {
+        self.inner.lock().elapsed
+    }
+}
+
+/// The [`NetClient`] built from a [`MockClientConnection`]; it owns the io of the connection
+pub(crate) struct MockClient {
+    connection: MockClientConnection,
+    io: Option<Io>,
+}
+
+impl MockClient {
+    pub(crate) fn new(connection: MockClientConnection) -> Self {
+        Self {
+            connection,
+            io: None,
+        }
+    }
+}
+
+impl NetClient for MockClient {
+    fn connect(&mut self) -> Result<()> {
+        let mut inner = self.connection.inner.lock();
+        if let Some(error) = inner.connect_error.take() {
+            return Err(anyhow!(error));
+        }
+        // the io doesn't send or receive anything, it only reports the io events scripted by the test
+        let mut io = IoConfig::from_transport(ClientTransport::Dummy).connect()?;
+        let (events_sender, events_receiver) = async_channel::unbounded();
+        io.context.event_receiver = Some(ClientIoEventReceiver(events_receiver));
+        inner.io_events = Some(events_sender);
+        self.io = Some(io);
+        inner.state = if inner.manual_connect {
+            NetworkingState::Connecting
+        } else {
+            NetworkingState::Connected
+        };
+        inner.last_received = inner.elapsed;
+        Ok(())
+    }
+
+    fn disconnect(&mut self) -> Result<()> {
+        let mut inner = self.connection.inner.lock();
+        inner.state = NetworkingState::Disconnected;
+        inner.io_events = None;
+        self.io = None;
+        Ok(())
+    }
+
+    fn state(&self) -> NetworkingState {
+        self.connection.inner.lock().state
+    }
+
+    fn try_update(&mut self, delta_ms: f64) -> Result<()> {
+        let mut inner = self.connection.inner.lock();
+        // the networking systems provide the delta in seconds
+        inner.elapsed += Duration::from_secs_f64(delta_ms);
+        if let Some(timeout) = inner.timeout {
+            if inner.state == NetworkingState::Connected
+                && inner.elapsed - inner.last_received > timeout
+            {
+                inner.state = NetworkingState::Disconnected;
+            }
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Option<Packet> {
+        let mut inner = self.connection.inner.lock();
+        if inner.state != NetworkingState::Connected {
+            return None;
+        }
+        while let Some(bytes) = inner.received.pop_front() {
+            let mut reader = BitcodeReader::start_read(&bytes);
+            match Packet::decode(&mut reader) {
+                Ok(packet) => return Some(packet),
+                Err(e) => error!(
+                    ?e,
+                    "could not decode the packet received by the mock client"
+                ),
+            }
+        }
+        None
+    }
+
+    fn send(&mut self, buf: &[u8]) -> Result<()> {
+        let mut inner = self.connection.inner.lock();
+        if let Some(error) = inner.send_error.take() {
+            return Err(anyhow!(error));
+        }
+        inner.sent.push(buf.to_vec());
+        Ok(())
+    }
+
+    fn id(&self) -> ClientId {
+        self.connection.inner.lock().id
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        LOCAL_SOCKET
+    }
+
+    fn io(&self) -> Option<&Io> {
+        self.io.as_ref()
+    }
+
+    fn io_mut(&mut self) -> Option<&mut Io> {
+        self.io.as_mut()
+    }
+}
@@ -0,0 +1,31 @@
+/*! Scriptable connections that don't use any sockets, to write unit tests for the networking code paths
+
+[`MockClientConnection`](client::MockClientConnection) and [`MockServerConnection`](server::MockServerConnection)
+are handles that can be cloned: one clone is given to the [`NetConfig`](crate::prelude::client::NetConfig)
+of the client or server, and the test keeps the other to script the connection (connect, disconnect, inject
+received packets or io errors) and to inspect the packets that were sent.
+
+```rust
+# use bevy::prelude::*;
+# use lightyear::connection::mock::client::MockClientConnection;
+# use lightyear::prelude::*;
+# use lightyear::prelude::client::{ClientConfig, NetworkingState};
+let mock = MockClientConnection::new(ClientId::Netcode(1));
+let config = ClientConfig {
+    net: client::NetConfig::Mock { connection: mock.clone() },
+    ..default()
+};
+# fn check(app: &mut App, mock: MockClientConnection) {
+// ... connect the client and update the app
+mock.fail_io("connection reset");
+app.update();
+assert_eq!(app.world.resource::<State<NetworkingState>>().get(), &NetworkingState::Disconnected);
+# }
+```
+
+These connections are available with the `test-utils` feature.
+*/
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "server")]
+pub mod server;
@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use tracing::error;
+
+use crate::connection::id::ClientId;
+use crate::connection::server::NetServer;
+use crate::packet::packet::Packet;
+use crate::serialize::bitcode::reader::BitcodeReader;
+use crate::serialize::reader::ReadBuffer;
+use crate::server::io::Io;
+
+#[derive(Debug, Default)]
+struct MockServerState {
+    started: bool,
+    clients: Vec<ClientId>,
+    pending_connections: Vec<ClientId>,
+    pending_disconnections: Vec<ClientId>,
+    new_connections: Vec<ClientId>,
+    new_disconnections: Vec<ClientId>,
+    start_error: Option<String>,
+    send_error: Option<String>,
+    io_error: Option<String>,
+    received: VecDeque<(Vec<u8>, ClientId)>,
+    sent: Vec<(Vec<u8>, ClientId)>,
+}
+
+/// [`NetServer`] whose behaviour is scripted by the test.
+///
+/// All the clones of a `MockServerConnection` share the same connection.
+#[derive(Debug, Clone, Default)]
+pub struct MockServerConnection {
+    inner: Arc<Mutex<MockServerState>>,
+}
+
+impl MockServerConnection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect a client; the connection is reported during the next update of the server
+    pub fn connect_client(&self, client_id: ClientId) {
+        self.inner.lock().pending_connections.push(client_id);
+    }
+
+    /// Disconnect a client as if it closed the connection; the disconnection is reported during the next update
+    pub fn disconnect_client(&self, client_id: ClientId) {
+        self.inner.lock().pending_disconnections.push(client_id);
+    }
+
+    /// The next call to `start` fails with this error
+    pub fn fail_next_start(&self, error: impl Into<String>) {
+        self.inner.lock().start_error = Some(error.into());
+    }
+
+    /// The next packet sent fails with this error
+    pub fn fail_next_send(&self, error: impl Into<String>) {
+        self.inner.lock().send_error = Some(error.into());
+    }
+
+    /// The next update of the server fails with this io error
+    pub fn fail_io(&self, error: impl Into<String>) {
+        self.inner.lock().io_error = Some(error.into());
+    }
+
+    /// Queue a packet (encoded with [`Packet::encode`]) that will be received from the client
+    pub fn push_received(&self, client_id: ClientId, packet: impl Into<Vec<u8>>) {
+        self.inner
+            .lock()
+            .received
+            .push_back((packet.into(), client_id));
+    }
+
+    /// Take the packets sent by the server since the last call
+    pub fn take_sent(&self) -> Vec<(Vec<u8>, ClientId)> {
+        std::mem::take(&mut self.inner.lock().sent)
+    }
+
+    /// Whether the server is started
+    pub fn is_started(&self) -> bool {
+        self.inner.lock().started
+    }
+}
+
+impl NetServer for MockServerConnection {
+    fn start(&mut self) -> Result<()> {
+        let mut inner = self.inner.lock();
+        if let Some(error) = inner.start_error.take() {
+            return Err(anyhow!(error));
+        }
+        inner.started = true;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        let mut inner = self.inner.lock();
+        inner.started = false;
+        let clients = std::mem::take(&mut inner.clients);
+        inner.new_disconnections.extend(clients);
+        Ok(())
+    }
+
+    fn disconnect(&mut self, client_id: ClientId) -> Result<()> {
+        let mut inner = self.inner.lock();
+        inner.clients.retain(|id| *id != client_id);
+        inner.new_disconnections.push(client_id);
+        Ok(())
+    }
+
+    fn connected_client_ids(&self) -> Vec<ClientId> {
+        self.inner.lock().clients.clone()
+    }
+
+    fn try_update(&mut self, _delta_ms: f64) -> Result<()> {
+        let mut inner = self.inner.lock();
+        // reset the new connections/disconnections
+        inner.new_connections.clear();
+        inner.new_disconnections.clear();
+        if let Some(error) = inner.io_error.take() {
+            return Err(anyhow!(error));
+        }
+        if !inner.started {
+            return Ok(());
+        }
+        let connections = std::mem::take(&mut inner.pending_connections);
+        for client_id in connections {
+            if !inner.clients.contains(&client_id) {
+                inner.clients.push(client_id);
+                inner.new_connections.push(client_id);
+            }
+        }
+        let disconnections = std::mem::take(&mut inner.pending_disconnections);
+        for client_id in disconnections {
+            if inner.clients.contains(&client_id) {
+                inner.clients.retain(|id| *id != client_id);
+                inner.new_disconnections.push(client_id);
+            }
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Option<(Packet, ClientId)> {
+        let mut inner = self.inner.lock();
+        while let Some((bytes, client_id)) = inner.received.pop_front() {
+            if !inner.clients.contains(&client_id) {
+                continue;
+            }
+            let mut reader = BitcodeReader::start_read(&bytes);
+            match Packet::decode(&mut reader) {
+                Ok(packet) => return Some((packet, client_id)),
+                Err(e) => error!(
+                    ?e,
+                    "could not decode the packet received by the mock server"
+                ),
+            }
+        }
+        None
+    }
+
+    fn send(&mut self, buf: &[u8], client_id: ClientId) -> Result<()> {
+        let mut inner = self.inner.lock();
+        if let Some(error) = inner.send_error.take() {
+            return Err(anyhow!(error));
+        }
+        inner.sent.push((buf.to_vec(), client_id));
+        Ok(())
+    }
+
+    fn new_connections(&self) -> Vec<ClientId> {
+        self.inner.lock().new_connections.clone()
+    }
+
+    fn new_disconnections(&self) -> Vec<ClientId> {
+        self.inner.lock().new_disconnections.clone()
+    }
+
+    fn io(&self) -> Option<&Io> {
+        None
+    }
+
+    fn io_mut(&mut self) -> Option<&mut Io> {
+        None
+    }
+}
@@ -1,12 +1,20 @@
 /*!  A connection is an abstraction over an unreliable transport of a connection between a client and server
 */
+#[cfg(feature = "client")]
 pub(crate) mod client;
 pub mod netcode;
+/// NAT hole-punching via a rendezvous server
+pub mod punch;
 
+#[cfg(feature = "server")]
 pub(crate) mod server;
 
 pub mod id;
+#[cfg(feature = "client")]
 mod local;
+#[cfg_attr(docsrs, doc(cfg(feature = "test-utils")))]
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock;
 #[cfg_attr(docsrs, doc(cfg(all(feature = "steam", not(target_family = "wasm")))))]
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
 pub(crate) mod steam;
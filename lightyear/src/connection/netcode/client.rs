@@ -13,7 +13,7 @@ use crate::prelude::client::NetworkingState;
 use crate::serialize::bitcode::reader::BufferPool;
 use crate::serialize::reader::ReadBuffer;
 use crate::transport::io::IoState;
-use crate::transport::{PacketReceiver, PacketSender, Transport, LOCAL_SOCKET};
+use crate::transport::{Delivery, PacketReceiver, PacketSender, Transport, LOCAL_SOCKET};
 
 use super::{
     bytes::Bytes,
@@ -336,6 +336,8 @@ impl<Ctx> NetcodeClient<Ctx> {
                 debug!("client sending connection response packet to server");
                 ResponsePacket::create(self.challenge_token_sequence, self.challenge_token_data)
             }
+            // the transport already keeps the connection alive
+            ClientState::Connected if io.native_liveness => return Ok(()),
             ClientState::Connected => {
                 trace!("client sending connection keep-alive packet to server");
                 KeepAlivePacket::create(0)
@@ -354,6 +356,14 @@ impl<Ctx> NetcodeClient<Ctx> {
         Ok(())
     }
     fn send_packet(&mut self, packet: Packet, io: &mut Io) -> Result<()> {
+        self.send_packet_with_delivery(packet, io, Delivery::Datagram)
+    }
+    fn send_packet_with_delivery(
+        &mut self,
+        packet: Packet,
+        io: &mut Io,
+        delivery: Delivery,
+    ) -> Result<()> {
         let mut buf = [0u8; MAX_PKT_BUF_SIZE];
         let size = packet.write(
             &mut buf,
@@ -361,7 +371,7 @@ impl<Ctx> NetcodeClient<Ctx> {
             &self.token.client_to_server_key,
             self.token.protocol_id,
         )?;
-        io.send(&buf[..size], &self.server_addr())
+        io.send_with_delivery(&buf[..size], &self.server_addr(), delivery)
             .map_err(Error::from)?;
         self.last_send_time = self.time;
         self.sequence += 1;
@@ -427,7 +437,8 @@ impl<Ctx> NetcodeClient<Ctx> {
         self.last_receive_time = self.time;
         Ok(())
     }
-    fn update_state(&mut self) {
+    /// If `native_liveness` is true, the transport detects dead connections itself so the connection timeout is ignored
+    fn update_state(&mut self, native_liveness: bool) {
         let is_token_expired = self.time - self.start_time
             >= self.token.expire_timestamp as f64 - self.token.create_timestamp as f64;
         let is_connection_timed_out = self.token.timeout_seconds.is_positive()
@@ -463,7 +474,7 @@ impl<Ctx> NetcodeClient<Ctx> {
                 };
                 ClientState::ChallengeResponseTimedOut
             }
-            ClientState::Connected if is_connection_timed_out => {
+            ClientState::Connected if is_connection_timed_out && !native_liveness => {
                 info!("client connection timed out");
                 ClientState::ConnectionTimedOut
             }
@@ -549,7 +560,7 @@ impl<Ctx> NetcodeClient<Ctx> {
         self.time += delta_ms;
         self.recv_packets(io)?;
         self.send_packets(io)?;
-        self.update_state();
+        self.update_state(io.native_liveness);
         Ok(())
     }
 
@@ -594,6 +605,15 @@ impl<Ctx> NetcodeClient<Ctx> {
     ///
     /// The provided buffer must be smaller than [`MAX_PACKET_SIZE`].
     pub fn send(&mut self, buf: &[u8], io: &mut Io) -> Result<()> {
+        self.send_with_delivery(buf, io, Delivery::Datagram)
+    }
+    /// Sends a packet to the server, using the given [`Delivery`] method if the transport supports it.
+    pub fn send_with_delivery(
+        &mut self,
+        buf: &[u8],
+        io: &mut Io,
+        delivery: Delivery,
+    ) -> Result<()> {
         if self.state != ClientState::Connected {
             trace!("tried to send but not connected");
             return Ok(());
@@ -601,7 +621,7 @@ impl<Ctx> NetcodeClient<Ctx> {
         if buf.len() > MAX_PACKET_SIZE {
             return Err(Error::SizeMismatch(MAX_PACKET_SIZE, buf.len()));
         }
-        self.send_packet(PayloadPacket::create(buf), io)?;
+        self.send_packet_with_delivery(PayloadPacket::create(buf), io, delivery)?;
         Ok(())
     }
     /// Disconnects the client from the server.
@@ -685,6 +705,11 @@ impl<Ctx: Send + Sync> NetClient for Client<Ctx> {
         }
     }
 
+    fn closed_by_server(&self) -> bool {
+        // the io is only closed when the client disconnects itself (or on io errors)
+        self.client.state() == ClientState::Disconnected && self.io.is_some()
+    }
+
     fn try_update(&mut self, delta_ms: f64) -> anyhow::Result<()> {
         let io = self
             .io
@@ -705,6 +730,13 @@ impl<Ctx: Send + Sync> NetClient for Client<Ctx> {
         self.client.send(buf, io).context("could not send")
     }
 
+    fn send_with_delivery(&mut self, buf: &[u8], delivery: Delivery) -> anyhow::Result<()> {
+        let io = self.io.as_mut().context("io is not initialized")?;
+        self.client
+            .send_with_delivery(buf, io, delivery)
+            .context("could not send")
+    }
+
     fn id(&self) -> id::ClientId {
         id::ClientId::Netcode(self.client.id())
     }
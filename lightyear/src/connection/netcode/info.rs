@@ -0,0 +1,649 @@
+//! Info requests, to query the state of a server without connecting to it
+//!
+//! A client can send an info request to a server before connecting, to measure the round-trip time
+//! and get some information about the server (protocol version, number of connected clients and custom data).
+//! This can be used to power server browsers or to select the closest region.
+//!
+//! The info packets are not part of the netcode protocol: they are not encrypted and don't require a connect token.
+//! They start with a byte that no netcode packet can start with, so the server can tell them apart.
+//! To avoid being used for traffic amplification, the server only answers requests that are at least as big as its response,
+//! and limits the number of requests it answers for each address.
+//!
+//! Queries (see [`ServerQueryInfo`]) are a simpler variant of the info requests, meant for third-party server browsers
+//! and hosting panels.
+use std::collections::VecDeque;
+use std::io::{Cursor, Read};
+use std::net::{IpAddr, SocketAddr};
+
+use bevy::utils::{Duration, HashMap, Instant};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::protocol::component::ProtocolVersion;
+
+/// Prefix of the info packets
+const INFO_MAGIC: [u8; 4] = [0xFF, b'L', b'Y', b'I'];
+const INFO_REQUEST: u8 = 0;
+const INFO_RESPONSE: u8 = 1;
+const QUERY_REQUEST: u8 = 2;
+const QUERY_RESPONSE: u8 = 3;
+const QUERY_VERSION: u8 = 1;
+/// Size of the info requests, which is also the maximum size of the info responses
+const INFO_PACKET_SIZE: usize = 256;
+/// magic, packet type, protocol id, nonce
+const INFO_HEADER_SIZE: usize = 4 + 1 + 8 + 8;
+/// Maximum size of the custom data of the [`ServerInfo`]
+pub const MAX_SERVER_INFO_DATA: usize = INFO_PACKET_SIZE - INFO_HEADER_SIZE - 4 - 4 - 2;
+/// Maximum length in bytes of the strings of the [`ServerQueryInfo`]; longer strings are truncated
+pub const MAX_QUERY_STRING_LEN: usize = 64;
+/// Maximum number of addresses for which the server keeps track of the rate of requests.
+/// When a request comes from a new address and the limit is reached, the least recently seen address is forgotten
+const MAX_RATE_LIMITED_ADDRESSES: usize = 4096;
+
+/// Information returned by the server to an info request
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerInfo {
+    /// Version of the protocol of the server
+    pub protocol_version: ProtocolVersion,
+    /// Number of clients connected to the server
+    pub num_clients: u32,
+    /// Custom data provided in the server config (name, region, map, etc.)
+    pub data: Vec<u8>,
+}
+
+/// Information returned by the server to a query
+///
+/// Queries are a simpler variant of the info requests, meant for third-party server browsers and hosting panels:
+/// they don't require knowing the protocol id of the server and return these standard fields.
+/// All integers are little-endian.
+///
+/// Query request (256 bytes):
+/// - `[0xFF, b'L', b'Y', b'I']`
+/// - packet type: `2` (u8)
+/// - nonce (u64), chosen by the sender and copied in the response
+/// - zero padding up to 256 bytes
+///
+/// Query response:
+/// - `[0xFF, b'L', b'Y', b'I']`
+/// - packet type: `3` (u8)
+/// - nonce (u64)
+/// - version of the query format: `1` (u8)
+/// - netcode protocol id (u64)
+/// - number of players (u32)
+/// - maximum number of players (u32)
+/// - server name: length (u8) followed by the UTF-8 bytes
+/// - map: length (u8) followed by the UTF-8 bytes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerQueryInfo {
+    pub name: String,
+    pub map: String,
+    /// Number of clients connected to the server, filled in by the server
+    pub num_players: u32,
+    pub max_players: u32,
+    /// Netcode protocol id of the server, filled in by the server
+    pub protocol_id: u64,
+}
+
+pub(crate) fn is_info_packet(buf: &[u8]) -> bool {
+    buf.starts_with(&INFO_MAGIC)
+}
+
+fn write_header(buf: &mut Vec<u8>, packet_type: u8, protocol_id: u64, nonce: u64) {
+    buf.extend_from_slice(&INFO_MAGIC);
+    buf.push(packet_type);
+    buf.write_u64::<LittleEndian>(protocol_id).unwrap();
+    buf.write_u64::<LittleEndian>(nonce).unwrap();
+}
+
+/// Read the header of an info packet, and return the nonce
+fn read_header(
+    reader: &mut Cursor<&[u8]>,
+    packet_type: u8,
+    protocol_id: u64,
+) -> std::io::Result<Option<u64>> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != INFO_MAGIC
+        || reader.read_u8()? != packet_type
+        || reader.read_u64::<LittleEndian>()? != protocol_id
+    {
+        return Ok(None);
+    }
+    Ok(Some(reader.read_u64::<LittleEndian>()?))
+}
+
+pub(crate) fn write_request(protocol_id: u64, nonce: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(INFO_PACKET_SIZE);
+    write_header(&mut buf, INFO_REQUEST, protocol_id, nonce);
+    // pad the request so that the response is never bigger than the request
+    buf.resize(INFO_PACKET_SIZE, 0);
+    buf
+}
+
+/// Returns the nonce of the request, if it is a valid request for this protocol
+pub(crate) fn read_request(buf: &[u8], protocol_id: u64) -> Option<u64> {
+    if buf.len() < INFO_PACKET_SIZE {
+        return None;
+    }
+    read_header(&mut Cursor::new(buf), INFO_REQUEST, protocol_id)
+        .ok()
+        .flatten()
+}
+
+pub(crate) fn write_response(protocol_id: u64, nonce: u64, info: &ServerInfo) -> Vec<u8> {
+    let data = &info.data[..info.data.len().min(MAX_SERVER_INFO_DATA)];
+    let mut buf = Vec::with_capacity(INFO_PACKET_SIZE);
+    write_header(&mut buf, INFO_RESPONSE, protocol_id, nonce);
+    buf.write_u32::<LittleEndian>(info.protocol_version)
+        .unwrap();
+    buf.write_u32::<LittleEndian>(info.num_clients).unwrap();
+    buf.write_u16::<LittleEndian>(data.len() as u16).unwrap();
+    buf.extend_from_slice(data);
+    buf
+}
+
+/// Returns the nonce and the content of the response, if it is a valid response for this protocol
+pub(crate) fn read_response(buf: &[u8], protocol_id: u64) -> Option<(u64, ServerInfo)> {
+    let mut reader = Cursor::new(buf);
+    let read = |reader: &mut Cursor<&[u8]>| -> std::io::Result<Option<(u64, ServerInfo)>> {
+        let Some(nonce) = read_header(reader, INFO_RESPONSE, protocol_id)? else {
+            return Ok(None);
+        };
+        let protocol_version = reader.read_u32::<LittleEndian>()?;
+        let num_clients = reader.read_u32::<LittleEndian>()?;
+        let mut data = vec![0; reader.read_u16::<LittleEndian>()? as usize];
+        reader.read_exact(&mut data)?;
+        Ok(Some((
+            nonce,
+            ServerInfo {
+                protocol_version,
+                num_clients,
+                data,
+            },
+        )))
+    };
+    read(&mut reader).ok().flatten()
+}
+
+pub(crate) fn write_query_request(nonce: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(INFO_PACKET_SIZE);
+    buf.extend_from_slice(&INFO_MAGIC);
+    buf.push(QUERY_REQUEST);
+    buf.write_u64::<LittleEndian>(nonce).unwrap();
+    buf.resize(INFO_PACKET_SIZE, 0);
+    buf
+}
+
+/// Returns the nonce of the request, if it is a valid query request
+pub(crate) fn read_query_request(buf: &[u8]) -> Option<u64> {
+    if buf.len() < INFO_PACKET_SIZE || !is_info_packet(buf) || buf[4] != QUERY_REQUEST {
+        return None;
+    }
+    Cursor::new(&buf[5..]).read_u64::<LittleEndian>().ok()
+}
+
+/// Truncate the string to at most [`MAX_QUERY_STRING_LEN`] bytes, without splitting a character
+fn truncate(s: &str) -> &str {
+    let mut len = s.len().min(MAX_QUERY_STRING_LEN);
+    while !s.is_char_boundary(len) {
+        len -= 1;
+    }
+    &s[..len]
+}
+
+pub(crate) fn write_query_response(nonce: u64, info: &ServerQueryInfo) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(INFO_PACKET_SIZE);
+    buf.extend_from_slice(&INFO_MAGIC);
+    buf.push(QUERY_RESPONSE);
+    buf.write_u64::<LittleEndian>(nonce).unwrap();
+    buf.push(QUERY_VERSION);
+    buf.write_u64::<LittleEndian>(info.protocol_id).unwrap();
+    buf.write_u32::<LittleEndian>(info.num_players).unwrap();
+    buf.write_u32::<LittleEndian>(info.max_players).unwrap();
+    for s in [&info.name, &info.map] {
+        let s = truncate(s);
+        buf.push(s.len() as u8);
+        buf.extend_from_slice(s.as_bytes());
+    }
+    buf
+}
+
+/// Returns the nonce and the content of the response, if it is a valid query response
+pub(crate) fn read_query_response(buf: &[u8]) -> Option<(u64, ServerQueryInfo)> {
+    let mut reader = Cursor::new(buf);
+    let read = |reader: &mut Cursor<&[u8]>| -> std::io::Result<Option<(u64, ServerQueryInfo)>> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != INFO_MAGIC || reader.read_u8()? != QUERY_RESPONSE {
+            return Ok(None);
+        }
+        let nonce = reader.read_u64::<LittleEndian>()?;
+        if reader.read_u8()? != QUERY_VERSION {
+            return Ok(None);
+        }
+        let protocol_id = reader.read_u64::<LittleEndian>()?;
+        let num_players = reader.read_u32::<LittleEndian>()?;
+        let max_players = reader.read_u32::<LittleEndian>()?;
+        let mut read_string = || -> std::io::Result<Option<String>> {
+            let mut bytes = vec![0; reader.read_u8()? as usize];
+            reader.read_exact(&mut bytes)?;
+            Ok(String::from_utf8(bytes).ok())
+        };
+        let (Some(name), Some(map)) = (read_string()?, read_string()?) else {
+            return Ok(None);
+        };
+        Ok(Some((
+            nonce,
+            ServerQueryInfo {
+                name,
+                map,
+                num_players,
+                max_players,
+                protocol_id,
+            },
+        )))
+    };
+    read(&mut reader).ok().flatten()
+}
+
+/// Limits the number of info and query requests that the server answers for each IP address
+pub(crate) struct RequestRateLimiter {
+    /// Number of requests answered per second for each address, which is also the maximum burst
+    requests_per_second: f64,
+    /// Number of requests that can still be answered for each address, with the time of the last update
+    buckets: HashMap<IpAddr, (f64, f64)>,
+    /// Addresses in the order in which they were last seen, with the time at which they were seen.
+    /// An entry is stale if the bucket of the address was updated since then
+    recency: VecDeque<(IpAddr, f64)>,
+}
+
+impl RequestRateLimiter {
+    pub(crate) fn new(requests_per_second: u32) -> Self {
+        Self {
+            requests_per_second: requests_per_second as f64,
+            buckets: HashMap::default(),
+            recency: VecDeque::new(),
+        }
+    }
+
+    /// Returns true if the request received from `ip` at `time` (in seconds) should be answered
+    pub(crate) fn allow(&mut self, ip: IpAddr, time: f64) -> bool {
+        let rate = self.requests_per_second;
+        let known = self.buckets.contains_key(&ip);
+        if !known && self.buckets.len() >= MAX_RATE_LIMITED_ADDRESSES {
+            self.evict_least_recent();
+        }
+        let (tokens, last) = self.buckets.entry(ip).or_insert((rate, time));
+        // the recency queue already has an up-to-date entry if the address was seen at the same time
+        if !known || *last != time {
+            self.recency.push_back((ip, time));
+        }
+        *tokens = (*tokens + (time - *last) * rate).min(rate);
+        *last = time;
+        let allowed = *tokens >= 1.0;
+        if allowed {
+            *tokens -= 1.0;
+        }
+        if self.recency.len() > 2 * MAX_RATE_LIMITED_ADDRESSES {
+            let buckets = &self.buckets;
+            self.recency
+                .retain(|(ip, time)| buckets.get(ip).is_some_and(|(_, last)| last == time));
+        }
+        allowed
+    }
+
+    /// Forget the address that was seen the least recently
+    fn evict_least_recent(&mut self) {
+        while let Some((ip, time)) = self.recency.pop_front() {
+            if self.buckets.get(&ip).is_some_and(|(_, last)| *last == time) {
+                self.buckets.remove(&ip);
+                return;
+            }
+        }
+    }
+}
+
+/// Response of a server to a [`ServerProbe`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbeResponse {
+    pub server_addr: SocketAddr,
+    /// Round-trip time of the info request
+    pub rtt: Duration,
+    pub info: ServerInfo,
+}
+
+/// Response of a server to a query sent with [`ServerProbe::query`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryResponse {
+    pub server_addr: SocketAddr,
+    /// Round-trip time of the query
+    pub rtt: Duration,
+    pub info: ServerQueryInfo,
+}
+
+/// Sends info requests to servers over UDP, without connecting to them.
+///
+/// The servers only answer if they were configured with
+/// [`NetcodeConfig::with_server_info`](crate::server::config::NetcodeConfig::with_server_info),
+/// or [`NetcodeConfig::with_query_info`](crate::server::config::NetcodeConfig::with_query_info) for queries.
+///
+/// ```rust,no_run
+/// # use lightyear::connection::netcode::ServerProbe;
+/// let mut probe = ServerProbe::new("0.0.0.0:0".parse().unwrap(), 0).unwrap();
+/// probe.probe("127.0.0.1:5000".parse().unwrap()).unwrap();
+/// // later, for example in a system that runs every frame
+/// for response in probe.poll() {
+///     println!("{}: {:?} ({} players)", response.server_addr, response.rtt, response.info.num_clients);
+/// }
+/// ```
+#[cfg(not(target_family = "wasm"))]
+pub struct ServerProbe {
+    socket: std::net::UdpSocket,
+    protocol_id: u64,
+    /// Requests that were not answered yet, with the time when they were sent
+    pending: HashMap<(SocketAddr, u64), Instant>,
+    timeout: Duration,
+    responses: Vec<ProbeResponse>,
+    query_responses: Vec<QueryResponse>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl ServerProbe {
+    /// Create a probe that sends requests from `local_addr`, for servers using the netcode `protocol_id`
+    pub fn new(local_addr: SocketAddr, protocol_id: u64) -> std::io::Result<Self> {
+        let socket = std::net::UdpSocket::bind(local_addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            protocol_id,
+            pending: HashMap::default(),
+            timeout: Duration::from_secs(5),
+            responses: vec![],
+            query_responses: vec![],
+        })
+    }
+
+    /// Set the duration after which the requests that were not answered are forgotten
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send an info request to the server at `server_addr`
+    pub fn probe(&mut self, server_addr: SocketAddr) -> std::io::Result<()> {
+        let nonce = rand::random();
+        self.socket
+            .send_to(&write_request(self.protocol_id, nonce), server_addr)?;
+        self.pending.insert((server_addr, nonce), Instant::now());
+        Ok(())
+    }
+
+    /// Send a query (see [`ServerQueryInfo`]) to the server at `server_addr`. The protocol id of the probe is not used.
+    pub fn query(&mut self, server_addr: SocketAddr) -> std::io::Result<()> {
+        let nonce = rand::random();
+        self.socket
+            .send_to(&write_query_request(nonce), server_addr)?;
+        self.pending.insert((server_addr, nonce), Instant::now());
+        Ok(())
+    }
+
+    /// Number of requests that were not answered yet
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Return the responses to the info requests that were received since the last call
+    pub fn poll(&mut self) -> Vec<ProbeResponse> {
+        self.recv();
+        std::mem::take(&mut self.responses)
+    }
+
+    /// Return the responses to the queries that were received since the last call
+    pub fn poll_queries(&mut self) -> Vec<QueryResponse> {
+        self.recv();
+        std::mem::take(&mut self.query_responses)
+    }
+
+    fn recv(&mut self) {
+        let now = Instant::now();
+        let mut buf = [0; INFO_PACKET_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, server_addr)) => {
+                    let buf = &buf[..len];
+                    if let Some((nonce, info)) = read_response(buf, self.protocol_id) {
+                        if let Some(sent) = self.pending.remove(&(server_addr, nonce)) {
+                            self.responses.push(ProbeResponse {
+                                server_addr,
+                                rtt: now - sent,
+                                info,
+                            });
+                        }
+                    } else if let Some((nonce, info)) = read_query_response(buf) {
+                        if let Some(sent) = self.pending.remove(&(server_addr, nonce)) {
+                            self.query_responses.push(QueryResponse {
+                                server_addr,
+                                rtt: now - sent,
+                                info,
+                            });
+                        }
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                // errors such as ConnectionReset (ICMP port unreachable) are ignored
+                Err(_) => continue,
+            }
+        }
+        let timeout = self.timeout;
+        self.pending.retain(|_, sent| now - *sent < timeout);
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_packets() {
+        let request = write_request(3, 42);
+        assert!(is_info_packet(&request));
+        assert_eq!(read_request(&request, 3), Some(42));
+        // wrong protocol
+        assert_eq!(read_request(&request, 4), None);
+        // requests that are smaller than the response are ignored
+        assert_eq!(read_request(&request[..100], 3), None);
+
+        let info = ServerInfo {
+            protocol_version: 2,
+            num_clients: 5,
+            data: vec![7; MAX_SERVER_INFO_DATA + 10],
+        };
+        let response = write_response(3, 42, &info);
+        assert!(response.len() <= request.len());
+        let (nonce, received) = read_response(&response, 3).unwrap();
+        assert_eq!(nonce, 42);
+        assert_eq!(received.num_clients, 5);
+        assert_eq!(received.protocol_version, 2);
+        // the data is truncated
+        assert_eq!(received.data.len(), MAX_SERVER_INFO_DATA);
+        assert_eq!(read_response(&response[..30], 3), None);
+    }
+
+    #[test]
+    fn test_query_packets() {
+        let request = write_query_request(42);
+        assert!(is_info_packet(&request));
+        assert_eq!(read_query_request(&request), Some(42));
+        assert_eq!(read_query_request(&request[..100]), None);
+        // info requests are not query requests
+        assert_eq!(read_query_request(&write_request(3, 42)), None);
+
+        let info = ServerQueryInfo {
+            name: "é".repeat(MAX_QUERY_STRING_LEN),
+            map: "arena".to_string(),
+            num_players: 3,
+            max_players: 16,
+            protocol_id: 7,
+        };
+        let response = write_query_response(42, &info);
+        assert!(response.len() <= request.len());
+        let (nonce, received) = read_query_response(&response).unwrap();
+        assert_eq!(nonce, 42);
+        // the name is truncated without splitting the characters
+        assert_eq!(received.name, "é".repeat(MAX_QUERY_STRING_LEN / 2));
+        assert_eq!(
+            received,
+            ServerQueryInfo {
+                name: received.name.clone(),
+                ..info
+            }
+        );
+        assert_eq!(read_query_response(&response[..30]), None);
+    }
+
+    #[test]
+    fn test_request_rate_limiter() {
+        let mut limiter = RequestRateLimiter::new(2);
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        let other_ip = IpAddr::from([127, 0, 0, 2]);
+        assert!(limiter.allow(ip, 0.0));
+        assert!(limiter.allow(ip, 0.0));
+        assert!(!limiter.allow(ip, 0.1));
+        // each address has its own limit
+        assert!(limiter.allow(other_ip, 0.1));
+        // the limit is replenished over time
+        assert!(limiter.allow(ip, 0.6));
+        assert!(!limiter.allow(ip, 0.6));
+    }
+
+    #[test]
+    fn test_request_rate_limiter_eviction() {
+        let mut limiter = RequestRateLimiter::new(1);
+        let ip = |i: usize| IpAddr::from((i as u32).to_be_bytes());
+        for i in 0..MAX_RATE_LIMITED_ADDRESSES {
+            assert!(limiter.allow(ip(i), i as f64 * 0.0001));
+        }
+        // the address 1 is seen again, so the address 0 is the least recently seen
+        assert!(!limiter.allow(ip(1), 0.5));
+
+        // a new address evicts the least recently seen address instead of being refused
+        assert!(limiter.allow(ip(MAX_RATE_LIMITED_ADDRESSES), 0.5));
+        assert_eq!(limiter.buckets.len(), MAX_RATE_LIMITED_ADDRESSES);
+        assert!(!limiter.buckets.contains_key(&ip(0)));
+        assert!(limiter.buckets.contains_key(&ip(1)));
+        assert!(limiter.recency.len() <= 2 * MAX_RATE_LIMITED_ADDRESSES);
+    }
+
+    #[test]
+    fn test_server_probe() {
+        use std::net::Ipv4Addr;
+
+        use crate::connection::netcode::{generate_key, NetcodeServer, ServerConfig};
+        use crate::prelude::server::{IoConfig, ServerTransport};
+
+        let mut io = IoConfig::from_transport(ServerTransport::UdpSocket(SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            0,
+        )))
+        .start()
+        .unwrap();
+        let server_addr = io.local_addr();
+        let mut server = NetcodeServer::with_config(
+            1,
+            generate_key(),
+            ServerConfig::with_context(()).server_info(ServerInfo {
+                protocol_version: 3,
+                num_clients: 0,
+                data: b"eu-west".to_vec(),
+            }),
+        )
+        .unwrap();
+
+        // a probe for another protocol doesn't get an answer
+        let mut other_probe =
+            ServerProbe::new(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), 2).unwrap();
+        other_probe.probe(server_addr).unwrap();
+        let mut probe =
+            ServerProbe::new(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), 1).unwrap();
+        probe.probe(server_addr).unwrap();
+        assert_eq!(probe.num_pending(), 1);
+
+        let mut responses = vec![];
+        for _ in 0..50 {
+            server.update(0.01, &mut io);
+            responses.extend(probe.poll());
+            assert!(other_probe.poll().is_empty());
+            if !responses.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].server_addr, server_addr);
+        assert_eq!(
+            responses[0].info,
+            ServerInfo {
+                protocol_version: 3,
+                num_clients: 0,
+                data: b"eu-west".to_vec(),
+            }
+        );
+        assert_eq!(probe.num_pending(), 0);
+    }
+
+    #[test]
+    fn test_server_query() {
+        use std::net::Ipv4Addr;
+
+        use crate::connection::netcode::{generate_key, NetcodeServer, ServerConfig};
+        use crate::prelude::server::{IoConfig, ServerTransport};
+
+        let mut io = IoConfig::from_transport(ServerTransport::UdpSocket(SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            0,
+        )))
+        .start()
+        .unwrap();
+        let server_addr = io.local_addr();
+        let mut server = NetcodeServer::with_config(
+            5,
+            generate_key(),
+            ServerConfig::with_context(())
+                .query_info(ServerQueryInfo {
+                    name: "my server".to_string(),
+                    map: "arena".to_string(),
+                    max_players: 8,
+                    ..Default::default()
+                })
+                .request_rate_limit(1),
+        )
+        .unwrap();
+
+        // the protocol id of the probe doesn't matter for queries
+        let mut probe =
+            ServerProbe::new(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), 0).unwrap();
+        probe.query(server_addr).unwrap();
+        // the second query is over the rate limit
+        probe.query(server_addr).unwrap();
+        assert_eq!(probe.num_pending(), 2);
+
+        let mut responses = vec![];
+        for _ in 0..20 {
+            server.update(0.0, &mut io);
+            responses.extend(probe.poll_queries());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].server_addr, server_addr);
+        assert_eq!(
+            responses[0].info,
+            ServerQueryInfo {
+                name: "my server".to_string(),
+                map: "arena".to_string(),
+                num_players: 0,
+                max_players: 8,
+                protocol_id: 5,
+            }
+        );
+        assert_eq!(probe.num_pending(), 1);
+    }
+}
@@ -121,18 +121,29 @@ loop {
 ```
 */
 
+#[cfg(feature = "client")]
 pub use client::{Client, ClientConfig, ClientState, NetcodeClient};
 pub use crypto::{generate_key, try_generate_key, Key};
 pub use error::{Error, Result};
-pub use server::{Callback, ClientId, NetcodeServer, Server, ServerConfig};
+#[cfg(not(target_family = "wasm"))]
+pub use info::ServerProbe;
+pub use info::{
+    ProbeResponse, QueryResponse, ServerInfo, ServerQueryInfo, MAX_QUERY_STRING_LEN,
+    MAX_SERVER_INFO_DATA,
+};
+#[cfg(feature = "server")]
+pub use server::{Callback, NetcodeServer, Server, ServerConfig, MAX_CLIENTS};
 pub use token::{ConnectToken, ConnectTokenBuilder, InvalidTokenError};
 
 mod bytes;
+#[cfg(feature = "client")]
 mod client;
 mod crypto;
 mod error;
+mod info;
 mod packet;
 mod replay;
+#[cfg(feature = "server")]
 mod server;
 mod token;
 mod utils;
@@ -152,3 +163,6 @@ pub const CONNECT_TOKEN_BYTES: usize = 2048;
 pub const MAX_PACKET_SIZE: usize = 1200;
 /// The version of the netcode protocol implemented by this crate.
 pub const NETCODE_VERSION: &[u8; 13] = b"NETCODE 1.02\0";
+
+/// The client id from a connect token, must be unique for each client.
+pub type ClientId = u64;
@@ -14,25 +14,28 @@ use crate::serialize::reader::ReadBuffer;
 use crate::server::config::NetcodeConfig;
 use crate::server::io::{Io, ServerIoEvent, ServerNetworkEventSender};
 use crate::transport::io::BaseIo;
-use crate::transport::{PacketReceiver, PacketSender, Transport};
+use crate::transport::{Delivery, PacketReceiver, PacketSender, Transport};
 
 use super::{
     bytes::Bytes,
     crypto::{self, Key},
     error::{Error, Result},
     generate_key,
+    info::{self, RequestRateLimiter, ServerInfo, ServerQueryInfo},
     packet::{
         ChallengePacket, DeniedPacket, DisconnectPacket, KeepAlivePacket, Packet, PayloadPacket,
         RequestPacket, ResponsePacket,
     },
     replay::ReplayProtection,
     token::{ChallengeToken, ConnectToken, ConnectTokenBuilder, ConnectTokenPrivate},
-    MAC_BYTES, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
+    ClientId, MAC_BYTES, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
 };
 
 pub const MAX_CLIENTS: usize = 256;
 
 const CLIENT_TIMEOUT_SECS: i32 = 10;
+/// Default number of info requests and queries answered per second for each IP address
+const REQUEST_RATE_LIMIT: u32 = 5;
 
 #[derive(Clone, Copy)]
 struct TokenEntry {
@@ -108,9 +111,6 @@ impl Connection {
     }
 }
 
-/// The client id from a connect token, must be unique for each client.
-pub type ClientId = u64;
-
 struct ConnectionCache {
     // this somewhat mimics the original C implementation,
     // the main difference being that `Connection` includes the encryption mapping as well.
@@ -250,6 +250,9 @@ pub struct ServerConfig<Ctx> {
     context: Ctx,
     on_connect: Option<Callback<Ctx>>,
     on_disconnect: Option<Callback<Ctx>>,
+    server_info: Option<ServerInfo>,
+    query_info: Option<ServerQueryInfo>,
+    request_rate_limit: u32,
 }
 
 impl Default for ServerConfig<()> {
@@ -263,6 +266,9 @@ impl Default for ServerConfig<()> {
             context: (),
             on_connect: None,
             on_disconnect: None,
+            server_info: None,
+            query_info: None,
+            request_rate_limit: REQUEST_RATE_LIMIT,
         }
     }
 }
@@ -283,6 +289,9 @@ impl<Ctx> ServerConfig<Ctx> {
             context: ctx,
             on_connect: None,
             on_disconnect: None,
+            server_info: None,
+            query_info: None,
+            request_rate_limit: REQUEST_RATE_LIMIT,
         }
     }
     /// Set the number of redundant disconnect packets that will be sent to a client when the server is disconnecting it. <br>
@@ -315,6 +324,24 @@ impl<Ctx> ServerConfig<Ctx> {
         self.server_addr = server_addr;
         self
     }
+    /// Answer the info requests sent by [`ServerProbe`](super::ServerProbe)s with `server_info`. <br>
+    /// The number of connected clients of the info is filled in by the server.
+    pub fn server_info(mut self, server_info: ServerInfo) -> Self {
+        self.server_info = Some(server_info);
+        self
+    }
+    /// Answer the queries (see [`ServerQueryInfo`]) with `query_info`. <br>
+    /// The number of players and the protocol hash of the info are filled in by the server.
+    pub fn query_info(mut self, query_info: ServerQueryInfo) -> Self {
+        self.query_info = Some(query_info);
+        self
+    }
+    /// Set the maximum number of info requests and queries answered per second for each IP address. <br>
+    /// The default is 5 requests per second.
+    pub fn request_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.request_rate_limit = requests_per_second;
+        self
+    }
     /// Provide a callback that will be called when a client is connected to the server. <br>
     /// The callback will be called with the client index and the context that was provided (provide a `None` context if you don't need one).
     ///
@@ -382,6 +409,7 @@ pub struct NetcodeServer<Ctx = ()> {
     protocol_id: u64,
     conn_cache: ConnectionCache,
     token_entries: TokenEntries,
+    request_limiter: RequestRateLimiter,
     cfg: ServerConfig<Ctx>,
 }
 
@@ -400,6 +428,7 @@ impl NetcodeServer {
             challenge_key: crypto::generate_key(),
             conn_cache: ConnectionCache::new(0.0),
             token_entries: TokenEntries::new(),
+            request_limiter: RequestRateLimiter::new(REQUEST_RATE_LIMIT),
             cfg: ServerConfig::default(),
         };
         // info!("server started on {}", server.io.local_addr());
@@ -434,6 +463,7 @@ impl<Ctx> NetcodeServer<Ctx> {
             challenge_key: crypto::generate_key(),
             conn_cache: ConnectionCache::new(0.0),
             token_entries: TokenEntries::new(),
+            request_limiter: RequestRateLimiter::new(cfg.request_rate_limit),
             cfg,
         };
         // info!("server started on {}", server.addr());
@@ -531,6 +561,15 @@ impl<Ctx> NetcodeServer<Ctx> {
         packet: Packet,
         id: ClientId,
         sender: &mut impl PacketSender,
+    ) -> Result<()> {
+        self.send_to_client_with_delivery(packet, id, sender, Delivery::Datagram)
+    }
+    fn send_to_client_with_delivery(
+        &mut self,
+        packet: Packet,
+        id: ClientId,
+        sender: &mut impl PacketSender,
+        delivery: Delivery,
     ) -> Result<()> {
         let mut buf = [0u8; MAX_PKT_BUF_SIZE];
         let conn = &mut self
@@ -540,7 +579,7 @@ impl<Ctx> NetcodeServer<Ctx> {
             .expect("invalid client id");
         let size = packet.write(&mut buf, conn.sequence, &conn.send_key, self.protocol_id)?;
         sender
-            .send(&buf[..size], &conn.addr)
+            .send_with_delivery(&buf[..size], &conn.addr, delivery)
             // .inspect_err(|e| error!("ERROR SENDING: {:?}", e))
             .map_err(Error::from)?;
         conn.last_access_time = self.time;
@@ -689,7 +728,13 @@ impl<Ctx> NetcodeServer<Ctx> {
         self.on_connect(id, from_addr);
         Ok(())
     }
-    fn check_for_timeouts(&mut self) {
+    /// Disconnect the clients that we haven't heard from in a while.
+    ///
+    /// Skipped if the transport detects dead connections itself (see [`LivenessConfig`](crate::transport::config::LivenessConfig))
+    fn check_for_timeouts(&mut self, native_liveness: bool) {
+        if native_liveness {
+            return;
+        }
         for id in self.conn_cache.ids() {
             let Some(client) = self.conn_cache.clients.get_mut(&id) else {
                 continue;
@@ -718,6 +763,11 @@ impl<Ctx> NetcodeServer<Ctx> {
             if client.last_send_time + self.cfg.keep_alive_send_rate >= self.time {
                 continue;
             }
+            // the transport already keeps the connection alive. We still need to send keep-alives
+            // until the client is confirmed, because they are used to complete the handshake
+            if io.native_liveness && client.is_confirmed() {
+                continue;
+            }
 
             self.send_to_client(KeepAlivePacket::create(id), id, io)?;
             trace!("server sent connection keep-alive packet to client {id}");
@@ -731,6 +781,9 @@ impl<Ctx> NetcodeServer<Ctx> {
         addr: SocketAddr,
         sender: &mut impl PacketSender,
     ) -> Result<()> {
+        if info::is_info_packet(buf) {
+            return self.process_info_request(buf, addr, sender);
+        }
         if buf.len() <= 1 {
             // TODO: make token request something else than this?
             // if buf == [u8::MAX].as_slice() {
@@ -779,6 +832,45 @@ impl<Ctx> NetcodeServer<Ctx> {
         self.process_packet(addr, packet, sender)
     }
 
+    /// Answer an info request or a query, which don't require the client to be connected
+    fn process_info_request(
+        &mut self,
+        buf: &[u8],
+        addr: SocketAddr,
+        sender: &mut impl PacketSender,
+    ) -> Result<()> {
+        let response = if let Some(nonce) = info::read_query_request(buf) {
+            let Some(query_info) = &self.cfg.query_info else {
+                return Ok(());
+            };
+            let query_info = ServerQueryInfo {
+                num_players: self.num_connected_clients() as u32,
+                protocol_id: self.protocol_id,
+                ..query_info.clone()
+            };
+            info::write_query_response(nonce, &query_info)
+        } else if let Some(nonce) = info::read_request(buf, self.protocol_id) {
+            let Some(server_info) = &self.cfg.server_info else {
+                return Ok(());
+            };
+            let server_info = ServerInfo {
+                num_clients: self.num_connected_clients() as u32,
+                ..server_info.clone()
+            };
+            info::write_response(self.protocol_id, nonce, &server_info)
+        } else {
+            debug!("server ignored invalid info request from {addr}");
+            return Ok(());
+        };
+        if !self.request_limiter.allow(addr.ip(), self.time) {
+            debug!("server ignored info request from {addr} because of the rate limit");
+            return Ok(());
+        }
+        sender.send(&response, &addr).map_err(Error::from)?;
+        trace!("server answered info request from {addr}");
+        Ok(())
+    }
+
     fn recv_packets(
         &mut self,
         sender: &mut impl PacketSender,
@@ -812,8 +904,9 @@ impl<Ctx> NetcodeServer<Ctx> {
     pub fn try_update(&mut self, delta_ms: f64, io: &mut Io) -> Result<()> {
         self.time += delta_ms;
         self.conn_cache.update(delta_ms);
+        let native_liveness = io.native_liveness;
         let (sender, receiver) = io.split();
-        self.check_for_timeouts();
+        self.check_for_timeouts(native_liveness);
         self.recv_packets(sender, receiver)?;
         self.send_packets(io)?;
         Ok(())
@@ -853,6 +946,19 @@ impl<Ctx> NetcodeServer<Ctx> {
     ///
     /// The provided buffer must be smaller than [`MAX_PACKET_SIZE`].
     pub fn send(&mut self, buf: &[u8], client_id: ClientId, io: &mut Io) -> Result<()> {
+        self.send_with_delivery(buf, client_id, io, Delivery::Datagram)
+    }
+
+    /// Sends a packet to a client, using the given [`Delivery`] method if the transport supports it.
+    ///
+    /// The provided buffer must be smaller than [`MAX_PACKET_SIZE`].
+    pub fn send_with_delivery(
+        &mut self,
+        buf: &[u8],
+        client_id: ClientId,
+        io: &mut Io,
+        delivery: Delivery,
+    ) -> Result<()> {
         if buf.len() > MAX_PACKET_SIZE {
             return Err(Error::SizeMismatch(MAX_PACKET_SIZE, buf.len()));
         }
@@ -870,7 +976,7 @@ impl<Ctx> NetcodeServer<Ctx> {
             self.send_to_client(KeepAlivePacket::create(client_id), client_id, io)?;
         }
         let packet = PayloadPacket::create(buf);
-        self.send_to_client(packet, client_id, io)
+        self.send_to_client_with_delivery(packet, client_id, io, delivery)
     }
 
     /// Sends a packet to all connected clients.
@@ -1079,6 +1185,21 @@ impl NetServer for Server {
             .context("could not send packet")
     }
 
+    fn send_with_delivery(
+        &mut self,
+        buf: &[u8],
+        client_id: id::ClientId,
+        delivery: Delivery,
+    ) -> anyhow::Result<()> {
+        let io = self.io.as_mut().context("io is not initialized")?;
+        let id::ClientId::Netcode(client_id) = client_id else {
+            return Err(anyhow!("the client id must be of type Netcode"));
+        };
+        self.server
+            .send_with_delivery(buf, client_id, io, delivery)
+            .context("could not send packet")
+    }
+
     fn new_connections(&self) -> Vec<id::ClientId> {
         self.server.cfg.context.connections.clone()
     }
@@ -1118,6 +1239,17 @@ impl Server {
         cfg = cfg.keep_alive_send_rate(config.keep_alive_send_rate);
         cfg = cfg.num_disconnect_packets(config.num_disconnect_packets);
         cfg = cfg.client_timeout_secs(config.client_timeout_secs);
+        if let Some(data) = config.server_info {
+            cfg = cfg.server_info(ServerInfo {
+                protocol_version: config.protocol_version,
+                num_clients: 0,
+                data,
+            });
+        }
+        if let Some(query_info) = config.query_info {
+            cfg = cfg.query_info(query_info);
+        }
+        cfg = cfg.request_rate_limit(config.request_rate_limit);
         let server = NetcodeServer::with_config(config.protocol_id, config.private_key, cfg)
             .expect("Could not create server netcode");
 
@@ -0,0 +1,224 @@
+//! Client that connects to a server behind a NAT via a [`RendezvousServer`](super::RendezvousServer)
+use std::net::SocketAddr;
+
+use anyhow::{anyhow, Context, Result};
+use crossbeam_channel::Receiver;
+use tracing::{info, trace};
+
+use crate::client::config::NetcodeConfig;
+use crate::client::io::Io;
+use crate::client::networking::NetworkingState;
+use crate::connection::client::{Authentication, IoConfig, NetClient};
+use crate::connection::id::ClientId;
+use crate::connection::netcode::{ConnectToken, NetcodeClient};
+use crate::connection::punch::{
+    PeerRole, RendezvousMessage, RendezvousReceiver, REGISTER_INTERVAL_SECS,
+};
+use crate::packet::packet::Packet;
+use crate::transport::dummy::DummyIo;
+use crate::transport::{PacketReceiver, PacketSender};
+
+/// Tracks the progress of the hole-punching
+enum PunchState {
+    /// We are not trying to connect
+    Idle,
+    /// We are waiting for the rendezvous server to send us the address of the host
+    Punching { since_register_secs: f64 },
+    /// We know the address of the host, the netcode handshake can run
+    Punched,
+}
+
+pub(crate) struct Client {
+    netcode: super::super::netcode::Client<()>,
+    rendezvous_addr: SocketAddr,
+    room_code: String,
+    auth: Authentication,
+    config: NetcodeConfig,
+    state: PunchState,
+    receiver: Option<Receiver<(RendezvousMessage, SocketAddr)>>,
+}
+
+impl Client {
+    pub(crate) fn new(
+        rendezvous_addr: SocketAddr,
+        room_code: String,
+        auth: Authentication,
+        config: NetcodeConfig,
+        io_config: IoConfig,
+    ) -> Result<Self> {
+        // we don't know the address of the server yet, so we start with a placeholder netcode client
+        let token = Authentication::None
+            .get_token(config.client_timeout_secs, config.token_expire_secs)
+            .context("could not generate token")?;
+        Ok(Self {
+            netcode: super::super::netcode::Client {
+                client: Self::netcode_client(token, &config)?,
+                io_config,
+                io: None,
+            },
+            rendezvous_addr,
+            room_code,
+            auth,
+            config,
+            state: PunchState::Idle,
+            receiver: None,
+        })
+    }
+
+    fn netcode_client(token: ConnectToken, config: &NetcodeConfig) -> Result<NetcodeClient<()>> {
+        let token_bytes = token.try_into_bytes()?;
+        NetcodeClient::with_config(&token_bytes, config.build())
+            .context("could not create netcode client")
+    }
+
+    fn io(&mut self) -> Result<&mut Io> {
+        self.netcode
+            .io
+            .as_mut()
+            .context("io is not initialized, did you call connect?")
+    }
+
+    fn register(&mut self) -> Result<()> {
+        let rendezvous_addr = self.rendezvous_addr;
+        let message = RendezvousMessage::Register {
+            role: PeerRole::Client,
+            room_code: self.room_code.clone(),
+        };
+        self.io()?
+            .send(&message.to_bytes(), &rendezvous_addr)
+            .context("could not send registration to the rendezvous server")
+    }
+
+    /// We received the observed address of the host: punch a hole and start the netcode handshake
+    fn start_handshake(&mut self, host_addr: SocketAddr) -> Result<()> {
+        info!(
+            ?host_addr,
+            "received host address from the rendezvous server"
+        );
+        let token = match self.auth.clone() {
+            Authentication::Manual {
+                client_id,
+                private_key,
+                protocol_id,
+                ..
+            } => ConnectToken::build(host_addr, protocol_id, client_id, private_key)
+                .timeout_seconds(self.config.client_timeout_secs)
+                .expire_seconds(self.config.token_expire_secs)
+                .generate()?,
+            // the token must already contain the public address of the host
+            Authentication::Token(token) => token,
+            Authentication::None => return Err(anyhow!("no authentication provided")),
+        };
+        self.netcode.client = Self::netcode_client(token, &self.config)?;
+        self.io()?
+            .send(&RendezvousMessage::Punch.to_bytes(), &host_addr)
+            .context("could not send punch packet")?;
+        self.netcode.client.connect();
+        self.state = PunchState::Punched;
+        Ok(())
+    }
+}
+
+impl NetClient for Client {
+    fn connect(&mut self) -> Result<()> {
+        let mut io = self
+            .netcode
+            .io_config
+            .clone()
+            .connect()
+            .context("could not connect io")?;
+        // intercept the rendezvous messages before they reach the netcode client
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let inner = std::mem::replace(&mut io.receiver, Box::new(DummyIo));
+        io.receiver = Box::new(RendezvousReceiver::new(inner, sender));
+        self.netcode.io = Some(io);
+        self.receiver = Some(receiver);
+        self.state = PunchState::Punching {
+            since_register_secs: 0.0,
+        };
+        self.register()
+    }
+
+    fn disconnect(&mut self) -> Result<()> {
+        if matches!(self.state, PunchState::Punched) {
+            self.netcode.disconnect()?;
+        } else if let Some(mut io) = self.netcode.io.take() {
+            io.close().context("Could not close the io")?;
+        }
+        self.state = PunchState::Idle;
+        self.receiver = None;
+        Ok(())
+    }
+
+    fn state(&self) -> NetworkingState {
+        match self.state {
+            PunchState::Idle => NetworkingState::Disconnected,
+            PunchState::Punching { .. } => NetworkingState::Connecting,
+            PunchState::Punched => self.netcode.state(),
+        }
+    }
+
+    fn try_update(&mut self, delta_ms: f64) -> Result<()> {
+        if let PunchState::Punching {
+            since_register_secs,
+        } = &mut self.state
+        {
+            *since_register_secs += delta_ms;
+            let should_register = *since_register_secs >= REGISTER_INTERVAL_SECS;
+            if should_register {
+                *since_register_secs = 0.0;
+                self.register()?;
+            }
+            // read the packets from the io so that the rendezvous messages get intercepted
+            // (the netcode client is not running yet, so any other packet is dropped)
+            let io = self.io()?;
+            while io.recv()?.is_some() {}
+            let rendezvous_addr = self.rendezvous_addr;
+            let host_addr = self.receiver.as_ref().and_then(|receiver| {
+                receiver
+                    .try_iter()
+                    .find_map(|(message, addr)| match message {
+                        RendezvousMessage::PeerAddress(host_addr) if addr == rendezvous_addr => {
+                            Some(host_addr)
+                        }
+                        _ => None,
+                    })
+            });
+            match host_addr {
+                Some(host_addr) => self.start_handshake(host_addr)?,
+                // the netcode handshake cannot start until we know the address of the host
+                None => return Ok(()),
+            }
+        }
+        if let Some(receiver) = self.receiver.as_ref() {
+            for (message, addr) in receiver.try_iter() {
+                trace!(?message, ?addr, "ignoring rendezvous message");
+            }
+        }
+        self.netcode.try_update(delta_ms)
+    }
+
+    fn recv(&mut self) -> Option<Packet> {
+        self.netcode.recv()
+    }
+
+    fn send(&mut self, buf: &[u8]) -> Result<()> {
+        self.netcode.send(buf)
+    }
+
+    fn id(&self) -> ClientId {
+        self.netcode.id()
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.netcode.local_addr()
+    }
+
+    fn io(&self) -> Option<&Io> {
+        self.netcode.io()
+    }
+
+    fn io_mut(&mut self) -> Option<&mut Io> {
+        self.netcode.io_mut()
+    }
+}
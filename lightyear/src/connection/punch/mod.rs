@@ -0,0 +1,359 @@
+/*! NAT hole-punching on top of the netcode connection
+
+Two peers that are both behind a NAT cannot directly connect to each other, because neither of them
+knows the public address of the other, and the NATs drop any packet that doesn't come from an address they have sent
+packets to.
+
+To work around this, both peers register with a publicly reachable [`RendezvousServer`] using a shared `room_code`:
+- the rendezvous server observes the public address of each peer (as seen after the NAT translation)
+- once both the host (the lightyear server) and the client have registered in the same room, the rendezvous server
+  sends to each peer the observed address of the other peer
+- both peers then send packets to each other, which opens a mapping in their respective NATs ("punching a hole")
+- the regular netcode handshake then runs over the same socket
+
+This only works with a UDP transport, since the socket used to talk to the rendezvous server must be the same as the
+one used for the netcode connection.
+*/
+use std::net::SocketAddr;
+
+use anyhow::{anyhow, Context, Result};
+use bevy::utils::{Duration, HashMap, Instant};
+use crossbeam_channel::Sender;
+use tracing::{debug, trace};
+
+use crate::transport::error::Result as TransportResult;
+use crate::transport::{BoxedReceiver, PacketReceiver, MTU};
+
+#[cfg(feature = "client")]
+pub(crate) mod client;
+#[cfg(feature = "server")]
+pub(crate) mod server;
+
+/// First byte of every rendezvous message.
+///
+/// The lower 4 bits of the first byte of a netcode packet encode the packet type, which is always lower than 7,
+/// so rendezvous messages can never be confused with netcode packets.
+const MAGIC: u8 = 0xFF;
+
+/// How long a host stays registered in a room without sending a new registration
+const HOST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Interval (in seconds) at which peers re-send their registration to the rendezvous server.
+///
+/// This also keeps the NAT mapping between the peers and the rendezvous server alive.
+pub(crate) const REGISTER_INTERVAL_SECS: f64 = 1.0;
+
+/// The role of a peer in a punched connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PeerRole {
+    /// The peer that runs the lightyear server
+    Host,
+    /// The peer that runs the lightyear client
+    Client,
+}
+
+/// Messages exchanged with the [`RendezvousServer`] and between the peers
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RendezvousMessage {
+    /// Sent by a peer to the rendezvous server to join a room
+    Register { role: PeerRole, room_code: String },
+    /// Sent by the rendezvous server to a peer, containing the observed address of the other peer
+    PeerAddress(SocketAddr),
+    /// Sent between the peers to open the NAT mappings
+    Punch,
+}
+
+impl RendezvousMessage {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![MAGIC];
+        match self {
+            RendezvousMessage::Register { role, room_code } => {
+                bytes.push(match role {
+                    PeerRole::Host => 0,
+                    PeerRole::Client => 1,
+                });
+                bytes.extend_from_slice(room_code.as_bytes());
+            }
+            RendezvousMessage::PeerAddress(addr) => {
+                bytes.push(2);
+                bytes.extend_from_slice(addr.to_string().as_bytes());
+            }
+            RendezvousMessage::Punch => bytes.push(3),
+        }
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let [MAGIC, kind, payload @ ..] = bytes else {
+            return Err(anyhow!("not a rendezvous message"));
+        };
+        let payload = std::str::from_utf8(payload).context("invalid rendezvous payload")?;
+        match kind {
+            0 | 1 => Ok(RendezvousMessage::Register {
+                role: if *kind == 0 {
+                    PeerRole::Host
+                } else {
+                    PeerRole::Client
+                },
+                room_code: payload.to_string(),
+            }),
+            2 => Ok(RendezvousMessage::PeerAddress(
+                payload.parse().context("invalid peer address")?,
+            )),
+            3 => Ok(RendezvousMessage::Punch),
+            _ => Err(anyhow!("unknown rendezvous message kind: {kind}")),
+        }
+    }
+
+    /// Returns true if the packet is a rendezvous message
+    pub(crate) fn is_rendezvous(bytes: &[u8]) -> bool {
+        bytes.first() == Some(&MAGIC)
+    }
+}
+
+/// [`PacketReceiver`] that intercepts the rendezvous messages so that they don't reach the netcode layer.
+///
+/// The intercepted messages are forwarded to the punching client or server via a channel.
+pub(crate) struct RendezvousReceiver {
+    inner: BoxedReceiver,
+    sender: Sender<(RendezvousMessage, SocketAddr)>,
+    buffer: [u8; MTU],
+}
+
+impl RendezvousReceiver {
+    pub(crate) fn new(
+        inner: BoxedReceiver,
+        sender: Sender<(RendezvousMessage, SocketAddr)>,
+    ) -> Self {
+        Self {
+            inner,
+            sender,
+            buffer: [0; MTU],
+        }
+    }
+}
+
+impl PacketReceiver for RendezvousReceiver {
+    fn recv(&mut self) -> TransportResult<Option<(&mut [u8], SocketAddr)>> {
+        loop {
+            let Some((buf, addr)) = self.inner.recv()? else {
+                return Ok(None);
+            };
+            if RendezvousMessage::is_rendezvous(buf) {
+                match RendezvousMessage::from_bytes(buf) {
+                    Ok(message) => {
+                        trace!(?message, ?addr, "received rendezvous message");
+                        let _ = self.sender.try_send((message, addr));
+                    }
+                    Err(e) => debug!(?addr, "invalid rendezvous message: {:?}", e),
+                }
+                continue;
+            }
+            let len = buf.len();
+            self.buffer[..len].copy_from_slice(buf);
+            return Ok(Some((&mut self.buffer[..len], addr)));
+        }
+    }
+}
+
+/// Standalone server that lets two peers behind NATs discover each other's public address.
+///
+/// It must be reachable by both peers, for example by running it on a machine with a public IP:
+/// ```rust,no_run
+/// use lightyear::prelude::RendezvousServer;
+///
+/// let server = RendezvousServer::bind("0.0.0.0:5000".parse().unwrap()).unwrap();
+/// server.run().unwrap();
+/// ```
+pub struct RendezvousServer {
+    socket: std::net::UdpSocket,
+    /// Map from the room code to the address of the host that registered the room.
+    /// Only that address can refresh the room until it times out
+    rooms: HashMap<String, (SocketAddr, Instant)>,
+    buffer: [u8; MTU],
+}
+
+impl RendezvousServer {
+    /// Create a new rendezvous server listening on `addr`
+    pub fn bind(addr: SocketAddr) -> Result<Self> {
+        let socket = std::net::UdpSocket::bind(addr).context("could not bind rendezvous socket")?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            rooms: HashMap::default(),
+            buffer: [0; MTU],
+        })
+    }
+
+    /// The local address of the rendezvous server
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Handle all the registrations that were received since the last update, without blocking
+    pub fn update(&mut self) -> Result<()> {
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut self.buffer) {
+                Ok(res) => res,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            };
+            match RendezvousMessage::from_bytes(&self.buffer[..len]) {
+                Ok(RendezvousMessage::Register { role, room_code }) => {
+                    self.register(role, room_code, addr)
+                }
+                Ok(message) => debug!(?message, ?addr, "unexpected rendezvous message"),
+                Err(e) => debug!(?addr, "invalid rendezvous message: {:?}", e),
+            }
+        }
+        self.rooms
+            .retain(|_, (_, last_seen)| last_seen.elapsed() < HOST_TIMEOUT);
+        Ok(())
+    }
+
+    /// Run the rendezvous server forever
+    pub fn run(mut self) -> Result<()> {
+        loop {
+            self.update()?;
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn register(&mut self, role: PeerRole, room_code: String, addr: SocketAddr) {
+        match role {
+            PeerRole::Host => {
+                // only the host that registered the room can refresh it, otherwise any peer could take over
+                // the room by registering with the same code. The room is freed once the host times out.
+                if let Some((host_addr, _)) = self.rooms.get(&room_code) {
+                    if *host_addr != addr {
+                        debug!(?addr, host = ?host_addr, ?room_code, "room is already registered by another host");
+                        return;
+                    }
+                }
+                trace!(?addr, ?room_code, "host registered");
+                self.rooms.insert(room_code, (addr, Instant::now()));
+            }
+            PeerRole::Client => {
+                let Some((host_addr, _)) = self.rooms.get(&room_code) else {
+                    debug!(?addr, ?room_code, "no host registered for this room");
+                    return;
+                };
+                debug!(client = ?addr, host = ?host_addr, ?room_code, "introducing peers");
+                // a peer that cannot be reached must not stop the rendezvous server
+                for (peer_addr, target) in [(addr, *host_addr), (*host_addr, addr)] {
+                    if let Err(e) = self.socket.send_to(
+                        &RendezvousMessage::PeerAddress(peer_addr).to_bytes(),
+                        target,
+                    ) {
+                        debug!(?target, "could not send the peer address: {e:?}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use crate::client::networking::NetworkingState;
+    use crate::connection::client::{Authentication, NetClient, NetConfig as ClientNetConfig};
+    use crate::connection::server::{NetConfig as ServerNetConfig, NetServer};
+    use crate::prelude::client::{ClientTransport, IoConfig as ClientIoConfig};
+    use crate::prelude::server::{IoConfig as ServerIoConfig, ServerTransport};
+    use crate::prelude::{client, server};
+
+    use super::*;
+
+    const LOCALHOST: SocketAddr =
+        SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0);
+
+    #[test]
+    fn test_message_serialization() {
+        for message in [
+            RendezvousMessage::Register {
+                role: PeerRole::Host,
+                room_code: "room".to_string(),
+            },
+            RendezvousMessage::Register {
+                role: PeerRole::Client,
+                room_code: "".to_string(),
+            },
+            RendezvousMessage::PeerAddress(SocketAddr::from(([1, 2, 3, 4], 5000))),
+            RendezvousMessage::Punch,
+        ] {
+            let bytes = message.to_bytes();
+            assert!(RendezvousMessage::is_rendezvous(&bytes));
+            assert_eq!(RendezvousMessage::from_bytes(&bytes).unwrap(), message);
+        }
+        assert!(RendezvousMessage::from_bytes(&[0, 1, 2]).is_err());
+    }
+
+    /// A room can only be refreshed by the host that registered it
+    #[test]
+    fn test_room_cannot_be_hijacked() {
+        let mut rendezvous = RendezvousServer::bind(LOCALHOST).unwrap();
+        let host_addr = SocketAddr::from(([1, 2, 3, 4], 5000));
+        let other_addr = SocketAddr::from(([5, 6, 7, 8], 5000));
+        rendezvous.register(PeerRole::Host, "room".to_string(), host_addr);
+        rendezvous.register(PeerRole::Host, "room".to_string(), other_addr);
+        assert_eq!(rendezvous.rooms.get("room").unwrap().0, host_addr);
+
+        // the original host can still refresh its registration
+        rendezvous.register(PeerRole::Host, "room".to_string(), host_addr);
+        assert_eq!(rendezvous.rooms.get("room").unwrap().0, host_addr);
+    }
+
+    /// Connect a client and a server that only know the address of the rendezvous server
+    #[test]
+    fn test_punched_connection() {
+        let mut rendezvous = RendezvousServer::bind(LOCALHOST).unwrap();
+        let rendezvous_addr = rendezvous.local_addr().unwrap();
+        let protocol_id = 0;
+        let private_key = [3; 32];
+
+        let mut server = ServerNetConfig::Punched {
+            rendezvous_addr,
+            room_code: "room".to_string(),
+            config: server::NetcodeConfig::default()
+                .with_protocol_id(protocol_id)
+                .with_key(private_key),
+            io: ServerIoConfig::from_transport(ServerTransport::UdpSocket(LOCALHOST)),
+        }
+        .build_server();
+        let mut client = ClientNetConfig::Punched {
+            rendezvous_addr,
+            room_code: "room".to_string(),
+            auth: Authentication::Manual {
+                // the server address will be provided by the rendezvous server
+                server_addr: LOCALHOST,
+                client_id: 1,
+                private_key,
+                protocol_id,
+            },
+            config: client::NetcodeConfig::default(),
+            io: ClientIoConfig::from_transport(ClientTransport::UdpSocket(LOCALHOST)),
+        }
+        .build_client()
+        .unwrap();
+
+        server.start().unwrap();
+        client.connect().unwrap();
+        assert_eq!(client.state(), NetworkingState::Connecting);
+        for _ in 0..100 {
+            rendezvous.update().unwrap();
+            server.try_update(0.01).unwrap();
+            client.try_update(0.01).unwrap();
+            if client.state() == NetworkingState::Connected {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.state(), NetworkingState::Connected);
+        assert_eq!(
+            server.connected_client_ids(),
+            vec![crate::prelude::ClientId::Netcode(1)]
+        );
+    }
+}
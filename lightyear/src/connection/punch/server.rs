@@ -0,0 +1,137 @@
+//! Server that accepts connections from clients behind a NAT via a [`RendezvousServer`](super::RendezvousServer)
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use crossbeam_channel::Receiver;
+use tracing::{debug, trace};
+
+use crate::connection::id::ClientId;
+use crate::connection::punch::{
+    PeerRole, RendezvousMessage, RendezvousReceiver, REGISTER_INTERVAL_SECS,
+};
+use crate::connection::server::{IoConfig, NetServer};
+use crate::packet::packet::Packet;
+use crate::server::config::NetcodeConfig;
+use crate::server::io::Io;
+use crate::transport::dummy::DummyIo;
+use crate::transport::PacketSender;
+
+pub(crate) struct Server {
+    netcode: super::super::netcode::Server,
+    rendezvous_addr: SocketAddr,
+    room_code: String,
+    since_register_secs: f64,
+    receiver: Option<Receiver<(RendezvousMessage, SocketAddr)>>,
+}
+
+impl Server {
+    pub(crate) fn new(
+        rendezvous_addr: SocketAddr,
+        room_code: String,
+        config: NetcodeConfig,
+        io_config: IoConfig,
+    ) -> Self {
+        Self {
+            netcode: super::super::netcode::Server::new(config, io_config),
+            rendezvous_addr,
+            room_code,
+            since_register_secs: 0.0,
+            receiver: None,
+        }
+    }
+
+    fn register(&mut self) -> Result<()> {
+        let message = RendezvousMessage::Register {
+            role: PeerRole::Host,
+            room_code: self.room_code.clone(),
+        };
+        self.netcode
+            .io_mut()
+            .context("io is not initialized")?
+            .send(&message.to_bytes(), &self.rendezvous_addr)
+            .context("could not send registration to the rendezvous server")
+    }
+}
+
+impl NetServer for Server {
+    fn start(&mut self) -> Result<()> {
+        self.netcode.start()?;
+        let io = self.netcode.io_mut().context("io is not initialized")?;
+        // intercept the rendezvous messages before they reach the netcode server
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let inner = std::mem::replace(&mut io.receiver, Box::new(DummyIo));
+        io.receiver = Box::new(RendezvousReceiver::new(inner, sender));
+        self.receiver = Some(receiver);
+        self.since_register_secs = 0.0;
+        self.register()
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.receiver = None;
+        self.netcode.stop()
+    }
+
+    fn disconnect(&mut self, client_id: ClientId) -> Result<()> {
+        self.netcode.disconnect(client_id)
+    }
+
+    fn connected_client_ids(&self) -> Vec<ClientId> {
+        self.netcode.connected_client_ids()
+    }
+
+    fn try_update(&mut self, delta_ms: f64) -> Result<()> {
+        // the netcode server reads the io, which forwards the rendezvous messages to us
+        self.netcode.try_update(delta_ms)?;
+
+        // keep the room (and the NAT mapping to the rendezvous server) alive
+        self.since_register_secs += delta_ms;
+        if self.since_register_secs >= REGISTER_INTERVAL_SECS {
+            self.since_register_secs = 0.0;
+            self.register()?;
+        }
+
+        let Some(receiver) = self.receiver.as_ref() else {
+            return Ok(());
+        };
+        let messages: Vec<_> = receiver.try_iter().collect();
+        for (message, addr) in messages {
+            match message {
+                // a client wants to connect: send it a packet to open our NAT to its address
+                RendezvousMessage::PeerAddress(client_addr) if addr == self.rendezvous_addr => {
+                    debug!(?client_addr, "punching a hole towards client");
+                    self.netcode
+                        .io_mut()
+                        .context("io is not initialized")?
+                        .send(&RendezvousMessage::Punch.to_bytes(), &client_addr)
+                        .context("could not send punch packet")?;
+                }
+                message => trace!(?message, ?addr, "ignoring rendezvous message"),
+            }
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Option<(Packet, ClientId)> {
+        self.netcode.recv()
+    }
+
+    fn send(&mut self, buf: &[u8], client_id: ClientId) -> Result<()> {
+        self.netcode.send(buf, client_id)
+    }
+
+    fn new_connections(&self) -> Vec<ClientId> {
+        self.netcode.new_connections()
+    }
+
+    fn new_disconnections(&self) -> Vec<ClientId> {
+        self.netcode.new_disconnections()
+    }
+
+    fn io(&self) -> Option<&Io> {
+        self.netcode.io()
+    }
+
+    fn io_mut(&mut self) -> Option<&mut Io> {
+        self.netcode.io_mut()
+    }
+}
@@ -7,12 +7,12 @@ use crate::connection::id::ClientId;
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
 use crate::connection::steam::server::SteamConfig;
 use crate::packet::packet::Packet;
-use crate::prelude::client::ClientTransport;
 use crate::prelude::server::ServerTransport;
 use crate::prelude::LinkConditionerConfig;
 use crate::server::config::NetcodeConfig;
 use crate::server::io::Io;
 use crate::transport::config::SharedIoConfig;
+use crate::transport::Delivery;
 
 pub trait NetServer: Send + Sync {
     /// Start the server
@@ -41,6 +41,18 @@ pub trait NetServer: Send + Sync {
     /// Send a packet to one of the connected clients
     fn send(&mut self, buf: &[u8], client_id: ClientId) -> Result<()>;
 
+    /// Send a packet to one of the connected clients, using the given [`Delivery`] method if
+    /// the transport supports it
+    fn send_with_delivery(
+        &mut self,
+        buf: &[u8],
+        client_id: ClientId,
+        delivery: Delivery,
+    ) -> Result<()> {
+        let _ = delivery;
+        self.send(buf, client_id)
+    }
+
     fn new_connections(&self) -> Vec<ClientId>;
 
     fn new_disconnections(&self) -> Vec<ClientId>;
@@ -70,6 +82,21 @@ pub enum NetConfig {
         config: SteamConfig,
         conditioner: Option<LinkConditionerConfig>,
     },
+    /// Netcode server that is reachable behind a NAT.
+    ///
+    /// The server registers with the [`RendezvousServer`](crate::prelude::RendezvousServer) at `rendezvous_addr`
+    /// under `room_code`, and punches a hole towards every client that joins the same room.
+    Punched {
+        rendezvous_addr: SocketAddr,
+        room_code: String,
+        config: NetcodeConfig,
+        io: IoConfig,
+    },
+    /// Connection scripted by the test, that doesn't use any sockets
+    #[cfg(any(test, feature = "test-utils"))]
+    Mock {
+        connection: super::mock::server::MockServerConnection,
+    },
 }
 
 impl Default for NetConfig {
@@ -104,6 +131,22 @@ impl NetConfig {
                     server: Box::new(server),
                 }
             }
+            NetConfig::Punched {
+                rendezvous_addr,
+                room_code,
+                config,
+                io,
+            } => {
+                let server =
+                    super::punch::server::Server::new(rendezvous_addr, room_code, config, io);
+                ServerConnection {
+                    server: Box::new(server),
+                }
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            NetConfig::Mock { connection } => ServerConnection {
+                server: Box::new(connection),
+            },
         }
     }
 }
@@ -137,6 +180,15 @@ impl NetServer for ServerConnection {
         self.server.send(buf, client_id)
     }
 
+    fn send_with_delivery(
+        &mut self,
+        buf: &[u8],
+        client_id: ClientId,
+        delivery: Delivery,
+    ) -> Result<()> {
+        self.server.send_with_delivery(buf, client_id, delivery)
+    }
+
     fn new_connections(&self) -> Vec<ClientId> {
         self.server.new_connections()
     }
@@ -217,6 +269,12 @@ impl ServerConnections {
         )
     }
 
+    /// Returns the index (in [`ServerConfig::net`](crate::server::config::ServerConfig::net)) of
+    /// the transport that the client used to connect
+    pub fn client_server_index(&self, client_id: ClientId) -> Option<usize> {
+        self.client_server_map.get(&client_id).copied()
+    }
+
     /// Returns true if the server is currently listening for client packets
     pub(crate) fn is_listening(&self) -> bool {
         self.is_listening
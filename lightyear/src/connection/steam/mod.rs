@@ -1,7 +1,9 @@
 use crate::prelude::LinkConditionerConfig;
 use steamworks::networking_types::{NetworkingConfigEntry, NetworkingConfigValue};
 
+#[cfg(feature = "client")]
 pub(crate) mod client;
+#[cfg(feature = "server")]
 pub(crate) mod server;
 
 // NOTE: it looks like there's SingleClient can actually be called on multiple threads
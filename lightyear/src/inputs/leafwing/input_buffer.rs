@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
 use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
 
 use bevy::ecs::entity::MapEntities;
 use bevy::math::Vec2;
@@ -21,6 +22,9 @@ use crate::shared::tick_manager::Tick;
 
 use super::LeafwingUserAction;
 
+/// Minimum number of ticks between two requests of the full [`ActionState`] to the same client
+const MIN_STATE_REQUEST_INTERVAL_TICKS: i16 = 32;
+
 // NOTE: we can have multiple Actionlike, (each entity could have a different Actionlike),
 //  so we will have a separate InputBuffer for each!
 
@@ -140,6 +144,46 @@ impl<A: LeafwingUserAction> ActionDiff<A> {
         }
     }
 
+    /// Returns the [`ActionDiff`]s that turn the `current` [`ActionState`] into `target`.
+    ///
+    /// Only the pressed state, the value and the axis pair of the actions are compared, so that applying the
+    /// diffs keeps the timing information of `current`.
+    pub(crate) fn between(current: &ActionState<A>, target: &ActionState<A>) -> Vec<ActionDiff<A>> {
+        let mut diffs = vec![];
+        for action in target.keys() {
+            if !target.pressed(&action) {
+                if current.pressed(&action) {
+                    diffs.push(ActionDiff::Released { action });
+                }
+                continue;
+            }
+            if let Some(axis_pair) = target.axis_pair(&action) {
+                if !current.pressed(&action) || current.axis_pair(&action) != Some(axis_pair) {
+                    diffs.push(ActionDiff::AxisPairChanged {
+                        action,
+                        axis_pair: axis_pair.xy(),
+                    });
+                }
+                continue;
+            }
+            let value = target.value(&action);
+            if !current.pressed(&action) || current.value(&action) != value {
+                if value == 1.0 {
+                    diffs.push(ActionDiff::Pressed { action });
+                } else {
+                    diffs.push(ActionDiff::ValueChanged { action, value });
+                }
+            }
+        }
+        // actions that are not present in the target state are released
+        for action in current.keys() {
+            if target.action_data(&action).is_none() && current.pressed(&action) {
+                diffs.push(ActionDiff::Released { action });
+            }
+        }
+        diffs
+    }
+
     /// Applies an [`ActionDiff`] (usually received over the network) to the [`ActionState`].
     ///
     /// This lets you reconstruct an [`ActionState`] from a stream of [`ActionDiff`]s
@@ -177,10 +221,28 @@ impl<A: LeafwingUserAction> ActionDiff<A> {
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Reflect)]
 /// We serialize the inputs by sending only the ActionDiffs of the last few ticks
 /// We will store the last N inputs starting from start_tick (in case of packet loss)
-pub struct InputMessage<A> {
+pub struct InputMessage<A: Actionlike> {
     pub(crate) end_tick: Tick,
     // first element is tick end_tick-N+1, last element is end_tick
     pub(crate) diffs: Vec<(InputTarget, Vec<Vec<ActionDiff<A>>>)>,
+    /// Full [`ActionState`]s at `end_tick`, sent periodically or when the server requests them,
+    /// so that the server can recover if some diffs were lost
+    pub(crate) states: Vec<(InputTarget, ActionState<A>)>,
+}
+
+/// Message sent by the server to request the full [`ActionState`]s of a client, when it notices that
+/// some [`ActionDiff`]s were lost
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct InputStateRequest<A> {
+    _marker: PhantomData<A>,
+}
+
+impl<A> Default for InputStateRequest<A> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Reflect)]
@@ -202,7 +264,9 @@ impl<A: LeafwingUserAction> MapEntities for InputMessage<A> {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
         self.diffs
             .iter_mut()
-            .filter_map(|(entity, _)| {
+            .map(|(entity, _)| entity)
+            .chain(self.states.iter_mut().map(|(entity, _)| entity))
+            .filter_map(|entity| {
                 if let InputTarget::PrePredictedEntity(e) = entity {
                     return Some(e);
                 } else {
@@ -218,14 +282,17 @@ impl<T: LeafwingUserAction> InputMessage<T> {
         Self {
             end_tick,
             diffs: vec![],
+            states: vec![],
         }
     }
 
     // we will always include
     pub fn is_empty(&self) -> bool {
-        self.diffs
-            .iter()
-            .all(|(_, diffs)| diffs.iter().all(|diffs_per_tick| diffs_per_tick.is_empty()))
+        self.states.is_empty()
+            && self
+                .diffs
+                .iter()
+                .all(|(_, diffs)| diffs.iter().all(|diffs_per_tick| diffs_per_tick.is_empty()))
     }
 }
 
@@ -363,6 +430,10 @@ impl<T: LeafwingUserAction> InputBuffer<T> {
 pub(crate) struct ActionDiffBuffer<A: LeafwingUserAction> {
     pub(crate) start_tick: Option<Tick>,
     buffer: VecDeque<HashMap<A, ActionDiff<A>>>,
+    /// Latest full [`ActionState`] received from the client, used to correct the server's state at that tick
+    full_state: Option<(Tick, ActionState<A>)>,
+    /// Tick at which the full [`ActionState`] was last requested from the client
+    last_state_request: Option<Tick>,
 }
 
 impl<A: LeafwingUserAction> Default for ActionDiffBuffer<A> {
@@ -370,6 +441,8 @@ impl<A: LeafwingUserAction> Default for ActionDiffBuffer<A> {
         Self {
             start_tick: None,
             buffer: VecDeque::new(),
+            full_state: None,
+            last_state_request: None,
         }
     }
 }
@@ -470,6 +543,52 @@ impl<A: LeafwingUserAction> ActionDiffBuffer<A> {
         }
     }
 
+    /// Returns true if a message that contains the diffs for the `num_ticks` ticks up to `end_tick` leaves a gap
+    /// with the ticks that were already received, i.e. if the diffs for some ticks were lost.
+    ///
+    /// This can also happen if the client didn't send any message for a while (because it had no diffs to send).
+    pub(crate) fn is_missing_ticks(&self, end_tick: Tick, num_ticks: usize) -> bool {
+        if self.start_tick.is_none() || num_ticks == 0 {
+            return false;
+        }
+        let message_start_tick = end_tick - num_ticks as u16 + 1;
+        message_start_tick > self.end_tick() + 1
+    }
+
+    /// Returns true if the full [`ActionState`] can be requested from the client at `tick`.
+    ///
+    /// The requests are throttled, to avoid sending one request per input message.
+    pub(crate) fn request_full_state(&mut self, tick: Tick) -> bool {
+        if self
+            .last_state_request
+            .is_some_and(|last| tick - last < MIN_STATE_REQUEST_INTERVAL_TICKS)
+        {
+            return false;
+        }
+        self.last_state_request = Some(tick);
+        true
+    }
+
+    /// Store the full [`ActionState`] of the client at `tick`.
+    ///
+    /// The states for ticks that were already applied are ignored.
+    pub(crate) fn set_full_state(&mut self, tick: Tick, state: ActionState<A>) {
+        if self.start_tick.is_some_and(|start_tick| tick < start_tick)
+            || self.full_state.as_ref().is_some_and(|(t, _)| *t >= tick)
+        {
+            return;
+        }
+        self.full_state = Some((tick, state));
+    }
+
+    /// Return the full [`ActionState`] of the client if it was received for `tick` (or an earlier tick)
+    pub(crate) fn pop_full_state(&mut self, tick: Tick) -> Option<ActionState<A>> {
+        if self.full_state.as_ref().is_some_and(|(t, _)| *t <= tick) {
+            return self.full_state.take().map(|(_, state)| state);
+        }
+        None
+    }
+
     // Convert the last N ticks up to end_tick included into a compressed message that we can send to the server
     // Return None if the last N inputs are all Absent
     pub(crate) fn add_to_message(
@@ -569,6 +688,7 @@ mod tests {
             message,
             InputMessage {
                 end_tick: Tick(10),
+                states: vec![],
                 diffs: vec![(
                     InputTarget::Entity(entity),
                     vec![
@@ -634,4 +754,59 @@ mod tests {
         );
         assert_eq!(diff_buffer.get(Tick(12)), vec![]);
     }
+
+    #[test]
+    fn test_diffs_between_states() {
+        let mut current = ActionState::<Action>::default();
+        let mut target = ActionState::<Action>::default();
+        assert_eq!(ActionDiff::between(&current, &target), vec![]);
+
+        // the button stayed pressed on the server
+        current.press(&Action::Jump);
+        assert_eq!(
+            ActionDiff::between(&current, &target),
+            vec![ActionDiff::Released {
+                action: Action::Jump
+            }]
+        );
+
+        target.press(&Action::Jump);
+        target.action_data_mut(&Action::Jump).unwrap().value = 0.5;
+        let diffs = ActionDiff::between(&current, &target);
+        assert_eq!(
+            diffs,
+            vec![ActionDiff::ValueChanged {
+                action: Action::Jump,
+                value: 0.5
+            }]
+        );
+        diffs.into_iter().for_each(|diff| diff.apply(&mut current));
+        assert_eq!(ActionDiff::between(&current, &target), vec![]);
+    }
+
+    #[test]
+    fn test_full_state_sync() {
+        let mut diff_buffer = ActionDiffBuffer::<Action>::default();
+        diff_buffer.update_from_message(Tick(10), vec![vec![]; 5]);
+        assert!(!diff_buffer.is_missing_ticks(Tick(15), 5));
+        // the diffs for the ticks 11 to 16 were lost
+        assert!(diff_buffer.is_missing_ticks(Tick(21), 5));
+
+        // the requests are throttled
+        assert!(diff_buffer.request_full_state(Tick(10)));
+        assert!(!diff_buffer.request_full_state(Tick(11)));
+        assert!(diff_buffer.request_full_state(Tick(10 + MIN_STATE_REQUEST_INTERVAL_TICKS as u16)));
+
+        let mut state = ActionState::<Action>::default();
+        state.press(&Action::Jump);
+        diff_buffer.set_full_state(Tick(8), state.clone());
+        assert_eq!(diff_buffer.pop_full_state(Tick(7)), None);
+        assert_eq!(diff_buffer.pop_full_state(Tick(8)), Some(state.clone()));
+        assert_eq!(diff_buffer.pop_full_state(Tick(8)), None);
+
+        // states for ticks that were already applied are ignored
+        diff_buffer.pop(Tick(8));
+        diff_buffer.set_full_state(Tick(7), state);
+        assert_eq!(diff_buffer.pop_full_state(Tick(9)), None);
+    }
 }
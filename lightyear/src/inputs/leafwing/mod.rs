@@ -8,7 +8,7 @@ use leafwing_input_manager::Actionlike;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-pub use input_buffer::InputMessage;
+pub use input_buffer::{InputMessage, InputStateRequest};
 
 use crate::protocol::BitSerializable;
 
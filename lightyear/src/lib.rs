@@ -36,6 +36,15 @@ In general, you will have to modify some parts of the [`ClientConfig`](prelude::
 Mostly the [`SharedConfig`], which must be the same on both the client and the server, and the `NetConfig` which defines
 how the client and server will communicate.
 
+The client and server plugins are behind the `client` and `server` features, which are both enabled by default.
+A dedicated server binary can disable the client code (prediction, interpolation, input capture, etc.) with:
+```toml
+lightyear = { version = "*", default-features = false, features = ["server"] }
+```
+and a wasm client can similarly only enable the `client` feature. The types that are part of the protocol are compiled
+with either feature, so that the client and server can still share the same protocol module. The host-server mode
+requires both features.
+
 ### Implement the protocol
 
 The [`Protocol`](protocol) is the set of types that can be sent over the network.
@@ -115,6 +124,8 @@ The [`Replicate`](prelude::server::Replicate) bundle contains many components to
 The marker component [`Replicating`] indicates that the entity is getting replicated to a remote peer.
 You can remove the [`Replicating`] component to pause the replication. This will not despawn the entity on the remote world; it will simply
 stop sending replication updates.
+On the server, you can also insert the [`ReplicationPaused`] component: the updates are not sent while it is present, and the
+current value of all the components is sent again when it is removed.
 
 In contrast, the [`ReplicationTarget`] component is used to indicate which clients you want to replicate this entity to.
 If you update the target to exclude a given client, the entity will get despawned on that client.
@@ -182,56 +193,101 @@ pub mod prelude {
     pub use crate::channel::builder::TickBufferChannel;
     pub use crate::channel::builder::{
         Channel, ChannelBuilder, ChannelContainer, ChannelDirection, ChannelMode, ChannelSettings,
-        DefaultUnorderedUnreliableChannel, ReliableSettings,
+        DefaultUnorderedUnreliableChannel, InspectorChannel, ReliableSettings, ResendStrategy,
+        ServerStatsChannel, StreamSettings,
+    };
+    pub use crate::channel::stats::{ChannelStatistics, ChannelStats};
+    pub use crate::channel::stream::{
+        TransferDirection, TransferId, TransferProgress, TransferStatus,
     };
-    pub use crate::client::prediction::prespawn::PreSpawnedPlayerObject;
+    pub use crate::client::components::PreSpawnedPlayerObject;
     pub use crate::connection::id::ClientId;
     pub use crate::connection::netcode::{generate_key, ConnectToken, Key};
+    pub use crate::connection::punch::RendezvousServer;
     #[cfg(feature = "leafwing")]
     pub use crate::inputs::leafwing::LeafwingUserAction;
     pub use crate::inputs::native::UserAction;
-    pub use crate::packet::message::Message;
-    pub use crate::protocol::channel::{AppChannelExt, ChannelKind, ChannelRegistry};
-    pub use crate::protocol::component::{AppComponentExt, ComponentRegistry, Linear};
+    pub use crate::packet::compression::PacketCompression;
+    pub use crate::packet::message::{Message, MessageHandle};
+    pub use crate::protocol::channel::{
+        AppChannelExt, ChannelKind, ChannelRegistry, RegisterChannelExt,
+    };
+    pub use crate::protocol::component::{
+        AppComponentExt, ComponentRegistry, Linear, MispredictionMetricFn,
+        RegisterDynamicComponentExt, ReplicateIfFn, ReplicationContext, ValidateFn, Validation,
+    };
     pub use crate::protocol::message::{AppMessageExt, MessageRegistry};
     pub use crate::protocol::serialize::AppSerializeExt;
-    pub use crate::shared::config::{Mode, SharedConfig};
+    pub use crate::protocol::DeniedReason;
+    pub use crate::serialize::quantize::{CompressedQuat, Quantized, QuatCompression};
+    pub use crate::server::inspector::{InspectedEntity, InspectorRequest, InspectorSnapshot};
+    pub use crate::server::stats::ServerStats;
+    pub use crate::shared::config::{Mode, NetworkScheduleConfig, SharedConfig};
     pub use crate::shared::input::InputPlugin;
     #[cfg(feature = "leafwing")]
     pub use crate::shared::input_leafwing::LeafwingInputPlugin;
     pub use crate::shared::ping::manager::PingConfig;
     pub use crate::shared::plugin::{NetworkIdentity, SharedPlugin};
+    pub use crate::shared::replication::commands::RefreshComponentExt;
     pub use crate::shared::replication::components::{
-        DisabledComponent, OverrideTargetComponent, PrePredicted, ReplicateHierarchy,
-        ReplicateOnceComponent, Replicated, Replicating, ReplicationGroup, ReplicationTarget,
-        ShouldBePredicted, TargetEntity, VisibilityMode,
+        DisableReplicateHierarchy, DisabledComponent, NetworkId, OverrideTargetComponent,
+        PrePredicted, RefreshComponent, ReplicateDisabled, ReplicateHierarchy, ReplicateIf,
+        ReplicateOnceComponent, Replicated, Replicating, ReplicationGroup, ReplicationPaused,
+        ReplicationTarget, ShouldBePredicted, TargetEntity, VisibilityMode,
     };
+    pub use crate::shared::replication::delta::Diffable;
     pub use crate::shared::replication::entity_map::RemoteEntityMap;
     pub use crate::shared::replication::hierarchy::ParentSync;
     pub use crate::shared::replication::network_target::NetworkTarget;
     pub use crate::shared::replication::resources::{
         ReplicateResourceExt, ReplicateResourceMetadata, StopReplicateResourceExt,
     };
+    pub use crate::shared::replication::{
+        DespawnReason, UnknownEntityUpdatePolicy, UnknownEntityUpdateStats,
+    };
+    pub use crate::shared::scheduled_events::{
+        EventScheduler, ScheduledEvent, ScheduledEventPlugin,
+    };
     pub use crate::shared::sets::{FixedUpdateSet, MainSet};
     pub use crate::shared::tick_manager::TickManager;
     pub use crate::shared::tick_manager::{Tick, TickConfig};
     pub use crate::shared::time_manager::TimeManager;
+    pub use crate::shared::timings::{NetworkTimings, PeerTimings, TimedPhase};
+    pub use crate::transport::config::{LivenessConfig, SocketConfig, SocketSharding};
+    pub use crate::transport::io::IoDiagnosticsConfig;
     pub use crate::transport::middleware::compression::CompressionConfig;
     pub use crate::transport::middleware::conditioner::LinkConditionerConfig;
+    pub use crate::transport::relay::RelayServer;
+    pub use crate::transport::Delivery;
 
+    #[cfg(feature = "client")]
     pub mod client {
+        pub use crate::client::background::{BackgroundConfig, BackgroundMode};
         pub use crate::client::components::{
-            ComponentSyncMode, Confirmed, LerpFn, SyncComponent, SyncMetadata,
+            ComponentSyncMode, Confirmed, LerpFn, MapEntitiesTarget, SyncComponent, SyncMetadata,
+        };
+        pub use crate::client::config::{
+            ClientConfig, NetcodeConfig, PacketConfig, ReplicationConfig,
         };
-        pub use crate::client::config::{ClientConfig, NetcodeConfig, PacketConfig};
         pub use crate::client::connection::ConnectionManager;
+        pub use crate::client::disable::AppDisableExt;
         pub use crate::client::events::{
             ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
-            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageEvent,
+            DisconnectEvent, EntityDespawnEvent, EntityEnteredInterest, EntityLeftInterest,
+            EntitySpawnEvent, FragmentProgressEvent, InputEvent, MessageAckedEvent, MessageEvent,
+            MessageExpiredEvent, TransferProgressEvent, UnknownEntityUpdateEvent,
         };
-        pub use crate::client::input::{InputConfig, InputManager, InputSystemSet};
+        pub use crate::client::fallback::ConnectionFallback;
+        pub use crate::client::input::{CoalesceFn, InputConfig, InputManager, InputSystemSet};
         #[cfg(feature = "leafwing")]
-        pub use crate::client::input_leafwing::{LeafwingInputConfig, ToggleActions};
+        pub use crate::client::input_leafwing::{
+            InputCoalescing, LeafwingInputConfig, ToggleActions,
+        };
+        pub use crate::client::inspector::InspectorClientPlugin;
+        pub use crate::client::interpolation::diagnostics::{
+            InterpolationBufferIssue, InterpolationBufferStats, InterpolationBufferWarning,
+            InterpolationDiagnosticsPlugin,
+        };
         pub use crate::client::interpolation::interpolation_history::ConfirmedHistory;
         pub use crate::client::interpolation::plugin::{
             InterpolationConfig, InterpolationDelay, InterpolationSet,
@@ -245,46 +301,102 @@ pub mod prelude {
         pub use crate::client::plugin::ClientPlugins;
         pub use crate::client::prediction::correction::Correction;
         pub use crate::client::prediction::despawn::PredictionDespawnCommandsExt;
+        pub use crate::client::prediction::diagnostics::{
+            ComponentPredictionStats, PredictionDiagnosticsPlugin, PredictionStats, RollbackStats,
+        };
+        pub use crate::client::prediction::extrapolation::{AppExtrapolationExt, ExtrapolateFn};
         pub use crate::client::prediction::plugin::is_in_rollback;
         pub use crate::client::prediction::plugin::{PredictionConfig, PredictionSet};
-        pub use crate::client::prediction::rollback::{Rollback, RollbackState};
+        pub use crate::client::prediction::resource_history::AppResourceRollbackExt;
+        pub use crate::client::prediction::rollback::{
+            Rollback, RollbackEnd, RollbackGroup, RollbackStart, RollbackState, SkipRollback,
+        };
+        pub use crate::client::prediction::visual_correction::{
+            CorrectionPlugin, VisualCorrection,
+        };
         pub use crate::client::prediction::Predicted;
+        pub use crate::client::reconnect::{
+            ReconnectAttemptEvent, ReconnectAuthFn, ReconnectConfig, ReconnectFailedEvent,
+            ReconnectedEvent, ReconnectingEvent,
+        };
         pub use crate::client::replication::commands::DespawnReplicationCommandExt;
         pub use crate::client::replication::send::Replicate;
         pub use crate::client::sync::SyncConfig;
+        pub use crate::client::transition::{
+            SyncTransition, SyncTransitionConfig, SyncTransitionSet,
+        };
         pub use crate::connection::client::{
             Authentication, ClientConnection, IoConfig, NetClient, NetConfig,
         };
+        #[cfg(any(test, feature = "test-utils"))]
+        pub use crate::connection::mock::client::MockClientConnection;
         #[cfg(all(feature = "steam", not(target_family = "wasm")))]
         pub use crate::connection::steam::client::SteamConfig;
+        #[cfg(feature = "websocket")]
+        pub use crate::transport::proxy::{ProxyConfig, ProxyCredentials};
+        #[cfg(feature = "websocket")]
+        pub use crate::transport::websocket::WebSocketClientTls;
     }
+    #[cfg(feature = "server")]
     pub mod server {
         #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
         pub use wtransport::tls::Identity;
 
+        #[cfg(any(test, feature = "test-utils"))]
+        pub use crate::connection::mock::server::MockServerConnection;
         pub use crate::connection::server::{
             IoConfig, NetConfig, NetServer, ServerConnection, ServerConnections,
         };
         #[cfg(all(feature = "steam", not(target_family = "wasm")))]
         pub use crate::connection::steam::server::SteamConfig;
-        pub use crate::server::clients::ControlledEntities;
-        pub use crate::server::config::{NetcodeConfig, PacketConfig, ServerConfig};
-        pub use crate::server::connection::ConnectionManager;
+        pub use crate::server::bandwidth_estimate::{BandwidthEstimate, BandwidthEstimateConfig};
+        pub use crate::server::clients::{
+            ConnectedClient, ControlledEntities, ReplicationPriorities, SendQueues, VisibleEntities,
+        };
+        pub use crate::server::config::{
+            LateJoinReplication, NetcodeConfig, PacketConfig, ReplicationConfig, ServerConfig,
+        };
+        pub use crate::server::connection::{ConnectionManager, TargetHandle};
+        #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+        pub use crate::server::events::CertificateRotatedEvent;
         pub use crate::server::events::{
             ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
-            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageEvent,
+            ControlGained, ControlLost, DisconnectEvent, EntityDespawnEvent,
+            EntityHiddenFromClient, EntitySpawnEvent, EntityVisibleToClient, FragmentProgressEvent,
+            InputEvent, MessageAckedEvent, MessageEvent, MessageExpiredEvent,
+            TransferProgressEvent, UnknownEntityUpdateEvent,
         };
+        #[cfg(feature = "leafwing")]
+        pub use crate::server::input_leafwing::SharedActionStates;
+        pub use crate::server::inspector::InspectorConfig;
         pub use crate::server::io::config::ServerTransport;
         pub use crate::server::io::Io;
         pub use crate::server::networking::{NetworkingState, ServerCommands};
+        pub use crate::server::persistence::{SavedEntity, SavedWorld, ServerReplication};
         pub use crate::server::plugin::ServerPlugins;
-        pub use crate::server::replication::commands::DespawnReplicationCommandExt;
+        pub use crate::server::priority::ReplicationPriorityScorer;
+        pub use crate::server::protocols::AppProtocolExt;
+        pub use crate::server::replication::commands::{
+            DespawnReplicationCommandExt, ServerReplicationCommandExt,
+        };
         pub use crate::server::replication::{
             send::{ControlledBy, Replicate, ServerFilter, SyncTarget, Visibility},
             ServerReplicationSet,
         };
+        pub use crate::server::speedhack::{SpeedHackConfig, SpeedHackEvent};
+        pub use crate::server::stats::{DurationPercentiles, ServerStatsConfig};
         pub use crate::server::visibility::immediate::VisibilityManager;
         pub use crate::server::visibility::room::{RoomId, RoomManager};
+        pub use crate::server::visibility::spatial::{
+            ClientView, InterestAnchor, SpatialInterestConfig, SpatialInterestPlugin,
+            SpatialPosition,
+        };
+        pub use crate::server::visibility::streaming::{
+            StreamedChunk, WorldStreaming, WorldStreamingConfig, WorldStreamingPlugin,
+        };
+        pub use crate::shared::events::network_events::ToClients;
+        #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
+        pub use crate::transport::websocket::server::WebSocketServerTls;
     }
 }
 
@@ -307,7 +419,7 @@ pub mod server;
 
 pub mod shared;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 pub(crate) mod tests;
 
 /// Provides an abstraction over an unreliable transport
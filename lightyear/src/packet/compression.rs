@@ -0,0 +1,214 @@
+//! Compression of the packets after they are assembled
+//!
+//! The algorithm is negotiated when the client connects: each peer announces the algorithms that it can
+//! decompress, and a peer only starts compressing its packets once the remote announced that it supports
+//! the configured [`PacketCompression`]. This means that peers that don't support compression (for example
+//! older clients) keep receiving uncompressed packets.
+//!
+//! A compressed packet is only sent if it is smaller than the uncompressed packet.
+#[cfg(any(feature = "lz4", feature = "zstd"))]
+use anyhow::anyhow;
+use anyhow::bail;
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::connection::netcode::MAX_PACKET_SIZE;
+
+/// Bitfield of the [`PacketCompression`] algorithms that a peer can decompress
+pub type SupportedCompressions = u8;
+
+#[cfg(feature = "lz4")]
+const LZ4: SupportedCompressions = 1 << 0;
+#[cfg(feature = "zstd")]
+const ZSTD: SupportedCompressions = 1 << 1;
+
+/// Algorithm used to compress the packets sent to the remote peer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum PacketCompression {
+    /// The packets are not compressed
+    #[default]
+    None,
+    /// Fast compression with a lower compression ratio
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Better compression ratio, at a higher CPU cost
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+}
+
+impl PacketCompression {
+    /// The algorithms that this peer can decompress
+    pub(crate) fn supported() -> SupportedCompressions {
+        #[allow(unused_mut)]
+        let mut supported = 0;
+        #[cfg(feature = "lz4")]
+        {
+            supported |= LZ4;
+        }
+        #[cfg(feature = "zstd")]
+        {
+            supported |= ZSTD;
+        }
+        supported
+    }
+
+    fn flag(&self) -> SupportedCompressions {
+        match self {
+            PacketCompression::None => 0,
+            #[cfg(feature = "lz4")]
+            PacketCompression::Lz4 => LZ4,
+            #[cfg(feature = "zstd")]
+            PacketCompression::Zstd { .. } => ZSTD,
+        }
+    }
+
+    /// Returns the algorithm to use to compress the packets sent to a remote that can decompress `remote`
+    pub(crate) fn negotiate(&self, remote: SupportedCompressions) -> PacketCompression {
+        if self.flag() & remote != 0 {
+            *self
+        } else {
+            PacketCompression::None
+        }
+    }
+}
+
+/// Compresses the packets with the algorithm that was negotiated with the remote peer
+pub(crate) struct PacketCompressor {
+    algorithm: PacketCompression,
+    #[cfg(feature = "zstd")]
+    zstd: Option<zstd::bulk::Compressor<'static>>,
+}
+
+impl PacketCompressor {
+    pub(crate) fn new(algorithm: PacketCompression) -> anyhow::Result<Self> {
+        Ok(Self {
+            algorithm,
+            #[cfg(feature = "zstd")]
+            zstd: match algorithm {
+                PacketCompression::Zstd { level } => Some(zstd::bulk::Compressor::new(level)?),
+                _ => None,
+            },
+        })
+    }
+
+    pub(crate) fn algorithm(&self) -> PacketCompression {
+        self.algorithm
+    }
+
+    /// Compress the encoded packet. The first byte identifies the algorithm that was used
+    pub(crate) fn compress(&mut self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut compressed = vec![self.algorithm.flag()];
+        match self.algorithm {
+            PacketCompression::None => compressed.extend_from_slice(data),
+            #[cfg(feature = "lz4")]
+            PacketCompression::Lz4 => {
+                compressed.extend(lz4_flex::block::compress_prepend_size(data));
+            }
+            #[cfg(feature = "zstd")]
+            PacketCompression::Zstd { .. } => {
+                let compressor = self
+                    .zstd
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("zstd compressor not initialized"))?;
+                compressed.extend(compressor.compress(data)?);
+            }
+        }
+        Ok(compressed)
+    }
+}
+
+/// Decompresses the packets received from the remote peer, with any of the algorithms that this peer supports
+#[derive(Default)]
+pub(crate) struct PacketDecompressor {
+    #[cfg(feature = "zstd")]
+    zstd: Option<zstd::bulk::Decompressor<'static>>,
+}
+
+impl PacketDecompressor {
+    pub(crate) fn decompress(&mut self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.decompress_with_max_size(data, MAX_PACKET_SIZE)
+    }
+
+    /// Decompress data that can be bigger than a packet once decompressed (at most `max_size` bytes)
+    pub(crate) fn decompress_with_max_size(
+        &mut self,
+        data: &[u8],
+        max_size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let Some((&flag, data)) = data.split_first() else {
+            bail!("empty compressed packet");
+        };
+        match flag {
+            #[cfg(feature = "lz4")]
+            LZ4 => {
+                // check the decompressed size before allocating
+                let size = data
+                    .get(..4)
+                    .map(|size| u32::from_le_bytes(size.try_into().unwrap()) as usize)
+                    .ok_or_else(|| anyhow!("invalid lz4 packet"))?;
+                if size > max_size {
+                    bail!("decompressed packet is too big: {size} bytes");
+                }
+                Ok(lz4_flex::block::decompress_size_prepended(data)?)
+            }
+            #[cfg(feature = "zstd")]
+            ZSTD => {
+                if self.zstd.is_none() {
+                    self.zstd = Some(zstd::bulk::Decompressor::new()?);
+                }
+                Ok(self.zstd.as_mut().unwrap().decompress(data, max_size)?)
+            }
+            // not compressed
+            0 => {
+                if data.len() > max_size {
+                    bail!("packet is too big: {} bytes", data.len());
+                }
+                Ok(data.to_vec())
+            }
+            _ => bail!("unsupported compression algorithm: {flag}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_compression() {
+        assert_eq!(
+            PacketCompression::default().negotiate(PacketCompression::supported()),
+            PacketCompression::None
+        );
+        #[cfg(feature = "lz4")]
+        {
+            assert_eq!(
+                PacketCompression::Lz4.negotiate(PacketCompression::supported()),
+                PacketCompression::Lz4
+            );
+            // the remote doesn't support compression
+            assert_eq!(PacketCompression::Lz4.negotiate(0), PacketCompression::None);
+        }
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_lz4_compression() -> anyhow::Result<()> {
+        let data = vec![7u8; 500];
+        let compressed = PacketCompressor::new(PacketCompression::Lz4)?.compress(&data)?;
+        assert!(compressed.len() < data.len());
+        assert_eq!(PacketDecompressor::default().decompress(&compressed)?, data);
+        Ok(())
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_compression() -> anyhow::Result<()> {
+        let data = vec![7u8; 500];
+        let compressed =
+            PacketCompressor::new(PacketCompression::Zstd { level: 0 })?.compress(&data)?;
+        assert!(compressed.len() < data.len());
+        assert_eq!(PacketDecompressor::default().decompress(&compressed)?, data);
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
 use serde::{Deserialize, Serialize};
 use tracing::trace;
@@ -43,12 +43,23 @@ impl PacketHeader {
     pub fn get_packet_type(&self) -> PacketType {
         self.packet_type
     }
+
+    /// The same header, for a packet whose content is compressed
+    pub(crate) fn to_compressed(&self) -> Self {
+        Self {
+            packet_type: PacketType::Compressed,
+            ..self.clone()
+        }
+    }
 }
 
 // we can only send acks for the last 32 packets ids before the last received packet
 const ACK_BITFIELD_SIZE: u8 = 32;
 // we can only buffer up to `MAX_SEND_PACKET_QUEUE_SIZE` packets for sending
 const MAX_SEND_PACKET_QUEUE_SIZE: u8 = 255;
+// a sent packet is considered lost if the remote acked a packet that was sent at least `NACK_THRESHOLD`
+// packets after it, but not the packet itself
+const NACK_THRESHOLD: u8 = 3;
 const CLEAR_UNACKED_PACKETS_DELAY: chrono::Duration = chrono::Duration::milliseconds(5000);
 
 /// Keeps track of sent and received packets to be able to write the packet headers correctly
@@ -62,6 +73,8 @@ pub struct PacketHeaderManager {
     // so we can resend them when dropped
     // sent_packets_not_acked: HashSet<PacketId>,
     sent_packets_not_acked: HashMap<PacketId, WrappedTime>,
+    // packets that were not acked yet and that we already reported as lost
+    nacked_packets: HashSet<PacketId>,
     stats_manager: PacketStatsManager,
 
     // channel to notify the sender of the packet_id of the packets that were delivered
@@ -84,6 +97,7 @@ impl PacketHeaderManager {
             stats_manager: PacketStatsManager::default(),
             // sent_packets_not_acked: HashSet::with_capacity(MAX_SEND_PACKET_QUEUE_SIZE as usize),
             sent_packets_not_acked: HashMap::new(),
+            nacked_packets: HashSet::new(),
             recv_buffer: ReceiveBuffer::new(),
             // ack_notification_sender,
             // ack_notification_receiver,
@@ -99,6 +113,7 @@ impl PacketHeaderManager {
             if self.current_time - (*time_sent) > CLEAR_UNACKED_PACKETS_DELAY {
                 trace!("sent packet got lost");
                 self.stats_manager.sent_packet_lost();
+                self.nacked_packets.remove(packet_id);
                 return false;
             }
             true
@@ -111,6 +126,11 @@ impl PacketHeaderManager {
     //     &self.ack_notification_receiver
     // }
 
+    /// Time of the last update
+    pub(crate) fn current_time(&self) -> WrappedTime {
+        self.current_time
+    }
+
     /// Return the packet id of the next packet to be sent
     pub fn next_packet_id(&self) -> PacketId {
         self.next_packet_id
@@ -128,8 +148,12 @@ impl PacketHeaderManager {
 
     /// Process the header of a received packet (update ack metadata)
     ///
-    /// Returns the list of packets that have been newly acked by the remote
-    pub(crate) fn process_recv_packet_header(&mut self, header: &PacketHeader) -> Vec<PacketId> {
+    /// Returns the list of packets that have been newly acked by the remote, and the list of
+    /// packets that have probably been lost (the remote acked more recent packets but not these ones)
+    pub(crate) fn process_recv_packet_header(
+        &mut self,
+        header: &PacketHeader,
+    ) -> (Vec<PacketId>, Vec<PacketId>) {
         // update the receive buffer
         self.stats_manager.received_packet();
         self.recv_buffer.recv_packet(header.packet_id);
@@ -151,7 +175,19 @@ impl PacketHeaderManager {
                 }
             }
         }
-        newly_acked_packets
+
+        let mut newly_nacked_packets = Vec::new();
+        for i in NACK_THRESHOLD..=ACK_BITFIELD_SIZE {
+            let packet_id = PacketId(header.last_ack_packet_id.wrapping_sub(i as u16));
+            if !header.get_bitfield_bit(i - 1)
+                && self.sent_packets_not_acked.contains_key(&packet_id)
+                && self.nacked_packets.insert(packet_id)
+            {
+                trace!(?packet_id, "sent packet was nacked");
+                newly_nacked_packets.push(packet_id);
+            }
+        }
+        (newly_acked_packets, newly_nacked_packets)
     }
 
     /// Update the list of sent packets that have not been acked yet
@@ -166,6 +202,7 @@ impl PacketHeaderManager {
             // self.ack_notification_sender.send(*packet_id)?;
 
             self.sent_packets_not_acked.remove(packet_id);
+            self.nacked_packets.remove(packet_id);
             return Some(*packet_id);
         }
         None
@@ -344,6 +381,35 @@ mod tests {
         assert_eq!(recv_buffer.get_bitfield(), 1 << (32 - 1));
     }
 
+    #[test]
+    fn test_nacked_packets() {
+        let mut manager = PacketHeaderManager::new();
+        // send packets 0 to 4
+        for _ in 0..5 {
+            manager.prepare_send_packet_header(PacketType::Data);
+        }
+        // the remote received packets 0, 2, 3 and 4 but not 1
+        let header = PacketHeader {
+            packet_type: PacketType::Data,
+            packet_id: PacketId(0),
+            last_ack_packet_id: PacketId(4),
+            ack_bitfield: 0b1011,
+            tick: Tick(0),
+        };
+        let (acked, nacked) = manager.process_recv_packet_header(&header);
+        assert_eq!(acked.len(), 4);
+        assert_eq!(nacked, vec![PacketId(1)]);
+
+        // the packet is only reported as lost once
+        let header = PacketHeader {
+            packet_id: PacketId(1),
+            ..header
+        };
+        let (acked, nacked) = manager.process_recv_packet_header(&header);
+        assert!(acked.is_empty());
+        assert!(nacked.is_empty());
+    }
+
     #[test]
     fn test_serde_header() -> anyhow::Result<()> {
         let header = PacketHeader {
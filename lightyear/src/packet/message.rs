@@ -7,7 +7,7 @@ use serde::Serialize;
 
 use bitcode::encoding::{Fixed, Gamma};
 
-use crate::packet::packet::FRAGMENT_SIZE;
+use crate::protocol::channel::ChannelKind;
 use crate::protocol::{BitSerializable, EventContext};
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
@@ -23,6 +23,16 @@ use crate::utils::wrapping_id::wrapping_id;
 // Internal id that we assign to each message sent over the network
 wrapping_id!(MessageId);
 
+/// Handle to a message sent on a channel that tracks acks, that can be used to know when the
+/// remote has received the message.
+///
+/// See for example [`send_message_with_handle`](crate::client::connection::ConnectionManager::send_message_with_handle)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageHandle {
+    pub channel: ChannelKind,
+    pub message_id: MessageId,
+}
+
 // TODO: for now messages must be able to be used as events, since we output them in our message events
 /// A [`Message`] is basically any type that can be (de)serialized over the network.
 ///
@@ -162,6 +172,29 @@ impl SingleData {
     }
 }
 
+/// Progress of a fragmented message sent on a reliable channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FragmentProgress {
+    pub message_id: MessageId,
+    /// Number of fragments that were acked by the remote
+    pub fragments_acked: usize,
+    pub num_fragments: usize,
+}
+
+impl FragmentProgress {
+    /// Fraction of the fragments that were acked, between 0.0 and 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.num_fragments == 0 {
+            return 1.0;
+        }
+        self.fragments_acked as f32 / self.num_fragments as f32
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.fragments_acked == self.num_fragments
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct FragmentData {
     // we always need a message_id for fragment messages, for re-assembly
@@ -186,16 +219,10 @@ impl FragmentData {
         writer.encode(&self.fragment_id, Gamma)?;
         writer.encode(&self.num_fragments, Gamma)?;
         // TODO: be able to just concat the bytes to the buffer?
-        if self.is_last_fragment() {
-            // writing the slice includes writing the length of the slice
-            writer.encode(self.bytes.as_ref(), Fixed)?;
-            // writer.serialize(&self.bytes.to_vec());
-            // writer.serialize(&self.fragment_message_bytes.as_ref());
-        } else {
-            let bytes_array: [u8; FRAGMENT_SIZE] = self.bytes.as_ref().try_into().unwrap();
-            // Serde does not handle arrays well (https://github.com/serde-rs/serde/issues/573)
-            writer.encode(&bytes_array, Fixed)?;
-        }
+        // writing the slice includes writing the length of the slice.
+        // We cannot use a fixed-size array for the non-last fragments because the fragment size
+        // depends on the packet size of the connection
+        writer.encode(self.bytes.as_ref(), Fixed)?;
         let num_bits_written = writer.num_bits_written() - num_bits_before;
         Ok(num_bits_written)
     }
@@ -208,24 +235,8 @@ impl FragmentData {
         let tick = reader.decode::<Option<Tick>>(Fixed)?;
         let fragment_id = reader.decode::<FragmentIndex>(Gamma)?;
         let num_fragments = reader.decode::<FragmentIndex>(Gamma)?;
-        let bytes = if fragment_id == num_fragments - 1 {
-            // let num_bytes = reader.decode::<usize>(Gamma)?;
-            // let num_bytes_non_zero = std::num::NonZeroUsize::new(num_bytes)
-            //     .ok_or_else(|| anyhow::anyhow!("num_bytes is 0"))?;
-            // let read_bytes = reader.read_bytes(num_bytes_non_zero)?;
-            // reader.
-            // let read_bytes = reader.decode::<&[u8]>()?;
-            // TODO: avoid the extra copy
-            //  - maybe have the encoding of bytes be
-            let read_bytes = reader.decode::<Vec<u8>>(Fixed)?;
-            Bytes::from(read_bytes)
-        } else {
-            // Serde does not handle arrays well (https://github.com/serde-rs/serde/issues/573)
-            let read_bytes = reader.decode::<[u8; FRAGMENT_SIZE]>(Fixed)?;
-            // TODO: avoid the extra copy
-            let bytes_vec: Vec<u8> = read_bytes.to_vec();
-            Bytes::from(bytes_vec)
-        };
+        // TODO: avoid the extra copy
+        let bytes = Bytes::from(reader.decode::<Vec<u8>>(Fixed)?);
         Ok(Self {
             message_id,
             tick,
@@ -1,8 +1,9 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use anyhow::{anyhow, Context};
 use bevy::ptr::UnsafeCellDeref;
 use bevy::reflect::Reflect;
+use bevy::utils::Duration;
 use bytes::Bytes;
 use crossbeam_channel::Receiver;
 use tracing::{error, info, trace};
@@ -10,23 +11,34 @@ use tracing::{error, info, trace};
 use bitcode::buffer::BufferTrait;
 use bitcode::word_buffer::WordBuffer;
 
-use crate::channel::builder::ChannelContainer;
-use crate::channel::receivers::ChannelReceive;
-use crate::channel::senders::ChannelSend;
-use crate::packet::message::{FragmentData, MessageAck, MessageId, SingleData};
-use crate::packet::packet::{Packet, PacketId, MTU_PAYLOAD_BYTES};
+use crate::channel::builder::{ChannelContainer, NackChannel};
+use crate::channel::receivers::nack::NackMessage;
+use crate::channel::receivers::{ChannelReceive, ChannelReceiver};
+use crate::channel::senders::{ChannelSend, ChannelSender};
+use crate::channel::stats::ChannelStatistics;
+use crate::channel::stream::{TransferId, TransferProgress};
+use crate::connection::netcode::MAX_PACKET_SIZE;
+use crate::packet::compression::{PacketCompression, PacketCompressor, PacketDecompressor};
+use crate::packet::message::{
+    FragmentData, FragmentProgress, MessageAck, MessageContainer, MessageHandle, MessageId,
+    SingleData,
+};
+use crate::packet::mtu::{MtuConfig, MtuDiscovery, PacketSizing};
+use crate::packet::packet::{Packet, PacketData, PacketId, MTU_PAYLOAD_BYTES};
 use crate::packet::packet_manager::{PacketBuilder, Payload, PACKET_BUFFER_CAPACITY};
 use crate::packet::priority_manager::{PriorityConfig, PriorityManager};
 use crate::protocol::channel::{ChannelKind, ChannelRegistry};
 use crate::protocol::registry::NetId;
 use crate::protocol::BitSerializable;
-use crate::serialize::bitcode::reader::BufferPool;
+use crate::serialize::bitcode::reader::{BitcodeReader, BufferPool};
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::RawData;
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
+use crate::transport::Delivery;
+use crate::utils::wrapping_id::wrapping_diff;
 
 // TODO: hard to split message manager into send/receive because the acks need both the send side and receive side
 //  maybe have a separate actor for acks?
@@ -45,19 +57,150 @@ pub struct MessageManager {
     /// Map to keep track of which messages have been sent in which packets, so that
     /// reliable senders can stop trying to send a message that has already been received
     packet_to_message_ack_map: HashMap<PacketId, HashMap<ChannelKind, Vec<MessageAck>>>,
+    /// If set, we are searching for the maximum packet size that can reach the remote
+    mtu_discovery: Option<MtuDiscovery>,
+    /// Acks of the messages that were sent with a [`MessageHandle`]
+    ack_trackers: HashMap<ChannelKind, AckTracker>,
+    /// Set once the remote announced that it can decompress the configured [`PacketCompression`]
+    compressor: Option<PacketCompressor>,
+    decompressor: PacketDecompressor,
+}
+
+/// Number of message ids after which a message that is still not acked stops being tracked (it was most likely lost).
+///
+/// It is below half of the range of the ids, so that the handle of an old message cannot be confused with a message
+/// that was sent after the ids wrapped around.
+const MAX_TRACKED_ACK_AGE: i16 = 1 << 14;
+
+/// Keeps track of the messages of a channel that were sent with a [`MessageHandle`] and are not acked yet
+struct AckTracker {
+    receiver: Receiver<MessageId>,
+    pending: HashSet<MessageId>,
+    /// Ids of the tracked messages, from the oldest to the most recent
+    tracked: VecDeque<MessageId>,
+}
+
+impl AckTracker {
+    fn new(receiver: Receiver<MessageId>) -> Self {
+        Self {
+            receiver,
+            pending: HashSet::default(),
+            tracked: VecDeque::default(),
+        }
+    }
+
+    fn track(&mut self, message_id: MessageId) {
+        // stop tracking the messages that are too old
+        while self
+            .tracked
+            .front()
+            .is_some_and(|id| wrapping_diff(id.0, message_id.0) >= MAX_TRACKED_ACK_AGE)
+        {
+            let id = self.tracked.pop_front().unwrap();
+            self.pending.remove(&id);
+        }
+        self.tracked.push_back(message_id);
+        self.pending.insert(message_id);
+    }
+
+    /// Returns true if the message was acked. The messages that are too old to be tracked are reported as not acked
+    fn is_acked(&self, message_id: MessageId) -> bool {
+        !self.pending.contains(&message_id)
+            && self.tracked.back().is_some_and(|latest| {
+                (0..MAX_TRACKED_ACK_AGE).contains(&wrapping_diff(message_id.0, latest.0))
+            })
+    }
 }
 
 impl MessageManager {
     pub fn new(channel_registry: &ChannelRegistry, priority_config: PriorityConfig) -> Self {
         Self {
             packet_manager: PacketBuilder::new(),
-            priority_manager: PriorityManager::new(priority_config),
+            priority_manager: PriorityManager::new(priority_config, channel_registry),
             channels: channel_registry.channels(),
             channel_registry: channel_registry.clone(),
             packet_to_message_ack_map: HashMap::new(),
+            mtu_discovery: None,
+            ack_trackers: HashMap::new(),
+            compressor: None,
+            decompressor: PacketDecompressor::default(),
         }
     }
 
+    /// Compress the packets sent to the remote with `algorithm`
+    pub(crate) fn set_compression(&mut self, algorithm: PacketCompression) -> anyhow::Result<()> {
+        self.compressor = if algorithm == PacketCompression::None {
+            None
+        } else {
+            Some(PacketCompressor::new(algorithm)?)
+        };
+        Ok(())
+    }
+
+    /// Algorithm used to compress the packets sent to the remote
+    pub fn compression(&self) -> PacketCompression {
+        self.compressor
+            .as_ref()
+            .map_or(PacketCompression::None, |compressor| compressor.algorithm())
+    }
+
+    /// Configure how the size of the packets is determined for this connection.
+    ///
+    /// If the transport supports it and `mtu_config` is provided, we start with a conservative packet
+    /// size and send probes to discover the maximum packet size that can reach the remote.
+    pub(crate) fn set_packet_sizing(
+        &mut self,
+        packet_sizing: PacketSizing,
+        mtu_config: Option<MtuConfig>,
+    ) {
+        match (packet_sizing, mtu_config) {
+            (PacketSizing::Discover, Some(mtu_config)) => {
+                let discovery = MtuDiscovery::new(mtu_config);
+                self.set_packet_size(discovery.packet_size());
+                self.mtu_discovery = Some(discovery);
+            }
+            (PacketSizing::Discover, None) => {
+                self.set_packet_size(MAX_PACKET_SIZE);
+                self.mtu_discovery = None;
+            }
+            (PacketSizing::Fixed(packet_size), _) => {
+                self.set_packet_size(packet_size);
+                self.mtu_discovery = None;
+            }
+        }
+    }
+
+    /// Maximum size of the packets sent on this connection
+    pub fn packet_size(&self) -> usize {
+        self.packet_manager.packet_size()
+    }
+
+    /// Update the maximum size of the packets, and the fragmentation threshold of every channel
+    fn set_packet_size(&mut self, packet_size: usize) {
+        self.packet_manager.set_packet_size(packet_size);
+        let fragment_size = self.packet_manager.fragment_size();
+        for channel in self.channels.values_mut() {
+            channel.set_fragment_size(fragment_size);
+        }
+    }
+
+    /// Create the channel `kind`, which was added to the [`ChannelRegistry`] after this manager was created
+    pub(crate) fn add_channel(&mut self, kind: ChannelKind) -> anyhow::Result<()> {
+        let builder = self
+            .channel_registry
+            .get_builder_from_kind(&kind)
+            .context("Channel not found")?;
+        let net_id = *self
+            .channel_registry
+            .get_net_from_kind(&kind)
+            .context("Channel has no network id")?;
+        let mut channel = builder.build();
+        channel.set_fragment_size(self.packet_manager.fragment_size());
+        self.priority_manager.add_channel(net_id, &builder.settings);
+        self.channels.insert(kind, channel);
+        Ok(())
+    }
+
     pub(crate) fn get_replication_update_send_receiver(&mut self) -> Receiver<MessageId> {
         self.priority_manager
             .subscribe_replication_update_sent_messages()
@@ -71,14 +214,27 @@ impl MessageManager {
         tick_manager: &TickManager,
     ) {
         self.packet_manager.header_manager.update(time_manager);
+        if let Some(discovery) = self.mtu_discovery.as_mut() {
+            discovery.update(time_manager.current_time());
+        }
         for channel in self.channels.values_mut() {
             channel
                 .sender
                 .update(time_manager, ping_manager, tick_manager);
+            if let ChannelReceiver::Nack(receiver) = &mut channel.receiver {
+                receiver.set_rtt(ping_manager.rtt());
+            }
             channel.receiver.update(time_manager, tick_manager);
         }
     }
 
+    /// Set the tick that is associated with the messages buffered on the tick-buffered channels
+    pub(crate) fn set_send_tick(&mut self, tick: Tick) {
+        for channel in self.channels.values_mut() {
+            channel.sender.set_tick(tick);
+        }
+    }
+
     /// Buffer a message to be sent on this connection
     /// Returns the message id associated with the message, if there is one
     pub fn buffer_send(
@@ -106,12 +262,191 @@ impl MessageManager {
         message: RawData,
         channel_kind: ChannelKind,
         priority: f32,
+    ) -> anyhow::Result<Option<MessageId>> {
+        self.buffer_send_bytes(message.into(), channel_kind, priority)
+    }
+
+    /// Buffer a message to be sent on this connection.
+    ///
+    /// The [`Bytes`] can be shared with other connections, to send the same message to multiple clients
+    /// without copying it.
+    pub(crate) fn buffer_send_bytes(
+        &mut self,
+        message: Bytes,
+        channel_kind: ChannelKind,
+        priority: f32,
     ) -> anyhow::Result<Option<MessageId>> {
         let channel = self
             .channels
             .get_mut(&channel_kind)
             .context("Channel not found")?;
-        Ok(channel.sender.buffer_send(message.into(), priority))
+        channel.stats.messages_sent += 1;
+        channel.stats.bytes_sent += message.len() as u64;
+        Ok(channel.sender.buffer_send(message, priority))
+    }
+
+    /// Buffer a message to be sent on this connection. On reliable channels, the message stops being
+    /// retransmitted if it is not acked within `ttl`.
+    /// Returns the message id associated with the message, if there is one
+    pub fn buffer_send_with_ttl(
+        &mut self,
+        message: RawData,
+        channel_kind: ChannelKind,
+        ttl: Duration,
+    ) -> anyhow::Result<Option<MessageId>> {
+        let channel = self
+            .channels
+            .get_mut(&channel_kind)
+            .context("Channel not found")?;
+        channel.stats.messages_sent += 1;
+        channel.stats.bytes_sent += message.len() as u64;
+        Ok(channel
+            .sender
+            .buffer_send_with_ttl(message.into(), DEFAULT_MESSAGE_PRIORITY, ttl))
+    }
+
+    /// Start watching the acks of the channel, so that messages can be sent with a [`MessageHandle`].
+    ///
+    /// Returns an error if the channel doesn't track acks
+    pub(crate) fn watch_acks(&mut self, channel_kind: ChannelKind) -> anyhow::Result<()> {
+        if self.ack_trackers.contains_key(&channel_kind) {
+            return Ok(());
+        }
+        let channel = self
+            .channels
+            .get_mut(&channel_kind)
+            .context("Channel not found")?;
+        if !channel.setting.mode.is_watching_acks() {
+            return Err(anyhow!("Channel does not track acks"));
+        }
+        self.ack_trackers.insert(
+            channel_kind,
+            AckTracker::new(channel.sender.subscribe_acks()),
+        );
+        Ok(())
+    }
+
+    /// Track the ack of a message that was sent on a channel that is watching acks
+    pub(crate) fn track_ack(&mut self, handle: MessageHandle) {
+        if let Some(tracker) = self.ack_trackers.get_mut(&handle.channel) {
+            tracker.track(handle.message_id);
+        }
+    }
+
+    /// Returns true if the message was acked by the remote.
+    ///
+    /// The acks are processed once per frame, when the events are emitted.
+    pub(crate) fn is_acked(&self, handle: MessageHandle) -> bool {
+        self.ack_trackers
+            .get(&handle.channel)
+            .is_some_and(|tracker| tracker.is_acked(handle.message_id))
+    }
+
+    /// Stop sending the message if it hasn't been acked yet.
+    ///
+    /// Returns true if the message was cancelled.
+    pub(crate) fn cancel_message(&mut self, handle: MessageHandle) -> anyhow::Result<bool> {
+        let channel = self
+            .channels
+            .get_mut(&handle.channel)
+            .context("Channel not found")?;
+        Ok(channel.sender.cancel_message(handle.message_id))
+    }
+
+    /// Drain the handles of the tracked messages that were acked since the last call
+    pub(crate) fn drain_acked_messages(&mut self) -> Vec<MessageHandle> {
+        let mut acked = vec![];
+        for (channel_kind, tracker) in self.ack_trackers.iter_mut() {
+            for message_id in tracker.receiver.try_iter() {
+                if tracker.pending.remove(&message_id) {
+                    acked.push(MessageHandle {
+                        channel: *channel_kind,
+                        message_id,
+                    });
+                }
+            }
+        }
+        acked
+    }
+
+    /// Drain the ids of the messages that expired before being acked, on all the channels
+    pub(crate) fn drain_expired_messages(&mut self) -> Vec<(ChannelKind, MessageId)> {
+        self.channels
+            .iter_mut()
+            .flat_map(|(channel_kind, channel)| {
+                channel
+                    .sender
+                    .drain_expired_messages()
+                    .into_iter()
+                    .map(|message_id| (*channel_kind, message_id))
+            })
+            .collect()
+    }
+
+    /// Drain the progress updates of the fragmented messages sent on reliable channels
+    pub(crate) fn drain_fragment_progress(&mut self) -> Vec<(ChannelKind, FragmentProgress)> {
+        self.channels
+            .iter_mut()
+            .flat_map(|(channel_kind, channel)| {
+                channel
+                    .sender
+                    .drain_fragment_progress()
+                    .into_iter()
+                    .map(|progress| (*channel_kind, progress))
+            })
+            .collect()
+    }
+
+    /// Abort an outgoing transfer on a [`ChannelMode::Stream`](crate::channel::builder::ChannelMode::Stream) channel.
+    ///
+    /// If `transfer_id` is None, all the outgoing transfers of the channel are aborted.
+    pub(crate) fn abort_transfer(
+        &mut self,
+        channel_kind: ChannelKind,
+        transfer_id: Option<TransferId>,
+    ) -> anyhow::Result<()> {
+        let channel = self
+            .channels
+            .get_mut(&channel_kind)
+            .context("Channel not found")?;
+        let ChannelSender::Stream(sender) = &mut channel.sender else {
+            return Err(anyhow!("Channel is not a stream channel"));
+        };
+        match transfer_id {
+            Some(transfer_id) => {
+                sender.abort(transfer_id);
+            }
+            None => sender.abort_all(),
+        }
+        Ok(())
+    }
+
+    /// Drain the progress updates of the transfers of all the stream channels
+    pub(crate) fn drain_transfer_progress(&mut self) -> Vec<(ChannelKind, TransferProgress)> {
+        let mut progress = vec![];
+        for (channel_kind, channel) in self.channels.iter_mut() {
+            if let ChannelSender::Stream(sender) = &mut channel.sender {
+                progress.extend(
+                    sender
+                        .drain_progress()
+                        .into_iter()
+                        .map(|p| (*channel_kind, p)),
+                );
+            }
+            let receiver = match &mut channel.receiver {
+                ChannelReceiver::Nack(receiver) => receiver.inner_mut(),
+                receiver => receiver,
+            };
+            if let ChannelReceiver::Stream(receiver) = receiver {
+                progress.extend(
+                    receiver
+                        .drain_progress()
+                        .into_iter()
+                        .map(|p| (*channel_kind, p)),
+                );
+            }
+        }
+        progress
     }
 
     /// Prepare buckets from the internal send buffers, and return the bytes to send
@@ -119,12 +454,106 @@ impl MessageManager {
     //  (ticks are not purely necessary without client prediction)
     //  maybe be generic over a Context ?
     pub fn send_packets(&mut self, current_tick: Tick) -> anyhow::Result<Vec<Payload>> {
+        let mut bytes = Vec::new();
+        // Step 0. Send a probe to discover the maximum packet size, if necessary
+        if let Some(probe_size) = self
+            .mtu_discovery
+            .as_ref()
+            .and_then(|discovery| discovery.next_probe_size())
+        {
+            let mut packet = self.packet_manager.build_mtu_probe(probe_size);
+            packet.header.tick = current_tick;
+            let payload = self.packet_manager.encode_packet(&packet)?;
+            let current_time = self.packet_manager.header_manager.current_time();
+            if let Some(discovery) = self.mtu_discovery.as_mut() {
+                discovery.on_probe_sent(packet.header.packet_id, probe_size, current_time);
+            }
+            bytes.push(payload);
+        }
+        self.buffer_nacks()?;
+        bytes.extend(self.build_payloads(current_tick, Delivery::Datagram)?);
+        Ok(bytes)
+    }
+
+    /// Request the missing messages of the channels that use [`ResendStrategy::Nack`](crate::channel::builder::ResendStrategy::Nack)
+    fn buffer_nacks(&mut self) -> anyhow::Result<()> {
+        let mut nacks = vec![];
+        for (channel_kind, channel) in self.channels.iter_mut() {
+            if let ChannelReceiver::Nack(receiver) = &mut channel.receiver {
+                let message_ids = receiver.collect_nacks();
+                if !message_ids.is_empty() {
+                    let channel_id = *self
+                        .channel_registry
+                        .get_net_from_kind(channel_kind)
+                        .context("cannot find channel id")?;
+                    nacks.push(NackMessage {
+                        channel_id,
+                        message_ids,
+                    });
+                }
+            }
+        }
+        if nacks.is_empty() {
+            return Ok(());
+        }
+        let nack_channel = self
+            .channels
+            .get_mut(&ChannelKind::of::<NackChannel>())
+            .context("Channel not found")?;
+        for nack in nacks {
+            trace!(?nack, "requesting missing messages");
+            nack_channel
+                .sender
+                .buffer_send(nack.to_bytes(), DEFAULT_MESSAGE_PRIORITY);
+        }
+        Ok(())
+    }
+
+    /// Resend the messages that were requested by the remote with a [`NackMessage`]
+    fn receive_nack(&mut self, message: MessageContainer) -> anyhow::Result<()> {
+        let MessageContainer::Single(data) = message else {
+            return Err(anyhow!("nack messages cannot be fragmented"));
+        };
+        let nack = NackMessage::from_bytes(data.bytes)?;
+        let channel_kind = self
+            .channel_registry
+            .get_kind_from_net_id(nack.channel_id)
+            .context("cannot find channel kind")?;
+        let channel = self
+            .channels
+            .get_mut(channel_kind)
+            .context("Channel not found")?;
+        for message_id in nack.message_ids {
+            channel.sender.notify_message_nacked(message_id);
+        }
+        Ok(())
+    }
+
+    /// Prepare the packets for the channels that should be sent on a reliable stream ([`Delivery::Stream`]),
+    /// and return the bytes to send.
+    ///
+    /// Those packets are kept separate from the datagram packets so that the transport can send them differently.
+    pub fn send_stream_packets(&mut self, current_tick: Tick) -> anyhow::Result<Vec<Payload>> {
+        self.build_payloads(current_tick, Delivery::Stream)
+    }
+
+    /// Build the packets for all the channels that use the given [`Delivery`] method
+    fn build_payloads(
+        &mut self,
+        current_tick: Tick,
+        delivery: Delivery,
+    ) -> anyhow::Result<Vec<Payload>> {
+        let mut bytes = Vec::new();
         // Step 1. Get the list of packets to send from all channels
         // for each channel, prepare packets using the buffered messages that are ready to be sent
         // TODO: iterate through the channels in order of channel priority? (with accumulation)
         let mut data_to_send: Vec<(NetId, (VecDeque<SingleData>, VecDeque<FragmentData>))> = vec![];
         let mut has_data_to_send = false;
-        for (channel_kind, channel) in self.channels.iter_mut() {
+        for (channel_kind, channel) in self
+            .channels
+            .iter_mut()
+            .filter(|(_, channel)| channel.setting.delivery == delivery)
+        {
             let channel_id = self
                 .channel_registry
                 .get_net_from_kind(channel_kind)
@@ -141,7 +570,7 @@ impl MessageManager {
         }
         // return early if there are no messages to send
         if !has_data_to_send {
-            return Ok(vec![]);
+            return Ok(bytes);
         }
 
         // priority manager: get the list of messages we can send according to the rate limiter
@@ -154,7 +583,6 @@ impl MessageManager {
 
         let packets = self.packet_manager.build_packets(data_to_send);
 
-        let mut bytes = Vec::new();
         for mut packet in packets {
             trace!(num_messages = ?packet.data.num_messages(), "sending packet");
             let packet_id = packet.header().packet_id;
@@ -164,6 +592,7 @@ impl MessageManager {
 
             // Step 2. Get the packets to send over the network
             let payload = self.packet_manager.encode_packet(&packet)?;
+            let payload = self.compress_payload(&packet, payload)?;
             bytes.push(payload);
             // io.send(payload, &self.remote_addr)?;
 
@@ -210,11 +639,58 @@ impl MessageManager {
         Ok(bytes)
     }
 
+    /// Replace the encoded `packet` with its compressed version, if compression is enabled for
+    /// one of its channels and if the compressed packet is smaller
+    fn compress_payload(&mut self, packet: &Packet, payload: Payload) -> anyhow::Result<Payload> {
+        let compress = packet.channel_ids().iter().any(|channel_id| {
+            self.channel_registry
+                .get_kind_from_net_id(*channel_id)
+                .and_then(|kind| self.channels.get(kind))
+                .is_some_and(|channel| channel.setting.compress)
+        });
+        let Some(compressor) = self.compressor.as_mut().filter(|_| compress) else {
+            return Ok(payload);
+        };
+        let compressed = compressor.compress(&payload)?;
+        let compressed_payload = self
+            .packet_manager
+            .encode_compressed_packet(packet, compressed)?;
+        if compressed_payload.len() < payload.len() {
+            trace!(
+                uncompressed = payload.len(),
+                compressed = compressed_payload.len(),
+                "compressed packet"
+            );
+            Ok(compressed_payload)
+        } else {
+            Ok(payload)
+        }
+    }
+
+    /// Decompress the packet if it was compressed by the remote
+    fn decompress_packet(&mut self, packet: Packet) -> anyhow::Result<Packet> {
+        let PacketData::Compressed(bytes) = &packet.data else {
+            return Ok(packet);
+        };
+        let bytes = self
+            .decompressor
+            .decompress(bytes)
+            .context("could not decompress packet")?;
+        let packet = Packet::decode(&mut BitcodeReader::start_read(bytes.as_slice()))?;
+        if matches!(packet.data, PacketData::Compressed(_)) {
+            return Err(anyhow!(
+                "a compressed packet cannot contain another compressed packet"
+            ));
+        }
+        Ok(packet)
+    }
+
     /// Process packet received over the network as raw bytes
     /// Update the acks, and put the messages from the packets in internal buffers
     /// Returns the tick of the packet
     pub fn recv_packet(&mut self, packet: Packet) -> anyhow::Result<Tick> {
         // Step 1. Parse the packet
+        let packet = self.decompress_packet(packet)?;
         let tick = packet.header().tick;
         trace!(?packet, "Received packet");
 
@@ -226,13 +702,20 @@ impl MessageManager {
 
         // Step 2. Update the packet acks (which packets have we received, and which of our packets
         // have been acked)
-        let acked_packets = self
+        let (acked_packets, nacked_packets) = self
             .packet_manager
             .header_manager
             .process_recv_packet_header(packet.header());
 
         // Step 3. Update the list of messages that have been acked
         for acked_packet in acked_packets {
+            if let Some(packet_size) = self
+                .mtu_discovery
+                .as_mut()
+                .and_then(|discovery| discovery.on_packet_acked(acked_packet))
+            {
+                self.set_packet_size(packet_size);
+            }
             if let Some(message_map) = self.packet_to_message_ack_map.remove(&acked_packet) {
                 for (channel_kind, message_acks) in message_map {
                     let channel = self
@@ -245,19 +728,39 @@ impl MessageManager {
                 }
             }
         }
+        // the packet might still be acked later, so we keep the message acks around
+        for nacked_packet in nacked_packets {
+            if let Some(message_map) = self.packet_to_message_ack_map.get(&nacked_packet) {
+                for (channel_kind, message_acks) in message_map {
+                    let channel = self
+                        .channels
+                        .get_mut(channel_kind)
+                        .context("Channel not found")?;
+                    for message_ack in message_acks {
+                        channel.sender.notify_message_lost(message_ack);
+                    }
+                }
+            }
+        }
 
         // Step 4. Put the messages from the packet in the internal buffers for each channel
         for (channel_net_id, messages) in packet.data.contents() {
-            let channel_kind = self
+            let channel_kind = *self
                 .channel_registry
                 .get_kind_from_net_id(channel_net_id)
                 .context(format!(
                     "Could not recognize net_id {} as a channel",
                     channel_net_id
                 ))?;
+            if channel_kind == ChannelKind::of::<NackChannel>() {
+                for message in messages {
+                    self.receive_nack(message)?;
+                }
+                continue;
+            }
             let channel = self
                 .channels
-                .get_mut(channel_kind)
+                .get_mut(&channel_kind)
                 .ok_or_else(|| anyhow!("Channel not found"))?;
             trace!(
                 "received {:?} messages from channel: {:?}",
@@ -272,6 +775,38 @@ impl MessageManager {
         Ok(tick)
     }
 
+    /// Remove the unacked messages of the channels that persist their outbox across reconnects,
+    /// so that they can be buffered again on a new connection
+    pub(crate) fn take_outbox(&mut self) -> Vec<(ChannelKind, Bytes, f32)> {
+        self.channels
+            .iter_mut()
+            .flat_map(|(channel_kind, channel)| {
+                channel
+                    .sender
+                    .take_outbox()
+                    .into_iter()
+                    .map(|(bytes, priority)| (*channel_kind, bytes, priority))
+            })
+            .collect()
+    }
+
+    /// Statistics of every channel of the connection
+    pub(crate) fn channel_stats(
+        &self,
+    ) -> impl Iterator<Item = (ChannelKind, ChannelStatistics)> + '_ {
+        self.channels.iter().map(|(channel_kind, channel)| {
+            (
+                *channel_kind,
+                ChannelStatistics {
+                    retransmissions: channel.sender.retransmissions(),
+                    messages_dropped: channel.receiver.dropped_messages(),
+                    send_queue_depth: channel.sender.send_queue_depth(),
+                    ..channel.stats
+                },
+            )
+        })
+    }
+
     /// Read all the messages in the internal buffers that are ready to be processed
     // TODO: this is where naia converts the messages to events and pushes them to an event queue
     //  let be conservative and just return the messages right now. We could switch to an iterator
@@ -281,6 +816,12 @@ impl MessageManager {
             let mut messages = vec![];
             while let Some(single_data) = channel.receiver.read_message() {
                 trace!(?channel_kind, "reading message: {:?}", single_data);
+                // an empty message replaces a message that expired before being acked
+                if single_data.bytes.is_empty() {
+                    continue;
+                }
+                channel.stats.messages_received += 1;
+                channel.stats.bytes_received += single_data.bytes.len() as u64;
                 // SAFETY: when we receive the message, we set the tick of the message to the header tick
                 // so every message has a tick
                 messages.push((single_data.tick.unwrap(), single_data.bytes));
@@ -295,11 +836,12 @@ impl MessageManager {
 
 // TODO: have a way to update the channels about the messages that have been acked
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use std::collections::HashMap;
 
     use bevy::prelude::default;
+    use bevy::utils::Duration;
 
     use crate::packet::message::MessageId;
     use crate::packet::packet::FRAGMENT_SIZE;
@@ -511,6 +1053,262 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    /// Check that the packets and the fragments respect a packet size lower than the maximum
+    fn test_message_manager_small_packet_size() -> Result<(), anyhow::Error> {
+        let (mut client_message_manager, mut server_message_manager) = setup();
+        let packet_size = 500;
+        client_message_manager.set_packet_sizing(PacketSizing::Fixed(packet_size), None);
+        assert_eq!(client_message_manager.packet_size(), packet_size);
+
+        let message = vec![1; 1000];
+        let channel_kind_1 = ChannelKind::of::<Channel1>();
+        client_message_manager.buffer_send(message.clone(), channel_kind_1)?;
+        let packet_bytes = client_message_manager.send_packets(Tick(0))?;
+        assert_eq!(packet_bytes.len(), 3);
+        for packet_byte in packet_bytes.iter() {
+            assert!(packet_byte.len() <= packet_size);
+            let packet = Packet::decode(&mut BitcodeReader::start_read(packet_byte.as_slice()))?;
+            server_message_manager.recv_packet(packet)?;
+        }
+        let data = server_message_manager.read_messages();
+        assert_eq!(
+            data.get(&channel_kind_1).unwrap(),
+            &vec![(Tick(0), message.into())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// Check that the messages of channels that use a stream are sent in separate packets
+    fn test_message_manager_stream_delivery() -> Result<(), anyhow::Error> {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        });
+        channel_registry.add_channel::<Channel2>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            delivery: Delivery::Stream,
+            ..default()
+        });
+        let mut client_message_manager =
+            MessageManager::new(&channel_registry, PriorityConfig::default());
+        let mut server_message_manager =
+            MessageManager::new(&channel_registry, PriorityConfig::default());
+
+        let message = vec![0, 1];
+        let channel_kind_1 = ChannelKind::of::<Channel1>();
+        let channel_kind_2 = ChannelKind::of::<Channel2>();
+        client_message_manager.buffer_send(message.clone(), channel_kind_1)?;
+        client_message_manager.buffer_send(message.clone(), channel_kind_2)?;
+
+        let datagram_bytes = client_message_manager.send_packets(Tick(0))?;
+        assert_eq!(datagram_bytes.len(), 1);
+        let packet = Packet::decode(&mut BitcodeReader::start_read(datagram_bytes[0].as_slice()))?;
+        server_message_manager.recv_packet(packet)?;
+        let data = server_message_manager.read_messages();
+        assert_eq!(
+            data.get(&channel_kind_1).unwrap(),
+            &vec![(Tick(0), message.clone().into())]
+        );
+        assert!(!data.contains_key(&channel_kind_2));
+
+        let stream_bytes = client_message_manager.send_stream_packets(Tick(0))?;
+        assert_eq!(stream_bytes.len(), 1);
+        let packet = Packet::decode(&mut BitcodeReader::start_read(stream_bytes[0].as_slice()))?;
+        server_message_manager.recv_packet(packet)?;
+        let data = server_message_manager.read_messages();
+        assert_eq!(
+            data.get(&channel_kind_2).unwrap(),
+            &vec![(Tick(0), message.into())]
+        );
+        assert!(!data.contains_key(&channel_kind_1));
+        Ok(())
+    }
+
+    #[test]
+    /// Check that the packet size converges to the maximum size that can go through the network path
+    fn test_mtu_discovery() -> Result<(), anyhow::Error> {
+        let (mut client_message_manager, mut server_message_manager) = setup();
+        let mtu_config = crate::packet::mtu::MtuConfig::default();
+        client_message_manager.set_packet_sizing(PacketSizing::Discover, Some(mtu_config));
+        assert_eq!(
+            client_message_manager.packet_size(),
+            mtu_config.min_packet_size
+        );
+
+        let mut time_manager = TimeManager::default();
+        let tick_manager =
+            TickManager::from_config(TickConfig::new(bevy::utils::Duration::from_millis(16)));
+        let ping_manager = PingManager::new(default());
+        // packets bigger than this are dropped
+        let path_mtu = 1000;
+        for _ in 0..50 {
+            time_manager.update(mtu_config.probe_timeout);
+            client_message_manager.update(&time_manager, &ping_manager, &tick_manager);
+            for packet_byte in client_message_manager.send_packets(Tick(0))? {
+                if packet_byte.len() > path_mtu {
+                    continue;
+                }
+                let packet =
+                    Packet::decode(&mut BitcodeReader::start_read(packet_byte.as_slice()))?;
+                server_message_manager.recv_packet(packet)?;
+            }
+            // the server sends a message back so that the client receives the acks
+            server_message_manager.buffer_send(vec![1], ChannelKind::of::<Channel1>())?;
+            for packet_byte in server_message_manager.send_packets(Tick(0))? {
+                let packet =
+                    Packet::decode(&mut BitcodeReader::start_read(packet_byte.as_slice()))?;
+                client_message_manager.recv_packet(packet)?;
+            }
+        }
+        let packet_size = client_message_manager.packet_size();
+        assert!(packet_size <= path_mtu);
+        assert!(packet_size > path_mtu - 50);
+
+        // the fragments now use the bigger packet size
+        let message = vec![1; 2 * packet_size];
+        client_message_manager.buffer_send(message.clone(), ChannelKind::of::<Channel1>())?;
+        let packet_bytes = client_message_manager.send_packets(Tick(0))?;
+        assert_eq!(packet_bytes.len(), 3);
+        for packet_byte in packet_bytes.iter() {
+            assert!(packet_byte.len() <= packet_size);
+            let packet = Packet::decode(&mut BitcodeReader::start_read(packet_byte.as_slice()))?;
+            server_message_manager.recv_packet(packet)?;
+        }
+        assert_eq!(
+            server_message_manager
+                .read_messages()
+                .get(&ChannelKind::of::<Channel1>())
+                .unwrap(),
+            &vec![(Tick(0), message.into())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// Check that a message that was lost is resent when the receiver requests it
+    fn test_message_manager_nack() -> anyhow::Result<()> {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default().with_resend_strategy(
+                ResendStrategy::Nack {
+                    fallback_delay: Duration::from_secs(10),
+                },
+            )),
+            ..default()
+        });
+        channel_registry.add_channel::<NackChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        });
+        let mut client_message_manager =
+            MessageManager::new(&channel_registry, PriorityConfig::default());
+        let mut server_message_manager =
+            MessageManager::new(&channel_registry, PriorityConfig::default());
+        let deliver = |payloads: Vec<Payload>, receiver: &mut MessageManager| {
+            for payload in payloads {
+                let packet = Packet::decode(&mut BitcodeReader::start_read(payload.as_slice()))?;
+                receiver.recv_packet(packet)?;
+            }
+            Ok::<(), anyhow::Error>(())
+        };
+
+        // the packet containing the first message is lost
+        client_message_manager.buffer_send(vec![0], Channel1::kind())?;
+        client_message_manager.send_packets(Tick(0))?;
+        client_message_manager.buffer_send(vec![1], Channel1::kind())?;
+        deliver(
+            client_message_manager.send_packets(Tick(0))?,
+            &mut server_message_manager,
+        )?;
+        assert_eq!(
+            server_message_manager.read_messages()[&Channel1::kind()],
+            vec![(Tick(0), vec![1].into())]
+        );
+
+        // the message is not resent on a timer
+        assert!(client_message_manager.send_packets(Tick(0))?.is_empty());
+
+        // the server requests the missing message, which is resent
+        deliver(
+            server_message_manager.send_packets(Tick(0))?,
+            &mut client_message_manager,
+        )?;
+        deliver(
+            client_message_manager.send_packets(Tick(0))?,
+            &mut server_message_manager,
+        )?;
+        assert_eq!(
+            server_message_manager.read_messages()[&Channel1::kind()],
+            vec![(Tick(0), vec![0].into())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// Check that a cancelled message is not resent, and does not block the ordered receiver,
+    /// when the bandwidth is limited by the priority manager
+    fn test_message_manager_cancel_with_priority() -> anyhow::Result<()> {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        });
+        let priority_config = PriorityConfig {
+            bandwidth_quota: governor::Quota::per_second(nonzero_ext::nonzero!(10000u32)),
+            enabled: true,
+        };
+        let mut client_message_manager =
+            MessageManager::new(&channel_registry, priority_config.clone());
+        let mut server_message_manager = MessageManager::new(&channel_registry, priority_config);
+        let deliver = |payloads: Vec<Payload>, receiver: &mut MessageManager| {
+            for payload in payloads {
+                let packet = Packet::decode(&mut BitcodeReader::start_read(payload.as_slice()))?;
+                receiver.recv_packet(packet)?;
+            }
+            Ok::<(), anyhow::Error>(())
+        };
+
+        // the packet containing the first message is lost
+        let message_id = client_message_manager
+            .buffer_send(vec![0], Channel1::kind())?
+            .unwrap();
+        client_message_manager.send_packets(Tick(0))?;
+        client_message_manager.buffer_send(vec![1], Channel1::kind())?;
+        deliver(
+            client_message_manager.send_packets(Tick(0))?,
+            &mut server_message_manager,
+        )?;
+        // the ordered receiver waits for the first message
+        assert!(server_message_manager.read_messages().is_empty());
+
+        // the first message is cancelled: it is removed from the sender
+        assert!(client_message_manager.cancel_message(MessageHandle {
+            channel: Channel1::kind(),
+            message_id,
+        })?);
+        assert_eq!(
+            client_message_manager.channels[&Channel1::kind()]
+                .sender
+                .send_queue_depth(),
+            1
+        );
+        // an empty message is sent in its place, which unblocks the receiver
+        deliver(
+            client_message_manager.send_packets(Tick(0))?,
+            &mut server_message_manager,
+        )?;
+        assert_eq!(
+            server_message_manager.read_messages()[&Channel1::kind()],
+            vec![(Tick(0), vec![1].into())]
+        );
+        // the empty message is not sent again
+        assert!(client_message_manager.send_packets(Tick(0))?.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_notify_ack() -> anyhow::Result<()> {
         let (mut client_message_manager, mut server_message_manager) = setup();
@@ -560,4 +1358,32 @@ mod tests {
         assert_eq!(update_acks_tracker.try_recv()?, message_id);
         Ok(())
     }
+
+    /// The messages that are never acked (for example because they were lost) stop being tracked once they
+    /// are too old, even when the message ids wrap around
+    #[test]
+    fn test_ack_tracker_evicts_old_messages() {
+        let (_sender, receiver) = crossbeam_channel::unbounded();
+        let mut tracker = AckTracker::new(receiver);
+        let mut message_id = MessageId(0);
+        for _ in 0..2 * (u16::MAX as usize + 1) {
+            tracker.track(message_id);
+            message_id += 1;
+        }
+        assert_eq!(tracker.pending.len(), MAX_TRACKED_ACK_AGE as usize);
+        assert_eq!(tracker.tracked.len(), MAX_TRACKED_ACK_AGE as usize);
+
+        let latest = message_id - 1;
+        assert!(!tracker.is_acked(latest));
+        // the oldest message that is still tracked
+        assert!(!tracker.is_acked(message_id - MAX_TRACKED_ACK_AGE as u16));
+        // a message that is too old is not reported as acked
+        assert!(!tracker.is_acked(message_id - MAX_TRACKED_ACK_AGE as u16 - 1));
+        assert!(!tracker
+            .pending
+            .contains(&(message_id - MAX_TRACKED_ACK_AGE as u16 - 1)));
+
+        tracker.pending.remove(&latest);
+        assert!(tracker.is_acked(latest));
+    }
 }
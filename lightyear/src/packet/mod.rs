@@ -19,6 +19,9 @@ Packets that are over the maximum packet size will be fragmented into multiple [
 [`FragmentedPacket`]: packet::FragmentedPacket
 */
 
+/// Compresses the packets after they are assembled
+pub mod compression;
+
 /// Manages the [`PacketHeader`](header::PacketHeader) which includes important packet information
 pub mod header;
 
@@ -28,9 +31,14 @@ pub mod message;
 /// Manages sending and receiving [`Packets`](packet::Packet) over the network
 pub mod message_manager;
 
+/// Discovers the maximum packet size that can reach the remote peer
+pub mod mtu;
+
 /// Defines the [`Packet`](packet::Packet) struct
 pub mod packet;
 
+/// Spreads the packet sends over time to respect a bandwidth limit
+pub mod pacer;
 /// Manages building a single [`Packet`](packet::Packet) from multiple [`Messages`](message::Message)
 pub(crate) mod packet_manager;
 /// Defines the [`PacketType`](packet_type::PacketType) enum
@@ -0,0 +1,243 @@
+/*! Path MTU discovery
+
+The maximum size of a packet that can reach the remote peer without being dropped depends on the network path
+between the two peers. Instead of always building packets against a fixed size, we start with a conservative size
+and send probe packets (that only contain padding) of increasing size. A probe that gets acked tells us that packets
+of that size can be delivered, so the packet builder and the fragmentation threshold are updated to use the bigger size.
+
+The search stops when the difference between the largest size that was acked and the smallest size that was lost is
+small enough.
+
+On native UDP sockets, the "Don't Fragment" bit is set (on Linux) so that probes that are too big for the path are
+dropped instead of being fragmented at the IP level. On other platforms the probes might get fragmented by the
+routers, in which case the discovery simply converges to the maximum packet size.
+
+Note that the probes that are lost are also counted as lost packets in the connection's
+packet statistics.
+*/
+use bevy::reflect::Reflect;
+use bevy::utils::Duration;
+use tracing::{debug, trace};
+
+use crate::connection::netcode::MAX_PACKET_SIZE;
+use crate::packet::packet::PacketId;
+use crate::shared::time_manager::WrappedTime;
+
+/// A safe packet size that can be delivered on virtually every network path:
+/// IPv4 guarantees that datagrams of 576 bytes can be delivered, and we leave room for the
+/// IP, UDP and netcode headers
+pub const DEFAULT_MIN_PACKET_SIZE: usize = 480;
+
+/// The search stops when the range of possible packet sizes is smaller than this
+const SEARCH_PRECISION: usize = 16;
+
+/// Configuration of the path MTU discovery
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct MtuConfig {
+    /// The packet size that is used before any probe has been acked.
+    /// It should be small enough to be delivered on any network path.
+    pub min_packet_size: usize,
+    /// The maximum packet size that will be probed
+    pub max_packet_size: usize,
+    /// How long we wait for the ack of a probe before considering it lost
+    pub probe_timeout: Duration,
+    /// Number of probes of a given size that must be lost before we consider that the size
+    /// is too big for the network path
+    pub probe_attempts: u8,
+}
+
+impl Default for MtuConfig {
+    fn default() -> Self {
+        Self {
+            min_packet_size: DEFAULT_MIN_PACKET_SIZE,
+            max_packet_size: MAX_PACKET_SIZE,
+            probe_timeout: Duration::from_millis(500),
+            probe_attempts: 2,
+        }
+    }
+}
+
+impl MtuConfig {
+    pub fn with_min_packet_size(mut self, min_packet_size: usize) -> Self {
+        self.min_packet_size = min_packet_size;
+        self
+    }
+
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size.min(MAX_PACKET_SIZE);
+        self
+    }
+
+    pub fn with_probe_timeout(mut self, probe_timeout: Duration) -> Self {
+        self.probe_timeout = probe_timeout;
+        self
+    }
+
+    pub fn with_probe_attempts(mut self, probe_attempts: u8) -> Self {
+        self.probe_attempts = probe_attempts;
+        self
+    }
+}
+
+/// How the size of the packets of a connection is determined
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketSizing {
+    /// Discover the maximum packet size by sending probes
+    Discover,
+    /// Always use the same packet size
+    Fixed(usize),
+}
+
+impl Default for PacketSizing {
+    fn default() -> Self {
+        PacketSizing::Fixed(MAX_PACKET_SIZE)
+    }
+}
+
+#[derive(Debug)]
+struct Probe {
+    packet_id: PacketId,
+    size: usize,
+    sent_at: WrappedTime,
+}
+
+/// Binary search of the largest packet size that can reach the remote
+#[derive(Debug)]
+pub(crate) struct MtuDiscovery {
+    config: MtuConfig,
+    /// Largest packet size that is known to reach the remote
+    low: usize,
+    /// Largest packet size that could reach the remote
+    high: usize,
+    /// Size of the next probe to send
+    candidate: usize,
+    /// Number of probes of size `candidate` that were lost
+    failed_attempts: u8,
+    in_flight: Option<Probe>,
+}
+
+impl MtuDiscovery {
+    pub(crate) fn new(config: MtuConfig) -> Self {
+        let low = config.min_packet_size.min(config.max_packet_size);
+        Self {
+            config,
+            low,
+            high: config.max_packet_size,
+            // start by probing the maximum size, which is the most likely to succeed
+            candidate: config.max_packet_size,
+            failed_attempts: 0,
+            in_flight: None,
+        }
+    }
+
+    /// The largest packet size that is known to reach the remote
+    pub(crate) fn packet_size(&self) -> usize {
+        self.low
+    }
+
+    /// Returns true if the search is over
+    pub(crate) fn is_complete(&self) -> bool {
+        self.high - self.low < SEARCH_PRECISION
+    }
+
+    /// Returns the size of the probe that should be sent now, if any
+    pub(crate) fn next_probe_size(&self) -> Option<usize> {
+        if self.in_flight.is_some() || self.is_complete() {
+            return None;
+        }
+        Some(self.candidate)
+    }
+
+    /// Keep track of the probe that was just sent
+    pub(crate) fn on_probe_sent(&mut self, packet_id: PacketId, size: usize, now: WrappedTime) {
+        trace!(?packet_id, ?size, "sent mtu probe");
+        self.in_flight = Some(Probe {
+            packet_id,
+            size,
+            sent_at: now,
+        });
+    }
+
+    /// Notify that a packet was acked by the remote.
+    ///
+    /// Returns the new packet size if it changed
+    pub(crate) fn on_packet_acked(&mut self, packet_id: PacketId) -> Option<usize> {
+        if self.in_flight.as_ref()?.packet_id != packet_id {
+            return None;
+        }
+        let probe = self.in_flight.take()?;
+        debug!(size = ?probe.size, "mtu probe was acked");
+        self.low = probe.size;
+        self.next_candidate();
+        Some(self.low)
+    }
+
+    /// Check if the probe in flight has timed out
+    pub(crate) fn update(&mut self, now: WrappedTime) {
+        match &self.in_flight {
+            Some(probe) if now > probe.sent_at + self.config.probe_timeout => {}
+            _ => return,
+        }
+        let Some(probe) = self.in_flight.take() else {
+            return;
+        };
+        self.failed_attempts += 1;
+        trace!(size = ?probe.size, attempts = ?self.failed_attempts, "mtu probe was lost");
+        if self.failed_attempts >= self.config.probe_attempts {
+            debug!(size = ?probe.size, "packet size is too big for the network path");
+            self.high = probe.size - 1;
+            self.next_candidate();
+        }
+    }
+
+    fn next_candidate(&mut self) {
+        self.failed_attempts = 0;
+        self.candidate = (self.low + self.high).div_ceil(2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discovery() {
+        let mut discovery = MtuDiscovery::new(MtuConfig::default());
+        let mut now = WrappedTime::default();
+        let mut packet_id = PacketId(0);
+        assert_eq!(discovery.packet_size(), DEFAULT_MIN_PACKET_SIZE);
+
+        // the path supports packets of at most 1000 bytes
+        let path_mtu = 1000;
+        while let Some(size) = discovery.next_probe_size() {
+            discovery.on_probe_sent(packet_id, size, now);
+            assert_eq!(discovery.next_probe_size(), None);
+            if size <= path_mtu {
+                let new_size = discovery.on_packet_acked(packet_id);
+                assert_eq!(new_size, Some(size));
+            } else {
+                // acks for other packets are not relevant
+                assert_eq!(discovery.on_packet_acked(PacketId(packet_id.0 + 100)), None);
+                now += Duration::from_secs(1);
+                discovery.update(now);
+            }
+            packet_id = PacketId(packet_id.0 + 1);
+        }
+        assert!(discovery.is_complete());
+        assert!(discovery.packet_size() <= path_mtu);
+        assert!(discovery.packet_size() > path_mtu - SEARCH_PRECISION);
+    }
+
+    #[test]
+    fn test_discovery_max_size() {
+        let mut discovery = MtuDiscovery::new(MtuConfig::default());
+        let size = discovery.next_probe_size().unwrap();
+        assert_eq!(size, MAX_PACKET_SIZE);
+        discovery.on_probe_sent(PacketId(0), size, WrappedTime::default());
+        assert_eq!(
+            discovery.on_packet_acked(PacketId(0)),
+            Some(MAX_PACKET_SIZE)
+        );
+        assert!(discovery.is_complete());
+    }
+}
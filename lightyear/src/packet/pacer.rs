@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+
+use bevy::reflect::Reflect;
+use bevy::utils::Duration;
+use tracing::debug;
+
+use crate::packet::packet_manager::Payload;
+
+/// Configuration of the outgoing packet pacing
+///
+/// Instead of sending all the packets that were produced during a send interval at once,
+/// the packets are queued and released progressively so that the outgoing bandwidth
+/// stays below `bytes_per_second`. This avoids loss spikes on constrained links when a large
+/// replication burst is produced.
+///
+/// If more data is produced than the link can handle, the packets get delayed more and more, until
+/// more than `max_queued_bytes` are waiting to be sent: the oldest packets are then dropped (the reliable
+/// messages that they contained will be resent). Use the bandwidth cap of the `PacketConfig` to drop
+/// low-priority messages instead.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct PacingConfig {
+    /// Maximum number of bytes that can be sent per second
+    pub bytes_per_second: u32,
+    /// Maximum number of bytes that can be sent at once, after the connection has been
+    /// idle for a while
+    pub max_burst_bytes: u32,
+    /// Maximum number of bytes that can wait in the queue. When the queue exceeds it,
+    /// the oldest packets are dropped
+    pub max_queued_bytes: u32,
+}
+
+impl PacingConfig {
+    pub fn new(bytes_per_second: u32) -> Self {
+        Self {
+            bytes_per_second,
+            // allow bursts of roughly one frame at 60fps
+            max_burst_bytes: bytes_per_second / 60,
+            // don't delay the packets by more than two seconds
+            max_queued_bytes: bytes_per_second.saturating_mul(2),
+        }
+    }
+
+    pub fn with_max_burst_bytes(mut self, max_burst_bytes: u32) -> Self {
+        self.max_burst_bytes = max_burst_bytes;
+        self
+    }
+
+    pub fn with_max_queued_bytes(mut self, max_queued_bytes: u32) -> Self {
+        self.max_queued_bytes = max_queued_bytes;
+        self
+    }
+}
+
+/// Token-bucket that spreads the packet sends over time
+#[derive(Debug)]
+pub(crate) struct PacketPacer {
+    config: PacingConfig,
+    /// Number of bytes that can be sent right now. Can be negative if we sent a packet
+    /// bigger than the remaining budget
+    budget: f32,
+    /// Packets waiting to be sent
+    queue: VecDeque<Payload>,
+    /// Total size of the packets in the queue
+    queued_bytes: usize,
+}
+
+impl PacketPacer {
+    pub(crate) fn new(config: PacingConfig) -> Self {
+        Self {
+            config,
+            budget: config.max_burst_bytes as f32,
+            queue: VecDeque::new(),
+            queued_bytes: 0,
+        }
+    }
+
+    /// Add packets to the queue of packets to send.
+    ///
+    /// The oldest packets are dropped if the queue grows bigger than `max_queued_bytes`
+    pub(crate) fn push(&mut self, payloads: impl IntoIterator<Item = Payload>) {
+        for payload in payloads {
+            self.queued_bytes += payload.len();
+            self.queue.push_back(payload);
+        }
+        let mut dropped = 0;
+        while self.queued_bytes > self.config.max_queued_bytes as usize {
+            let Some(payload) = self.queue.pop_front() else {
+                break;
+            };
+            self.queued_bytes -= payload.len();
+            dropped += 1;
+        }
+        if dropped > 0 {
+            debug!(?dropped, "pacer queue is full, dropping the oldest packets");
+        }
+    }
+
+    /// Number of packets waiting to be sent
+    pub(crate) fn queued_packets(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Refill the budget with the time elapsed since the last update, and return the packets
+    /// that can be sent now
+    pub(crate) fn drain_ready(&mut self, delta: Duration) -> Vec<Payload> {
+        self.budget += self.config.bytes_per_second as f32 * delta.as_secs_f32();
+        let mut ready = vec![];
+        // a packet can be sent as long as the budget is positive, so that packets bigger
+        // than the burst size still get sent eventually
+        while self.budget > 0.0 {
+            let Some(payload) = self.queue.pop_front() else {
+                break;
+            };
+            self.budget -= payload.len() as f32;
+            self.queued_bytes -= payload.len();
+            ready.push(payload);
+        }
+        // the budget only gets capped when there is nothing left to send: while packets are queued all
+        // the refill is kept, so that the throughput does not depend on the frame rate
+        if self.queue.is_empty() {
+            self.budget = self.budget.min(self.config.max_burst_bytes as f32);
+        }
+        ready
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::prelude::client::ClientConfig;
+    use crate::prelude::server::ServerConfig;
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct ReceivedMessages(usize);
+
+    fn count_messages(
+        mut received: ResMut<ReceivedMessages>,
+        mut events: EventReader<client::MessageEvent<Message1>>,
+    ) {
+        received.0 += events.read().count();
+    }
+
+    #[test]
+    fn test_pacing() {
+        let mut pacer = PacketPacer::new(PacingConfig::new(1000).with_max_burst_bytes(200));
+        pacer.push((0..10).map(|_| vec![0; 100]));
+
+        // the initial burst can be sent directly
+        assert_eq!(pacer.drain_ready(Duration::default()).len(), 2);
+        assert_eq!(pacer.queued_packets(), 8);
+
+        // 100ms at 1000 bytes/s: we can send 100 bytes
+        assert_eq!(pacer.drain_ready(Duration::from_millis(100)).len(), 1);
+        assert_eq!(pacer.drain_ready(Duration::default()).len(), 0);
+
+        // the budget accumulated while the queue is empty cannot exceed the max burst
+        assert_eq!(pacer.drain_ready(Duration::from_secs(10)).len(), 7);
+        assert_eq!(pacer.drain_ready(Duration::from_secs(10)).len(), 0);
+        pacer.push((0..10).map(|_| vec![0; 100]));
+        assert_eq!(pacer.drain_ready(Duration::default()).len(), 2);
+    }
+
+    /// The throughput stays at `bytes_per_second` even if the frame rate is lower than 60fps
+    #[test]
+    fn test_pacing_low_frame_rate() {
+        let mut pacer = PacketPacer::new(PacingConfig::new(6000));
+        pacer.push((0..10).map(|_| vec![0; 100]));
+        assert_eq!(pacer.drain_ready(Duration::default()).len(), 1);
+        // 6000 bytes/s at 20fps: 300 bytes per frame
+        assert_eq!(pacer.drain_ready(Duration::from_millis(50)).len(), 3);
+        assert_eq!(pacer.drain_ready(Duration::from_millis(50)).len(), 3);
+    }
+
+    /// The oldest packets are dropped when the queue is full
+    #[test]
+    fn test_pacing_queue_full() {
+        let mut pacer = PacketPacer::new(PacingConfig::new(1000).with_max_queued_bytes(300));
+        pacer.push((0..5u8).map(|i| vec![i; 100]));
+        assert_eq!(pacer.queued_packets(), 3);
+        let ready = pacer.drain_ready(Duration::from_secs(1));
+        assert_eq!(ready, vec![vec![2; 100], vec![3; 100], vec![4; 100]]);
+    }
+
+    /// Check that a burst of messages gets spread over multiple frames
+    #[test]
+    fn test_paced_connection() {
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(Duration::from_millis(10)),
+                ..default()
+            },
+            client::SyncConfig::default(),
+            client::PredictionConfig::default(),
+            client::InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            Duration::from_millis(10),
+        );
+        stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConfig>()
+            .packet = server::PacketConfig::default().with_send_pacing(10_000);
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ClientConfig>()
+            .packet = client::PacketConfig::default().with_send_pacing(10_000);
+        stepper.client_app.init_resource::<ReceivedMessages>();
+        stepper.client_app.add_systems(Update, count_messages);
+        stepper.init();
+
+        let client_id = crate::prelude::ClientId::Netcode(crate::tests::stepper::TEST_CLIENT_ID);
+        let mut connection_manager = stepper
+            .server_app
+            .world
+            .resource_mut::<server::ConnectionManager>();
+        for _ in 0..20 {
+            connection_manager
+                .send_message::<Channel1, Message1>(client_id, &Message1("a".repeat(500)))
+                .unwrap();
+        }
+        stepper.frame_step();
+        let queued_packets = stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .connection(client_id)
+            .unwrap()
+            .pacer
+            .as_ref()
+            .unwrap()
+            .queued_packets();
+        assert!(queued_packets > 0);
+        assert!(stepper.client_app.world.resource::<ReceivedMessages>().0 < 20);
+
+        // 10KB at 10KB/s
+        for _ in 0..100 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper.client_app.world.resource::<ReceivedMessages>().0,
+            20
+        );
+    }
+}
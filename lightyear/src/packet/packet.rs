@@ -24,13 +24,23 @@ wrapping_id!(PacketId);
 const HEADER_BYTES: usize = 11;
 /// The maximum of bytes that the payload of the packet can contain (excluding the header)
 /// remove 1 byte for byte alignment at the end
-pub(crate) const MTU_PAYLOAD_BYTES: usize = MAX_PACKET_SIZE - HEADER_BYTES - 1;
+pub(crate) const MTU_PAYLOAD_BYTES: usize = max_payload_bytes(MAX_PACKET_SIZE);
 
 /// The maximum number of bytes for a message before it is fragmented
 /// The final size of the fragmented packet (channel_net_id: 2, fragment_id: 1, tick: 2, message_id: 2, num_fragments: 1, number of bytes in fragment: 4)
 /// must be lower than MTU_PAYLOAD_BYTES
 /// (might even be 13 in some situations?)
-pub(crate) const FRAGMENT_SIZE: usize = MTU_PAYLOAD_BYTES - 12;
+pub(crate) const FRAGMENT_SIZE: usize = fragment_size(MAX_PACKET_SIZE);
+
+/// The maximum number of bytes that the payload of a packet of size `packet_size` can contain
+pub(crate) const fn max_payload_bytes(packet_size: usize) -> usize {
+    packet_size - HEADER_BYTES - 1
+}
+
+/// The maximum number of bytes of a fragment, for packets of size `packet_size`
+pub(crate) const fn fragment_size(packet_size: usize) -> usize {
+    max_payload_bytes(packet_size) - 12
+}
 
 // TODO: we don't need SinglePacket vs FragmentPacket; we can just re-use the same thing
 //  because MessageContainer already has the information about whether it is a fragment or not
@@ -231,6 +241,11 @@ impl BitSerializable for FragmentedPacket {
 pub(crate) enum PacketData {
     Single(SinglePacket),
     Fragmented(FragmentedPacket),
+    /// Packet that only contains padding, used to discover the maximum packet size
+    /// that can reach the remote. Contains the number of padding bytes.
+    MtuProbe(usize),
+    /// Compressed bytes of a full packet, see [`PacketCompression`](crate::packet::compression::PacketCompression)
+    Compressed(Vec<u8>),
 }
 
 impl PacketData {
@@ -240,6 +255,7 @@ impl PacketData {
             PacketData::Fragmented(fragmented_packet) => {
                 1 + fragmented_packet.packet.num_messages()
             }
+            PacketData::MtuProbe(_) | PacketData::Compressed(_) => 0,
         }
     }
     pub(crate) fn contents(self) -> HashMap<NetId, Vec<MessageContainer>> {
@@ -265,6 +281,8 @@ impl PacketData {
                         .extend(message_containers);
                 }
             }
+            // compressed packets are decompressed before their contents are read
+            PacketData::MtuProbe(_) | PacketData::Compressed(_) => {}
         }
         res
     }
@@ -281,6 +299,7 @@ impl Packet {
         match &self.data {
             PacketData::Single(single_packet) => single_packet.data.is_empty(),
             PacketData::Fragmented(fragmented_packet) => fragmented_packet.packet.data.is_empty(),
+            PacketData::MtuProbe(_) | PacketData::Compressed(_) => true,
         }
     }
 
@@ -293,6 +312,14 @@ impl Packet {
         match &self.data {
             PacketData::Single(single_packet) => single_packet.encode(writer),
             PacketData::Fragmented(fragmented_packet) => fragmented_packet.encode(writer),
+            PacketData::MtuProbe(padding) => {
+                writer.encode(vec![0u8; *padding].as_slice(), Fixed)?;
+                Ok(())
+            }
+            PacketData::Compressed(bytes) => {
+                writer.encode(bytes.as_slice(), Fixed)?;
+                Ok(())
+            }
         }
     }
 
@@ -314,6 +341,20 @@ impl Packet {
                     header,
                     data: PacketData::Fragmented(fragmented_packet),
                 })
+            }
+            PacketType::MtuProbe => {
+                let padding = reader.decode::<Vec<u8>>(Fixed)?;
+                Ok(Self {
+                    header,
+                    data: PacketData::MtuProbe(padding.len()),
+                })
+            }
+            PacketType::Compressed => {
+                let bytes = reader.decode::<Vec<u8>>(Fixed)?;
+                Ok(Self {
+                    header,
+                    data: PacketData::Compressed(bytes),
+                })
             } // _ => Err(anyhow::anyhow!("Packet type not supported")),
         }
     }
@@ -331,6 +372,9 @@ impl Packet {
             PacketData::Fragmented(fragmented_packet) => {
                 fragmented_packet.packet.add_channel(channel);
             }
+            PacketData::MtuProbe(_) | PacketData::Compressed(_) => {
+                unreachable!("cannot add channels to a probe or compressed packet")
+            }
         }
     }
 
@@ -340,6 +384,9 @@ impl Packet {
             PacketData::Fragmented(fragmented_packet) => {
                 fragmented_packet.packet.add_message(channel, message);
             }
+            PacketData::MtuProbe(_) | PacketData::Compressed(_) => {
+                unreachable!("cannot add messages to a probe or compressed packet")
+            }
         }
     }
 
@@ -349,6 +396,7 @@ impl Packet {
         match &self.data {
             PacketData::Single(single_packet) => single_packet.num_messages(),
             PacketData::Fragmented(fragmented_packet) => fragmented_packet.packet.num_messages(),
+            PacketData::MtuProbe(_) | PacketData::Compressed(_) => 0,
         }
     }
 
@@ -356,6 +404,20 @@ impl Packet {
         match &self.data {
             PacketData::Single(single_packet) => single_packet.message_acks(),
             PacketData::Fragmented(fragmented_packet) => fragmented_packet.message_acks(),
+            PacketData::MtuProbe(_) | PacketData::Compressed(_) => HashMap::new(),
+        }
+    }
+
+    /// Network ids of the channels that have messages in this packet
+    pub(crate) fn channel_ids(&self) -> Vec<ChannelId> {
+        match &self.data {
+            PacketData::Single(single_packet) => single_packet.data.keys().copied().collect(),
+            PacketData::Fragmented(fragmented_packet) => {
+                std::iter::once(fragmented_packet.channel_id)
+                    .chain(fragmented_packet.packet.data.keys().copied())
+                    .collect()
+            }
+            PacketData::MtuProbe(_) | PacketData::Compressed(_) => vec![],
         }
     }
 }
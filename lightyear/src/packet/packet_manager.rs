@@ -6,7 +6,8 @@ use crate::connection::netcode::MAX_PACKET_SIZE;
 use crate::packet::header::PacketHeaderManager;
 use crate::packet::message::{FragmentData, MessageContainer, SingleData};
 use crate::packet::packet::{
-    FragmentedPacket, Packet, PacketData, SinglePacket, FRAGMENT_SIZE, MTU_PAYLOAD_BYTES,
+    fragment_size, max_payload_bytes, FragmentedPacket, Packet, PacketData, SinglePacket,
+    MTU_PAYLOAD_BYTES,
 };
 use crate::packet::packet_type::PacketType;
 use crate::protocol::registry::NetId;
@@ -29,25 +30,44 @@ pub(crate) struct PacketBuilder {
     // TODO: should this be associated with Packet?
     try_write_buffer: BitcodeWriter,
     write_buffer: BitcodeWriter,
+    /// Maximum size of the packets that we build (can be lower than [`MAX_PACKET_SIZE`]
+    /// depending on the network path)
+    packet_size: usize,
 }
 
 impl PacketBuilder {
     pub fn new() -> Self {
         Self {
             header_manager: PacketHeaderManager::new(),
+            packet_size: MAX_PACKET_SIZE,
             // write buffer to encode packets bit by bit
             try_write_buffer: WriteBuffer::with_capacity(2 * PACKET_BUFFER_CAPACITY),
             write_buffer: WriteBuffer::with_capacity(PACKET_BUFFER_CAPACITY),
         }
     }
 
+    /// Maximum size of the packets that we build
+    pub(crate) fn packet_size(&self) -> usize {
+        self.packet_size
+    }
+
+    /// Maximum size of the fragments, for the current packet size
+    pub(crate) fn fragment_size(&self) -> usize {
+        fragment_size(self.packet_size)
+    }
+
+    /// Update the maximum size of the packets that we build
+    pub(crate) fn set_packet_size(&mut self, packet_size: usize) {
+        self.packet_size = packet_size.min(MAX_PACKET_SIZE);
+    }
+
     /// Reset the buffers used to encode packets
     pub fn clear_try_write_buffer(&mut self) {
         self.try_write_buffer.start_write();
         debug_assert_eq!(self.try_write_buffer.num_bits_written(), 0);
         // self.try_write_buffer = WriteBuffer::with_capacity(2 * PACKET_BUFFER_CAPACITY);
         self.try_write_buffer
-            .set_reserved_bits(PACKET_BUFFER_CAPACITY);
+            .set_reserved_bits(max_payload_bytes(self.packet_size) * (u8::BITS as usize));
     }
 
     //
@@ -69,7 +89,15 @@ impl PacketBuilder {
         // TODO: we should actually call finish write to byte align!
         // TODO: CAREFUL, THIS COULD ALLOCATE A BIT MORE TO BYTE ALIGN?
         let payload = Payload::from(write_buffer.finish_write());
-        debug_assert!(payload.len() <= MAX_PACKET_SIZE, "packet = {:?}", packet);
+        debug_assert!(
+            payload.len() <= self.packet_size
+                || matches!(
+                    packet.data,
+                    PacketData::MtuProbe(_) | PacketData::Compressed(_)
+                ),
+            "packet = {:?}",
+            packet
+        );
         Ok(payload)
 
         // packet.encode(&mut self.write_buffer)?;
@@ -77,6 +105,18 @@ impl PacketBuilder {
         // Ok(bytes)
     }
 
+    /// Encode a packet that contains the `compressed` bytes of `packet`
+    pub(crate) fn encode_compressed_packet(
+        &mut self,
+        packet: &Packet,
+        compressed: Vec<u8>,
+    ) -> anyhow::Result<Payload> {
+        self.encode_packet(&Packet {
+            header: packet.header.to_compressed(),
+            data: PacketData::Compressed(compressed),
+        })
+    }
+
     /// Start building new packet, we start with an empty packet
     /// that can write to a given channel
     pub(crate) fn build_new_single_packet(&mut self) -> Packet {
@@ -97,6 +137,20 @@ impl PacketBuilder {
         }
     }
 
+    /// Build a packet that only contains padding, so that the encoded packet is
+    /// at most `packet_size` bytes
+    pub(crate) fn build_mtu_probe(&mut self, packet_size: usize) -> Packet {
+        let header = self
+            .header_manager
+            .prepare_send_packet_header(PacketType::MtuProbe);
+        // leave some room for the length of the padding
+        let padding = max_payload_bytes(packet_size).saturating_sub(4);
+        Packet {
+            header,
+            data: PacketData::MtuProbe(padding),
+        }
+    }
+
     pub(crate) fn build_new_fragment_packet(
         &mut self,
         channel_id: NetId,
@@ -119,7 +173,7 @@ impl PacketBuilder {
         //  - could try to compute it manually, but the length of Bytes is encoded with Gamma
         //  - could serialize the packet somewhere, and check the number of bits written
 
-        debug_assert!(packet.fragment.bytes.len() <= FRAGMENT_SIZE);
+        debug_assert!(packet.fragment.bytes.len() <= self.fragment_size());
         if is_last_fragment {
             packet.encode(&mut self.try_write_buffer).unwrap();
             // reserve one extra bit for the continuation bit between fragment/single packet data
@@ -337,7 +391,7 @@ impl PacketBuilder {
             // Start by writing all fragmented packets
             for fragment_data in fragment_messages.into_iter() {
                 let is_last_fragment = fragment_data.is_last_fragment();
-                debug_assert!(fragment_data.bytes.len() <= FRAGMENT_SIZE);
+                debug_assert!(fragment_data.bytes.len() <= self.fragment_size());
                 let mut packet = self.build_new_fragment_packet(channel_id, fragment_data);
                 if is_last_fragment {
                     loop {
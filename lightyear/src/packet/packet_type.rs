@@ -21,4 +21,10 @@ pub enum PacketType {
     // A packet containing actual data, but which is fragmented into multiple parts
     #[bitcode_hint(frequency = 5)]
     DataFragment,
+    // A packet that only contains padding, used for path MTU discovery
+    #[bitcode_hint(frequency = 1)]
+    MtuProbe,
+    // A packet whose content is compressed. The content is a full packet once decompressed
+    #[bitcode_hint(frequency = 1)]
+    Compressed,
 }
@@ -1,4 +1,5 @@
-use std::collections::{BTreeMap, VecDeque};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::num::NonZeroU32;
 
 use crate::channel::builder::EntityUpdatesChannel;
@@ -8,12 +9,13 @@ use nonzero_ext::*;
 use tracing::{debug, error, trace};
 
 use crate::packet::message::{FragmentData, MessageContainer, MessageId, SingleData};
-use crate::prelude::{ChannelKind, ChannelRegistry, Tick};
+use crate::prelude::{ChannelKind, ChannelRegistry, ChannelSettings, Tick};
 use crate::protocol::registry::NetId;
 
 #[derive(Debug)]
 pub struct BufferedMessage {
     priority: f32,
+    channel_priority: f32,
     channel_net_id: NetId,
     message_container: MessageContainer,
 }
@@ -37,6 +39,7 @@ impl Default for PriorityConfig {
     }
 }
 
+#[cfg(feature = "client")]
 impl From<crate::client::config::PacketConfig> for PriorityConfig {
     fn from(value: crate::client::config::PacketConfig) -> Self {
         Self {
@@ -46,6 +49,7 @@ impl From<crate::client::config::PacketConfig> for PriorityConfig {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<crate::server::config::PacketConfig> for PriorityConfig {
     fn from(value: crate::server::config::PacketConfig) -> Self {
         Self {
@@ -59,6 +63,8 @@ pub(crate) struct PriorityManager {
     pub(crate) config: PriorityConfig,
     // TODO: can I do without this limiter?
     pub(crate) limiter: DefaultDirectRateLimiter,
+    /// Rate limiters for the channels that have a [`send_budget`](crate::prelude::ChannelSettings::send_budget)
+    channel_limiters: HashMap<NetId, DefaultDirectRateLimiter>,
     // Messages that could not be sent because of the bandwidth quota
     // buffered_data: Vec<BufferedMessage>,
     /// List of senders to notify when a replication update message is actually sent (included in packet)
@@ -66,15 +72,31 @@ pub(crate) struct PriorityManager {
 }
 
 impl PriorityManager {
-    pub(crate) fn new(config: PriorityConfig) -> Self {
+    pub(crate) fn new(config: PriorityConfig, channel_registry: &ChannelRegistry) -> Self {
+        let channel_limiters = channel_registry
+            .settings()
+            .filter_map(|(net_id, settings)| {
+                let send_budget = settings.send_budget?;
+                Some((net_id, DefaultDirectRateLimiter::direct(send_budget)))
+            })
+            .collect();
         Self {
             config: config.clone(),
             limiter: DefaultDirectRateLimiter::direct(config.bandwidth_quota),
+            channel_limiters,
             // buffered_data: Vec::new(),
             replication_update_senders: Vec::new(),
         }
     }
 
+    /// Start tracking the send budget of a channel that was registered after the manager was created
+    pub(crate) fn add_channel(&mut self, net_id: NetId, settings: &ChannelSettings) {
+        if let Some(send_budget) = settings.send_budget {
+            self.channel_limiters
+                .insert(net_id, DefaultDirectRateLimiter::direct(send_budget));
+        }
+    }
+
     /// Create a channel to notify when a replication update message is actually sent (included in packet)
     /// (as opposed to dropped because of the bandwidth quota)
     pub(crate) fn subscribe_replication_update_sent_messages(&mut self) -> Receiver<MessageId> {
@@ -96,9 +118,9 @@ impl PriorityManager {
         BTreeMap<NetId, (VecDeque<SingleData>, VecDeque<FragmentData>)>,
         u32,
     ) {
-        // if the bandwidth quota is disabled, just pass all messages through
+        // if the bandwidth quota is disabled and no channel has a budget, just pass all messages through
         // As an optimization: no need to send the tick of the message, it is the same as the header tick
-        if !self.config.enabled {
+        if !self.config.enabled && self.channel_limiters.is_empty() {
             let mut data_to_send: BTreeMap<NetId, (VecDeque<SingleData>, VecDeque<FragmentData>)> =
                 BTreeMap::new();
             for (net_id, (single, fragment)) in data {
@@ -131,6 +153,7 @@ impl PriorityManager {
                         }
                         BufferedMessage {
                             priority: single.priority * channel_priority,
+                            channel_priority,
                             channel_net_id: net_id,
                             message_container: MessageContainer::Single(single),
                         }
@@ -141,6 +164,7 @@ impl PriorityManager {
                         }
                         BufferedMessage {
                             priority: fragment.priority * channel_priority,
+                            channel_priority,
                             channel_net_id: net_id,
                             message_container: MessageContainer::Fragment(fragment),
                         }
@@ -152,35 +176,71 @@ impl PriorityManager {
         // // add all new messages to the list of messages that could not be sent
         // self.buffered_data.extend(all_messages);
 
-        // sort from highest priority to lower
-        // self.buffered_data
-        all_messages.sort_by(|a, b| a.priority.partial_cmp(&b.priority).unwrap());
+        // sort from highest priority to lower.
+        // Ties are broken by channel priority and then by channel id, so that the order does not
+        // depend on the order in which the channels were drained. The sort is stable, so the messages
+        // of a channel keep their relative order.
+        all_messages.sort_by(|a, b| {
+            b.priority
+                .partial_cmp(&a.priority)
+                .unwrap_or(Ordering::Equal)
+                .then(
+                    b.channel_priority
+                        .partial_cmp(&a.channel_priority)
+                        .unwrap_or(Ordering::Equal),
+                )
+                .then(a.channel_net_id.cmp(&b.channel_net_id))
+        });
         trace!(
             "all messages to send, sorted by priority: {:?}",
             all_messages
         );
 
-        // select the top messages with the rate limiter
+        // select the top messages with the rate limiters.
+        // The messages that don't fit in the bandwidth are evicted, but the messages with a lower priority
+        // can still be sent if they are small enough to fit in the remaining bandwidth
         let mut data_to_send: BTreeMap<NetId, (VecDeque<SingleData>, VecDeque<FragmentData>)> =
             BTreeMap::new();
         let mut bytes_used = 0;
-        while let Some(buffered_message) = all_messages.pop() {
+        let mut num_messages_discarded = 0;
+        for buffered_message in all_messages {
             trace!(channel=?buffered_message.channel_net_id, "Sending message with priority {:?}", buffered_message.priority);
             // we don't use the exact size of the message, but the size of the bytes
             // we will adjust for this later
             let message_bytes = buffered_message.message_container.bytes().len() as u32;
-            let nonzero_message_bytes = NonZeroU32::try_from(message_bytes).unwrap();
-            let Ok(result) = self.limiter.check_n(nonzero_message_bytes) else {
-                error!("the bandwidth does not have enough capacity for a message of this size!");
-                break;
-            };
-            let Ok(()) = result else {
-                debug!("Bandwidth quota reached, no more messages can be sent this tick");
-                break;
-            };
-
-            // keep track of the bytes we added to the rate limiter
-            bytes_used += message_bytes;
+
+            // the channel budget only prevents this channel from sending more messages,
+            // the messages of the other channels can still be sent
+            // (empty messages don't use any bandwidth, so they are not rate limited)
+            if let Some((channel_limiter, nonzero_message_bytes)) = self
+                .channel_limiters
+                .get(&buffered_message.channel_net_id)
+                .zip(NonZeroU32::new(message_bytes))
+            {
+                if !matches!(channel_limiter.check_n(nonzero_message_bytes), Ok(Ok(()))) {
+                    trace!(channel=?buffered_message.channel_net_id, "Channel budget reached, message is not sent");
+                    num_messages_discarded += 1;
+                    continue;
+                }
+            }
+            if let Some(nonzero_message_bytes) =
+                NonZeroU32::new(message_bytes).filter(|_| self.config.enabled)
+            {
+                let Ok(result) = self.limiter.check_n(nonzero_message_bytes) else {
+                    error!(
+                        "the bandwidth does not have enough capacity for a message of this size!"
+                    );
+                    num_messages_discarded += 1;
+                    continue;
+                };
+                let Ok(()) = result else {
+                    trace!("Bandwidth quota reached, the message is not sent this tick");
+                    num_messages_discarded += 1;
+                    continue;
+                };
+                // keep track of the bytes we added to the rate limiter
+                bytes_used += message_bytes;
+            }
 
             // the message is allowed, add it to the list of messages to send
             let channel_data = data_to_send
@@ -216,7 +276,7 @@ impl PriorityManager {
             }
         }
 
-        // all the other messages that don't make the cut, we just drop
+        // all the messages that didn't make the cut are dropped
         // - unreliable messages: they are unreliable so it's ok
         // - reliable messages: they will be retried later, maybe with higher priority?
         // - unreliable entity updates: the replication sender keeps track for each entity of when we were able to send an update
@@ -230,9 +290,176 @@ impl PriorityManager {
         debug!(
             bytes_sent = ?bytes_used,
             ?num_messages_sent,
-            num_messages_discarded = ?all_messages.len(),
+            ?num_messages_discarded,
             "priority filter done.");
 
         (data_to_send, bytes_used)
     }
 }
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::default;
+    use bytes::Bytes;
+
+    use crate::prelude::{ChannelMode, ChannelSettings};
+    use crate::tests::protocol::{Channel1, Channel2};
+
+    use super::*;
+
+    fn single(bytes: &'static str) -> VecDeque<SingleData> {
+        VecDeque::from([SingleData::new(None, Bytes::from(bytes), 1.0)])
+    }
+
+    #[test]
+    fn test_priority_filter_channel_priority() {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        });
+        channel_registry.add_channel::<Channel2>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            priority: 2.0,
+            ..default()
+        });
+        let channel_1 = *channel_registry
+            .get_net_from_kind(&ChannelKind::of::<Channel1>())
+            .unwrap();
+        let channel_2 = *channel_registry
+            .get_net_from_kind(&ChannelKind::of::<Channel2>())
+            .unwrap();
+        let config = PriorityConfig {
+            bandwidth_quota: Quota::per_second(nonzero!(10u32)),
+            enabled: true,
+        };
+        let mut manager = PriorityManager::new(config, &channel_registry);
+
+        // only one message fits in the bandwidth: the channel with the highest priority wins
+        let (data, bytes_used) = manager.priority_filter(
+            vec![
+                (channel_1, (single("hello!"), VecDeque::new())),
+                (channel_2, (single("world!"), VecDeque::new())),
+            ],
+            &channel_registry,
+            Tick(0),
+        );
+        assert_eq!(bytes_used, 6);
+        assert_eq!(data.keys().copied().collect::<Vec<_>>(), vec![channel_2]);
+    }
+
+    #[test]
+    fn test_priority_filter_evicts_messages_that_dont_fit() {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        });
+        channel_registry.add_channel::<Channel2>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            priority: 2.0,
+            ..default()
+        });
+        let channel_1 = *channel_registry
+            .get_net_from_kind(&ChannelKind::of::<Channel1>())
+            .unwrap();
+        let channel_2 = *channel_registry
+            .get_net_from_kind(&ChannelKind::of::<Channel2>())
+            .unwrap();
+        let config = PriorityConfig {
+            bandwidth_quota: Quota::per_second(nonzero!(10u32)),
+            enabled: true,
+        };
+        let mut manager = PriorityManager::new(config, &channel_registry);
+
+        let mut channel_1_messages = single("hello!");
+        channel_1_messages.extend(single("hi"));
+        let (data, bytes_used) = manager.priority_filter(
+            vec![
+                (channel_1, (channel_1_messages, VecDeque::new())),
+                (channel_2, (single("world!"), VecDeque::new())),
+            ],
+            &channel_registry,
+            Tick(0),
+        );
+        // the first message of channel 1 doesn't fit after the message of channel 2,
+        // but it doesn't prevent the smaller message from being sent
+        assert_eq!(bytes_used, 8);
+        assert_eq!(data.get(&channel_2).unwrap().0.len(), 1);
+        assert_eq!(data.get(&channel_1).unwrap().0.len(), 1);
+        assert_eq!(data.get(&channel_1).unwrap().0[0].bytes, Bytes::from("hi"));
+    }
+
+    #[test]
+    fn test_priority_filter_channel_budget() {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            priority: 2.0,
+            send_budget: Some(Quota::per_second(nonzero!(10u32))),
+            ..default()
+        });
+        channel_registry.add_channel::<Channel2>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        });
+        let channel_1 = *channel_registry
+            .get_net_from_kind(&ChannelKind::of::<Channel1>())
+            .unwrap();
+        let channel_2 = *channel_registry
+            .get_net_from_kind(&ChannelKind::of::<Channel2>())
+            .unwrap();
+        // the channel budget is enforced even without a global bandwidth cap
+        let mut manager = PriorityManager::new(PriorityConfig::default(), &channel_registry);
+
+        let mut channel_1_messages = single("hello!");
+        channel_1_messages.extend(single("again!"));
+        let (data, bytes_used) = manager.priority_filter(
+            vec![
+                (channel_1, (channel_1_messages, VecDeque::new())),
+                (channel_2, (single("world!"), VecDeque::new())),
+            ],
+            &channel_registry,
+            Tick(0),
+        );
+        // the global limiter is disabled
+        assert_eq!(bytes_used, 0);
+        // the first message of channel 1 is sent, the second one goes over the budget,
+        // but it does not prevent channel 2 from sending its message
+        assert_eq!(data.get(&channel_1).unwrap().0.len(), 1);
+        assert_eq!(
+            data.get(&channel_1).unwrap().0[0].bytes,
+            Bytes::from("hello!")
+        );
+        assert_eq!(data.get(&channel_2).unwrap().0.len(), 1);
+    }
+
+    #[test]
+    fn test_priority_filter_empty_message() {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            send_budget: Some(Quota::per_second(nonzero!(10u32))),
+            ..default()
+        });
+        let channel_1 = *channel_registry
+            .get_net_from_kind(&ChannelKind::of::<Channel1>())
+            .unwrap();
+        let config = PriorityConfig {
+            bandwidth_quota: Quota::per_second(nonzero!(10u32)),
+            enabled: true,
+        };
+        let mut manager = PriorityManager::new(config, &channel_registry);
+
+        // empty messages (that replace the reliable messages that expired) don't use any bandwidth
+        let mut channel_1_messages = single("");
+        channel_1_messages.extend(single("hello!"));
+        let (data, bytes_used) = manager.priority_filter(
+            vec![(channel_1, (channel_1_messages, VecDeque::new()))],
+            &channel_registry,
+            Tick(0),
+        );
+        assert_eq!(bytes_used, 6);
+        assert_eq!(data.get(&channel_1).unwrap().0.len(), 2);
+    }
+}
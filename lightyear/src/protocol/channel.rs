@@ -1,20 +1,30 @@
 use bevy::app::App;
 use bevy::ecs::entity::MapEntities;
-use bevy::prelude::{Resource, TypePath};
+use bevy::prelude::{default, Commands, Resource, TypePath, World};
 use bevy::reflect::Reflect;
+use bitcode::{Decode, Encode};
 use serde::Deserialize;
 use std::any::TypeId;
 use std::collections::HashMap;
 
-use crate::channel::builder::{Channel, ChannelBuilder, ChannelSettings};
+use crate::channel::builder::{Channel, ChannelBuilder, ChannelSettings, StreamSettings};
 use crate::channel::builder::{
-    ChannelContainer, EntityActionsChannel, EntityUpdatesChannel, InputChannel, PingChannel,
+    ChannelContainer, ChannelSyncChannel, EntityActionsChannel, EntityUpdatesChannel, InputChannel,
+    InspectorChannel, NackChannel, PingChannel, ScheduledEventChannel, ServerStatsChannel,
+    WorldSnapshotChannel,
 };
+#[cfg(feature = "client")]
+use crate::prelude::client;
+#[cfg(feature = "server")]
+use crate::prelude::server;
 use crate::prelude::{
     ChannelDirection, ChannelMode, DefaultUnorderedUnreliableChannel, Message, ReliableSettings,
     TickBufferChannel,
 };
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
+#[cfg(feature = "server")]
+use crate::server::config::ServerConfig;
+use crate::transport::Delivery;
 
 // TODO: derive Reflect once we reach bevy 0.14
 /// ChannelKind - internal wrapper around the type of the channel
@@ -23,6 +33,10 @@ pub struct ChannelKind(TypeId);
 
 pub type ChannelId = NetId;
 
+/// Maximum number of messages that can wait for the network id of a channel registered at runtime to be
+/// exchanged with the remote peer. Sending more messages on the channel returns an error.
+pub(crate) const MAX_PENDING_CHANNEL_MESSAGES: usize = 1024;
+
 impl ChannelKind {
     pub fn of<C: Channel>() -> Self {
         Self(TypeId::of::<C>())
@@ -68,6 +82,12 @@ pub struct ChannelRegistry {
     pub(in crate::protocol) builder_map: HashMap<ChannelKind, ChannelBuilder>,
     pub(in crate::protocol) kind_map: TypeMapper<ChannelKind>,
     pub(in crate::protocol) name_map: HashMap<ChannelKind, String>,
+    /// Channels that were registered after the app was built (see [`RegisterChannelExt`]).
+    /// The server announces their network id to every client.
+    runtime_channels: Vec<ChannelKind>,
+    /// Channels that were registered at runtime on the client, but whose network id
+    /// was not announced by the server yet
+    unbound_channels: HashMap<String, (ChannelKind, ChannelBuilder)>,
     built: bool,
 }
 
@@ -77,39 +97,89 @@ impl ChannelRegistry {
             builder_map: HashMap::new(),
             kind_map: TypeMapper::new(),
             name_map: HashMap::new(),
+            runtime_channels: Vec::new(),
+            unbound_channels: HashMap::new(),
             built: false,
         };
         registry.add_channel::<EntityUpdatesChannel>(ChannelSettings {
             mode: ChannelMode::UnorderedUnreliableWithAcks,
             direction: ChannelDirection::Bidirectional,
             priority: 1.0,
+            ..default()
         });
         registry.add_channel::<EntityActionsChannel>(ChannelSettings {
             mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
             direction: ChannelDirection::Bidirectional,
             // we want to send the entity actions as soon as possible
             priority: 10.0,
+            ..default()
+        });
+        registry.add_channel::<WorldSnapshotChannel>(ChannelSettings {
+            mode: ChannelMode::Stream(StreamSettings::default()),
+            direction: ChannelDirection::ServerToClient,
+            priority: 10.0,
+            // the snapshot can be big, and is already compressed
+            delivery: Delivery::Stream,
+            compress: false,
+            ..default()
         });
         registry.add_channel::<PingChannel>(ChannelSettings {
             mode: ChannelMode::SequencedUnreliable,
             direction: ChannelDirection::Bidirectional,
             // we always want to include the ping in the packet
             priority: 1000.0,
+            ..default()
+        });
+        registry.add_channel::<NackChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            direction: ChannelDirection::Bidirectional,
+            // the missing messages should be requested as soon as possible
+            priority: 100.0,
+            ..default()
+        });
+        registry.add_channel::<ChannelSyncChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            direction: ChannelDirection::Bidirectional,
+            // the channels must be registered before they can be used
+            priority: 100.0,
+            ..default()
+        });
+        registry.add_channel::<ServerStatsChannel>(ChannelSettings {
+            mode: ChannelMode::SequencedUnreliable,
+            direction: ChannelDirection::ServerToClient,
+            priority: 1.0,
+            ..default()
+        });
+        registry.add_channel::<InspectorChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            direction: ChannelDirection::Bidirectional,
+            priority: 1.0,
+            ..default()
+        });
+        registry.add_channel::<ScheduledEventChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
+            direction: ChannelDirection::ServerToClient,
+            // the events must arrive before they are triggered
+            priority: 10.0,
+            ..default()
         });
         registry.add_channel::<InputChannel>(ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             direction: ChannelDirection::ClientToServer,
             priority: 3.0,
+            ..default()
         });
         registry.add_channel::<DefaultUnorderedUnreliableChannel>(ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             direction: ChannelDirection::Bidirectional,
             priority: 1.0,
+            ..default()
         });
         registry.add_channel::<TickBufferChannel>(ChannelSettings {
             mode: ChannelMode::TickBuffered,
             direction: ChannelDirection::ClientToServer,
             priority: 1.0,
+            ..default()
         });
         registry
     }
@@ -123,6 +193,14 @@ impl ChannelRegistry {
         channels
     }
 
+    /// Iterate over the settings of all the registered channels, along with their network id
+    pub(crate) fn settings(&self) -> impl Iterator<Item = (NetId, &ChannelSettings)> {
+        self.builder_map.iter().filter_map(|(kind, builder)| {
+            let net_id = self.kind_map.net_id(kind)?;
+            Some((*net_id, &builder.settings))
+        })
+    }
+
     pub fn kind_map(&self) -> TypeMapper<ChannelKind> {
         self.kind_map.clone()
     }
@@ -135,6 +213,91 @@ impl ChannelRegistry {
         self.name_map.insert(kind, name.to_string());
     }
 
+    /// Register a channel after the app was built.
+    ///
+    /// If `assign_net_id` is true (on the server), the channel gets a network id right away.
+    /// Otherwise (on the client), the channel can only be used once the server announced its network id
+    /// (see [`bind_channel`](Self::bind_channel)).
+    ///
+    /// Registering the same channel multiple times has no effect.
+    pub(crate) fn add_runtime_channel<C: Channel>(
+        &mut self,
+        settings: ChannelSettings,
+        assign_net_id: bool,
+    ) -> ChannelKind {
+        let kind = ChannelKind::of::<C>();
+        if self.builder_map.contains_key(&kind) || self.unbound_channels.contains_key(C::name()) {
+            return kind;
+        }
+        if assign_net_id {
+            self.add_channel::<C>(settings);
+            self.runtime_channels.push(kind);
+        } else {
+            self.unbound_channels
+                .insert(C::name().to_string(), (kind, C::get_builder(settings)));
+        }
+        kind
+    }
+
+    /// Assign the network id announced by the server to a channel that was registered at runtime.
+    ///
+    /// Returns `None` if no channel with this name was registered yet.
+    pub(crate) fn bind_channel(
+        &mut self,
+        name: &str,
+        net_id: ChannelId,
+    ) -> anyhow::Result<Option<ChannelKind>> {
+        let Some((kind, _)) = self.unbound_channels.get(name) else {
+            return Ok(None);
+        };
+        if let Some(existing) = self.kind_map.kind(net_id) {
+            if existing != kind {
+                anyhow::bail!("network id {net_id} of channel {name} is already used");
+            }
+        }
+        let (kind, builder) = self.unbound_channels.remove(name).unwrap();
+        self.kind_map.insert(kind, net_id);
+        self.builder_map.insert(kind, builder);
+        self.name_map.insert(kind, name.to_string());
+        Ok(Some(kind))
+    }
+
+    /// True if the channel was registered at runtime, but doesn't have a network id yet
+    pub(crate) fn is_unbound(&self, kind: &ChannelKind) -> bool {
+        self.unbound_channels.values().any(|(k, _)| k == kind)
+    }
+
+    /// Iterate over the channels that were registered at runtime, along with their network id
+    pub(crate) fn runtime_channels(&self) -> impl Iterator<Item = (ChannelKind, NetId)> + '_ {
+        self.runtime_channels
+            .iter()
+            .filter_map(|kind| Some((*kind, *self.kind_map.net_id(kind)?)))
+    }
+
+    /// Write a description of the channels registered before the app was built, that must be identical
+    /// on the client and the server (used to compute the [`registries_hash`](crate::protocol::registries_hash))
+    pub(crate) fn describe(&self, description: &mut String) {
+        let mut channels = self
+            .kind_map
+            .id_map
+            .iter()
+            .filter(|(_, kind)| !self.runtime_channels.contains(kind))
+            .collect::<Vec<_>>();
+        channels.sort_by_key(|(net_id, _)| **net_id);
+        for (net_id, kind) in channels {
+            let name = self.name(kind).unwrap_or("unknown");
+            let Some(builder) = self.builder_map.get(kind) else {
+                continue;
+            };
+            // the reliability settings can be tuned separately on each peer
+            description.push_str(&format!(
+                "channel {net_id} {name} {:?} {:?}\n",
+                std::mem::discriminant(&builder.settings.mode),
+                builder.settings.direction
+            ));
+        }
+    }
+
     /// get the registered object for a given type
     pub fn get_builder_from_kind(&self, channel_kind: &ChannelKind) -> Option<&ChannelBuilder> {
         self.builder_map.get(channel_kind)
@@ -175,6 +338,52 @@ impl AppChannelExt for App {
     }
 }
 
+/// Extension trait to register a [`Channel`] after the app was built (for example from a plugin that is
+/// loaded later) via [`Commands`].
+///
+/// The channel must be registered (with the same settings) on both the server and the client.
+/// The server assigns a network id to the channel and announces it to the clients.
+/// The messages sent on the channel before both peers agree on its network id are buffered until then.
+pub trait RegisterChannelExt {
+    fn register_channel<C: Channel>(&mut self, settings: ChannelSettings);
+}
+
+impl RegisterChannelExt for Commands<'_, '_> {
+    fn register_channel<C: Channel>(&mut self, settings: ChannelSettings) {
+        self.add(move |world: &mut World| {
+            #[cfg(feature = "server")]
+            let is_server = world.get_resource::<ServerConfig>().is_some();
+            #[cfg(not(feature = "server"))]
+            let is_server = false;
+            world
+                .resource_mut::<ChannelRegistry>()
+                .add_runtime_channel::<C>(settings.clone(), is_server);
+            if is_server {
+                #[cfg(feature = "server")]
+                if let Some(mut connection_manager) =
+                    world.get_resource_mut::<server::ConnectionManager>()
+                {
+                    connection_manager.register_channel::<C>(settings);
+                }
+            } else {
+                #[cfg(feature = "client")]
+                if let Some(mut connection_manager) =
+                    world.get_resource_mut::<client::ConnectionManager>()
+                {
+                    connection_manager.register_channel::<C>(settings);
+                }
+            }
+        });
+    }
+}
+
+/// Message sent by the server to announce the network id of a channel that was registered at runtime
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct ChannelRegistration {
+    pub name: String,
+    pub net_id: ChannelId,
+}
+
 #[cfg(test)]
 mod tests {
     use bevy::prelude::{default, TypePath};
@@ -1,4 +1,4 @@
-use anyhow::Context;
+use anyhow::{bail, Context};
 use bevy::app::PreUpdate;
 use bevy::ecs::entity::MapEntities;
 use std::any::TypeId;
@@ -7,11 +7,13 @@ use std::hash::Hash;
 use std::ops::{Add, Mul};
 
 use bevy::prelude::{
-    App, Component, DetectChangesMut, Entity, EntityMapper, EntityWorldMut, IntoSystemConfigs,
-    Resource, TypePath, World,
+    App, AppTypeRegistry, Commands, Component, DetectChangesMut, Entity, EntityMapper, EntityRef,
+    EntityWorldMut, IntoSystemConfigs, Mut, Quat, Reflect, ReflectComponent, Resource, TypePath,
+    World,
 };
-use bevy::reflect::{FromReflect, GetTypeRegistration};
-use bevy::utils::HashMap;
+use bevy::reflect::serde::{TypedReflectDeserializer, TypedReflectSerializer};
+use bevy::reflect::{FromReflect, GetTypeRegistration, TypeRegistry};
+use bevy::utils::{get_short_name, Duration, HashMap};
 use cfg_if::cfg_if;
 
 use bitcode::encoding::Fixed;
@@ -20,15 +22,23 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, trace, warn};
 
-use crate::client::components::{ComponentSyncMode, SyncMetadata};
+use crate::client::components::{
+    ComponentSyncMode, MapEntitiesTarget, SyncComponent, SyncMetadata,
+};
+#[cfg(feature = "client")]
 use crate::client::config::ClientConfig;
+#[cfg(feature = "client")]
 use crate::client::interpolation::{add_interpolation_systems, add_prepare_interpolation_systems};
+#[cfg(feature = "client")]
 use crate::client::prediction::plugin::add_prediction_systems;
-use crate::prelude::client::SyncComponent;
-use crate::prelude::server::{ServerConfig, ServerPlugins};
+#[cfg(feature = "client")]
+use crate::client::transition::add_sync_transition_systems;
+use crate::connection::id::ClientId;
+#[cfg(feature = "client")]
+use crate::prelude::client;
 use crate::prelude::{
-    client, server, AppMessageExt, ChannelDirection, Message, MessageRegistry,
-    PreSpawnedPlayerObject, RemoteEntityMap, ReplicateResourceMetadata, Tick,
+    AppMessageExt, ChannelDirection, Message, MessageRegistry, PreSpawnedPlayerObject,
+    RemoteEntityMap, ReplicateResourceMetadata, Tick,
 };
 use crate::protocol::message::{MessageKind, MessageRegistration, MessageType};
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
@@ -36,15 +46,18 @@ use crate::protocol::serialize::{ErasedSerializeFns, MapEntitiesFn, SerializeFns
 use crate::protocol::{BitSerializable, EventContext};
 use crate::serialize::bitcode::reader::BitcodeReader;
 use crate::serialize::bitcode::writer::BitcodeWriter;
+use crate::serialize::quantize::QuatCompression;
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::serialize::RawData;
-use crate::server::networking::is_started;
+#[cfg(feature = "server")]
+use crate::server::config::ServerConfig;
 use crate::shared::events::connection::{
     ConnectionEvents, IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
 };
-use crate::shared::replication::components::ShouldBePredicted;
 use crate::shared::replication::components::{PrePredicted, ShouldBeInterpolated};
+use crate::shared::replication::components::{Replicated, ShouldBePredicted};
+use crate::shared::replication::delta::{DeltaHistory, DeltaValue, Diffable};
 use crate::shared::replication::entity_map::EntityMap;
 use crate::shared::replication::ReplicationSend;
 use crate::shared::sets::InternalMainSet;
@@ -85,6 +98,10 @@ pub type ComponentNetId = NetId;
 /// Provided that your type implements [`MapEntities`], you can extend the protocol to support this behaviour, by
 /// calling the [`add_map_entities`](ComponentRegistration::add_map_entities) method.
 ///
+/// When the component is copied from the Confirmed entity to a Predicted or Interpolated entity, the entities it references
+/// are mapped to their Predicted (or Interpolated) counterparts. You can choose another target with the
+/// [`with_map_entities_target`](ComponentRegistration::with_map_entities_target) method.
+///
 /// #### Prediction
 /// When client-prediction is enabled, we create two distinct entities on the client when the server replicates an entity: a Confirmed entity and a Predicted entity.
 /// The Confirmed entity will just get updated when the client receives the server updates, while the Predicted entity will be updated by the client's prediction system.
@@ -143,24 +160,85 @@ pub struct ComponentRegistry {
     replication_map: HashMap<ComponentKind, ReplicationMetadata>,
     interpolation_map: HashMap<ComponentKind, InterpolationMetadata>,
     prediction_map: HashMap<ComponentKind, PredictionMetadata>,
+    /// Predicates evaluated on the server before sending a component update
+    replicate_if_map: HashMap<ComponentKind, unsafe fn()>,
+    /// Minimum interval between two updates of the component sent by the server
+    send_interval_map: HashMap<ComponentKind, Duration>,
+    /// Validations evaluated on the server before applying a component update received from a client
+    validation_map: HashMap<ComponentKind, unsafe fn()>,
+    /// Version of the protocol, that the client announces to the server when it connects
+    protocol_version: ProtocolVersion,
+    /// Migrations to the representations of the components used by older protocol versions, sorted by version.
+    ///
+    /// The functions are: serialize, downgrade, deserialize, upgrade
+    migrations_map: HashMap<ComponentKind, Vec<(ProtocolVersion, [unsafe fn(); 4])>>,
     serialize_fns_map: HashMap<ComponentKind, ErasedSerializeFns>,
+    /// True if the components are serialized with the [`canonical`](crate::serialize::canonical) encoding
+    canonical_encoding: bool,
+    /// Rotations that are compressed instead of being serialized with the serialize fns.
+    ///
+    /// The functions are: serialize, deserialize, quantize
+    quantization_map: HashMap<ComponentKind, (QuatCompression, [unsafe fn(); 3])>,
+    /// Components that are sent as a difference with the last value acked by the remote.
+    ///
+    /// The functions are: serialize_delta, value, read
+    delta_map: HashMap<ComponentKind, [unsafe fn(); 3]>,
+    /// Local entities that the entity references resolve to when the component is synced from the Confirmed entity
+    map_entities_target_map: HashMap<ComponentKind, MapEntitiesTarget>,
+    /// Functions used to serialize the components of an entity for the [inspector](crate::server::inspector)
+    inspect_map: HashMap<ComponentKind, RawInspectFn>,
+    /// Functions used to serialize the components of an entity when [saving](crate::server::persistence) the replicated world
+    save_map: HashMap<ComponentKind, RawSaveFn>,
+    /// Components registered at runtime by their [`TypePath`], that are serialized with reflection,
+    /// by the network id assigned locally (which is the one used by the server)
+    dynamic_map: HashMap<ComponentNetId, DynamicComponent>,
+    /// Dynamic components by the network id that the server assigned to them (only used on the client)
+    remote_dynamic_map: HashMap<ComponentNetId, DynamicComponent>,
     pub(crate) kind_map: TypeMapper<ComponentKind>,
 }
 
+/// A component registered at runtime with [`ComponentRegistry::register_dynamic_component`]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DynamicComponent {
+    pub(crate) type_path: String,
+    pub(crate) type_id: TypeId,
+}
+
+/// The network ids of the dynamic components are in `DYNAMIC_NET_ID_START..`, the network ids of the components
+/// of the protocol are below. They are assigned by the server in the order of registration, and sent to the
+/// clients that registered the same components.
+pub(crate) const DYNAMIC_NET_ID_START: ComponentNetId = 1 << 15;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReplicationMetadata {
     pub write: RawWriteFn,
     pub remove: RawRemoveFn,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct PredictionMetadata {
     pub prediction_mode: ComponentSyncMode,
     pub correction: Option<unsafe fn()>,
+    /// Number of ticks over which the correction is applied.
+    /// If `None`, it is computed from [`PredictionConfig::correction_ticks_factor`](crate::client::prediction::plugin::PredictionConfig)
+    pub correction_ticks: Option<u16>,
     /// Function used to compare the confirmed component with the predicted component's history
     /// to determine if a rollback is needed. Returns true if we should do a rollback.
     /// Will default to a PartialEq::ne implementation, but can be overriden.
     pub should_rollback: unsafe fn(),
+    /// Function used to measure how far the predicted component was from the confirmed component
+    /// when there was a misprediction
+    pub misprediction_metric: Option<unsafe fn()>,
+}
+
+/// The function pointers are not compared, since their addresses are not guaranteed to be unique
+impl PartialEq for PredictionMetadata {
+    fn eq(&self, other: &Self) -> bool {
+        self.prediction_mode == other.prediction_mode
+            && self.correction.is_some() == other.correction.is_some()
+            && self.correction_ticks == other.correction_ticks
+            && self.misprediction_metric.is_some() == other.misprediction_metric.is_some()
+    }
 }
 
 impl PredictionMetadata {
@@ -169,7 +247,9 @@ impl PredictionMetadata {
         Self {
             prediction_mode: mode,
             correction: None,
+            correction_ticks: None,
             should_rollback: unsafe { std::mem::transmute(should_rollback) },
+            misprediction_metric: None,
         }
     }
 }
@@ -181,15 +261,42 @@ pub struct InterpolationMetadata {
 }
 
 type RawRemoveFn = fn(&ComponentRegistry, &mut EntityWorldMut);
+type RawInspectFn =
+    fn(&ComponentRegistry, EntityRef, &mut BitcodeWriter) -> anyhow::Result<RawData>;
+type RawSaveFn =
+    fn(&ComponentRegistry, EntityRef, &mut BitcodeWriter) -> Option<anyhow::Result<RawData>>;
 type RawWriteFn = fn(
     &ComponentRegistry,
     &mut BitcodeReader,
     ComponentNetId,
+    Option<ProtocolVersion>,
     &mut EntityWorldMut,
     &mut EntityMap,
     &mut ConnectionEvents,
 ) -> anyhow::Result<()>;
 
+type MigrationSerializeFn<C> =
+    fn(&C, downgrade: unsafe fn(), writer: &mut BitcodeWriter) -> anyhow::Result<()>;
+type MigrationDeserializeFn<C> =
+    fn(reader: &mut BitcodeReader, upgrade: unsafe fn()) -> anyhow::Result<C>;
+
+type QuantizedSerializeFn<C> =
+    fn(&C, compression: QuatCompression, writer: &mut BitcodeWriter) -> anyhow::Result<()>;
+type QuantizedDeserializeFn<C> =
+    fn(reader: &mut BitcodeReader, compression: QuatCompression) -> anyhow::Result<C>;
+type QuantizeFn<C> = fn(&C, compression: QuatCompression) -> C;
+
+type DeltaSerializeFn<C> =
+    fn(&C, base: &DeltaValue, writer: &mut BitcodeWriter) -> anyhow::Result<()>;
+type DeltaValueFn<C> = fn(&C) -> DeltaValue;
+type DeltaReadFn<C> = fn(
+    &ComponentRegistry,
+    &mut BitcodeReader,
+    ComponentNetId,
+    &mut EntityWorldMut,
+    &mut EntityMap,
+) -> anyhow::Result<Option<C>>;
+
 /// Function used to interpolate from one component state (`start`) to another (`other`)
 /// t goes from 0.0 (`start`) to 1.0 (`other`)
 pub type LerpFn<C> = fn(start: &C, other: &C, t: f32) -> C;
@@ -198,6 +305,155 @@ pub type LerpFn<C> = fn(start: &C, other: &C, t: f32) -> C;
 /// Defaults to PartialEq::ne
 type ShouldRollbackFn<C> = fn(this: &C, that: &C) -> bool;
 
+/// Function that measures the magnitude of a misprediction, by comparing the client's predicted value
+/// with the server's value (for example the distance between the two positions)
+pub type MispredictionMetricFn<C> = fn(predicted: &C, confirmed: &C) -> f32;
+
+/// Function evaluated on the server before sending an update for a component.
+/// Returns false if the update should not be sent.
+pub type ReplicateIfFn<C> = fn(component: &C, context: &ReplicationContext) -> bool;
+
+/// Information about the update that is about to be sent, provided to the [`ReplicateIfFn`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplicationContext {
+    /// The server entity that holds the component
+    pub entity: Entity,
+    /// The current server tick
+    pub tick: Tick,
+}
+
+/// Result of the validation of a component value received from a client, returned by a [`ValidateFn`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Validation<C> {
+    /// Apply the received value
+    Accept,
+    /// Apply `value` instead of the received value (for example the received value clamped to the allowed range).
+    ///
+    /// If `correct` is true, `value` is sent back to the client so that it overwrites its own value.
+    Clamp { value: C, correct: bool },
+    /// Ignore the received value and keep the current value of the server.
+    ///
+    /// If `correct` is true, the current value is sent back to the client so that it overwrites its own value.
+    Reject { correct: bool },
+}
+
+/// Function evaluated on the server before applying an update of a component received from a client.
+///
+/// `old` is the current value of the component on the server (None if the component is being inserted)
+/// and `new` is the value received from `client_id`.
+pub type ValidateFn<C> = fn(old: Option<&C>, new: &C, client_id: ClientId) -> Validation<C>;
+
+/// Version of the protocol.
+///
+/// When a client uses an older version of the protocol than the server, the components that changed
+/// in between are converted with the migrations registered with [`ComponentRegistration::add_migration`].
+pub type ProtocolVersion = u32;
+
+/// Function that converts a component to the representation `Old` used by older versions of the protocol
+pub type DowngradeFn<C, Old> = fn(component: &C) -> Old;
+
+/// Function that converts the representation `Old` used by older versions of the protocol to the component
+pub type UpgradeFn<C, Old> = fn(old: Old) -> C;
+
+fn serialize_downgraded<C, Old: BitSerializable>(
+    component: &C,
+    downgrade: unsafe fn(),
+    writer: &mut BitcodeWriter,
+) -> anyhow::Result<()> {
+    let downgrade: DowngradeFn<C, Old> = unsafe { std::mem::transmute(downgrade) };
+    downgrade(component).encode(writer)
+}
+
+fn deserialize_upgraded<C, Old: BitSerializable>(
+    reader: &mut BitcodeReader,
+    upgrade: unsafe fn(),
+) -> anyhow::Result<C> {
+    let upgrade: UpgradeFn<C, Old> = unsafe { std::mem::transmute(upgrade) };
+    Ok(upgrade(Old::decode(reader)?))
+}
+
+fn serialize_quantized<C: Clone + Into<Quat>>(
+    component: &C,
+    compression: QuatCompression,
+    writer: &mut BitcodeWriter,
+) -> anyhow::Result<()> {
+    compression.write(component.clone().into(), writer)
+}
+
+fn deserialize_quantized<C: From<Quat>>(
+    reader: &mut BitcodeReader,
+    compression: QuatCompression,
+) -> anyhow::Result<C> {
+    Ok(compression.read(reader)?.into())
+}
+
+fn quantize<C: Clone + Into<Quat> + From<Quat>>(component: &C, compression: QuatCompression) -> C {
+    compression.quantize(component.clone().into()).into()
+}
+
+fn serialize_delta<C: Diffable + 'static>(
+    component: &C,
+    base: &DeltaValue,
+    writer: &mut BitcodeWriter,
+) -> anyhow::Result<()> {
+    let base = base
+        .downcast_ref::<C>()
+        .context("the delta baseline has the wrong type")?;
+    base.diff(component).encode(writer)
+}
+
+fn delta_value<C: Diffable + Send + Sync + 'static>(component: &C) -> DeltaValue {
+    Box::new(component.clone())
+}
+
+/// Read a delta-compressed component, which is serialized as:
+/// - `false` followed by the full value, if the value cannot be used as a baseline (for example for inserts)
+/// - `true`, the tick at which the value was sent, and either the full value or the difference with a baseline.
+///   The value is then kept in the [`DeltaHistory`] of the entity, to be used as a baseline for the next updates.
+///
+/// Returns None if the baseline of the delta is not in the history of the entity.
+fn read_delta<C: Component + Diffable>(
+    registry: &ComponentRegistry,
+    reader: &mut BitcodeReader,
+    net_id: ComponentNetId,
+    entity_world_mut: &mut EntityWorldMut,
+    entity_map: &mut EntityMap,
+) -> anyhow::Result<Option<C>> {
+    if !reader.deserialize::<bool>()? {
+        return registry
+            .raw_deserialize::<C>(reader, net_id, None, entity_map)
+            .map(Some);
+    }
+    let tick = reader.decode::<Tick>(Fixed)?;
+    let component = if reader.deserialize::<bool>()? {
+        let base_tick = tick - reader.decode::<u8>(Fixed)? as u16;
+        let delta = C::Delta::decode(reader)?;
+        let Some(mut component) = entity_world_mut
+            .get_mut::<DeltaHistory<C>>()
+            .and_then(|mut history| history.base(base_tick).cloned())
+        else {
+            error!(
+                ?base_tick,
+                "received a delta for component {} but the baseline is missing",
+                std::any::type_name::<C>()
+            );
+            return Ok(None);
+        };
+        component.apply_diff(&delta);
+        component
+    } else {
+        registry.raw_deserialize::<C>(reader, net_id, None, entity_map)?
+    };
+    if let Some(mut history) = entity_world_mut.get_mut::<DeltaHistory<C>>() {
+        history.record(tick, component.clone());
+    } else {
+        let mut history = DeltaHistory::<C>::default();
+        history.record(tick, component.clone());
+        entity_world_mut.insert(history);
+    }
+    Ok(Some(component))
+}
+
 pub trait Linear {
     fn lerp(start: &Self, other: &Self, t: f32) -> Self;
 }
@@ -227,6 +483,11 @@ impl ComponentRegistry {
         self.kind_map.net_id(&ComponentKind::of::<C>()).is_some()
     }
 
+    /// Returns true if no component (static or dynamic) is registered
+    pub(crate) fn is_empty(&self) -> bool {
+        self.kind_map.is_empty() && self.dynamic_map.is_empty()
+    }
+
     /// Check that the protocol is correct:
     /// - emits warnings for every component that has prediction/interpolation metadata but wasn't registered
     pub fn check(&self) {
@@ -250,12 +511,17 @@ impl ComponentRegistry {
 
     pub(crate) fn register_component<C: Component + Message + PartialEq>(&mut self) {
         let component_kind = self.kind_map.add::<C>();
-        self.serialize_fns_map
-            .insert(component_kind, ErasedSerializeFns::new::<C>());
+        let mut erased_fns = ErasedSerializeFns::new::<C>();
+        erased_fns.canonical = self.canonical_encoding;
+        self.serialize_fns_map.insert(component_kind, erased_fns);
         let write: RawWriteFn = Self::write::<C>;
         let remove: RawRemoveFn = Self::remove::<C>;
         self.replication_map
             .insert(component_kind, ReplicationMetadata { write, remove });
+        let inspect: RawInspectFn = Self::inspect::<C>;
+        self.inspect_map.insert(component_kind, inspect);
+        let save: RawSaveFn = Self::save::<C>;
+        self.save_map.insert(component_kind, save);
     }
 
     pub(crate) fn try_add_map_entities<C: MapEntities + 'static>(&mut self) {
@@ -276,6 +542,261 @@ impl ComponentRegistry {
         erased_fns.add_map_entities::<C>();
     }
 
+    pub(crate) fn set_stable_id<C: Component>(&mut self, net_id: ComponentNetId) {
+        let kind = ComponentKind::of::<C>();
+        if self.kind_map.net_id(&kind).is_none() {
+            panic!(
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            );
+        }
+        self.kind_map.set_stable_id(kind, net_id);
+    }
+
+    /// Serialize all the components (including the ones that are registered later) with the canonical encoding
+    pub(crate) fn enable_canonical_encoding(&mut self) {
+        self.canonical_encoding = true;
+        for erased_fns in self.serialize_fns_map.values_mut() {
+            erased_fns.canonical = true;
+        }
+    }
+
+    pub(crate) fn add_versioned_fields<C: Component + Message>(&mut self) {
+        let kind = ComponentKind::of::<C>();
+        let erased_fns = self.serialize_fns_map.get_mut(&kind).unwrap_or_else(|| {
+            panic!(
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            )
+        });
+        erased_fns.add_versioned_fields::<C>();
+    }
+
+    pub(crate) fn set_replicate_if<C: Component>(&mut self, predicate: ReplicateIfFn<C>) {
+        let kind = ComponentKind::of::<C>();
+        if !self.replication_map.contains_key(&kind) {
+            panic!(
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            )
+        }
+        self.replicate_if_map.insert(kind, unsafe {
+            std::mem::transmute::<ReplicateIfFn<C>, unsafe fn()>(predicate)
+        });
+    }
+
+    pub(crate) fn set_validation<C: Component>(&mut self, validate: ValidateFn<C>) {
+        let kind = ComponentKind::of::<C>();
+        if !self.replication_map.contains_key(&kind) {
+            panic!(
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            )
+        }
+        self.validation_map.insert(kind, unsafe {
+            std::mem::transmute::<ValidateFn<C>, unsafe fn()>(validate)
+        });
+    }
+
+    pub(crate) fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        self.protocol_version = version;
+    }
+
+    /// Version of the protocol
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    pub(crate) fn add_migration<C: Component, Old: BitSerializable>(
+        &mut self,
+        version: ProtocolVersion,
+        downgrade: DowngradeFn<C, Old>,
+        upgrade: UpgradeFn<C, Old>,
+    ) {
+        let kind = ComponentKind::of::<C>();
+        if !self.replication_map.contains_key(&kind) {
+            panic!(
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            )
+        }
+        let serialize: MigrationSerializeFn<C> = serialize_downgraded::<C, Old>;
+        let deserialize: MigrationDeserializeFn<C> = deserialize_upgraded::<C, Old>;
+        let fns = unsafe {
+            [
+                std::mem::transmute::<MigrationSerializeFn<C>, unsafe fn()>(serialize),
+                std::mem::transmute::<DowngradeFn<C, Old>, unsafe fn()>(downgrade),
+                std::mem::transmute::<MigrationDeserializeFn<C>, unsafe fn()>(deserialize),
+                std::mem::transmute::<UpgradeFn<C, Old>, unsafe fn()>(upgrade),
+            ]
+        };
+        let migrations = self.migrations_map.entry(kind).or_default();
+        migrations.retain(|(v, _)| *v != version);
+        migrations.push((version, fns));
+        migrations.sort_by_key(|(v, _)| *v);
+    }
+
+    pub(crate) fn set_quantization<C: Component + Clone + Into<Quat> + From<Quat>>(
+        &mut self,
+        compression: QuatCompression,
+    ) {
+        let kind = ComponentKind::of::<C>();
+        let serialize: QuantizedSerializeFn<C> = serialize_quantized::<C>;
+        let deserialize: QuantizedDeserializeFn<C> = deserialize_quantized::<C>;
+        let quantize: QuantizeFn<C> = quantize::<C>;
+        let fns = unsafe {
+            [
+                std::mem::transmute::<QuantizedSerializeFn<C>, unsafe fn()>(serialize),
+                std::mem::transmute::<QuantizedDeserializeFn<C>, unsafe fn()>(deserialize),
+                std::mem::transmute::<QuantizeFn<C>, unsafe fn()>(quantize),
+            ]
+        };
+        self.quantization_map.insert(kind, (compression, fns));
+    }
+
+    pub(crate) fn set_delta_compression<C: Component + Diffable>(&mut self) {
+        let kind = ComponentKind::of::<C>();
+        let serialize_delta: DeltaSerializeFn<C> = serialize_delta::<C>;
+        let value: DeltaValueFn<C> = delta_value::<C>;
+        let read: DeltaReadFn<C> = read_delta::<C>;
+        let fns = unsafe {
+            [
+                std::mem::transmute::<DeltaSerializeFn<C>, unsafe fn()>(serialize_delta),
+                std::mem::transmute::<DeltaValueFn<C>, unsafe fn()>(value),
+                std::mem::transmute::<DeltaReadFn<C>, unsafe fn()>(read),
+            ]
+        };
+        self.delta_map.insert(kind, fns);
+    }
+
+    /// Returns true if the component `C` is sent as a difference with the last value acked by the remote
+    pub(crate) fn has_delta_compression<C: Component>(&self) -> bool {
+        self.delta_map.contains_key(&ComponentKind::of::<C>())
+    }
+
+    /// Copy of the component, to use it as a baseline for the next updates
+    pub(crate) fn delta_value<C: Component>(&self, component: &C) -> Option<DeltaValue> {
+        let delta = self.delta_map.get(&ComponentKind::of::<C>())?;
+        let value: DeltaValueFn<C> = unsafe { std::mem::transmute(delta[1]) };
+        Some(value(component))
+    }
+
+    /// Serialize an update of the component sent at `tick`, so that the remote can use it as a baseline
+    /// for the next updates.
+    ///
+    /// The update contains the difference with the `base` value that was sent at `base_tick`, if provided,
+    /// or the full value otherwise.
+    pub(crate) fn serialize_delta<C: Component>(
+        &self,
+        component: &C,
+        tick: Tick,
+        base: Option<(Tick, &DeltaValue)>,
+        writer: &mut BitcodeWriter,
+    ) -> anyhow::Result<RawData> {
+        let kind = ComponentKind::of::<C>();
+        let delta = self
+            .delta_map
+            .get(&kind)
+            .context("the component does not use delta compression")?;
+        let net_id = self.kind_map.net_id(&kind).unwrap();
+        writer.start_write();
+        writer.encode(net_id, Fixed)?;
+        writer.serialize(&true)?;
+        writer.encode(&tick, Fixed)?;
+        if let Some((base_tick, base)) = base {
+            let serialize_delta: DeltaSerializeFn<C> = unsafe { std::mem::transmute(delta[0]) };
+            writer.serialize(&true)?;
+            writer.encode(&((tick - base_tick) as u8), Fixed)?;
+            serialize_delta(component, base, writer)?;
+        } else {
+            writer.serialize(&false)?;
+            self.write_value(&kind, component, writer)?;
+        }
+        Ok(writer.finish_write().to_vec())
+    }
+
+    /// Returns true if some components have migrations to older versions of the protocol
+    pub(crate) fn has_migrations(&self) -> bool {
+        !self.migrations_map.is_empty()
+    }
+
+    /// Write a description of the registered components, that must be identical on the client and the server
+    /// (used to compute the [`registries_hash`](crate::protocol::registries_hash))
+    pub(crate) fn describe(&self, description: &mut String) {
+        let mut components = self.kind_map.id_map.iter().collect::<Vec<_>>();
+        components.sort_by_key(|(net_id, _)| **net_id);
+        for (net_id, kind) in components {
+            let fns = self.serialize_fns_map.get(kind);
+            let name = fns.map_or("unknown", |fns| fns.type_name);
+            description.push_str(&format!("component {net_id} {}", get_short_name(name)));
+            if fns.is_some_and(|fns| fns.versioned_fields) {
+                description.push_str(" versioned");
+            }
+            if self.delta_map.contains_key(kind) {
+                description.push_str(" delta");
+            }
+            if let Some((compression, _)) = self.quantization_map.get(kind) {
+                description.push_str(&format!(" quantized {compression:?}"));
+            }
+            description.push('\n');
+        }
+    }
+
+    /// Find the migration that converts the component to the representation used by the `remote_version`
+    /// of the protocol: it is the oldest migration that was introduced after the `remote_version`
+    fn migration(
+        &self,
+        kind: &ComponentKind,
+        remote_version: Option<ProtocolVersion>,
+    ) -> Option<&(ProtocolVersion, [unsafe fn(); 4])> {
+        let remote_version = remote_version?;
+        self.migrations_map
+            .get(kind)?
+            .iter()
+            .find(|(version, _)| *version > remote_version)
+    }
+
+    /// Version of the migration that is applied to the component `C` when talking to a peer that uses
+    /// the `remote_version` of the protocol, or None if the component is not migrated
+    pub(crate) fn migration_version<C: Component>(
+        &self,
+        remote_version: Option<ProtocolVersion>,
+    ) -> Option<ProtocolVersion> {
+        self.migration(&ComponentKind::of::<C>(), remote_version)
+            .map(|(version, _)| *version)
+    }
+
+    pub(crate) fn set_send_interval<C: Component>(&mut self, send_interval: Duration) {
+        let kind = ComponentKind::of::<C>();
+        if !self.replication_map.contains_key(&kind) {
+            panic!(
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            )
+        }
+        self.send_interval_map.insert(kind, send_interval);
+    }
+
+    /// Minimum interval between two updates of the component `C`, if it is sent less often than every send_interval
+    pub(crate) fn send_interval<C: Component>(&self) -> Option<Duration> {
+        self.send_interval_map
+            .get(&ComponentKind::of::<C>())
+            .copied()
+    }
+
+    /// Returns false if the [`ReplicateIfFn`] of the component rejects the update
+    pub(crate) fn should_replicate<C: Component>(
+        &self,
+        component: &C,
+        context: &ReplicationContext,
+    ) -> bool {
+        let kind = ComponentKind::of::<C>();
+        self.replicate_if_map.get(&kind).map_or(true, |predicate| {
+            let predicate: ReplicateIfFn<C> = unsafe { std::mem::transmute(*predicate) };
+            predicate(component, context)
+        })
+    }
+
     pub(crate) fn set_prediction_mode<C: SyncComponent>(&mut self, mode: ComponentSyncMode) {
         let kind = ComponentKind::of::<C>();
         let default_equality_fn = <C as PartialEq>::eq;
@@ -295,6 +816,18 @@ impl ComponentRegistry {
             .should_rollback = unsafe { std::mem::transmute(should_rollback) };
     }
 
+    pub(crate) fn set_misprediction_metric<C: Component + PartialEq>(
+        &mut self,
+        metric: MispredictionMetricFn<C>,
+    ) {
+        let kind = ComponentKind::of::<C>();
+        self.prediction_map
+            .entry(kind)
+            .or_insert_with(|| PredictionMetadata::default_from::<C>(ComponentSyncMode::Full))
+            .misprediction_metric =
+            Some(unsafe { std::mem::transmute::<MispredictionMetricFn<C>, unsafe fn()>(metric) });
+    }
+
     pub(crate) fn set_linear_correction<C: Component + Linear + PartialEq>(&mut self) {
         self.set_correction(<C as Linear>::lerp);
     }
@@ -307,6 +840,14 @@ impl ComponentRegistry {
             .correction = Some(unsafe { std::mem::transmute(correction_fn) });
     }
 
+    pub(crate) fn set_correction_ticks<C: Component + PartialEq>(&mut self, ticks: u16) {
+        let kind = ComponentKind::of::<C>();
+        self.prediction_map
+            .entry(kind)
+            .or_insert_with(|| PredictionMetadata::default_from::<C>(ComponentSyncMode::Full))
+            .correction_ticks = Some(ticks);
+    }
+
     pub(crate) fn set_interpolation_mode<C: Component>(&mut self, mode: ComponentSyncMode) {
         let kind = ComponentKind::of::<C>();
         self.interpolation_map
@@ -338,22 +879,74 @@ impl ComponentRegistry {
         writer: &mut BitcodeWriter,
     ) -> anyhow::Result<RawData> {
         let kind = ComponentKind::of::<C>();
+        let net_id = self
+            .kind_map
+            .net_id(&kind)
+            .context("the component is not part of the protocol")?;
+        writer.start_write();
+        writer.encode(net_id, Fixed)?;
+        if self.delta_map.contains_key(&kind) {
+            // the value cannot be used as a baseline for delta compression
+            writer.serialize(&false)?;
+        }
+        self.write_value(&kind, component, writer)?;
+        Ok(writer.finish_write().to_vec())
+    }
+
+    /// Serialize the value of the component, without the ComponentNetId
+    fn write_value<C: Component>(
+        &self,
+        kind: &ComponentKind,
+        component: &C,
+        writer: &mut BitcodeWriter,
+    ) -> anyhow::Result<()> {
+        if let Some((compression, fns)) = self.quantization_map.get(kind) {
+            let serialize: QuantizedSerializeFn<C> = unsafe { std::mem::transmute(fns[0]) };
+            return serialize(component, *compression, writer);
+        }
         let erased_fns = self
             .serialize_fns_map
-            .get(&kind)
+            .get(kind)
             .context("the component is not part of the protocol")?;
-        let net_id = self.kind_map.net_id(&kind).unwrap();
+        erased_fns.serialize(component, writer)
+    }
+
+    /// Serialize the component with the migration that was introduced in `migration_version`
+    /// (as returned by [`Self::migration_version`]), or without migration if it is None
+    pub(crate) fn serialize_migrated<C: Component>(
+        &self,
+        component: &C,
+        writer: &mut BitcodeWriter,
+        migration_version: Option<ProtocolVersion>,
+    ) -> anyhow::Result<RawData> {
+        let kind = ComponentKind::of::<C>();
+        let Some((_, fns)) = migration_version.and_then(|migration_version| {
+            self.migrations_map
+                .get(&kind)?
+                .iter()
+                .find(|(version, _)| *version == migration_version)
+        }) else {
+            return self.serialize(component, writer);
+        };
+        let net_id = self
+            .kind_map
+            .net_id(&kind)
+            .context("the component is not part of the protocol")?;
+        let serialize: MigrationSerializeFn<C> = unsafe { std::mem::transmute(fns[0]) };
         writer.start_write();
         writer.encode(net_id, Fixed)?;
-        erased_fns.serialize(component, writer)?;
+        serialize(component, fns[1], writer)?;
         Ok(writer.finish_write().to_vec())
     }
 
     /// Deserialize only the component value (the ComponentNetId has already been read)
+    ///
+    /// The component is upgraded if the remote uses an older version of the protocol.
     fn raw_deserialize<C: Component>(
         &self,
         reader: &mut BitcodeReader,
         net_id: ComponentNetId,
+        remote_version: Option<ProtocolVersion>,
         entity_map: &mut EntityMap,
     ) -> anyhow::Result<C> {
         let kind = self
@@ -364,7 +957,19 @@ impl ComponentRegistry {
             .serialize_fns_map
             .get(kind)
             .context("the component is not part of the protocol")?;
-        erased_fns.deserialize(reader, entity_map)
+        let Some((_, fns)) = self.migration(kind, remote_version) else {
+            let Some((compression, fns)) = self.quantization_map.get(kind) else {
+                return erased_fns.deserialize(reader, entity_map);
+            };
+            let deserialize: QuantizedDeserializeFn<C> = unsafe { std::mem::transmute(fns[1]) };
+            let mut component = deserialize(reader, *compression)?;
+            erased_fns.map_entities(&mut component, entity_map);
+            return Ok(component);
+        };
+        let deserialize: MigrationDeserializeFn<C> = unsafe { std::mem::transmute(fns[2]) };
+        let mut component = deserialize(reader, fns[3])?;
+        erased_fns.map_entities(&mut component, entity_map);
+        Ok(component)
     }
 
     pub(crate) fn deserialize<C: Component>(
@@ -373,7 +978,7 @@ impl ComponentRegistry {
         entity_map: &mut EntityMap,
     ) -> anyhow::Result<C> {
         let net_id = reader.decode::<ComponentNetId>(Fixed)?;
-        self.raw_deserialize(reader, net_id, entity_map)
+        self.raw_deserialize(reader, net_id, None, entity_map)
     }
 
     pub(crate) fn map_entities<C: 'static>(&self, component: &mut C, entity_map: &mut EntityMap) {
@@ -386,6 +991,16 @@ impl ComponentRegistry {
         erased_fns.map_entities(component, entity_map)
     }
 
+    pub(crate) fn set_map_entities_target<C: Component>(&mut self, target: MapEntitiesTarget) {
+        let kind = ComponentKind::of::<C>();
+        self.map_entities_target_map.insert(kind, target);
+    }
+
+    pub(crate) fn map_entities_target<C: Component>(&self) -> Option<MapEntitiesTarget> {
+        let kind = ComponentKind::of::<C>();
+        self.map_entities_target_map.get(&kind).copied()
+    }
+
     pub(crate) fn prediction_mode<C: Component>(&self) -> ComponentSyncMode {
         let kind = ComponentKind::of::<C>();
         self.prediction_map
@@ -404,6 +1019,13 @@ impl ComponentRegistry {
             })
     }
 
+    pub(crate) fn has_interpolation_fn<C: Component>(&self) -> bool {
+        let kind = ComponentKind::of::<C>();
+        self.interpolation_map
+            .get(&kind)
+            .is_some_and(|metadata| metadata.interpolation.is_some())
+    }
+
     pub(crate) fn has_correction<C: Component>(&self) -> bool {
         let kind = ComponentKind::of::<C>();
         self.prediction_map
@@ -412,7 +1034,19 @@ impl ComponentRegistry {
             .map_or(false, |metadata| metadata.correction.is_some())
     }
 
+    /// Number of ticks over which the correction of the component is applied, if it was
+    /// specified for this component
+    pub(crate) fn correction_ticks<C: Component>(&self) -> Option<u16> {
+        let kind = ComponentKind::of::<C>();
+        self.prediction_map
+            .get(&kind)
+            .and_then(|metadata| metadata.correction_ticks)
+    }
+
     /// Returns true if we should do a rollback
+    ///
+    /// If the component is quantized, the predicted value `this` is quantized before being compared
+    /// with the confirmed value `that`, which was received quantized.
     pub(crate) fn should_rollback<C: Component>(&self, this: &C, that: &C) -> bool {
         let kind = ComponentKind::of::<C>();
         let prediction_metadata = self
@@ -422,9 +1056,25 @@ impl ComponentRegistry {
             .unwrap();
         let should_rollback_fn: ShouldRollbackFn<C> =
             unsafe { std::mem::transmute(prediction_metadata.should_rollback) };
+        if let Some((compression, fns)) = self.quantization_map.get(&kind) {
+            let quantize: QuantizeFn<C> = unsafe { std::mem::transmute(fns[2]) };
+            return should_rollback_fn(&quantize(this, *compression), that);
+        }
         should_rollback_fn(this, that)
     }
 
+    /// Returns the magnitude of the misprediction, if a metric function was registered for the component
+    pub(crate) fn misprediction_magnitude<C: Component>(
+        &self,
+        predicted: &C,
+        confirmed: &C,
+    ) -> Option<f32> {
+        let kind = ComponentKind::of::<C>();
+        let metric = self.prediction_map.get(&kind)?.misprediction_metric?;
+        let metric_fn: MispredictionMetricFn<C> = unsafe { std::mem::transmute(metric) };
+        Some(metric_fn(predicted, confirmed))
+    }
+
     pub(crate) fn correct<C: Component>(&self, predicted: &C, corrected: &C, t: f32) -> C {
         let kind = ComponentKind::of::<C>();
         let prediction_metadata = self
@@ -453,11 +1103,15 @@ impl ComponentRegistry {
     pub(crate) fn raw_write(
         &self,
         reader: &mut BitcodeReader,
+        remote_version: Option<ProtocolVersion>,
         entity_world_mut: &mut EntityWorldMut,
         entity_map: &mut EntityMap,
         events: &mut ConnectionEvents,
     ) -> anyhow::Result<()> {
         let net_id = reader.decode::<ComponentNetId>(Fixed)?;
+        if let Some(dynamic) = self.remote_dynamic_map.get(&net_id) {
+            return Self::write_dynamic(dynamic, reader, net_id, entity_world_mut, events);
+        }
         let kind = self
             .kind_map
             .kind(net_id)
@@ -466,19 +1120,44 @@ impl ComponentRegistry {
             .replication_map
             .get(kind)
             .context("the component is not part of the protocol")?;
-        (replication_metadata.write)(self, reader, net_id, entity_world_mut, entity_map, events)
+        (replication_metadata.write)(
+            self,
+            reader,
+            net_id,
+            remote_version,
+            entity_world_mut,
+            entity_map,
+            events,
+        )
     }
 
     pub(crate) fn write<C: Component + PartialEq>(
         &self,
         reader: &mut BitcodeReader,
         net_id: ComponentNetId,
+        remote_version: Option<ProtocolVersion>,
         entity_world_mut: &mut EntityWorldMut,
         entity_map: &mut EntityMap,
         events: &mut ConnectionEvents,
     ) -> anyhow::Result<()> {
         trace!("Writing component {} to entity", std::any::type_name::<C>());
-        let component = self.raw_deserialize::<C>(reader, net_id, entity_map)?;
+        let kind = ComponentKind::of::<C>();
+        // migrated components are never delta-compressed
+        let component = match self.delta_map.get(&kind) {
+            Some(delta) if self.migration(&kind, remote_version).is_none() => {
+                let read: DeltaReadFn<C> = unsafe { std::mem::transmute(delta[2]) };
+                let Some(component) = read(self, reader, net_id, entity_world_mut, entity_map)?
+                else {
+                    return Ok(());
+                };
+                component
+            }
+            _ => self.raw_deserialize::<C>(reader, net_id, remote_version, entity_map)?,
+        };
+        let Some(component) = self.validate(entity_world_mut, component, remote_version, events)?
+        else {
+            return Ok(());
+        };
         let entity = entity_world_mut.id();
         // TODO: do we need the tick information in the event?
         let tick = Tick(0);
@@ -496,7 +1175,59 @@ impl ComponentRegistry {
         Ok(())
     }
 
+    /// Run the [`ValidateFn`] of the component if the value was received from a client.
+    ///
+    /// Both the inserts and the updates are validated, so that a client cannot bypass the validation by
+    /// removing the component and inserting it again.
+    ///
+    /// Returns the value that should be applied, if any. The values that must be sent back to the client are
+    /// added to the corrections of the `events`.
+    fn validate<C: Component>(
+        &self,
+        entity_world_mut: &EntityWorldMut,
+        component: C,
+        remote_version: Option<ProtocolVersion>,
+        events: &mut ConnectionEvents,
+    ) -> anyhow::Result<Option<C>> {
+        let Some(validate) = self.validation_map.get(&ComponentKind::of::<C>()) else {
+            return Ok(Some(component));
+        };
+        let Some(client_id) = entity_world_mut
+            .get::<Replicated>()
+            .and_then(|replicated| replicated.from)
+        else {
+            return Ok(Some(component));
+        };
+        let old = entity_world_mut.get::<C>();
+        let validate: ValidateFn<C> = unsafe { std::mem::transmute(*validate) };
+        let (applied, correct) = match validate(old, &component, client_id) {
+            Validation::Accept => return Ok(Some(component)),
+            Validation::Clamp { value, correct } => (Some(value), correct),
+            Validation::Reject { correct } => (None, correct),
+        };
+        trace!(
+            ?client_id,
+            component = std::any::type_name::<C>(),
+            "the received component value did not pass the validation"
+        );
+        // a rejected insert cannot be corrected since the server has no value to send back
+        if let Some(value) = applied.as_ref().or(old).filter(|_| correct) {
+            // the client might use an older version of the protocol
+            let mut writer = BitcodeWriter::with_capacity(64);
+            let correction = self.serialize_migrated(
+                value,
+                &mut writer,
+                self.migration_version::<C>(remote_version),
+            )?;
+            events.push_component_correction(entity_world_mut.id(), correction);
+        }
+        Ok(applied)
+    }
+
     pub(crate) fn raw_remove(&self, net_id: ComponentNetId, entity_world_mut: &mut EntityWorldMut) {
+        if let Some(dynamic) = self.remote_dynamic_map.get(&net_id) {
+            return Self::remove_dynamic(dynamic, entity_world_mut);
+        }
         let kind = self.kind_map.kind(net_id).expect("unknown component kind");
         let replication_metadata = self
             .replication_map
@@ -508,16 +1239,272 @@ impl ComponentRegistry {
     pub(crate) fn remove<C: Component>(&self, entity_world_mut: &mut EntityWorldMut) {
         entity_world_mut.remove::<C>();
     }
+
+    /// Serialize the value of the component of kind `kind` of the entity, without the ComponentNetId.
+    ///
+    /// Returns None if the component is not part of the protocol.
+    pub(crate) fn raw_inspect(
+        &self,
+        kind: &ComponentKind,
+        entity: EntityRef,
+        writer: &mut BitcodeWriter,
+    ) -> Option<anyhow::Result<(ComponentNetId, RawData)>> {
+        let net_id = *self.kind_map.net_id(kind)?;
+        let inspect = self.inspect_map.get(kind)?;
+        Some(inspect(self, entity, writer).map(|data| (net_id, data)))
+    }
+
+    fn inspect<C: Component>(
+        &self,
+        entity: EntityRef,
+        writer: &mut BitcodeWriter,
+    ) -> anyhow::Result<RawData> {
+        let component = entity
+            .get::<C>()
+            .context("the entity does not have the component")?;
+        writer.start_write();
+        self.write_value(&ComponentKind::of::<C>(), component, writer)?;
+        Ok(writer.finish_write().to_vec())
+    }
+
+    /// Serialize all the components of the entity that are part of the protocol, in the same format
+    /// as the replication messages (so that they can be written back with [`Self::raw_write`])
+    pub(crate) fn save_entity(
+        &self,
+        entity: EntityRef,
+        writer: &mut BitcodeWriter,
+    ) -> anyhow::Result<Vec<RawData>> {
+        self.save_map
+            .values()
+            .filter_map(|save| save(self, entity, writer))
+            .collect()
+    }
+
+    fn save<C: Component>(
+        &self,
+        entity: EntityRef,
+        writer: &mut BitcodeWriter,
+    ) -> Option<anyhow::Result<RawData>> {
+        entity
+            .get::<C>()
+            .map(|component| self.serialize(component, writer))
+    }
+
+    /// Network id assigned locally to the dynamic component with this [`TypePath`], if it is registered.
+    ///
+    /// On the server, this is the network id used to replicate the component. The clients use the network
+    /// ids that the server sent them instead.
+    pub fn dynamic_net_id(&self, type_path: &str) -> Option<ComponentNetId> {
+        self.dynamic_map
+            .iter()
+            .find(|(_, dynamic)| dynamic.type_path == type_path)
+            .map(|(net_id, _)| *net_id)
+    }
+
+    /// Register a component that is not part of the protocol by its [`TypePath`], for example a component
+    /// provided by a mod. The component is serialized with reflection, so it must be registered in the
+    /// `type_registry` with [`ReflectComponent`].
+    ///
+    /// This can be called while the app is running; the server only replicates a dynamic component to the
+    /// clients that registered it too.
+    ///
+    /// The network ids are assigned in the order of registration, so they are different on each peer: the
+    /// server sends the network ids that it assigned to the clients, which only use these.
+    ///
+    /// The dynamic components are only replicated from the server to the clients, and do not support the
+    /// options of the components of the protocol (prediction, interpolation, entity mapping, delta compression, etc.)
+    pub fn register_dynamic_component(
+        &mut self,
+        type_path: &str,
+        type_registry: &TypeRegistry,
+    ) -> anyhow::Result<ComponentNetId> {
+        let registration = type_registry
+            .get_with_type_path(type_path)
+            .with_context(|| format!("{type_path} is not registered in the type registry"))?;
+        if registration.data::<ReflectComponent>().is_none() {
+            bail!("{type_path} does not reflect Component");
+        }
+        let type_id = registration.type_id();
+        if self
+            .kind_map
+            .net_id(&ComponentKind::from(type_id))
+            .is_some()
+        {
+            bail!("{type_path} is already part of the protocol");
+        }
+        if let Some(net_id) = self.dynamic_net_id(type_path) {
+            return Ok(net_id);
+        }
+        let net_id = ComponentNetId::try_from(self.dynamic_map.len())
+            .ok()
+            .and_then(|index| DYNAMIC_NET_ID_START.checked_add(index))
+            .context("too many dynamic components are registered")?;
+        if self.kind_map.kind(net_id).is_some() {
+            bail!("the network id of {type_path} is already used by a component of the protocol");
+        }
+        debug!(?net_id, "register dynamic component {type_path}");
+        self.dynamic_map.insert(
+            net_id,
+            DynamicComponent {
+                type_path: type_path.to_string(),
+                type_id,
+            },
+        );
+        Ok(net_id)
+    }
+
+    /// Returns true if the dynamic component with this [`TypePath`] was registered
+    pub fn is_dynamic_component_registered(&self, type_path: &str) -> bool {
+        self.dynamic_net_id(type_path).is_some()
+    }
+
+    /// Use the network ids that the server assigned to the dynamic components.
+    ///
+    /// Returns the network ids of the components that are registered locally too, which can now be received.
+    pub(crate) fn set_remote_dynamic_net_ids(
+        &mut self,
+        net_ids: &[(String, ComponentNetId)],
+    ) -> Vec<ComponentNetId> {
+        let mut bound = vec![];
+        for (type_path, net_id) in net_ids {
+            let Some(dynamic) = self
+                .dynamic_map
+                .values()
+                .find(|dynamic| &dynamic.type_path == type_path)
+            else {
+                debug!(
+                    "the server assigned a network id to the unknown dynamic component {type_path}"
+                );
+                continue;
+            };
+            if *net_id < DYNAMIC_NET_ID_START {
+                debug!(
+                    ?net_id,
+                    "invalid network id for the dynamic component {type_path}"
+                );
+                continue;
+            }
+            self.remote_dynamic_map.insert(*net_id, dynamic.clone());
+            bound.push(*net_id);
+        }
+        bound
+    }
+
+    /// Forget the network ids that the server assigned to the dynamic components (for example when the client
+    /// connects to another server)
+    pub(crate) fn clear_remote_dynamic_net_ids(&mut self) {
+        self.remote_dynamic_map.clear();
+    }
+
+    pub(crate) fn dynamic_components(
+        &self,
+    ) -> impl Iterator<Item = (ComponentNetId, &DynamicComponent)> {
+        self.dynamic_map
+            .iter()
+            .map(|(net_id, dynamic)| (*net_id, dynamic))
+    }
+
+    /// Serialize the value of a dynamic component, in the same format as the components of the protocol
+    pub(crate) fn serialize_dynamic(
+        &self,
+        net_id: ComponentNetId,
+        component: &dyn Reflect,
+        type_registry: &TypeRegistry,
+        writer: &mut BitcodeWriter,
+    ) -> anyhow::Result<RawData> {
+        let value = bitcode::serialize(&TypedReflectSerializer::new(component, type_registry))
+            .context("could not serialize the dynamic component")?;
+        writer.start_write();
+        writer.encode(&net_id, Fixed)?;
+        writer.encode(&value, Fixed)?;
+        Ok(writer.finish_write().to_vec())
+    }
+
+    /// Insert or update the dynamic component read from the `reader` (the ComponentNetId has already been read)
+    fn write_dynamic(
+        dynamic: &DynamicComponent,
+        reader: &mut BitcodeReader,
+        net_id: ComponentNetId,
+        entity_world_mut: &mut EntityWorldMut,
+        events: &mut ConnectionEvents,
+    ) -> anyhow::Result<()> {
+        let value = reader.decode::<Vec<u8>>(Fixed)?;
+        let type_registry = entity_world_mut
+            .world()
+            .get_resource::<AppTypeRegistry>()
+            .context("the dynamic components need the AppTypeRegistry")?
+            .clone();
+        let type_registry = type_registry.read();
+        let registration = type_registry
+            .get(dynamic.type_id)
+            .context("the dynamic component is not in the type registry")?;
+        let reflect_component = registration
+            .data::<ReflectComponent>()
+            .context("the dynamic component does not reflect Component")?;
+        let component = bitcode::serde::deserialize_seed(
+            TypedReflectDeserializer::new(registration, &type_registry),
+            &value,
+        )
+        .with_context(|| format!("could not deserialize {}", dynamic.type_path))?;
+        let entity = entity_world_mut.id();
+        let tick = Tick(0);
+        match reflect_component.reflect(EntityRef::from(&*entity_world_mut)) {
+            Some(existing) => {
+                // only apply the update if the component is different, to not trigger change detection
+                if existing.reflect_partial_eq(component.as_ref()) != Some(true) {
+                    events.push_update_component(entity, net_id, tick);
+                    reflect_component.apply(entity_world_mut, component.as_ref());
+                }
+            }
+            None => {
+                events.push_insert_component(entity, net_id, tick);
+                reflect_component.insert(entity_world_mut, component.as_ref(), &type_registry);
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_dynamic(dynamic: &DynamicComponent, entity_world_mut: &mut EntityWorldMut) {
+        let Some(type_registry) = entity_world_mut
+            .world()
+            .get_resource::<AppTypeRegistry>()
+            .cloned()
+        else {
+            return;
+        };
+        let type_registry = type_registry.read();
+        if let Some(reflect_component) =
+            type_registry.get_type_data::<ReflectComponent>(dynamic.type_id)
+        {
+            reflect_component.remove(entity_world_mut);
+        }
+    }
+
+    /// Deserialize a component value that was serialized with [`Self::raw_inspect`].
+    ///
+    /// The entities referenced by the component are not mapped.
+    pub(crate) fn deserialize_inspected<C: Component>(
+        &self,
+        net_id: ComponentNetId,
+        data: &[u8],
+    ) -> anyhow::Result<C> {
+        let mut reader = BitcodeReader::start_read(data);
+        self.raw_deserialize(&mut reader, net_id, None, &mut EntityMap::default())
+    }
 }
 
 fn register_component_send<C: Component>(app: &mut App, direction: ChannelDirection) {
+    #[cfg(feature = "client")]
     let is_client = app.world.get_resource::<ClientConfig>().is_some();
+    #[cfg(feature = "server")]
     let is_server = app.world.get_resource::<ServerConfig>().is_some();
     match direction {
         ChannelDirection::ClientToServer => {
+            #[cfg(feature = "client")]
             if is_client {
                 crate::client::replication::send::register_replicate_component_send::<C>(app);
             }
+            #[cfg(feature = "server")]
             if is_server {
                 debug!(
                     "register send events on server for {}",
@@ -527,9 +1514,11 @@ fn register_component_send<C: Component>(app: &mut App, direction: ChannelDirect
             }
         }
         ChannelDirection::ServerToClient => {
+            #[cfg(feature = "server")]
             if is_server {
                 crate::server::replication::send::register_replicate_component_send::<C>(app);
             }
+            #[cfg(feature = "client")]
             if is_client {
                 debug!(
                     "register send events on client for {}",
@@ -564,12 +1553,56 @@ pub trait AppComponentExt {
     /// Add a `Correction` behaviour to this component.
     fn add_correction_fn<C: SyncComponent>(&mut self, correction_fn: LerpFn<C>);
 
+    /// Set the number of ticks over which the `Correction` of this component is applied.
+    ///
+    /// (By default the number of ticks depends on the number of ticks that were rolled back,
+    /// see [`PredictionConfig::correction_ticks_factor`](crate::client::prediction::plugin::PredictionConfig))
+    fn add_correction_ticks<C: SyncComponent>(&mut self, ticks: u16);
+
     /// Add a custom function to use for checking if a rollback is needed.
     ///
     /// (By default we use the PartialEq::ne function, but you can use this to override the
     ///  equality check. For example, you might want to add a threshold for floating point numbers)
     fn add_should_rollback_fn<C: SyncComponent>(&mut self, should_rollback: ShouldRollbackFn<C>);
 
+    /// Add a function that measures the magnitude of the mispredictions of this component.
+    ///
+    /// The average and maximum magnitudes are then available in the
+    /// [`PredictionStats`](crate::client::prediction::diagnostics::PredictionStats)
+    fn add_misprediction_metric_fn<C: SyncComponent>(&mut self, metric: MispredictionMetricFn<C>);
+
+    /// Add a predicate that is evaluated on the server before sending an update for this component.
+    ///
+    /// The update is only sent if the predicate returns true. The insertion of the component is
+    /// always replicated.
+    fn add_replicate_if_fn<C: Component>(&mut self, predicate: ReplicateIfFn<C>);
+
+    /// Add a validation that is evaluated on the server before applying an update of this component
+    /// received from a client.
+    fn add_validation_fn<C: Component>(&mut self, validate: ValidateFn<C>);
+
+    /// Set the version of the protocol. The client announces it to the server when it connects.
+    fn set_protocol_version(&mut self, version: ProtocolVersion);
+
+    /// Add a migration of the component `C` for the clients that use a protocol version lower than `version`.
+    ///
+    /// `Old` is the representation of the component used by these clients.
+    fn add_component_migration<C: Component, Old: BitSerializable>(
+        &mut self,
+        version: ProtocolVersion,
+        downgrade: DowngradeFn<C, Old>,
+        upgrade: UpgradeFn<C, Old>,
+    );
+
+    /// Send the updates of the component `C` as a difference with the last value acked by each client
+    fn add_delta_compression<C: Component + Diffable>(&mut self);
+
+    /// Serialize the rotation `C` with the given [`QuatCompression`] instead of its `Serialize` implementation
+    fn add_quantization<C: Component + Clone + Into<Quat> + From<Quat>>(
+        &mut self,
+        compression: QuatCompression,
+    );
+
     /// Register helper systems to perform interpolation for the component; but the user has to define the interpolation logic
     /// themselves (the interpolation_fn will not be used)
     fn add_custom_interpolation<C: SyncComponent>(&mut self, interpolation_mode: ComponentSyncMode);
@@ -602,6 +1635,20 @@ impl<C> ComponentRegistration<'_, C> {
         self
     }
 
+    /// Choose which local entities the entities referenced by this component resolve to, when the component
+    /// is synced from the Confirmed entity to the Predicted or Interpolated entity.
+    ///
+    /// By default, they resolve to the Predicted entities for predicted components and to the Interpolated entities
+    /// for interpolated components.
+    pub fn with_map_entities_target(self, target: MapEntitiesTarget) -> Self
+    where
+        C: Component,
+    {
+        let mut registry = self.app.world.resource_mut::<ComponentRegistry>();
+        registry.set_map_entities_target::<C>(target);
+        self
+    }
+
     /// Enable prediction systems for this component.
     /// You can specify the prediction [`ComponentSyncMode`]
     pub fn add_prediction(self, prediction_mode: ComponentSyncMode) -> Self
@@ -630,6 +1677,41 @@ impl<C> ComponentRegistration<'_, C> {
         self
     }
 
+    /// Set the number of ticks over which the `Correction` of this component is applied.
+    ///
+    /// During a correction, the simulation immediately uses the corrected value, but the visual value
+    /// is smoothly interpolated from the previous prediction to the corrected value over these ticks.
+    /// For example, a large correction ticks for the position avoids visual teleports:
+    /// ```rust
+    /// # use std::ops::{Add, Mul};
+    /// # use bevy::prelude::*;
+    /// # use lightyear::prelude::*;
+    /// # use lightyear::prelude::client::*;
+    /// # #[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
+    /// # struct Position(Vec2);
+    /// # impl Add for Position {
+    /// #     type Output = Position;
+    /// #     fn add(self, rhs: Position) -> Position { Position(self.0 + rhs.0) }
+    /// # }
+    /// # impl Mul<f32> for &Position {
+    /// #     type Output = Position;
+    /// #     fn mul(self, rhs: f32) -> Position { Position(self.0 * rhs) }
+    /// # }
+    /// # fn add_components(app: &mut App) {
+    /// app.register_component::<Position>(ChannelDirection::ServerToClient)
+    ///     .add_prediction(ComponentSyncMode::Full)
+    ///     .add_linear_correction_fn()
+    ///     .add_correction_ticks(10);
+    /// # }
+    /// ```
+    pub fn add_correction_ticks(self, ticks: u16) -> Self
+    where
+        C: SyncComponent,
+    {
+        self.app.add_correction_ticks::<C>(ticks);
+        self
+    }
+
     /// Add a custom function to use for checking if a rollback is needed.
     ///
     /// (By default we use the PartialEq::ne function, but you can use this to override the
@@ -642,6 +1724,225 @@ impl<C> ComponentRegistration<'_, C> {
         self
     }
 
+    /// Add a function that measures the magnitude of the mispredictions of this component.
+    ///
+    /// The average and maximum magnitudes are then available in the
+    /// [`PredictionStats`](crate::client::prediction::diagnostics::PredictionStats)
+    pub fn add_misprediction_metric(self, metric: MispredictionMetricFn<C>) -> Self
+    where
+        C: SyncComponent,
+    {
+        self.app.add_misprediction_metric_fn::<C>(metric);
+        self
+    }
+
+    /// Add a predicate that is evaluated on the server before sending an update for this component.
+    ///
+    /// The update is only sent if the predicate returns true. The insertion of the component is
+    /// always replicated.
+    /// For example, to only replicate the velocity when it is above a threshold:
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use lightyear::prelude::*;
+    /// # #[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
+    /// # struct Velocity(Vec2);
+    /// # fn add_components(app: &mut App) {
+    /// app.register_component::<Velocity>(ChannelDirection::ServerToClient)
+    ///     .add_replicate_if(|velocity, _| velocity.0.length() > 0.1);
+    /// # }
+    /// ```
+    /// When the predicate starts returning false, the value that made it fail is still replicated (until the
+    /// component changes again), so that the client doesn't keep a stale value; the following updates are not
+    /// replicated until the predicate returns true again.
+    pub fn add_replicate_if(self, predicate: ReplicateIfFn<C>) -> Self
+    where
+        C: Component,
+    {
+        self.app.add_replicate_if_fn::<C>(predicate);
+        self
+    }
+
+    /// Add a validation that is evaluated on the server before applying an update of this component
+    /// received from a client (for components replicated with [`ChannelDirection::ClientToServer`]).
+    ///
+    /// The [`Validation`] can accept the received value, replace it with another value, or reject it.
+    /// The server can also send the value that it kept back to the client, which overwrites its own value.
+    /// For example, to prevent the clients from moving too fast:
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use lightyear::prelude::*;
+    /// # #[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
+    /// # struct Position(Vec2);
+    /// # const MAX_DISTANCE: f32 = 10.0;
+    /// # fn add_components(app: &mut App) {
+    /// app.register_component::<Position>(ChannelDirection::ClientToServer)
+    ///     .add_validation(|old, new, _client_id| match old {
+    ///         Some(old) if old.0.distance(new.0) > MAX_DISTANCE => {
+    ///             Validation::Reject { correct: true }
+    ///         }
+    ///         _ => Validation::Accept,
+    ///     });
+    /// # }
+    /// ```
+    /// Both the inserts and the updates of the component are validated: `old` is None when the component
+    /// is inserted (including when the client removes the component and inserts it again).
+    pub fn add_validation(self, validate: ValidateFn<C>) -> Self
+    where
+        C: Component,
+    {
+        self.app.add_validation_fn::<C>(validate);
+        self
+    }
+
+    /// Add a migration for the clients that use a protocol version lower than `version`, which
+    /// is the version in which the component changed.
+    ///
+    /// The server converts the component to the representation `Old` before sending it to these clients,
+    /// and converts it back when receiving it from them. The clients announce their protocol version
+    /// (set with [`set_protocol_version`](AppComponentExt::set_protocol_version)) when they connect.
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use lightyear::prelude::*;
+    /// # #[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
+    /// # struct Health(f32);
+    /// // in version 2, the health became a float
+    /// #[derive(Serialize, Deserialize)]
+    /// struct HealthV1(u32);
+    ///
+    /// # fn add_components(app: &mut App) {
+    /// app.set_protocol_version(2);
+    /// app.register_component::<Health>(ChannelDirection::ServerToClient)
+    ///     .add_migration::<HealthV1>(2, |health| HealthV1(health.0 as u32), |old| Health(old.0 as f32));
+    /// # }
+    /// ```
+    /// If the component changes several times, register one migration per version: the client uses
+    /// the oldest migration that was introduced after its own version.
+    pub fn add_migration<Old: BitSerializable>(
+        self,
+        version: ProtocolVersion,
+        downgrade: DowngradeFn<C, Old>,
+        upgrade: UpgradeFn<C, Old>,
+    ) -> Self
+    where
+        C: Component,
+    {
+        self.app
+            .add_component_migration::<C, Old>(version, downgrade, upgrade);
+        self
+    }
+
+    /// Send the updates of this component at most once every `send_interval`, instead of at every
+    /// [`server_send_interval`](crate::prelude::SharedConfig::server_send_interval).
+    ///
+    /// This is useful for components that don't need to be updated as often as the others:
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use bevy::utils::Duration;
+    /// # use lightyear::prelude::*;
+    /// # #[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
+    /// # struct Health(f32);
+    /// # fn add_components(app: &mut App) {
+    /// // 5 updates per second
+    /// app.register_component::<Health>(ChannelDirection::ServerToClient)
+    ///     .with_send_interval(Duration::from_millis(200));
+    /// # }
+    /// ```
+    /// The updates are still buffered with the other updates of the entity's replication group, so they are sent
+    /// in the same packets. The insertions of the component are always sent immediately.
+    pub fn with_send_interval(self, send_interval: Duration) -> Self
+    where
+        C: Component,
+    {
+        let mut registry = self.app.world.resource_mut::<ComponentRegistry>();
+        registry.set_send_interval::<C>(send_interval);
+        self
+    }
+
+    /// Use the network id `net_id` for this component, instead of the one assigned from the registration order.
+    ///
+    /// The id stays the same when other components are added or removed from the protocol.
+    /// If another component was assigned this id automatically, it gets a new one.
+    ///
+    /// # Panics
+    ///
+    /// If the id was already chosen for another component.
+    pub fn with_stable_id(self, net_id: ComponentNetId) -> Self
+    where
+        C: Component,
+    {
+        let mut registry = self.app.world.resource_mut::<ComponentRegistry>();
+        registry.set_stable_id::<C>(net_id);
+        self
+    }
+
+    /// Serialize this component with the [`versioned`](crate::serialize::versioned) fields encoding,
+    /// so that peers built with an older or newer definition of the component can still read it
+    /// (the unknown fields are skipped and the missing fields are defaulted).
+    ///
+    /// The encoding must be enabled on both the client and the server.
+    pub fn add_versioned_fields(self) -> Self
+    where
+        C: Component + Message,
+    {
+        let mut registry = self.app.world.resource_mut::<ComponentRegistry>();
+        registry.add_versioned_fields::<C>();
+        self
+    }
+
+    /// Send the updates of this component as a difference with the last value acked by each client,
+    /// instead of the full value. See [`Diffable`] and the [`delta`](crate::shared::replication::delta) module.
+    ///
+    /// The delta compression must be enabled on both the client and the server.
+    pub fn add_delta_compression(self) -> Self
+    where
+        C: Component + Diffable,
+    {
+        self.app.add_delta_compression::<C>();
+        self
+    }
+
+    /// Serialize this rotation with the given [`QuatCompression`] instead of its `Serialize` implementation.
+    ///
+    /// The component is converted to a [`Quat`], compressed, and converted back when it is received:
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use lightyear::prelude::*;
+    /// # use lightyear::prelude::client::*;
+    /// #[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+    /// struct Rotation(Quat);
+    ///
+    /// impl From<Rotation> for Quat {
+    ///     fn from(rotation: Rotation) -> Quat {
+    ///         rotation.0
+    ///     }
+    /// }
+    ///
+    /// impl From<Quat> for Rotation {
+    ///     fn from(quat: Quat) -> Rotation {
+    ///         Rotation(quat)
+    ///     }
+    /// }
+    ///
+    /// # fn add_components(app: &mut App) {
+    /// // 29 bits per update instead of 128
+    /// app.register_component::<Rotation>(ChannelDirection::ServerToClient)
+    ///     .add_prediction(ComponentSyncMode::Full)
+    ///     .add_quantization(QuatCompression::SmallestThree(9))
+    ///     .add_interpolation(ComponentSyncMode::Full)
+    ///     .add_interpolation_fn(|start, other, t| Rotation(start.0.slerp(other.0, t)));
+    /// # }
+    /// ```
+    /// The rollback check compares the confirmed value with the quantized predicted value, so that the
+    /// precision loss doesn't cause rollbacks. The rotations received are normalized, but they should be
+    /// interpolated with a spherical interpolation (`slerp`) rather than a linear interpolation.
+    pub fn add_quantization(self, compression: QuatCompression) -> Self
+    where
+        C: Component + Clone + Into<Quat> + From<Quat>,
+    {
+        self.app.add_quantization::<C>(compression);
+        self
+    }
+
     /// Enable interpolation systems for this component.
     /// You can specify the interpolation [`ComponentSyncMode`]
     pub fn add_interpolation(self, interpolation_mode: ComponentSyncMode) -> Self
@@ -703,8 +2004,8 @@ impl AppComponentExt for App {
         registry.set_prediction_mode::<C>(prediction_mode);
 
         // TODO: make prediction/interpolation possible on server?
-        let is_client = self.world.get_resource::<ClientConfig>().is_some();
-        if is_client {
+        #[cfg(feature = "client")]
+        if self.world.get_resource::<ClientConfig>().is_some() {
             add_prediction_systems::<C>(self, prediction_mode);
         }
     }
@@ -720,11 +2021,59 @@ impl AppComponentExt for App {
         registry.set_correction::<C>(correction_fn);
     }
 
+    fn add_correction_ticks<C: SyncComponent>(&mut self, ticks: u16) {
+        let mut registry = self.world.resource_mut::<ComponentRegistry>();
+        registry.set_correction_ticks::<C>(ticks);
+    }
+
     fn add_should_rollback_fn<C: SyncComponent>(&mut self, rollback_check: ShouldRollbackFn<C>) {
         let mut registry = self.world.resource_mut::<ComponentRegistry>();
         registry.set_should_rollback::<C>(rollback_check);
     }
 
+    fn add_misprediction_metric_fn<C: SyncComponent>(&mut self, metric: MispredictionMetricFn<C>) {
+        let mut registry = self.world.resource_mut::<ComponentRegistry>();
+        registry.set_misprediction_metric::<C>(metric);
+    }
+
+    fn add_replicate_if_fn<C: Component>(&mut self, predicate: ReplicateIfFn<C>) {
+        let mut registry = self.world.resource_mut::<ComponentRegistry>();
+        registry.set_replicate_if::<C>(predicate);
+    }
+
+    fn add_validation_fn<C: Component>(&mut self, validate: ValidateFn<C>) {
+        let mut registry = self.world.resource_mut::<ComponentRegistry>();
+        registry.set_validation::<C>(validate);
+    }
+
+    fn set_protocol_version(&mut self, version: ProtocolVersion) {
+        let mut registry = self.world.resource_mut::<ComponentRegistry>();
+        registry.set_protocol_version(version);
+    }
+
+    fn add_component_migration<C: Component, Old: BitSerializable>(
+        &mut self,
+        version: ProtocolVersion,
+        downgrade: DowngradeFn<C, Old>,
+        upgrade: UpgradeFn<C, Old>,
+    ) {
+        let mut registry = self.world.resource_mut::<ComponentRegistry>();
+        registry.add_migration::<C, Old>(version, downgrade, upgrade);
+    }
+
+    fn add_delta_compression<C: Component + Diffable>(&mut self) {
+        let mut registry = self.world.resource_mut::<ComponentRegistry>();
+        registry.set_delta_compression::<C>();
+    }
+
+    fn add_quantization<C: Component + Clone + Into<Quat> + From<Quat>>(
+        &mut self,
+        compression: QuatCompression,
+    ) {
+        let mut registry = self.world.resource_mut::<ComponentRegistry>();
+        registry.set_quantization::<C>(compression);
+    }
+
     fn add_custom_interpolation<C: SyncComponent>(
         &mut self,
         interpolation_mode: ComponentSyncMode,
@@ -732,8 +2081,8 @@ impl AppComponentExt for App {
         let mut registry = self.world.resource_mut::<ComponentRegistry>();
         registry.set_interpolation_mode::<C>(interpolation_mode);
         // TODO: make prediction/interpolation possible on server?
-        let is_client = self.world.get_resource::<ClientConfig>().is_some();
-        if is_client {
+        #[cfg(feature = "client")]
+        if self.world.get_resource::<ClientConfig>().is_some() {
             add_prepare_interpolation_systems::<C>(self, interpolation_mode);
         }
     }
@@ -742,12 +2091,13 @@ impl AppComponentExt for App {
         let mut registry = self.world.resource_mut::<ComponentRegistry>();
         registry.set_interpolation_mode::<C>(interpolation_mode);
         // TODO: make prediction/interpolation possible on server?
-        let is_client = self.world.get_resource::<ClientConfig>().is_some();
-        if is_client {
+        #[cfg(feature = "client")]
+        if self.world.get_resource::<ClientConfig>().is_some() {
             add_prepare_interpolation_systems::<C>(self, interpolation_mode);
             if interpolation_mode == ComponentSyncMode::Full {
                 // TODO: handle custom interpolation
                 add_interpolation_systems::<C>(self);
+                add_sync_transition_systems::<C>(self);
             }
         }
     }
@@ -763,6 +2113,57 @@ impl AppComponentExt for App {
     }
 }
 
+/// Extension trait to register a dynamic component (see [`ComponentRegistry::register_dynamic_component`])
+/// while the app is running, for example when a mod is loaded, via [`Commands`].
+///
+/// The component must be registered in the [`AppTypeRegistry`] with [`ReflectComponent`] beforehand.
+/// The clients announce their dynamic components to the server, which only replicates a dynamic component
+/// to the clients that registered it too, with the network id assigned by the server.
+pub trait RegisterDynamicComponentExt {
+    fn register_dynamic_component(&mut self, type_path: impl Into<String>);
+}
+
+impl RegisterDynamicComponentExt for Commands<'_, '_> {
+    fn register_dynamic_component(&mut self, type_path: impl Into<String>) {
+        let type_path = type_path.into();
+        self.add(move |world: &mut World| {
+            let type_registry = world.resource::<AppTypeRegistry>().clone();
+            let result = world
+                .resource_mut::<ComponentRegistry>()
+                .register_dynamic_component(&type_path, &type_registry.read());
+            match result {
+                Ok(_) => {
+                    #[cfg(feature = "client")]
+                    announce_dynamic_component(world, &type_path);
+                }
+                Err(e) => {
+                    error!("could not register the dynamic component {type_path}: {e:?}");
+                }
+            }
+        });
+    }
+}
+
+/// Announce a new dynamic component to the server if we are a client that is already connected
+#[cfg(feature = "client")]
+fn announce_dynamic_component(world: &mut World, type_path: &str) {
+    #[cfg(feature = "server")]
+    if world.get_resource::<ServerConfig>().is_some() {
+        return;
+    }
+    if world.contains_resource::<client::ConnectionManager>() {
+        world.resource_scope(
+            |world, mut connection_manager: Mut<client::ConnectionManager>| {
+                if let Err(e) = connection_manager
+                    .send_dynamic_components(world.resource::<ComponentRegistry>())
+                {
+                    error!("could not announce the dynamic component {type_path}: {e:?}");
+                }
+            },
+        );
+    }
+}
+
 /// [`ComponentKind`] is an internal wrapper around the type of the component
 #[derive(Debug, Eq, Hash, Copy, Clone, PartialEq)]
 pub struct ComponentKind(TypeId);
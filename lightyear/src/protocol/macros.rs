@@ -0,0 +1,207 @@
+/*! Declare a whole protocol in one place with the [`protocol!`](crate::protocol!) macro
+
+Instead of registering the channels, messages, components and resources separately (which makes it
+easy for the client and server binaries to drift apart), the [`protocol!`](crate::protocol!) macro
+declares them all at once and generates:
+- a [`Plugin`](bevy::app::Plugin) that registers every item of the protocol. Add it to both the client and the server apps.
+- a `PROTOCOL_HASH` constant: a hash of the text of the declaration, i.e. the names of the items, their directions,
+  the channel settings expressions and the methods called on the components (with their arguments), in the order in
+  which they are declared. It can be used as the netcode `protocol_id`, so that clients built with a different
+  declaration are unable to connect.
+  The hash does not cover anything outside of the macro: the fields or the serialization of the types, the values that
+  the settings expressions evaluate to (e.g. a changed `default()`), or items that are registered elsewhere.
+
+Because every item is referenced by its type, a typo is caught at compile time.
+
+```rust,ignore
+use lightyear::prelude::*;
+
+lightyear::protocol! {
+    /// Protocol shared by the client and the server
+    pub struct ProtocolPlugin;
+    inputs {
+        Inputs,
+    }
+    channels {
+        Channel1 => ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        },
+    }
+    messages {
+        Message1 => Bidirectional,
+    }
+    components {
+        PlayerId => ServerToClient {
+            add_prediction(client::ComponentSyncMode::Once),
+            add_interpolation(client::ComponentSyncMode::Once),
+        },
+        PlayerPosition => ServerToClient {
+            add_prediction(client::ComponentSyncMode::Full),
+            add_linear_interpolation_fn(),
+        },
+    }
+    resources {
+        Score => ServerToClient,
+    }
+}
+
+let netcode_config = NetcodeConfig::default().with_protocol_id(ProtocolPlugin::PROTOCOL_HASH);
+```
+
+Every section is optional, but the sections must appear in the order above.
+The entries of the `components` section accept any method of [`ComponentRegistration`](crate::protocol::component::ComponentRegistration).
+*/
+
+/// FNV-1a hash of the declaration of a protocol
+pub const fn protocol_hash(declaration: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let bytes = declaration.as_bytes();
+    let mut hash = OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(PRIME);
+        i += 1;
+    }
+    hash
+}
+
+/// Declare all the channels, messages, components and resources of a protocol in one place.
+///
+/// See the [module-level documentation](crate::protocol::macros) for more details.
+#[macro_export]
+macro_rules! protocol {
+    (
+        @impl [$(#[$meta:meta])* $vis:vis struct $name:ident] [$declaration:expr]
+        $( inputs { $($input:ty),* $(,)? } )?
+        $( channels { $($channel:ty => $settings:expr),* $(,)? } )?
+        $( messages { $($message:ty => $message_direction:ident),* $(,)? } )?
+        $( components {
+            $($component:ty => $component_direction:ident $({
+                $($method:ident ( $($arg:expr),* )),* $(,)?
+            })?),* $(,)?
+        } )?
+        $( resources { $($resource:ty => $resource_direction:ident),* $(,)? } )?
+    ) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $name {
+            /// Hash of the text of the protocol declaration, which can be used as the netcode `protocol_id`.
+            ///
+            /// It doesn't cover the fields or the serialization of the declared types.
+            pub const PROTOCOL_HASH: u64 = $crate::protocol::macros::protocol_hash($declaration);
+        }
+
+        impl ::bevy::app::Plugin for $name {
+            fn build(&self, app: &mut ::bevy::app::App) {
+                #[allow(unused_imports)]
+                use $crate::prelude::{AppChannelExt as _, AppComponentExt as _, AppMessageExt as _};
+                // inputs
+                $($(
+                    app.add_plugins($crate::prelude::InputPlugin::<$input>::default());
+                )*)?
+                // channels
+                $($(
+                    app.add_channel::<$channel>($settings);
+                )*)?
+                // messages
+                $($(
+                    app.add_message::<$message>($crate::prelude::ChannelDirection::$message_direction);
+                )*)?
+                // components
+                $($(
+                    app.register_component::<$component>(
+                        $crate::prelude::ChannelDirection::$component_direction,
+                    )
+                    $($(.$method($($arg),*))*)?;
+                )*)?
+                // resources
+                $($(
+                    app.register_resource::<$resource>(
+                        $crate::prelude::ChannelDirection::$resource_direction,
+                    );
+                )*)?
+            }
+        }
+    };
+    (
+        $(#[$meta:meta])* $vis:vis struct $name:ident;
+        $($body:tt)*
+    ) => {
+        $crate::protocol! {
+            @impl [$(#[$meta])* $vis struct $name] [stringify!($($body)*)]
+            $($body)*
+        }
+    };
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::prelude::client::ComponentSyncMode;
+    use crate::prelude::{ChannelKind, ChannelMode, ChannelRegistry, ChannelSettings};
+    use crate::protocol::component::ComponentRegistry;
+    use crate::protocol::message::MessageRegistry;
+    use crate::tests::protocol::{Channel1, Component1, Component2, Message1, Resource1};
+
+    crate::protocol! {
+        struct DeclaredProtocolPlugin;
+        channels {
+            Channel1 => ChannelSettings {
+                mode: ChannelMode::UnorderedUnreliable,
+                ..default()
+            },
+        }
+        messages {
+            Message1 => Bidirectional,
+        }
+        components {
+            Component1 => ServerToClient {
+                add_prediction(ComponentSyncMode::Full),
+                add_linear_interpolation_fn(),
+            },
+            Component2 => ServerToClient,
+        }
+        resources {
+            Resource1 => ServerToClient,
+        }
+    }
+
+    crate::protocol! {
+        struct OtherProtocolPlugin;
+        messages {
+            Message1 => ServerToClient,
+        }
+    }
+
+    #[test]
+    fn test_protocol_macro() {
+        let mut app = App::new();
+        app.init_resource::<ChannelRegistry>()
+            .init_resource::<MessageRegistry>()
+            .init_resource::<ComponentRegistry>();
+        app.add_plugins(DeclaredProtocolPlugin);
+
+        assert!(app
+            .world
+            .resource::<ChannelRegistry>()
+            .get_net_from_kind(&ChannelKind::of::<Channel1>())
+            .is_some());
+        let message_registry = app.world.resource::<MessageRegistry>();
+        assert!(message_registry.is_registered::<Message1>());
+        assert!(message_registry.is_registered::<Resource1>());
+        let component_registry = app.world.resource::<ComponentRegistry>();
+        assert!(component_registry.is_registered::<Component1>());
+        assert!(component_registry.is_registered::<Component2>());
+
+        // the hash changes when the protocol changes
+        assert_ne!(
+            DeclaredProtocolPlugin::PROTOCOL_HASH,
+            OtherProtocolPlugin::PROTOCOL_HASH
+        );
+    }
+}
@@ -4,17 +4,19 @@ use bevy::ecs::entity::MapEntities;
 use std::any::TypeId;
 use std::fmt::Debug;
 
-use crate::client::config::ClientConfig;
-use crate::client::message::add_server_to_client_message;
+#[cfg(feature = "client")]
+use crate::client::{config::ClientConfig, message::add_server_to_client_message};
+#[cfg(feature = "client")]
+use crate::prelude::client;
 use crate::prelude::{
-    client, server, AppComponentExt, Channel, ComponentRegistry, RemoteEntityMap,
-    ReplicateResourceMetadata,
+    AppComponentExt, Channel, ComponentRegistry, RemoteEntityMap, ReplicateResourceMetadata,
 };
 use bevy::prelude::{
-    App, Component, EntityMapper, EventWriter, IntoSystemConfigs, ResMut, Resource, TypePath, World,
+    App, Component, EntityMapper, Event, EventWriter, IntoSystemConfigs, ResMut, Resource,
+    TypePath, World,
 };
 use bevy::reflect::Map;
-use bevy::utils::HashMap;
+use bevy::utils::{get_short_name, HashMap};
 use bitcode::encoding::Fixed;
 use bitcode::{Decode, Encode};
 use serde::de::DeserializeOwned;
@@ -23,7 +25,8 @@ use tracing::{debug, error};
 
 use crate::inputs::native::input_buffer::InputMessage;
 use crate::packet::message::Message;
-use crate::prelude::server::ServerConfig;
+#[cfg(feature = "server")]
+use crate::prelude::server;
 use crate::prelude::{ChannelDirection, ChannelKind, MainSet};
 use crate::protocol::component::{ComponentKind, ComponentRegistration};
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
@@ -34,8 +37,13 @@ use crate::serialize::bitcode::writer::BitcodeWriter;
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::serialize::RawData;
+#[cfg(feature = "server")]
+use crate::server::config::ServerConfig;
+#[cfg(feature = "server")]
 use crate::server::message::add_client_to_server_message;
+use crate::shared::events::network_events;
 use crate::shared::replication::entity_map::EntityMap;
+use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::resources::DespawnResource;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -104,19 +112,27 @@ pub(crate) enum MessageType {
 pub struct MessageRegistry {
     typed_map: HashMap<MessageKind, MessageType>,
     serialize_fns_map: HashMap<MessageKind, ErasedSerializeFns>,
+    /// True if the messages are serialized with the [`canonical`](crate::serialize::canonical) encoding
+    canonical_encoding: bool,
     pub(crate) kind_map: TypeMapper<MessageKind>,
 }
 
 fn register_message_send<M: Message>(app: &mut App, direction: ChannelDirection) {
+    #[cfg(feature = "client")]
     let is_client = app.world.get_resource::<ClientConfig>().is_some();
+    #[cfg(feature = "server")]
     let is_server = app.world.get_resource::<ServerConfig>().is_some();
     match direction {
-        ChannelDirection::ClientToServer => {
+        ChannelDirection::ClientToServer =>
+        {
+            #[cfg(feature = "server")]
             if is_server {
                 add_client_to_server_message::<M>(app);
             }
         }
-        ChannelDirection::ServerToClient => {
+        ChannelDirection::ServerToClient =>
+        {
+            #[cfg(feature = "client")]
             if is_client {
                 add_server_to_client_message::<M>(app);
             }
@@ -128,17 +144,82 @@ fn register_message_send<M: Message>(app: &mut App, direction: ChannelDirection)
     }
 }
 
+fn register_network_event_send<E: Event + Message + Clone, C: Channel>(
+    app: &mut App,
+    direction: ChannelDirection,
+) {
+    #[cfg(feature = "client")]
+    let is_client = app.world.get_resource::<ClientConfig>().is_some();
+    #[cfg(feature = "server")]
+    let is_server = app.world.get_resource::<ServerConfig>().is_some();
+    let is_bidirectional = direction == ChannelDirection::Bidirectional;
+    #[cfg(feature = "client")]
+    if is_client
+        && matches!(
+            direction,
+            ChannelDirection::ClientToServer | ChannelDirection::Bidirectional
+        )
+    {
+        // the events are only sent to the server, which doesn't relay them to the other clients
+        network_events::add_network_event_send_systems::<E, C, client::ConnectionManager>(
+            app,
+            NetworkTarget::None,
+        );
+    }
+    #[cfg(feature = "client")]
+    if is_client
+        && matches!(
+            direction,
+            ChannelDirection::ServerToClient | ChannelDirection::Bidirectional
+        )
+    {
+        network_events::add_network_event_receive_systems::<E, client::ConnectionManager>(
+            app,
+            is_bidirectional,
+        );
+    }
+    #[cfg(feature = "server")]
+    if is_server
+        && matches!(
+            direction,
+            ChannelDirection::ServerToClient | ChannelDirection::Bidirectional
+        )
+    {
+        network_events::add_network_event_send_systems::<E, C, server::ConnectionManager>(
+            app,
+            NetworkTarget::All,
+        );
+        network_events::add_network_event_target_systems::<E, C, server::ConnectionManager>(app);
+    }
+    #[cfg(feature = "server")]
+    if is_server
+        && matches!(
+            direction,
+            ChannelDirection::ClientToServer | ChannelDirection::Bidirectional
+        )
+    {
+        network_events::add_network_event_receive_systems::<E, server::ConnectionManager>(
+            app,
+            is_bidirectional,
+        );
+    }
+}
+
 fn register_resource_send<R: Resource + Message>(app: &mut App, direction: ChannelDirection) {
+    #[cfg(feature = "client")]
     let is_client = app.world.get_resource::<ClientConfig>().is_some();
+    #[cfg(feature = "server")]
     let is_server = app.world.get_resource::<ServerConfig>().is_some();
     match direction {
         ChannelDirection::ClientToServer => {
+            #[cfg(feature = "client")]
             if is_client {
                 crate::shared::replication::resources::send::add_resource_send_systems::<
                     R,
                     client::ConnectionManager,
                 >(app);
             }
+            #[cfg(feature = "server")]
             if is_server {
                 crate::shared::replication::resources::receive::add_resource_receive_systems::<
                     R,
@@ -147,12 +228,14 @@ fn register_resource_send<R: Resource + Message>(app: &mut App, direction: Chann
             }
         }
         ChannelDirection::ServerToClient => {
+            #[cfg(feature = "server")]
             if is_server {
                 crate::shared::replication::resources::send::add_resource_send_systems::<
                     R,
                     server::ConnectionManager,
                 >(app);
             }
+            #[cfg(feature = "client")]
             if is_client {
                 crate::shared::replication::resources::receive::add_resource_receive_systems::<
                     R,
@@ -161,6 +244,7 @@ fn register_resource_send<R: Resource + Message>(app: &mut App, direction: Chann
             }
         }
         ChannelDirection::Bidirectional => {
+            #[cfg(feature = "server")]
             if is_server {
                 crate::shared::replication::resources::send::add_resource_send_systems::<
                     R,
@@ -171,6 +255,7 @@ fn register_resource_send<R: Resource + Message>(app: &mut App, direction: Chann
                     server::ConnectionManager,
                 >(app, true);
             }
+            #[cfg(feature = "client")]
             if is_client {
                 crate::shared::replication::resources::send::add_resource_send_systems::<
                     R,
@@ -203,6 +288,37 @@ impl<M> MessageRegistration<'_, M> {
         registry.add_map_entities::<M>();
         self
     }
+
+    /// Use the network id `net_id` for this message, instead of the one assigned from the registration order.
+    ///
+    /// The id stays the same when other messages are added or removed from the protocol.
+    /// If another message was assigned this id automatically, it gets a new one.
+    ///
+    /// # Panics
+    ///
+    /// If the id was already chosen for another message.
+    pub fn with_stable_id(self, net_id: NetId) -> Self
+    where
+        M: 'static,
+    {
+        let mut registry = self.app.world.resource_mut::<MessageRegistry>();
+        registry
+            .kind_map
+            .set_stable_id(MessageKind::of::<M>(), net_id);
+        self
+    }
+
+    /// Serialize this message with the [`versioned`](crate::serialize::versioned) fields encoding,
+    /// so that peers built with an older or newer definition of the message can still read it
+    /// (the unknown fields are skipped and the missing fields are defaulted).
+    pub fn add_versioned_fields(self) -> Self
+    where
+        M: Message,
+    {
+        let mut registry = self.app.world.resource_mut::<MessageRegistry>();
+        registry.add_versioned_fields::<M>();
+        self
+    }
 }
 
 /// Add a message to the list of messages that can be sent
@@ -214,9 +330,29 @@ pub trait AppMessageExt {
         direction: ChannelDirection,
     ) -> MessageRegistration<'_, M>;
 
+    /// Registers a message that contains [`Entities`](bevy::prelude::Entity).
+    ///
+    /// When the message is received, the entities are mapped from the remote world to the local world,
+    /// the same way as the entities of replicated components.
+    fn add_message_with_map_entities<M: Message + MapEntities>(
+        &mut self,
+        direction: ChannelDirection,
+    ) -> MessageRegistration<'_, M>;
+
     /// Registers the resource in the Registry
     /// This resource can now be sent over the network.
     fn register_resource<R: Resource + Message>(&mut self, direction: ChannelDirection);
+
+    /// Registers a bevy [`Event`] that is sent over the network on the channel `C`.
+    ///
+    /// The events written to an [`EventWriter<E>`](bevy::prelude::EventWriter) are sent to the remote peer,
+    /// which emits them again as normal bevy events. On the server, write a
+    /// [`ToClients<E>`](crate::shared::events::network_events::ToClients) event to only send
+    /// the event to some clients.
+    fn add_network_event<E: Event + Message + Clone, C: Channel>(
+        &mut self,
+        direction: ChannelDirection,
+    ) -> MessageRegistration<'_, E>;
 }
 
 impl AppMessageExt for App {
@@ -236,12 +372,27 @@ impl AppMessageExt for App {
         }
     }
 
+    fn add_message_with_map_entities<M: Message + MapEntities>(
+        &mut self,
+        direction: ChannelDirection,
+    ) -> MessageRegistration<'_, M> {
+        self.add_message::<M>(direction).add_map_entities()
+    }
+
     /// Register a resource to be automatically replicated over the network
     fn register_resource<R: Resource + Message>(&mut self, direction: ChannelDirection) {
         self.add_message::<R>(direction);
         self.add_message::<DespawnResource<R>>(direction);
         register_resource_send::<R>(self, direction)
     }
+
+    fn add_network_event<E: Event + Message + Clone, C: Channel>(
+        &mut self,
+        direction: ChannelDirection,
+    ) -> MessageRegistration<'_, E> {
+        register_network_event_send::<E, C>(self, direction);
+        self.add_message::<E>(direction)
+    }
 }
 
 impl MessageRegistry {
@@ -256,13 +407,46 @@ impl MessageRegistry {
         self.kind_map.net_id(&MessageKind::of::<M>()).is_some()
     }
 
+    /// Write a description of the registered messages, that must be identical on the client and the server
+    /// (used to compute the [`registries_hash`](crate::protocol::registries_hash))
+    pub(crate) fn describe(&self, description: &mut String) {
+        let mut messages = self.kind_map.id_map.iter().collect::<Vec<_>>();
+        messages.sort_by_key(|(net_id, _)| **net_id);
+        for (net_id, kind) in messages {
+            let fns = self.serialize_fns_map.get(kind);
+            let name = fns.map_or("unknown", |fns| fns.type_name);
+            let versioned = if fns.is_some_and(|fns| fns.versioned_fields) {
+                " versioned"
+            } else {
+                ""
+            };
+            let message_type = self
+                .typed_map
+                .get(kind)
+                .map_or(MessageType::Normal, |message_type| *message_type);
+            description.push_str(&format!(
+                "message {net_id} {} {message_type:?}{versioned}\n",
+                get_short_name(name)
+            ));
+        }
+    }
+
     pub(crate) fn add_message<M: Message>(&mut self, message_type: MessageType) {
         let message_kind = self.kind_map.add::<M>();
-        self.serialize_fns_map
-            .insert(message_kind, ErasedSerializeFns::new::<M>());
+        let mut erased_fns = ErasedSerializeFns::new::<M>();
+        erased_fns.canonical = self.canonical_encoding;
+        self.serialize_fns_map.insert(message_kind, erased_fns);
         self.typed_map.insert(message_kind, message_type);
     }
 
+    /// Serialize all the messages (including the ones that are registered later) with the canonical encoding
+    pub(crate) fn enable_canonical_encoding(&mut self) {
+        self.canonical_encoding = true;
+        for erased_fns in self.serialize_fns_map.values_mut() {
+            erased_fns.canonical = true;
+        }
+    }
+
     pub(crate) fn try_add_map_entities<M: MapEntities + 'static>(&mut self) {
         let kind = MessageKind::of::<M>();
         if let Some(erased_fns) = self.serialize_fns_map.get_mut(&kind) {
@@ -270,6 +454,15 @@ impl MessageRegistry {
         }
     }
 
+    pub(crate) fn add_versioned_fields<M: Message>(&mut self) {
+        let kind = MessageKind::of::<M>();
+        let erased_fns = self
+            .serialize_fns_map
+            .get_mut(&kind)
+            .expect("the message is not part of the protocol");
+        erased_fns.add_versioned_fields::<M>();
+    }
+
     pub(crate) fn add_map_entities<M: MapEntities + 'static>(&mut self) {
         let kind = MessageKind::of::<M>();
         let erased_fns = self
@@ -20,6 +20,9 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::channel::builder::{Channel, ChannelSettings};
+use crate::protocol::channel::ChannelRegistry;
+use crate::protocol::component::ComponentRegistry;
+use crate::protocol::message::MessageRegistry;
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::shared::replication::ReplicationSend;
@@ -33,10 +36,45 @@ pub(crate) mod component;
 /// Defines the various messages that can be sent over the network
 pub(crate) mod message;
 
+pub mod macros;
+
 /// Provides a mapping from a type to a unique identifier that can be serialized
 pub(crate) mod registry;
 pub(crate) mod serialize;
 
+/// Reason why the server refused the connection of a client
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeniedReason {
+    /// The components, messages or channels registered on the client and the server are different
+    /// (the client and server binaries were probably built from different versions of the protocol)
+    ProtocolMismatch { client_hash: u64, server_hash: u64 },
+}
+
+/// Deterministic hash of the components, messages and channels that are registered in the protocol.
+///
+/// The hash covers:
+/// - for each component: its network id, its type name (without the module path) and whether it is versioned, delta-compressed or quantized
+///   (with the compression)
+/// - for each message: its network id, its type name, its kind and whether it is versioned
+/// - for each channel registered before the app is built: its network id, its type name, its mode and its direction
+///
+/// It doesn't cover the fields or the serialization of the types, the settings of the channels (apart from the mode),
+/// or the channels added at runtime.
+///
+/// The client sends it to the server when it connects, so that the server can refuse clients
+/// whose protocol is different.
+pub(crate) fn registries_hash(
+    component_registry: &ComponentRegistry,
+    message_registry: &MessageRegistry,
+    channel_registry: &ChannelRegistry,
+) -> u64 {
+    let mut description = String::new();
+    component_registry.describe(&mut description);
+    message_registry.describe(&mut description);
+    channel_registry.describe(&mut description);
+    macros::protocol_hash(&description)
+}
+
 /// Something that can be serialized bit by bit
 pub trait BitSerializable {
     fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()>;
@@ -1,5 +1,5 @@
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 
 /// Id used to serialize IDs over the network efficiently
@@ -22,6 +22,8 @@ pub struct TypeMapper<K: TypeKind> {
     pub(crate) next_net_id: NetId,
     pub(crate) kind_map: HashMap<K, NetId>,
     pub(crate) id_map: HashMap<NetId, K>,
+    /// Network ids that were chosen explicitly, and that stay the same across versions of the protocol
+    pub(crate) stable_ids: HashSet<NetId>,
 }
 
 impl<K: TypeKind> Default for TypeMapper<K> {
@@ -36,6 +38,7 @@ impl<K: TypeKind> TypeMapper<K> {
             next_net_id: 0,
             kind_map: HashMap::new(),
             id_map: HashMap::new(),
+            stable_ids: HashSet::new(),
         }
     }
 
@@ -45,13 +48,60 @@ impl<K: TypeKind> TypeMapper<K> {
         if self.kind_map.contains_key(&kind) {
             panic!("Type {:?} already registered", std::any::type_name::<T>());
         }
-        let net_id = self.next_net_id;
+        let net_id = self.next_free_id();
         self.kind_map.insert(kind, net_id);
         self.id_map.insert(net_id, kind);
-        self.next_net_id += 1;
         kind
     }
 
+    /// Skip the network ids that were chosen explicitly
+    fn next_free_id(&mut self) -> NetId {
+        while self.id_map.contains_key(&self.next_net_id) {
+            self.next_net_id += 1;
+        }
+        let net_id = self.next_net_id;
+        self.next_net_id += 1;
+        net_id
+    }
+
+    /// Use a network id chosen explicitly for an already registered type, instead of the one that was
+    /// assigned from the registration order.
+    ///
+    /// If the id was already assigned to another type, that type gets a new id.
+    ///
+    /// # Panics
+    ///
+    /// If the id was already chosen explicitly for another type.
+    pub(crate) fn set_stable_id(&mut self, kind: K, net_id: NetId) {
+        let previous_id = *self
+            .kind_map
+            .get(&kind)
+            .expect("the type is not registered");
+        if previous_id == net_id {
+            self.stable_ids.insert(net_id);
+            return;
+        }
+        self.stable_ids.remove(&previous_id);
+        assert!(
+            self.stable_ids.insert(net_id),
+            "network id {net_id} is already used by another type"
+        );
+        self.id_map.remove(&previous_id);
+        if let Some(other) = self.id_map.insert(net_id, kind) {
+            let other_id = self.next_free_id();
+            self.kind_map.insert(other, other_id);
+            self.id_map.insert(other_id, other);
+        }
+        self.kind_map.insert(kind, net_id);
+    }
+
+    /// Register a type with a network id that was chosen by the remote
+    pub(crate) fn insert(&mut self, kind: K, net_id: NetId) {
+        self.kind_map.insert(kind, net_id);
+        self.id_map.insert(net_id, kind);
+        self.next_net_id = std::cmp::max(self.next_net_id, net_id + 1);
+    }
+
     pub fn kind(&self, net_id: NetId) -> Option<&K> {
         self.id_map.get(&net_id)
     }
@@ -60,8 +110,57 @@ impl<K: TypeKind> TypeMapper<K> {
         self.kind_map.get(kind)
     }
 
+    pub(crate) fn is_empty(&self) -> bool {
+        self.kind_map.is_empty()
+    }
+
     #[cfg(test)]
     pub(in crate::protocol) fn len(&self) -> usize {
         self.kind_map.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct Kind(TypeId);
+
+    impl From<TypeId> for Kind {
+        fn from(type_id: TypeId) -> Self {
+            Self(type_id)
+        }
+    }
+
+    impl TypeKind for Kind {}
+
+    #[test]
+    fn test_stable_ids() {
+        let mut mapper = TypeMapper::<Kind>::new();
+        let a = mapper.add::<u8>();
+        let b = mapper.add::<u16>();
+        // the id of `a` is given to `b`, `a` gets a new id
+        mapper.set_stable_id(b, 0);
+        assert_eq!(mapper.net_id(&b), Some(&0));
+        assert_eq!(mapper.net_id(&a), Some(&2));
+        assert_eq!(mapper.kind(1), None);
+
+        // the types registered afterwards don't use the stable ids
+        mapper.set_stable_id(a, 3);
+        let c = mapper.add::<u32>();
+        let d = mapper.add::<u64>();
+        assert_eq!(mapper.net_id(&c), Some(&4));
+        assert_eq!(mapper.net_id(&d), Some(&5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate_stable_ids() {
+        let mut mapper = TypeMapper::<Kind>::new();
+        let a = mapper.add::<u8>();
+        let b = mapper.add::<u16>();
+        mapper.set_stable_id(a, 5);
+        mapper.set_stable_id(b, 5);
+    }
+}
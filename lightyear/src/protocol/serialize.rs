@@ -7,7 +7,9 @@ use crate::protocol::registry::NetId;
 use crate::protocol::BitSerializable;
 use crate::serialize::bitcode::reader::BitcodeReader;
 use crate::serialize::bitcode::writer::BitcodeWriter;
+use crate::serialize::canonical::{self, Canonical};
 use crate::serialize::reader::ReadBuffer;
+use crate::serialize::versioned;
 use crate::serialize::writer::WriteBuffer;
 use crate::shared::replication::entity_map::EntityMap;
 use bevy::app::App;
@@ -26,12 +28,19 @@ pub struct ErasedSerializeFns {
     pub serialize: unsafe fn(),
     pub deserialize: unsafe fn(),
     pub map_entities: Option<unsafe fn()>,
+    /// Serialize fn used instead of `serialize` when the [`canonical`](crate::serialize::canonical) encoding is enabled
+    pub canonical_serialize: unsafe fn(),
+    /// True if the type is serialized with the [`versioned`](crate::serialize::versioned) fields encoding
+    pub(crate) versioned_fields: bool,
+    /// True if the type is serialized with the [`canonical`](crate::serialize::canonical) encoding
+    pub(crate) canonical: bool,
 }
 
 pub struct SerializeFns<M> {
     pub serialize: SerializeFn<M>,
     pub deserialize: DeserializeFn<M>,
     pub map_entities: Option<MapEntitiesFn<M>>,
+    pub canonical_serialize: SerializeFn<M>,
 }
 
 type SerializeFn<M> = fn(&M, writer: &mut BitcodeWriter) -> anyhow::Result<()>;
@@ -42,12 +51,18 @@ impl ErasedSerializeFns {
     pub(crate) fn new<M: Message>() -> Self {
         let serialize: SerializeFn<M> = <M as BitSerializable>::encode;
         let deserialize: DeserializeFn<M> = <M as BitSerializable>::decode;
+        let canonical_serialize: SerializeFn<M> = canonical::serialize::<M, BitcodeWriter>;
         Self {
             type_id: TypeId::of::<M>(),
             type_name: std::any::type_name::<M>(),
             serialize: unsafe { std::mem::transmute(serialize) },
             deserialize: unsafe { std::mem::transmute(deserialize) },
             map_entities: None,
+            canonical_serialize: unsafe {
+                std::mem::transmute::<SerializeFn<M>, unsafe fn()>(canonical_serialize)
+            },
+            versioned_fields: false,
+            canonical: false,
         }
     }
     pub(crate) unsafe fn typed<M: 'static>(&self) -> SerializeFns<M> {
@@ -63,6 +78,9 @@ impl ErasedSerializeFns {
             serialize: unsafe { std::mem::transmute(self.serialize) },
             deserialize: unsafe { std::mem::transmute(self.deserialize) },
             map_entities: self.map_entities.map(|m| unsafe { std::mem::transmute(m) }),
+            canonical_serialize: unsafe {
+                std::mem::transmute::<unsafe fn(), SerializeFn<M>>(self.canonical_serialize)
+            },
         }
     }
 
@@ -71,6 +89,21 @@ impl ErasedSerializeFns {
         self.map_entities = Some(unsafe { std::mem::transmute(map_entities) });
     }
 
+    /// Serialize the type with the [`versioned`](crate::serialize::versioned) fields encoding,
+    /// so that peers that use another version of the type can still read it
+    pub(crate) fn add_versioned_fields<M: Message>(&mut self) {
+        let serialize: SerializeFn<M> = versioned::serialize::<M, BitcodeWriter>;
+        let deserialize: DeserializeFn<M> = versioned::deserialize::<M, BitcodeReader>;
+        let canonical_serialize: SerializeFn<M> =
+            |message, writer| versioned::serialize(&Canonical(message), writer);
+        self.serialize = unsafe { std::mem::transmute::<SerializeFn<M>, unsafe fn()>(serialize) };
+        self.deserialize =
+            unsafe { std::mem::transmute::<DeserializeFn<M>, unsafe fn()>(deserialize) };
+        self.canonical_serialize =
+            unsafe { std::mem::transmute::<SerializeFn<M>, unsafe fn()>(canonical_serialize) };
+        self.versioned_fields = true;
+    }
+
     pub(crate) fn map_entities<M: 'static>(&self, message: &mut M, entity_map: &mut EntityMap) {
         let fns = unsafe { self.typed::<M>() };
         if let Some(map_entities_fn) = fns.map_entities {
@@ -84,7 +117,11 @@ impl ErasedSerializeFns {
         writer: &mut BitcodeWriter,
     ) -> anyhow::Result<()> {
         let fns = unsafe { self.typed::<M>() };
-        (fns.serialize)(message, writer)
+        if self.canonical {
+            (fns.canonical_serialize)(message, writer)
+        } else {
+            (fns.serialize)(message, writer)
+        }
     }
 
     /// Deserialize the message value from the reader
@@ -106,6 +143,10 @@ pub trait AppSerializeExt {
     /// Indicate that the type `M` contains Entity references, and that the entities
     /// should be mapped during deserialization
     fn add_map_entities<M: MapEntities + 'static>(&mut self);
+
+    /// Serialize all the components and messages of the protocol with the [`canonical`](crate::serialize::canonical)
+    /// encoding, so that equal values are always serialized to the same bytes
+    fn enable_canonical_encoding(&mut self);
 }
 
 impl AppSerializeExt for App {
@@ -119,4 +160,13 @@ impl AppSerializeExt for App {
         let mut registry = self.world.resource_mut::<ComponentRegistry>();
         registry.try_add_map_entities::<M>();
     }
+
+    fn enable_canonical_encoding(&mut self) {
+        self.world
+            .resource_mut::<MessageRegistry>()
+            .enable_canonical_encoding();
+        self.world
+            .resource_mut::<ComponentRegistry>()
+            .enable_canonical_encoding();
+    }
 }
@@ -107,7 +107,7 @@ mod tests {
 
         // finish
         let bytes = buffer.finish_write();
-        assert_eq!(bytes, &[]);
+        assert!(bytes.is_empty());
         Ok(())
     }
 
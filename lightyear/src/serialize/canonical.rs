@@ -0,0 +1,574 @@
+//! Canonical encoding, that serializes equal values to the same bytes across runs and platforms
+//!
+//! The contents of a `HashMap` are serialized in the iteration order of the map, which depends on the random
+//! state of the map, and floats can have several NaN representations. So two equal values can be serialized
+//! to different bytes, which makes the state hashes (for desync detection) or the recorded files not reproducible.
+//!
+//! With the canonical encoding:
+//! - the entries of the maps are serialized in the order of their serialized keys
+//! - the NaN floats are serialized as [`f32::NAN`] or [`f64::NAN`]
+//!
+//! The canonical encoding is enabled for a whole protocol with
+//! [`AppSerializeExt::enable_canonical_encoding`](crate::protocol::serialize::AppSerializeExt::enable_canonical_encoding).
+//! The bytes are read in the same way as with the default encoding, so the peers don't need to agree on using it.
+//! The differences sent for the components that use delta compression are serialized with their own
+//! [`BitSerializable`](crate::protocol::BitSerializable) implementation.
+//!
+//! Serde serializes the sets as sequences, so they cannot be distinguished from the lists, whose order matters.
+//! The sets that must be serialized deterministically should use [`sorted_set`]:
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Team {
+//!     #[serde(serialize_with = "lightyear::serialize::canonical::sorted_set")]
+//!     players: HashSet<ClientId>,
+//! }
+//! ```
+use anyhow::Context;
+use bitcode::Error;
+use serde::ser::{
+    Error as _, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::{Serialize, Serializer};
+
+use crate::serialize::writer::WriteBuffer;
+
+/// Serialize `value` with the canonical encoding
+pub(crate) fn serialize<T: Serialize + ?Sized, W: WriteBuffer>(
+    value: &T,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let value = value
+        .serialize(ValueSerializer)
+        .context("could not canonicalize the value")?;
+    writer.serialize(&value)
+}
+
+/// Wrapper that serializes the value with the canonical encoding
+pub(crate) struct Canonical<'a, T: ?Sized>(pub(crate) &'a T);
+
+impl<T: Serialize + ?Sized> Serialize for Canonical<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0
+            .serialize(ValueSerializer)
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Serialize the elements of a set in the order of their canonical encoding
+///
+/// Use it with `#[serde(serialize_with = "lightyear::serialize::canonical::sorted_set")]`
+pub fn sorted_set<I, S>(set: I, serializer: S) -> Result<S::Ok, S::Error>
+where
+    I: IntoIterator,
+    I::Item: Serialize,
+    S: Serializer,
+{
+    let mut elements = set
+        .into_iter()
+        .map(|element| Ok((bitcode::serialize(&Canonical(&element))?, element)))
+        .collect::<Result<Vec<_>, Error>>()
+        .map_err(S::Error::custom)?;
+    elements.sort_by(|(a, _), (b, _)| a.cmp(b));
+    serializer.collect_seq(elements.into_iter().map(|(_, element)| element))
+}
+
+/// A serialized value, recorded so that it can be serialized again with the same calls to the [`Serializer`]
+enum Value {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Str(String),
+    Bytes(Vec<u8>),
+    None,
+    Some(Box<Value>),
+    Unit,
+    UnitStruct(&'static str),
+    UnitVariant(&'static str, u32, &'static str),
+    NewtypeStruct(&'static str, Box<Value>),
+    NewtypeVariant(&'static str, u32, &'static str, Box<Value>),
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+    TupleStruct(&'static str, Vec<Value>),
+    TupleVariant(&'static str, u32, &'static str, Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    /// The fields that were skipped are None
+    Struct(&'static str, usize, Fields),
+    StructVariant(&'static str, u32, &'static str, usize, Fields),
+}
+
+type Fields = Vec<(&'static str, Option<Value>)>;
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::I8(v) => serializer.serialize_i8(*v),
+            Value::I16(v) => serializer.serialize_i16(*v),
+            Value::I32(v) => serializer.serialize_i32(*v),
+            Value::I64(v) => serializer.serialize_i64(*v),
+            Value::I128(v) => serializer.serialize_i128(*v),
+            Value::U8(v) => serializer.serialize_u8(*v),
+            Value::U16(v) => serializer.serialize_u16(*v),
+            Value::U32(v) => serializer.serialize_u32(*v),
+            Value::U64(v) => serializer.serialize_u64(*v),
+            Value::U128(v) => serializer.serialize_u128(*v),
+            Value::F32(v) => serializer.serialize_f32(*v),
+            Value::F64(v) => serializer.serialize_f64(*v),
+            Value::Char(v) => serializer.serialize_char(*v),
+            Value::Str(v) => serializer.serialize_str(v),
+            Value::Bytes(v) => serializer.serialize_bytes(v),
+            Value::None => serializer.serialize_none(),
+            Value::Some(v) => serializer.serialize_some(v.as_ref()),
+            Value::Unit => serializer.serialize_unit(),
+            Value::UnitStruct(name) => serializer.serialize_unit_struct(name),
+            Value::UnitVariant(name, index, variant) => {
+                serializer.serialize_unit_variant(name, *index, variant)
+            }
+            Value::NewtypeStruct(name, v) => serializer.serialize_newtype_struct(name, v.as_ref()),
+            Value::NewtypeVariant(name, index, variant, v) => {
+                serializer.serialize_newtype_variant(name, *index, variant, v.as_ref())
+            }
+            Value::Seq(elements) => serializer.collect_seq(elements),
+            Value::Tuple(elements) => {
+                let mut tuple = serializer.serialize_tuple(elements.len())?;
+                for element in elements {
+                    tuple.serialize_element(element)?;
+                }
+                tuple.end()
+            }
+            Value::TupleStruct(name, elements) => {
+                let mut tuple = serializer.serialize_tuple_struct(name, elements.len())?;
+                for element in elements {
+                    tuple.serialize_field(element)?;
+                }
+                tuple.end()
+            }
+            Value::TupleVariant(name, index, variant, elements) => {
+                let mut tuple =
+                    serializer.serialize_tuple_variant(name, *index, variant, elements.len())?;
+                for element in elements {
+                    tuple.serialize_field(element)?;
+                }
+                tuple.end()
+            }
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Value::Struct(name, len, fields) => {
+                let mut s = serializer.serialize_struct(name, *len)?;
+                for (key, value) in fields {
+                    match value {
+                        Some(value) => s.serialize_field(key, value)?,
+                        None => s.skip_field(key)?,
+                    }
+                }
+                s.end()
+            }
+            Value::StructVariant(name, index, variant, len, fields) => {
+                let mut s = serializer.serialize_struct_variant(name, *index, variant, *len)?;
+                for (key, value) in fields {
+                    match value {
+                        Some(value) => s.serialize_field(key, value)?,
+                        None => s.skip_field(key)?,
+                    }
+                }
+                s.end()
+            }
+        }
+    }
+}
+
+/// Serializer that records the value, canonicalizing the maps and the floats
+struct ValueSerializer;
+
+macro_rules! record {
+    ($($name:ident($ty:ty) => $variant:ident),* $(,)?) => {
+        $(
+            fn $name(self, v: $ty) -> Result<Value, Error> {
+                Ok(Value::$variant(v))
+            }
+        )*
+    };
+}
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqRecorder;
+    type SerializeTuple = SeqRecorder;
+    type SerializeTupleStruct = SeqRecorder;
+    type SerializeTupleVariant = SeqRecorder;
+    type SerializeMap = MapRecorder;
+    type SerializeStruct = StructRecorder;
+    type SerializeStructVariant = StructRecorder;
+
+    record!(
+        serialize_bool(bool) => Bool,
+        serialize_i8(i8) => I8,
+        serialize_i16(i16) => I16,
+        serialize_i32(i32) => I32,
+        serialize_i64(i64) => I64,
+        serialize_i128(i128) => I128,
+        serialize_u8(u8) => U8,
+        serialize_u16(u16) => U16,
+        serialize_u32(u32) => U32,
+        serialize_u64(u64) => U64,
+        serialize_u128(u128) => U128,
+        serialize_char(char) => Char,
+    );
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        Ok(Value::F32(if v.is_nan() { f32::NAN } else { v }))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::F64(if v.is_nan() { f64::NAN } else { v }))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Str(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        Ok(Value::Some(Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Value, Error> {
+        Ok(Value::UnitStruct(name))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::UnitVariant(name, index, variant))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        Ok(Value::NewtypeStruct(name, Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        Ok(Value::NewtypeVariant(
+            name,
+            index,
+            variant,
+            Box::new(value.serialize(self)?),
+        ))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqRecorder, Error> {
+        Ok(SeqRecorder::new(Value::Seq, len.unwrap_or_default()))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqRecorder, Error> {
+        Ok(SeqRecorder::new(Value::Tuple, len))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<SeqRecorder, Error> {
+        Ok(SeqRecorder::new(
+            move |elements| Value::TupleStruct(name, elements),
+            len,
+        ))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqRecorder, Error> {
+        Ok(SeqRecorder::new(
+            move |elements| Value::TupleVariant(name, index, variant, elements),
+            len,
+        ))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapRecorder, Error> {
+        Ok(MapRecorder {
+            entries: Vec::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<StructRecorder, Error> {
+        Ok(StructRecorder {
+            build: Box::new(move |fields| Value::Struct(name, len, fields)),
+            fields: Fields::with_capacity(len),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<StructRecorder, Error> {
+        Ok(StructRecorder {
+            build: Box::new(move |fields| Value::StructVariant(name, index, variant, len, fields)),
+            fields: Fields::with_capacity(len),
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        // same as the bitcode serializer
+        false
+    }
+}
+
+struct SeqRecorder {
+    build: Box<dyn FnOnce(Vec<Value>) -> Value>,
+    elements: Vec<Value>,
+}
+
+impl SeqRecorder {
+    fn new(build: impl FnOnce(Vec<Value>) -> Value + 'static, len: usize) -> Self {
+        Self {
+            build: Box::new(build),
+            elements: Vec::with_capacity(len),
+        }
+    }
+
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.elements.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, Error> {
+        Ok((self.build)(self.elements))
+    }
+}
+
+macro_rules! impl_seq_recorder {
+    ($($tr:ident::$fun:ident),* $(,)?) => {
+        $(
+            impl $tr for SeqRecorder {
+                type Ok = Value;
+                type Error = Error;
+
+                fn $fun<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+                    self.push(value)
+                }
+
+                fn end(self) -> Result<Value, Error> {
+                    self.finish()
+                }
+            }
+        )*
+    };
+}
+
+impl_seq_recorder!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field,
+);
+
+struct MapRecorder {
+    /// The entries, along with the serialized key that they are sorted by
+    entries: Vec<(Vec<u8>, Value, Value)>,
+    key: Option<Value>,
+}
+
+impl SerializeMap for MapRecorder {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::custom("serialize_value called before serialize_key"))?;
+        let sort_key = bitcode::serialize(&key)?;
+        self.entries
+            .push((sort_key, key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<Value, Error> {
+        self.entries.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+        Ok(Value::Map(
+            self.entries
+                .into_iter()
+                .map(|(_, key, value)| (key, value))
+                .collect(),
+        ))
+    }
+}
+
+struct StructRecorder {
+    build: Box<dyn FnOnce(Fields) -> Value>,
+    fields: Fields,
+}
+
+impl StructRecorder {
+    fn push<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.fields
+            .push((key, Some(value.serialize(ValueSerializer)?)));
+        Ok(())
+    }
+}
+
+macro_rules! impl_struct_recorder {
+    ($($tr:ident),* $(,)?) => {
+        $(
+            impl $tr for StructRecorder {
+                type Ok = Value;
+                type Error = Error;
+
+                fn serialize_field<T: ?Sized + Serialize>(
+                    &mut self,
+                    key: &'static str,
+                    value: &T,
+                ) -> Result<(), Error> {
+                    self.push(key, value)
+                }
+
+                fn skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+                    self.fields.push((key, None));
+                    Ok(())
+                }
+
+                fn end(self) -> Result<Value, Error> {
+                    Ok((self.build)(self.fields))
+                }
+            }
+        )*
+    };
+}
+
+impl_struct_recorder!(SerializeStruct, SerializeStructVariant);
+
+#[cfg(test)]
+mod tests {
+    // the std maps use a random state, so they iterate in a different order
+    use std::collections::{HashMap, HashSet};
+
+    use bevy::prelude::Component;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::prelude::ComponentRegistry;
+    use crate::serialize::bitcode::reader::BitcodeReader;
+    use crate::serialize::bitcode::writer::BitcodeWriter;
+    use crate::serialize::reader::ReadBuffer;
+    use crate::shared::replication::entity_map::EntityMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct State {
+        scores: HashMap<u32, f32>,
+        #[serde(serialize_with = "sorted_set")]
+        players: HashSet<u64>,
+        name: Option<String>,
+        position: (f32, f64),
+    }
+
+    fn state(keys: impl Iterator<Item = u32> + Clone) -> State {
+        State {
+            scores: keys.clone().map(|key| (key, key as f32)).collect(),
+            players: keys.map(|key| key as u64).collect(),
+            name: None,
+            position: (1.0, f64::NAN),
+        }
+    }
+
+    #[test]
+    fn test_canonical_encoding_is_deterministic() {
+        // the maps are filled in a different order, so they iterate in a different order
+        let a = state(0..100);
+        let b = state((0..100).rev());
+        let a_bytes = bitcode::serialize(&Canonical(&a)).unwrap();
+        assert_eq!(a_bytes, bitcode::serialize(&Canonical(&b)).unwrap());
+
+        // NaNs with a different payload are encoded the same way
+        let mut c = state(0..100);
+        c.position.1 = f64::from_bits(f64::NAN.to_bits() | 1);
+        assert_eq!(a_bytes, bitcode::serialize(&Canonical(&c)).unwrap());
+    }
+
+    #[test]
+    fn test_canonical_encoding_roundtrip() {
+        let mut value = state(0..10);
+        value.position.1 = 2.0;
+        value.name = Some("name".to_string());
+        let bytes = bitcode::serialize(&Canonical(&value)).unwrap();
+        // the canonical encoding is read like the default encoding
+        assert_eq!(bitcode::deserialize::<State>(&bytes).unwrap(), value);
+        assert_eq!(
+            bytes.len(),
+            bitcode::serialize(&value).unwrap().len(),
+            "the canonical encoding has the same size as the default encoding"
+        );
+    }
+
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    struct Scores(HashMap<u32, f32>);
+
+    #[test]
+    fn test_canonical_encoding_registry() {
+        let mut registry = ComponentRegistry::default();
+        registry.register_component::<Scores>();
+        registry.enable_canonical_encoding();
+        let a = Scores((0..100).map(|key| (key, key as f32)).collect());
+        let b = Scores((0..100).rev().map(|key| (key, key as f32)).collect());
+        let mut writer = BitcodeWriter::with_capacity(100);
+        let a_bytes = registry.serialize(&a, &mut writer).unwrap();
+        assert_eq!(a_bytes, registry.serialize(&b, &mut writer).unwrap());
+        let mut reader = BitcodeReader::start_read(&a_bytes);
+        assert_eq!(
+            registry
+                .deserialize::<Scores>(&mut reader, &mut EntityMap::default())
+                .unwrap(),
+            a
+        );
+    }
+}
@@ -1,7 +1,10 @@
 //! Serialization and deserialization of types
 
 pub mod bitcode;
+pub mod canonical;
+pub mod quantize;
 pub mod reader;
+pub mod versioned;
 pub mod writer;
 
 pub type RawData = Vec<u8>;
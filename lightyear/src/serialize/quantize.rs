@@ -0,0 +1,512 @@
+//! Compact encodings for the fields of components and messages
+//!
+//! By default, integers and floats are serialized with their full size (a `u32` always takes 32 bits).
+//! Component updates are often dominated by small integers and floats that live in a known range, which can be
+//! encoded with far fewer bits:
+//! - [`varint`]: variable-length integers, to use with `#[serde(with = "...")]`
+//! - [`Quantized`]: fixed-point floats in a range, with a configurable number of bits
+//! - [`CompressedQuat`]: rotations encoded with the 'smallest three' method (29 bits by default instead of 128)
+//!
+//! Bools don't need any special handling: bitcode already packs each bool in a single bit.
+//!
+//! ```rust,ignore
+//! #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+//! pub struct Player {
+//!     #[serde(with = "lightyear::serialize::quantize::varint")]
+//!     score: u32,
+//!     // angle in degrees, encoded in 10 bits (~0.35 degree of precision)
+//!     rotation: Quantized<0, 360, 10>,
+//!     is_alive: bool,
+//! }
+//! ```
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::fmt::Formatter;
+
+use anyhow::bail;
+use bevy::prelude::{Deref, DerefMut, Quat};
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::{Error as _, SerializeSeq, SerializeTuple};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::protocol::component::Linear;
+use crate::serialize::bitcode::reader::BitcodeReader;
+use crate::serialize::bitcode::writer::BitcodeWriter;
+use crate::serialize::reader::ReadBuffer;
+use crate::serialize::writer::WriteBuffer;
+
+/// Serialize the `len` lowest bits of `value`, one bit at a time
+fn serialize_bits<S: Serializer>(
+    serializer: S,
+    value: u64,
+    len: u32,
+    with_len: bool,
+) -> Result<S::Ok, S::Error> {
+    let mut bits = (0..len).map(|i| (value >> i) & 1 == 1);
+    if with_len {
+        let mut seq = serializer.serialize_seq(Some(len as usize))?;
+        bits.try_for_each(|bit| seq.serialize_element(&bit))?;
+        seq.end()
+    } else {
+        let mut tuple = serializer.serialize_tuple(len as usize)?;
+        bits.try_for_each(|bit| tuple.serialize_element(&bit))?;
+        tuple.end()
+    }
+}
+
+struct BitsVisitor;
+
+impl<'de> Visitor<'de> for BitsVisitor {
+    type Value = u64;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a sequence of at most 64 bits")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<u64, A::Error> {
+        let mut value = 0;
+        let mut i = 0;
+        while let Some(bit) = seq.next_element::<bool>()? {
+            if i == u64::BITS {
+                return Err(A::Error::custom("too many bits"));
+            }
+            value |= (bit as u64) << i;
+            i += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// Variable-length encoding of integers: small values are serialized with fewer bits.
+///
+/// A value with `n` significant bits is serialized with `n` bits, plus the length `n` which takes
+/// `2 * log2(n + 1) + 1` bits. For example a `u32` smaller than 16 takes at most 9 bits instead of 32.
+/// Signed integers are zigzag-encoded so that small negative values are also cheap.
+///
+/// Use it with `#[serde(with = "lightyear::serialize::quantize::varint")]`.
+pub mod varint {
+    use super::*;
+
+    /// Integers that can be encoded with the [`varint`](self) encoding
+    pub trait VarInt: Copy {
+        fn to_varint(self) -> u64;
+        fn from_varint(value: u64) -> Option<Self>;
+    }
+
+    macro_rules! impl_unsigned {
+        ($($t:ty),*) => {$(
+            impl VarInt for $t {
+                fn to_varint(self) -> u64 {
+                    self as u64
+                }
+                fn from_varint(value: u64) -> Option<Self> {
+                    value.try_into().ok()
+                }
+            }
+        )*};
+    }
+
+    macro_rules! impl_signed {
+        ($($t:ty),*) => {$(
+            impl VarInt for $t {
+                fn to_varint(self) -> u64 {
+                    let value = self as i64;
+                    ((value << 1) ^ (value >> 63)) as u64
+                }
+                fn from_varint(value: u64) -> Option<Self> {
+                    (((value >> 1) as i64) ^ -((value & 1) as i64)).try_into().ok()
+                }
+            }
+        )*};
+    }
+
+    impl_unsigned!(u8, u16, u32, u64, usize);
+    impl_signed!(i8, i16, i32, i64, isize);
+
+    pub fn serialize<T: VarInt, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let value = value.to_varint();
+        serialize_bits(serializer, value, u64::BITS - value.leading_zeros(), true)
+    }
+
+    pub fn deserialize<'de, T: VarInt, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let value = deserializer.deserialize_seq(BitsVisitor)?;
+        T::from_varint(value).ok_or_else(|| D::Error::custom("varint out of range"))
+    }
+}
+
+/// A float in the range `[MIN, MAX]` that is serialized as a fixed-point number with `BITS` bits.
+///
+/// The precision is `(MAX - MIN) / (2^BITS - 1)`. Values outside of the range are clamped, and the value
+/// that is received by the remote is the quantized value.
+/// `BITS` must be between 1 and 32, and `MIN` must be smaller than `MAX`: otherwise the serialization returns an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Deref, DerefMut)]
+pub struct Quantized<const MIN: i32, const MAX: i32, const BITS: u32>(pub f32);
+
+impl<const MIN: i32, const MAX: i32, const BITS: u32> Quantized<MIN, MAX, BITS> {
+    const STEPS: f32 = ((1u64 << BITS) - 1) as f32;
+    const RANGE: f32 = MAX as f32 - MIN as f32;
+
+    /// Returns an error if the parameters cannot be used to serialize the value
+    fn check() -> anyhow::Result<()> {
+        if !(1..=32).contains(&BITS) || MIN >= MAX {
+            bail!("invalid Quantized parameters: MIN={MIN}, MAX={MAX}, BITS={BITS}");
+        }
+        Ok(())
+    }
+
+    fn quantize(&self) -> u64 {
+        let normalized = (self.0.clamp(MIN as f32, MAX as f32) - MIN as f32) / Self::RANGE;
+        // NaN is converted to 0
+        (normalized * Self::STEPS).round() as u64
+    }
+
+    fn dequantize(value: u64) -> Self {
+        Self(MIN as f32 + value as f32 / Self::STEPS * Self::RANGE)
+    }
+}
+
+impl<const MIN: i32, const MAX: i32, const BITS: u32> From<f32> for Quantized<MIN, MAX, BITS> {
+    fn from(value: f32) -> Self {
+        Self(value)
+    }
+}
+
+impl<const MIN: i32, const MAX: i32, const BITS: u32> Serialize for Quantized<MIN, MAX, BITS> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Self::check().map_err(S::Error::custom)?;
+        serialize_bits(serializer, self.quantize(), BITS, false)
+    }
+}
+
+impl<'de, const MIN: i32, const MAX: i32, const BITS: u32> Deserialize<'de>
+    for Quantized<MIN, MAX, BITS>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::check().map_err(D::Error::custom)?;
+        let value = deserializer.deserialize_tuple(BITS as usize, BitsVisitor)?;
+        Ok(Self::dequantize(value))
+    }
+}
+
+/// Compression of the rotations, used by [`CompressedQuat`] and by
+/// [`ComponentRegistration::add_quantization`](crate::protocol::component::ComponentRegistration::add_quantization)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuatCompression {
+    /// 'Smallest three' encoding: the largest component of the normalized quaternion is dropped (it can be
+    /// recomputed from the other three), and the three remaining components (which are in `[-1/√2, 1/√2]`)
+    /// are quantized with the given number of bits, between 1 and 20 (the serialization returns an error otherwise).
+    ///
+    /// The rotation takes `2 + 3 * bits` bits, for example 29 bits with 9 bits per component.
+    SmallestThree(u32),
+}
+
+impl QuatCompression {
+    /// Number of bits used to serialize a rotation
+    pub fn num_bits(&self) -> u32 {
+        match self {
+            QuatCompression::SmallestThree(bits) => 2 + 3 * bits,
+        }
+    }
+
+    /// Returns an error if the compression cannot be used to serialize a rotation
+    fn check(&self) -> anyhow::Result<()> {
+        match *self {
+            QuatCompression::SmallestThree(bits) => {
+                if !(1..=20).contains(&bits) {
+                    bail!("invalid QuatCompression: {bits} bits per component, expected between 1 and 20");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The compression must be valid (see [`QuatCompression::check`])
+    fn compress(&self, quat: Quat) -> u64 {
+        match *self {
+            QuatCompression::SmallestThree(bits) => {
+                let quat = if quat.is_finite() && quat.length_squared() > 0.0 {
+                    quat.normalize()
+                } else {
+                    Quat::IDENTITY
+                };
+                let components = quat.to_array();
+                let largest = (0..4)
+                    .max_by(|a, b| components[*a].abs().total_cmp(&components[*b].abs()))
+                    .unwrap();
+                // q and -q are the same rotation, so we can always make the largest component positive
+                let sign = if components[largest] < 0.0 { -1.0 } else { 1.0 };
+                let steps = ((1u64 << bits) - 1) as f32;
+                let mut value = largest as u64;
+                let mut offset = 2;
+                for (i, component) in components.iter().enumerate() {
+                    if i == largest {
+                        continue;
+                    }
+                    let normalized = ((sign * component + FRAC_1_SQRT_2) / (2.0 * FRAC_1_SQRT_2))
+                        .clamp(0.0, 1.0);
+                    value |= ((normalized * steps).round() as u64) << offset;
+                    offset += bits;
+                }
+                value
+            }
+        }
+    }
+
+    /// The compression must be valid (see [`QuatCompression::check`])
+    fn decompress(&self, value: u64) -> Quat {
+        match *self {
+            QuatCompression::SmallestThree(bits) => {
+                let largest = (value & 0b11) as usize;
+                let steps = ((1u64 << bits) - 1) as f32;
+                let mask = (1u64 << bits) - 1;
+                let mut components = [0.0; 4];
+                let mut offset = 2;
+                let mut sum_squares = 0.0;
+                for (i, component) in components.iter_mut().enumerate() {
+                    if i == largest {
+                        continue;
+                    }
+                    let quantized = ((value >> offset) & mask) as f32;
+                    *component = quantized / steps * 2.0 * FRAC_1_SQRT_2 - FRAC_1_SQRT_2;
+                    sum_squares += *component * *component;
+                    offset += bits;
+                }
+                components[largest] = (1.0 - sum_squares).max(0.0).sqrt();
+                Quat::from_array(components).normalize()
+            }
+        }
+    }
+
+    /// Returns the rotation that the remote receives when `quat` is sent with this compression
+    /// (or `quat` itself if the compression is invalid, since the rotation cannot be sent)
+    pub fn quantize(&self, quat: Quat) -> Quat {
+        if self.check().is_err() {
+            return quat;
+        }
+        self.decompress(self.compress(quat))
+    }
+
+    pub(crate) fn write(&self, quat: Quat, writer: &mut BitcodeWriter) -> anyhow::Result<()> {
+        self.check()?;
+        let value = self.compress(quat);
+        (0..self.num_bits()).try_for_each(|i| writer.serialize(&((value >> i) & 1 == 1)))
+    }
+
+    pub(crate) fn read(&self, reader: &mut BitcodeReader) -> anyhow::Result<Quat> {
+        self.check()?;
+        let mut value = 0;
+        for i in 0..self.num_bits() {
+            value |= (reader.deserialize::<bool>()? as u64) << i;
+        }
+        Ok(self.decompress(value))
+    }
+}
+
+/// A rotation that is serialized with the [`QuatCompression::SmallestThree`] encoding, with `BITS` bits
+/// per component (9 by default, which gives a precision of about 0.1 degree).
+///
+/// The value received by the remote is normalized. It is interpolated with a spherical linear interpolation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deref, DerefMut)]
+pub struct CompressedQuat<const BITS: u32 = 9>(pub Quat);
+
+impl<const BITS: u32> From<Quat> for CompressedQuat<BITS> {
+    fn from(value: Quat) -> Self {
+        Self(value)
+    }
+}
+
+impl<const BITS: u32> Linear for CompressedQuat<BITS> {
+    fn lerp(start: &Self, other: &Self, t: f32) -> Self {
+        Self(start.0.slerp(other.0, t))
+    }
+}
+
+impl<const BITS: u32> Serialize for CompressedQuat<BITS> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let compression = QuatCompression::SmallestThree(BITS);
+        compression.check().map_err(S::Error::custom)?;
+        serialize_bits(
+            serializer,
+            compression.compress(self.0),
+            compression.num_bits(),
+            false,
+        )
+    }
+}
+
+impl<'de, const BITS: u32> Deserialize<'de> for CompressedQuat<BITS> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let compression = QuatCompression::SmallestThree(BITS);
+        compression.check().map_err(D::Error::custom)?;
+        let value = deserializer.deserialize_tuple(compression.num_bits() as usize, BitsVisitor)?;
+        Ok(Self(compression.decompress(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Data {
+        #[serde(with = "varint")]
+        small: u32,
+        #[serde(with = "varint")]
+        large: u64,
+        #[serde(with = "varint")]
+        negative: i16,
+        angle: Quantized<0, 360, 10>,
+        flag: bool,
+    }
+
+    #[test]
+    fn test_quantized_serialization() -> anyhow::Result<()> {
+        let data = Data {
+            small: 5,
+            large: u64::MAX,
+            negative: -3,
+            angle: Quantized(90.2),
+            flag: true,
+        };
+        let mut writer = BitcodeWriter::with_capacity(50);
+        writer.serialize(&data)?;
+        // 5 + 3 bits for `small`, 13 + 64 for `large`, 5 + 3 for `negative`, 10 for `angle`, 1 for `flag`
+        assert_eq!(writer.num_bits_written(), 104);
+        let bytes = writer.finish_write().to_vec();
+
+        let mut reader = BitcodeReader::start_read(&bytes);
+        let decoded = reader.deserialize::<Data>()?;
+        assert_eq!(decoded.small, 5);
+        assert_eq!(decoded.large, u64::MAX);
+        assert_eq!(decoded.negative, -3);
+        assert!((decoded.angle.0 - 90.2).abs() < 360.0 / 1023.0);
+        assert!(decoded.flag);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantized_range() {
+        type Angle = Quantized<-1, 1, 8>;
+        assert_eq!(Angle::dequantize(Angle::from(-5.0).quantize()).0, -1.0);
+        assert_eq!(Angle::dequantize(Angle::from(5.0).quantize()).0, 1.0);
+        assert_eq!(Angle::dequantize(Angle::from(f32::NAN).quantize()).0, -1.0);
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let mut writer = BitcodeWriter::with_capacity(10);
+        assert!(writer.serialize(&Quantized::<1, 1, 8>(1.0)).is_err());
+        assert!(writer.serialize(&Quantized::<0, 1, 40>(1.0)).is_err());
+        assert!(writer
+            .serialize(&CompressedQuat::<21>(Quat::IDENTITY))
+            .is_err());
+        let compression = QuatCompression::SmallestThree(0);
+        assert!(compression.write(Quat::IDENTITY, &mut writer).is_err());
+        assert_eq!(compression.quantize(Quat::IDENTITY), Quat::IDENTITY);
+    }
+
+    #[test]
+    fn test_varint_out_of_range() -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Large(#[serde(with = "varint")] u32);
+        #[derive(Deserialize, Debug)]
+        struct Small(
+            #[serde(with = "varint")]
+            #[allow(dead_code)]
+            u8,
+        );
+
+        let mut writer = BitcodeWriter::with_capacity(10);
+        writer.serialize(&Large(300))?;
+        let bytes = writer.finish_write().to_vec();
+        let mut reader = BitcodeReader::start_read(&bytes);
+        assert!(reader.deserialize::<Small>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_quat() -> anyhow::Result<()> {
+        let rotations = [
+            Quat::IDENTITY,
+            Quat::from_rotation_y(2.5),
+            Quat::from_euler(bevy::math::EulerRot::XYZ, 0.3, -1.2, 3.0),
+            // the largest component is negative
+            -Quat::from_rotation_z(0.1),
+        ];
+        for rotation in rotations {
+            let mut writer = BitcodeWriter::with_capacity(10);
+            writer.serialize(&CompressedQuat::<9>(rotation))?;
+            assert_eq!(writer.num_bits_written(), 29);
+            let bytes = writer.finish_write().to_vec();
+            let mut reader = BitcodeReader::start_read(&bytes);
+            let decoded = reader.deserialize::<CompressedQuat>()?;
+            assert!(decoded.is_normalized());
+            // less than 0.5 degree of difference
+            assert!(decoded.angle_between(rotation).to_degrees() < 0.5);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_quat_compression_write() -> anyhow::Result<()> {
+        let compression = QuatCompression::SmallestThree(12);
+        let rotation = Quat::from_rotation_x(1.0);
+        let mut writer = BitcodeWriter::with_capacity(10);
+        compression.write(rotation, &mut writer)?;
+        assert_eq!(writer.num_bits_written(), 38);
+        let bytes = writer.finish_write().to_vec();
+        let mut reader = BitcodeReader::start_read(&bytes);
+        assert_eq!(
+            compression.read(&mut reader)?,
+            compression.quantize(rotation)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_component_quantization() -> anyhow::Result<()> {
+        use bevy::prelude::Component;
+
+        use crate::client::components::ComponentSyncMode;
+        use crate::protocol::component::ComponentRegistry;
+        use crate::shared::replication::entity_map::EntityMap;
+
+        #[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+        struct Rotation(Quat);
+        impl From<Quat> for Rotation {
+            fn from(quat: Quat) -> Self {
+                Self(quat)
+            }
+        }
+        impl From<Rotation> for Quat {
+            fn from(rotation: Rotation) -> Self {
+                rotation.0
+            }
+        }
+
+        let mut registry = ComponentRegistry::default();
+        registry.register_component::<Rotation>();
+        registry.set_prediction_mode::<Rotation>(ComponentSyncMode::Full);
+        registry.set_quantization::<Rotation>(QuatCompression::SmallestThree(9));
+
+        let rotation = Rotation(Quat::from_rotation_y(1.0));
+        let mut writer = BitcodeWriter::with_capacity(10);
+        let bytes = registry.serialize(&rotation, &mut writer)?;
+        // the net id takes 16 bits
+        assert_eq!(bytes.len(), 6);
+        let mut reader = BitcodeReader::start_read(&bytes);
+        let received = registry.deserialize::<Rotation>(&mut reader, &mut EntityMap::default())?;
+        assert_ne!(received, rotation);
+        assert!(received.0.angle_between(rotation.0).to_degrees() < 0.5);
+        // the predicted value is quantized before being compared with the confirmed value
+        assert!(!registry.should_rollback(&rotation, &received));
+        assert!(registry.should_rollback(&Rotation(Quat::IDENTITY), &received));
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+//! Length-prefixed field encoding, that lets the definition of a struct evolve between versions of the protocol
+//!
+//! Each field of the struct is serialized separately, along with its index and its length. This costs a few
+//! extra bytes per field, but a peer that uses another version of the struct can still read it:
+//! - the fields that the receiver doesn't know (because they were added in a newer version) are skipped
+//! - the fields that the sender didn't send (because it uses an older version) are set to their default value.
+//!   They must be marked with `#[serde(default)]` (`Option` fields default to `None` without it)
+//!
+//! ```rust,ignore
+//! // v1.0
+//! #[derive(Serialize, Deserialize)]
+//! struct PlayerInfo {
+//!     name: String,
+//! }
+//!
+//! // v1.1
+//! #[derive(Serialize, Deserialize)]
+//! struct PlayerInfo {
+//!     name: String,
+//!     #[serde(default)]
+//!     score: u32,
+//! }
+//! ```
+//!
+//! The fields are identified by their position, so new fields must be added at the end of the struct,
+//! and existing fields must not be removed or reordered.
+//! Only structs with named fields (and unit structs) can use this encoding.
+use anyhow::Context;
+use bitcode::Error;
+use serde::de::value::U16Deserializer;
+use serde::de::{DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::ser::{Error as _, Impossible, SerializeStruct};
+use serde::{forward_to_deserialize_any, Deserializer, Serialize, Serializer};
+
+use crate::serialize::reader::ReadBuffer;
+use crate::serialize::writer::WriteBuffer;
+use crate::serialize::RawData;
+
+/// The index of each field that was serialized, along with its value
+type Fields = Vec<(u16, RawData)>;
+
+const UNSUPPORTED: &str = "the versioned fields encoding only supports structs with named fields";
+
+/// Serialize `value` with the length-prefixed field encoding
+pub(crate) fn serialize<T: Serialize, W: WriteBuffer>(
+    value: &T,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let fields = value
+        .serialize(FieldsSerializer)
+        .context("could not serialize the fields")?;
+    writer.serialize(&fields)
+}
+
+/// Deserialize a value that was serialized with the length-prefixed field encoding
+pub(crate) fn deserialize<T: DeserializeOwned, R: ReadBuffer>(reader: &mut R) -> anyhow::Result<T> {
+    let fields = reader.deserialize::<Fields>()?;
+    T::deserialize(FieldsDeserializer { fields }).context("could not deserialize the fields")
+}
+
+struct FieldsSerializer;
+
+macro_rules! unsupported {
+    ($($name:ident($($arg:ty),*)),* $(,)?) => {
+        $(
+            fn $name(self, $(_: $arg),*) -> Result<Fields, Error> {
+                Err(Error::custom(UNSUPPORTED))
+            }
+        )*
+    };
+}
+
+impl Serializer for FieldsSerializer {
+    type Ok = Fields;
+    type Error = Error;
+    type SerializeSeq = Impossible<Fields, Error>;
+    type SerializeTuple = Impossible<Fields, Error>;
+    type SerializeTupleStruct = Impossible<Fields, Error>;
+    type SerializeTupleVariant = Impossible<Fields, Error>;
+    type SerializeMap = Impossible<Fields, Error>;
+    type SerializeStruct = StructFields;
+    type SerializeStructVariant = Impossible<Fields, Error>;
+
+    unsupported!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    );
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<Fields, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Fields, Error> {
+        Ok(Fields::new())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Fields, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Fields, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<StructFields, Error> {
+        Ok(StructFields {
+            fields: Fields::with_capacity(len),
+            index: 0,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::custom(UNSUPPORTED))
+    }
+}
+
+struct StructFields {
+    fields: Fields,
+    /// Position of the next field in the struct
+    index: u16,
+}
+
+impl SerializeStruct for StructFields {
+    type Ok = Fields;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.fields.push((self.index, bitcode::serialize(value)?));
+        self.index += 1;
+        Ok(())
+    }
+
+    fn skip_field(&mut self, _: &'static str) -> Result<(), Error> {
+        // the next fields keep their position
+        self.index += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Fields, Error> {
+        Ok(self.fields)
+    }
+}
+
+struct FieldsDeserializer {
+    fields: Fields,
+}
+
+impl<'de> Deserializer<'de> for FieldsDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+        Err(serde::de::Error::custom(UNSUPPORTED))
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        // skip the fields that were added in a newer version of the struct
+        let known_fields = self
+            .fields
+            .into_iter()
+            .filter(|(index, _)| (*index as usize) < fields.len())
+            .collect::<Fields>();
+        visitor.visit_map(FieldsAccess {
+            fields: known_fields.into_iter(),
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit newtype_struct seq tuple tuple_struct map enum
+        identifier ignored_any
+    }
+}
+
+struct FieldsAccess {
+    fields: std::vec::IntoIter<(u16, RawData)>,
+    /// Value of the field whose index was just read
+    value: Option<RawData>,
+}
+
+impl<'de> MapAccess<'de> for FieldsAccess {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((index, value)) = self.fields.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        let index: U16Deserializer<Error> = index.into_deserializer();
+        seed.deserialize(index).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| serde::de::Error::custom("the value of the field was already read"))?;
+        bitcode::serde::deserialize_seed(seed, &value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::serialize::bitcode::reader::BitcodeReader;
+    use crate::serialize::bitcode::writer::BitcodeWriter;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct V1 {
+        name: String,
+        position: (f32, f32),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct V2 {
+        name: String,
+        position: (f32, f32),
+        #[serde(default)]
+        score: u32,
+        nickname: Option<String>,
+    }
+
+    fn write<T: Serialize>(value: &T) -> RawData {
+        let mut writer = BitcodeWriter::with_capacity(50);
+        serialize(value, &mut writer).unwrap();
+        writer.finish_write().to_vec()
+    }
+
+    fn read<T: DeserializeOwned>(bytes: &[u8]) -> T {
+        let mut reader = BitcodeReader::start_read(bytes);
+        deserialize(&mut reader).unwrap()
+    }
+
+    #[test]
+    fn test_versioned_fields() {
+        let v1 = V1 {
+            name: "a".to_string(),
+            position: (1.0, 2.0),
+        };
+        let v2 = V2 {
+            name: "b".to_string(),
+            position: (3.0, 4.0),
+            score: 5,
+            nickname: Some("c".to_string()),
+        };
+        assert_eq!(read::<V1>(&write(&v1)), v1);
+        assert_eq!(read::<V2>(&write(&v2)), v2);
+
+        // the fields unknown to the older version are skipped
+        assert_eq!(
+            read::<V1>(&write(&v2)),
+            V1 {
+                name: "b".to_string(),
+                position: (3.0, 4.0),
+            }
+        );
+        // the fields missing from the older version are defaulted
+        assert_eq!(
+            read::<V2>(&write(&v1)),
+            V2 {
+                name: "a".to_string(),
+                position: (1.0, 2.0),
+                score: 0,
+                nickname: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unsupported_types() {
+        let mut writer = BitcodeWriter::with_capacity(50);
+        assert!(serialize(&(1u32, 2u32), &mut writer).is_err());
+    }
+}
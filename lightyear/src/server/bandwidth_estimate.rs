@@ -0,0 +1,268 @@
+/*! Estimate the bandwidth that the server needs for each client
+
+When [`ServerConfig::bandwidth_estimate`](crate::server::config::ServerConfig::bandwidth_estimate) is set, the server
+records the number of bytes of the packets that it sends to each client in the [`BandwidthEstimate`] resource.
+The estimates take into account the current replication config (priorities, send intervals, etc.) and are averaged
+over [`BandwidthEstimateConfig::window`].
+
+With [`BandwidthEstimateConfig::dry_run`], the server runs its replication pipeline normally but the packets are
+only recorded, not sent. This can be used to estimate the bandwidth requirements of a game for a target number of players
+before deploying it:
+```rust,ignore
+fn log_bandwidth(estimate: Res<BandwidthEstimate>) {
+    info!(
+        "100 players would need {} bytes/s",
+        estimate.bytes_per_second_for_clients(100)
+    );
+}
+```
+Note that in dry-run mode the clients never receive the packets, so they never acknowledge them: the messages sent on
+reliable channels are sent again at every resend interval.
+*/
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap};
+
+use crate::prelude::ClientId;
+use crate::server::config::ServerConfig;
+use crate::shared::time_manager::WrappedTime;
+
+/// Configuration of the bandwidth estimation
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct BandwidthEstimateConfig {
+    /// Duration over which the bytes sent to each client are averaged
+    pub window: Duration,
+    /// If true, the packets are recorded but not sent to the clients
+    pub dry_run: bool,
+}
+
+impl Default for BandwidthEstimateConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(5),
+            dry_run: false,
+        }
+    }
+}
+
+impl BandwidthEstimateConfig {
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
+/// Bytes sent to a client during the window
+#[derive(Debug, Default)]
+struct ClientBandwidth {
+    /// Bytes that were sent at each send
+    samples: VecDeque<(WrappedTime, usize)>,
+    /// Sum of the bytes in `samples`
+    window_bytes: usize,
+    /// Time of the first send to the client
+    start: Option<WrappedTime>,
+    /// Time of the last send to the client
+    last: Option<WrappedTime>,
+    total_bytes: usize,
+}
+
+impl ClientBandwidth {
+    fn bytes_per_second(&self, window: Duration) -> f32 {
+        let (Some(start), Some(last)) = (self.start, self.last) else {
+            return 0.0;
+        };
+        // before a full window has elapsed, average over the time since the first send
+        let elapsed = (last - start).to_std().unwrap_or_default().min(window);
+        self.window_bytes as f32 / elapsed.as_secs_f32().max(f32::EPSILON)
+    }
+}
+
+/// Number of bytes that the server sends (or would send, in dry-run mode) to each client
+///
+/// This resource is only present if [`ServerConfig::bandwidth_estimate`](crate::server::config::ServerConfig::bandwidth_estimate) is set.
+#[derive(Resource, Debug)]
+pub struct BandwidthEstimate {
+    window: Duration,
+    dry_run: bool,
+    clients: HashMap<ClientId, ClientBandwidth>,
+}
+
+impl BandwidthEstimate {
+    pub(crate) fn new(config: &BandwidthEstimateConfig) -> Self {
+        Self {
+            window: config.window,
+            dry_run: config.dry_run,
+            clients: HashMap::default(),
+        }
+    }
+
+    /// Returns true if the packets are only recorded, and not sent to the clients
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Record the bytes of the packets sent to a client at time `now`
+    pub(crate) fn record(&mut self, client_id: ClientId, now: WrappedTime, bytes: usize) {
+        let client = self.clients.entry(client_id).or_default();
+        client.start.get_or_insert(now);
+        client.last = Some(now);
+        client.samples.push_back((now, bytes));
+        client.window_bytes += bytes;
+        client.total_bytes += bytes;
+        let window_start = now - self.window;
+        while let Some((time, bytes)) = client.samples.front().copied() {
+            if time >= window_start {
+                break;
+            }
+            client.samples.pop_front();
+            client.window_bytes -= bytes;
+        }
+    }
+
+    /// Stop recording the bytes sent to the clients that are not connected anymore
+    pub(crate) fn retain_clients(&mut self, mut is_connected: impl FnMut(&ClientId) -> bool) {
+        self.clients.retain(|client_id, _| is_connected(client_id));
+    }
+
+    /// Bytes per second sent to the client, averaged over the window
+    pub fn bytes_per_second(&self, client_id: ClientId) -> Option<f32> {
+        self.clients
+            .get(&client_id)
+            .map(|client| client.bytes_per_second(self.window))
+    }
+
+    /// Total number of bytes sent to the client since it connected
+    pub fn total_bytes(&self, client_id: ClientId) -> Option<usize> {
+        self.clients
+            .get(&client_id)
+            .map(|client| client.total_bytes)
+    }
+
+    /// Bytes per second sent to each client, averaged over the window
+    pub fn clients(&self) -> impl Iterator<Item = (ClientId, f32)> + '_ {
+        self.clients
+            .iter()
+            .map(|(client_id, client)| (*client_id, client.bytes_per_second(self.window)))
+    }
+
+    /// Bytes per second sent to all the clients, averaged over the window
+    pub fn total_bytes_per_second(&self) -> f32 {
+        self.clients().map(|(_, bytes)| bytes).sum()
+    }
+
+    /// Average of the bytes per second sent to a client
+    pub fn average_bytes_per_client(&self) -> f32 {
+        if self.clients.is_empty() {
+            return 0.0;
+        }
+        self.total_bytes_per_second() / self.clients.len() as f32
+    }
+
+    /// Estimate of the bytes per second that the server would send with `num_clients` clients,
+    /// assuming that each client would receive as much data as the current clients on average.
+    ///
+    /// This is a lower bound if the amount of data replicated to each client grows with the number of clients
+    /// (for example if every client controls an entity that is visible to all the other clients)
+    pub fn bytes_per_second_for_clients(&self, num_clients: usize) -> f32 {
+        self.average_bytes_per_client() * num_clients as f32
+    }
+}
+
+pub(crate) struct BandwidthEstimatePlugin;
+
+impl Plugin for BandwidthEstimatePlugin {
+    fn build(&self, _app: &mut App) {}
+
+    // the config is read in `finish` so that it can still be modified after the plugins are added
+    fn finish(&self, app: &mut App) {
+        let Some(config) = app
+            .world
+            .resource::<ServerConfig>()
+            .bandwidth_estimate
+            .clone()
+        else {
+            return;
+        };
+        app.insert_resource(BandwidthEstimate::new(&config));
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::{LinkConditionerConfig, SharedConfig, TickConfig};
+    use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+    #[test]
+    fn test_window() {
+        let mut estimate = BandwidthEstimate::new(
+            &BandwidthEstimateConfig::default().with_window(Duration::from_secs(1)),
+        );
+        let client_id = ClientId::Netcode(0);
+        for i in 0..=10 {
+            estimate.record(client_id, WrappedTime::new(i * 100), 100);
+        }
+        assert_eq!(estimate.bytes_per_second(client_id), Some(1100.0));
+        // the old samples are dropped from the window
+        for i in 11..=20 {
+            estimate.record(client_id, WrappedTime::new(i * 100), 200);
+        }
+        assert_eq!(estimate.bytes_per_second(client_id), Some(2100.0));
+        assert_eq!(estimate.total_bytes(client_id), Some(3100));
+        assert_eq!(estimate.bytes_per_second_for_clients(10), 21000.0);
+
+        estimate.retain_clients(|_| false);
+        assert_eq!(estimate.bytes_per_second(client_id), None);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper.init();
+        // the dry-run is enabled once the client is connected
+        stepper.server_app.insert_resource(BandwidthEstimate::new(
+            &BandwidthEstimateConfig::default().with_dry_run(true),
+        ));
+        stepper
+            .server_app
+            .world
+            .spawn(crate::prelude::server::Replicate::default());
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let estimate = stepper.server_app.world.resource::<BandwidthEstimate>();
+        assert!(estimate.bytes_per_second(client_id).unwrap() > 0.0);
+        // the packets were not sent, so the entity was not replicated
+        assert!(stepper
+            .client_app
+            .world
+            .resource::<crate::prelude::client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .is_empty());
+    }
+}
@@ -1,15 +1,149 @@
 //! The server spawns an entity per connected client to store metadata about them.
 //!
 //! This module contains components and systems to manage the metadata on client entities.
-use crate::prelude::ClientId;
-use crate::shared::sets::{InternalReplicationSet, ServerMarker};
-use bevy::ecs::entity::EntityHashSet;
+//!
+//! The per-client networking state is stored on the client entity, so that it can be accessed with regular
+//! queries (including from systems that run in parallel), inspected, or extended by plugins:
+//! - [`ConnectedClient`]: the [`ClientId`] of the client
+//! - [`ReplicationPriorities`]: the priority accumulated by each replication group. The send system reads
+//!   the accumulators from this component and stores them back after the packets are sent, so modifying them
+//!   changes which groups are sent first when the bandwidth is limited
+//! - [`VisibleEntities`]: the entities that are visible to the client through interest management. It is
+//!   maintained by the visibility systems and read by the replication systems
+//! - [`SendQueues`]: the number of messages waiting to be sent (or acked) on each channel. The messages
+//!   themselves are buffered in the channel senders of the
+//!   [`ConnectionManager`](crate::server::connection::ConnectionManager), so this component is refreshed
+//!   after the packets are sent
+//!
+//! The components only trigger change detection when their content changes.
+//!
+//! Plugins can also insert their own components on the client entities to store additional per-client data,
+//! the entity is despawned when the client disconnects.
+//! ```rust
+//! # use bevy::prelude::*;
+//! # use lightyear::prelude::server::*;
+//! fn my_system(query: Query<(&ConnectedClient, &SendQueues)>) {
+//!     for (client, queues) in query.iter() {
+//!         println!("{:?}: {} queued messages", client.0, queues.values().sum::<usize>());
+//!     }
+//! }
+//! ```
+use crate::prelude::{ChannelKind, ClientId};
+use crate::server::networking::is_started;
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::replication::components::ReplicationGroupId;
+use crate::shared::sets::{InternalMainSet, InternalReplicationSet, ServerMarker};
+use bevy::ecs::entity::{EntityHashMap, EntityHashSet};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 
 /// List of entities under the control of a client
+///
+/// The same information is available from the [`ConnectionManager`](crate::server::connection::ConnectionManager)
+/// with [`entities_controlled_by`](crate::server::connection::ConnectionManager::entities_controlled_by) and
+/// [`owner_of`](crate::server::connection::ConnectionManager::owner_of).
 #[derive(Component, Default, Debug, Deref, DerefMut)]
 pub struct ControlledEntities(pub EntityHashSet);
 
+/// Which clients control each entity (see [`ControlledBy`](crate::prelude::server::ControlledBy)),
+/// and which entities each client controls
+#[derive(Default, Debug)]
+pub(crate) struct ControlMap {
+    entities: HashMap<ClientId, EntityHashSet>,
+    controllers: EntityHashMap<Vec<ClientId>>,
+}
+
+impl ControlMap {
+    pub(crate) fn entities(&self, client_id: ClientId) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.get(&client_id).into_iter().flatten().copied()
+    }
+
+    pub(crate) fn controllers(&self, entity: Entity) -> &[ClientId] {
+        self.controllers.get(&entity).map_or(&[], Vec::as_slice)
+    }
+
+    /// Set the clients that control the entity, and return the clients that gained and lost control of it
+    pub(crate) fn set_controllers(
+        &mut self,
+        entity: Entity,
+        controllers: Vec<ClientId>,
+    ) -> (Vec<ClientId>, Vec<ClientId>) {
+        let previous = if controllers.is_empty() {
+            self.controllers.remove(&entity).unwrap_or_default()
+        } else {
+            self.controllers
+                .insert(entity, controllers.clone())
+                .unwrap_or_default()
+        };
+        let gained: Vec<ClientId> = controllers
+            .iter()
+            .filter(|client_id| !previous.contains(client_id))
+            .copied()
+            .collect();
+        let lost: Vec<ClientId> = previous
+            .into_iter()
+            .filter(|client_id| !controllers.contains(client_id))
+            .collect();
+        for client_id in &gained {
+            self.entities.entry(*client_id).or_default().insert(entity);
+        }
+        for client_id in &lost {
+            if let Some(entities) = self.entities.get_mut(client_id) {
+                entities.remove(&entity);
+                if entities.is_empty() {
+                    self.entities.remove(client_id);
+                }
+            }
+        }
+        (gained, lost)
+    }
+
+    /// Forget the entities controlled by a client that disconnected
+    pub(crate) fn remove_client(&mut self, client_id: ClientId) {
+        for entity in self.entities.remove(&client_id).into_iter().flatten() {
+            if let Some(controllers) = self.controllers.get_mut(&entity) {
+                controllers.retain(|id| *id != client_id);
+                if controllers.is_empty() {
+                    self.controllers.remove(&entity);
+                }
+            }
+        }
+    }
+}
+
+/// Identifies the client that a client entity represents
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub struct ConnectedClient(pub ClientId);
+
+/// Number of messages of each channel that are waiting to be sent to the client, or to be acked on reliable channels
+#[derive(Component, Default, Debug, PartialEq, Deref)]
+pub struct SendQueues(pub(crate) HashMap<ChannelKind, usize>);
+
+/// Replication priority accumulated by each replication group for the client.
+///
+/// The priority of a group keeps growing while its messages cannot be sent because of the bandwidth cap,
+/// and is reset when they are sent. The groups that are missing use their base priority.
+#[derive(Component, Default, Debug, PartialEq, Deref, DerefMut)]
+pub struct ReplicationPriorities(pub(crate) HashMap<ReplicationGroupId, f32>);
+
+/// Entities that are currently visible to the client.
+///
+/// Only the entities that use interest management (with
+/// [`VisibilityMode::InterestManagement`](crate::prelude::VisibilityMode::InterestManagement)) are tracked.
+#[derive(Component, Default, Debug, PartialEq, Deref)]
+pub struct VisibleEntities(pub(crate) EntityHashSet);
+
+/// Components inserted on the entity that the server spawns for each connected client
+pub(crate) fn client_entity_bundle(client_id: ClientId) -> impl Bundle {
+    (
+        ConnectedClient(client_id),
+        ControlledEntities::default(),
+        SendQueues::default(),
+        ReplicationPriorities::default(),
+        VisibleEntities::default(),
+    )
+}
+
 pub(crate) struct ClientsMetadataPlugin;
 
 mod systems {
@@ -17,61 +151,102 @@ mod systems {
     use crate::prelude::server::ControlledBy;
     use crate::server::clients::ControlledEntities;
     use crate::server::connection::ConnectionManager;
-    use crate::server::events::DisconnectEvent;
+    use crate::server::events::{ControlGained, ControlLost, DisconnectEvent};
     use crate::shared::replication::network_target::NetworkTarget;
     use tracing::{debug, error, trace};
 
-    // TODO: remove entity from ControlledBy when ControlledBy gets removed! (via observers)?
-    // TODO: remove entity in controlled by lists after the component gets updated
-
+    /// Keep track of the clients that control each entity when [`ControlledBy`] changes, and emit the
+    /// [`ControlGained`] and [`ControlLost`] events
     pub(super) fn handle_controlled_by_update(
-        sender: Res<ConnectionManager>,
-        query: Query<(Entity, &ControlledBy), Changed<ControlledBy>>,
+        mut sender: ResMut<ConnectionManager>,
+        query: Query<(Entity, Ref<ControlledBy>)>,
+        mut removed: RemovedComponents<ControlledBy>,
         mut client_query: Query<&mut ControlledEntities>,
+        mut gained_events: EventWriter<ControlGained>,
+        mut lost_events: EventWriter<ControlLost>,
     ) {
-        let update_controlled_entities =
-            |entity: Entity,
-             client_id: ClientId,
-             client_query: &mut Query<&mut ControlledEntities>,
-             sender: &ConnectionManager| {
-                trace!(
-                    "Adding entity {:?} to client {:?}'s controlled entities",
-                    entity,
-                    client_id
-                );
+        // the clients that just connected can be targeted by the existing entities
+        let new_clients = !sender.new_clients.is_empty();
+        let mut updates: Vec<(Entity, Vec<ClientId>)> = query
+            .iter()
+            .filter(|(_, controlled_by)| new_clients || controlled_by.is_changed())
+            .map(|(entity, controlled_by)| {
+                let controllers = match &controlled_by.target {
+                    NetworkTarget::Single(client_id) => vec![*client_id],
+                    NetworkTarget::Only(client_ids) => client_ids.clone(),
+                    target => sender
+                        .connected_clients()
+                        .filter(|client_id| target.targets(client_id))
+                        .collect(),
+                };
+                // only the connected clients can control entities
+                let controllers = controllers
+                    .into_iter()
+                    .filter(|client_id| sender.connections.contains_key(client_id))
+                    .collect();
+                (entity, controllers)
+            })
+            .collect();
+        // the entities that were despawned or whose ControlledBy was removed lose all their controllers
+        updates.extend(
+            removed
+                .read()
+                .filter(|entity| !query.contains(*entity))
+                .map(|entity| (entity, vec![])),
+        );
+        for (entity, controllers) in updates {
+            let (gained, lost) = sender.control.set_controllers(entity, controllers);
+            for client_id in gained {
+                trace!(?entity, ?client_id, "client gained control of the entity");
                 if let Ok(client_entity) = sender.client_entity(client_id) {
                     if let Ok(mut controlled_entities) = client_query.get_mut(client_entity) {
-                        // first check if it already contains, to not trigger change detection needlessly
-                        if controlled_entities.contains(&entity) {
-                            return;
-                        }
                         controlled_entities.insert(entity);
                     }
                 }
-            };
-
-        for (entity, controlled_by) in query.iter() {
-            match &controlled_by.target {
-                NetworkTarget::None => {}
-                NetworkTarget::Single(client_id) => {
-                    update_controlled_entities(entity, *client_id, &mut client_query, &sender);
+                gained_events.send(ControlGained { entity, client_id });
+            }
+            for client_id in lost {
+                trace!(?entity, ?client_id, "client lost control of the entity");
+                if let Ok(client_entity) = sender.client_entity(client_id) {
+                    if let Ok(mut controlled_entities) = client_query.get_mut(client_entity) {
+                        controlled_entities.remove(&entity);
+                    }
                 }
-                NetworkTarget::Only(client_ids) => client_ids.iter().for_each(|client_id| {
-                    update_controlled_entities(entity, *client_id, &mut client_query, &sender);
-                }),
-                _ => {
-                    let client_ids: Vec<ClientId> = sender.connected_clients().collect();
-                    client_ids.iter().for_each(|client_id| {
-                        update_controlled_entities(entity, *client_id, &mut client_query, &sender);
-                    });
+                lost_events.send(ControlLost { entity, client_id });
+            }
+        }
+    }
+
+    /// Refresh the send queues of each client from its channel senders.
+    ///
+    /// The components are updated in place, and only trigger change detection when their content changed.
+    pub(super) fn update_send_queues(
+        connection_manager: Res<ConnectionManager>,
+        mut client_query: Query<(&ConnectedClient, &mut SendQueues)>,
+    ) {
+        for (client, mut queues) in client_query.iter_mut() {
+            let Ok(connection) = connection_manager.connection(client.0) else {
+                continue;
+            };
+            let mut changed = false;
+            let queues_map = &mut queues.bypass_change_detection().0;
+            for (kind, stats) in connection.message_manager.channel_stats() {
+                if queues_map.get(&kind) != Some(&stats.send_queue_depth) {
+                    queues_map.insert(kind, stats.send_queue_depth);
+                    changed = true;
                 }
             }
+            if changed {
+                queues.set_changed();
+            }
         }
     }
 
     /// When a client disconnect, we despawn all the entities it controlled
+    /// (except the ones that are still controlled by other clients)
     pub(super) fn handle_client_disconnect(
         mut commands: Commands,
+        connection_manager: Option<Res<ConnectionManager>>,
         client_query: Query<&ControlledEntities>,
         mut events: EventReader<DisconnectEvent>,
     ) {
@@ -83,6 +258,16 @@ mod systems {
                     event.client_id
                 );
                 for entity in controlled_entities.iter() {
+                    if connection_manager
+                        .as_ref()
+                        .is_some_and(|manager| manager.owner_of(*entity).is_some())
+                    {
+                        debug!(
+                            "Entity {entity:?} is still controlled by another client than {:?}",
+                            event.client_id
+                        );
+                        continue;
+                    }
                     error!(
                         "Despawning entity {entity:?} controlled by client {:?}",
                         event.client_id
@@ -99,12 +284,229 @@ mod systems {
 impl Plugin for ClientsMetadataPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            PostUpdate,
+            NetworkScheduleConfig::get(app).send,
             systems::handle_controlled_by_update
                 .in_set(InternalReplicationSet::<ServerMarker>::BeforeBuffer),
         );
+        app.add_systems(
+            NetworkScheduleConfig::get(app).send,
+            systems::update_send_queues
+                .after(InternalMainSet::<ServerMarker>::Send)
+                .run_if(is_started),
+        );
         // we handle this in the `Last` `SystemSet` to let the user handle the disconnect event
         // however they want first, before the client entity gets despawned
         app.add_systems(Last, systems::handle_client_disconnect);
     }
 }
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::utils::Duration;
+
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::server::{ControlledBy, Replicate, SyncTarget};
+    use crate::prelude::*;
+    use crate::server::connection::ConnectionManager;
+    use crate::server::events::{ControlGained, ControlLost};
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::prelude::client::ClientCommands;
+    use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
+    use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+    use super::*;
+
+    /// Step a few frames and return the control events emitted on the server
+    fn step(stepper: &mut BevyStepper) -> (Vec<ControlGained>, Vec<ControlLost>) {
+        let mut gained = vec![];
+        let mut lost = vec![];
+        for _ in 0..3 {
+            stepper.frame_step();
+            gained.extend(
+                stepper
+                    .server_app
+                    .world
+                    .resource_mut::<Events<ControlGained>>()
+                    .drain(),
+            );
+            lost.extend(
+                stepper
+                    .server_app
+                    .world
+                    .resource_mut::<Events<ControlLost>>()
+                    .drain(),
+            );
+        }
+        (gained, lost)
+    }
+
+    fn controlled_entities(stepper: &BevyStepper, client_id: ClientId) -> Vec<Entity> {
+        let client_entity = stepper
+            .server_app
+            .world
+            .resource::<ConnectionManager>()
+            .client_entity(client_id)
+            .unwrap();
+        stepper
+            .server_app
+            .world
+            .get::<ControlledEntities>(client_entity)
+            .unwrap()
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_control_gained_and_lost() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper.init();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let entity = stepper
+            .server_app
+            .world
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(client_id),
+                },
+                ..default()
+            })
+            .id();
+        let (gained, lost) = step(&mut stepper);
+        assert_eq!(gained, vec![ControlGained { entity, client_id }]);
+        assert!(lost.is_empty());
+        let connection_manager = stepper.server_app.world.resource::<ConnectionManager>();
+        assert_eq!(connection_manager.owner_of(entity), Some(client_id));
+        assert_eq!(
+            connection_manager
+                .entities_controlled_by(client_id)
+                .collect::<Vec<_>>(),
+            vec![entity]
+        );
+        assert_eq!(controlled_entities(&stepper, client_id), vec![entity]);
+
+        // the client loses control when ControlledBy changes
+        stepper
+            .server_app
+            .world
+            .get_mut::<ControlledBy>(entity)
+            .unwrap()
+            .target = NetworkTarget::None;
+        let (gained, lost) = step(&mut stepper);
+        assert!(gained.is_empty());
+        assert_eq!(lost, vec![ControlLost { entity, client_id }]);
+        let connection_manager = stepper.server_app.world.resource::<ConnectionManager>();
+        assert_eq!(connection_manager.owner_of(entity), None);
+        assert_eq!(
+            connection_manager.entities_controlled_by(client_id).count(),
+            0
+        );
+        assert!(controlled_entities(&stepper, client_id).is_empty());
+
+        // and when the entity is despawned
+        stepper
+            .server_app
+            .world
+            .get_mut::<ControlledBy>(entity)
+            .unwrap()
+            .target = NetworkTarget::All;
+        let (gained, _) = step(&mut stepper);
+        assert_eq!(gained, vec![ControlGained { entity, client_id }]);
+        stepper.server_app.world.despawn(entity);
+        let (_, lost) = step(&mut stepper);
+        assert_eq!(lost, vec![ControlLost { entity, client_id }]);
+        assert_eq!(
+            stepper
+                .server_app
+                .world
+                .resource::<ConnectionManager>()
+                .owner_of(entity),
+            None
+        );
+    }
+
+    /// An entity controlled by several clients is only despawned when its last controller disconnects
+    #[test]
+    fn test_shared_control_disconnect() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+        let controlled_by = ControlledBy {
+            target: NetworkTarget::Only(vec![client_1, client_2]),
+        };
+        let entity = stepper
+            .server_app
+            .world
+            .spawn(Replicate {
+                sync: SyncTarget::controllers(&controlled_by),
+                controlled_by,
+                ..default()
+            })
+            .id();
+        for _ in 0..3 {
+            stepper.frame_step();
+        }
+        let connection_manager = stepper.server_app.world.resource::<ConnectionManager>();
+        assert_eq!(
+            connection_manager
+                .controllers_of(entity)
+                .collect::<Vec<_>>(),
+            vec![client_1, client_2]
+        );
+        // both controllers predict the entity
+        for client_app in [&stepper.client_app_1, &stepper.client_app_2] {
+            let confirmed = *client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(entity)
+                .unwrap();
+            assert!(client_app
+                .world
+                .get::<client::Confirmed>(confirmed)
+                .unwrap()
+                .predicted
+                .is_some());
+        }
+
+        let disconnect =
+            |stepper: &mut MultiBevyStepper, client_app: fn(&mut MultiBevyStepper) -> &mut App| {
+                client_app(stepper)
+                    .world
+                    .run_system_once(|mut commands: Commands| commands.disconnect_client());
+                for _ in 0..10 {
+                    stepper.frame_step();
+                }
+            };
+        disconnect(&mut stepper, |stepper| &mut stepper.client_app_1);
+        assert!(stepper.server_app.world.get_entity(entity).is_some());
+        assert_eq!(
+            stepper
+                .server_app
+                .world
+                .resource::<ConnectionManager>()
+                .owner_of(entity),
+            Some(client_2)
+        );
+        disconnect(&mut stepper, |stepper| &mut stepper.client_app_2);
+        assert!(stepper.server_app.world.get_entity(entity).is_none());
+    }
+}
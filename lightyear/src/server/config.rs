@@ -3,10 +3,20 @@ use bevy::prelude::Resource;
 use governor::Quota;
 use nonzero_ext::nonzero;
 
-use crate::connection::netcode::{Key, PRIVATE_KEY_BYTES};
+use crate::connection::netcode::{Key, ServerQueryInfo, MAX_CLIENTS, PRIVATE_KEY_BYTES};
 use crate::connection::server::NetConfig;
+use crate::packet::compression::PacketCompression;
+use crate::packet::mtu::MtuConfig;
+use crate::packet::pacer::PacingConfig;
+use crate::protocol::component::ProtocolVersion;
+use crate::server::bandwidth_estimate::BandwidthEstimateConfig;
+use crate::server::inspector::InspectorConfig;
+use crate::server::speedhack::SpeedHackConfig;
+use crate::server::stats::ServerStatsConfig;
 use crate::shared::config::SharedConfig;
 use crate::shared::ping::manager::PingConfig;
+use crate::shared::replication::UnknownEntityUpdatePolicy;
+use crate::transport::io::IoDiagnosticsConfig;
 
 #[derive(Clone, Debug)]
 pub struct NetcodeConfig {
@@ -18,6 +28,22 @@ pub struct NetcodeConfig {
     pub client_timeout_secs: i32,
     pub protocol_id: u64,
     pub private_key: Key,
+    /// If set, the server answers the info requests sent by
+    /// [`ServerProbe`](crate::connection::netcode::ServerProbe)s (before connecting) with this custom data,
+    /// along with the protocol version and the number of connected clients.
+    ///
+    /// The data can contain at most [`MAX_SERVER_INFO_DATA`](crate::connection::netcode::MAX_SERVER_INFO_DATA) bytes.
+    pub server_info: Option<Vec<u8>>,
+    /// If set, the server answers the queries (see [`ServerQueryInfo`]) sent by server browsers
+    /// or hosting panels with this information.
+    /// The number of players and the protocol hash (the `protocol_id`) are filled in by the server.
+    pub query_info: Option<ServerQueryInfo>,
+    /// Maximum number of info requests and queries answered per second for each IP address.
+    ///
+    /// The default is 5 requests per second.
+    pub request_rate_limit: u32,
+    /// Version of the protocol, set from the [`ComponentRegistry`](crate::prelude::ComponentRegistry) when the server starts
+    pub(crate) protocol_version: ProtocolVersion,
 }
 
 impl Default for NetcodeConfig {
@@ -28,6 +54,10 @@ impl Default for NetcodeConfig {
             client_timeout_secs: 3,
             protocol_id: 0,
             private_key: [0; PRIVATE_KEY_BYTES],
+            server_info: None,
+            query_info: None,
+            request_rate_limit: 5,
+            protocol_version: 0,
         }
     }
 }
@@ -46,6 +76,27 @@ impl NetcodeConfig {
         self.client_timeout_secs = client_timeout_secs;
         self
     }
+
+    pub fn with_server_info(mut self, data: Vec<u8>) -> Self {
+        self.server_info = Some(data);
+        self
+    }
+
+    /// Answer the server queries with the name and the map of the server
+    pub fn with_query_info(mut self, name: impl Into<String>, map: impl Into<String>) -> Self {
+        self.query_info = Some(ServerQueryInfo {
+            name: name.into(),
+            map: map.into(),
+            max_players: MAX_CLIENTS as u32,
+            ..Default::default()
+        });
+        self
+    }
+
+    pub fn with_request_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.request_rate_limit = requests_per_second;
+        self
+    }
 }
 
 /// Configuration related to sending packets
@@ -55,6 +106,17 @@ pub struct PacketConfig {
     pub per_client_send_bandwidth_cap: Quota,
     /// If false, there is no bandwidth cap and all messages are sent as soon as possible
     pub bandwidth_cap_enabled: bool,
+    /// If set, the packets sent to each client are paced so that they are spread over time
+    /// instead of being sent in a single burst
+    pub send_pacing: Option<PacingConfig>,
+    /// If set, and if the transport supports it (UDP), the maximum packet size that can reach
+    /// each client is discovered by sending probes. Otherwise a fixed packet size is used.
+    pub mtu_discovery: Option<MtuConfig>,
+    /// Algorithm used to compress the packets after they are assembled.
+    ///
+    /// The packets are only compressed once the remote announced that it supports the algorithm,
+    /// and channels can opt out with [`ChannelSettings::compress`](crate::prelude::ChannelSettings::compress).
+    pub compression: PacketCompression,
 }
 
 impl Default for PacketConfig {
@@ -63,6 +125,9 @@ impl Default for PacketConfig {
             // 56 KB/s bandwidth cap
             per_client_send_bandwidth_cap: Quota::per_second(nonzero!(56000u32)),
             bandwidth_cap_enabled: false,
+            send_pacing: None,
+            mtu_discovery: None,
+            compression: PacketCompression::None,
         }
     }
 }
@@ -83,6 +148,94 @@ impl PacketConfig {
         self.bandwidth_cap_enabled = true;
         self
     }
+
+    /// Pace the packets sent to each client so that at most `bytes_per_second` are sent per second
+    pub fn with_send_pacing(mut self, bytes_per_second: u32) -> Self {
+        self.send_pacing = Some(PacingConfig::new(bytes_per_second));
+        self
+    }
+
+    /// Enable path MTU discovery, so that the packet size adapts to the network path
+    pub fn with_mtu_discovery(mut self, mtu_config: MtuConfig) -> Self {
+        self.mtu_discovery = Some(mtu_config);
+        self
+    }
+
+    /// Compress the packets with the given algorithm, if the remote supports it
+    pub fn with_compression(mut self, compression: PacketCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+}
+
+/// Configuration related to replication
+#[derive(Clone, Debug)]
+pub struct ReplicationConfig {
+    /// If set, when at least this many entities are despawned during the same frame, their despawns
+    /// are sent to each client in a single compact message instead of one message per entity.
+    ///
+    /// Only the entities that are the only entity of their [`ReplicationGroup`](crate::prelude::ReplicationGroup)
+    /// (the default) are batched.
+    pub despawn_batch_threshold: Option<usize>,
+    /// What to do with the component updates received from a client for an entity that doesn't exist
+    /// ([`UnknownEntityUpdatePolicy::RequestResend`] behaves like [`UnknownEntityUpdatePolicy::Drop`])
+    pub unknown_entity_updates: UnknownEntityUpdatePolicy,
+    /// How the replicated world is sent to a client that connects while entities are already replicated
+    pub late_join: LateJoinReplication,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            despawn_batch_threshold: Some(16),
+            unknown_entity_updates: UnknownEntityUpdatePolicy::default(),
+            late_join: LateJoinReplication::default(),
+        }
+    }
+}
+
+/// How the replicated world is sent to a client that connects while entities are already replicated
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LateJoinReplication {
+    /// Each replication group is sent in its own message, like the entities that are spawned after the client
+    /// connected. With a bandwidth cap, the groups can arrive over many ticks.
+    #[default]
+    PerGroup,
+    /// The state of all the entities replicated to the client is sent in a single snapshot, compressed with
+    /// the given algorithm, on the
+    /// [`WorldSnapshotChannel`](crate::channel::builder::WorldSnapshotChannel).
+    ///
+    /// The snapshot is streamed to the client at a capped rate, and its progress is reported with
+    /// [`TransferProgressEvent`](crate::prelude::client::TransferProgressEvent)s. The client applies the whole
+    /// snapshot during the same frame, so all the entities start being predicted or interpolated together.
+    ///
+    /// The connection is completed once the client announced the algorithms that it can decompress. If it
+    /// cannot decompress the snapshot, the world is sent to it like with [`LateJoinReplication::PerGroup`].
+    Snapshot { compression: PacketCompression },
+}
+
+impl ReplicationConfig {
+    pub fn with_despawn_batch_threshold(mut self, threshold: usize) -> Self {
+        self.despawn_batch_threshold = Some(threshold);
+        self
+    }
+
+    /// Always send one despawn message per entity
+    pub fn without_despawn_batching(mut self) -> Self {
+        self.despawn_batch_threshold = None;
+        self
+    }
+
+    pub fn with_unknown_entity_updates(mut self, policy: UnknownEntityUpdatePolicy) -> Self {
+        self.unknown_entity_updates = policy;
+        self
+    }
+
+    /// Send the replicated world to the clients that connect late in a single compressed snapshot
+    pub fn with_late_join_snapshot(mut self, compression: PacketCompression) -> Self {
+        self.late_join = LateJoinReplication::Snapshot { compression };
+        self
+    }
 }
 
 /// Configuration for the server plugin
@@ -94,4 +247,25 @@ pub struct ServerConfig {
     pub net: Vec<NetConfig>,
     pub packet: PacketConfig,
     pub ping: PingConfig,
+    pub replication: ReplicationConfig,
+    /// Debugging options for the packets sent and received (requires the `pcap` feature)
+    pub io_diagnostics: IoDiagnosticsConfig,
+    /// If set, the server detects the clients whose clock runs faster than the server's clock
+    pub speed_hack: Option<SpeedHackConfig>,
+    /// If set, the server computes aggregate [`ServerStats`](crate::server::stats::ServerStats) and replicates them
+    /// to the target clients (for example an admin dashboard)
+    pub stats: Option<ServerStatsConfig>,
+    /// If set, the clients that present the admin token can inspect the server world with the
+    /// [inspector](crate::server::inspector)
+    pub inspector: Option<InspectorConfig>,
+    /// If set, the server records the bytes sent to each client in the
+    /// [`BandwidthEstimate`](crate::server::bandwidth_estimate::BandwidthEstimate) resource
+    pub bandwidth_estimate: Option<BandwidthEstimateConfig>,
+    /// If true, the clients are only considered connected once they announced the hash of their
+    /// [registries](crate::protocol::registries_hash), and the clients whose hash is different from the server's
+    /// are refused with [`DeniedReason::ProtocolMismatch`](crate::protocol::DeniedReason::ProtocolMismatch).
+    ///
+    /// The clients that announce an older protocol version (if some components have migrations) are not checked,
+    /// since their protocol is expected to be different.
+    pub verify_protocol_hash: bool,
 }
@@ -1,22 +1,30 @@
 //! Specify how a Server sends/receives messages with a Client
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::entity::{EntityHash, MapEntities};
 use bevy::prelude::{Component, Entity, Mut, Resource, World};
-use bevy::utils::{HashMap, HashSet};
+use bevy::utils::{Duration, HashMap, HashSet};
 use bytes::Bytes;
+use crossbeam_channel::Receiver;
 use hashbrown::hash_map::Entry;
 use serde::Serialize;
 use tracing::{debug, error, info, trace, trace_span, warn};
 
-use crate::channel::builder::{EntityUpdatesChannel, PingChannel};
+use crate::channel::builder::{
+    ChannelSettings, ChannelSyncChannel, EntityActionsChannel, EntityUpdatesChannel, PingChannel,
+};
 use bitcode::encoding::Fixed;
 
 use crate::channel::senders::ChannelSend;
+use crate::channel::stream::TransferId;
 use crate::client::message::ClientMessage;
 use crate::connection::id::ClientId;
 use crate::inputs::native::input_buffer::InputBuffer;
-use crate::packet::message_manager::MessageManager;
+use crate::packet::compression::{PacketCompression, PacketCompressor, SupportedCompressions};
+use crate::packet::message::{MessageHandle, MessageId};
+use crate::packet::message_manager::{MessageManager, DEFAULT_MESSAGE_PRIORITY};
+use crate::packet::mtu::PacketSizing;
+use crate::packet::pacer::PacketPacer;
 use crate::packet::packet::Packet;
 use crate::packet::packet_manager::{Payload, PACKET_BUFFER_CAPACITY};
 use crate::prelude::server::{DisconnectEvent, RoomId, RoomManager};
@@ -24,20 +32,26 @@ use crate::prelude::{
     Channel, ChannelKind, Message, Mode, PreSpawnedPlayerObject, ReplicationGroup,
     ShouldBePredicted, TargetEntity,
 };
-use crate::protocol::channel::ChannelRegistry;
-use crate::protocol::component::{ComponentNetId, ComponentRegistry};
+use crate::protocol::channel::{
+    ChannelId, ChannelRegistration, ChannelRegistry, MAX_PENDING_CHANNEL_MESSAGES,
+};
+use crate::protocol::component::{
+    ComponentKind, ComponentNetId, ComponentRegistry, ProtocolVersion,
+};
 use crate::protocol::message::{MessageRegistry, MessageType};
 use crate::protocol::registry::NetId;
-use crate::protocol::BitSerializable;
+use crate::protocol::{BitSerializable, DeniedReason};
 use crate::serialize::bitcode::reader::BufferPool;
 use crate::serialize::bitcode::writer::BitcodeWriter;
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::serialize::RawData;
-use crate::server::config::PacketConfig;
+use crate::server::clients::ControlMap;
+use crate::server::config::{LateJoinReplication, PacketConfig, ReplicationConfig};
 use crate::server::events::{ConnectEvent, ServerEvents};
-use crate::server::message::ServerMessage;
-use crate::server::replication::send::ReplicateCache;
+use crate::server::message::{ComponentCorrection, ServerMessage};
+use crate::server::protocols::ProtocolRegistries;
+use crate::server::replication::send::{GroupSendTimer, ReplicateCache};
 use crate::shared::events::connection::ConnectionEvents;
 use crate::shared::message::MessageSend;
 use crate::shared::ping::manager::{PingConfig, PingManager};
@@ -48,20 +62,68 @@ use crate::shared::replication::components::{
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
+use crate::shared::replication::{
+    DespawnReason, UnknownEntityUpdatePolicy, UnknownEntityUpdateStats,
+};
 use crate::shared::replication::{ReplicationMessage, ReplicationReceive, ReplicationSend};
 use crate::shared::replication::{ReplicationMessageData, ReplicationPeer};
 use crate::shared::sets::ServerMarker;
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
+#[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+use crate::transport::pcap::PacketCapture;
 
 type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
 
+/// Handle to a [`NetworkTarget`] that was registered with [`ConnectionManager::register_target`].
+///
+/// The list of clients matching the target is cached and kept up-to-date when clients connect or disconnect,
+/// so that sending a message to the handle doesn't need to resolve the target again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetHandle(u32);
+
+/// A message serialized with the registries of each protocol that contains it
+struct SerializedMessage<T = RawData> {
+    main: Option<T>,
+    protocols: HashMap<usize, T>,
+}
+
+impl<T> SerializedMessage<T> {
+    fn get(&self, protocol: Option<usize>) -> Option<&T> {
+        match protocol {
+            Some(index) => self.protocols.get(&index),
+            None => self.main.as_ref(),
+        }
+    }
+}
+
+/// Message that is encoded once and shared by all the connections that it is sent to
+struct SharedMessage {
+    /// The serialized message, which is needed if the channel is not available yet on the connection
+    raw: RawData,
+    /// The encoded [`ServerMessage`]
+    encoded: Bytes,
+}
+
+#[derive(Debug)]
+struct RegisteredTarget {
+    target: NetworkTarget,
+    /// Connected clients that match the target
+    clients: Vec<ClientId>,
+}
+
+/// Wrapper that handles the connections with all the clients
+///
+/// The resource is only inserted when the server is started, so systems that can run before
+/// that should use `Option<Res<ConnectionManager>>`.
 #[derive(Resource)]
 pub struct ConnectionManager {
     pub(crate) connections: HashMap<ClientId, Connection>,
     pub(crate) message_registry: MessageRegistry,
     channel_registry: ChannelRegistry,
+    /// The additional protocols, keyed by the index of the transport that their clients connect through
+    pub(crate) protocols: HashMap<usize, ProtocolRegistries>,
     pub(crate) events: ServerEvents,
 
     // NOTE: we put this here because we only need one per world, not one per connection
@@ -72,10 +134,38 @@ pub struct ConnectionManager {
     // list of clients that connected since the last time we sent replication messages
     // (we want to keep track of them because we need to replicate the entire world state to them)
     pub(crate) new_clients: Vec<ClientId>,
+    /// Clients that asked to receive each entity again (its spawn and the current value of its components),
+    /// since the last time we sent replication messages
+    pub(crate) resend_requests: EntityHashMap<Entity, Vec<ClientId>>,
+    /// Clients that receive the replication stream of another client (spectator -> spectated client)
+    pub(crate) spectators: HashMap<ClientId, ClientId>,
+    /// Clients that stopped spectating since the last time the visibility was updated
+    pub(crate) stopped_spectators: Vec<ClientId>,
     pub(crate) writer: BitcodeWriter,
     pub(crate) reader_pool: BufferPool,
     packet_config: PacketConfig,
     ping_config: PingConfig,
+    replication_config: ReplicationConfig,
+    registered_targets: HashMap<TargetHandle, RegisteredTarget>,
+    next_target_handle: u32,
+    /// If true, the clients are only considered connected once they announced their protocol version,
+    /// so that the components can be migrated to it
+    pub(crate) wait_for_protocol_version: bool,
+    /// Hash of the registered components, messages and channels.
+    /// If set, the clients are only considered connected once they announced the same hash,
+    /// and the clients that announce a different hash are disconnected
+    pub(crate) protocol_hash: Option<u64>,
+    /// Version of the protocol of the server
+    pub(crate) protocol_version: ProtocolVersion,
+    /// Writes the packets sent to and received from all clients to a file, if packet capture is enabled
+    #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+    pub(crate) packet_capture: Option<PacketCapture>,
+    /// Timers of the replication groups that have their own send interval
+    pub(crate) group_send_timers: HashMap<ReplicationGroupId, GroupSendTimer>,
+    /// Components whose current value must be sent during the next replication update, even if they didn't change
+    forced_updates: HashMap<ComponentKind, EntityHashMap<Entity, NetworkTarget>>,
+    /// Clients that control each entity
+    pub(crate) control: ControlMap,
 }
 
 impl ConnectionManager {
@@ -84,31 +174,157 @@ impl ConnectionManager {
         channel_registry: ChannelRegistry,
         packet_config: PacketConfig,
         ping_config: PingConfig,
+        replication_config: ReplicationConfig,
     ) -> Self {
         Self {
             connections: HashMap::default(),
             message_registry,
             channel_registry,
+            protocols: HashMap::default(),
             events: ServerEvents::new(),
             replicate_component_cache: EntityHashMap::default(),
             new_clients: vec![],
+            resend_requests: EntityHashMap::default(),
+            spectators: HashMap::default(),
+            stopped_spectators: vec![],
             writer: BitcodeWriter::with_capacity(PACKET_BUFFER_CAPACITY),
             reader_pool: BufferPool::new(1),
             packet_config,
             ping_config,
+            replication_config,
+            registered_targets: HashMap::default(),
+            next_target_handle: 0,
+            wait_for_protocol_version: false,
+            protocol_hash: None,
+            protocol_version: ProtocolVersion::default(),
+            #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+            packet_capture: None,
+            group_send_timers: HashMap::default(),
+            forced_updates: HashMap::default(),
+            control: ControlMap::default(),
         }
     }
 
+    /// Clients that asked to receive the entity again, because they received updates for it without having it
+    pub(crate) fn resend_target(&self, entity: Entity) -> NetworkTarget {
+        self.resend_requests
+            .get(&entity)
+            .map_or(NetworkTarget::None, |clients| {
+                NetworkTarget::from(clients.clone())
+            })
+    }
+
+    /// Returns true if the updates of the group should be sent at this run, along with the system tick
+    /// of the previous run where they were sent (if the group has its own send interval)
+    pub(crate) fn group_send_status(
+        &self,
+        group_id: ReplicationGroupId,
+    ) -> (bool, Option<BevyTick>) {
+        self.group_send_timers
+            .get(&group_id)
+            .map_or((true, None), GroupSendTimer::status)
+    }
+
     /// Return the [`Entity`] associated with the given [`ClientId`]
     pub fn client_entity(&self, client_id: ClientId) -> Result<Entity> {
         self.connection(client_id).map(|c| c.entity)
     }
 
+    /// Entities controlled by the client (see [`ControlledBy`](crate::prelude::server::ControlledBy))
+    pub fn entities_controlled_by(&self, client_id: ClientId) -> impl Iterator<Item = Entity> + '_ {
+        self.control.entities(client_id)
+    }
+
+    /// The client that controls the entity.
+    ///
+    /// If several clients control the entity, this is the first one (see [`Self::controllers_of`])
+    pub fn owner_of(&self, entity: Entity) -> Option<ClientId> {
+        self.control.controllers(entity).first().copied()
+    }
+
+    /// All the clients that control the entity
+    pub fn controllers_of(&self, entity: Entity) -> impl Iterator<Item = ClientId> + '_ {
+        self.control.controllers(entity).iter().copied()
+    }
+
     /// Return the list of connected [`ClientId`]s
     pub fn connected_clients(&self) -> impl Iterator<Item = ClientId> + '_ {
         self.connections.keys().copied()
     }
 
+    /// Number of component updates received from the client for entities that didn't exist locally
+    pub fn unknown_entity_update_stats(
+        &self,
+        client_id: ClientId,
+    ) -> Option<UnknownEntityUpdateStats> {
+        self.connections
+            .get(&client_id)
+            .map(|connection| connection.replication_receiver.unknown_entity_stats)
+    }
+
+    /// Algorithm used to compress the packets sent to the client.
+    ///
+    /// This is [`PacketCompression::None`] until the client announced that it supports the configured algorithm.
+    pub fn packet_compression(&self, client_id: ClientId) -> Option<PacketCompression> {
+        self.connections
+            .get(&client_id)
+            .map(|connection| connection.message_manager.compression())
+    }
+
+    /// Version of the protocol announced by the client when it connected
+    pub fn client_protocol_version(&self, client_id: ClientId) -> Option<ProtocolVersion> {
+        self.connections
+            .get(&client_id)
+            .and_then(|connection| connection.protocol_version)
+    }
+
+    /// Returns true if the client registered the dynamic component with this type path, in which case
+    /// the component is replicated to it
+    pub fn has_dynamic_component(&self, client_id: ClientId, type_path: &str) -> bool {
+        self.connections.get(&client_id).is_some_and(|connection| {
+            connection
+                .dynamic_components
+                .values()
+                .any(|path| path == type_path)
+        })
+    }
+
+    /// Returns true if the client knows the network id of the channel `C`, which is the case for all
+    /// the channels except the ones registered at runtime that the client didn't acknowledge yet
+    pub fn is_channel_registered<C: Channel>(&self, client_id: ClientId) -> bool {
+        let kind = ChannelKind::of::<C>();
+        self.connections.get(&client_id).is_some_and(|connection| {
+            connection.message_manager.channels.contains_key(&kind)
+                && !connection.pending_channels.contains_key(&kind)
+        })
+    }
+
+    /// Register a channel while the server is running, and announce its network id to the clients
+    pub(crate) fn register_channel<C: Channel>(&mut self, settings: ChannelSettings) {
+        let kind = self
+            .channel_registry
+            .add_runtime_channel::<C>(settings, true);
+        let net_id = *self.channel_registry.get_net_from_kind(&kind).unwrap();
+        // the clients of the additional protocols don't use the channels of the main protocol
+        for connection in self
+            .connections
+            .values_mut()
+            .filter(|connection| connection.protocol.is_none())
+        {
+            if connection.message_manager.channels.contains_key(&kind) {
+                continue;
+            }
+            connection.message_manager.channel_registry = self.channel_registry.clone();
+            if let Err(e) = connection
+                .message_manager
+                .add_channel(kind)
+                .and_then(|_| connection.announce_channel(kind, net_id))
+            {
+                error!("could not register channel {}: {e:?}", C::name());
+            }
+        }
+    }
+
     /// Queues up a message to be sent to all clients matching the specific [`NetworkTarget`]
     pub fn send_message_to_target<C: Channel, M: Message>(
         &mut self,
@@ -118,6 +334,106 @@ impl ConnectionManager {
         self.erased_send_message_to_target(message, ChannelKind::of::<C>(), target)
     }
 
+    /// Queues up a message to be sent to all clients except the ones in `excluded`
+    ///
+    /// Contrary to [`NetworkTarget::AllExcept`], this doesn't need to allocate a list of clients,
+    /// and the serialized message is shared by all the clients instead of being copied for each client.
+    pub fn send_message_to_all_except<C: Channel, M: Message>(
+        &mut self,
+        message: &M,
+        excluded: &[ClientId],
+    ) -> Result<()> {
+        let shared = self.serialize_shared_message(message)?;
+        self.connections
+            .iter_mut()
+            .filter(|(id, _)| !excluded.contains(id))
+            .try_for_each(|(_, c)| match shared.get(c.protocol) {
+                Some(message) => c.buffer_shared_message(message, ChannelKind::of::<C>()),
+                None => Ok(()),
+            })
+    }
+
+    /// Register a [`NetworkTarget`] that will be re-used to send messages.
+    ///
+    /// The clients matching the target are computed only once (and updated when clients connect or disconnect)
+    /// instead of every time a message is sent, which is useful for messages that are broadcast every tick.
+    /// The handle stays valid when the server is restarted.
+    pub fn register_target(&mut self, target: NetworkTarget) -> TargetHandle {
+        let handle = TargetHandle(self.next_target_handle);
+        self.next_target_handle += 1;
+        let clients = self
+            .connections
+            .keys()
+            .filter(|id| target.targets(id))
+            .copied()
+            .collect();
+        self.registered_targets
+            .insert(handle, RegisteredTarget { target, clients });
+        handle
+    }
+
+    /// Update the [`NetworkTarget`] associated with a registered handle
+    pub fn update_target(&mut self, handle: TargetHandle, target: NetworkTarget) -> Result<()> {
+        let registered = self
+            .registered_targets
+            .get_mut(&handle)
+            .context("target handle not found")?;
+        registered.clients.clear();
+        registered.clients.extend(
+            self.connections
+                .keys()
+                .filter(|id| target.targets(id))
+                .copied(),
+        );
+        registered.target = target;
+        Ok(())
+    }
+
+    /// Keep the targets that were registered on the connection manager of the previous server start,
+    /// so that their [`TargetHandle`]s stay valid when the server is restarted
+    pub(crate) fn keep_registered_targets(&mut self, previous: &mut ConnectionManager) {
+        self.next_target_handle = previous.next_target_handle;
+        self.registered_targets = std::mem::take(&mut previous.registered_targets);
+        // the clients of the previous server start are not connected anymore
+        for registered in self.registered_targets.values_mut() {
+            registered.clients.clear();
+        }
+    }
+
+    /// Remove a target that was registered with [`ConnectionManager::register_target`]
+    pub fn unregister_target(&mut self, handle: TargetHandle) {
+        self.registered_targets.remove(&handle);
+    }
+
+    /// Queues up a message to be sent to all clients matching a registered target
+    ///
+    /// The serialized message is shared by all the clients instead of being copied for each client.
+    pub fn send_message_to_handle<C: Channel, M: Message>(
+        &mut self,
+        message: &M,
+        handle: TargetHandle,
+    ) -> Result<()> {
+        let registered = self
+            .registered_targets
+            .get(&handle)
+            .context("target handle not found")?;
+        if registered.clients.is_empty() {
+            return Ok(());
+        }
+        let shared = self.serialize_shared_message(message)?;
+        let registered = &self.registered_targets[&handle];
+        registered.clients.iter().try_for_each(|client_id| {
+            let connection = self
+                .connections
+                .get_mut(client_id)
+                .context("client id not found")?;
+            match shared.get(connection.protocol) {
+                Some(message) => connection.buffer_shared_message(message, ChannelKind::of::<C>()),
+                None => Ok(()),
+            }
+        })
+    }
+
     /// Send a message to all clients in a room
     pub fn send_message_to_room<C: Channel, M: Message>(
         &mut self,
@@ -139,6 +455,113 @@ impl ConnectionManager {
         self.send_message_to_target::<C, M>(message, NetworkTarget::Only(vec![client_id]))
     }
 
+    /// Queues up a message to be sent to a client on the reliable channel `C`.
+    ///
+    /// If the message is not acked within `ttl`, it stops being retransmitted and a
+    /// [`MessageExpiredEvent`](crate::server::events::MessageExpiredEvent) is emitted.
+    pub fn send_message_with_ttl<C: Channel, M: Message>(
+        &mut self,
+        client_id: ClientId,
+        message: &M,
+        ttl: Duration,
+    ) -> Result<()> {
+        self.send_message_to_target_with_ttl::<C, M>(
+            message,
+            NetworkTarget::Only(vec![client_id]),
+            ttl,
+        )
+    }
+
+    /// Queues up a message to be sent to all the clients in `target` on the reliable channel `C`,
+    /// which stops being retransmitted if it is not acked within `ttl`
+    pub fn send_message_to_target_with_ttl<C: Channel, M: Message>(
+        &mut self,
+        message: &M,
+        target: NetworkTarget,
+        ttl: Duration,
+    ) -> Result<()> {
+        self.buffer_serialized_message(
+            message,
+            |id| target.targets(id),
+            |c, message_bytes| {
+                c.buffer_message_with_ttl(message_bytes, ChannelKind::of::<C>(), Some(ttl))
+                    .map(|_| ())
+            },
+        )
+    }
+
+    /// Queues up a message to be sent to a client, and get a [`MessageHandle`] that can be used to
+    /// know when the client received the message.
+    ///
+    /// The channel `C` must track acks (reliable channels or [`ChannelMode::UnorderedUnreliableWithAcks`](crate::channel::builder::ChannelMode::UnorderedUnreliableWithAcks)).
+    /// A [`MessageAckedEvent`](crate::server::events::MessageAckedEvent) is emitted when the message is acked,
+    /// or you can poll [`is_message_acked`](Self::is_message_acked).
+    pub fn send_message_with_handle<C: Channel, M: Message>(
+        &mut self,
+        client_id: ClientId,
+        message: &M,
+    ) -> Result<MessageHandle> {
+        let channel = ChannelKind::of::<C>();
+        let serialized = self.serialize_message(message)?;
+        let connection = self.connection_mut(client_id)?;
+        let message_bytes = serialized
+            .get(connection.protocol)
+            .context("the message is not part of the protocol of the client")?
+            .clone();
+        connection.message_manager.watch_acks(channel)?;
+        if connection.pending_channels.contains_key(&channel) {
+            return Err(anyhow!("the channel is not registered on the client yet"));
+        }
+        let message_id = connection
+            .buffer_message_with_ttl(message_bytes, channel, None)?
+            .context("the channel did not assign a message id")?;
+        let handle = MessageHandle {
+            channel,
+            message_id,
+        };
+        connection.message_manager.track_ack(handle);
+        Ok(handle)
+    }
+
+    /// Returns true if the client received the message sent with [`send_message_with_handle`](Self::send_message_with_handle)
+    pub fn is_message_acked(&self, client_id: ClientId, handle: MessageHandle) -> bool {
+        self.connections
+            .get(&client_id)
+            .is_some_and(|connection| connection.message_manager.is_acked(handle))
+    }
+
+    /// Cancel a message sent with [`send_message_with_handle`](Self::send_message_with_handle), so that
+    /// it is not retransmitted anymore.
+    ///
+    /// Returns true if the message was cancelled, and false if it was already acked.
+    /// The message might still be received by the client if it was sent before being cancelled.
+    pub fn cancel_message(&mut self, client_id: ClientId, handle: MessageHandle) -> Result<bool> {
+        self.connection_mut(client_id)?
+            .message_manager
+            .cancel_message(handle)
+    }
+
+    /// Abort a transfer that is being sent to a client on the
+    /// [`ChannelMode::Stream`](crate::channel::builder::ChannelMode::Stream) channel `C`
+    ///
+    /// The id of the transfer is provided by the [`TransferProgressEvent`](crate::server::events::TransferProgressEvent)s
+    pub fn abort_transfer<C: Channel>(
+        &mut self,
+        client_id: ClientId,
+        transfer_id: TransferId,
+    ) -> Result<()> {
+        self.connection_mut(client_id)?
+            .message_manager
+            .abort_transfer(ChannelKind::of::<C>(), Some(transfer_id))
+    }
+
+    /// Abort all the transfers that are being sent to a client on the stream channel `C`
+    pub fn abort_transfers<C: Channel>(&mut self, client_id: ClientId) -> Result<()> {
+        self.connection_mut(client_id)?
+            .message_manager
+            .abort_transfer(ChannelKind::of::<C>(), None)
+    }
+
     /// Update the priority of a `ReplicationGroup` that is replicated to a given client
     pub fn update_priority(
         &mut self,
@@ -158,12 +581,44 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Send the current value of the component `C` of the entity to the clients of the `target` during the next
+    /// replication update, even if the component didn't change.
+    ///
+    /// This can be used to resynchronize a client that reports a desync, without having to mutate the component.
+    /// Only the clients that have the entity and are targeted by its replication receive the update.
+    pub fn force_update<C: Component>(&mut self, entity: Entity, target: NetworkTarget) {
+        self.forced_updates
+            .entry(ComponentKind::of::<C>())
+            .or_default()
+            .entry(entity)
+            .or_insert(NetworkTarget::None)
+            .union(&target);
+    }
+
+    /// Take the forced updates of the component `C`
+    pub(crate) fn take_forced_updates<C: Component>(
+        &mut self,
+    ) -> EntityHashMap<Entity, NetworkTarget> {
+        self.forced_updates
+            .remove(&ComponentKind::of::<C>())
+            .unwrap_or_default()
+    }
+
     /// Find the list of clients that should receive the replication message
     pub(crate) fn apply_replication(
         &mut self,
         target: NetworkTarget,
     ) -> Box<dyn Iterator<Item = ClientId>> {
-        let connected_clients = self.connections.keys().copied().collect::<Vec<_>>();
+        // the clients that didn't complete the handshake yet cannot receive replication messages,
+        // and the entities are not replicated to the clients of the additional protocols
+        let connected_clients = self
+            .connections
+            .iter()
+            .filter(|(_, connection)| {
+                !connection.awaiting_handshake && connection.protocol.is_none()
+            })
+            .map(|(client_id, _)| *client_id)
+            .collect::<Vec<_>>();
         match target {
             NetworkTarget::All => {
                 // TODO: maybe only send stuff when the client is time-synced ?
@@ -183,7 +638,7 @@ impl ConnectionManager {
                 )
             }
             NetworkTarget::Single(client_id) => {
-                if self.connections.contains_key(&client_id) {
+                if connected_clients.contains(&client_id) {
                     Box::new(std::iter::once(client_id))
                 } else {
                     Box::new(std::iter::empty())
@@ -198,6 +653,53 @@ impl ConnectionManager {
         }
     }
 
+    /// Split the insert and update targets of the component `C` into groups of clients that receive the
+    /// same representation of the component, depending on the version of the protocol that they use.
+    ///
+    /// Returns the version of the migration applied to each group (None if the component is not migrated)
+    pub(crate) fn split_by_protocol_version<C: Component>(
+        &self,
+        registry: &ComponentRegistry,
+        insert_target: NetworkTarget,
+        update_target: NetworkTarget,
+    ) -> Vec<(Option<ProtocolVersion>, NetworkTarget, NetworkTarget)> {
+        if !registry.has_migrations() {
+            return vec![(None, insert_target, update_target)];
+        }
+        let mut groups: Vec<(Option<ProtocolVersion>, Vec<ClientId>, Vec<ClientId>)> = vec![];
+        for (client_id, connection) in self.connections.iter() {
+            let insert = insert_target.targets(client_id);
+            let update = update_target.targets(client_id);
+            if !insert && !update {
+                continue;
+            }
+            let version = registry.migration_version::<C>(connection.protocol_version);
+            let index = groups
+                .iter()
+                .position(|(v, _, _)| *v == version)
+                .unwrap_or_else(|| {
+                    groups.push((version, vec![], vec![]));
+                    groups.len() - 1
+                });
+            if insert {
+                groups[index].1.push(*client_id);
+            }
+            if update {
+                groups[index].2.push(*client_id);
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(version, insert, update)| {
+                (
+                    version,
+                    NetworkTarget::from(insert),
+                    NetworkTarget::from(update),
+                )
+            })
+            .collect()
+    }
+
     pub(crate) fn connection(&self, client_id: ClientId) -> Result<&Connection> {
         self.connections
             .get(&client_id)
@@ -217,25 +719,67 @@ impl ConnectionManager {
     }
 
     /// Add a new [`Connection`] to the list of connections with the given [`ClientId`]
-    pub(crate) fn add(&mut self, client_id: ClientId, client_entity: Entity) {
+    ///
+    /// `packet_sizing` depends on the transport that the client used to connect, and `protocol` is the
+    /// additional protocol bound to that transport (None for the main protocol)
+    pub(crate) fn add(
+        &mut self,
+        client_id: ClientId,
+        client_entity: Entity,
+        packet_sizing: PacketSizing,
+        protocol: Option<usize>,
+    ) {
         if let Entry::Vacant(e) = self.connections.entry(client_id) {
             #[cfg(feature = "metrics")]
             metrics::gauge!("connected_clients").increment(1.0);
 
             info!("New connection from id: {}", client_id);
-            let connection = Connection::new(
+            let channel_registry = match protocol.and_then(|index| self.protocols.get(&index)) {
+                Some(registries) => &registries.channel_registry,
+                None => &self.channel_registry,
+            };
+            let mut connection = Connection::new(
                 client_id,
                 client_entity,
-                &self.channel_registry,
+                channel_registry,
                 self.packet_config.clone(),
                 self.ping_config.clone(),
+                &self.replication_config,
+                packet_sizing,
             );
-            self.events.add_connect_event(ConnectEvent {
-                client_id,
-                entity: client_entity,
-            });
-            self.new_clients.push(client_id);
+            connection.protocol = protocol;
+            // the client needs to learn the network id of the channels that were registered at runtime
+            if protocol.is_none() {
+                for (kind, net_id) in self.channel_registry.runtime_channels() {
+                    if let Err(e) = connection.announce_channel(kind, net_id) {
+                        error!("could not announce channel to client {client_id}: {e:?}");
+                    }
+                }
+            }
+            // the clients of the additional protocols don't announce their protocol version
+            if (self.wait_for_protocol_version && protocol.is_none())
+                || self.protocol_hash.is_some()
+                || (connection.pending_snapshot_compression.is_some() && protocol.is_none())
+            {
+                // the connection is completed once the client announced its protocol version
+                // and its supported compressions, and its protocol hash was verified
+                connection.awaiting_handshake = true;
+            } else {
+                self.events.add_connect_event(ConnectEvent {
+                    client_id,
+                    entity: client_entity,
+                });
+                // the world is not replicated to the clients of the additional protocols
+                if protocol.is_none() {
+                    connection.replication_sender.request_snapshot();
+                    self.new_clients.push(client_id);
+                }
+            }
             e.insert(connection);
+            self.registered_targets
+                .values_mut()
+                .filter(|registered| registered.target.targets(&client_id))
+                .for_each(|registered| registered.clients.push(client_id));
         } else {
             info!("Client {} was already in the connections list", client_id);
         }
@@ -254,18 +798,33 @@ impl ConnectionManager {
         self.events
             .add_disconnect_event(DisconnectEvent { client_id, entity });
         self.connections.remove(&client_id);
+        self.control.remove_client(client_id);
+        self.registered_targets
+            .values_mut()
+            .for_each(|registered| registered.clients.retain(|id| *id != client_id));
+        self.spectators.remove(&client_id);
+        let spectators = self
+            .spectators
+            .iter()
+            .filter_map(|(spectator, spectated)| (*spectated == client_id).then_some(*spectator))
+            .collect::<Vec<_>>();
+        for spectator in spectators {
+            self.stop_spectating(spectator);
+        }
         entity
     }
 
+    /// Buffer a message serialized with the registries of `protocol` to the clients in `target` that use that protocol
     pub(crate) fn buffer_message(
         &mut self,
         message: RawData,
         channel: ChannelKind,
         target: NetworkTarget,
+        protocol: Option<usize>,
     ) -> Result<()> {
         self.connections
             .iter_mut()
-            .filter(|(id, _)| target.targets(id))
+            .filter(|(id, c)| c.protocol == protocol && target.targets(id))
             // TODO: is it worth it to use Arc<Vec<u8>> or Bytes to have a free clone?
             //  at some point the bytes will have to be copied into the final message, so maybe do it now?
             .try_for_each(|(_, c)| c.buffer_message(message.clone(), channel))
@@ -277,11 +836,78 @@ impl ConnectionManager {
         channel_kind: ChannelKind,
         target: NetworkTarget,
     ) -> Result<()> {
-        let message_bytes = self
-            .message_registry
-            .serialize(message, &mut self.writer)
-            .context("could not serialize message")?;
-        self.buffer_message(message_bytes, channel_kind, target)
+        self.buffer_serialized_message(
+            message,
+            |id| target.targets(id),
+            |c, message_bytes| c.buffer_message(message_bytes, channel_kind),
+        )
+    }
+
+    /// Serialize the message once for each protocol that contains it
+    fn serialize_message<M: Message>(&mut self, message: &M) -> Result<SerializedMessage> {
+        let main = self.message_registry.serialize(message, &mut self.writer);
+        let protocols = self
+            .protocols
+            .iter()
+            .filter_map(|(index, registries)| {
+                registries
+                    .message_registry
+                    .serialize(message, &mut self.writer)
+                    .ok()
+                    .map(|bytes| (*index, bytes))
+            })
+            .collect::<HashMap<_, _>>();
+        let main = match main {
+            Ok(bytes) => Some(bytes),
+            Err(_) if !protocols.is_empty() => None,
+            Err(e) => return Err(e).context("could not serialize message"),
+        };
+        Ok(SerializedMessage { main, protocols })
+    }
+
+    /// Serialize the message once for each protocol that contains it, and encode it as a [`ServerMessage`]
+    /// so that the same bytes can be buffered on multiple connections
+    fn serialize_shared_message<M: Message>(
+        &mut self,
+        message: &M,
+    ) -> Result<SerializedMessage<SharedMessage>> {
+        let serialized = self.serialize_message(message)?;
+        let writer = &mut self.writer;
+        let mut encode = |raw: RawData| -> Result<SharedMessage> {
+            writer.start_write();
+            ServerMessage::Message(raw.clone()).encode(writer)?;
+            Ok(SharedMessage {
+                raw,
+                encoded: Bytes::copy_from_slice(writer.finish_write()),
+            })
+        };
+        Ok(SerializedMessage {
+            main: serialized.main.map(&mut encode).transpose()?,
+            protocols: serialized
+                .protocols
+                .into_iter()
+                .map(|(index, raw)| Ok((index, encode(raw)?)))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Call `buffer` for each client that matches `filter`, with the message serialized for the protocol of the client.
+    ///
+    /// The clients whose protocol doesn't contain the message are skipped.
+    fn buffer_serialized_message<M: Message>(
+        &mut self,
+        message: &M,
+        filter: impl Fn(&ClientId) -> bool,
+        mut buffer: impl FnMut(&mut Connection, RawData) -> Result<()>,
+    ) -> Result<()> {
+        let serialized = self.serialize_message(message)?;
+        self.connections
+            .iter_mut()
+            .filter(|(id, _)| filter(id))
+            .try_for_each(|(_, c)| match serialized.get(c.protocol) {
+                Some(message_bytes) => buffer(c, message_bytes.clone()),
+                None => Ok(()),
+            })
     }
 
     /// Buffer all the replication messages to send.
@@ -310,11 +936,16 @@ impl ConnectionManager {
             .iter_mut()
             .for_each(|(client_id, connection)| {
                 let _span = trace_span!("receive", ?client_id).entered();
+                let message_registry = match connection.protocol {
+                    Some(index) => &self.protocols[&index].message_registry,
+                    None => &self.message_registry,
+                };
                 world.resource_scope(|world, component_registry: Mut<ComponentRegistry>| {
                     // receive events on the connection
                     let events = connection.receive(
                         world,
                         component_registry.as_ref(),
+                        message_registry,
                         time_manager,
                         tick_manager,
                     );
@@ -322,12 +953,94 @@ impl ConnectionManager {
                     self.events.push_events(*client_id, events);
                 });
 
-                // rebroadcast messages
-                messages_to_rebroadcast
-                    .extend(std::mem::take(&mut connection.messages_to_rebroadcast));
+                // rebroadcast messages, to the clients that use the same protocol
+                messages_to_rebroadcast.extend(
+                    std::mem::take(&mut connection.messages_to_rebroadcast)
+                        .into_iter()
+                        .map(|message| (message, connection.protocol)),
+                );
+                for entity in std::mem::take(&mut connection.resend_requests) {
+                    self.resend_requests
+                        .entry(entity)
+                        .or_default()
+                        .push(*client_id);
+                }
             });
-        for (message, target, channel_kind) in messages_to_rebroadcast {
-            self.buffer_message(message, channel_kind, target)?;
+        for ((message, target, channel_kind), protocol) in messages_to_rebroadcast {
+            self.buffer_message(message, channel_kind, target, protocol)?;
+        }
+        // refuse the clients that were built with a different protocol
+        if let Some(main_hash) = self.protocol_hash {
+            let protocol_hashes = self
+                .protocols
+                .iter()
+                .map(|(index, registries)| (*index, registries.hash()))
+                .collect::<HashMap<_, _>>();
+            for (client_id, connection) in self.connections.iter_mut() {
+                if connection.protocol_hash_verified || connection.denied.is_some() {
+                    continue;
+                }
+                let Some(client_hash) = connection.protocol_hash else {
+                    continue;
+                };
+                if connection.protocol.is_none() {
+                    // we need the protocol version of the client to know if its protocol can be different
+                    if self.wait_for_protocol_version && connection.protocol_version.is_none() {
+                        continue;
+                    }
+                    // the clients on an older version of the protocol are handled by the component migrations
+                    if connection
+                        .protocol_version
+                        .is_some_and(|version| version != self.protocol_version)
+                    {
+                        connection.protocol_hash_verified = true;
+                        continue;
+                    }
+                }
+                let server_hash = connection
+                    .protocol
+                    .map_or(main_hash, |index| protocol_hashes[&index]);
+                if client_hash == server_hash {
+                    connection.protocol_hash_verified = true;
+                } else {
+                    warn!(
+                        ?client_id,
+                        client_hash,
+                        server_hash,
+                        "client uses a different protocol, refusing the connection"
+                    );
+                    connection.deny(DeniedReason::ProtocolMismatch {
+                        client_hash,
+                        server_hash,
+                    })?;
+                }
+            }
+        }
+        // complete the connection of the clients that announced their protocol version
+        // and their supported compressions, and whose protocol hash was verified
+        for (client_id, connection) in self.connections.iter_mut() {
+            if !connection.awaiting_handshake || connection.denied.is_some() {
+                continue;
+            }
+            let version_known = !self.wait_for_protocol_version
+                || connection.protocol.is_some()
+                || connection.protocol_version.is_some();
+            let hash_verified = self.protocol_hash.is_none() || connection.protocol_hash_verified;
+            // the snapshot of the world can only be sent once we know if the client can decompress it
+            let compressions_known =
+                connection.protocol.is_some() || connection.pending_snapshot_compression.is_none();
+            if version_known && hash_verified && compressions_known {
+                connection.awaiting_handshake = false;
+                self.events.add_connect_event(ConnectEvent {
+                    client_id: *client_id,
+                    entity: connection.entity,
+                });
+                // the world is not replicated to the clients of the additional protocols
+                if connection.protocol.is_none() {
+                    connection.replication_sender.request_snapshot();
+                    self.new_clients.push(*client_id);
+                }
+            }
         }
         Ok(())
     }
@@ -359,7 +1072,7 @@ pub struct Connection {
     client_id: ClientId,
     /// We create one entity per connected client, so that users
     /// can store metadata about the client using the ECS
-    entity: Entity,
+    pub(crate) entity: Entity,
     pub(crate) message_manager: MessageManager,
     pub(crate) replication_sender: ReplicationSender,
     pub(crate) replication_receiver: ReplicationReceiver,
@@ -377,6 +1090,54 @@ pub struct Connection {
     pub(crate) reader_pool: BufferPool,
     // messages that we have received that need to be rebroadcasted to other clients
     pub(crate) messages_to_rebroadcast: Vec<(RawData, NetworkTarget, ChannelKind)>,
+    /// Entities that the client asked to receive again because it received updates for them without having them
+    resend_requests: Vec<Entity>,
+    /// Spreads the packet sends over time, if pacing is enabled
+    pub(crate) pacer: Option<PacketPacer>,
+    /// Get notified whenever an entity actions message was received by the client
+    /// (used to know when the client received a world reset)
+    actions_ack_tracker: Receiver<MessageId>,
+    /// True if the client received the world reset since we last sent replication messages
+    /// (the client needs to receive the entire world state again)
+    pub(crate) world_reset_acked: bool,
+    /// Group of each entity actions message that was sent but not acked yet
+    actions_message_id_to_group: HashMap<MessageId, ReplicationGroupId>,
+    /// Groups whose entity actions messages were received by the client since the packets were last sent
+    pub(crate) acked_action_groups: Vec<ReplicationGroupId>,
+    /// Channels registered at runtime whose network id was not acknowledged by the client yet,
+    /// along with the messages that are waiting to be sent on them (at most [`MAX_PENDING_CHANNEL_MESSAGES`])
+    pending_channels: HashMap<ChannelKind, Vec<(RawData, Option<Duration>)>>,
+    /// Version of the protocol announced by the client
+    pub(crate) protocol_version: Option<ProtocolVersion>,
+    /// True if the client is waiting to announce its protocol version or to have its protocol hash verified
+    /// before being considered connected
+    pub(crate) awaiting_handshake: bool,
+    /// Hash of the registered components, messages and channels announced by the client
+    pub(crate) protocol_hash: Option<u64>,
+    /// True if the protocol hash announced by the client is compatible with the server's protocol
+    pub(crate) protocol_hash_verified: bool,
+    /// Set if the connection was refused
+    pub(crate) denied: Option<DeniedReason>,
+    /// True if the reason of the refusal was sent to the client, which can now be disconnected
+    pub(crate) denial_sent: bool,
+    /// Compression algorithm that the server would like to use for the packets sent to the client
+    packet_compression: PacketCompression,
+    /// Compression algorithm of the world snapshot sent to the late joiners, until the client announced
+    /// the algorithms that it can decompress (see [`LateJoinReplication::Snapshot`])
+    pub(crate) pending_snapshot_compression: Option<PacketCompression>,
+    /// The additional protocol used by the client, if it didn't connect with the main protocol
+    pub(crate) protocol: Option<usize>,
+    /// Send intervals of the replication groups of the client that are sent less often than the server's
+    /// send interval, as announced to the client
+    announced_send_intervals: HashMap<ReplicationGroupId, Duration>,
+    /// Dynamic components that the client registered too, and that can be replicated to it
+    /// (the network ids are the ones assigned by the server)
+    pub(crate) dynamic_components: HashMap<ComponentNetId, String>,
+    /// Dynamic components whose network id was sent to the client, but not acknowledged yet
+    pending_dynamic_components: HashMap<ComponentNetId, String>,
+    /// Dynamic components that the client registered since the replication messages were last sent
+    /// (the client needs to receive their current values)
+    pub(crate) new_dynamic_components: Vec<ComponentNetId>,
 }
 
 impl Connection {
@@ -386,9 +1147,15 @@ impl Connection {
         channel_registry: &ChannelRegistry,
         packet_config: PacketConfig,
         ping_config: PingConfig,
+        replication_config: &ReplicationConfig,
+        packet_sizing: PacketSizing,
     ) -> Self {
+        let pacer = packet_config.send_pacing.map(PacketPacer::new);
+        let mtu_config = packet_config.mtu_discovery;
+        let packet_compression = packet_config.compression;
         // create the message manager and the channels
         let mut message_manager = MessageManager::new(channel_registry, packet_config.into());
+        message_manager.set_packet_sizing(packet_sizing, mtu_config);
         // get the acks-tracker for entity updates
         let update_acks_tracker = message_manager
             .channels
@@ -396,12 +1163,40 @@ impl Connection {
             .unwrap()
             .sender
             .subscribe_acks();
+        // get the acks-tracker for entity actions
+        let actions_ack_tracker = message_manager
+            .channels
+            .get_mut(&ChannelKind::of::<EntityActionsChannel>())
+            .unwrap()
+            .sender
+            .subscribe_acks();
         // get a channel to get notified when a replication update message gets actually send (to update priority)
         let replication_update_send_receiver =
             message_manager.get_replication_update_send_receiver();
-        let replication_sender =
+        let mut replication_sender =
             ReplicationSender::new(update_acks_tracker, replication_update_send_receiver);
-        let replication_receiver = ReplicationReceiver::new();
+        replication_sender.despawn_batch_threshold = replication_config.despawn_batch_threshold;
+        // the client must be able to decompress the snapshot, so the compressor is only created once the
+        // compression algorithms are negotiated (uncompressed snapshots don't need to wait for it)
+        let mut pending_snapshot_compression = None;
+        if let LateJoinReplication::Snapshot { compression } = replication_config.late_join {
+            if compression == PacketCompression::None {
+                replication_sender.snapshot_compressor = PacketCompressor::new(compression)
+                    .inspect_err(|e| {
+                        error!("could not create the world snapshot compressor: {e:?}")
+                    })
+                    .ok();
+            } else {
+                pending_snapshot_compression = Some(compression);
+            }
+        }
+        let mut replication_receiver = ReplicationReceiver::new();
+        // only the clients can ask for an entity to be sent again
+        replication_receiver.unknown_entity_policy = match replication_config.unknown_entity_updates
+        {
+            UnknownEntityUpdatePolicy::RequestResend => UnknownEntityUpdatePolicy::Drop,
+            policy => policy,
+        };
         Self {
             client_id,
             entity,
@@ -418,6 +1213,26 @@ impl Connection {
             // TODO: it looks like we don't really need the pool this case, we can just keep re-using the same buffer
             reader_pool: BufferPool::new(1),
             messages_to_rebroadcast: vec![],
+            resend_requests: vec![],
+            pacer,
+            actions_ack_tracker,
+            world_reset_acked: false,
+            actions_message_id_to_group: HashMap::default(),
+            acked_action_groups: Vec::new(),
+            pending_channels: HashMap::default(),
+            protocol_version: None,
+            awaiting_handshake: false,
+            protocol_hash: None,
+            protocol_hash_verified: false,
+            denied: None,
+            denial_sent: false,
+            packet_compression,
+            pending_snapshot_compression,
+            protocol: None,
+            announced_send_intervals: HashMap::default(),
+            dynamic_components: HashMap::default(),
+            pending_dynamic_components: HashMap::default(),
+            new_dynamic_components: Vec::new(),
         }
     }
 
@@ -428,6 +1243,41 @@ impl Connection {
     }
 
     pub(crate) fn buffer_message(&mut self, message: Vec<u8>, channel: ChannelKind) -> Result<()> {
+        self.buffer_message_with_ttl(message, channel, None)
+            .map(|_| ())
+    }
+
+    /// Buffer a message that was already encoded and is shared with other connections
+    fn buffer_shared_message(
+        &mut self,
+        message: &SharedMessage,
+        channel: ChannelKind,
+    ) -> Result<()> {
+        if self.pending_channels.contains_key(&channel) {
+            return self.buffer_message(message.raw.clone(), channel);
+        }
+        self.message_manager
+            .buffer_send_bytes(message.encoded.clone(), channel, DEFAULT_MESSAGE_PRIORITY)
+            .map(|_| ())
+    }
+
+    /// Returns the id of the message, if the channel assigns one
+    fn buffer_message_with_ttl(
+        &mut self,
+        message: Vec<u8>,
+        channel: ChannelKind,
+        ttl: Option<Duration>,
+    ) -> Result<Option<MessageId>> {
+        if let Some(pending_messages) = self.pending_channels.get_mut(&channel) {
+            // the client doesn't know the network id of the channel yet
+            if pending_messages.len() >= MAX_PENDING_CHANNEL_MESSAGES {
+                return Err(anyhow!(
+                    "too many messages are waiting for the client to acknowledge the channel"
+                ));
+            }
+            pending_messages.push((message, ttl));
+            return Ok(None);
+        }
         // TODO: i know channel names never change so i should be able to get them as static
         // TODO: just have a channel registry enum as well?
         let channel_name = self
@@ -442,8 +1292,12 @@ impl Connection {
         // TODO: doesn't this serialize the bytes twice?
         let message_bytes = self.writer.finish_write().to_vec();
         // message.emit_send_logs(&channel_name);
-        self.message_manager.buffer_send(message_bytes, channel)?;
-        Ok(())
+        match ttl {
+            Some(ttl) => self
+                .message_manager
+                .buffer_send_with_ttl(message_bytes, channel, ttl),
+            None => self.message_manager.buffer_send(message_bytes, channel),
+        }
     }
 
     pub(crate) fn buffer_replication_messages(
@@ -452,17 +1306,19 @@ impl Connection {
         bevy_tick: BevyTick,
     ) -> Result<()> {
         self.replication_sender
-            .finalize(tick)
+            .finalize(tick, bevy_tick)
             .into_iter()
             .try_for_each(|(channel, group_id, message_data, priority)| {
                 let should_track_ack = matches!(message_data, ReplicationMessageData::Updates(_));
+                let is_actions = matches!(message_data, ReplicationMessageData::Actions(_));
+                let is_world_reset = matches!(message_data, ReplicationMessageData::Reset(_));
                 let channel_name = self
                     .message_manager
                     .channel_registry
                     .name(&channel)
                     .unwrap_or("unknown")
                     .to_string();
-                let message = ClientMessage::Replication(ReplicationMessage {
+                let message = ServerMessage::Replication(ReplicationMessage {
                     group_id,
                     data: message_data,
                 });
@@ -480,12 +1336,184 @@ impl Connection {
                 if should_track_ack {
                     self.replication_sender
                         .updates_message_id_to_group_id
-                        .insert(message_id, (group_id, bevy_tick));
+                        .insert(message_id, (group_id, bevy_tick, tick));
+                }
+                if is_world_reset {
+                    self.replication_sender.world_reset_message_id = Some(message_id);
+                }
+                if is_actions {
+                    self.actions_message_id_to_group
+                        .insert(message_id, group_id);
                 }
                 Ok(())
             })
     }
 
+    /// Send the network id of a channel that was registered at runtime to the client.
+    ///
+    /// The messages sent on the channel are buffered until the client acknowledges it.
+    pub(crate) fn announce_channel(&mut self, kind: ChannelKind, net_id: ChannelId) -> Result<()> {
+        let name = self
+            .message_manager
+            .channel_registry
+            .name(&kind)
+            .context("channel not found")?
+            .to_string();
+        self.writer.start_write();
+        ServerMessage::ChannelRegistration(ChannelRegistration { name, net_id })
+            .encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        self.pending_channels.insert(kind, vec![]);
+        Ok(())
+    }
+
+    /// The client knows the network id of the channel: send the messages that were buffered
+    fn receive_channel_ack(&mut self, net_id: ChannelId) -> Result<()> {
+        let kind = *self
+            .message_manager
+            .channel_registry
+            .get_kind_from_net_id(net_id)
+            .context("channel not found")?;
+        if let Some(pending_messages) = self.pending_channels.remove(&kind) {
+            pending_messages
+                .into_iter()
+                .try_for_each(|(message, ttl)| {
+                    self.buffer_message_with_ttl(message, kind, ttl).map(|_| ())
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Tell the client the send intervals of the replication groups that it receives and that are sent less
+    /// often than the server's send interval, so that it can interpolate them
+    pub(crate) fn announce_send_intervals(
+        &mut self,
+        send_intervals: HashMap<ReplicationGroupId, Duration>,
+    ) -> Result<()> {
+        let mut changes: Vec<(ReplicationGroupId, Option<u64>)> = send_intervals
+            .iter()
+            .filter(|(group_id, interval)| {
+                self.announced_send_intervals.get(*group_id) != Some(*interval)
+            })
+            .map(|(group_id, interval)| (*group_id, Some(interval.as_micros() as u64)))
+            .collect();
+        changes.extend(
+            self.announced_send_intervals
+                .keys()
+                .filter(|group_id| !send_intervals.contains_key(*group_id))
+                .map(|group_id| (*group_id, None)),
+        );
+        if changes.is_empty() {
+            return Ok(());
+        }
+        self.announced_send_intervals = send_intervals;
+        self.writer.start_write();
+        ServerMessage::GroupSendIntervals(changes).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    /// Send the value that the server kept for a component of an entity replicated by the client
+    fn send_component_correction(&mut self, entity: Entity, component: RawData) -> Result<()> {
+        let entity = *self
+            .replication_receiver
+            .remote_entity_map
+            .get_remote(entity)
+            .context("the entity was not replicated by the client")?;
+        self.writer.start_write();
+        ServerMessage::ComponentCorrection(ComponentCorrection { entity, component })
+            .encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    /// Tell the client why its connection is refused
+    fn deny(&mut self, reason: DeniedReason) -> Result<()> {
+        self.denied = Some(reason);
+        self.writer.start_write();
+        ServerMessage::ConnectionDenied(reason).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    /// Send the network ids assigned by the server to the dynamic components that the client registered.
+    ///
+    /// The components are replicated to the client once it acknowledged their ids, because the
+    /// ids might differ from the ones assigned on the client.
+    fn receive_dynamic_components(
+        &mut self,
+        type_paths: Vec<String>,
+        component_registry: &ComponentRegistry,
+    ) -> Result<()> {
+        let mut ids = vec![];
+        for type_path in type_paths {
+            let Some(net_id) = component_registry.dynamic_net_id(&type_path) else {
+                debug!(client_id = ?self.client_id, "the dynamic component {type_path} of the client is not registered on the server");
+                continue;
+            };
+            if self.dynamic_components.contains_key(&net_id)
+                || self.pending_dynamic_components.contains_key(&net_id)
+            {
+                continue;
+            }
+            self.pending_dynamic_components
+                .insert(net_id, type_path.clone());
+            ids.push((type_path, net_id));
+        }
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.writer.start_write();
+        ServerMessage::DynamicComponentIds(ids).encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
+    /// The client knows the network ids of its dynamic components: they can now be replicated to it
+    fn receive_dynamic_component_ids_ack(&mut self, net_ids: Vec<ComponentNetId>) {
+        for net_id in net_ids {
+            if let Some(type_path) = self.pending_dynamic_components.remove(&net_id) {
+                debug!(client_id = ?self.client_id, "client registered the dynamic component {type_path}");
+                self.dynamic_components.insert(net_id, type_path);
+                self.new_dynamic_components.push(net_id);
+            }
+        }
+    }
+
+    /// The client announced the compression algorithms that it supports: start compressing the packets
+    /// if it supports the configured algorithm, and announce the algorithms that the server supports
+    fn receive_supported_compressions(&mut self, supported: SupportedCompressions) -> Result<()> {
+        let compression = self.packet_compression.negotiate(supported);
+        debug!(client_id = ?self.client_id, ?compression, "negotiated packet compression");
+        self.message_manager.set_compression(compression)?;
+        if let Some(snapshot_compression) = self.pending_snapshot_compression.take() {
+            if snapshot_compression.negotiate(supported) == snapshot_compression {
+                self.replication_sender.snapshot_compressor =
+                    Some(PacketCompressor::new(snapshot_compression)?);
+            } else {
+                // the world is sent to the client in one message per replication group instead
+                debug!(client_id = ?self.client_id, ?snapshot_compression, "the client cannot decompress the world snapshot");
+            }
+        }
+        self.writer.start_write();
+        ServerMessage::SupportedCompressions(PacketCompression::supported())
+            .encode(&mut self.writer)?;
+        let message_bytes = self.writer.finish_write().to_vec();
+        self.message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<ChannelSyncChannel>())?;
+        Ok(())
+    }
+
     fn send_ping(&mut self, ping: Ping) -> Result<()> {
         trace!("Sending ping {:?}", ping);
         self.writer.start_write();
@@ -507,11 +1535,14 @@ impl Connection {
     }
 
     /// Send packets that are ready to be sent
+    ///
+    /// Returns the payloads that should be sent as datagrams, and the payloads that should be
+    /// sent on a reliable stream (for channels that use [`Delivery::Stream`](crate::transport::Delivery::Stream))
     pub fn send_packets(
         &mut self,
         time_manager: &TimeManager,
         tick_manager: &TickManager,
-    ) -> Result<Vec<Payload>> {
+    ) -> Result<(Vec<Payload>, Vec<Payload>)> {
         // update the ping manager with the actual send time
         // TODO: issues here: we would like to send the ping/pong messages immediately, otherwise the recorded current time is incorrect
         //   - can give infinity priority to this channel?
@@ -538,22 +1569,26 @@ impl Connection {
                     Ok::<(), anyhow::Error>(())
                 })?;
         }
-        let payloads = self.message_manager.send_packets(tick_manager.tick());
+        let payloads = self.message_manager.send_packets(tick_manager.tick())?;
+        let stream_payloads = self
+            .message_manager
+            .send_stream_packets(tick_manager.tick())?;
 
         // update the replication sender about which messages were actually sent, and accumulate priority
         self.replication_sender.recv_send_notification();
-        payloads
+        self.acked_action_groups.clear();
+        Ok((payloads, stream_payloads))
     }
 
     pub fn receive(
         &mut self,
         world: &mut World,
         component_registry: &ComponentRegistry,
+        message_registry: &MessageRegistry,
         time_manager: &TimeManager,
         tick_manager: &TickManager,
     ) -> ConnectionEvents {
         let _span = trace_span!("receive").entered();
-        let message_registry = world.resource::<MessageRegistry>();
         for (channel_kind, messages) in self.message_manager.read_messages() {
             let channel_name = self
                 .message_manager
@@ -592,6 +1627,10 @@ impl Connection {
                             let data = (message.clone().into(), target.clone(), channel_kind);
 
                             match message_registry.message_type(net_id) {
+                                // the inputs are only read with the main protocol
+                                _ if self.protocol.is_some() => {
+                                    self.received_messages.entry(net_id).or_default().push(data);
+                                }
                                 #[cfg(feature = "leafwing")]
                                 MessageType::LeafwingInput => self
                                     .received_leafwing_input_messages
@@ -609,6 +1648,9 @@ impl Connection {
                                 }
                             }
                         }
+                        ClientMessage::Replication(_) if self.protocol.is_some() => {
+                            trace!("ignoring replication message from a client of an additional protocol");
+                        }
                         ClientMessage::Replication(replication) => {
                             trace!(?tick, ?replication, "received replication message");
                             // buffer the replication message
@@ -626,6 +1668,39 @@ impl Connection {
                             self.ping_manager
                                 .process_pong(&pong, time_manager.current_time());
                         }
+                        ClientMessage::ChannelRegistrationAck(net_id) => {
+                            if let Err(e) = self.receive_channel_ack(net_id) {
+                                error!("could not handle channel registration ack: {e:?}");
+                            }
+                        }
+                        ClientMessage::ProtocolVersion(version) => {
+                            debug!(client_id = ?self.client_id, ?version, "client announced its protocol version");
+                            self.protocol_version = Some(version);
+                            self.replication_receiver.remote_protocol_version = Some(version);
+                        }
+                        ClientMessage::SupportedCompressions(supported) => {
+                            if let Err(e) = self.receive_supported_compressions(supported) {
+                                error!("could not negotiate the packet compression: {e:?}");
+                            }
+                        }
+                        ClientMessage::ProtocolHash(hash) => {
+                            debug!(client_id = ?self.client_id, hash, "client announced its protocol hash");
+                            self.protocol_hash = Some(hash);
+                        }
+                        ClientMessage::DynamicComponents(type_paths) => {
+                            if let Err(e) =
+                                self.receive_dynamic_components(type_paths, component_registry)
+                            {
+                                error!("could not send the dynamic component ids: {e:?}");
+                            }
+                        }
+                        ClientMessage::DynamicComponentIdsAck(net_ids) => {
+                            self.receive_dynamic_component_ids_ack(net_ids);
+                        }
+                        ClientMessage::ResendEntities(entities) => {
+                            debug!(client_id = ?self.client_id, ?entities, "client requested the resend of entities");
+                            self.resend_requests.extend(entities);
+                        }
                     }
                 }
             }
@@ -653,6 +1728,13 @@ impl Connection {
                 });
         }
 
+        // send the values that didn't pass the validation back to the client
+        for (entity, component) in std::mem::take(&mut self.events.component_corrections) {
+            if let Err(e) = self.send_component_correction(entity, component) {
+                error!("could not send the component correction: {e:?}");
+            }
+        }
+
         // TODO: do i really need this? I could just create events in this function directly?
         //  why do i need to make events a field of the connection?
         //  is it because of push_connection?
@@ -664,16 +1746,116 @@ impl Connection {
         let tick = self.message_manager.recv_packet(packet)?;
         // notify the replication sender that some sent messages were received
         self.replication_sender.recv_update_acks();
+        while let Ok(message_id) = self.actions_ack_tracker.try_recv() {
+            if self.replication_sender.recv_world_reset_ack(message_id) {
+                self.world_reset_acked = true;
+            }
+            if let Some(group_id) = self.actions_message_id_to_group.remove(&message_id) {
+                self.acked_action_groups.push(group_id);
+            }
+        }
         debug!("Received server packet with tick: {:?}", tick);
         Ok(())
     }
 }
 
 impl ConnectionManager {
+    /// Start resetting the replicated world of all the connected clients
+    pub(crate) fn reset_world(&mut self) {
+        for (client_id, connection) in self.connections.iter_mut() {
+            debug!(?client_id, "resetting the replicated world");
+            connection.replication_sender.reset_world();
+            connection.world_reset_acked = false;
+        }
+    }
+
+    /// Start resetting the replicated world of a single client
+    fn reset_client_world(&mut self, client_id: ClientId) -> Result<()> {
+        debug!(?client_id, "resetting the replicated world");
+        let connection = self.connection_mut(client_id)?;
+        connection.replication_sender.reset_world();
+        connection.world_reset_acked = false;
+        Ok(())
+    }
+
+    /// The client `spectator` will receive the exact replication stream that the client `spectated` receives:
+    /// the same entities (using the visibility of `spectated`) and the same components.
+    ///
+    /// The world of the spectator is reset, and then the entire view of the spectated client is replicated to it.
+    /// The [`Controlled`](crate::shared::replication::components::Controlled) and [`ShouldBePredicted`]/[`ShouldBeInterpolated`](crate::shared::replication::components::ShouldBeInterpolated)
+    /// markers still depend on the spectator,
+    /// so the spectator can interpolate the entities that the spectated client predicts.
+    pub fn start_spectating(&mut self, spectator: ClientId, spectated: ClientId) -> Result<()> {
+        if spectator == spectated {
+            anyhow::bail!("a client cannot spectate itself");
+        }
+        self.connection(spectated)
+            .context("the spectated client is not connected")?;
+        if self.spectators.contains_key(&spectated) {
+            anyhow::bail!("the spectated client is already spectating another client");
+        }
+        if self.spectators.values().any(|id| *id == spectator) {
+            anyhow::bail!("the spectator is being spectated by another client");
+        }
+        self.reset_client_world(spectator)?;
+        if self.spectators.insert(spectator, spectated).is_some() {
+            // the visibility mirrored from the previous spectated client must be cleared
+            self.stopped_spectators.push(spectator);
+        }
+        info!(?spectator, ?spectated, "start spectating");
+        Ok(())
+    }
+
+    /// Stop spectating. The world of the spectator is reset, and it receives its own replication stream again.
+    ///
+    /// The entities that use [`VisibilityMode::InterestManagement`](crate::prelude::VisibilityMode::InterestManagement) have to be made visible to the spectator again
+    /// (for example via the [`RoomManager`]).
+    ///
+    /// Returns the client that was spectated
+    pub fn stop_spectating(&mut self, spectator: ClientId) -> Option<ClientId> {
+        let spectated = self.spectators.remove(&spectator)?;
+        // the spectator might have disconnected
+        let _ = self.reset_client_world(spectator);
+        self.stopped_spectators.push(spectator);
+        info!(?spectator, ?spectated, "stop spectating");
+        Some(spectated)
+    }
+
+    /// Returns the client that is being spectated by `spectator`
+    pub fn spectated_client(&self, spectator: ClientId) -> Option<ClientId> {
+        self.spectators.get(&spectator).copied()
+    }
+
+    /// Update the [`NetworkTarget`] so that the spectators are targeted only if the client
+    /// that they are spectating is targeted
+    pub(crate) fn spectators_target(&self, target: &NetworkTarget) -> NetworkTarget {
+        let mut new_target = target.clone();
+        for (spectator, spectated) in self.spectators.iter() {
+            let spectator_target = NetworkTarget::Single(*spectator);
+            if target.targets(spectated) {
+                new_target.union(&spectator_target);
+            } else {
+                new_target.exclude(&spectator_target);
+            }
+        }
+        new_target
+    }
+
+    /// Returns the clients that received the world reset since we last sent replication messages
+    pub(crate) fn take_world_reset_clients(&mut self) -> Vec<ClientId> {
+        self.connections
+            .iter_mut()
+            .filter_map(|(client_id, connection)| {
+                std::mem::take(&mut connection.world_reset_acked).then_some(*client_id)
+            })
+            .collect()
+    }
+
     pub(crate) fn prepare_entity_despawn(
         &mut self,
         entity: Entity,
         group: &ReplicationGroup,
+        reason: Option<DespawnReason>,
         target: NetworkTarget,
     ) -> Result<()> {
         let group_id = group.group_id(Some(entity));
@@ -686,7 +1868,7 @@ impl ConnectionManager {
             // );
             self.connection_mut(client_id)?
                 .replication_sender
-                .prepare_entity_despawn(entity, group_id);
+                .prepare_entity_despawn(entity, group_id, reason);
             Ok(())
         })
     }
@@ -786,6 +1968,7 @@ impl ConnectionManager {
         group: &ReplicationGroup,
         target: NetworkTarget,
         component_change_tick: BevyTick,
+        last_send_tick: Option<BevyTick>,
         system_current_tick: BevyTick,
     ) -> Result<()> {
         trace!(
@@ -815,7 +1998,8 @@ impl ConnectionManager {
 
             if collect_changes_since_this_tick.map_or(true, |tick| {
                 component_change_tick.is_newer_than(tick, system_current_tick)
-            }) {
+            }) || changed_since_last_send(component_change_tick, last_send_tick, system_current_tick)
+            {
                 trace!(
                     change_tick = ?component_change_tick,
                     ?collect_changes_since_this_tick,
@@ -836,6 +2020,108 @@ impl ConnectionManager {
     }
 }
 
+impl ConnectionManager {
+    /// Clients of `target` that did not receive the latest change of the component yet, i.e. the clients for
+    /// which [`ConnectionManager::prepare_component_update`] would send the update.
+    ///
+    /// Used to avoid serializing components that don't need to be sent.
+    pub(crate) fn clients_needing_update(
+        &mut self,
+        entity: Entity,
+        group: &ReplicationGroup,
+        target: NetworkTarget,
+        component_change_tick: BevyTick,
+        last_send_tick: Option<BevyTick>,
+        system_current_tick: BevyTick,
+    ) -> NetworkTarget {
+        if changed_since_last_send(component_change_tick, last_send_tick, system_current_tick) {
+            return target;
+        }
+        let group_id = group.group_id(Some(entity));
+        let clients = self
+            .apply_replication(target)
+            .filter(|client_id| {
+                self.connections.get(client_id).is_some_and(|connection| {
+                    connection
+                        .replication_sender
+                        .group_channels
+                        .get(&group_id)
+                        .and_then(|channel| channel.collect_changes_since_this_tick)
+                        .map_or(true, |tick| {
+                            component_change_tick.is_newer_than(tick, system_current_tick)
+                        })
+                })
+            })
+            .collect::<Vec<_>>();
+        NetworkTarget::from(clients)
+    }
+
+    /// Prepare an update for a component that uses delta compression: the update is serialized for each
+    /// client as the difference with the last value that the client acked.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn prepare_delta_component_update<C: Component>(
+        &mut self,
+        entity: Entity,
+        kind: ComponentNetId,
+        component: &C,
+        registry: &ComponentRegistry,
+        group: &ReplicationGroup,
+        target: NetworkTarget,
+        component_change_tick: BevyTick,
+        last_send_tick: Option<BevyTick>,
+        system_current_tick: BevyTick,
+        tick: Tick,
+    ) -> Result<()> {
+        let group_id = group.group_id(Some(entity));
+        self.apply_replication(target).try_for_each(|client_id| {
+            let replication_sender = &mut self
+                .connections
+                .get_mut(&client_id)
+                .context("client id not found")?
+                .replication_sender;
+            let collect_changes_since_this_tick = replication_sender
+                .group_channels
+                .entry(group_id)
+                .or_default()
+                .collect_changes_since_this_tick;
+            // only send the update if the component changed since the last ack for the group
+            if collect_changes_since_this_tick
+                .is_some_and(|tick| !component_change_tick.is_newer_than(tick, system_current_tick))
+                && !changed_since_last_send(
+                    component_change_tick,
+                    last_send_tick,
+                    system_current_tick,
+                )
+            {
+                return Ok(());
+            }
+            let base = replication_sender.delta.base(group_id, entity, kind, tick);
+            let raw_data = registry.serialize_delta(component, tick, base, &mut self.writer)?;
+            if replication_sender.prepare_entity_update(entity, group_id, kind, raw_data) {
+                if let Some(value) = registry.delta_value(component) {
+                    replication_sender
+                        .delta
+                        .record_sent(group_id, entity, kind, tick, value);
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Returns true if the component changed since `last_send_tick`, the last time we sent the updates of a component
+/// that has its own send interval.
+///
+/// These changes were not sent yet, even if the group got acked since then.
+fn changed_since_last_send(
+    component_change_tick: BevyTick,
+    last_send_tick: Option<BevyTick>,
+    system_current_tick: BevyTick,
+) -> bool {
+    last_send_tick
+        .is_some_and(|tick| component_change_tick.is_newer_than(tick, system_current_tick))
+}
+
 impl MessageSend for ConnectionManager {
     fn send_message_to_target<C: Channel, M: Message>(
         &mut self,
@@ -901,3 +2187,167 @@ impl ReplicationSend for ConnectionManager {
         }
     }
 }
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::*;
+
+    use crate::prelude::client::ClientCommands;
+    use crate::prelude::server::ServerCommands;
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct ReceivedMessages(Vec<String>);
+
+    fn receive_messages(
+        mut received: ResMut<ReceivedMessages>,
+        mut events: EventReader<client::MessageEvent<Message1>>,
+    ) {
+        received
+            .0
+            .extend(events.read().map(|event| event.message().0.clone()));
+    }
+
+    #[test]
+    fn test_send_message_to_registered_target() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<ReceivedMessages>();
+        stepper.client_app.add_systems(Update, receive_messages);
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let mut connection_manager = stepper.server_app.world.resource_mut::<ConnectionManager>();
+        let all = connection_manager.register_target(NetworkTarget::All);
+        let others = connection_manager.register_target(NetworkTarget::AllExceptSingle(client_id));
+        assert_eq!(
+            connection_manager.registered_targets[&all].clients,
+            vec![client_id]
+        );
+        assert!(connection_manager.registered_targets[&others]
+            .clients
+            .is_empty());
+
+        connection_manager
+            .send_message_to_handle::<Channel1, _>(&Message1("all".to_string()), all)
+            .unwrap();
+        connection_manager
+            .send_message_to_handle::<Channel1, _>(&Message1("others".to_string()), others)
+            .unwrap();
+        connection_manager
+            .send_message_to_all_except::<Channel1, _>(
+                &Message1("all_except_client".to_string()),
+                &[client_id],
+            )
+            .unwrap();
+        connection_manager
+            .send_message_to_all_except::<Channel1, _>(&Message1("all_except".to_string()), &[])
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+        let mut received = stepper
+            .client_app
+            .world
+            .resource::<ReceivedMessages>()
+            .0
+            .clone();
+        received.sort();
+        assert_eq!(received, vec!["all".to_string(), "all_except".to_string()]);
+
+        // the clients of the registered targets are updated when a client disconnects
+        let mut connection_manager = stepper.server_app.world.resource_mut::<ConnectionManager>();
+        connection_manager.remove(client_id);
+        assert!(connection_manager.registered_targets[&all]
+            .clients
+            .is_empty());
+        connection_manager.unregister_target(all);
+        assert!(connection_manager
+            .update_target(all, NetworkTarget::None)
+            .is_err());
+    }
+
+    /// The registered targets are kept when the server restarts
+    #[test]
+    fn test_registered_target_after_restart() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<ReceivedMessages>();
+        stepper.client_app.add_systems(Update, receive_messages);
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let all = stepper
+            .server_app
+            .world
+            .resource_mut::<ConnectionManager>()
+            .register_target(NetworkTarget::All);
+
+        stepper
+            .client_app
+            .world
+            .run_system_once(|mut commands: Commands| commands.disconnect_client());
+        stepper
+            .server_app
+            .world
+            .run_system_once(|mut commands: Commands| commands.stop_server());
+        stepper.frame_step();
+        stepper
+            .server_app
+            .world
+            .run_system_once(|mut commands: Commands| commands.start_server());
+        stepper
+            .client_app
+            .world
+            .run_system_once(|mut commands: Commands| commands.connect_client());
+        for _ in 0..50 {
+            stepper.frame_step();
+        }
+
+        let mut connection_manager = stepper.server_app.world.resource_mut::<ConnectionManager>();
+        assert_eq!(
+            connection_manager.registered_targets[&all].clients,
+            vec![client_id]
+        );
+        connection_manager
+            .send_message_to_handle::<Channel1, _>(&Message1("all".to_string()), all)
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper.client_app.world.resource::<ReceivedMessages>().0,
+            vec!["all".to_string()]
+        );
+    }
+
+    /// The world snapshot is only compressed with an algorithm that the client can decompress
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_snapshot_compression_negotiation() {
+        let stepper = BevyStepper::default();
+        let replication_config =
+            ReplicationConfig::default().with_late_join_snapshot(PacketCompression::Lz4);
+        let new_connection = || {
+            Connection::new(
+                ClientId::Netcode(TEST_CLIENT_ID),
+                Entity::PLACEHOLDER,
+                stepper.server_app.world.resource::<ChannelRegistry>(),
+                PacketConfig::default(),
+                PingConfig::default(),
+                &replication_config,
+                PacketSizing::default(),
+            )
+        };
+        let mut connection = new_connection();
+        assert!(connection.replication_sender.snapshot_compressor.is_none());
+        connection
+            .receive_supported_compressions(PacketCompression::supported())
+            .unwrap();
+        assert!(connection.replication_sender.snapshot_compressor.is_some());
+
+        // the client cannot decompress the snapshot: the world is sent in one message per group
+        let mut connection = new_connection();
+        connection.receive_supported_compressions(0).unwrap();
+        assert!(connection.pending_snapshot_compression.is_none());
+        assert!(connection.replication_sender.snapshot_compressor.is_none());
+    }
+}
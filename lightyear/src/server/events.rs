@@ -9,12 +9,14 @@ use crate::inputs::leafwing::LeafwingUserAction;
 use crate::packet::message::Message;
 use crate::prelude::ComponentRegistry;
 use crate::server::connection::ConnectionManager;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::events::connection::{
     ConnectionEvents, IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
     IterEntityDespawnEvent, IterEntitySpawnEvent,
 };
 use crate::shared::events::plugin::EventsPlugin;
 use crate::shared::events::systems::push_component_events;
+use crate::shared::replication::DespawnReason;
 use crate::shared::sets::{InternalMainSet, ServerMarker};
 
 type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
@@ -25,12 +27,124 @@ pub struct ServerEventsPlugin;
 
 impl Plugin for ServerEventsPlugin {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         app
             // EVENTS
             .add_event::<ConnectEvent>()
             .add_event::<DisconnectEvent>()
+            .add_event::<TransferProgressEvent>()
+            .add_event::<MessageExpiredEvent>()
+            .add_event::<FragmentProgressEvent>()
+            .add_event::<MessageAckedEvent>()
+            .add_event::<UnknownEntityUpdateEvent>()
+            .add_event::<EntityVisibleToClient>()
+            .add_event::<EntityHiddenFromClient>()
+            .add_event::<ControlGained>()
+            .add_event::<ControlLost>()
+            // SYSTEMS
+            .add_systems(
+                schedules.receive,
+                (
+                    emit_transfer_progress,
+                    emit_expired_messages,
+                    emit_fragment_progress,
+                    emit_acked_messages,
+                    emit_unknown_entity_updates,
+                )
+                    .in_set(InternalMainSet::<ServerMarker>::EmitEvents),
+            )
             // PLUGIN
             .add_plugins(EventsPlugin::<ConnectionManager>::default());
+        #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+        app.add_event::<CertificateRotatedEvent>();
+    }
+}
+
+/// Send the progress updates of the transfers on stream channels as bevy [`Events`]
+fn emit_transfer_progress(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut events: EventWriter<TransferProgressEvent>,
+) {
+    for (client_id, connection) in connection_manager.connections.iter_mut() {
+        events.send_batch(
+            connection
+                .message_manager
+                .drain_transfer_progress()
+                .into_iter()
+                .map(|(channel, progress)| {
+                    TransferProgressEvent::new(channel, progress, *client_id)
+                }),
+        );
+    }
+}
+
+/// Send the messages that expired before being acked as bevy [`Events`]
+fn emit_expired_messages(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut events: EventWriter<MessageExpiredEvent>,
+) {
+    for (client_id, connection) in connection_manager.connections.iter_mut() {
+        events.send_batch(
+            connection
+                .message_manager
+                .drain_expired_messages()
+                .into_iter()
+                .map(|(channel, message_id)| {
+                    MessageExpiredEvent::new(channel, message_id, *client_id)
+                }),
+        );
+    }
+}
+
+/// Send the progress updates of the fragmented messages as bevy [`Events`]
+fn emit_fragment_progress(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut events: EventWriter<FragmentProgressEvent>,
+) {
+    for (client_id, connection) in connection_manager.connections.iter_mut() {
+        events.send_batch(
+            connection
+                .message_manager
+                .drain_fragment_progress()
+                .into_iter()
+                .map(|(channel, progress)| {
+                    FragmentProgressEvent::new(channel, progress, *client_id)
+                }),
+        );
+    }
+}
+
+/// Send the acks of the messages that were sent with a [`MessageHandle`](crate::packet::message::MessageHandle) as bevy [`Events`]
+fn emit_acked_messages(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut events: EventWriter<MessageAckedEvent>,
+) {
+    for (client_id, connection) in connection_manager.connections.iter_mut() {
+        events.send_batch(
+            connection
+                .message_manager
+                .drain_acked_messages()
+                .into_iter()
+                .map(|handle| MessageAckedEvent::new(handle, *client_id)),
+        );
+    }
+}
+
+/// Send the updates received for entities that don't exist locally as bevy [`Events`]
+fn emit_unknown_entity_updates(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut events: EventWriter<UnknownEntityUpdateEvent>,
+) {
+    for (client_id, connection) in connection_manager.connections.iter_mut() {
+        events.send_batch(
+            connection
+                .replication_receiver
+                .drain_unknown_entity_events()
+                .into_iter()
+                .map(|(remote_entity, tick)| {
+                    UnknownEntityUpdateEvent::new(remote_entity, tick, *client_id)
+                }),
+        );
     }
 }
 
@@ -47,7 +161,7 @@ pub(crate) fn emit_replication_events<C: Component>(app: &mut App) {
     app.add_event::<ComponentInsertEvent<C>>();
     app.add_event::<ComponentRemoveEvent<C>>();
     app.add_systems(
-        PreUpdate,
+        NetworkScheduleConfig::get(app).receive,
         push_component_events::<C, ConnectionManager>
             .in_set(InternalMainSet::<ServerMarker>::EmitEvents),
     );
@@ -150,11 +264,14 @@ impl IterEntitySpawnEvent<ClientId> for ServerEvents {
 }
 
 impl IterEntityDespawnEvent<ClientId> for ServerEvents {
-    fn into_iter_entity_despawn(&mut self) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_> {
+    fn into_iter_entity_despawn(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, Option<DespawnReason>, ClientId)> + '_> {
         Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
-            let entities = events.into_iter_entity_despawn().map(|(entity, _)| entity);
-            let client_ids = std::iter::once(*client_id).cycle();
-            entities.zip(client_ids)
+            let client_id = *client_id;
+            events
+                .into_iter_entity_despawn()
+                .map(move |(entity, reason, _)| (entity, reason, client_id))
         }))
     }
 
@@ -224,6 +341,53 @@ pub struct DisconnectEvent {
     pub entity: Entity,
 }
 
+/// Bevy [`Event`] emitted on the server when an entity becomes visible to a client because of interest management
+/// (the [`VisibilityManager`](crate::server::visibility::immediate::VisibilityManager) or the rooms)
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct EntityVisibleToClient {
+    pub entity: Entity,
+    pub client_id: ClientId,
+}
+
+/// Bevy [`Event`] emitted on the server when an entity stops being visible to a client because of interest management
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct EntityHiddenFromClient {
+    pub entity: Entity,
+    pub client_id: ClientId,
+}
+
+/// Bevy [`Event`] emitted on the server when a client gains control of an entity, because the
+/// [`ControlledBy`](crate::prelude::server::ControlledBy) component of the entity changed or the client connected
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct ControlGained {
+    pub entity: Entity,
+    pub client_id: ClientId,
+}
+
+/// Bevy [`Event`] emitted on the server when a client loses control of an entity, because the
+/// [`ControlledBy`](crate::prelude::server::ControlledBy) component of the entity changed or was removed,
+/// or the entity was despawned.
+///
+/// It is not emitted for the entities of a client that disconnects.
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct ControlLost {
+    pub entity: Entity,
+    pub client_id: ClientId,
+}
+
+/// Bevy [`Event`] emitted on the server when a WebTransport server started using a new certificate
+/// (see [`ServerCommands::rotate_certificate`](crate::server::networking::ServerCommands::rotate_certificate))
+///
+/// The digest of the new certificate must be sent to the clients that validate the server certificate with a
+/// digest (i.e. wasm clients), so that they can still connect to the server.
+#[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+#[derive(Event, Debug, Clone)]
+pub struct CertificateRotatedEvent {
+    /// Index of the server transport in [`ServerConfig::net`](crate::server::config::ServerConfig::net)
+    pub server_index: usize,
+    pub digest: wtransport::tls::Sha256Digest,
+}
+
 /// Bevy [`Event`] emitted on the server on the frame where an input message from a client is received
 pub type InputEvent<I> = crate::shared::events::components::InputEvent<I, ClientId>;
 /// Bevy [`Event`] emitted on the server on the frame where a EntitySpawn replication message is received
@@ -242,8 +406,19 @@ pub type ComponentRemoveEvent<C> =
 
 /// Bevy [`Event`] emitted on the server on the frame where a (non-replication) message is received
 pub type MessageEvent<M> = crate::shared::events::components::MessageEvent<M, ClientId>;
-
-#[cfg(test)]
+/// Bevy [`Event`] emitted on the server when the progress of a transfer on a stream channel is updated
+pub type TransferProgressEvent = crate::shared::events::components::TransferProgressEvent<ClientId>;
+/// Bevy [`Event`] emitted on the server when a message sent to a client expired before being acked
+pub type MessageExpiredEvent = crate::shared::events::components::MessageExpiredEvent<ClientId>;
+/// Bevy [`Event`] emitted on the server when a fragment of a message sent to a client is acked
+pub type FragmentProgressEvent = crate::shared::events::components::FragmentProgressEvent<ClientId>;
+/// Bevy [`Event`] emitted on the server when a client received a message sent with a [`MessageHandle`](crate::packet::message::MessageHandle)
+pub type MessageAckedEvent = crate::shared::events::components::MessageAckedEvent<ClientId>;
+/// Bevy [`Event`] emitted on the server when component updates are received for an entity that doesn't exist locally
+pub type UnknownEntityUpdateEvent =
+    crate::shared::events::components::UnknownEntityUpdateEvent<ClientId>;
+
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use crate::prelude::Tick;
     use crate::protocol::channel::ChannelKind;
@@ -9,13 +9,16 @@ use crate::inputs::native::input_buffer::InputBuffer;
 use crate::inputs::native::InputMessage;
 use crate::prelude::server::MessageEvent;
 use crate::prelude::{
-    AppMessageExt, ChannelDirection, ClientId, Message, MessageRegistry, TickManager, UserAction,
+    AppMessageExt, ChannelDirection, ClientId, Message, MessageRegistry, TickManager, TimeManager,
+    UserAction,
 };
 use crate::protocol::message::MessageKind;
 use crate::protocol::BitSerializable;
 use crate::server::connection::ConnectionManager;
 use crate::server::events::InputEvent;
 use crate::server::networking::is_started;
+use crate::server::speedhack::ClientClocks;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::sets::{InternalMainSet, ServerMarker};
 
@@ -80,8 +83,9 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
         // EVENTS
         app.add_event::<InputEvent<A>>();
         // SETS
+        let schedules = NetworkScheduleConfig::get(app);
         app.configure_sets(
-            PreUpdate,
+            schedules.receive,
             InputSystemSet::ReceiveInputMessage
                 .in_set(InternalMainSet::<ServerMarker>::EmitEvents)
                 .run_if(is_started),
@@ -96,7 +100,7 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
         );
 
         app.add_systems(
-            PreUpdate,
+            schedules.receive,
             receive_input_message::<A>.in_set(InputSystemSet::ReceiveInputMessage),
         );
         app.add_systems(
@@ -113,8 +117,10 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
 /// Read the message received from the client and emit the MessageEvent event
 fn receive_input_message<A: UserAction>(
     message_registry: Res<MessageRegistry>,
+    time_manager: Res<TimeManager>,
     mut connection_manager: ResMut<ConnectionManager>,
     mut input_buffers: ResMut<InputBuffers<A>>,
+    mut client_clocks: Option<ResMut<ClientClocks>>,
 ) {
     let kind = MessageKind::of::<InputMessage<A>>();
     let Some(net) = message_registry.kind_map.net_id(&kind).copied() else {
@@ -137,6 +143,16 @@ fn receive_input_message<A: UserAction>(
                 ) {
                     Ok(message) => {
                         debug!("Received input message: {:?}", message);
+                        if client_clocks.as_mut().is_some_and(|clocks| {
+                            !clocks.on_input_message(
+                                *client_id,
+                                message.end_tick,
+                                time_manager.current_time(),
+                            )
+                        }) {
+                            connection.reader_pool.attach(reader);
+                            continue;
+                        }
                         input_buffers
                             .buffers
                             .entry(*client_id)
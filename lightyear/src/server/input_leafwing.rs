@@ -3,27 +3,33 @@ use std::ops::DerefMut;
 
 use anyhow::Context;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use leafwing_input_manager::prelude::*;
 
 use bitcode::encoding::Fixed;
 
+use crate::channel::builder::DefaultUnorderedUnreliableChannel;
 use crate::client::components::Confirmed;
 use crate::client::config::ClientConfig;
 use crate::client::prediction::Predicted;
 use crate::connection::client::NetClient;
 use crate::inputs::leafwing::input_buffer::{
-    ActionDiffBuffer, ActionDiffEvent, InputBuffer, InputTarget,
+    ActionDiff, ActionDiffBuffer, ActionDiffEvent, InputBuffer, InputTarget,
 };
-use crate::inputs::leafwing::{InputMessage, LeafwingUserAction};
+use crate::inputs::leafwing::{InputMessage, InputStateRequest, LeafwingUserAction};
 use crate::prelude::client::is_in_rollback;
 use crate::prelude::server::MessageEvent;
-use crate::prelude::{client, MessageRegistry, Mode, SharedConfig, TickManager};
+use crate::prelude::{
+    client, ClientId, MessageRegistry, Mode, SharedConfig, Tick, TickManager, TimeManager,
+};
 use crate::protocol::message::MessageKind;
 use crate::protocol::registry::NetId;
 use crate::protocol::BitSerializable;
 use crate::server::config::ServerConfig;
 use crate::server::connection::ConnectionManager;
 use crate::server::networking::is_started;
+use crate::server::speedhack::ClientClocks;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::replication::components::PrePredicted;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::sets::{InternalMainSet, ServerMarker};
@@ -67,12 +73,13 @@ pub enum InputSystemSet {
 
 impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A> {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         // RESOURCES
         // app.init_resource::<GlobalActions<A>>();
         // TODO: (global action states) add a resource tracking the action-state of all clients
         // SETS
         app.configure_sets(
-            PreUpdate,
+            schedules.receive,
             (
                 InternalMainSet::<ServerMarker>::Receive,
                 InputSystemSet::AddBuffers,
@@ -84,10 +91,14 @@ impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A> {
         app.configure_sets(FixedPreUpdate, InputSystemSet::Update.run_if(is_started));
         // SYSTEMS
         app.add_systems(
-            PreUpdate,
+            schedules.receive,
             (
                 // TODO: ideally we have a Flush between add_action_diff_buffer and Tick?
-                add_action_diff_buffer::<A>.in_set(InputSystemSet::AddBuffers),
+                (
+                    add_action_diff_buffer::<A>,
+                    update_shared_action_states::<A>,
+                )
+                    .in_set(InputSystemSet::AddBuffers),
                 receive_input_message::<A>.in_set(InputSystemSet::ReceiveInputs),
             ),
         );
@@ -105,6 +116,111 @@ impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A> {
     }
 }
 
+/// Inputs received from each client that controls the entity, for the entities that are controlled by
+/// several clients at the same time (see [`ControlledBy`](crate::prelude::server::ControlledBy)).
+///
+/// Each controller sends the diffs of its own [`ActionState`], and the [`ActionState`] of the entity
+/// is the merge of the action states of all its controllers:
+/// - an action is pressed if any controller presses it
+/// - its value is the largest value among the controllers that press it
+/// - its axis pair is the sum of the axis pairs of the controllers that press it
+///
+/// The component is added and removed automatically when the number of controllers of the entity changes.
+#[derive(Component)]
+pub struct SharedActionStates<A: LeafwingUserAction> {
+    controllers: HashMap<ClientId, (ActionDiffBuffer<A>, ActionState<A>)>,
+}
+
+impl<A: LeafwingUserAction> Default for SharedActionStates<A> {
+    fn default() -> Self {
+        Self {
+            controllers: HashMap::default(),
+        }
+    }
+}
+
+impl<A: LeafwingUserAction> SharedActionStates<A> {
+    /// The [`ActionState`] of one of the controllers of the entity
+    pub fn get(&self, client_id: ClientId) -> Option<&ActionState<A>> {
+        self.controllers.get(&client_id).map(|(_, state)| state)
+    }
+
+    fn buffer_mut(&mut self, client_id: ClientId) -> &mut ActionDiffBuffer<A> {
+        &mut self.controllers.entry(client_id).or_default().0
+    }
+
+    /// Update the action state of each controller with its inputs for this tick
+    fn update(&mut self, tick: Tick) {
+        for (buffer, state) in self.controllers.values_mut() {
+            apply_action_diffs(tick, state, buffer);
+        }
+    }
+
+    /// The [`ActionState`] that combines the action states of all the controllers
+    fn merged(&self) -> ActionState<A> {
+        let mut merged = ActionState::<A>::default();
+        for (_, state) in self.controllers.values() {
+            for action in state.get_pressed() {
+                let pressed = merged.pressed(&action);
+                let diff = match state.axis_pair(&action) {
+                    Some(axis_pair) => ActionDiff::AxisPairChanged {
+                        axis_pair: merged
+                            .axis_pair(&action)
+                            .filter(|_| pressed)
+                            .map_or(Vec2::ZERO, |merged| merged.xy())
+                            + axis_pair.xy(),
+                        action,
+                    },
+                    None => ActionDiff::ValueChanged {
+                        value: if pressed {
+                            merged.value(&action).max(state.value(&action))
+                        } else {
+                            state.value(&action)
+                        },
+                        action,
+                    },
+                };
+                diff.apply(&mut merged);
+            }
+        }
+        merged
+    }
+}
+
+/// Keep a separate input buffer for each controller of the entities that are controlled by several clients
+fn update_shared_action_states<A: LeafwingUserAction>(
+    mut commands: Commands,
+    connection_manager: Res<ConnectionManager>,
+    mut query: Query<(Entity, Option<&mut SharedActionStates<A>>), With<ActionDiffBuffer<A>>>,
+) {
+    for (entity, shared) in query.iter_mut() {
+        let controllers = connection_manager.control.controllers(entity);
+        match shared {
+            Some(mut shared) if controllers.len() > 1 => {
+                // forget the clients that stopped controlling the entity
+                shared
+                    .bypass_change_detection()
+                    .controllers
+                    .retain(|client_id, _| controllers.contains(client_id));
+            }
+            Some(_) => {
+                commands.entity(entity).remove::<SharedActionStates<A>>();
+            }
+            None if controllers.len() > 1 => {
+                debug!(
+                    ?entity,
+                    ?controllers,
+                    "entity is controlled by several clients"
+                );
+                commands
+                    .entity(entity)
+                    .insert(SharedActionStates::<A>::default());
+            }
+            None => {}
+        }
+    }
+}
+
 /// For each entity that has an action-state, insert an action-state-buffer
 /// that will store the value of the action-state for the last few ticks
 /// (we use a buffer because the client's inputs might arrive out of order)
@@ -127,12 +243,16 @@ fn add_action_diff_buffer<A: LeafwingUserAction>(
 }
 
 /// Read the input messages from the server events to update the ActionDiffBuffers
+///
+/// If some diffs are missing, the full [`ActionState`] is requested from the client.
 fn receive_input_message<A: LeafwingUserAction>(
     // mut global: Option<ResMut<ActionDiffBuffer<A>>>,
     message_registry: Res<MessageRegistry>,
+    tick_manager: Res<TickManager>,
+    time_manager: Res<TimeManager>,
     mut connection_manager: ResMut<ConnectionManager>,
-    // TODO: currently we do not handle entities that are controlled by multiple clients
-    mut query: Query<&mut ActionDiffBuffer<A>>,
+    mut client_clocks: Option<ResMut<ClientClocks>>,
+    mut query: Query<(&mut ActionDiffBuffer<A>, Option<&mut SharedActionStates<A>>)>,
 ) {
     let kind = MessageKind::of::<InputMessage<A>>();
     let Some(net) = message_registry.kind_map.net_id(&kind).copied() else {
@@ -142,6 +262,9 @@ fn receive_input_message<A: LeafwingUserAction>(
         );
         return;
     };
+    let tick = tick_manager.tick();
+    // clients that should send their full ActionStates
+    let mut state_requests = vec![];
     // re-borrow to allow split borrows
     let connection_manager = connection_manager.deref_mut();
     for (client_id, connection) in connection_manager.connections.iter_mut() {
@@ -157,6 +280,31 @@ fn receive_input_message<A: LeafwingUserAction>(
                 ) {
                     Ok(mut message) => {
                         debug!(action = ?A::short_type_path(), ?message.end_tick, ?message.diffs, "received input message");
+                        if client_clocks.as_mut().is_some_and(|clocks| {
+                            !clocks.on_input_message(
+                                *client_id,
+                                message.end_tick,
+                                time_manager.current_time(),
+                            )
+                        }) {
+                            connection.reader_pool.attach(reader);
+                            continue;
+                        }
+                        for (target, state) in message.states.iter() {
+                            if let InputTarget::Entity(entity)
+                            | InputTarget::PrePredictedEntity(entity) = target
+                            {
+                                if let Ok((buffer, shared)) = query.get_mut(*entity) {
+                                    // the entities controlled by several clients have one buffer per controller
+                                    let buffer = match shared {
+                                        Some(shared) => shared.into_inner().buffer_mut(*client_id),
+                                        None => buffer.into_inner(),
+                                    };
+                                    debug!(?entity, end_tick = ?message.end_tick, "received full action state");
+                                    buffer.set_full_state(message.end_tick, state.clone());
+                                }
+                            }
+                        }
                         for (target, diffs) in std::mem::take(&mut message.diffs) {
                             match target {
                                 // - for pre-predicted entities, we already did the mapping on server side upon receiving the message
@@ -166,8 +314,20 @@ fn receive_input_message<A: LeafwingUserAction>(
                                 InputTarget::Entity(entity)
                                 | InputTarget::PrePredictedEntity(entity) => {
                                     debug!("received input for entity: {:?}", entity);
-                                    if let Ok(mut buffer) = query.get_mut(entity) {
+                                    if let Ok((buffer, shared)) = query.get_mut(entity) {
+                                        let buffer = match shared {
+                                            Some(shared) => {
+                                                shared.into_inner().buffer_mut(*client_id)
+                                            }
+                                            None => buffer.into_inner(),
+                                        };
                                         debug!(?entity, ?diffs, end_tick = ?message.end_tick, "update action diff buffer for PREPREDICTED using input message");
+                                        if buffer.is_missing_ticks(message.end_tick, diffs.len())
+                                            && buffer.request_full_state(tick)
+                                        {
+                                            debug!(?entity, ?client_id, end_tick = ?message.end_tick, "some action diffs are missing, requesting the full action state");
+                                            state_requests.push(*client_id);
+                                        }
                                         buffer.update_from_message(message.end_tick, diffs);
                                     } else {
                                         // TODO: maybe if the entity is pre-predicted, apply map-entities, so we can handle pre-predicted inputs
@@ -204,18 +364,37 @@ fn receive_input_message<A: LeafwingUserAction>(
             }
         }
     }
+    state_requests.dedup();
+    for client_id in state_requests {
+        connection_manager
+            .send_message::<DefaultUnorderedUnreliableChannel, _>(
+                client_id,
+                &InputStateRequest::<A>::default(),
+            )
+            .unwrap_or_else(|err| {
+                error!(?err, "could not send the input state request");
+            });
+    }
 }
 
 /// Read the ActionDiff for the current tick from the buffer, and use them to update the ActionState
+///
+/// If the client sent its full ActionState for this tick, the actions that don't match it are corrected.
 fn update_action_state<A: LeafwingUserAction>(
     tick_manager: Res<TickManager>,
     // global_input_buffer: Res<InputBuffer<A>>,
     // global_action_state: Option<ResMut<ActionState<A>>>,
-    mut action_state_query: Query<(Entity, &mut ActionState<A>, &mut ActionDiffBuffer<A>)>,
+    mut action_state_query: Query<(
+        Entity,
+        &mut ActionState<A>,
+        &mut ActionDiffBuffer<A>,
+        Option<&mut SharedActionStates<A>>,
+    )>,
 ) {
     let tick = tick_manager.tick();
 
-    for (entity, mut action_state, mut action_diff_buffer) in action_state_query.iter_mut() {
+    for (entity, mut action_state, mut action_diff_buffer, shared) in action_state_query.iter_mut()
+    {
         // the state on the server is only updated from client inputs!
         trace!(
             ?tick,
@@ -225,15 +404,46 @@ fn update_action_state<A: LeafwingUserAction>(
             &action_state.get_pressed(),
             action_diff_buffer.end_tick(),
         );
-        action_diff_buffer.pop(tick).into_iter().for_each(|diff| {
-            debug!(
-                ?tick,
-                ?entity,
-                "update action state using action diff: {:?}",
-                &diff
-            );
-            diff.apply(action_state.deref_mut());
-        });
+        match shared {
+            // the entity is controlled by several clients: merge their action states
+            Some(mut shared) => {
+                shared.update(tick);
+                ActionDiff::between(&action_state, &shared.merged())
+                    .into_iter()
+                    .for_each(|diff| {
+                        debug!(?tick, ?entity, "update shared action state: {:?}", &diff);
+                        diff.apply(action_state.deref_mut());
+                    });
+            }
+            None => apply_action_diffs(
+                tick,
+                action_state.deref_mut(),
+                action_diff_buffer.deref_mut(),
+            ),
+        }
+    }
+}
+
+/// Apply the diffs (and the full state, if any) of the buffer for this tick to the action state
+fn apply_action_diffs<A: LeafwingUserAction>(
+    tick: Tick,
+    action_state: &mut ActionState<A>,
+    action_diff_buffer: &mut ActionDiffBuffer<A>,
+) {
+    action_diff_buffer.pop(tick).into_iter().for_each(|diff| {
+        debug!(?tick, "update action state using action diff: {:?}", &diff);
+        diff.apply(action_state);
+    });
+    if let Some(full_state) = action_diff_buffer.pop_full_state(tick) {
+        ActionDiff::between(action_state, &full_state)
+            .into_iter()
+            .for_each(|diff| {
+                debug!(
+                    ?tick,
+                    "correct action state using the full state: {:?}", &diff
+                );
+                diff.apply(action_state);
+            });
     }
 }
 
@@ -408,4 +618,77 @@ mod tests {
             }]
         );
     }
+
+    #[test]
+    fn test_merge_shared_action_states() {
+        let client_1 = ClientId::Netcode(1);
+        let client_2 = ClientId::Netcode(2);
+        let mut shared = SharedActionStates::<LeafwingInput1>::default();
+        let set_state = |shared: &mut SharedActionStates<LeafwingInput1>,
+                         client_id,
+                         diff: Option<ActionDiff<LeafwingInput1>>| {
+            let mut state = ActionState::<LeafwingInput1>::default();
+            if let Some(diff) = diff {
+                diff.apply(&mut state);
+            }
+            shared.controllers.entry(client_id).or_default().1 = state;
+        };
+
+        // the action is pressed if any controller presses it
+        set_state(
+            &mut shared,
+            client_1,
+            Some(ActionDiff::Pressed {
+                action: LeafwingInput1::Jump,
+            }),
+        );
+        set_state(&mut shared, client_2, None);
+        assert!(shared.merged().pressed(&LeafwingInput1::Jump));
+        set_state(&mut shared, client_1, None);
+        assert!(!shared.merged().pressed(&LeafwingInput1::Jump));
+
+        // the largest value is kept
+        set_state(
+            &mut shared,
+            client_1,
+            Some(ActionDiff::ValueChanged {
+                action: LeafwingInput1::Jump,
+                value: 0.5,
+            }),
+        );
+        set_state(
+            &mut shared,
+            client_2,
+            Some(ActionDiff::ValueChanged {
+                action: LeafwingInput1::Jump,
+                value: 0.25,
+            }),
+        );
+        assert_eq!(shared.merged().value(&LeafwingInput1::Jump), 0.5);
+
+        // the axis pairs are added
+        set_state(
+            &mut shared,
+            client_1,
+            Some(ActionDiff::AxisPairChanged {
+                action: LeafwingInput1::Jump,
+                axis_pair: Vec2::new(1.0, 0.0),
+            }),
+        );
+        set_state(
+            &mut shared,
+            client_2,
+            Some(ActionDiff::AxisPairChanged {
+                action: LeafwingInput1::Jump,
+                axis_pair: Vec2::new(0.0, 1.0),
+            }),
+        );
+        assert_eq!(
+            shared
+                .merged()
+                .axis_pair(&LeafwingInput1::Jump)
+                .map(|axis_pair| axis_pair.xy()),
+            Some(Vec2::new(1.0, 1.0))
+        );
+    }
 }
@@ -0,0 +1,289 @@
+/*! Read-only feed of the server world for remote inspectors
+
+When [`ServerConfig::inspector`](crate::server::config::ServerConfig::inspector) is set, the clients can request
+to inspect the server world by sending an [`InspectorRequest`] that contains the admin token of the
+[`InspectorConfig`]. The server then sends an [`InspectorSnapshot`] to these clients every
+[`InspectorConfig::update_interval`], on the [`InspectorChannel`]. The snapshot contains:
+- the current tick of the server
+- the list of the replicated entities
+- the values of the components of these entities that are registered in the protocol
+
+The feed is read-only: the inspectors cannot modify the server world. The requests that contain an invalid token
+are ignored.
+
+The inspector is a regular client that uses the same protocol as the server, so that it can decode the
+component values. The [`InspectorClientPlugin`](crate::client::inspector::InspectorClientPlugin) sends the request
+when the client connects and inserts the latest snapshot as a resource:
+```rust,ignore
+app.add_plugins(InspectorClientPlugin::new("admin-token"));
+
+fn display_positions(snapshot: Res<InspectorSnapshot>, registry: Res<ComponentRegistry>) {
+    for entity in snapshot.entities() {
+        if let Ok(Some(position)) = snapshot.component::<Position>(&registry, entity) {
+            info!(?entity, ?position, tick = ?snapshot.tick);
+        }
+    }
+}
+```
+*/
+use anyhow::Context;
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashSet};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::channel::builder::InspectorChannel;
+use crate::prelude::{ClientId, ComponentRegistry, NetworkTarget, Tick, TickManager, TimeManager};
+use crate::protocol::component::{ComponentKind, ComponentNetId};
+use crate::serialize::bitcode::writer::BitcodeWriter;
+use crate::serialize::writer::WriteBuffer;
+use crate::serialize::RawData;
+#[cfg(feature = "server")]
+use crate::server::config::ServerConfig;
+#[cfg(feature = "server")]
+use crate::server::connection::ConnectionManager;
+#[cfg(feature = "server")]
+use crate::server::events::{DisconnectEvent, MessageEvent};
+#[cfg(feature = "server")]
+use crate::server::networking::is_started;
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::replication::components::Replicating;
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+use crate::shared::time_manager::WrappedTime;
+
+/// Configuration of the remote inspection of the server world
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct InspectorConfig {
+    /// Token that the clients must present to inspect the server world
+    pub admin_token: String,
+    /// How often the [`InspectorSnapshot`] is sent to the inspectors
+    pub update_interval: Duration,
+}
+
+impl InspectorConfig {
+    pub fn new(admin_token: impl Into<String>) -> Self {
+        Self {
+            admin_token: admin_token.into(),
+            update_interval: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_update_interval(mut self, update_interval: Duration) -> Self {
+        self.update_interval = update_interval;
+        self
+    }
+}
+
+/// Message sent by a client to start receiving the [`InspectorSnapshot`]s
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InspectorRequest {
+    pub admin_token: String,
+}
+
+/// The value of a component, serialized with the protocol of the server
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InspectedComponent {
+    pub net_id: ComponentNetId,
+    pub value: RawData,
+}
+
+/// A replicated entity of the server world
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InspectedEntity {
+    /// The entity in the server world
+    pub entity: Entity,
+    /// The components of the entity that are registered in the protocol
+    pub components: Vec<InspectedComponent>,
+}
+
+/// Snapshot of the replicated entities of the server world, sent to the inspectors
+///
+/// On the client, this resource is inserted by the [`InspectorClientPlugin`](crate::client::inspector::InspectorClientPlugin)
+/// when a snapshot is received.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct InspectorSnapshot {
+    /// Tick of the server when the snapshot was taken
+    pub tick: Tick,
+    pub entities: Vec<InspectedEntity>,
+}
+
+impl InspectorSnapshot {
+    /// The entities of the snapshot (in the server world)
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().map(|inspected| inspected.entity)
+    }
+
+    /// Decode the value of the component `C` of the entity.
+    ///
+    /// Returns None if the entity is not in the snapshot or doesn't have the component.
+    /// The entities referenced by the component are the entities of the server world.
+    pub fn component<C: Component>(
+        &self,
+        registry: &ComponentRegistry,
+        entity: Entity,
+    ) -> anyhow::Result<Option<C>> {
+        let Some(net_id) = registry.get_net_id::<C>() else {
+            return Ok(None);
+        };
+        let Some(inspected) = self
+            .entities
+            .iter()
+            .find(|inspected| inspected.entity == entity)
+            .and_then(|inspected| {
+                inspected
+                    .components
+                    .iter()
+                    .find(|component| component.net_id == net_id)
+            })
+        else {
+            return Ok(None);
+        };
+        registry
+            .deserialize_inspected::<C>(net_id, &inspected.value)
+            .context("could not decode the inspected component")
+            .map(Some)
+    }
+}
+
+/// The clients that were authorized to inspect the server world
+#[cfg(feature = "server")]
+#[derive(Resource, Debug)]
+struct InspectorState {
+    config: InspectorConfig,
+    inspectors: HashSet<ClientId>,
+    last_update: Option<WrappedTime>,
+}
+
+#[cfg(feature = "server")]
+pub(crate) struct InspectorPlugin;
+
+#[cfg(feature = "server")]
+impl Plugin for InspectorPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    // the config is read in `finish` so that it can still be modified after the plugins are added
+    fn finish(&self, app: &mut App) {
+        let Some(config) = app.world.resource::<ServerConfig>().inspector.clone() else {
+            return;
+        };
+        app.insert_resource(InspectorState {
+            config,
+            inspectors: HashSet::default(),
+            last_update: None,
+        });
+        let schedules = NetworkScheduleConfig::get(app);
+        app.add_systems(
+            schedules.receive,
+            authorize_inspectors.after(InternalMainSet::<ServerMarker>::EmitEvents),
+        );
+        app.add_systems(
+            schedules.send,
+            send_inspector_snapshots
+                .before(InternalMainSet::<ServerMarker>::SendPackets)
+                .run_if(is_started),
+        );
+    }
+}
+
+/// Compare the tokens in constant time, so that the admin token cannot be guessed from the response times
+#[cfg(feature = "server")]
+fn tokens_match(token: &str, admin_token: &str) -> bool {
+    token.len() == admin_token.len()
+        && token
+            .bytes()
+            .zip(admin_token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Add the clients that presented the admin token to the inspectors
+#[cfg(feature = "server")]
+fn authorize_inspectors(
+    mut state: ResMut<InspectorState>,
+    mut requests: EventReader<MessageEvent<InspectorRequest>>,
+    mut disconnect_events: EventReader<DisconnectEvent>,
+) {
+    for event in disconnect_events.read() {
+        state.inspectors.remove(&event.client_id);
+    }
+    for event in requests.read() {
+        let client_id = *event.context();
+        if tokens_match(&event.message.admin_token, &state.config.admin_token) {
+            state.inspectors.insert(client_id);
+        } else {
+            warn!(
+                ?client_id,
+                "Ignoring inspector request with an invalid admin token"
+            );
+        }
+    }
+}
+
+/// Send a snapshot of the replicated entities to the inspectors once per update interval
+#[cfg(feature = "server")]
+fn send_inspector_snapshots(world: &mut World) {
+    let now = world.resource::<TimeManager>().current_time();
+    let state = world.resource::<InspectorState>();
+    if state.inspectors.is_empty()
+        || state
+            .last_update
+            .is_some_and(|last_update| now < last_update + state.config.update_interval)
+    {
+        return;
+    }
+    let inspectors = state.inspectors.iter().copied().collect::<Vec<_>>();
+    world.resource_mut::<InspectorState>().last_update = Some(now);
+
+    let mut system_state: SystemState<Query<EntityRef, With<Replicating>>> =
+        SystemState::new(world);
+    let snapshot = {
+        let query = system_state.get(world);
+        let registry = world.resource::<ComponentRegistry>();
+        let components = world.components();
+        let mut writer = BitcodeWriter::with_capacity(64);
+        InspectorSnapshot {
+            tick: world.resource::<TickManager>().tick(),
+            entities: query
+                .iter()
+                .map(|entity| InspectedEntity {
+                    entity: entity.id(),
+                    components: entity
+                        .archetype()
+                        .components()
+                        .filter_map(|component_id| {
+                            let kind =
+                                ComponentKind::from(components.get_info(component_id)?.type_id()?);
+                            match registry.raw_inspect(&kind, entity, &mut writer)? {
+                                Ok((net_id, value)) => Some(InspectedComponent { net_id, value }),
+                                Err(e) => {
+                                    error!(?e, "could not serialize the inspected component");
+                                    None
+                                }
+                            }
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    };
+    if let Err(e) = world
+        .resource_mut::<ConnectionManager>()
+        .send_message_to_target::<InspectorChannel, _>(&snapshot, NetworkTarget::Only(inspectors))
+    {
+        error!(?e, "could not send the inspector snapshot");
+    }
+}
+
+#[cfg(all(feature = "server", test))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+        assert!(!tokens_match("", "secret"));
+    }
+}
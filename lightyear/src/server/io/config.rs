@@ -2,7 +2,7 @@ use super::*;
 use crate::prelude::CompressionConfig;
 use crate::server::io::transport::{ServerTransportBuilder, ServerTransportBuilderEnum};
 use crate::transport::channels::Channels;
-use crate::transport::config::SharedIoConfig;
+use crate::transport::config::{LivenessConfig, SharedIoConfig, SocketConfig};
 use crate::transport::dummy::DummyIo;
 use crate::transport::io::IoStats;
 #[cfg(feature = "zstd")]
@@ -11,9 +11,10 @@ use crate::transport::middleware::compression::zstd::compression::ZstdCompressor
 use crate::transport::middleware::compression::zstd::decompression::ZstdDecompressor;
 use crate::transport::middleware::conditioner::LinkConditioner;
 use crate::transport::middleware::{PacketReceiverWrapper, PacketSenderWrapper};
+use crate::transport::relay;
 use crate::transport::udp::UdpSocketBuilder;
 #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
-use crate::transport::websocket::server::WebSocketServerSocketBuilder;
+use crate::transport::websocket::server::{WebSocketServerSocketBuilder, WebSocketServerTls};
 #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
 use crate::transport::webtransport::server::WebTransportServerSocketBuilder;
 use crate::transport::BoxedReceiver;
@@ -37,7 +38,11 @@ pub enum ServerTransport {
     },
     /// Use [`WebSocket`](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket) as a transport
     #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
-    WebSocketServer { server_addr: SocketAddr },
+    WebSocketServer {
+        server_addr: SocketAddr,
+        /// If provided, the server accepts secure WebSocket (`wss://`) connections instead of plain `ws://` connections
+        tls: Option<WebSocketServerTls>,
+    },
     /// Use a crossbeam_channel as a transport. This is useful for testing.
     /// This is server-only: each tuple corresponds to a different client.
     Channels {
@@ -70,8 +75,10 @@ impl Clone for ServerTransport {
             #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
             ServerTransport::WebSocketServer {
                 server_addr: __self_0,
+                tls: __self_1,
             } => ServerTransport::WebSocketServer {
                 server_addr: Clone::clone(__self_0),
+                tls: Clone::clone(__self_1),
             },
             ServerTransport::Channels { channels: __self_0 } => ServerTransport::Channels {
                 channels: Clone::clone(__self_0),
@@ -82,10 +89,28 @@ impl Clone for ServerTransport {
 }
 
 impl ServerTransport {
-    fn build(self) -> ServerTransportBuilderEnum {
+    /// Returns true if the transport can check the liveness of the connections itself
+    fn has_native_liveness(&self) -> bool {
+        match self {
+            #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+            ServerTransport::WebTransportServer { .. } => true,
+            #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
+            ServerTransport::WebSocketServer { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn build(
+        self,
+        socket_config: SocketConfig,
+        liveness: Option<LivenessConfig>,
+    ) -> ServerTransportBuilderEnum {
         match self {
             ServerTransport::UdpSocket(addr) => {
-                ServerTransportBuilderEnum::UdpSocket(UdpSocketBuilder { local_addr: addr })
+                ServerTransportBuilderEnum::UdpSocket(UdpSocketBuilder {
+                    local_addr: addr,
+                    config: socket_config,
+                })
             }
             #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
             ServerTransport::WebTransportServer {
@@ -94,11 +119,14 @@ impl ServerTransport {
             } => ServerTransportBuilderEnum::WebTransportServer(WebTransportServerSocketBuilder {
                 server_addr,
                 certificate,
+                liveness,
             }),
             #[cfg(all(feature = "websocket", not(target_family = "wasm")))]
-            ServerTransport::WebSocketServer { server_addr } => {
+            ServerTransport::WebSocketServer { server_addr, tls } => {
                 ServerTransportBuilderEnum::WebSocketServer(WebSocketServerSocketBuilder {
                     server_addr,
+                    tls,
+                    liveness,
                 })
             }
             ServerTransport::Channels { channels } => {
@@ -117,10 +145,17 @@ impl Default for ServerTransport {
 
 impl SharedIoConfig<ServerTransport> {
     pub fn start(self) -> Result<Io> {
-        let (transport, state, io_rx, network_tx) = self.transport.build().start()?;
+        let native_liveness = self.liveness.is_some() && self.transport.has_native_liveness();
+        let (transport, state, io_rx, network_tx) =
+            self.transport.build(self.socket, self.liveness).start()?;
         let local_addr = transport.local_addr();
+        let packet_sizing = transport.packet_sizing();
+        let (sender, receiver) = transport.split();
         #[allow(unused_mut)]
-        let (mut sender, receiver) = transport.split();
+        let (mut sender, receiver) = match self.relay {
+            Some(relay_config) => relay::wrap_server(relay_config, sender, receiver),
+            None => (sender, receiver),
+        };
         #[allow(unused_mut)]
         let mut receiver: BoxedReceiver = if let Some(conditioner_config) = self.conditioner {
             let conditioner = LinkConditioner::new(conditioner_config);
@@ -144,6 +179,8 @@ impl SharedIoConfig<ServerTransport> {
             receiver,
             state,
             stats: IoStats::default(),
+            packet_sizing,
+            native_liveness,
             context: IoContext {
                 event_sender: network_tx,
                 event_receiver: io_rx,
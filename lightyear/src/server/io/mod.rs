@@ -40,6 +40,12 @@ pub(crate) enum ServerIoEvent {
     ServerConnected,
     ServerDisconnected(Error),
     ClientDisconnected(SocketAddr),
+    /// Replace the certificate used by the WebTransport server
+    #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+    RotateCertificate(wtransport::Identity),
+    /// The WebTransport server now uses the certificate with this digest
+    #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+    CertificateRotated(wtransport::tls::Sha256Digest),
 }
 
 /// Events that will be sent from the main thread to the io thread
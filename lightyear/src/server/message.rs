@@ -1,8 +1,8 @@
 use std::ops::DerefMut;
 
 use anyhow::Context;
-use bevy::app::{App, PreUpdate};
-use bevy::prelude::{EventWriter, IntoSystemConfigs, Res, ResMut, Resource};
+use bevy::app::App;
+use bevy::prelude::{Entity, EventWriter, IntoSystemConfigs, Res, ResMut, Resource};
 use bevy::utils::HashMap;
 use bytes::Bytes;
 use tracing::{error, info_span, trace};
@@ -10,18 +10,27 @@ use tracing::{error, info_span, trace};
 use bitcode::__private::Fixed;
 use bitcode::{Decode, Encode};
 
+use crate::packet::compression::SupportedCompressions;
 use crate::packet::message::SingleData;
 use crate::prelude::{MainSet, Message};
+use crate::protocol::channel::ChannelRegistration;
+use crate::protocol::component::ComponentNetId;
 use crate::protocol::message::{MessageKind, MessageRegistry};
 use crate::protocol::registry::NetId;
 use crate::protocol::BitSerializable;
+use crate::protocol::DeniedReason;
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::serialize::RawData;
+#[cfg(feature = "server")]
 use crate::server::connection::ConnectionManager;
+#[cfg(feature = "server")]
 use crate::server::events::MessageEvent;
+#[cfg(feature = "server")]
 use crate::server::networking::is_started;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::ping::message::{Ping, Pong, SyncMessage};
+use crate::shared::replication::components::ReplicationGroupId;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::{ReplicationMessage, ReplicationMessageData};
 use crate::shared::sets::{InternalMainSet, ServerMarker};
@@ -40,25 +49,63 @@ pub enum ServerMessage {
     Ping(Ping),
     #[bitcode_hint(frequency = 1)]
     Pong(Pong),
+    /// Network id of a channel that was registered at runtime
+    ChannelRegistration(ChannelRegistration),
+    /// Compression algorithms that the server can decompress, sent in response to the client's
+    SupportedCompressions(SupportedCompressions),
+    /// The server refused the connection, and will disconnect the client
+    ConnectionDenied(DeniedReason),
+    /// Value kept by the server for a component of an entity of the client, that didn't pass the
+    /// [validation](crate::protocol::component::ValidateFn) of the component
+    ComponentCorrection(ComponentCorrection),
+    /// Send intervals (in microseconds) of the replication groups that the client receives and that are sent
+    /// less often than the server's send interval (`None` if the group is sent at the server's send interval again)
+    GroupSendIntervals(Vec<(ReplicationGroupId, Option<u64>)>),
+    /// Network ids assigned by the server to the dynamic components that the client registered too
+    DynamicComponentIds(Vec<(String, ComponentNetId)>),
+}
+
+/// Value of a component that the client must apply to its own entity
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct ComponentCorrection {
+    /// The entity in the client world
+    #[bitcode(with_serde)]
+    pub entity: Entity,
+    /// The serialized component, including its network id
+    pub component: RawData,
 }
 
 /// Read the messages received from the clients and emit the MessageEvent event
+#[cfg(feature = "server")]
 fn read_message<M: Message>(
     message_registry: Res<MessageRegistry>,
     mut connection_manager: ResMut<ConnectionManager>,
     mut event: EventWriter<MessageEvent<M>>,
 ) {
     let kind = MessageKind::of::<M>();
-    let Some(net) = message_registry.kind_map.net_id(&kind).copied() else {
+    // re-borrow to allow split borrows
+    let connection_manager = connection_manager.deref_mut();
+    if message_registry.kind_map.net_id(&kind).is_none()
+        && !connection_manager
+            .protocols
+            .values()
+            .any(|registries| registries.message_registry.kind_map.net_id(&kind).is_some())
+    {
         error!(
             "Could not find the network id for the message kind: {:?}",
             kind
         );
         return;
-    };
-    // re-borrow to allow split borrows
-    let connection_manager = connection_manager.deref_mut();
+    }
     for (client_id, connection) in connection_manager.connections.iter_mut() {
+        // the clients of the additional protocols use the network ids of their protocol
+        let message_registry = match connection.protocol {
+            Some(index) => &connection_manager.protocols[&index].message_registry,
+            None => message_registry.as_ref(),
+        };
+        let Some(net) = message_registry.kind_map.net_id(&kind).copied() else {
+            continue;
+        };
         if let Some(message_list) = connection.received_messages.remove(&net) {
             for (message_bytes, target, channel_kind) in message_list {
                 let mut reader = connection.reader_pool.start_read(&message_bytes);
@@ -100,10 +147,11 @@ fn read_message<M: Message>(
 }
 
 /// Register a message that can be sent from server to client
+#[cfg(feature = "server")]
 pub(crate) fn add_client_to_server_message<M: Message>(app: &mut App) {
     app.add_event::<MessageEvent<M>>();
     app.add_systems(
-        PreUpdate,
+        NetworkScheduleConfig::get(app).receive,
         read_message::<M>
             .in_set(InternalMainSet::<ServerMarker>::EmitEvents)
             .run_if(is_started),
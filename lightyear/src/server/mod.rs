@@ -2,26 +2,51 @@
 //!
 //! # Server
 //! The server module contains all the code that is used to run the server.
+//!
+//! Without the `server` feature, only the messages and resources that are needed to define the protocol are available.
+
+#[cfg(feature = "server")]
+pub mod bandwidth_estimate;
 
+#[cfg(feature = "server")]
 pub mod config;
 
+#[cfg(feature = "server")]
 pub mod connection;
 
+#[cfg(feature = "server")]
 pub mod events;
 
+#[cfg(feature = "server")]
 pub mod input;
 
+pub mod inspector;
+
+#[cfg(feature = "server")]
 pub(crate) mod io;
 
+#[cfg(feature = "server")]
 pub mod plugin;
 
 #[cfg_attr(docsrs, doc(cfg(feature = "leafwing")))]
-#[cfg(feature = "leafwing")]
+#[cfg(all(feature = "server", feature = "leafwing"))]
 pub mod input_leafwing;
 pub(crate) mod message;
 pub(crate) mod prediction;
+#[cfg(feature = "server")]
+pub mod priority;
+#[cfg(feature = "server")]
+pub mod protocols;
 
+#[cfg(feature = "server")]
 pub(crate) mod clients;
+#[cfg(feature = "server")]
 pub(crate) mod networking;
+#[cfg(feature = "server")]
+pub mod persistence;
+#[cfg(feature = "server")]
 pub mod replication;
+#[cfg(feature = "server")]
+pub mod speedhack;
+pub mod stats;
 pub mod visibility;
@@ -5,24 +5,30 @@ use bevy::ecs::system::{RunSystemOnce, SystemChangeTick, SystemParam};
 use bevy::prelude::*;
 use tracing::{debug, error, trace, trace_span};
 
-use crate::client::config::ClientConfig;
-use crate::client::networking::is_disconnected;
-use crate::connection::client::{ClientConnection, NetClient};
+use crate::channel::stats::{update_server_channel_stats, ChannelStats};
 use crate::connection::server::{
     IoConfig, NetConfig, NetServer, ServerConnection, ServerConnections,
 };
 use crate::prelude::{ChannelRegistry, MainSet, MessageRegistry, Mode, TickManager, TimeManager};
 use crate::protocol::component::ComponentRegistry;
-use crate::server::clients::ControlledEntities;
+use crate::protocol::registries_hash;
+use crate::server::bandwidth_estimate::BandwidthEstimate;
+use crate::server::clients::{client_entity_bundle, ReplicationPriorities};
 use crate::server::config::ServerConfig;
 use crate::server::connection::ConnectionManager;
+#[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+use crate::server::events::CertificateRotatedEvent;
 use crate::server::events::{ConnectEvent, DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent};
 use crate::server::io::ServerIoEvent;
+use crate::server::protocols::AdditionalProtocols;
 use crate::server::visibility::room::RoomManager;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::events::connection::{IterEntityDespawnEvent, IterEntitySpawnEvent};
 use crate::shared::replication::ReplicationSend;
-use crate::shared::sets::{InternalMainSet, ServerMarker};
+use crate::shared::sets::{InternalMainSet, InternalReplicationSet, ServerMarker};
 use crate::shared::time_manager::is_server_ready_to_send;
+use crate::shared::timings::{add_set_timing, TimedPhase};
+use crate::transport::Delivery;
 
 /// Plugin handling the server networking systems: sending/receiving packets to clients
 #[derive(Default)]
@@ -36,14 +42,17 @@ pub(crate) struct ServerNetworkingPlugin;
 
 impl Plugin for ServerNetworkingPlugin {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         app
             // REFLECTION
             .register_type::<IoConfig>()
             // STATE
             .init_state::<NetworkingState>()
+            // RESOURCE
+            .init_resource::<ChannelStats>()
             // SYSTEM SETS
             .configure_sets(
-                PreUpdate,
+                schedules.receive,
                 (
                     InternalMainSet::<ServerMarker>::Receive.in_set(MainSet::Receive),
                     InternalMainSet::<ServerMarker>::EmitEvents.in_set(MainSet::EmitEvents),
@@ -52,7 +61,7 @@ impl Plugin for ServerNetworkingPlugin {
                     .run_if(is_started),
             )
             .configure_sets(
-                PostUpdate,
+                schedules.send,
                 (
                     // we don't send packets every frame, but on a timer instead
                     InternalMainSet::<ServerMarker>::Send
@@ -65,18 +74,51 @@ impl Plugin for ServerNetworkingPlugin {
             )
             // SYSTEMS //
             .add_systems(
-                PreUpdate,
+                schedules.receive,
                 receive.in_set(InternalMainSet::<ServerMarker>::Receive),
             )
             .add_systems(
-                PostUpdate,
-                send.in_set(InternalMainSet::<ServerMarker>::SendPackets),
+                schedules.send,
+                (
+                    send.in_set(InternalMainSet::<ServerMarker>::SendPackets),
+                    send_paced_packets
+                        .after(InternalMainSet::<ServerMarker>::Send)
+                        .run_if(is_started),
+                    update_server_channel_stats
+                        .after(InternalMainSet::<ServerMarker>::Send)
+                        .run_if(is_started),
+                ),
             );
 
+        // TIMINGS
+        add_set_timing::<ServerMarker>(
+            app,
+            schedules.receive,
+            InternalMainSet::<ServerMarker>::Receive,
+            TimedPhase::Receive,
+        );
+        add_set_timing::<ServerMarker>(
+            app,
+            schedules.receive,
+            InternalMainSet::<ServerMarker>::EmitEvents,
+            TimedPhase::EmitEvents,
+        );
+        add_set_timing::<ServerMarker>(
+            app,
+            schedules.send,
+            InternalReplicationSet::<ServerMarker>::All,
+            TimedPhase::ReplicationBuffer,
+        );
+        add_set_timing::<ServerMarker>(
+            app,
+            schedules.send,
+            InternalMainSet::<ServerMarker>::Send,
+            TimedPhase::Send,
+        );
+
         // STARTUP
-        // create the server connection resources to avoid some systems panicking
-        // TODO: remove this when possible?
-        app.world.run_system_once(rebuild_server_connections);
+        // The `ServerConnections` and `ConnectionManager` resources are only created when the server
+        // is started (see `on_start`); until then, all the systems that depend on them don't run.
 
         // ON_START
         app.add_systems(OnEnter(NetworkingState::Started), on_start);
@@ -121,6 +163,13 @@ pub(crate) fn receive(world: &mut World) {
                                                                         error!("Disconnect server because of io error: {:?}", e);
                                                                         world.resource_mut::<NextState<NetworkingState>>().set(NetworkingState::Stopped);
                                                                     }
+                                                                    #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+                                                                    ServerIoEvent::CertificateRotated(digest) => {
+                                                                        world.send_event(CertificateRotatedEvent {
+                                                                            server_index: server_idx,
+                                                                            digest,
+                                                                        });
+                                                                    }
                                                                     _ => {}
                                                                 }
                                                             }
@@ -134,13 +183,37 @@ pub(crate) fn receive(world: &mut World) {
                                                     .try_update(delta.as_secs_f64())
                                                     .map_err(|e| error!("Error updating netcode server: {:?}", e));
                                                 for client_id in netserver.new_connections().iter().copied() {
+                                                    // the client ids must be unique across all the transports
+                                                    if netservers.client_server_map.get(&client_id).is_some_and(|idx| *idx != server_idx) {
+                                                        error!(?client_id, "Client id is already connected via another transport; disconnecting the new connection");
+                                                        let _ = netserver
+                                                            .disconnect(client_id)
+                                                            .map_err(|e| error!("Error disconnecting client: {:?}", e));
+                                                        continue;
+                                                    }
                                                     netservers.client_server_map.insert(client_id, server_idx);
                                                     // spawn an entity for the client
-                                                    let client_entity = world.spawn(ControlledEntities::default()).id();
-                                                    connection_manager.add(client_id, client_entity);
+                                                    let client_entity = world.spawn(client_entity_bundle(client_id)).id();
+                                                    let packet_sizing = netserver.io().map(|io| io.packet_sizing).unwrap_or_default();
+                                                    // the clients that connect through a transport bound to an additional protocol use that protocol
+                                                    let protocol = connection_manager.protocols.contains_key(&server_idx).then_some(server_idx);
+                                                    connection_manager.add(client_id, client_entity, packet_sizing, protocol);
+                                                }
+                                                // disconnect the clients whose connection was refused
+                                                for (client_id, connection) in connection_manager.connections.iter() {
+                                                    if connection.denial_sent && netservers.client_server_map.get(client_id) == Some(&server_idx) {
+                                                        let _ = netserver
+                                                            .disconnect(*client_id)
+                                                            .map_err(|e| error!("Error disconnecting refused client: {:?}", e));
+                                                    }
                                                 }
                                                 // handle disconnections
                                                 for client_id in netserver.new_disconnections().iter().copied() {
+                                                    // ignore the disconnections of clients that were rejected because their id
+                                                    // was already used on another transport
+                                                    if netservers.client_server_map.get(&client_id).is_some_and(|idx| *idx != server_idx) {
+                                                        continue;
+                                                    }
                                                     if netservers.client_server_map.remove(&client_id).is_some() {
                                                         connection_manager.remove(client_id);
                                                         // NOTE: we don't despawn the entity right away to let the user react to
@@ -160,6 +233,10 @@ pub(crate) fn receive(world: &mut World) {
                                             // RECV_PACKETS: buffer packets into message managers
                                             for (server_idx, netserver) in netservers.servers.iter_mut().enumerate() {
                                                 while let Some((packet, client_id)) = netserver.recv() {
+                                                    #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+                                                    if let Some(capture) = connection_manager.packet_capture.as_mut() {
+                                                        capture.capture_received(tick_manager.tick(), Some(client_id), &packet);
+                                                    }
                                                     // Note: the client_id might not be present in the connection_manager if we receive
                                                     // packets from a client
                                                     // TODO: use connection to apply on BOTH message manager and replication manager
@@ -222,8 +299,17 @@ pub(crate) fn send(
     mut connection_manager: ResMut<ConnectionManager>,
     tick_manager: Res<TickManager>,
     time_manager: Res<TimeManager>,
+    mut bandwidth_estimate: Option<ResMut<BandwidthEstimate>>,
+    mut priorities_query: Query<&mut ReplicationPriorities>,
 ) {
     trace!("Send packets to clients");
+    // the priorities accumulated by the replication groups are stored on the client entities,
+    // the replication senders use them while the messages are sent
+    for connection in connection_manager.connections.values_mut() {
+        if let Ok(priorities) = priorities_query.get(connection.entity) {
+            connection.replication_sender.priorities = priorities.0.clone();
+        }
+    }
     // finalize any packets that are needed for replication
     connection_manager
         .buffer_replication_messages(tick_manager.tick(), change_tick.this_run())
@@ -233,6 +319,7 @@ pub(crate) fn send(
 
     // SEND_PACKETS: send buffered packets to io
     let span = trace_span!("send_packets").entered();
+    let connection_manager = connection_manager.as_mut();
     connection_manager
         .connections
         .iter_mut()
@@ -247,7 +334,40 @@ pub(crate) fn send(
                 .servers
                 .get_mut(netserver_idx)
                 .context("could not find server with the provided netserver idx")?;
-            for packet_byte in connection.send_packets(&time_manager, &tick_manager)? {
+            let (packet_bytes, stream_packet_bytes) =
+                connection.send_packets(&time_manager, &tick_manager)?;
+            #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+            if let Some(capture) = connection_manager.packet_capture.as_mut() {
+                for packet_byte in packet_bytes.iter().chain(stream_packet_bytes.iter()) {
+                    capture.capture_sent(tick_manager.tick(), Some(*client_id), packet_byte);
+                }
+            }
+            if let Some(estimate) = bandwidth_estimate.as_mut() {
+                let bytes = packet_bytes
+                    .iter()
+                    .chain(stream_packet_bytes.iter())
+                    .map(|packet_byte| packet_byte.len())
+                    .sum();
+                estimate.record(*client_id, time_manager.current_time(), bytes);
+                // in dry-run mode, the packets are only recorded
+                if estimate.is_dry_run() {
+                    return Ok(());
+                }
+            }
+            // streams have their own flow control, so they are not paced
+            for packet_byte in stream_packet_bytes {
+                netserver.send_with_delivery(
+                    packet_byte.as_slice(),
+                    *client_id,
+                    Delivery::Stream,
+                )?;
+            }
+            // with pacing, the packets are sent progressively in `send_paced_packets`
+            if let Some(pacer) = connection.pacer.as_mut() {
+                pacer.push(packet_bytes);
+                return Ok(());
+            }
+            for packet_byte in packet_bytes {
                 netserver.send(packet_byte.as_slice(), *client_id)?;
             }
             Ok(())
@@ -255,10 +375,68 @@ pub(crate) fn send(
         .unwrap_or_else(|e: anyhow::Error| {
             error!("Error sending packets: {}", e);
         });
+    for connection in connection_manager.connections.values_mut() {
+        if let Ok(mut priorities) = priorities_query.get_mut(connection.entity) {
+            priorities.set_if_neq(ReplicationPriorities(std::mem::take(
+                &mut connection.replication_sender.priorities,
+            )));
+        }
+    }
+
+    if let Some(estimate) = bandwidth_estimate.as_mut() {
+        estimate.retain_clients(|client_id| connection_manager.connections.contains_key(client_id));
+    }
+
+    // the refused clients are disconnected once the reason of the refusal was sent to them
+    for connection in connection_manager.connections.values_mut() {
+        if connection.denied.is_some()
+            && connection
+                .pacer
+                .as_ref()
+                .map_or(true, |pacer| pacer.queued_packets() == 0)
+        {
+            connection.denial_sent = true;
+        }
+    }
 
     // clear the list of newly connected clients
     // (cannot just use the ConnectionEvent because it is cleared after each frame)
     connection_manager.new_clients.clear();
+    connection_manager.resend_requests.clear();
+}
+
+/// Send the packets that were queued by the pacer, within the bandwidth limit.
+///
+/// This runs every frame (and not only when the server is ready to send) so that the packets
+/// are spread over time.
+pub(crate) fn send_paced_packets(
+    mut netservers: ResMut<ServerConnections>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    time_manager: Res<TimeManager>,
+) {
+    connection_manager
+        .connections
+        .iter_mut()
+        .try_for_each(|(client_id, connection)| {
+            let Some(pacer) = connection.pacer.as_mut() else {
+                return Ok(());
+            };
+            let netserver_idx = *netservers
+                .client_server_map
+                .get(client_id)
+                .context("could not find server connection corresponding to client id")?;
+            let netserver = netservers
+                .servers
+                .get_mut(netserver_idx)
+                .context("could not find server with the provided netserver idx")?;
+            for packet_byte in pacer.drain_ready(time_manager.delta()) {
+                netserver.send(packet_byte.as_slice(), *client_id)?;
+            }
+            Ok(())
+        })
+        .unwrap_or_else(|e: anyhow::Error| {
+            error!("Error sending paced packets: {}", e);
+        });
 }
 
 /// Run condition to check that the server is ready to send packets
@@ -289,26 +467,70 @@ pub enum NetworkingState {
     Started,
 }
 
-/// This runs only when we restart the server.
+/// This runs only when we (re)start the server.
 ///
-/// We rebuild the [`ServerConnections`] by using the latest [`ServerConfig`].
+/// We (re)build the [`ServerConnections`] by using the latest [`ServerConfig`].
 /// This has several benefits:
 /// - the server connection's internal time is up-to-date (otherwise it might not be, since we don't run any server systems while the server is stopped)
 /// - we can take into account any changes to the server config
 fn rebuild_server_connections(world: &mut World) {
     let server_config = world.resource::<ServerConfig>().clone();
+    // the packets must not be fragmented by the network to discover the path MTU
+    let dont_fragment = server_config.packet.mtu_discovery.is_some();
 
     // insert a new connection manager (to reset message numbers, ping manager, etc.)
-    let connection_manager = ConnectionManager::new(
+    let mut connection_manager = ConnectionManager::new(
         world.resource::<MessageRegistry>().clone(),
         world.resource::<ChannelRegistry>().clone(),
         server_config.packet,
         server_config.ping,
+        server_config.replication,
     );
+    #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+    {
+        connection_manager.packet_capture = server_config.io_diagnostics.build_packet_capture();
+    }
+    #[cfg(not(all(feature = "pcap", not(target_family = "wasm"))))]
+    server_config.io_diagnostics.warn_if_packet_capture();
+    // if some components are migrated between protocol versions, we need to know the version of the client
+    // before sending it any replication data
+    connection_manager.wait_for_protocol_version =
+        world.resource::<ComponentRegistry>().has_migrations();
+    // refuse the clients that were built with a different protocol
+    if server_config.verify_protocol_hash {
+        connection_manager.protocol_hash = Some(registries_hash(
+            world.resource::<ComponentRegistry>(),
+            world.resource::<MessageRegistry>(),
+            world.resource::<ChannelRegistry>(),
+        ));
+    }
+    connection_manager.protocol_version = world.resource::<ComponentRegistry>().protocol_version();
+    if let Some(protocols) = world.get_resource::<AdditionalProtocols>() {
+        connection_manager.protocols = protocols.0.clone();
+    }
+    // the target handles that were registered before a restart are still valid
+    if let Some(mut previous) = world.get_resource_mut::<ConnectionManager>() {
+        connection_manager.keep_registered_targets(&mut previous);
+    }
     world.insert_resource(connection_manager);
 
     // rebuild the server connections and insert them
-    let server_connections = ServerConnections::new(server_config.net);
+    let protocol_version = world.resource::<ComponentRegistry>().protocol_version();
+    let mut net_configs = server_config.net;
+    for net_config in net_configs.iter_mut() {
+        match net_config {
+            NetConfig::Netcode { config, io } | NetConfig::Punched { config, io, .. } => {
+                // the protocol version is returned to the info requests
+                config.protocol_version = protocol_version;
+                io.socket.dont_fragment = dont_fragment;
+            }
+            #[cfg(all(feature = "steam", not(target_family = "wasm")))]
+            NetConfig::Steam { .. } => {}
+            #[cfg(any(test, feature = "test-utils"))]
+            NetConfig::Mock { .. } => {}
+        }
+    }
+    let server_connections = ServerConnections::new(net_configs);
     world.insert_resource(server_connections);
 }
 
@@ -317,7 +539,10 @@ fn rebuild_server_connections(world: &mut World) {
 /// - rebuild the server connection manager
 /// - start listening on the server connections
 fn on_start(world: &mut World) {
-    if world.resource::<ServerConnections>().is_listening() {
+    if world
+        .get_resource::<ServerConnections>()
+        .is_some_and(|server_connections| server_connections.is_listening())
+    {
         error!("The server is already started. The server can only be started when it is stopped.");
         return;
     }
@@ -329,7 +554,11 @@ fn on_start(world: &mut World) {
 }
 
 /// System that runs when we enter the Stopped state
-fn on_stop(mut server_connections: ResMut<ServerConnections>) {
+fn on_stop(server_connections: Option<ResMut<ServerConnections>>) {
+    // the server was never started (this runs when entering the initial `Stopped` state)
+    let Some(mut server_connections) = server_connections else {
+        return;
+    };
     let _ = server_connections
         .stop()
         .inspect_err(|e| error!("Error stopping server connections: {:?}", e));
@@ -339,6 +568,14 @@ pub trait ServerCommands {
     fn start_server(&mut self);
 
     fn stop_server(&mut self);
+
+    /// Replace the certificate of the WebTransport server at index `server_index` in [`ServerConfig::net`],
+    /// without restarting the server.
+    ///
+    /// The clients that are already connected keep their connection; new connections use the new certificate.
+    /// A [`CertificateRotatedEvent`] is emitted once the server uses the new certificate.
+    #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+    fn rotate_certificate(&mut self, server_index: usize, certificate: wtransport::Identity);
 }
 
 impl ServerCommands for Commands<'_, '_> {
@@ -349,4 +586,72 @@ impl ServerCommands for Commands<'_, '_> {
     fn stop_server(&mut self) {
         self.insert_resource(NextState::<NetworkingState>(Some(NetworkingState::Stopped)));
     }
+
+    #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+    fn rotate_certificate(&mut self, server_index: usize, certificate: wtransport::Identity) {
+        self.add(move |world: &mut World| {
+            let _ = rotate_certificate(world, server_index, certificate)
+                .inspect_err(|e| error!("Error rotating the certificate: {:?}", e));
+        });
+    }
+}
+
+#[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+fn rotate_certificate(
+    world: &mut World,
+    server_index: usize,
+    certificate: wtransport::Identity,
+) -> anyhow::Result<()> {
+    use crate::server::io::config::ServerTransport;
+    // update the config so that the new certificate is used if the server is restarted
+    let mut config = world.resource_mut::<ServerConfig>();
+    match config.net.get_mut(server_index) {
+        Some(
+            NetConfig::Netcode {
+                io:
+                    IoConfig {
+                        transport:
+                            ServerTransport::WebTransportServer {
+                                certificate: config_certificate,
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            }
+            | NetConfig::Punched {
+                io:
+                    IoConfig {
+                        transport:
+                            ServerTransport::WebTransportServer {
+                                certificate: config_certificate,
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            },
+        ) => {
+            *config_certificate = certificate.clone_identity();
+        }
+        _ => {
+            return Err(anyhow!(
+                "the server transport at index {server_index} is not a WebTransport server"
+            ))
+        }
+    }
+    // update the certificate of the running server
+    if let Some(mut server_connections) = world.get_resource_mut::<ServerConnections>() {
+        if let Some(sender) = server_connections
+            .servers
+            .get_mut(server_index)
+            .and_then(|server| server.io_mut())
+            .and_then(|io| io.context.event_sender.as_mut())
+        {
+            sender
+                .try_send(ServerIoEvent::RotateCertificate(certificate))
+                .map_err(|_| anyhow!("could not send the new certificate to the io task"))?;
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,225 @@
+/*! Save and restore the replicated world across server restarts
+
+[`ServerReplication::save`] serializes all the entities that the server replicates, along with their replication
+settings and the values of their components that are registered in the protocol. [`ServerReplication::load`]
+spawns them back, for example after a crash or a deploy:
+```rust
+# use bevy::prelude::*;
+# use lightyear::prelude::server::{SavedWorld, ServerReplication};
+// before shutting down; the `SavedWorld` can be stored with any serde format
+fn save(world: &World) -> anyhow::Result<SavedWorld> {
+    ServerReplication::save(world)
+}
+
+// after restarting
+fn load(world: &mut World, saved: &SavedWorld) -> anyhow::Result<()> {
+    ServerReplication::load(world, saved)?;
+    Ok(())
+}
+```
+
+The saved entities are spawned again with new [`Entity`]s, so they are identified by their stable [`NetworkId`]
+instead. This means that:
+- the entities that reference other saved entities (for example with [`ParentSync`]) are mapped to the new entities
+- the clients that enable [`ReplicationConfig::reattach_entities`](crate::client::config::ReplicationConfig::reattach_entities)
+  keep their local entities when they reconnect: the entities sent by the restarted server are linked to the local
+  entity with the same [`NetworkId`] instead of being spawned again.
+
+Give a [`NetworkId`] to the replicated entities that the clients should keep across a restart. The saved entities
+without a [`NetworkId`] are restored on the server, but the clients despawn their previous copy and spawn them again.
+
+Only the [`Replicate`] bundle and the components of the protocol are saved: the per-component overrides
+(for example [`OverrideTargetComponent`](crate::prelude::OverrideTargetComponent)) and the components that are not
+registered in the protocol are not restored.
+*/
+use anyhow::{bail, Context};
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::ComponentRegistry;
+use crate::protocol::macros::protocol_hash;
+use crate::serialize::bitcode::reader::BitcodeReader;
+use crate::serialize::bitcode::writer::BitcodeWriter;
+use crate::serialize::reader::ReadBuffer;
+use crate::serialize::writer::WriteBuffer;
+use crate::serialize::RawData;
+use crate::server::replication::send::Replicate;
+use crate::shared::events::connection::ConnectionEvents;
+use crate::shared::replication::components::{
+    ControlledBy, NetworkId, ReplicateHierarchy, Replicating, ReplicationGroup, ReplicationTarget,
+    SyncTarget, VisibilityMode,
+};
+use crate::shared::replication::entity_map::EntityMap;
+use crate::shared::replication::hierarchy::ParentSync;
+
+/// Entry point to save and restore the replicated world of the server
+pub struct ServerReplication;
+
+/// The replicated entities of the server, as saved by [`ServerReplication::save`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SavedWorld {
+    /// Hash of the components of the protocol, to refuse loading a world saved with a different protocol
+    protocol_hash: u64,
+    pub entities: Vec<SavedEntity>,
+}
+
+/// A replicated entity saved by [`ServerReplication::save`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SavedEntity {
+    /// The stable id of the entity, used to find it after it is loaded, and by the clients to re-attach it
+    pub network_id: Option<NetworkId>,
+    /// The entity on the server when it was saved. It is only used to map the references between the saved
+    /// entities: the entity gets a new [`Entity`] when it is loaded
+    pub entity: Entity,
+    pub replicate: Replicate,
+    /// False if the replication of the entity was paused (the [`Replicating`] component was removed)
+    pub replicating: bool,
+    /// The components of the entity that are registered in the protocol
+    pub(crate) components: Vec<RawData>,
+}
+
+impl SavedEntity {
+    /// Number of components of the protocol that were saved for this entity
+    pub fn num_components(&self) -> usize {
+        self.components.len()
+    }
+}
+
+fn components_hash(component_registry: &ComponentRegistry) -> u64 {
+    let mut description = String::new();
+    component_registry.describe(&mut description);
+    protocol_hash(&description)
+}
+
+impl ServerReplication {
+    /// Save all the entities that are replicated by the server
+    pub fn save(world: &World) -> anyhow::Result<SavedWorld> {
+        let component_registry = world.resource::<ComponentRegistry>();
+        let mut writer = BitcodeWriter::with_capacity(1024);
+        let entities = world
+            .iter_entities()
+            .filter(|entity| entity.contains::<ReplicationTarget>())
+            .map(|entity| {
+                let replicate = Replicate {
+                    target: entity
+                        .get::<ReplicationTarget>()
+                        .cloned()
+                        .unwrap_or_default(),
+                    sync: entity.get::<SyncTarget>().cloned().unwrap_or_default(),
+                    controlled_by: entity.get::<ControlledBy>().cloned().unwrap_or_default(),
+                    visibility: entity.get::<VisibilityMode>().copied().unwrap_or_default(),
+                    group: entity
+                        .get::<ReplicationGroup>()
+                        .copied()
+                        .unwrap_or_default(),
+                    hierarchy: entity
+                        .get::<ReplicateHierarchy>()
+                        .copied()
+                        .unwrap_or_default(),
+                    marker: Replicating,
+                };
+                let components = component_registry
+                    .save_entity(entity, &mut writer)
+                    .with_context(|| format!("could not save entity {:?}", entity.id()))?;
+                Ok(SavedEntity {
+                    network_id: entity.get::<NetworkId>().copied(),
+                    entity: entity.id(),
+                    replicate,
+                    replicating: entity.contains::<Replicating>(),
+                    components,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(SavedWorld {
+            protocol_hash: components_hash(component_registry),
+            entities,
+        })
+    }
+
+    /// Spawn back the entities saved by [`ServerReplication::save`], and return the entity that was spawned for
+    /// each saved [`Entity`].
+    ///
+    /// Fails without modifying the world if the world was saved with a different protocol, if the
+    /// [`NetworkId`] of one of the saved entities is already used, or if one of the saved components
+    /// cannot be decoded.
+    pub fn load(world: &mut World, saved: &SavedWorld) -> anyhow::Result<EntityHashMap<Entity>> {
+        world.resource_scope(|world, component_registry: Mut<ComponentRegistry>| {
+            if saved.protocol_hash != components_hash(&component_registry) {
+                bail!("the world was saved with a different protocol");
+            }
+            let mut network_ids: HashSet<NetworkId> =
+                world.query::<&NetworkId>().iter(world).copied().collect();
+            if let Some(network_id) = saved
+                .entities
+                .iter()
+                .filter_map(|saved_entity| saved_entity.network_id)
+                .find(|network_id| !network_ids.insert(*network_id))
+            {
+                bail!(
+                    "could not load the entity with {network_id:?} because the id is already used"
+                );
+            }
+            // write the entities in a scratch world first, so that the world is left untouched if one of
+            // the saved components cannot be decoded
+            Self::spawn_saved(&mut World::new(), &component_registry, saved)?;
+            Self::spawn_saved(world, &component_registry, saved)
+        })
+    }
+
+    /// Spawn the saved entities in `world`
+    fn spawn_saved(
+        world: &mut World,
+        component_registry: &ComponentRegistry,
+        saved: &SavedWorld,
+    ) -> anyhow::Result<EntityHashMap<Entity>> {
+        // spawn all the entities first, so that the references between them can be mapped
+        let mut entity_map = EntityMap::default();
+        for saved_entity in &saved.entities {
+            let entity = world.spawn_empty().id();
+            entity_map.insert(saved_entity.entity, entity);
+        }
+        // the entities are restored without any connection, so there is no event to emit
+        let mut events = ConnectionEvents::default();
+        let mut parents = Vec::new();
+        for saved_entity in &saved.entities {
+            let entity = entity_map.0[&saved_entity.entity];
+            let mut entity_world_mut = world.entity_mut(entity);
+            entity_world_mut.insert(saved_entity.replicate.clone());
+            if let Some(network_id) = saved_entity.network_id {
+                entity_world_mut.insert(network_id);
+            }
+            if !saved_entity.replicating {
+                entity_world_mut.remove::<Replicating>();
+            }
+            for component in &saved_entity.components {
+                let mut reader = BitcodeReader::start_read(component.as_slice());
+                component_registry
+                    .raw_write(
+                        &mut reader,
+                        None,
+                        &mut entity_world_mut,
+                        &mut entity_map,
+                        &mut events,
+                    )
+                    .with_context(|| {
+                        format!("could not load a component of {:?}", saved_entity.entity)
+                    })?;
+            }
+            if let Some(parent) = entity_world_mut
+                .get::<ParentSync>()
+                .and_then(ParentSync::parent)
+            {
+                parents.push((entity, parent));
+            }
+        }
+        // restore the hierarchy once all the entities exist
+        for (child, parent) in parents {
+            if world.get_entity(parent).is_some() {
+                world.entity_mut(child).set_parent(parent);
+            }
+        }
+        Ok(entity_map.0)
+    }
+}
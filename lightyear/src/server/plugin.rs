@@ -8,15 +8,20 @@
 //! while keeping the rest of the features intact.
 //!
 //! Most plugins are truly necessary for the server functionality to work properly, but some could be disabled.
+use crate::server::bandwidth_estimate::BandwidthEstimatePlugin;
 use crate::server::clients::ClientsMetadataPlugin;
 use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 
 use crate::server::events::ServerEventsPlugin;
+use crate::server::inspector::InspectorPlugin;
 use crate::server::networking::ServerNetworkingPlugin;
+use crate::server::priority::ReplicationPriorityPlugin;
 use crate::server::replication::{
     receive::ServerReplicationReceivePlugin, send::ServerReplicationSendPlugin,
 };
+use crate::server::speedhack::SpeedHackPlugin;
+use crate::server::stats::ServerStatsPlugin;
 use crate::server::visibility::immediate::VisibilityPlugin;
 use crate::server::visibility::room::RoomPlugin;
 use crate::shared::plugin::SharedPlugin;
@@ -35,6 +40,11 @@ use super::config::ServerConfig;
 ///   disabled if you don't need client to server replication.
 /// - [`ServerReplicationSendPlugin`]: Handles the replication of entities and resources from the server to the client. This can be
 ///   disabled if you don't need server to client replication.
+/// - [`ReplicationPriorityPlugin`]: Scores the replicated entities for each client with the [`ReplicationPriorityScorer`](crate::server::priority::ReplicationPriorityScorer), if one is inserted
+/// - [`SpeedHackPlugin`]: Detects the clients whose clock runs faster than the server's clock, if enabled in the [`ServerConfig`]
+/// - [`ServerStatsPlugin`]: Computes aggregate server statistics and replicates them to admin clients, if enabled in the [`ServerConfig`]
+/// - [`InspectorPlugin`]: Sends snapshots of the replicated entities to the inspector clients that present the admin token, if enabled in the [`ServerConfig`]
+/// - [`BandwidthEstimatePlugin`]: Records the bytes sent to each client to estimate the bandwidth requirements, if enabled in the [`ServerConfig`]
 pub struct ServerPlugins {
     pub config: ServerConfig,
 }
@@ -60,6 +70,11 @@ impl PluginGroup for ServerPlugins {
             .add(ClientsMetadataPlugin)
             .add(ServerReplicationReceivePlugin { tick_interval })
             .add(ServerReplicationSendPlugin { tick_interval })
+            .add(ReplicationPriorityPlugin)
+            .add(SpeedHackPlugin)
+            .add(ServerStatsPlugin)
+            .add(InspectorPlugin)
+            .add(BandwidthEstimatePlugin)
     }
 }
 
@@ -3,20 +3,62 @@
 use std::any::TypeId;
 use std::hash::{BuildHasher, Hash, Hasher};
 
+#[cfg(feature = "client")]
 use crate::client::replication::send::ReplicateToServer;
 use bevy::ecs::component::Components;
 use bevy::prelude::*;
 
+#[cfg(feature = "server")]
 use crate::prelude::server::ControlledBy;
 use crate::prelude::{
     ComponentRegistry, ParentSync, PreSpawnedPlayerObject, ReplicateHierarchy, Replicated,
     Replicating, ReplicationTarget, ShouldBePredicted, TargetEntity, TickManager, VisibilityMode,
 };
 use crate::protocol::component::ComponentKind;
+#[cfg(feature = "server")]
 use crate::server::replication::send::SyncTarget;
+#[cfg(feature = "server")]
 use crate::server::visibility::immediate::ReplicateVisibility;
 use crate::shared::replication::components::DespawnTracker;
 
+/// Book-keeping components that are not part of the hash of a pre-spawned entity
+pub(crate) fn ignored_components() -> Vec<TypeId> {
+    [
+        TypeId::of::<VisibilityMode>(),
+        TypeId::of::<ReplicationTarget>(),
+        TypeId::of::<Replicating>(),
+        TypeId::of::<Replicated>(),
+        TypeId::of::<TargetEntity>(),
+        TypeId::of::<ReplicateHierarchy>(),
+        TypeId::of::<PreSpawnedPlayerObject>(),
+        TypeId::of::<ShouldBePredicted>(),
+        TypeId::of::<DespawnTracker>(),
+        TypeId::of::<ParentSync>(),
+    ]
+    .into_iter()
+    .chain(client_ignored_components())
+    .chain(server_ignored_components())
+    .collect()
+}
+
+fn client_ignored_components() -> Vec<TypeId> {
+    #[cfg(feature = "client")]
+    return vec![TypeId::of::<ReplicateToServer>()];
+    #[cfg(not(feature = "client"))]
+    return vec![];
+}
+
+fn server_ignored_components() -> Vec<TypeId> {
+    #[cfg(feature = "server")]
+    return vec![
+        TypeId::of::<SyncTarget>(),
+        TypeId::of::<ControlledBy>(),
+        TypeId::of::<ReplicateVisibility>(),
+    ];
+    #[cfg(not(feature = "server"))]
+    return vec![];
+}
+
 /// Compute the hash of the spawned entity by hashing the NetId of all its components along with the tick at which it was created
 /// 1. Client spawns an entity and adds the PreSpawnedPlayerObject component
 /// 2. Client will compute the hash of the entity and store it internally
@@ -34,6 +76,8 @@ pub(crate) fn compute_hash(
 ) {
     let tick = set.p2().tick();
     let net_id_map = set.p1().kind_map.kind_map.clone();
+    // ignore some book-keeping components
+    let ignored_components = ignored_components();
 
     // get the list of entities that need to have a new hash computed, along with the hash
     for mut entity_mut in set.p0().iter_mut() {
@@ -67,23 +111,7 @@ pub(crate) fn compute_hash(
             .components()
             .filter_map(|component_id| {
                 if let Some(type_id) = components.get_info(component_id).unwrap().type_id() {
-                    // ignore some book-keeping components
-                    if type_id != TypeId::of::<VisibilityMode>()
-                        && type_id != TypeId::of::<ReplicationTarget>()
-                        && type_id != TypeId::of::<SyncTarget>()
-                        && type_id != TypeId::of::<ControlledBy>()
-                        && type_id != TypeId::of::<Replicating>()
-                        && type_id != TypeId::of::<Replicated>()
-                        && type_id != TypeId::of::<ReplicateToServer>()
-                        && type_id != TypeId::of::<ReplicateVisibility>()
-                        && type_id != TypeId::of::<VisibilityMode>()
-                        && type_id != TypeId::of::<TargetEntity>()
-                        && type_id != TypeId::of::<ReplicateHierarchy>()
-                        && type_id != TypeId::of::<PreSpawnedPlayerObject>()
-                        && type_id != TypeId::of::<ShouldBePredicted>()
-                        && type_id != TypeId::of::<DespawnTracker>()
-                        && type_id != TypeId::of::<ParentSync>()
-                    {
+                    if !ignored_components.contains(&type_id) {
                         return net_id_map.get(&ComponentKind::from(type_id)).copied();
                     }
                 }
@@ -0,0 +1,134 @@
+/*! Score the replicated entities to decide which ones are sent first when the bandwidth is limited
+
+Each replication group accumulates priority for each client every time that the server sends packets,
+and the priority is reset once an update of the group is sent to the client. When the bandwidth cap
+([`PacketConfig::bandwidth_cap_enabled`](crate::server::config::PacketConfig::bandwidth_cap_enabled)) doesn't
+allow sending all the messages, the messages with the lowest priority are evicted, so the groups that miss
+a send keep gaining priority until they are sent: no entity starves.
+
+By default all the groups accumulate their [`ReplicationGroup`] priority at the same rate.
+Insert a [`ReplicationPriorityScorer`] to score each entity for each client; the score multiplies the priority
+accumulated by the group of the entity (the highest score is used for groups that contain several entities):
+```rust,ignore
+app.insert_resource(ReplicationPriorityScorer::new(|world, entity, client_id| {
+    // the entities that are close to the player of the client are updated more often
+    let distance = player_distance(world, entity, client_id);
+    1.0 / (1.0 + distance)
+}));
+```
+The entities are scored every time that the replication messages are buffered.
+*/
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::prelude::{ClientId, ReplicationGroup};
+use crate::server::connection::ConnectionManager;
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::replication::components::{Replicating, ReplicationGroupId};
+use crate::shared::sets::{InternalReplicationSet, ServerMarker};
+
+type ScoreFn = dyn Fn(&World, Entity, ClientId) -> f32 + Send + Sync;
+
+/// Computes the score of a replicated entity for a client.
+///
+/// Entities with a higher score accumulate priority faster, so they are sent first when the bandwidth is limited.
+#[derive(Resource)]
+pub struct ReplicationPriorityScorer {
+    score: Box<ScoreFn>,
+}
+
+impl ReplicationPriorityScorer {
+    pub fn new(score: impl Fn(&World, Entity, ClientId) -> f32 + Send + Sync + 'static) -> Self {
+        Self {
+            score: Box::new(score),
+        }
+    }
+}
+
+pub(crate) struct ReplicationPriorityPlugin;
+
+impl Plugin for ReplicationPriorityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            NetworkScheduleConfig::get(app).send,
+            score_replication_groups
+                .in_set(InternalReplicationSet::<ServerMarker>::BufferEntityUpdates)
+                .run_if(resource_exists::<ReplicationPriorityScorer>),
+        );
+    }
+}
+
+/// Update the score of each replication group for each client
+fn score_replication_groups(world: &mut World) {
+    world.resource_scope(|world, mut connection_manager: Mut<ConnectionManager>| {
+        let mut query = world.query_filtered::<(Entity, &ReplicationGroup), With<Replicating>>();
+        let scorer = world.resource::<ReplicationPriorityScorer>();
+        for (client_id, connection) in connection_manager.connections.iter_mut() {
+            let mut scores: HashMap<ReplicationGroupId, f32> = HashMap::default();
+            for (entity, group) in query.iter(world) {
+                let score = (scorer.score)(world, entity, *client_id);
+                scores
+                    .entry(group.group_id(Some(entity)))
+                    .and_modify(|group_score| *group_score = group_score.max(score))
+                    .or_insert(score);
+            }
+            for (group_id, score) in scores {
+                connection.replication_sender.update_score(group_id, score);
+            }
+        }
+    });
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use crate::prelude::server::{Replicate, ReplicationPriorities};
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+    use super::*;
+
+    #[test]
+    fn test_scored_groups_accumulate_priority_faster() {
+        let mut stepper = BevyStepper::default();
+        let important = stepper
+            .server_app
+            .world
+            .spawn((Component1(1.0), Replicate::default()))
+            .id();
+        let other = stepper
+            .server_app
+            .world
+            .spawn((Component1(2.0), Replicate::default()))
+            .id();
+        stepper
+            .server_app
+            .insert_resource(ReplicationPriorityScorer::new(move |_, entity, _| {
+                if entity == important {
+                    3.0
+                } else {
+                    1.0
+                }
+            }));
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        // the entities don't change, so no updates are sent and the priorities keep accumulating
+        let connection_manager = stepper.server_app.world.resource::<ConnectionManager>();
+        let connection = connection_manager
+            .connection(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+        let group_channels = &connection.replication_sender.group_channels;
+        let priorities = stepper
+            .server_app
+            .world
+            .get::<ReplicationPriorities>(connection.entity)
+            .unwrap();
+        let priority = |entity: Entity| priorities[&ReplicationGroupId(entity.to_bits())];
+        assert_eq!(
+            group_channels[&ReplicationGroupId(important.to_bits())].score,
+            3.0
+        );
+        assert!(priority(important) > 2.0 * priority(other));
+    }
+}
@@ -0,0 +1,179 @@
+/*! Serve multiple independent protocols from the same server
+
+By default all the clients use the same protocol: the components, messages and channels registered in the app.
+A server can also serve other clients with a different protocol, for example to accept connections from an editor
+or from admin tools next to the connections of the players. Each additional protocol is bound to one of the server's
+transports ([`ServerConfig::net`](crate::server::config::ServerConfig::net)), and the clients that connect through
+that transport use the registries of that protocol instead of the ones of the main protocol:
+```rust
+# use bevy::prelude::*;
+# use serde::{Deserialize, Serialize};
+# use lightyear::prelude::*;
+# use lightyear::prelude::server::AppProtocolExt;
+# #[derive(Channel)]
+# struct EditorChannel;
+# #[derive(Serialize, Deserialize)]
+# struct EditorCommand;
+// the protocol shared with the editor app
+fn editor_protocol(app: &mut App) {
+    app.add_channel::<EditorChannel>(ChannelSettings {
+        mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+        ..default()
+    });
+    app.add_message::<EditorCommand>(ChannelDirection::Bidirectional);
+}
+
+// the clients that connect through the second transport of the server use the editor protocol
+fn build_server(server_app: &mut App) {
+    server_app.add_protocol(1, editor_protocol);
+}
+```
+The additional protocols must be added while the app is being built (before the plugins are finished).
+They can only contain channels and messages: registering a component in an additional protocol panics, because
+the replication systems of the server use the components of the main protocol. The protocols don't have their
+own `ComponentRegistry`, so the components cannot differ between the protocols.
+
+The messages are only sent to the clients whose protocol contains them, so the messages of the main protocol
+sent to [`NetworkTarget::All`](crate::prelude::NetworkTarget::All) are not sent to the clients of an additional
+protocol, and vice versa. The entities are not replicated to the clients of an additional protocol, and the
+replication messages that they send are ignored. The inputs are only read from the clients of the main protocol.
+*/
+use bevy::prelude::{App, Resource};
+use bevy::utils::HashMap;
+
+use crate::prelude::{ChannelRegistry, ComponentRegistry, MessageRegistry};
+use crate::protocol::registries_hash;
+use crate::shared::plugin::register_internal_protocol;
+
+/// The registries of a protocol that is used by some of the server's connections.
+///
+/// There is no [`ComponentRegistry`] per protocol: the entities are only replicated with the components of the
+/// main protocol, so the clients of an additional protocol only exchange messages with the server.
+#[derive(Clone, Debug)]
+pub(crate) struct ProtocolRegistries {
+    pub(crate) message_registry: MessageRegistry,
+    pub(crate) channel_registry: ChannelRegistry,
+    /// Hash announced by the clients that use this protocol. It includes the internal components,
+    /// which are registered by every client
+    hash: u64,
+}
+
+impl ProtocolRegistries {
+    /// Hash announced by the clients that use this protocol
+    pub(crate) fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Replace the registries of the app with empty registries, and return the previous ones
+fn take_registries(app: &mut App) -> (ComponentRegistry, MessageRegistry, ChannelRegistry) {
+    let registries = (
+        app.world
+            .remove_resource::<ComponentRegistry>()
+            .expect("the ComponentRegistry should exist"),
+        app.world
+            .remove_resource::<MessageRegistry>()
+            .expect("the MessageRegistry should exist"),
+        app.world
+            .remove_resource::<ChannelRegistry>()
+            .expect("the ChannelRegistry should exist"),
+    );
+    insert_registries(
+        app,
+        (
+            ComponentRegistry::default(),
+            MessageRegistry::default(),
+            ChannelRegistry::new(),
+        ),
+    );
+    registries
+}
+
+fn insert_registries(
+    app: &mut App,
+    (component_registry, message_registry, channel_registry): (
+        ComponentRegistry,
+        MessageRegistry,
+        ChannelRegistry,
+    ),
+) {
+    app.insert_resource(component_registry);
+    app.insert_resource(message_registry);
+    app.insert_resource(channel_registry);
+}
+
+/// The additional protocols of the server, keyed by the index of the transport (in [`ServerConfig::net`](crate::server::config::ServerConfig::net))
+/// that their clients connect through
+#[derive(Resource, Clone, Debug, Default)]
+pub(crate) struct AdditionalProtocols(pub(crate) HashMap<usize, ProtocolRegistries>);
+
+impl AdditionalProtocols {
+    /// Add the internal components and messages to the registries of the additional protocols, the same
+    /// way as they are added to the main protocol
+    pub(crate) fn register_internal(&mut self) {
+        for registries in self.0.values_mut() {
+            // the registrations are done in a separate app so that no systems are added to the server
+            let mut app = App::new();
+            insert_registries(
+                &mut app,
+                (
+                    ComponentRegistry::default(),
+                    std::mem::take(&mut registries.message_registry),
+                    std::mem::replace(&mut registries.channel_registry, ChannelRegistry::new()),
+                ),
+            );
+            register_internal_protocol(&mut app);
+            // the internal components are only needed to compute the hash of the protocol
+            let (component_registry, message_registry, channel_registry) =
+                take_registries(&mut app);
+            registries.hash =
+                registries_hash(&component_registry, &message_registry, &channel_registry);
+            registries.message_registry = message_registry;
+            registries.channel_registry = channel_registry;
+        }
+    }
+}
+
+pub trait AppProtocolExt {
+    /// Register an additional protocol, which is used instead of the main protocol by the clients that
+    /// connect through the transport at index `net_index` of [`ServerConfig::net`](crate::server::config::ServerConfig::net).
+    ///
+    /// `protocol` registers the messages and channels of the protocol, like the protocol plugin of the
+    /// clients that use it. The additional protocols cannot contain components, since the entities are
+    /// only replicated with the main protocol.
+    ///
+    /// # Panics
+    ///
+    /// If `protocol` registers a component: the entities are not replicated to the clients of an
+    /// additional protocol, so it can only contain channels and messages.
+    fn add_protocol(&mut self, net_index: usize, protocol: impl FnOnce(&mut App));
+}
+
+impl AppProtocolExt for App {
+    fn add_protocol(&mut self, net_index: usize, protocol: impl FnOnce(&mut App)) {
+        // register the protocol in new registries, so that it doesn't modify the main protocol
+        let main_registries = take_registries(self);
+        protocol(self);
+        let (component_registry, message_registry, channel_registry) = take_registries(self);
+        insert_registries(self, main_registries);
+        // the closure runs on the server app, so the replication systems of its components would use
+        // the main registry, where they are not registered
+        assert!(
+            component_registry.is_empty(),
+            "the additional protocol for the transport {net_index} registers components, but the entities \
+            are not replicated to its clients: it can only contain channels and messages"
+        );
+        self.world
+            .get_resource_or_insert_with(AdditionalProtocols::default)
+            .0
+            .insert(
+                net_index,
+                ProtocolRegistries {
+                    message_registry,
+                    channel_registry,
+                    // computed once the internal protocol is registered
+                    hash: 0,
+                },
+            );
+    }
+}
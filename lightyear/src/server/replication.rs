@@ -2,16 +2,15 @@ use bevy::ecs::query::QueryFilter;
 use bevy::prelude::*;
 use bevy::utils::Duration;
 
-use crate::client::components::Confirmed;
-use crate::client::interpolation::Interpolated;
-use crate::client::prediction::Predicted;
-use crate::connection::client::NetClient;
-use crate::prelude::client::ClientConnection;
+use crate::client::components::{Confirmed, Interpolated, Predicted};
+#[cfg(feature = "client")]
+use crate::connection::client::{ClientConnection, NetClient};
 use crate::prelude::{PrePredicted, SharedConfig};
 use crate::server::config::ServerConfig;
 use crate::server::connection::ConnectionManager;
 use crate::server::networking::is_started;
 use crate::server::prediction::compute_hash;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::replication::plugin::receive::ReplicationReceivePlugin;
 use crate::shared::replication::plugin::send::ReplicationSendPlugin;
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet, ServerMarker};
@@ -32,6 +31,7 @@ pub(crate) mod receive {
 
     impl Plugin for ServerReplicationReceivePlugin {
         fn build(&self, app: &mut App) {
+            let schedules = NetworkScheduleConfig::get(app);
             app
                 // PLUGIN
                 .add_plugins(ReplicationReceivePlugin::<ConnectionManager>::new(
@@ -39,7 +39,7 @@ pub(crate) mod receive {
                 ))
                 // SETS
                 .configure_sets(
-                    PreUpdate,
+                    schedules.receive,
                     ServerReplicationSet::ClientReplication
                         .run_if(is_started)
                         .after(InternalMainSet::<ServerMarker>::EmitEvents),
@@ -52,17 +52,31 @@ pub(crate) mod send {
     use super::*;
     use crate::prelude::{
         ClientId, ComponentRegistry, DisabledComponent, OverrideTargetComponent,
-        ReplicateHierarchy, ReplicateOnceComponent, ReplicationGroup, ShouldBePredicted,
-        TargetEntity, VisibilityMode,
+        ReplicateHierarchy, ReplicateIf, ReplicateOnceComponent, ReplicationContext,
+        ReplicationGroup, ShouldBePredicted, TargetEntity, TickManager, TimeManager,
+        VisibilityMode,
     };
+    use crate::protocol::component::ComponentNetId;
+    use crate::server::clients::VisibleEntities;
     use crate::server::visibility::immediate::{ClientVisibility, ReplicateVisibility};
+    use crate::server::visibility::room::RoomManager;
     use crate::shared::replication::components::{
-        Controlled, DespawnTracker, Replicating, ReplicationTarget, ShouldBeInterpolated,
+        Controlled, DespawnTracker, NetworkId, RefreshComponent, RejectedUpdates, Replicating,
+        ReplicationGroupId, ReplicationPaused, ReplicationTarget, ShouldBeInterpolated,
     };
+    pub use crate::shared::replication::components::{ControlledBy, SyncTarget};
     use crate::shared::replication::network_target::NetworkTarget;
-    use crate::shared::replication::{systems, ReplicationSend};
+    use crate::shared::replication::systems::remove_refresh_markers;
+    use crate::shared::replication::{systems, DespawnReason, ReplicationSend};
+    use crate::shared::time_manager::WrappedTime;
+    use bevy::ecs::component::{ComponentId, Tick as BevyTick};
     use bevy::ecs::entity::Entities;
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::ecs::removal_detection::RemovedComponentEntity;
     use bevy::ecs::system::SystemChangeTick;
+    use bevy::utils::HashMap;
+    use serde::{Deserialize, Serialize};
+    use std::any::TypeId;
 
     #[derive(Default)]
     pub struct ServerReplicationSendPlugin {
@@ -71,7 +85,7 @@ pub(crate) mod send {
 
     impl Plugin for ServerReplicationSendPlugin {
         fn build(&self, app: &mut App) {
-            let config = app.world.resource::<ServerConfig>();
+            let schedules = NetworkScheduleConfig::get(app);
 
             app
                 // REFLECTION
@@ -82,53 +96,70 @@ pub(crate) mod send {
                 ))
                 // SYSTEM SETS
                 .configure_sets(
-                    PostUpdate,
+                    schedules.send,
                     // on server: we need to set the hash value before replicating the component
                     InternalReplicationSet::<ServerMarker>::SetPreSpawnedHash
                         .before(InternalReplicationSet::<ServerMarker>::BufferComponentUpdates)
                         .in_set(InternalReplicationSet::<ServerMarker>::All),
                 )
                 .configure_sets(
-                    PostUpdate,
+                    schedules.send,
                     InternalReplicationSet::<ServerMarker>::All.run_if(is_started),
                 )
                 // SYSTEMS
                 .add_systems(
-                    PostUpdate,
+                    schedules.send,
                     compute_hash.in_set(InternalReplicationSet::<ServerMarker>::SetPreSpawnedHash),
                 );
             // SYSTEMS
             app.add_systems(
-                PreUpdate,
+                schedules.receive,
                 // we need to add despawn trackers immediately for entities for which we add replicate
                 // TODO: why?
-                handle_replicating_add.after(ServerReplicationSet::ClientReplication),
+                handle_replicating_add
+                    .after(ServerReplicationSet::ClientReplication)
+                    .run_if(is_started),
             );
             app.add_systems(
-                PostUpdate,
+                schedules.send,
                 (
                     // NOTE: we need to run `send_entity_despawn` once per frame (and not once per send_interval)
                     //  because the RemovedComponents Events are present only for 1 frame and we might miss them if we don't run this every frame
                     //  It is ok to run it every frame because it creates at most one message per despawn
                     // NOTE: we make sure to update the replicate_cache before we make use of it in `send_entity_despawn`
-                    handle_replicating_remove
+                    (
+                        handle_replicating_remove,
+                        handle_replication_resume,
+                        handle_world_reset_acks,
+                        update_group_send_timers,
+                    )
                         .in_set(InternalReplicationSet::<ServerMarker>::BeforeBuffer),
                     // TODO: putting it here means we might miss entities that are spawned and despawned within the send_interval? bug or feature?
                     //  be careful that newly_connected_client is cleared every send_interval, not every frame.
-                    send_entity_spawn
+                    (send_entity_spawn, send_sync_target_update)
                         .in_set(InternalReplicationSet::<ServerMarker>::BufferEntityUpdates),
-                    send_entity_despawn
+                    send_dynamic_component_updates
+                        .in_set(InternalReplicationSet::<ServerMarker>::BufferComponentUpdates),
+                    (send_entity_despawn, send_dynamic_component_removed)
                         .in_set(InternalReplicationSet::<ServerMarker>::BufferDespawnsAndRemovals),
-                    (handle_replicating_add, handle_replication_target_update)
+                    (
+                        handle_replicating_add,
+                        handle_replication_target_update,
+                        remove_resumed_markers,
+                    )
                         .in_set(InternalReplicationSet::<ServerMarker>::AfterBuffer),
                 ),
             );
             // HOST-SERVER
+            #[cfg(feature = "client")]
             app.add_systems(
-                PostUpdate,
+                schedules.send,
                 add_prediction_interpolation_components
                     .after(InternalMainSet::<ServerMarker>::Send)
-                    .run_if(SharedConfig::is_host_server_condition),
+                    .run_if(
+                        SharedConfig::is_host_server_condition
+                            .and_then(resource_exists::<ClientConnection>),
+                    ),
             );
         }
     }
@@ -143,31 +174,6 @@ pub(crate) mod send {
         ),
     }
 
-    /// Component that indicates which clients should predict and interpolate the entity
-    #[derive(Component, Default, Clone, Debug, PartialEq, Reflect)]
-    pub struct SyncTarget {
-        /// Which clients should predict this entity (unused for client to server replication)
-        pub prediction: NetworkTarget,
-        /// Which clients should interpolate this entity (unused for client to server replication)
-        pub interpolation: NetworkTarget,
-    }
-
-    /// Component storing metadata about which clients have control over the entity
-    ///
-    /// This is only used for server to client replication.
-    #[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
-    pub struct ControlledBy {
-        /// Which client(s) control this entity?
-        pub target: NetworkTarget,
-    }
-
-    impl ControlledBy {
-        /// Returns true if the entity is controlled by the specified client
-        pub fn targets(&self, client_id: &ClientId) -> bool {
-            self.target.targets(client_id)
-        }
-    }
-
     /// Component to have more fine-grained control over the visibility of an entity
     /// (which clients do we replicate this entity to?)
     ///
@@ -203,7 +209,7 @@ pub(crate) mod send {
     ///
     /// Some of the components can be updated at runtime even after the entity has been replicated.
     /// For example you can update the [`ReplicationTarget`] to change which clients should receive the entity.
-    #[derive(Bundle, Clone, Default, PartialEq, Debug, Reflect)]
+    #[derive(Bundle, Clone, Default, PartialEq, Debug, Reflect, Serialize, Deserialize)]
     pub struct Replicate {
         /// Which clients should this entity be replicated to?
         pub target: ReplicationTarget,
@@ -225,8 +231,44 @@ pub(crate) mod send {
         pub marker: Replicating,
     }
 
+    /// The clients that received a world reset need to receive the entire world state again,
+    /// as if they had just connected
+    pub(crate) fn handle_world_reset_acks(
+        mut sender: ResMut<ConnectionManager>,
+        client_query: Query<&VisibleEntities>,
+        mut query: Query<&mut ReplicateVisibility>,
+    ) {
+        let clients = sender.take_world_reset_clients();
+        if clients.is_empty() {
+            return;
+        }
+        // with interest management, the entity spawns are only sent to clients that gain visibility
+        for client_id in clients.iter() {
+            let Some(visible) = sender
+                .client_entity(*client_id)
+                .ok()
+                .and_then(|entity| client_query.get(entity).ok())
+            else {
+                continue;
+            };
+            for entity in visible.iter() {
+                if let Some(client_visibility) = query
+                    .get_mut(*entity)
+                    .ok()
+                    .and_then(|visibility| visibility.into_inner().clients_cache.get_mut(client_id))
+                {
+                    if *client_visibility == ClientVisibility::Maintained {
+                        *client_visibility = ClientVisibility::Gained;
+                    }
+                }
+            }
+        }
+        sender.new_clients.extend(clients);
+    }
+
     /// In HostServer mode, we will add the Predicted/Interpolated components to the server entities
     /// So that client code can still query for them
+    #[cfg(feature = "client")]
     fn add_prediction_interpolation_components(
         mut commands: Commands,
         query: Query<(
@@ -277,6 +319,10 @@ pub(crate) mod send {
         pub(crate) visibility_mode: VisibilityMode,
         /// If mode = Room, the list of clients that could see the entity
         pub(crate) replication_clients_cache: Vec<ClientId>,
+        /// Which clients were predicting/interpolating the entity
+        pub(crate) sync_target: SyncTarget,
+        /// Reason code to send with the despawn of the entity
+        pub(crate) despawn_reason: Option<DespawnReason>,
     }
 
     /// For every entity that removes their ReplicationTarget component but are not despawned, remove the component
@@ -297,6 +343,35 @@ pub(crate) mod send {
         }
     }
 
+    /// Marker inserted on the entities whose [`ReplicationPaused`] component was removed, so that the current
+    /// value of all their components is sent during the next replication update
+    #[derive(Component)]
+    pub(crate) struct ResumedReplication;
+
+    /// Force a full update of the entities whose replication was resumed
+    pub(crate) fn handle_replication_resume(
+        mut commands: Commands,
+        mut removed: RemovedComponents<ReplicationPaused>,
+        query: Query<(), With<Replicating>>,
+    ) {
+        for entity in removed.read() {
+            if query.contains(entity) {
+                debug!(?entity, "replication resumed, sending a full update");
+                commands.entity(entity).insert(ResumedReplication);
+            }
+        }
+    }
+
+    /// Remove the [`ResumedReplication`] markers once the full updates have been buffered for replication
+    pub(crate) fn remove_resumed_markers(
+        mut commands: Commands,
+        query: Query<Entity, With<ResumedReplication>>,
+    ) {
+        for entity in query.iter() {
+            commands.entity(entity).remove::<ResumedReplication>();
+        }
+    }
+
     /// This system does all the additional bookkeeping required after [`Replicating`] has been added:
     /// - adds DespawnTracker to each entity that was ever replicated, so that we can track when they are despawned
     /// (we have a distinction between removing Replicating, which just stops replication; and despawning the entity)
@@ -313,11 +388,12 @@ pub(crate) mod send {
                 &ReplicationTarget,
                 &ReplicationGroup,
                 &VisibilityMode,
+                Option<&SyncTarget>,
             ),
             (With<Replicating>, Without<DespawnTracker>),
         >,
     ) {
-        for (entity, replication_target, group, visibility_mode) in query.iter() {
+        for (entity, replication_target, group, visibility_mode, sync_target) in query.iter() {
             debug!("Replicate component was added for entity {entity:?}");
             commands.entity(entity).insert(DespawnTracker);
             let despawn_metadata = ReplicateCache {
@@ -325,6 +401,8 @@ pub(crate) mod send {
                 replication_group: *group,
                 visibility_mode: *visibility_mode,
                 replication_clients_cache: vec![],
+                sync_target: sync_target.cloned().unwrap_or_default(),
+                despawn_reason: None,
             };
             sender
                 .replicate_component_cache
@@ -337,6 +415,7 @@ pub(crate) mod send {
     /// - newly_connected_clients should receive the entity spawn message even if the entity was not just spawned
     /// - adds ControlledBy, ShouldBePredicted, ShouldBeInterpolated component
     /// - handles TargetEntity if it's a Preexisting entity
+    /// - handles the pre-placed entities that have a NetworkId
     pub(crate) fn send_entity_spawn(
         component_registry: Res<ComponentRegistry>,
         query: Query<(
@@ -346,12 +425,16 @@ pub(crate) mod send {
             &ReplicationGroup,
             &ControlledBy,
             Option<&TargetEntity>,
+            Option<&NetworkId>,
             Option<&ReplicateVisibility>,
         )>,
         mut sender: ResMut<ConnectionManager>,
     ) {
         // Replicate to already connected clients (replicate only new entities)
-        query.iter().for_each(|(entity, replication_target, sync_target, group, controlled_by, target_entity, visibility )| {
+        query.iter().for_each(|(entity, replication_target, sync_target, group, controlled_by, target_entity, network_id, visibility )| {
+            let base_target = sender.spectators_target(&replication_target.target);
+            // clients that asked to receive the entity again
+            let resend_target = sender.resend_target(entity);
             let target = match visibility {
                 // for room mode, no need to handle newly-connected clients specially; they just need
                 // to be added to the correct room
@@ -359,7 +442,7 @@ pub(crate) mod send {
                     visibility.clients_cache
                         .iter()
                         .filter_map(|(client_id, visibility)| {
-                            if replication_target.target.targets(client_id) {
+                            if base_target.targets(client_id) {
                                 match visibility {
                                     ClientVisibility::Gained => {
                                         trace!(
@@ -372,7 +455,7 @@ pub(crate) mod send {
                                     ClientVisibility::Lost => {}
                                     ClientVisibility::Maintained => {
                                         // only try to replicate if the replicate component was just added
-                                        if replication_target.is_added() {
+                                        if replication_target.is_added() || resend_target.targets(client_id) {
                                             trace!(
                                                 ?entity,
                                                 ?client_id,
@@ -391,13 +474,13 @@ pub(crate) mod send {
                     // only try to replicate if the replicate component was just added
                     if replication_target.is_added() {
                         trace!(?entity, "send entity spawn");
-                        target = replication_target.target.clone();
+                        target = base_target.clone();
                     } else if replication_target.is_changed() {
-                        target = replication_target.target.clone();
+                        target = base_target.clone();
                         if let Some(cached_replicate) = sender.replicate_component_cache.get(&entity) {
                             // do not re-send a spawn message to the clients for which we already have
                             // replicated the entity
-                            target.exclude(&cached_replicate.replication_target)
+                            target.exclude(&sender.spectators_target(&cached_replicate.replication_target))
                         }
                     }
 
@@ -406,10 +489,16 @@ pub(crate) mod send {
                     if !new_connected_clients.is_empty() {
                         // replicate to the newly connected clients that match our target
                         let mut new_connected_target = NetworkTarget::Only(new_connected_clients);
-                        new_connected_target.intersection(&replication_target.target);
+                        new_connected_target.intersection(&base_target);
                         debug!(?entity, target = ?new_connected_target, "Replicate to newly connected clients");
                         target.union(&new_connected_target);
                     }
+                    if !resend_target.is_empty() {
+                        let mut resend_target = resend_target;
+                        resend_target.intersection(&base_target);
+                        debug!(?entity, target = ?resend_target, "Replicate again to clients that requested it");
+                        target.union(&resend_target);
+                    }
                     target
                 }
             };
@@ -453,6 +542,12 @@ pub(crate) mod send {
                         group_id,
                         *remote_entity,
                     );
+                } else if let Some(network_id) = network_id {
+                    sender.connection_mut(client_id)?.replication_sender.prepare_entity_spawn_link(
+                        entity,
+                        group_id,
+                        *network_id,
+                    );
                 } else {
                     sender.connection_mut(client_id)?.replication_sender
                         .prepare_entity_spawn(entity, group_id);
@@ -468,6 +563,92 @@ pub(crate) mod send {
         });
     }
 
+    /// Send the [`ShouldBePredicted`]/[`ShouldBeInterpolated`] markers to the clients that start
+    /// predicting or interpolating an entity after it was spawned (because its [`SyncTarget`] was updated).
+    ///
+    /// The clients that receive the entity spawn in the same frame get the markers via [`send_entity_spawn`].
+    pub(crate) fn send_sync_target_update(
+        component_registry: Res<ComponentRegistry>,
+        query: Query<
+            (
+                Entity,
+                Ref<SyncTarget>,
+                &ReplicationTarget,
+                &ReplicationGroup,
+                Option<&ReplicateVisibility>,
+            ),
+            With<Replicating>,
+        >,
+        mut sender: ResMut<ConnectionManager>,
+    ) {
+        query.iter().for_each(
+            |(entity, sync_target, replication_target, group, visibility)| {
+                if !sync_target.is_changed() || sync_target.is_added() {
+                    return;
+                }
+                let Some(cache) = sender.replicate_component_cache.get_mut(&entity) else {
+                    return;
+                };
+                let previous_sync_target =
+                    std::mem::replace(&mut cache.sync_target, sync_target.clone());
+                let cached_target = cache.replication_target.clone();
+                // only notify the clients that already had the entity
+                let mut target = sender.spectators_target(&replication_target.target);
+                target.intersection(&sender.spectators_target(&cached_target));
+                if let Some(visibility) = visibility {
+                    target.intersection(&NetworkTarget::from(
+                        visibility
+                            .clients_cache
+                            .iter()
+                            .filter(|(_, visibility)| {
+                                matches!(visibility, ClientVisibility::Maintained)
+                            })
+                            .map(|(client_id, _)| *client_id)
+                            .collect::<Vec<_>>(),
+                    ));
+                }
+                target.exclude(&NetworkTarget::Only(sender.new_connected_clients()));
+                let group_id = group.group_id(Some(entity));
+                let _ = sender
+                    .apply_replication(target)
+                    .try_for_each(|client_id| {
+                        if sync_target.prediction.targets(&client_id)
+                            && !previous_sync_target.prediction.targets(&client_id)
+                        {
+                            debug!(?entity, ?client_id, "client starts predicting the entity");
+                            sender.prepare_typed_component_insert(
+                                entity,
+                                group_id,
+                                client_id,
+                                component_registry.as_ref(),
+                                &ShouldBePredicted,
+                            )?;
+                        }
+                        if sync_target.interpolation.targets(&client_id)
+                            && !previous_sync_target.interpolation.targets(&client_id)
+                        {
+                            debug!(
+                                ?entity,
+                                ?client_id,
+                                "client starts interpolating the entity"
+                            );
+                            sender.prepare_typed_component_insert(
+                                entity,
+                                group_id,
+                                client_id,
+                                component_registry.as_ref(),
+                                &ShouldBeInterpolated,
+                            )?;
+                        }
+                        Ok(())
+                    })
+                    .inspect_err(|e: &anyhow::Error| {
+                        error!("error sending sync target update: {:?}", e);
+                    });
+            },
+        );
+    }
+
     /// Send entity despawn is:
     /// 1) the client lost visibility of the entity
     /// 2) the replication target was updated and the client is no longer in the ReplicationTarget
@@ -488,6 +669,7 @@ pub(crate) mod send {
         query
             .iter()
             .for_each(|(entity, replication_target, group, visibility)| {
+                let base_target = sender.spectators_target(&replication_target.target);
                 let mut target: NetworkTarget = match visibility {
                     Some(visibility) => {
                         // send despawn for clients that lost visibility
@@ -495,7 +677,7 @@ pub(crate) mod send {
                             .clients_cache
                             .iter()
                             .filter_map(|(client_id, visibility)| {
-                                if replication_target.target.targets(client_id)
+                                if base_target.targets(client_id)
                                     && matches!(visibility, ClientVisibility::Lost) {
                                     debug!(
                                     "sending entity despawn for entity: {:?} because ClientVisibility::Lost",
@@ -512,9 +694,9 @@ pub(crate) mod send {
                 };
                 // 2. if the replication target changed, find the clients that were removed in the new replication target
                 if replication_target.is_changed() && !replication_target.is_added() {
-                    if let Some(cache) = sender.replicate_component_cache.get_mut(&entity) {
-                        let mut new_despawn = cache.replication_target.clone();
-                        new_despawn.exclude(&replication_target.target);
+                    if let Some(cache) = sender.replicate_component_cache.get(&entity) {
+                        let mut new_despawn = sender.spectators_target(&cache.replication_target);
+                        new_despawn.exclude(&base_target);
                         target.union(&new_despawn);
                     }
                 }
@@ -523,6 +705,7 @@ pub(crate) mod send {
                         .prepare_entity_despawn(
                             entity,
                             group,
+                            Some(DespawnReason::LOST_VISIBILITY),
                             target
                         )
                         .inspect_err(|e| {
@@ -538,7 +721,8 @@ pub(crate) mod send {
             // only replicate the despawn if the entity still had a Replicate component
             if let Some(replicate_cache) = sender.replicate_component_cache.remove(&entity) {
                 // TODO: DO NOT SEND ENTITY DESPAWN TO THE CLIENT WHO JUST DISCONNECTED!
-                let mut network_target = replicate_cache.replication_target;
+                let mut network_target =
+                    sender.spectators_target(&replicate_cache.replication_target);
 
                 // TODO: for this to work properly, we need the replicate stored in `sender.get_mut_replicate_component_cache()`
                 //  to be updated for every replication change! Wait for observers instead.
@@ -555,6 +739,7 @@ pub(crate) mod send {
                     .prepare_entity_despawn(
                         entity,
                         &replicate_cache.replication_group,
+                        replicate_cache.despawn_reason,
                         network_target,
                     )
                     // TODO: bubble up errors to user via ConnectionEvents?
@@ -576,9 +761,22 @@ pub(crate) mod send {
     /// - last time we sent an update for that group which got acked.
     /// (currently we only check for the second condition, which is enough but less efficient)
     ///
+    /// If the component has its own [`send_interval`](crate::prelude::ComponentRegistration::with_send_interval),
+    /// the updates (but not the inserts) are only sent once that interval has elapsed.
+    /// The same goes for the replication groups that have their own send interval.
+    ///
+    /// A client that connects while the entity is already replicated receives exactly one value per component:
+    /// the current one, in the same reliable message as the spawn of the entity. This is also the case for the
+    /// [`ReplicateOnceComponent`]s and the components whose [replication predicate](crate::prelude::ComponentRegistration::add_replicate_if)
+    /// or [`ReplicateIf`] rejects the current value. The changes that happened before the client connected are not sent,
+    /// and the values contained in the spawn message are not sent again as updates.
+    ///
     /// NOTE: cannot use ConnectEvents because they are reset every frame
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn send_component_update<C: Component>(
         registry: Res<ComponentRegistry>,
+        mut send_timer: Local<ComponentSendTimer>,
+        mut rejected_updates: Local<RejectedUpdates>,
         query: Query<
             (
                 Entity,
@@ -589,27 +787,59 @@ pub(crate) mod send {
                 Option<&ReplicateVisibility>,
                 Has<DisabledComponent<C>>,
                 Has<ReplicateOnceComponent<C>>,
+                Has<RefreshComponent<C>>,
+                Has<ResumedReplication>,
                 Option<&OverrideTargetComponent<C>>,
+                Option<&ReplicateIf<C>>,
             ),
-            With<Replicating>,
+            (With<Replicating>, Without<ReplicationPaused>),
         >,
         system_bevy_ticks: SystemChangeTick,
+        tick_manager: Res<TickManager>,
+        time_manager: Res<TimeManager>,
         mut sender: ResMut<ConnectionManager>,
     ) {
         let kind = registry.net_id::<C>();
+        let tick = tick_manager.tick();
+        let (ready_to_send, last_send_tick) = match registry.send_interval::<C>() {
+            Some(send_interval) => send_timer.update(
+                send_interval,
+                time_manager.current_time(),
+                system_bevy_ticks.this_run(),
+            ),
+            None => (true, None),
+        };
+        let mut forced_updates = sender.take_forced_updates::<C>();
+        rejected_updates.retain(|entity| query.contains(entity));
         query
             .iter()
-            .for_each(|(entity, component, replication_target, sync_target, group,  visibility, disabled, replicate_once, override_target)| {
+            .for_each(|(entity, component, replication_target, sync_target, group,  visibility, disabled, replicate_once, refresh, resumed, override_target, replicate_if)| {
                 // do not replicate components that are disabled
                 if disabled {
                     return;
                 }
+                // after a pause, the current value is sent as if the component was refreshed
+                let refresh = refresh || resumed;
+                let change_tick = if resumed {
+                    system_bevy_ticks.this_run()
+                } else {
+                    component.last_changed()
+                };
+                // the group can also have its own send interval
+                let (group_ready, group_last_send_tick) = sender.group_send_status(group.group_id(Some(entity)));
+                let ready_to_send = ready_to_send && group_ready;
+                let last_send_tick = oldest_tick(last_send_tick, group_last_send_tick, system_bevy_ticks.this_run());
                 // use the overriden target if present
-                let target = override_target.map_or(&replication_target.target, |override_target| &override_target.target);
-                let (insert_target, update_target): (NetworkTarget, NetworkTarget) = match visibility {
+                let target = &sender.spectators_target(override_target.map_or(&replication_target.target, |override_target| &override_target.target));
+                // clients that asked to receive the entity again
+                let resend_target = sender.resend_target(entity);
+                // the clients that receive the component for the first time (without being an insert) get its
+                // current value whether or not it changed or passes the replication predicate
+                let (insert_target, mut update_target, initial_target): (NetworkTarget, NetworkTarget, NetworkTarget) = match visibility {
                     Some(visibility) => {
                         let mut insert_clients = vec![];
                         let mut update_clients = vec![];
+                        let mut initial_clients = vec![];
                         visibility
                             .clients_cache
                             .iter()
@@ -624,20 +854,24 @@ pub(crate) mod send {
                                             // send a component_insert for components that were newly added
                                             if component.is_added() {
                                                 insert_clients.push(*client_id);
+                                            } else if resend_target.targets(client_id) {
+                                                initial_clients.push(*client_id);
                                             } else {
                                                 // for components that were not newly added, only send as updates
-                                                if replicate_once {
+                                                if replicate_once && !refresh {
                                                     // we can exit the function immediately because we know we don't want to replicate
                                                     // to any client
                                                     return;
                                                 }
-                                                update_clients.push(*client_id);
+                                                if ready_to_send || refresh {
+                                                    update_clients.push(*client_id);
+                                                }
                                             }
                                         }
                                     }
                                 }
                             });
-                        (NetworkTarget::from(insert_clients), NetworkTarget::from(update_clients))
+                        (NetworkTarget::from(insert_clients), NetworkTarget::from(update_clients), NetworkTarget::from(initial_clients))
                     }
                     None => {
                         let (mut insert_target, mut update_target) =
@@ -653,37 +887,77 @@ pub(crate) mod send {
                         if component.is_added() || replication_target.is_added() {
                             trace!("component is added or replication_target is added");
                             insert_target.union(target);
-                        } else {
+                        } else if replicate_once && !refresh {
                             // do not send updates for these components, only inserts/removes
-                            if replicate_once {
-                                trace!(?entity,
+                            // (unless the component was explicitly refreshed)
+                            trace!(?entity,
                                 "not replicating updates for {:?} because it is marked as replicate_once",
                                 kind
                             );
-                                return;
-                            }
+                        } else if ready_to_send || refresh {
                             // otherwise send an update for all components that changed since the
                             // last update we have ack-ed
                             update_target.union(target);
                         }
 
+                        let mut initial_target = NetworkTarget::None;
                         let new_connected_clients = sender.new_connected_clients();
-                        // replicate all components to newly connected clients
+                        // replicate the current value of all components to newly connected clients
                         if !new_connected_clients.is_empty() {
                             // replicate to the newly connected clients that match our target
-                            let mut new_connected_target = NetworkTarget::Only(new_connected_clients);
-                            new_connected_target.intersection(target);
-                            debug!(?entity, target = ?new_connected_target, "Replicate to newly connected clients");
-                            update_target.union(&new_connected_target);
+                            initial_target = NetworkTarget::Only(new_connected_clients);
+                            initial_target.intersection(target);
+                            debug!(?entity, target = ?initial_target, "Replicate to newly connected clients");
+                        }
+                        if !resend_target.is_empty() {
+                            let mut resend_target = resend_target;
+                            resend_target.intersection(target);
+                            initial_target.union(&resend_target);
+                        }
+                        (insert_target, update_target, initial_target)
+                    }
+                };
+                // do not send updates that are rejected by the replication predicates of the component
+                // (unless the component was explicitly refreshed), except for the first rejected value
+                if !update_target.is_empty() && !refresh {
+                    let rejected = !registry.should_replicate(component.as_ref(), &ReplicationContext { entity, tick })
+                        || replicate_if.is_some_and(|replicate_if| !replicate_if.should_replicate(component.as_ref()));
+                    if !rejected_updates.should_send(entity, rejected, change_tick) {
+                        update_target = NetworkTarget::None;
+                    }
+                }
+                update_target.union(&initial_target);
+                // the clients that have the entity and for which an update was forced receive the current value
+                let forced_target = match forced_updates.remove(&entity) {
+                    Some(mut forced_target) => {
+                        forced_target.intersection(target);
+                        if let Some(visibility) = visibility {
+                            forced_target.intersection(&NetworkTarget::from(
+                                visibility
+                                    .clients_cache
+                                    .iter()
+                                    .filter(|(_, visibility)| matches!(visibility, ClientVisibility::Maintained))
+                                    .map(|(client_id, _)| *client_id)
+                                    .collect::<Vec<_>>(),
+                            ));
                         }
-                        (insert_target, update_target)
+                        forced_target.exclude(&insert_target);
+                        update_target.exclude(&forced_target);
+                        forced_target
                     }
+                    None => NetworkTarget::None,
                 };
-                if !insert_target.is_empty() || !update_target.is_empty() {
+                if insert_target.is_empty() && update_target.is_empty() && forced_target.is_empty() {
+                    return;
+                }
+                // the clients that use an older version of the protocol can receive a different representation of the component
+                for (migration_version, insert_target, update_target) in
+                    sender.split_by_protocol_version::<C>(&registry, insert_target, update_target)
+                {
                     // serialize component
                     let writer = sender.writer();
                     let raw_data = registry
-                        .serialize(component.as_ref(), writer)
+                        .serialize_migrated(component.as_ref(), writer, migration_version)
                         .expect("Could not serialize component");
 
                     if !insert_target.is_empty() {
@@ -704,22 +978,188 @@ pub(crate) mod send {
                             });
                     }
                     if !update_target.is_empty() {
-                        let _ = sender
-                            .prepare_component_update(
+                        // migrated components are sent without delta compression
+                        let result = if migration_version.is_none() && registry.has_delta_compression::<C>() {
+                            sender.prepare_delta_component_update(
+                                entity,
+                                kind,
+                                component.as_ref(),
+                                &registry,
+                                group,
+                                update_target,
+                                change_tick,
+                                last_send_tick,
+                                system_bevy_ticks.this_run(),
+                                tick,
+                            )
+                        } else {
+                            sender.prepare_component_update(
                                 entity,
                                 kind,
                                 raw_data,
                                 group,
                                 update_target,
-                                component.last_changed(),
+                                change_tick,
+                                last_send_tick,
                                 system_bevy_ticks.this_run(),
                             )
-                            .inspect_err(|e| {
-                                error!("error sending component update: {:?}", e);
-                            });
+                        };
+                        let _ = result.inspect_err(|e| {
+                            error!("error sending component update: {:?}", e);
+                        });
+                    }
+                }
+                if forced_target.is_empty() {
+                    return;
+                }
+                for (migration_version, _, forced_target) in
+                    sender.split_by_protocol_version::<C>(&registry, NetworkTarget::None, forced_target)
+                {
+                    if forced_target.is_empty() {
+                        continue;
                     }
+                    // the full value is sent, without delta compression
+                    let writer = sender.writer();
+                    let raw_data = registry
+                        .serialize_migrated(component.as_ref(), writer, migration_version)
+                        .expect("Could not serialize component");
+                    let _ = sender
+                        .prepare_component_update(
+                            entity,
+                            kind,
+                            raw_data,
+                            group,
+                            forced_target,
+                            // consider the component as changed so that it is sent
+                            system_bevy_ticks.this_run(),
+                            last_send_tick,
+                            system_bevy_ticks.this_run(),
+                        )
+                        .inspect_err(|e| {
+                            error!("error sending forced component update: {:?}", e);
+                        });
                 }
             });
+        if !forced_updates.is_empty() {
+            debug!(
+                entities = ?forced_updates.keys().collect::<Vec<_>>(),
+                "could not force the update of entities that are not replicated"
+            );
+        }
+    }
+
+    /// Timer of the updates of a component that has its own send interval
+    #[derive(Default)]
+    pub(crate) struct ComponentSendTimer {
+        /// Time and system tick of the last run where we sent the updates of the component
+        last_send: Option<(WrappedTime, BevyTick)>,
+    }
+
+    impl ComponentSendTimer {
+        /// Returns true if the updates of the component should be sent at this run,
+        /// along with the system tick of the previous run where they were sent.
+        fn update(
+            &mut self,
+            send_interval: Duration,
+            now: WrappedTime,
+            this_run: BevyTick,
+        ) -> (bool, Option<BevyTick>) {
+            let previous = self.last_send;
+            if previous
+                .is_some_and(|(time, _)| (now - time).to_std().unwrap_or_default() < send_interval)
+            {
+                return (false, None);
+            }
+            self.last_send = Some((now, this_run));
+            (true, previous.map(|(_, tick)| tick))
+        }
+    }
+
+    /// Returns the oldest of the two ticks, so that we don't miss the changes that happened since either of them
+    fn oldest_tick(
+        a: Option<BevyTick>,
+        b: Option<BevyTick>,
+        this_run: BevyTick,
+    ) -> Option<BevyTick> {
+        match (a, b) {
+            (Some(a), Some(b)) if a.is_newer_than(b, this_run) => Some(b),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Timer of the updates of a replication group that has its own send interval
+    #[derive(Default)]
+    pub(crate) struct GroupSendTimer {
+        timer: ComponentSendTimer,
+        /// Whether the updates of the group are sent at this run, and the system tick of the previous send
+        status: (bool, Option<BevyTick>),
+    }
+
+    impl GroupSendTimer {
+        pub(crate) fn status(&self) -> (bool, Option<BevyTick>) {
+            self.status
+        }
+    }
+
+    /// Update the timers of the replication groups that have their own send interval, either on their
+    /// [`ReplicationGroup`] or on the [`Room`](crate::prelude::server::Room)s of their entities.
+    ///
+    /// Each client is told the send intervals of the slower groups that are replicated to it, so that it
+    /// can interpolate them.
+    pub(crate) fn update_group_send_timers(
+        config: Res<ServerConfig>,
+        query: Query<(Entity, &ReplicationGroup), With<Replicating>>,
+        room_manager: Option<Res<RoomManager>>,
+        system_bevy_ticks: SystemChangeTick,
+        time_manager: Res<TimeManager>,
+        mut sender: ResMut<ConnectionManager>,
+    ) {
+        // a group is sent at the full rate if one of its entities is
+        let mut send_intervals: HashMap<ReplicationGroupId, Option<Duration>> = HashMap::default();
+        for (entity, group) in query.iter() {
+            let send_interval = group.send_interval().or_else(|| {
+                room_manager
+                    .as_ref()
+                    .and_then(|room_manager| room_manager.entity_send_interval(entity))
+            });
+            send_intervals
+                .entry(group.group_id(Some(entity)))
+                .and_modify(|interval| {
+                    *interval = interval.zip(send_interval).map(|(a, b)| a.min(b));
+                })
+                .or_insert(send_interval);
+        }
+        let now = time_manager.current_time();
+        let this_run = system_bevy_ticks.this_run();
+        let sender = sender.as_mut();
+        sender
+            .group_send_timers
+            .retain(|group_id, _| send_intervals.get(group_id).is_some_and(Option::is_some));
+        for (group_id, send_interval) in send_intervals.iter() {
+            if let Some(send_interval) = send_interval {
+                let timer = sender.group_send_timers.entry(*group_id).or_default();
+                timer.status = timer.timer.update(*send_interval, now, this_run);
+            }
+        }
+
+        for connection in sender.connections.values_mut() {
+            let slow_groups = connection
+                .replication_sender
+                .group_channels
+                .keys()
+                .filter_map(|group_id| {
+                    send_intervals
+                        .get(group_id)
+                        .copied()
+                        .flatten()
+                        .filter(|interval| *interval > config.shared.server_send_interval)
+                        .map(|interval| (*group_id, interval))
+                })
+                .collect();
+            let _ = connection
+                .announce_send_intervals(slow_groups)
+                .inspect_err(|e| error!("could not announce the send intervals: {:?}", e));
+        }
     }
 
     /// This system sends updates for all components that were removed
@@ -749,10 +1189,11 @@ pub(crate) mod send {
                     return;
                 }
                 // use the overriden target if present
-                let base_target = override_target
-                    .map_or(&replication_target.target, |override_target| {
+                let base_target = &sender.spectators_target(
+                    override_target.map_or(&replication_target.target, |override_target| {
                         &override_target.target
-                    });
+                    }),
+                );
                 let target = match visibility {
                     Some(visibility) => {
                         visibility
@@ -786,6 +1227,284 @@ pub(crate) mod send {
         })
     }
 
+    /// Send the inserts and updates of the dynamic components (see [`ComponentRegistry::register_dynamic_component`])
+    /// to the clients that registered them too.
+    ///
+    /// The dynamic components are not known at compile-time, so this is an exclusive system that reads them with
+    /// reflection. They are sent like the other components, but without the per-component options
+    /// (target overrides, replication predicates, delta compression, send interval, etc.)
+    pub(crate) fn send_dynamic_component_updates(world: &mut World) {
+        let dynamic_components: Vec<(ComponentNetId, TypeId)> = world
+            .resource::<ComponentRegistry>()
+            .dynamic_components()
+            .map(|(net_id, dynamic)| (net_id, dynamic.type_id))
+            .collect();
+        if dynamic_components.is_empty() {
+            return;
+        }
+        let Some(type_registry) = world.get_resource::<AppTypeRegistry>().cloned() else {
+            return;
+        };
+        let type_registry = type_registry.read();
+        // in an exclusive system, the last change tick of the world is the last run of the system
+        let last_run = world.last_change_tick();
+        let this_run = world.read_change_tick();
+        world.resource_scope(|world, mut sender: Mut<ConnectionManager>| {
+            let registry = world.resource::<ComponentRegistry>();
+            let new_connected_clients = sender.new_connected_clients();
+            for (net_id, type_id) in dynamic_components {
+                let (Some(component_id), Some(reflect_component)) = (
+                    world.components().get_id(type_id),
+                    type_registry.get_type_data::<ReflectComponent>(type_id),
+                ) else {
+                    continue;
+                };
+                let supporting_clients = NetworkTarget::from(
+                    sender
+                        .connections
+                        .iter()
+                        .filter(|(_, connection)| {
+                            connection.dynamic_components.contains_key(&net_id)
+                        })
+                        .map(|(client_id, _)| *client_id)
+                        .collect::<Vec<_>>(),
+                );
+                if supporting_clients.is_empty() {
+                    continue;
+                }
+                // the clients that just registered the component receive its current value
+                let new_supporting_clients = NetworkTarget::from(
+                    sender
+                        .connections
+                        .iter()
+                        .filter(|(_, connection)| {
+                            connection.new_dynamic_components.contains(&net_id)
+                        })
+                        .map(|(client_id, _)| *client_id)
+                        .collect::<Vec<_>>(),
+                );
+                let entities: Vec<Entity> = world
+                    .archetypes()
+                    .iter()
+                    .filter(|archetype| archetype.contains(component_id))
+                    .flat_map(|archetype| archetype.entities().iter().map(|entity| entity.id()))
+                    .collect();
+                for entity in entities {
+                    let entity_ref = world.entity(entity);
+                    if !entity_ref.contains::<Replicating>()
+                        || entity_ref.contains::<ReplicationPaused>()
+                    {
+                        continue;
+                    }
+                    let (Some(replication_target), Some(group), Some(ticks), Some(component)) = (
+                        entity_ref.get_ref::<ReplicationTarget>(),
+                        entity_ref.get::<ReplicationGroup>(),
+                        entity_ref.get_change_ticks_by_id(component_id),
+                        reflect_component.reflect(entity_ref),
+                    ) else {
+                        continue;
+                    };
+                    let is_added = ticks.is_added(last_run, this_run);
+                    let mut target = sender.spectators_target(&replication_target.target);
+                    target.intersection(&supporting_clients);
+                    // the clients that asked to receive the entity again also get the current value
+                    let mut new_clients = new_supporting_clients.clone();
+                    new_clients.union(&sender.resend_target(entity));
+                    let (mut insert_target, mut update_target, mut initial_target) =
+                        match entity_ref.get::<ReplicateVisibility>() {
+                            Some(visibility) => {
+                                let mut insert_clients = vec![];
+                                let mut update_clients = vec![];
+                                let mut maintained_clients = vec![];
+                                for (client_id, visibility) in visibility.clients_cache.iter() {
+                                    if !target.targets(client_id) {
+                                        continue;
+                                    }
+                                    match visibility {
+                                        ClientVisibility::Gained => insert_clients.push(*client_id),
+                                        ClientVisibility::Lost => {}
+                                        ClientVisibility::Maintained => {
+                                            maintained_clients.push(*client_id);
+                                            if is_added {
+                                                insert_clients.push(*client_id);
+                                            } else {
+                                                update_clients.push(*client_id);
+                                            }
+                                        }
+                                    }
+                                }
+                                let mut initial_target = new_clients;
+                                initial_target
+                                    .intersection(&NetworkTarget::from(maintained_clients));
+                                (
+                                    NetworkTarget::from(insert_clients),
+                                    NetworkTarget::from(update_clients),
+                                    initial_target,
+                                )
+                            }
+                            None => {
+                                let (mut insert_target, mut update_target) =
+                                    (NetworkTarget::None, NetworkTarget::None);
+                                if is_added || replication_target.is_added() {
+                                    insert_target.union(&target);
+                                } else {
+                                    update_target.union(&target);
+                                }
+                                let mut initial_target = new_clients;
+                                initial_target
+                                    .union(&NetworkTarget::Only(new_connected_clients.clone()));
+                                initial_target.intersection(&target);
+                                (insert_target, update_target, initial_target)
+                            }
+                        };
+                    initial_target.exclude(&insert_target);
+                    insert_target.union(&initial_target);
+                    update_target.exclude(&insert_target);
+                    let (group_ready, last_send_tick) =
+                        sender.group_send_status(group.group_id(Some(entity)));
+                    if !group_ready {
+                        update_target = NetworkTarget::None;
+                    } else if !update_target.is_empty() {
+                        // only serialize the component if some clients didn't receive its latest change
+                        update_target = sender.clients_needing_update(
+                            entity,
+                            group,
+                            update_target,
+                            ticks.last_changed_tick(),
+                            last_send_tick,
+                            this_run,
+                        );
+                    }
+                    if insert_target.is_empty() && update_target.is_empty() {
+                        continue;
+                    }
+                    let raw_data = match registry.serialize_dynamic(
+                        net_id,
+                        component,
+                        &type_registry,
+                        sender.writer(),
+                    ) {
+                        Ok(raw_data) => raw_data,
+                        Err(e) => {
+                            error!("could not serialize the dynamic component: {:?}", e);
+                            continue;
+                        }
+                    };
+                    if !insert_target.is_empty() {
+                        let _ = sender
+                            .prepare_component_insert(
+                                entity,
+                                net_id,
+                                raw_data.clone(),
+                                registry,
+                                replication_target.as_ref(),
+                                None,
+                                group,
+                                insert_target,
+                            )
+                            .inspect_err(|e| {
+                                error!("error sending component insert: {:?}", e);
+                            });
+                    }
+                    if !update_target.is_empty() {
+                        let _ = sender
+                            .prepare_component_update(
+                                entity,
+                                net_id,
+                                raw_data,
+                                group,
+                                update_target,
+                                ticks.last_changed_tick(),
+                                last_send_tick,
+                                this_run,
+                            )
+                            .inspect_err(|e| {
+                                error!("error sending component update: {:?}", e);
+                            });
+                    }
+                }
+            }
+            sender
+                .connections
+                .values_mut()
+                .for_each(|connection| connection.new_dynamic_components.clear());
+        });
+    }
+
+    /// Send the removals of the dynamic components to the clients that registered them too
+    pub(crate) fn send_dynamic_component_removed(
+        world: &mut World,
+        mut removed_readers: Local<HashMap<ComponentId, ManualEventReader<RemovedComponentEntity>>>,
+    ) {
+        let dynamic_components: Vec<(ComponentNetId, ComponentId)> = world
+            .resource::<ComponentRegistry>()
+            .dynamic_components()
+            .filter_map(|(net_id, dynamic)| {
+                world
+                    .components()
+                    .get_id(dynamic.type_id)
+                    .map(|component_id| (net_id, component_id))
+            })
+            .collect();
+        world.resource_scope(|world, mut sender: Mut<ConnectionManager>| {
+            for (net_id, component_id) in dynamic_components {
+                let Some(events) = world.removed_components().get(component_id) else {
+                    continue;
+                };
+                let removed: Vec<Entity> = removed_readers
+                    .entry(component_id)
+                    .or_default()
+                    .read(events)
+                    .map(|entity| entity.clone().into())
+                    .collect();
+                let supporting_clients = NetworkTarget::from(
+                    sender
+                        .connections
+                        .iter()
+                        .filter(|(_, connection)| {
+                            connection.dynamic_components.contains_key(&net_id)
+                        })
+                        .map(|(client_id, _)| *client_id)
+                        .collect::<Vec<_>>(),
+                );
+                for entity in removed {
+                    // only remove the component for entities that are being actively replicated
+                    let Some(entity_ref) = world.get_entity(entity) else {
+                        continue;
+                    };
+                    if !entity_ref.contains::<Replicating>() {
+                        continue;
+                    }
+                    let (Some(replication_target), Some(group)) = (
+                        entity_ref.get::<ReplicationTarget>(),
+                        entity_ref.get::<ReplicationGroup>(),
+                    ) else {
+                        continue;
+                    };
+                    let mut target = sender.spectators_target(&replication_target.target);
+                    target.intersection(&supporting_clients);
+                    if let Some(visibility) = entity_ref.get::<ReplicateVisibility>() {
+                        target.intersection(&NetworkTarget::from(
+                            visibility
+                                .clients_cache
+                                .iter()
+                                .filter(|(_, visibility)| {
+                                    matches!(visibility, ClientVisibility::Maintained)
+                                })
+                                .map(|(client_id, _)| *client_id)
+                                .collect::<Vec<_>>(),
+                        ));
+                    }
+                    if target.is_empty() {
+                        continue;
+                    }
+                    debug!(?entity, ?net_id, "Sending RemoveComponent");
+                    let _ = sender.prepare_component_remove(entity, net_id, group, target);
+                }
+            }
+        });
+    }
+
     /// Update the replication_target in the cache when the ReplicationTarget component changes
     pub(crate) fn handle_replication_target_update(
         mut sender: ResMut<ConnectionManager>,
@@ -809,7 +1528,7 @@ pub(crate) mod send {
 
     pub(crate) fn register_replicate_component_send<C: Component>(app: &mut App) {
         app.add_systems(
-            PostUpdate,
+            NetworkScheduleConfig::get(app).send,
             (
                 // NOTE: we need to run `send_component_removed` once per frame (and not once per send_interval)
                 //  because the RemovedComponents Events are present only for 1 frame and we might miss them if we don't run this every frame
@@ -820,16 +1539,18 @@ pub(crate) mod send {
                 //  and use up all the bandwidth
                 send_component_update::<C>
                     .in_set(InternalReplicationSet::<ServerMarker>::BufferComponentUpdates),
+                remove_refresh_markers::<C>
+                    .in_set(InternalReplicationSet::<ServerMarker>::AfterBuffer),
             ),
         );
     }
 
-    #[cfg(test)]
+    #[cfg(all(test, feature = "client", feature = "server"))]
     mod tests {
         use super::*;
         use crate::client::events::ComponentUpdateEvent;
         use crate::prelude::client::Confirmed;
-        use crate::prelude::server::{ControlledBy, Replicate, VisibilityManager};
+        use crate::prelude::server::{ControlledBy, Replicate, RoomId, VisibilityManager};
         use crate::prelude::{client, server, Replicated};
         use crate::server::replication::send::SyncTarget;
         use crate::shared::replication::components::Controlled;
@@ -903,30 +1624,95 @@ pub(crate) mod send {
                 .is_some());
         }
 
+        /// Check that the entities of a group spawned in the same frame are all replicated
+        /// (their spawns are sent in a batch)
         #[test]
-        fn test_entity_spawn_visibility() {
-            let mut stepper = MultiBevyStepper::default();
+        fn test_entity_spawn_batch() {
+            let mut stepper = BevyStepper::default();
 
-            // spawn an entity on server with visibility::InterestManagement
-            let server_entity = stepper
+            let group = ReplicationGroup::new_id(1);
+            let server_entities: Vec<Entity> = (0..100)
+                .map(|i| {
+                    stepper
+                        .server_app
+                        .world
+                        .spawn((
+                            Replicate { group, ..default() },
+                            Component1(i as f32),
+                            Component2(-(i as f32)),
+                        ))
+                        .id()
+                })
+                .collect();
+            // an entity of the same group with different components
+            let other_entity = stepper
                 .server_app
                 .world
-                .spawn(server::Replicate {
-                    visibility: VisibilityMode::InterestManagement,
-                    ..default()
-                })
+                .spawn((Replicate { group, ..default() }, Component1(1000.0)))
                 .id();
-            stepper.frame_step();
-            stepper.frame_step();
+            for _ in 0..10 {
+                stepper.frame_step();
+            }
 
-            // check that entity wasn't spawned
-            assert!(stepper
-                .client_app_1
+            for (i, server_entity) in server_entities.iter().enumerate() {
+                let client_entity = *stepper
+                    .client_app
+                    .world
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(*server_entity)
+                    .expect("entity was not replicated to client");
+                let client_entity_ref = stepper.client_app.world.entity(client_entity);
+                assert_eq!(
+                    client_entity_ref.get::<Component1>(),
+                    Some(&Component1(i as f32))
+                );
+                assert_eq!(
+                    client_entity_ref.get::<Component2>(),
+                    Some(&Component2(-(i as f32)))
+                );
+            }
+            let client_entity = *stepper
+                .client_app
                 .world
                 .resource::<client::ConnectionManager>()
                 .replication_receiver
                 .remote_entity_map
-                .get_local(server_entity)
+                .get_local(other_entity)
+                .expect("entity was not replicated to client");
+            let client_entity_ref = stepper.client_app.world.entity(client_entity);
+            assert_eq!(
+                client_entity_ref.get::<Component1>(),
+                Some(&Component1(1000.0))
+            );
+            assert!(client_entity_ref.get::<Component2>().is_none());
+        }
+
+        #[test]
+        fn test_entity_spawn_visibility() {
+            let mut stepper = MultiBevyStepper::default();
+
+            // spawn an entity on server with visibility::InterestManagement
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn(server::Replicate {
+                    visibility: VisibilityMode::InterestManagement,
+                    ..default()
+                })
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that entity wasn't spawned
+            assert!(stepper
+                .client_app_1
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
                 .is_none());
             // make entity visible
             stepper
@@ -957,6 +1743,126 @@ pub(crate) mod send {
                 .is_none());
         }
 
+        #[test]
+        fn test_spectator() {
+            let mut stepper = MultiBevyStepper::default();
+            let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+            let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+            let targeted = stepper
+                .server_app
+                .world
+                .spawn((
+                    Component1(1.0),
+                    Replicate {
+                        target: ReplicationTarget {
+                            target: NetworkTarget::Single(client_1),
+                        },
+                        ..default()
+                    },
+                ))
+                .id();
+            let visible = stepper
+                .server_app
+                .world
+                .spawn(Replicate {
+                    visibility: VisibilityMode::InterestManagement,
+                    ..default()
+                })
+                .id();
+            let own = stepper
+                .server_app
+                .world
+                .spawn(Replicate {
+                    target: ReplicationTarget {
+                        target: NetworkTarget::Single(client_2),
+                    },
+                    ..default()
+                })
+                .id();
+            stepper
+                .server_app
+                .world
+                .resource_mut::<VisibilityManager>()
+                .gain_visibility(client_1, visible);
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_2_entity = |stepper: &MultiBevyStepper, server_entity| {
+                stepper
+                    .client_app_2
+                    .world
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(server_entity)
+                    .copied()
+            };
+            assert!(client_2_entity(&stepper, own).is_some());
+            assert!(client_2_entity(&stepper, targeted).is_none());
+            assert!(client_2_entity(&stepper, visible).is_none());
+
+            // client 2 spectates client 1: it receives the same entities as client 1
+            stepper
+                .server_app
+                .world
+                .resource_mut::<ConnectionManager>()
+                .start_spectating(client_2, client_1)
+                .unwrap();
+            for _ in 0..10 {
+                stepper.frame_step();
+            }
+            assert!(client_2_entity(&stepper, own).is_none());
+            assert!(client_2_entity(&stepper, visible).is_some());
+            let spectated_entity = client_2_entity(&stepper, targeted).unwrap();
+            assert_eq!(
+                stepper
+                    .client_app_2
+                    .world
+                    .get::<Component1>(spectated_entity),
+                Some(&Component1(1.0))
+            );
+            // updates are also replicated to the spectator
+            stepper
+                .server_app
+                .world
+                .get_mut::<Component1>(targeted)
+                .unwrap()
+                .0 = 2.0;
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app_2
+                    .world
+                    .get::<Component1>(spectated_entity),
+                Some(&Component1(2.0))
+            );
+
+            // client 2 receives its own replication stream again
+            assert_eq!(
+                stepper
+                    .server_app
+                    .world
+                    .resource_mut::<ConnectionManager>()
+                    .stop_spectating(client_2),
+                Some(client_1)
+            );
+            for _ in 0..10 {
+                stepper.frame_step();
+            }
+            assert!(client_2_entity(&stepper, own).is_some());
+            assert!(client_2_entity(&stepper, targeted).is_none());
+            assert!(client_2_entity(&stepper, visible).is_none());
+            // client 1 was not affected
+            assert!(stepper
+                .client_app_1
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(targeted)
+                .is_some());
+        }
+
         #[test]
         fn test_entity_spawn_preexisting_target() {
             let mut stepper = BevyStepper::default();
@@ -994,6 +1900,76 @@ pub(crate) mod send {
             assert_eq!(stepper.client_app.world.entities().len(), 1);
         }
 
+        #[test]
+        fn test_entity_spawn_link_pre_placed() {
+            let mut stepper = BevyStepper::default();
+
+            // the entity is placed in the level on both peers
+            let network_id = NetworkId::from_name("level/door");
+            let client_entity = stepper
+                .client_app
+                .world
+                .spawn((network_id, Component2(0.0)))
+                .id();
+            stepper.frame_step();
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn((network_id, Component1(1.0), server::Replicate::default()))
+                .id();
+            // no pre-placed entity on the client for this id
+            let other_server_entity = stepper
+                .server_app
+                .world
+                .spawn((
+                    NetworkId::new(1),
+                    Component1(2.0),
+                    server::Replicate::default(),
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that the entity was linked to the pre-placed entity instead of spawning a new one
+            let remote_entity_map = &stepper
+                .client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map;
+            assert_eq!(
+                remote_entity_map.get_local(server_entity),
+                Some(&client_entity)
+            );
+            let other_client_entity = *remote_entity_map.get_local(other_server_entity).unwrap();
+            assert_ne!(other_client_entity, client_entity);
+            let client_ref = stepper.client_app.world.entity(client_entity);
+            assert!(client_ref.contains::<Replicated>());
+            assert_eq!(client_ref.get::<Component1>(), Some(&Component1(1.0)));
+            assert_eq!(client_ref.get::<Component2>(), Some(&Component2(0.0)));
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .get::<Component1>(other_client_entity),
+                Some(&Component1(2.0))
+            );
+            assert_eq!(stepper.client_app.world.entities().len(), 2);
+
+            // the updates are applied to the pre-placed entity
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert(Component1(3.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper.client_app.world.get::<Component1>(client_entity),
+                Some(&Component1(3.0))
+            );
+        }
+
         /// Check that if we change the replication target on an entity that already has one
         /// we spawn the entity for new clients
         #[test]
@@ -1699,19 +2675,17 @@ pub(crate) mod send {
             );
         }
 
+        /// Check that no updates are sent while the replication is paused, and that all the components
+        /// are sent again when it resumes
         #[test]
-        fn test_component_update_replicate_once() {
+        fn test_component_update_paused() {
             let mut stepper = BevyStepper::default();
 
             // spawn an entity on server
             let server_entity = stepper
                 .server_app
                 .world
-                .spawn((
-                    Replicate::default(),
-                    Component1(1.0),
-                    ReplicateOnceComponent::<Component1>::default(),
-                ))
+                .spawn((Replicate::default(), Component1(1.0), Component2(1.0)))
                 .id();
             stepper.frame_step();
             stepper.frame_step();
@@ -1723,7 +2697,24 @@ pub(crate) mod send {
                 .remote_entity_map
                 .get_local(server_entity)
                 .expect("entity was not replicated to client");
-            // check that the component was replicated
+
+            // pause the replication and update the component
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert((ReplicationPaused, Component1(2.0)));
+            stepper.frame_step();
+            stepper.frame_step();
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert(Component1(3.0));
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that the entity still exists but was not updated
             assert_eq!(
                 stepper
                     .client_app
@@ -1734,16 +2725,84 @@ pub(crate) mod send {
                 &Component1(1.0)
             );
 
-            // update component
+            // the client has a different value for a component that did not change on the server
+            stepper
+                .client_app
+                .world
+                .entity_mut(client_entity)
+                .insert(Component2(5.0));
+
+            // resume the replication
             stepper
                 .server_app
                 .world
                 .entity_mut(server_entity)
-                .insert(Component1(2.0));
+                .remove::<ReplicationPaused>();
             stepper.frame_step();
             stepper.frame_step();
 
-            // check that the component was not updated
+            // check that the current value of all the components was sent
+            let client_entity_ref = stepper.client_app.world.entity(client_entity);
+            assert_eq!(
+                client_entity_ref.get::<Component1>(),
+                Some(&Component1(3.0))
+            );
+            assert_eq!(
+                client_entity_ref.get::<Component2>(),
+                Some(&Component2(1.0))
+            );
+            assert!(!stepper
+                .server_app
+                .world
+                .entity(server_entity)
+                .contains::<ResumedReplication>());
+        }
+
+        /// Check that a forced update sends the current value of the component even if it didn't change
+        #[test]
+        fn test_component_force_update() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn((Replicate::default(), Component1(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = *stepper
+                .client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            // the client is desynced
+            stepper
+                .client_app
+                .world
+                .entity_mut(client_entity)
+                .insert(Component1(5.0));
+            let change_ticks = stepper
+                .server_app
+                .world
+                .entity(server_entity)
+                .get_change_ticks::<Component1>()
+                .unwrap();
+
+            // force the update
+            stepper
+                .server_app
+                .world
+                .resource_mut::<ConnectionManager>()
+                .force_update::<Component1>(server_entity, NetworkTarget::All);
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that the current value was sent, without modifying the component on the server
             assert_eq!(
                 stepper
                     .client_app
@@ -1753,17 +2812,31 @@ pub(crate) mod send {
                     .expect("component missing"),
                 &Component1(1.0)
             );
+            let new_change_ticks = stepper
+                .server_app
+                .world
+                .entity(server_entity)
+                .get_change_ticks::<Component1>()
+                .unwrap();
+            assert_eq!(
+                new_change_ticks.last_changed_tick(),
+                change_ticks.last_changed_tick()
+            );
         }
 
         #[test]
-        fn test_component_remove() {
+        fn test_component_update_replicate_once() {
             let mut stepper = BevyStepper::default();
 
             // spawn an entity on server
             let server_entity = stepper
                 .server_app
                 .world
-                .spawn((Replicate::default(), Component1(1.0)))
+                .spawn((
+                    Replicate::default(),
+                    Component1(1.0),
+                    ReplicateOnceComponent::<Component1>::default(),
+                ))
                 .id();
             stepper.frame_step();
             stepper.frame_step();
@@ -1775,6 +2848,7 @@ pub(crate) mod send {
                 .remote_entity_map
                 .get_local(server_entity)
                 .expect("entity was not replicated to client");
+            // check that the component was replicated
             assert_eq!(
                 stepper
                     .client_app
@@ -1785,31 +2859,478 @@ pub(crate) mod send {
                 &Component1(1.0)
             );
 
-            // remove component
+            // update component
             stepper
                 .server_app
                 .world
                 .entity_mut(server_entity)
-                .remove::<Component1>();
+                .insert(Component1(2.0));
             stepper.frame_step();
             stepper.frame_step();
 
-            // check that the component was replicated
-            assert!(stepper
-                .client_app
-                .world
-                .entity(client_entity)
-                .get::<Component1>()
-                .is_none());
+            // check that the component was not updated
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(1.0)
+            );
         }
 
         #[test]
-        fn test_replicating_add() {
+        fn test_component_update_replicate_if() {
             let mut stepper = BevyStepper::default();
+            // only replicate updates when the value is above a threshold
+            stepper
+                .server_app
+                .world
+                .resource_mut::<ComponentRegistry>()
+                .set_replicate_if::<Component1>(|component, _| component.0 > 5.0);
+
+            // the insert is replicated even if the predicate returns false
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn((Replicate::default(), Component1(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = *stepper
+                .client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(1.0)
+            );
+
+            // the update is rejected by the predicate
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert(Component1(2.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(1.0)
+            );
+
+            // the update is accepted by the predicate
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert(Component1(10.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(10.0)
+            );
+
+            // the first value rejected by the predicate is replicated, so that the client doesn't keep a stale value
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert(Component1(3.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(3.0)
+            );
+
+            // the following rejected values are not replicated
+            stepper.frame_step();
+            stepper.frame_step();
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert(Component1(4.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(3.0)
+            );
+        }
+
+        #[test]
+        fn test_component_update_replicate_if_component() {
+            let mut stepper = BevyStepper::default();
+            // only replicate the updates of this entity while the value is non-zero
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn((
+                    Replicate::default(),
+                    Component1(1.0),
+                    ReplicateIf::<Component1>::new(|component| component.0 > 0.0),
+                ))
+                .id();
+            // an entity without the predicate
+            let other_server_entity = stepper
+                .server_app
+                .world
+                .spawn((Replicate::default(), Component1(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_value = |stepper: &BevyStepper, server_entity: Entity| {
+                let client_entity = *stepper
+                    .client_app
+                    .world
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(server_entity)
+                    .expect("entity was not replicated to client");
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing")
+                    .clone()
+            };
+
+            // the first value rejected by the predicate of the entity is still replicated
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert(Component1(0.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(client_value(&stepper, server_entity), Component1(0.0));
+
+            // the following updates are rejected by the predicate of the entity
+            stepper.frame_step();
+            stepper.frame_step();
+            for entity in [server_entity, other_server_entity] {
+                stepper
+                    .server_app
+                    .world
+                    .entity_mut(entity)
+                    .insert(Component1(-1.0));
+            }
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(client_value(&stepper, server_entity), Component1(0.0));
+            assert_eq!(
+                client_value(&stepper, other_server_entity),
+                Component1(-1.0)
+            );
+
+            // the update is accepted by the predicate of the entity
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .insert(Component1(2.0));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(client_value(&stepper, server_entity), Component1(2.0));
+        }
+
+        #[test]
+        fn test_component_update_send_interval() {
+            let mut stepper = BevyStepper::default();
+            // send the updates of Component1 every 300ms instead of every frame
+            stepper
+                .server_app
+                .world
+                .resource_mut::<ComponentRegistry>()
+                .set_send_interval::<Component1>(Duration::from_millis(300));
+            stepper.client_app.init_resource::<Counter>();
+            stepper.client_app.add_systems(
+                Update,
+                |mut events: EventReader<ComponentUpdateEvent<Component1>>,
+                 mut counter: ResMut<Counter>| {
+                    counter.0 += events.read().count() as u32;
+                },
+            );
+
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn((Replicate::default(), Component1(0.0), Component2(0.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = *stepper
+                .client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            // Component2 is updated every frame, so the group gets acked between the updates of Component1
+            for i in 1..=15 {
+                stepper
+                    .server_app
+                    .world
+                    .entity_mut(server_entity)
+                    .insert((Component1(i as f32), Component2(i as f32)));
+                stepper.frame_step();
+            }
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component2>()
+                    .expect("component missing"),
+                &Component2(15.0)
+            );
+            let updates = stepper.client_app.world.resource::<Counter>().0;
+            assert!(updates <= 1, "{updates} updates were received");
+
+            // the last change is sent at the next interval
+            for _ in 0..30 {
+                stepper.frame_step();
+            }
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(15.0)
+            );
+        }
+
+        /// Count the updates of Component1 received by the client
+        fn count_component1_updates(
+            mut events: EventReader<ComponentUpdateEvent<Component1>>,
+            mut counter: ResMut<Counter>,
+        ) {
+            counter.0 += events.read().count() as u32;
+        }
+
+        /// Update Component1 on the server every frame, and return the number of updates received by the client
+        fn step_component1_updates(stepper: &mut BevyStepper, server_entity: Entity) -> u32 {
+            stepper.client_app.world.resource_mut::<Counter>().0 = 0;
+            for i in 1..=15 {
+                stepper
+                    .server_app
+                    .world
+                    .entity_mut(server_entity)
+                    .insert(Component1(i as f32));
+                stepper.frame_step();
+            }
+            stepper.frame_step();
+            stepper.client_app.world.resource::<Counter>().0
+        }
+
+        #[test]
+        fn test_group_send_interval() {
+            let mut stepper = BevyStepper::default();
+            stepper.client_app.init_resource::<Counter>();
+            stepper
+                .client_app
+                .add_systems(Update, count_component1_updates);
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn((
+                    Replicate {
+                        group: ReplicationGroup::default()
+                            .set_send_interval(Duration::from_millis(300)),
+                        ..default()
+                    },
+                    Component1(0.0),
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = *stepper
+                .client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            let updates = step_component1_updates(&mut stepper, server_entity);
+            assert!(updates <= 1, "{updates} updates were received");
+            // the client was told that the group is sent less often
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .resource::<client::ConnectionManager>()
+                    .sync_manager
+                    .group_send_intervals
+                    .get(&ReplicationGroupId(server_entity.to_bits())),
+                Some(&Duration::from_millis(300))
+            );
+
+            // the last change is sent at the next interval
+            for _ in 0..30 {
+                stepper.frame_step();
+            }
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(15.0)
+            );
+        }
+
+        #[test]
+        fn test_room_send_interval() {
+            let mut stepper = BevyStepper::default();
+            stepper.client_app.init_resource::<Counter>();
+            stepper
+                .client_app
+                .add_systems(Update, count_component1_updates);
+            let room_id = RoomId(1);
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn((
+                    Replicate {
+                        visibility: VisibilityMode::InterestManagement,
+                        ..default()
+                    },
+                    Component1(0.0),
+                ))
+                .id();
+            let mut room_manager = stepper.server_app.world.resource_mut::<RoomManager>();
+            room_manager.add_client(ClientId::Netcode(TEST_CLIENT_ID), room_id);
+            room_manager.add_entity(server_entity, room_id);
+            room_manager.set_send_interval(room_id, Some(Duration::from_millis(300)));
+            stepper.frame_step();
+            stepper.frame_step();
+            assert!(stepper
+                .client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .is_some());
+
+            let updates = step_component1_updates(&mut stepper, server_entity);
+            assert!(updates <= 1, "{updates} updates were received");
+
+            // without the interval, the updates are sent every frame again
+            stepper
+                .server_app
+                .world
+                .resource_mut::<RoomManager>()
+                .set_send_interval(room_id, None);
+            let updates = step_component1_updates(&mut stepper, server_entity);
+            assert!(updates >= 10, "{updates} updates were received");
+            assert!(stepper
+                .client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .sync_manager
+                .group_send_intervals
+                .is_empty());
+        }
+
+        #[test]
+        fn test_component_remove() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world
+                .spawn((Replicate::default(), Component1(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = *stepper
+                .client_app
+                .world
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .entity(client_entity)
+                    .get::<Component1>()
+                    .expect("component missing"),
+                &Component1(1.0)
+            );
+
+            // remove component
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .remove::<Component1>();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that the component was replicated
+            assert!(stepper
+                .client_app
+                .world
+                .entity(client_entity)
+                .get::<Component1>()
+                .is_none());
+        }
+
+        #[test]
+        fn test_replicating_add() {
+            let mut stepper = BevyStepper::default();
+
+            let server_entity = stepper.server_app.world.spawn(Replicate::default()).id();
+            stepper.frame_step();
 
-            let server_entity = stepper.server_app.world.spawn(Replicate::default()).id();
-            stepper.frame_step();
-
             // check that a DespawnTracker was added
             assert!(stepper
                 .server_app
@@ -1831,6 +3352,8 @@ pub(crate) mod send {
                     replication_group: ReplicationGroup::new_from_entity(),
                     visibility_mode: VisibilityMode::All,
                     replication_clients_cache: vec![],
+                    sync_target: SyncTarget::default(),
+                    despawn_reason: None,
                 }
             );
         }
@@ -1964,38 +3487,115 @@ pub(crate) mod send {
 
 pub(crate) mod commands {
     use crate::server::connection::ConnectionManager;
+    use crate::shared::replication::components::Replicating;
+    use crate::shared::replication::DespawnReason;
     use crate::shared::replication::ReplicationSend;
+    use bevy::ecs::entity::EntityHashSet;
+    use bevy::ecs::query::QueryFilter;
     use bevy::ecs::system::{Command, EntityCommands};
-    use bevy::prelude::{Entity, World};
+    use bevy::prelude::{Commands, DespawnRecursiveExt, Entity, With, World};
 
     fn despawn_without_replication(entity: Entity, world: &mut World) {
-        let mut sender = world.resource_mut::<ConnectionManager>();
         // remove the entity from the cache of entities that are being replicated
         // so that if it gets despawned, the despawn won't be replicated
-        sender.replicate_component_cache.remove(&entity);
+        if let Some(mut sender) = world.get_resource_mut::<ConnectionManager>() {
+            sender.replicate_component_cache.remove(&entity);
+        }
         world.despawn(entity);
     }
 
+    fn despawn_with_reason(reason: DespawnReason) -> impl FnOnce(Entity, &mut World) {
+        move |entity, world| {
+            if let Some(cache) = world
+                .get_resource_mut::<ConnectionManager>()
+                .and_then(|sender| {
+                    sender
+                        .into_inner()
+                        .replicate_component_cache
+                        .get_mut(&entity)
+                })
+            {
+                cache.despawn_reason = Some(reason);
+            }
+            world.despawn(entity);
+        }
+    }
+
     pub trait DespawnReplicationCommandExt {
         /// Despawn the entity and makes sure that the despawn won't be replicated.
         fn despawn_without_replication(&mut self);
+
+        /// Despawn the entity and replicate the despawn along with a reason code,
+        /// which is available in the remote's [`EntityDespawnEvent`](crate::prelude::client::EntityDespawnEvent).
+        ///
+        /// The reason can be any type that converts into a [`DespawnReason`], such as a game-defined enum
+        fn despawn_with_reason(&mut self, reason: impl Into<DespawnReason>);
     }
     impl DespawnReplicationCommandExt for EntityCommands<'_> {
         fn despawn_without_replication(&mut self) {
             self.add(despawn_without_replication);
         }
+
+        fn despawn_with_reason(&mut self, reason: impl Into<DespawnReason>) {
+            self.add(despawn_with_reason(reason.into()));
+        }
     }
 
-    #[cfg(test)]
+    fn reset_world<F: QueryFilter>(world: &mut World) {
+        let kept: EntityHashSet = world
+            .query_filtered::<Entity, (With<Replicating>, F)>()
+            .iter(world)
+            .collect();
+        let despawned: Vec<Entity> = world
+            .query_filtered::<Entity, With<Replicating>>()
+            .iter(world)
+            .filter(|entity| !kept.contains(entity))
+            .collect();
+        if let Some(mut sender) = world.get_resource_mut::<ConnectionManager>() {
+            sender.reset_world();
+        }
+        for entity in despawned {
+            // the entity could have been despawned already with its parent
+            if let Some(entity_mut) = world.get_entity_mut(entity) {
+                entity_mut.despawn_recursive();
+            }
+        }
+    }
+
+    pub trait ServerReplicationCommandExt {
+        /// Reset the replicated world for all the connected clients, for example to start a new round.
+        ///
+        /// In a single step:
+        /// - the replicated entities that don't match the filter `F` are despawned (along with their children)
+        /// - the replication state of every client is cleared, and nothing is replicated until the client
+        ///   receives the reset
+        /// - the clients despawn all the entities they received from the server, then receive the entities
+        ///   that were kept (and any entity spawned since the reset) as if they had just connected
+        ///
+        /// ```rust,ignore
+        /// commands.reset_world::<With<Player>>();
+        /// ```
+        fn reset_world<F: QueryFilter + 'static>(&mut self);
+    }
+
+    impl ServerReplicationCommandExt for Commands<'_, '_> {
+        fn reset_world<F: QueryFilter + 'static>(&mut self) {
+            self.add(reset_world::<F>);
+        }
+    }
+
+    #[cfg(all(test, feature = "client", feature = "server"))]
     mod tests {
+        use bevy::prelude::{default, EventReader, ResMut, Resource, Update};
         use bevy::utils::Duration;
 
         use crate::client::sync::SyncConfig;
         use crate::prelude::client::{InterpolationConfig, PredictionConfig};
-        use crate::prelude::server::Replicate;
-        use crate::prelude::{server, LinkConditionerConfig, SharedConfig, TickConfig};
+        use crate::prelude::server::{Replicate, VisibilityManager};
+        use crate::prelude::{client, server, LinkConditionerConfig, SharedConfig, TickConfig};
+        use crate::prelude::{ClientId, VisibilityMode};
         use crate::tests::protocol::*;
-        use crate::tests::stepper::{BevyStepper, Step};
+        use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
 
         use super::*;
 
@@ -2085,5 +3685,301 @@ pub(crate) mod commands {
                 .get_single(&stepper.client_app.world)
                 .is_ok());
         }
+
+        #[derive(Resource, Default)]
+        struct DespawnReasons(Vec<Option<DespawnReason>>);
+
+        #[test]
+        fn test_reset_world() {
+            let mut stepper = BevyStepper::default();
+            let kept = stepper
+                .server_app
+                .world
+                .spawn((Component1(1.0), Replicate::default()))
+                .id();
+            let removed = stepper
+                .server_app
+                .world
+                .spawn((Component2(1.0), Replicate::default()))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = |stepper: &BevyStepper, server_entity| {
+                stepper
+                    .client_app
+                    .world
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(server_entity)
+                    .copied()
+            };
+            let kept_before_reset = client_entity(&stepper, kept).unwrap();
+            let removed_before_reset = client_entity(&stepper, removed).unwrap();
+
+            reset_world::<bevy::prelude::With<Component1>>(&mut stepper.server_app.world);
+            // an update sent during the reset is included in the new world state
+            stepper
+                .server_app
+                .world
+                .get_mut::<Component1>(kept)
+                .unwrap()
+                .0 = 2.0;
+            for _ in 0..10 {
+                stepper.frame_step();
+            }
+
+            assert!(stepper.server_app.world.get_entity(kept).is_some());
+            assert!(stepper.server_app.world.get_entity(removed).is_none());
+            // the client despawned all the entities it had received
+            assert!(stepper
+                .client_app
+                .world
+                .get_entity(kept_before_reset)
+                .is_none());
+            assert!(stepper
+                .client_app
+                .world
+                .get_entity(removed_before_reset)
+                .is_none());
+            assert!(client_entity(&stepper, removed).is_none());
+            // and received the kept entity again
+            let kept_after_reset = client_entity(&stepper, kept).unwrap();
+            assert_eq!(
+                stepper.client_app.world.get::<Component1>(kept_after_reset),
+                Some(&Component1(2.0))
+            );
+            assert!(stepper
+                .server_app
+                .world
+                .resource::<server::ConnectionManager>()
+                .connection(ClientId::Netcode(TEST_CLIENT_ID))
+                .unwrap()
+                .replication_sender
+                .world_reset_message_id
+                .is_none());
+        }
+
+        /// With interest management, the entities that were visible to the client are sent again after the reset
+        #[test]
+        fn test_reset_world_interest_management() {
+            let mut stepper = BevyStepper::default();
+            let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+            let visible = stepper
+                .server_app
+                .world
+                .spawn((
+                    Component1(1.0),
+                    Replicate {
+                        visibility: VisibilityMode::InterestManagement,
+                        ..default()
+                    },
+                ))
+                .id();
+            let hidden = stepper
+                .server_app
+                .world
+                .spawn((
+                    Component1(2.0),
+                    Replicate {
+                        visibility: VisibilityMode::InterestManagement,
+                        ..default()
+                    },
+                ))
+                .id();
+            stepper
+                .server_app
+                .world
+                .resource_mut::<VisibilityManager>()
+                .gain_visibility(client_id, visible);
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = |stepper: &BevyStepper, server_entity| {
+                stepper
+                    .client_app
+                    .world
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(server_entity)
+                    .copied()
+            };
+            let visible_before_reset = client_entity(&stepper, visible).unwrap();
+            assert!(client_entity(&stepper, hidden).is_none());
+
+            reset_world::<bevy::prelude::With<Component1>>(&mut stepper.server_app.world);
+            for _ in 0..10 {
+                stepper.frame_step();
+            }
+
+            assert!(stepper
+                .client_app
+                .world
+                .get_entity(visible_before_reset)
+                .is_none());
+            let visible_after_reset = client_entity(&stepper, visible).unwrap();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .get::<Component1>(visible_after_reset),
+                Some(&Component1(1.0))
+            );
+            assert!(client_entity(&stepper, hidden).is_none());
+        }
+
+        fn collect_despawn_reasons(
+            mut reasons: ResMut<DespawnReasons>,
+            mut events: EventReader<client::EntityDespawnEvent>,
+        ) {
+            reasons.0.extend(events.read().map(|event| event.reason()));
+        }
+
+        #[test]
+        fn test_batched_despawn_with_reason() {
+            let mut stepper = BevyStepper::default();
+            stepper.client_app.init_resource::<DespawnReasons>();
+            stepper
+                .client_app
+                .add_systems(Update, collect_despawn_reasons);
+
+            let entities: Vec<Entity> = (0..50)
+                .map(|i| {
+                    stepper
+                        .server_app
+                        .world
+                        .spawn((Component1(i as f32), Replicate::default()))
+                        .id()
+                })
+                .collect();
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .query::<&Component1>()
+                    .iter(&stepper.client_app.world)
+                    .count(),
+                50
+            );
+
+            // despawn all the entities at once: the despawns are sent in a single batched message
+            for entity in entities {
+                despawn_with_reason(DespawnReason(7))(entity, &mut stepper.server_app.world);
+            }
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world
+                    .query::<&Component1>()
+                    .iter(&stepper.client_app.world)
+                    .count(),
+                0
+            );
+            let reasons = &stepper.client_app.world.resource::<DespawnReasons>().0;
+            assert_eq!(reasons.len(), 50);
+            assert!(reasons.iter().all(|r| *r == Some(DespawnReason(7))));
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum GameDespawnReason {
+            Killed,
+            OutOfRange,
+            RoundReset,
+        }
+
+        impl From<GameDespawnReason> for DespawnReason {
+            fn from(reason: GameDespawnReason) -> Self {
+                DespawnReason(reason as u16)
+            }
+        }
+
+        impl TryFrom<DespawnReason> for GameDespawnReason {
+            type Error = ();
+            fn try_from(reason: DespawnReason) -> Result<Self, ()> {
+                match reason.0 {
+                    0 => Ok(GameDespawnReason::Killed),
+                    1 => Ok(GameDespawnReason::OutOfRange),
+                    2 => Ok(GameDespawnReason::RoundReset),
+                    _ => Err(()),
+                }
+            }
+        }
+
+        #[derive(Resource, Default)]
+        struct GameDespawnReasons(Vec<(Entity, Option<GameDespawnReason>)>);
+
+        fn collect_game_despawn_reasons(
+            mut reasons: ResMut<GameDespawnReasons>,
+            mut events: EventReader<client::EntityDespawnEvent>,
+        ) {
+            reasons.0.extend(
+                events
+                    .read()
+                    .map(|event| (event.entity(), event.reason_as::<GameDespawnReason>())),
+            );
+        }
+
+        #[test]
+        fn test_despawn_with_game_reason() {
+            let mut stepper = BevyStepper::default();
+            stepper.client_app.init_resource::<GameDespawnReasons>();
+            stepper
+                .client_app
+                .add_systems(Update, collect_game_despawn_reasons);
+
+            let killed = stepper
+                .server_app
+                .world
+                .spawn((Component1(0.0), Replicate::default()))
+                .id();
+            let reset = stepper
+                .server_app
+                .world
+                .spawn((Component1(1.0), Replicate::default()))
+                .id();
+            let unknown = stepper
+                .server_app
+                .world
+                .spawn((Component1(2.0), Replicate::default()))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = |stepper: &BevyStepper, server_entity| {
+                *stepper
+                    .client_app
+                    .world
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(server_entity)
+                    .unwrap()
+            };
+            let client_killed = client_entity(&stepper, killed);
+            let client_reset = client_entity(&stepper, reset);
+            let client_unknown = client_entity(&stepper, unknown);
+
+            despawn_with_reason(GameDespawnReason::Killed.into())(
+                killed,
+                &mut stepper.server_app.world,
+            );
+            despawn_with_reason(GameDespawnReason::RoundReset.into())(
+                reset,
+                &mut stepper.server_app.world,
+            );
+            // a code that the client doesn't know about
+            despawn_with_reason(DespawnReason(100))(unknown, &mut stepper.server_app.world);
+            stepper.frame_step();
+            stepper.frame_step();
+
+            let reasons = &stepper.client_app.world.resource::<GameDespawnReasons>().0;
+            assert_eq!(reasons.len(), 3);
+            assert!(reasons.contains(&(client_killed, Some(GameDespawnReason::Killed))));
+            assert!(reasons.contains(&(client_reset, Some(GameDespawnReason::RoundReset))));
+            assert!(reasons.contains(&(client_unknown, None)));
+        }
     }
 }
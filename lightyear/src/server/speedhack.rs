@@ -0,0 +1,285 @@
+/*! Detect clients whose clock runs faster than the server's clock (speed hacking)
+
+Every input message sent by a client contains the client tick at which it was sent. An honest client
+advances its tick at the same rate as the server (the sync systems only speed up or slow down the
+client slightly for a short amount of time), so over a few seconds the number of ticks that the client
+advanced should match the wall-clock time that elapsed on the server.
+
+The server measures the tick progression of each client over windows of [`SpeedHackConfig::window`].
+If a client advanced more than [`SpeedHackConfig::tolerance`] times the expected number of ticks, a
+[`SpeedHackEvent`] is emitted so that the game can act on it (log it, kick the client, etc.).
+
+If [`SpeedHackConfig::clamp_inputs`] is enabled, the input messages for ticks that are further ahead
+than what the tolerance allows are dropped, so that a client cannot provide inputs at a faster rate than
+the server simulates them.
+
+Detection is disabled by default; enable it with [`ServerConfig::speed_hack`](crate::server::config::ServerConfig::speed_hack).
+*/
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap};
+use tracing::{debug, warn};
+
+use crate::prelude::{ClientId, Tick, TimeManager};
+use crate::server::config::ServerConfig;
+use crate::server::events::DisconnectEvent;
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+use crate::shared::time_manager::WrappedTime;
+
+/// Configuration of the speed hack detection
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct SpeedHackConfig {
+    /// Maximum ratio between the number of ticks that a client advanced and the number of ticks that
+    /// elapsed on the server during a window. For example 1.1 means that clients can run up to 10% faster.
+    pub tolerance: f32,
+    /// Duration of the windows over which the tick progression of each client is measured
+    pub window: Duration,
+    /// Number of ticks that a client can be ahead of the expected progression without being flagged,
+    /// to absorb the network jitter
+    pub tick_slack: u16,
+    /// If true, the input messages for ticks that are further ahead than what the tolerance allows are dropped
+    pub clamp_inputs: bool,
+}
+
+impl Default for SpeedHackConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 1.1,
+            window: Duration::from_secs(5),
+            tick_slack: 10,
+            clamp_inputs: false,
+        }
+    }
+}
+
+impl SpeedHackConfig {
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn with_tick_slack(mut self, tick_slack: u16) -> Self {
+        self.tick_slack = tick_slack;
+        self
+    }
+
+    /// Drop the input messages of clients that send inputs faster than what the tolerance allows
+    pub fn with_input_clamping(mut self) -> Self {
+        self.clamp_inputs = true;
+        self
+    }
+}
+
+/// Bevy [`Event`] emitted on the server when a client's tick progression was faster than the
+/// tolerance during a measurement window
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct SpeedHackEvent {
+    pub client_id: ClientId,
+    /// Ratio between the number of ticks that the client advanced and the number of ticks that
+    /// elapsed on the server during the window
+    pub speed_ratio: f32,
+}
+
+#[derive(Debug)]
+struct ClientClock {
+    window_start: WrappedTime,
+    window_start_tick: Tick,
+    /// Most recent tick for which we received an input message
+    latest_tick: Tick,
+    /// Most recent tick for which we accepted an input message
+    latest_accepted_tick: Tick,
+}
+
+impl ClientClock {
+    fn new(now: WrappedTime, tick: Tick) -> Self {
+        Self {
+            window_start: now,
+            window_start_tick: tick,
+            latest_tick: tick,
+            latest_accepted_tick: tick,
+        }
+    }
+
+    /// Number of ticks that elapsed on the server since the start of the window
+    fn expected_ticks(&self, now: WrappedTime, tick_duration: Duration) -> f32 {
+        let elapsed = (now - self.window_start).to_std().unwrap_or_default();
+        elapsed.as_secs_f32() / tick_duration.as_secs_f32()
+    }
+
+    /// Most recent tick for which we can accept an input message
+    fn max_allowed_tick(
+        &self,
+        now: WrappedTime,
+        tick_duration: Duration,
+        config: &SpeedHackConfig,
+    ) -> Tick {
+        let allowed_ticks = (self.expected_ticks(now, tick_duration) * config.tolerance) as u16;
+        self.window_start_tick + Tick(allowed_ticks.saturating_add(config.tick_slack))
+    }
+}
+
+/// Keeps track of the tick progression of each client
+#[derive(Resource, Debug)]
+pub(crate) struct ClientClocks {
+    config: SpeedHackConfig,
+    tick_duration: Duration,
+    clocks: HashMap<ClientId, ClientClock>,
+}
+
+impl ClientClocks {
+    pub(crate) fn new(config: SpeedHackConfig, tick_duration: Duration) -> Self {
+        Self {
+            config,
+            tick_duration,
+            clocks: HashMap::default(),
+        }
+    }
+
+    /// Record that we received an input message sent by the client at `tick`.
+    ///
+    /// Returns false if the message should be dropped because the client is sending inputs too fast
+    pub(crate) fn on_input_message(
+        &mut self,
+        client_id: ClientId,
+        tick: Tick,
+        now: WrappedTime,
+    ) -> bool {
+        let Some(clock) = self.clocks.get_mut(&client_id) else {
+            self.clocks.insert(client_id, ClientClock::new(now, tick));
+            return true;
+        };
+        if tick > clock.latest_tick {
+            clock.latest_tick = tick;
+        }
+        if self.config.clamp_inputs
+            && tick > clock.max_allowed_tick(now, self.tick_duration, &self.config)
+        {
+            debug!(
+                ?client_id,
+                ?tick,
+                "dropping input message from client that is too fast"
+            );
+            return false;
+        }
+        if tick > clock.latest_accepted_tick {
+            clock.latest_accepted_tick = tick;
+        }
+        true
+    }
+
+    /// Check the tick progression of the clients whose measurement window is over, and start a new window
+    pub(crate) fn update(&mut self, now: WrappedTime) -> Vec<SpeedHackEvent> {
+        let mut events = vec![];
+        for (client_id, clock) in self.clocks.iter_mut() {
+            if now < clock.window_start + self.config.window {
+                continue;
+            }
+            let expected_ticks = clock.expected_ticks(now, self.tick_duration);
+            let ticks = (clock.latest_tick - clock.window_start_tick) as f32
+                - self.config.tick_slack as f32;
+            let speed_ratio = ticks.max(0.0) / expected_ticks;
+            if speed_ratio > self.config.tolerance {
+                warn!(
+                    ?client_id,
+                    ?speed_ratio,
+                    "client is running faster than the server"
+                );
+                events.push(SpeedHackEvent {
+                    client_id: *client_id,
+                    speed_ratio,
+                });
+            }
+            clock.window_start = now;
+            // when clamping, the inputs that were dropped must still be compensated by the client
+            clock.window_start_tick = if self.config.clamp_inputs {
+                clock.latest_accepted_tick
+            } else {
+                clock.latest_tick
+            };
+        }
+        events
+    }
+
+    pub(crate) fn remove(&mut self, client_id: ClientId) {
+        self.clocks.remove(&client_id);
+    }
+}
+
+pub(crate) struct SpeedHackPlugin;
+
+impl Plugin for SpeedHackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpeedHackEvent>();
+        let config = app.world.resource::<ServerConfig>();
+        if let Some(speed_hack_config) = config.speed_hack {
+            let tick_duration = config.shared.tick.tick_duration;
+            app.insert_resource(ClientClocks::new(speed_hack_config, tick_duration));
+            app.add_systems(
+                NetworkScheduleConfig::get(app).receive,
+                detect_speed_hacks.after(InternalMainSet::<ServerMarker>::EmitEvents),
+            );
+        }
+    }
+}
+
+/// Emit a [`SpeedHackEvent`] for the clients that run faster than the server
+fn detect_speed_hacks(
+    time_manager: Res<TimeManager>,
+    mut client_clocks: ResMut<ClientClocks>,
+    mut disconnect_events: EventReader<DisconnectEvent>,
+    mut speed_hack_events: EventWriter<SpeedHackEvent>,
+) {
+    for event in disconnect_events.read() {
+        client_clocks.remove(event.client_id);
+    }
+    speed_hack_events.send_batch(client_clocks.update(time_manager.current_time()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_hack_detection() {
+        let tick_duration = Duration::from_millis(10);
+        let config = SpeedHackConfig::default()
+            .with_window(Duration::from_secs(1))
+            .with_input_clamping();
+        let mut clocks = ClientClocks::new(config, tick_duration);
+        let honest = ClientId::Netcode(1);
+        let cheater = ClientId::Netcode(2);
+        let mut now = WrappedTime::default();
+
+        let mut dropped = 0;
+        for i in 0..100 {
+            let honest_tick = Tick(i);
+            // the cheater advances 2 ticks for every server tick
+            let cheater_tick = Tick(2 * i);
+            assert!(clocks.on_input_message(honest, honest_tick, now));
+            if !clocks.on_input_message(cheater, cheater_tick, now) {
+                dropped += 1;
+            }
+            now += tick_duration;
+            if now < WrappedTime::default() + config.window {
+                assert!(clocks.update(now).is_empty());
+            }
+        }
+        // the inputs of the cheater that are too far ahead are dropped
+        assert!(dropped > 0);
+
+        let events = clocks.update(now);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].client_id, cheater);
+        assert!(events[0].speed_ratio > 1.8);
+
+        // a new window starts
+        assert!(clocks.update(now).is_empty());
+        clocks.remove(cheater);
+        assert!(clocks.on_input_message(cheater, Tick(300), now));
+    }
+}
@@ -0,0 +1,305 @@
+/*! Aggregate statistics about the server simulation, replicated to admin clients
+
+When [`ServerConfig::stats`](crate::server::config::ServerConfig::stats) is set, the server periodically
+computes a [`ServerStats`] resource containing:
+- the percentiles of the duration of the fixed-timestep ticks
+- the number of connected clients
+- the number of entities in each room
+- the bandwidth used by the server
+
+The resource is updated every [`ServerStatsConfig::update_interval`] and replicated on the
+[`ServerStatsChannel`] to the clients in [`ServerStatsConfig::target`], so that an admin dashboard can
+display it. The target can be changed at runtime (for example when an admin connects) with:
+```rust,ignore
+commands.replicate_resource::<ServerStats, ServerStatsChannel>(NetworkTarget::Only(vec![admin_id]));
+```
+*/
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::channel::builder::ServerStatsChannel;
+#[cfg(feature = "server")]
+use crate::connection::server::{NetServer, ServerConnections};
+use crate::prelude::{ChannelKind, NetworkTarget, TimeManager};
+#[cfg(feature = "server")]
+use crate::server::config::ServerConfig;
+#[cfg(feature = "server")]
+use crate::server::connection::ConnectionManager;
+use crate::server::visibility::room::RoomId;
+#[cfg(feature = "server")]
+use crate::server::visibility::room::RoomManager;
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::replication::resources::ReplicateResourceMetadata;
+use crate::shared::sets::{InternalReplicationSet, ServerMarker};
+use crate::shared::time_manager::WrappedTime;
+
+/// Configuration of the aggregate server statistics
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct ServerStatsConfig {
+    /// How often the [`ServerStats`] are computed and replicated
+    pub update_interval: Duration,
+    /// Clients that receive the [`ServerStats`]
+    pub target: NetworkTarget,
+}
+
+impl Default for ServerStatsConfig {
+    fn default() -> Self {
+        Self {
+            update_interval: Duration::from_secs(1),
+            target: NetworkTarget::None,
+        }
+    }
+}
+
+impl ServerStatsConfig {
+    pub fn with_update_interval(mut self, update_interval: Duration) -> Self {
+        self.update_interval = update_interval;
+        self
+    }
+
+    pub fn with_target(mut self, target: NetworkTarget) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+/// Percentiles of a set of durations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub struct DurationPercentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl DurationPercentiles {
+    /// Compute the percentiles of the samples, using the nearest-rank method
+    pub(crate) fn from_samples(samples: &mut [Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let percentile = |p: f32| {
+            let rank = (p * samples.len() as f32).ceil() as usize;
+            samples[rank.clamp(1, samples.len()) - 1]
+        };
+        Self {
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// Aggregate statistics about the server, computed over the last [`ServerStatsConfig::update_interval`]
+///
+/// On the server, this resource is only present if [`ServerConfig::stats`](crate::server::config::ServerConfig::stats) is set.
+/// On the client, it is inserted when the server replicates it.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ServerStats {
+    /// Duration of the server's fixed-timestep ticks
+    pub tick_duration: DurationPercentiles,
+    /// Number of ticks that were run during the interval
+    pub tick_count: usize,
+    pub connected_clients: usize,
+    /// Number of entities in each room
+    pub room_entities: HashMap<RoomId, usize>,
+    /// Bytes sent per second, summed over all the transports of the server
+    pub bytes_sent_per_second: f32,
+    /// Bytes received per second, summed over all the transports of the server
+    pub bytes_received_per_second: f32,
+}
+
+/// Samples accumulated during the current interval
+#[cfg(feature = "server")]
+#[derive(Resource, Debug)]
+struct ServerStatsState {
+    update_interval: Duration,
+    last_update: Option<WrappedTime>,
+    tick_start: Option<Instant>,
+    tick_samples: Vec<Duration>,
+    /// Total number of bytes sent and received at the last update
+    bytes_sent: usize,
+    bytes_received: usize,
+}
+
+#[cfg(feature = "server")]
+pub(crate) struct ServerStatsPlugin;
+
+#[cfg(feature = "server")]
+impl Plugin for ServerStatsPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    // the config is read in `finish` so that it can still be modified after the plugins are added
+    fn finish(&self, app: &mut App) {
+        let Some(config) = app.world.resource::<ServerConfig>().stats.clone() else {
+            return;
+        };
+        app.insert_resource(ServerStats::default());
+        app.insert_resource(ServerStatsState {
+            update_interval: config.update_interval,
+            last_update: None,
+            tick_start: None,
+            tick_samples: Vec::new(),
+            bytes_sent: 0,
+            bytes_received: 0,
+        });
+        app.insert_resource(ReplicateResourceMetadata::<ServerStats>::new(
+            config.target,
+            ChannelKind::of::<ServerStatsChannel>(),
+        ));
+        let schedules = NetworkScheduleConfig::get(app);
+        app.add_systems(FixedFirst, start_tick_measurement);
+        app.add_systems(FixedLast, end_tick_measurement);
+        app.add_systems(
+            schedules.send,
+            update_server_stats
+                .before(InternalReplicationSet::<ServerMarker>::BufferResourceUpdates),
+        );
+    }
+}
+
+#[cfg(feature = "server")]
+fn start_tick_measurement(mut state: ResMut<ServerStatsState>) {
+    state.tick_start = Some(Instant::now());
+}
+
+#[cfg(feature = "server")]
+fn end_tick_measurement(mut state: ResMut<ServerStatsState>) {
+    if let Some(tick_start) = state.tick_start.take() {
+        state.tick_samples.push(tick_start.elapsed());
+    }
+}
+
+/// Recompute the [`ServerStats`] once per update interval
+#[cfg(feature = "server")]
+fn update_server_stats(
+    time_manager: Res<TimeManager>,
+    mut state: ResMut<ServerStatsState>,
+    mut stats: ResMut<ServerStats>,
+    connection_manager: Option<Res<ConnectionManager>>,
+    room_manager: Option<Res<RoomManager>>,
+    netservers: Option<Res<ServerConnections>>,
+) {
+    let now = time_manager.current_time();
+    let Some(last_update) = state.last_update else {
+        state.last_update = Some(now);
+        return;
+    };
+    if now < last_update + state.update_interval {
+        return;
+    }
+    let elapsed = (now - last_update).to_std().unwrap_or_default();
+    state.last_update = Some(now);
+
+    let (bytes_sent, bytes_received) = netservers
+        .iter()
+        .flat_map(|netservers| netservers.servers.iter())
+        .filter_map(|netserver| netserver.io())
+        .fold((0, 0), |(sent, received), io| {
+            (
+                sent + io.stats().bytes_sent,
+                received + io.stats().bytes_received,
+            )
+        });
+    let per_second = |bytes: usize| bytes as f32 / elapsed.as_secs_f32().max(f32::EPSILON);
+    // the stats of the io are reset when the server restarts
+    let bytes_sent_per_second = per_second(bytes_sent.saturating_sub(state.bytes_sent));
+    let bytes_received_per_second = per_second(bytes_received.saturating_sub(state.bytes_received));
+    state.bytes_sent = bytes_sent;
+    state.bytes_received = bytes_received;
+
+    let tick_count = state.tick_samples.len();
+    let tick_duration = DurationPercentiles::from_samples(&mut state.tick_samples);
+    state.tick_samples.clear();
+
+    *stats = ServerStats {
+        tick_duration,
+        tick_count,
+        connected_clients: connection_manager
+            .map_or(0, |manager| manager.connected_clients().count()),
+        room_entities: room_manager.map_or_else(HashMap::default, |manager| {
+            manager
+                .rooms()
+                .map(|(room_id, room)| (*room_id, room.entities.len()))
+                .collect()
+        }),
+        bytes_sent_per_second,
+        bytes_received_per_second,
+    };
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::{LinkConditionerConfig, SharedConfig, TickConfig};
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    #[test]
+    fn test_duration_percentiles() {
+        let mut samples: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        assert_eq!(
+            DurationPercentiles::from_samples(&mut samples),
+            DurationPercentiles {
+                p50: Duration::from_millis(50),
+                p90: Duration::from_millis(90),
+                p99: Duration::from_millis(99),
+                max: Duration::from_millis(100),
+            }
+        );
+        assert_eq!(
+            DurationPercentiles::from_samples(&mut []),
+            DurationPercentiles::default()
+        );
+    }
+
+    #[test]
+    fn test_server_stats_replication() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConfig>()
+            .stats = Some(
+            ServerStatsConfig::default()
+                .with_update_interval(Duration::from_millis(100))
+                .with_target(NetworkTarget::All),
+        );
+        stepper.init();
+        for _ in 0..30 {
+            stepper.frame_step();
+        }
+
+        let stats = stepper.server_app.world.resource::<ServerStats>();
+        assert_eq!(stats.connected_clients, 1);
+        assert!(stats.tick_count > 0);
+        assert!(stats.bytes_sent_per_second > 0.0);
+        // the stats are replicated to the client
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .resource::<ServerStats>()
+                .connected_clients,
+            1
+        );
+    }
+}
@@ -23,8 +23,10 @@ fn my_system(
 */
 use crate::prelude::server::ConnectionManager;
 use crate::prelude::ClientId;
+use crate::server::clients::{ConnectedClient, VisibleEntities};
 use crate::server::networking::is_started;
 use crate::server::visibility::room::{RoomManager, RoomSystemSets};
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet, ServerMarker};
 use bevy::ecs::entity::EntityHashSet;
 use bevy::prelude::*;
@@ -102,6 +104,7 @@ pub(super) mod systems {
     use super::*;
     use crate::prelude::server::DisconnectEvent;
     use crate::prelude::VisibilityMode;
+    use crate::server::events::{EntityHiddenFromClient, EntityVisibleToClient};
     use crate::shared::replication::ReplicationSend;
     use bevy::prelude::DetectChanges;
 
@@ -184,10 +187,104 @@ pub(super) mod systems {
         }
     }
 
+    /// The spectators see the same entities as the client that they are spectating
+    pub(in crate::server::visibility) fn update_spectator_visibility(
+        mut sender: ResMut<ConnectionManager>,
+        mut query: Query<&mut ReplicateVisibility>,
+        mut client_query: Query<&mut VisibleEntities>,
+    ) {
+        // the world of the clients that stopped spectating was reset, so we can forget their visibility
+        // without sending despawns
+        let stopped_spectators = std::mem::take(&mut sender.stopped_spectators);
+        if !stopped_spectators.is_empty() {
+            for mut cache in query.iter_mut() {
+                for spectator in stopped_spectators.iter() {
+                    cache.clients_cache.remove(spectator);
+                }
+            }
+            for spectator in stopped_spectators.iter() {
+                if let Some(mut visible) = sender
+                    .client_entity(*spectator)
+                    .ok()
+                    .and_then(|entity| client_query.get_mut(entity).ok())
+                {
+                    visible.0.clear();
+                }
+            }
+        }
+        if sender.spectators.is_empty() {
+            return;
+        }
+        for mut cache in query.iter_mut() {
+            for (spectator, spectated) in sender.spectators.iter() {
+                let visible = matches!(
+                    cache.clients_cache.get(spectated),
+                    Some(ClientVisibility::Gained | ClientVisibility::Maintained)
+                );
+                let new_visibility = match (cache.clients_cache.get(spectator), visible) {
+                    (None, true) => Some(ClientVisibility::Gained),
+                    // the spectator was about to lose visibility
+                    (Some(ClientVisibility::Lost), true) => Some(ClientVisibility::Maintained),
+                    (Some(ClientVisibility::Maintained), false) => Some(ClientVisibility::Lost),
+                    (Some(ClientVisibility::Gained), false) => None,
+                    _ => continue,
+                };
+                trace!(
+                    ?spectator,
+                    ?spectated,
+                    ?new_visibility,
+                    "update spectator visibility"
+                );
+                match new_visibility {
+                    Some(visibility) => {
+                        cache.clients_cache.insert(*spectator, visibility);
+                    }
+                    None => {
+                        cache.clients_cache.remove(spectator);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Emit the visibility events for the visibility changes that were just replicated
+    pub(in crate::server::visibility) fn emit_visibility_events(
+        query: Query<(Entity, &ReplicateVisibility)>,
+        mut visible_events: EventWriter<EntityVisibleToClient>,
+        mut hidden_events: EventWriter<EntityHiddenFromClient>,
+    ) {
+        for (entity, replicate) in query.iter() {
+            for (client_id, visibility) in replicate.clients_cache.iter() {
+                match visibility {
+                    ClientVisibility::Gained => {
+                        visible_events.send(EntityVisibleToClient {
+                            entity,
+                            client_id: *client_id,
+                        });
+                    }
+                    ClientVisibility::Lost => {
+                        hidden_events.send(EntityHiddenFromClient {
+                            entity,
+                            client_id: *client_id,
+                        });
+                    }
+                    ClientVisibility::Maintained => {}
+                }
+            }
+        }
+    }
+
     /// After replication, update the Replication Cache:
     /// - Visibility Gained becomes Visibility Maintained
     /// - Visibility Lost gets removed from the cache
-    pub fn update_replicate_visibility(mut query: Query<(Entity, &mut ReplicateVisibility)>) {
+    ///
+    /// The [`VisibleEntities`] of the client entities are updated accordingly.
+    pub fn update_replicate_visibility(
+        mut query: Query<(Entity, &mut ReplicateVisibility)>,
+        mut client_query: Query<(&ConnectedClient, &mut VisibleEntities)>,
+    ) {
+        let mut gained: HashMap<ClientId, Vec<Entity>> = HashMap::default();
+        let mut lost: HashMap<ClientId, Vec<Entity>> = HashMap::default();
         for (entity, mut replicate) in query.iter_mut() {
             replicate
                 .clients_cache
@@ -197,16 +294,51 @@ pub(super) mod systems {
                             "Visibility for client {client_id:?} and entity {entity:?} goes from gained to maintained"
                         );
                         *visibility = ClientVisibility::Maintained;
+                        gained.entry(*client_id).or_default().push(entity);
                         true
                     }
                     ClientVisibility::Lost => {
                         trace!("remove client {client_id:?} and entity {entity:?} from visibility cache");
+                        lost.entry(*client_id).or_default().push(entity);
                         false
                     }
                     ClientVisibility::Maintained => true,
                 });
             // error!("replicate.clients_cache: {0:?}", replicate.clients_cache);
         }
+        if gained.is_empty() && lost.is_empty() {
+            return;
+        }
+        for (client, mut visible) in client_query.iter_mut() {
+            let entities = &mut visible.bypass_change_detection().0;
+            let mut changed = false;
+            for entity in gained.remove(&client.0).into_iter().flatten() {
+                changed |= entities.insert(entity);
+            }
+            for entity in lost.remove(&client.0).into_iter().flatten() {
+                changed |= entities.remove(&entity);
+            }
+            if changed {
+                visible.set_changed();
+            }
+        }
+    }
+
+    /// The entities that were despawned (or stopped using interest management) are not visible anymore.
+    ///
+    /// This runs every frame so that no removal is missed.
+    pub(in crate::server::visibility) fn remove_visible_entities(
+        query: Query<(), With<ReplicateVisibility>>,
+        mut removed: RemovedComponents<ReplicateVisibility>,
+        mut client_query: Query<&mut VisibleEntities>,
+    ) {
+        for entity in removed.read().filter(|entity| !query.contains(*entity)) {
+            for mut visible in client_query.iter_mut() {
+                if visible.contains(&entity) {
+                    visible.0.remove(&entity);
+                }
+            }
+        }
     }
 
     /// Whenever the visibility of an entity changes, update the replication metadata cache
@@ -267,11 +399,12 @@ pub(crate) struct VisibilityPlugin;
 
 impl Plugin for VisibilityPlugin {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         // RESOURCES
         app.init_resource::<VisibilityManager>();
         // SETS
         app.configure_sets(
-            PostUpdate,
+            schedules.send,
             (
                 (
                     // update replication caches must happen before replication, but after we add ReplicateVisibility
@@ -297,14 +430,27 @@ impl Plugin for VisibilityPlugin {
         //     systems::handle_client_disconnect.after(InternalMainSet::<ServerMarker>::EmitEvents),
         // );
         app.add_systems(
-            PostUpdate,
+            schedules.send,
             (
                 systems::add_replicate_visibility
                     .in_set(InternalReplicationSet::<ServerMarker>::BeforeBuffer),
-                systems::update_visibility_from_events.in_set(VisibilitySet::UpdateVisibility),
+                (
+                    systems::update_visibility_from_events,
+                    systems::update_spectator_visibility,
+                )
+                    .chain()
+                    .in_set(VisibilitySet::UpdateVisibility),
                 systems::update_replication_cache
                     .in_set(InternalReplicationSet::<ServerMarker>::AfterBuffer),
-                systems::update_replicate_visibility.in_set(VisibilitySet::VisibilityCleanup),
+                (
+                    systems::emit_visibility_events,
+                    systems::update_replicate_visibility,
+                )
+                    .chain()
+                    .in_set(VisibilitySet::VisibilityCleanup),
+                systems::remove_visible_entities
+                    .after(InternalMainSet::<ServerMarker>::Send)
+                    .run_if(is_started),
             ),
         );
     }
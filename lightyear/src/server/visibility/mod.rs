@@ -1,3 +1,10 @@
+#[cfg(feature = "server")]
 pub mod immediate;
 
 pub mod room;
+
+#[cfg(feature = "server")]
+pub mod spatial;
+
+#[cfg(feature = "server")]
+pub mod streaming;
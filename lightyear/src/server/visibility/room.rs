@@ -44,23 +44,29 @@ use bevy::prelude::{
     Query, RemovedComponents, Res, ResMut, Resource, SystemSet,
 };
 use bevy::reflect::Reflect;
-use bevy::utils::{HashMap, HashSet};
+use bevy::utils::{Duration, HashMap, HashSet};
 use bitcode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use tracing::{error, info, trace};
 
 use crate::connection::id::ClientId;
+#[cfg(feature = "server")]
 use crate::server::connection::ConnectionManager;
 
+#[cfg(feature = "server")]
 use crate::server::networking::is_started;
+#[cfg(feature = "server")]
 use crate::server::visibility::immediate::{VisibilityManager, VisibilitySet};
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::replication::components::DespawnTracker;
 use crate::shared::replication::ReplicationSend;
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet, ServerMarker};
 use crate::shared::time_manager::is_server_ready_to_send;
 use crate::utils::wrapping_id::wrapping_id;
 
+#[cfg(feature = "server")]
 type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
+#[cfg(feature = "server")]
 type EntityHashSet<K> = hashbrown::HashSet<K, EntityHash>;
 
 /// Id for a [`Room`], which is used to perform interest management.
@@ -98,6 +104,7 @@ impl From<ClientId> for RoomId {
 /// leaves then re-joins a room within the same send_interval period, we don't need to send any update)
 ///
 /// This will be cleared every time the Server sends updates to the Client (every send_interval)
+#[cfg(feature = "server")]
 #[derive(Resource, Debug, Default)]
 struct RoomEvents {
     client_enter_room: HashMap<ClientId, HashSet<RoomId>>,
@@ -106,12 +113,14 @@ struct RoomEvents {
     entity_leave_room: EntityHashMap<Entity, HashSet<RoomId>>,
 }
 
+#[cfg(feature = "server")]
 #[derive(Resource, Debug, Default)]
 struct VisibilityEvents {
     gained: HashMap<ClientId, Entity>,
     lost: HashMap<ClientId, Entity>,
 }
 
+#[cfg(feature = "server")]
 #[derive(Default, Debug)]
 struct RoomData {
     /// List of rooms that a client is in
@@ -129,15 +138,19 @@ struct RoomData {
 ///
 /// Entities and clients can belong to multiple rooms, they just need to both be present in one room
 /// for the entity to be replicated to the client.
+#[cfg(feature = "server")]
 #[derive(Debug, Default)]
 pub struct Room {
     /// list of clients that are in the room
     pub clients: HashSet<ClientId>,
     /// list of entities that are in the room
     pub entities: EntityHashSet<Entity>,
+    /// If set, the updates of the entities in the room are sent at most once per interval
+    pub send_interval: Option<Duration>,
 }
 
 /// Manager responsible for handling rooms
+#[cfg(feature = "server")]
 #[derive(Default, Resource)]
 pub struct RoomManager {
     events: RoomEvents,
@@ -145,10 +158,12 @@ pub struct RoomManager {
 }
 
 /// Plugin used to handle interest managements via [`Room`]s
+#[cfg(feature = "server")]
 #[derive(Default)]
 pub struct RoomPlugin;
 
 /// System sets related to Rooms
+#[cfg(feature = "server")]
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub enum RoomSystemSets {
     /// Use all the room events that happened, and use those to update
@@ -159,13 +174,15 @@ pub enum RoomSystemSets {
     RoomBookkeeping,
 }
 
+#[cfg(feature = "server")]
 impl Plugin for RoomPlugin {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         // RESOURCES
         app.init_resource::<RoomManager>();
         // SETS
         app.configure_sets(
-            PostUpdate,
+            schedules.send,
             (
                 (
                     // the room events must be processed before the visibility events
@@ -185,11 +202,11 @@ impl Plugin for RoomPlugin {
         );
         // SYSTEMS
         app.add_systems(
-            PreUpdate,
+            schedules.receive,
             systems::handle_client_disconnect.after(InternalMainSet::<ServerMarker>::EmitEvents),
         );
         app.add_systems(
-            PostUpdate,
+            schedules.send,
             (
                 systems::buffer_room_visibility_events
                     .in_set(RoomSystemSets::UpdateReplicationCaches),
@@ -199,6 +216,7 @@ impl Plugin for RoomPlugin {
     }
 }
 
+#[cfg(feature = "server")]
 impl RoomManager {
     /// Remove the client from all the rooms it was in
     fn client_disconnect(&mut self, client_id: ClientId) {
@@ -248,11 +266,40 @@ impl RoomManager {
         self.has_entity_internal(room_id, entity)
     }
 
+    /// Send the updates of the entities in the room at most once per `send_interval`
+    /// (or every [`server_send_interval`](crate::prelude::SharedConfig::server_send_interval) if `None`).
+    ///
+    /// This can be used to replicate the rooms that matter less at a lower rate. An entity that is in several rooms
+    /// uses the shortest interval (the rooms without an interval are sent at the full rate); an interval set on its [`ReplicationGroup`](crate::prelude::ReplicationGroup)
+    /// takes precedence.
+    pub fn set_send_interval(&mut self, room_id: RoomId, send_interval: Option<Duration>) {
+        self.data.rooms.entry(room_id).or_default().send_interval = send_interval;
+    }
+
+    /// The send interval of the updates of the entity, which is the shortest interval of its rooms.
+    ///
+    /// Returns None if one of the rooms of the entity doesn't have a send interval.
+    pub(crate) fn entity_send_interval(&self, entity: Entity) -> Option<Duration> {
+        self.data
+            .entity_to_rooms
+            .get(&entity)?
+            .iter()
+            .map(|room_id| self.data.rooms.get(room_id)?.send_interval)
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min()
+    }
+
     /// Get a room by its [`RoomId`]
     pub fn get_room(&self, room_id: RoomId) -> Option<&Room> {
         self.data.rooms.get(&room_id)
     }
 
+    /// Iterate over all the rooms
+    pub fn rooms(&self) -> impl Iterator<Item = (&RoomId, &Room)> {
+        self.data.rooms.iter()
+    }
+
     /// Get a room by its [`RoomId`]
     ///
     /// Panics if the room does not exist.
@@ -336,6 +383,7 @@ impl RoomManager {
     }
 }
 
+#[cfg(feature = "server")]
 impl RoomEvents {
     fn is_empty(&self) -> bool {
         self.client_enter_room.is_empty()
@@ -427,6 +475,7 @@ impl RoomEvents {
     }
 }
 
+#[cfg(feature = "server")]
 pub(super) mod systems {
     use super::*;
     use crate::server::events::DisconnectEvent;
@@ -514,7 +563,7 @@ pub(super) mod systems {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use bevy::ecs::system::RunSystemOnce;
     use bevy::prelude::Events;
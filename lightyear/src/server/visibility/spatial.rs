@@ -0,0 +1,522 @@
+/*! Grid-based visibility module, where the entities are visible to the clients that are close to them
+
+# Spatial interest management
+
+Rooms need to be updated manually, which is cumbersome for open worlds where the entities move around.
+The [`SpatialInterestPlugin`] buckets the entities that use
+[`VisibilityMode::InterestManagement`](crate::prelude::VisibilityMode::InterestManagement) into a grid
+of cells of size [`SpatialInterestConfig::cell_size`], using their position (their [`Transform`] by default).
+
+Each client gets a [`ClientView`], a position and a radius, by inserting the component on the entity
+that the server spawned for the client. The entities that are within the radius of the view are visible to the client,
+and the others are not. Only the cells that overlap with the view are searched, so the cost doesn't depend on the
+total number of entities.
+
+```rust,ignore
+app.add_plugins(SpatialInterestPlugin::<Transform>::new(SpatialInterestConfig { cell_size: 50.0 }));
+
+// the view of each client follows its player
+fn update_views(
+    players: Query<(&Transform, &PlayerId)>,
+    connection_manager: Res<ConnectionManager>,
+    mut views: Query<&mut ClientView>,
+) {
+    for (transform, player) in players.iter() {
+        let Ok(client_entity) = connection_manager.client_entity(player.0) else { continue };
+        if let Ok(mut view) = views.get_mut(client_entity) {
+            view.position = transform.translation;
+        }
+    }
+}
+```
+
+A client can see around several positions (a deployed camera, squad members...): each entity with an
+[`InterestAnchor`] for the client adds an area around the position of that entity, and the client sees the
+entities that are in the union of these areas and of its [`ClientView`].
+
+```rust,ignore
+commands.spawn((Camera, Transform::from_translation(position), InterestAnchor::new(client_id, 30.0)));
+```
+
+## Implementation
+
+The visibility is updated with the [`VisibilityManager`] every send_interval, so it should not be combined
+with rooms or with manual visibility updates for the same entities.
+A client without a [`ClientView`] or an [`InterestAnchor`] doesn't see any of the entities managed by the grid.
+*/
+use bevy::ecs::entity::{EntityHashMap, EntityHashSet};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::connection::id::ClientId;
+use crate::server::clients::ConnectedClient;
+use crate::server::networking::is_started;
+use crate::server::visibility::immediate::{ReplicateVisibility, VisibilityManager, VisibilitySet};
+use crate::server::visibility::streaming::StreamedChunk;
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::sets::{InternalMainSet, InternalReplicationSet, ServerMarker};
+
+/// Component that gives the position of an entity in the spatial grid
+pub trait SpatialPosition: Component {
+    fn spatial_position(&self) -> Vec3;
+}
+
+impl SpatialPosition for Transform {
+    fn spatial_position(&self) -> Vec3 {
+        self.translation
+    }
+}
+
+impl SpatialPosition for GlobalTransform {
+    fn spatial_position(&self) -> Vec3 {
+        self.translation()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpatialInterestConfig {
+    /// Size of the cells of the grid.
+    ///
+    /// It should be in the same order of magnitude as the radius of the client views.
+    pub cell_size: f32,
+}
+
+impl Default for SpatialInterestConfig {
+    fn default() -> Self {
+        Self { cell_size: 100.0 }
+    }
+}
+
+/// The area that a client can see. Insert it on the entity that the server spawned for the client.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct ClientView {
+    pub position: Vec3,
+    pub radius: f32,
+}
+
+impl ClientView {
+    pub fn new(position: Vec3, radius: f32) -> Self {
+        Self { position, radius }
+    }
+}
+
+/// An additional area seen by a client, around the position of the entity that has this component
+/// (for example a squad member or a deployed camera).
+///
+/// The entity needs the position component of the [`SpatialInterestPlugin`]; it doesn't need to be replicated.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct InterestAnchor {
+    /// The client that sees around this entity
+    pub client_id: ClientId,
+    pub radius: f32,
+}
+
+impl InterestAnchor {
+    pub fn new(client_id: ClientId, radius: f32) -> Self {
+        Self { client_id, radius }
+    }
+}
+
+/// The entities bucketed by the cell of the grid that contains them, and the entities visible to each client
+#[derive(Resource, Debug)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec3, EntityHashSet>,
+    positions: EntityHashMap<(IVec3, Vec3)>,
+    visible: HashMap<ClientId, EntityHashSet>,
+}
+
+impl SpatialGrid {
+    pub(super) fn new(config: SpatialInterestConfig) -> Self {
+        Self {
+            cell_size: config.cell_size,
+            cells: HashMap::default(),
+            positions: EntityHashMap::default(),
+            visible: HashMap::default(),
+        }
+    }
+
+    fn cell(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+
+    pub(super) fn insert(&mut self, entity: Entity, position: Vec3) {
+        let cell = self.cell(position);
+        if let Some((previous_cell, _)) = self.positions.insert(entity, (cell, position)) {
+            if previous_cell == cell {
+                return;
+            }
+            self.remove_from_cell(entity, previous_cell);
+        }
+        self.cells.entry(cell).or_default().insert(entity);
+    }
+
+    pub(super) fn remove(&mut self, entity: Entity) {
+        if let Some((cell, _)) = self.positions.remove(&entity) {
+            self.remove_from_cell(entity, cell);
+        }
+        for visible in self.visible.values_mut() {
+            visible.remove(&entity);
+        }
+    }
+
+    fn remove_from_cell(&mut self, entity: Entity, cell: IVec3) {
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.remove(&entity);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// The last position of the entity in the grid
+    pub(super) fn position(&self, entity: Entity) -> Option<Vec3> {
+        self.positions.get(&entity).map(|(_, position)| *position)
+    }
+
+    /// The entities of the grid that are within the view
+    pub fn entities_in_view(&self, view: &ClientView) -> impl Iterator<Item = Entity> + '_ {
+        let min = self.cell(view.position - Vec3::splat(view.radius));
+        let max = self.cell(view.position + Vec3::splat(view.radius));
+        let radius_squared = view.radius * view.radius;
+        let position = view.position;
+        (min.x..=max.x)
+            .flat_map(move |x| {
+                (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z)))
+            })
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(move |entity| {
+                self.positions[entity].1.distance_squared(position) <= radius_squared
+            })
+    }
+}
+
+/// Plugin that handles the visibility of the entities with a spatial grid.
+///
+/// `P` is the component that contains the position of the entities.
+pub struct SpatialInterestPlugin<P: SpatialPosition = Transform> {
+    config: SpatialInterestConfig,
+    _marker: std::marker::PhantomData<P>,
+}
+
+impl<P: SpatialPosition> SpatialInterestPlugin<P> {
+    pub fn new(config: SpatialInterestConfig) -> Self {
+        Self {
+            config,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: SpatialPosition> Default for SpatialInterestPlugin<P> {
+    fn default() -> Self {
+        Self::new(SpatialInterestConfig::default())
+    }
+}
+
+/// System sets related to the spatial grid
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum SpatialInterestSet {
+    /// Update the cells of the entities that moved
+    UpdateGrid,
+    /// Compute the entities visible to each client
+    UpdateVisibility,
+}
+
+impl<P: SpatialPosition> Plugin for SpatialInterestPlugin<P> {
+    fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
+        // RESOURCES
+        app.register_type::<ClientView>()
+            .register_type::<InterestAnchor>();
+        app.insert_resource(SpatialGrid::new(self.config));
+        // SETS
+        app.configure_sets(
+            schedules.send,
+            (
+                // the grid is updated every frame, so that removals are not missed
+                (
+                    InternalReplicationSet::<ServerMarker>::BeforeBuffer,
+                    SpatialInterestSet::UpdateGrid,
+                    SpatialInterestSet::UpdateVisibility,
+                    VisibilitySet::UpdateVisibility,
+                )
+                    .run_if(is_started)
+                    .chain(),
+                SpatialInterestSet::UpdateVisibility.in_set(InternalMainSet::<ServerMarker>::Send),
+            ),
+        );
+        // SYSTEMS
+        app.add_systems(
+            schedules.send,
+            (
+                systems::update_grid::<P>.in_set(SpatialInterestSet::UpdateGrid),
+                systems::update_visibility::<P>.in_set(SpatialInterestSet::UpdateVisibility),
+            ),
+        );
+    }
+}
+
+pub(super) mod systems {
+    use super::*;
+
+    /// Move the entities to the cell of their current position
+    pub(super) fn update_grid<P: SpatialPosition>(
+        mut grid: ResMut<SpatialGrid>,
+        query: Query<
+            (Entity, &P),
+            (
+                With<ReplicateVisibility>,
+                // the chunks are handled by the WorldStreamingPlugin
+                Without<StreamedChunk>,
+                Or<(Changed<P>, Added<ReplicateVisibility>)>,
+            ),
+        >,
+        mut removed_positions: RemovedComponents<P>,
+        mut removed_visibility: RemovedComponents<ReplicateVisibility>,
+    ) {
+        for entity in removed_positions.read().chain(removed_visibility.read()) {
+            grid.remove(entity);
+        }
+        for (entity, position) in query.iter() {
+            grid.insert(entity, position.spatial_position());
+        }
+    }
+
+    /// Update the visibility of the entities of the grid for each client
+    pub(super) fn update_visibility<P: SpatialPosition>(
+        mut grid: ResMut<SpatialGrid>,
+        mut visibility_manager: ResMut<VisibilityManager>,
+        views: Query<(&ConnectedClient, Option<&ClientView>)>,
+        anchors: Query<(&InterestAnchor, &P)>,
+    ) {
+        let grid = grid.as_mut();
+        let mut visible = std::mem::take(&mut grid.visible);
+        // forget the clients that disconnected
+        visible.retain(|client_id, _| views.iter().any(|(client, _)| client.0 == *client_id));
+        let mut anchor_views: HashMap<ClientId, Vec<ClientView>> = HashMap::default();
+        for (anchor, position) in anchors.iter() {
+            anchor_views
+                .entry(anchor.client_id)
+                .or_default()
+                .push(ClientView::new(position.spatial_position(), anchor.radius));
+        }
+        for (client, view) in views.iter() {
+            // the client sees the union of its view and of the areas of its anchors
+            let new_visible: EntityHashSet = view
+                .into_iter()
+                .chain(anchor_views.get(&client.0).into_iter().flatten())
+                .flat_map(|view| grid.entities_in_view(view))
+                .collect();
+            let previous_visible = visible.entry(client.0).or_default();
+            for entity in previous_visible.difference(&new_visible) {
+                visibility_manager.lose_visibility(client.0, *entity);
+            }
+            for entity in new_visible.difference(previous_visible) {
+                visibility_manager.gain_visibility(client.0, *entity);
+            }
+            *previous_visible = new_visible;
+        }
+        grid.visible = visible;
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::utils::Duration;
+
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::server::{ConnectionManager, Replicate};
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+    use super::*;
+
+    fn client_has_entity(stepper: &mut BevyStepper) -> bool {
+        stepper
+            .client_app
+            .world
+            .query::<&Component1>()
+            .iter(&stepper.client_app.world)
+            .next()
+            .is_some()
+    }
+
+    #[test]
+    fn test_spatial_interest() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_plugins(SpatialInterestPlugin::<Transform>::new(
+                SpatialInterestConfig { cell_size: 10.0 },
+            ));
+        stepper.init();
+        let client_entity = stepper
+            .server_app
+            .world
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+        stepper
+            .server_app
+            .world
+            .entity_mut(client_entity)
+            .insert(ClientView::new(Vec3::ZERO, 15.0));
+        let entity = stepper
+            .server_app
+            .world
+            .spawn((
+                Component1(1.0),
+                Transform::from_xyz(100.0, 0.0, 0.0),
+                Replicate {
+                    visibility: VisibilityMode::InterestManagement,
+                    ..default()
+                },
+            ))
+            .id();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        // the entity is outside of the view of the client
+        assert!(!client_has_entity(&mut stepper));
+
+        // the entity moves in a cell next to the client's cell, within the radius
+        stepper
+            .server_app
+            .world
+            .entity_mut(entity)
+            .insert(Transform::from_xyz(-12.0, 0.0, 0.0));
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(client_has_entity(&mut stepper));
+
+        // the entity leaves the view again
+        stepper
+            .server_app
+            .world
+            .entity_mut(entity)
+            .insert(Transform::from_xyz(0.0, 20.0, 0.0));
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(!client_has_entity(&mut stepper));
+    }
+
+    #[test]
+    fn test_interest_anchors() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_plugins(SpatialInterestPlugin::<Transform>::new(
+                SpatialInterestConfig { cell_size: 10.0 },
+            ));
+        stepper.init();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let client_entity = stepper
+            .server_app
+            .world
+            .resource::<ConnectionManager>()
+            .client_entity(client_id)
+            .unwrap();
+        stepper
+            .server_app
+            .world
+            .entity_mut(client_entity)
+            .insert(ClientView::new(Vec3::ZERO, 15.0));
+        stepper.server_app.world.spawn((
+            Component1(1.0),
+            Transform::from_xyz(100.0, 0.0, 0.0),
+            Replicate {
+                visibility: VisibilityMode::InterestManagement,
+                ..default()
+            },
+        ));
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(!client_has_entity(&mut stepper));
+
+        // an anchor of the client is close to the entity
+        let anchor = stepper
+            .server_app
+            .world
+            .spawn((
+                Transform::from_xyz(95.0, 0.0, 0.0),
+                InterestAnchor::new(client_id, 10.0),
+            ))
+            .id();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(client_has_entity(&mut stepper));
+
+        // the entity is not visible anymore once the anchor is removed
+        stepper.server_app.world.despawn(anchor);
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(!client_has_entity(&mut stepper));
+    }
+
+    #[test]
+    fn test_entities_in_view() {
+        let mut grid = SpatialGrid::new(SpatialInterestConfig { cell_size: 10.0 });
+        let inside = Entity::from_raw(1);
+        let outside = Entity::from_raw(2);
+        // in the same cell as the view, but outside of the radius
+        let corner = Entity::from_raw(3);
+        grid.insert(inside, Vec3::new(-5.0, 5.0, 0.0));
+        grid.insert(outside, Vec3::new(30.0, 0.0, 0.0));
+        grid.insert(corner, Vec3::new(9.0, 9.0, 0.0));
+        let view = ClientView::new(Vec3::new(1.0, 1.0, 0.0), 8.0);
+        assert_eq!(
+            grid.entities_in_view(&view).collect::<Vec<_>>(),
+            vec![inside]
+        );
+
+        // moving an entity updates its cell
+        grid.insert(outside, Vec3::new(2.0, 1.0, 0.0));
+        let mut visible = grid.entities_in_view(&view).collect::<Vec<_>>();
+        visible.sort();
+        assert_eq!(visible, vec![inside, outside]);
+        grid.remove(inside);
+        assert_eq!(
+            grid.entities_in_view(&view).collect::<Vec<_>>(),
+            vec![outside]
+        );
+    }
+}
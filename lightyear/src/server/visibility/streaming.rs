@@ -0,0 +1,481 @@
+/*! Stream the chunks of a tile or voxel world to the clients, starting with the closest ones
+
+# World streaming
+
+Sending all the chunks that enter the view of a client at once creates a burst of bandwidth, and the client
+might receive the far away chunks before the ones it is standing on.
+The [`WorldStreamingPlugin`] gives a client visibility on the chunks (the entities with a [`StreamedChunk`])
+that are within its [`ClientView`] or the area of one of its [`InterestAnchor`]s, but:
+- the chunks are sent in order of distance from the view and anchors of the client
+- at most [`WorldStreamingConfig::max_in_flight`] chunks are in flight for each client: a new chunk is sent
+  only once the client has acknowledged the spawn of a previous one
+- a chunk that leaves the area of the client is cancelled: it is not sent if it was still waiting, and it is
+  despawned on the client otherwise
+
+The chunks need to use [`VisibilityMode::InterestManagement`](crate::prelude::VisibilityMode::InterestManagement)
+and to have a position component (their [`Transform`] by default).
+
+```rust,ignore
+app.add_plugins(WorldStreamingPlugin::<Transform>::new(WorldStreamingConfig {
+    cell_size: 64.0,
+    max_in_flight: 8,
+}));
+
+commands.spawn((
+    chunk,
+    StreamedChunk,
+    Transform::from_translation(chunk_position),
+    Replicate {
+        visibility: VisibilityMode::InterestManagement,
+        ..default()
+    },
+));
+```
+
+The chunks are ignored by the [`SpatialInterestPlugin`](super::spatial::SpatialInterestPlugin), so both plugins
+can be used at the same time: one for the chunks and one for the entities that move around.
+
+## Implementation
+
+A chunk is considered delivered when a message of the entity actions of its [`ReplicationGroup`] is acknowledged,
+so the chunks should use their own replication group (the default).
+*/
+use bevy::ecs::entity::{EntityHashMap, EntityHashSet};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::connection::id::ClientId;
+use crate::server::clients::ConnectedClient;
+use crate::server::connection::ConnectionManager;
+use crate::server::networking::is_started;
+use crate::server::visibility::immediate::{ReplicateVisibility, VisibilityManager, VisibilitySet};
+use crate::server::visibility::spatial::{
+    ClientView, InterestAnchor, SpatialGrid, SpatialInterestConfig, SpatialPosition,
+};
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::replication::components::{ReplicationGroup, ReplicationGroupId};
+use crate::shared::sets::{InternalMainSet, InternalReplicationSet, ServerMarker};
+
+/// Marker component for the chunks that are streamed by the [`WorldStreamingPlugin`]
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub struct StreamedChunk;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldStreamingConfig {
+    /// Size of the cells of the grid used to find the chunks close to the clients.
+    ///
+    /// It should be in the same order of magnitude as the radius of the client views.
+    pub cell_size: f32,
+    /// Maximum number of chunks that were sent to a client but not acknowledged yet
+    pub max_in_flight: usize,
+}
+
+impl Default for WorldStreamingConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 100.0,
+            max_in_flight: 4,
+        }
+    }
+}
+
+/// The chunks streamed to a client
+#[derive(Debug, Default)]
+struct ClientStreaming {
+    /// Chunks that the client received
+    delivered: EntityHashSet,
+    /// Chunks that were sent to the client, but not acknowledged yet
+    in_flight: EntityHashMap<ReplicationGroupId>,
+}
+
+/// The chunks bucketed in a spatial grid, and the progress of the streaming for each client
+#[derive(Resource, Debug)]
+pub struct WorldStreaming {
+    config: WorldStreamingConfig,
+    grid: SpatialGrid,
+    clients: HashMap<ClientId, ClientStreaming>,
+}
+
+impl WorldStreaming {
+    fn new(config: WorldStreamingConfig) -> Self {
+        Self {
+            config,
+            grid: SpatialGrid::new(SpatialInterestConfig {
+                cell_size: config.cell_size,
+            }),
+            clients: HashMap::default(),
+        }
+    }
+
+    /// Number of chunks that were sent to the client but not acknowledged yet
+    pub fn in_flight(&self, client_id: ClientId) -> usize {
+        self.clients
+            .get(&client_id)
+            .map_or(0, |client| client.in_flight.len())
+    }
+
+    /// Returns true if the client acknowledged the spawn of the chunk
+    pub fn is_delivered(&self, client_id: ClientId, chunk: Entity) -> bool {
+        self.clients
+            .get(&client_id)
+            .is_some_and(|client| client.delivered.contains(&chunk))
+    }
+
+    fn remove(&mut self, chunk: Entity) {
+        self.grid.remove(chunk);
+        for client in self.clients.values_mut() {
+            client.delivered.remove(&chunk);
+            client.in_flight.remove(&chunk);
+        }
+    }
+}
+
+/// Plugin that streams the [`StreamedChunk`]s to the clients, closest first.
+///
+/// `P` is the component that contains the position of the chunks and of the [`InterestAnchor`]s.
+pub struct WorldStreamingPlugin<P: SpatialPosition = Transform> {
+    config: WorldStreamingConfig,
+    _marker: std::marker::PhantomData<P>,
+}
+
+impl<P: SpatialPosition> WorldStreamingPlugin<P> {
+    pub fn new(config: WorldStreamingConfig) -> Self {
+        Self {
+            config,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: SpatialPosition> Default for WorldStreamingPlugin<P> {
+    fn default() -> Self {
+        Self::new(WorldStreamingConfig::default())
+    }
+}
+
+/// System sets related to the world streaming
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum WorldStreamingSet {
+    /// Update the cells of the chunks
+    UpdateGrid,
+    /// Choose the chunks to send to each client
+    Stream,
+}
+
+impl<P: SpatialPosition> Plugin for WorldStreamingPlugin<P> {
+    fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
+        // RESOURCES
+        app.register_type::<StreamedChunk>()
+            .register_type::<ClientView>()
+            .register_type::<InterestAnchor>();
+        app.insert_resource(WorldStreaming::new(self.config));
+        // SETS
+        app.configure_sets(
+            schedules.send,
+            (
+                // the grid is updated every frame, so that removals are not missed
+                (
+                    InternalReplicationSet::<ServerMarker>::BeforeBuffer,
+                    WorldStreamingSet::UpdateGrid,
+                    WorldStreamingSet::Stream,
+                    VisibilitySet::UpdateVisibility,
+                )
+                    .run_if(is_started)
+                    .chain(),
+                // the acks are read before they are cleared when the packets are sent
+                WorldStreamingSet::Stream.in_set(InternalMainSet::<ServerMarker>::Send),
+            ),
+        );
+        // SYSTEMS
+        app.add_systems(
+            schedules.send,
+            (
+                systems::update_grid::<P>.in_set(WorldStreamingSet::UpdateGrid),
+                systems::stream_chunks::<P>.in_set(WorldStreamingSet::Stream),
+            ),
+        );
+    }
+}
+
+pub(super) mod systems {
+    use super::*;
+
+    /// Move the chunks to the cell of their current position
+    pub(super) fn update_grid<P: SpatialPosition>(
+        mut streaming: ResMut<WorldStreaming>,
+        query: Query<
+            (Entity, &P),
+            (
+                With<StreamedChunk>,
+                With<ReplicateVisibility>,
+                Or<(Changed<P>, Added<StreamedChunk>, Added<ReplicateVisibility>)>,
+            ),
+        >,
+        mut removed_chunks: RemovedComponents<StreamedChunk>,
+        mut removed_positions: RemovedComponents<P>,
+        mut removed_visibility: RemovedComponents<ReplicateVisibility>,
+    ) {
+        for entity in removed_chunks
+            .read()
+            .chain(removed_positions.read())
+            .chain(removed_visibility.read())
+        {
+            streaming.remove(entity);
+        }
+        for (entity, position) in query.iter() {
+            streaming.grid.insert(entity, position.spatial_position());
+        }
+    }
+
+    /// For each client, cancel the chunks that left its area, and send the closest chunks
+    /// that it didn't receive yet
+    pub(super) fn stream_chunks<P: SpatialPosition>(
+        mut streaming: ResMut<WorldStreaming>,
+        mut visibility_manager: ResMut<VisibilityManager>,
+        connection_manager: Res<ConnectionManager>,
+        views: Query<(&ConnectedClient, Option<&ClientView>)>,
+        anchors: Query<(&InterestAnchor, &P)>,
+        groups: Query<&ReplicationGroup>,
+    ) {
+        let streaming = streaming.as_mut();
+        // forget the clients that disconnected
+        streaming
+            .clients
+            .retain(|client_id, _| views.iter().any(|(client, _)| client.0 == *client_id));
+        let mut anchor_views: HashMap<ClientId, Vec<ClientView>> = HashMap::default();
+        for (anchor, position) in anchors.iter() {
+            anchor_views
+                .entry(anchor.client_id)
+                .or_default()
+                .push(ClientView::new(position.spatial_position(), anchor.radius));
+        }
+        for (client, view) in views.iter() {
+            let client_views: Vec<ClientView> = view
+                .into_iter()
+                .chain(anchor_views.get(&client.0).into_iter().flatten())
+                .copied()
+                .collect();
+            let in_range: EntityHashSet = client_views
+                .iter()
+                .flat_map(|view| streaming.grid.entities_in_view(view))
+                .collect();
+            let state = streaming.clients.entry(client.0).or_default();
+
+            // the chunks whose spawn was acknowledged are delivered
+            if let Ok(connection) = connection_manager.connection(client.0) {
+                let acked = &connection.acked_action_groups;
+                state.in_flight.retain(|chunk, group_id| {
+                    if acked.contains(group_id) {
+                        state.delivered.insert(*chunk);
+                        return false;
+                    }
+                    true
+                });
+            }
+
+            // cancel the chunks that left the area of the client
+            state.delivered.retain(|chunk| {
+                if in_range.contains(chunk) {
+                    return true;
+                }
+                visibility_manager.lose_visibility(client.0, *chunk);
+                false
+            });
+            state.in_flight.retain(|chunk, _| {
+                if in_range.contains(chunk) {
+                    return true;
+                }
+                visibility_manager.lose_visibility(client.0, *chunk);
+                false
+            });
+
+            // send the closest chunks, as long as the in-flight budget allows it
+            let budget = streaming
+                .config
+                .max_in_flight
+                .saturating_sub(state.in_flight.len());
+            if budget == 0 {
+                continue;
+            }
+            let mut candidates: Vec<(f32, Entity)> = in_range
+                .iter()
+                .filter(|chunk| {
+                    !state.delivered.contains(*chunk) && !state.in_flight.contains_key(*chunk)
+                })
+                .filter_map(|chunk| {
+                    let position = streaming.grid.position(*chunk)?;
+                    let distance = client_views
+                        .iter()
+                        .map(|view| view.position.distance_squared(position))
+                        .fold(f32::INFINITY, f32::min);
+                    Some((distance, *chunk))
+                })
+                .collect();
+            candidates.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            for (_, chunk) in candidates.into_iter().take(budget) {
+                let group_id = groups
+                    .get(chunk)
+                    .copied()
+                    .unwrap_or_default()
+                    .group_id(Some(chunk));
+                visibility_manager.gain_visibility(client.0, chunk);
+                state.in_flight.insert(chunk, group_id);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::utils::Duration;
+
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::server::Replicate;
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+    use super::*;
+
+    fn client_has_chunk(stepper: &BevyStepper, chunk: Entity) -> bool {
+        stepper
+            .client_app
+            .world
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(chunk)
+            .is_some()
+    }
+
+    #[test]
+    fn test_stream_closest_chunks_first() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::from_millis(20),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_plugins(WorldStreamingPlugin::<Transform>::new(
+                WorldStreamingConfig {
+                    cell_size: 10.0,
+                    max_in_flight: 2,
+                },
+            ));
+        stepper.init();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let client_entity = stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .client_entity(client_id)
+            .unwrap();
+        // chunks spawned from the farthest to the closest
+        let chunks: Vec<Entity> = (0..6)
+            .rev()
+            .map(|i| {
+                stepper
+                    .server_app
+                    .world
+                    .spawn((
+                        Component1(i as f32),
+                        StreamedChunk,
+                        Transform::from_xyz(i as f32 * 5.0, 0.0, 0.0),
+                        Replicate {
+                            visibility: VisibilityMode::InterestManagement,
+                            ..default()
+                        },
+                    ))
+                    .id()
+            })
+            .rev()
+            .collect();
+        // a chunk that is out of range
+        let far = stepper
+            .server_app
+            .world
+            .spawn((
+                Component1(100.0),
+                StreamedChunk,
+                Transform::from_xyz(100.0, 0.0, 0.0),
+                Replicate {
+                    visibility: VisibilityMode::InterestManagement,
+                    ..default()
+                },
+            ))
+            .id();
+        stepper
+            .server_app
+            .world
+            .entity_mut(client_entity)
+            .insert(ClientView::new(Vec3::ZERO, 30.0));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // only the 2 closest chunks are sent at first
+        let in_flight = |stepper: &BevyStepper| {
+            stepper
+                .server_app
+                .world
+                .resource::<WorldStreaming>()
+                .in_flight(client_id)
+        };
+        assert_eq!(in_flight(&stepper), 2);
+        for _ in 0..10 {
+            stepper.frame_step();
+            assert!(in_flight(&stepper) <= 2);
+        }
+        assert!(client_has_chunk(&stepper, chunks[0]));
+        assert!(client_has_chunk(&stepper, chunks[1]));
+        // the chunks are received in order of distance
+        let received = chunks
+            .iter()
+            .take_while(|chunk| client_has_chunk(&stepper, **chunk))
+            .count();
+        assert!(chunks[received..]
+            .iter()
+            .all(|chunk| !client_has_chunk(&stepper, *chunk)));
+
+        // all the chunks in range are eventually received
+        for _ in 0..50 {
+            stepper.frame_step();
+            assert!(in_flight(&stepper) <= 2);
+        }
+        assert!(chunks
+            .iter()
+            .all(|chunk| client_has_chunk(&stepper, *chunk)));
+        assert!(chunks.iter().all(|chunk| stepper
+            .server_app
+            .world
+            .resource::<WorldStreaming>()
+            .is_delivered(client_id, *chunk)));
+        assert!(!client_has_chunk(&stepper, far));
+
+        // the chunks that leave the view are despawned on the client
+        stepper
+            .server_app
+            .world
+            .entity_mut(client_entity)
+            .insert(ClientView::new(Vec3::new(100.0, 0.0, 0.0), 10.0));
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        assert!(chunks
+            .iter()
+            .all(|chunk| !client_has_chunk(&stepper, *chunk)));
+        assert!(client_has_chunk(&stepper, far));
+    }
+}
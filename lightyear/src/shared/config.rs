@@ -1,9 +1,13 @@
 //! Configuration that has to be the same between the server and the client.
-use bevy::prelude::Res;
+use bevy::app::{App, PostUpdate, PreUpdate};
+use bevy::ecs::schedule::{InternedScheduleLabel, ScheduleLabel};
+use bevy::prelude::{Res, Resource};
 use bevy::reflect::Reflect;
 use bevy::utils::Duration;
 
+#[cfg(feature = "server")]
 use crate::connection::server::ServerConnections;
+#[cfg(feature = "server")]
 use crate::server::config::ServerConfig;
 use crate::shared::tick_manager::TickConfig;
 
@@ -19,6 +23,58 @@ pub struct SharedConfig {
     /// configuration for the [`FixedUpdate`](bevy::prelude::FixedUpdate) schedule
     pub tick: TickConfig,
     pub mode: Mode,
+    /// schedules in which the networking system sets run
+    #[reflect(ignore)]
+    pub schedules: NetworkScheduleConfig,
+}
+
+/// Schedules in which the networking [`SystemSet`](bevy::prelude::SystemSet)s run.
+///
+/// By default, the [`MainSet::Receive`](crate::prelude::MainSet::Receive) and [`MainSet::EmitEvents`](crate::prelude::MainSet::EmitEvents)
+/// sets run in [`PreUpdate`], and the [`MainSet::Send`](crate::prelude::MainSet::Send) and
+/// [`MainSet::SendPackets`](crate::prelude::MainSet::SendPackets) sets run in [`PostUpdate`].
+///
+/// You can move them to other schedules, for example to receive in `First` and send in `Last`,
+/// or to a custom schedule if you are embedding bevy in a server framework that drives the schedules itself.
+/// Note that the prediction and interpolation systems still run in `PreUpdate`/`PostUpdate`, so the
+/// receive schedule should run before `PreUpdate` and the send schedule after `PostUpdate`.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct NetworkScheduleConfig {
+    /// Schedule in which the data is received and the networking events are emitted
+    pub receive: InternedScheduleLabel,
+    /// Schedule in which the data is buffered and sent
+    pub send: InternedScheduleLabel,
+}
+
+impl Default for NetworkScheduleConfig {
+    fn default() -> Self {
+        Self {
+            receive: PreUpdate.intern(),
+            send: PostUpdate.intern(),
+        }
+    }
+}
+
+impl NetworkScheduleConfig {
+    /// Set the schedule in which the data is received
+    pub fn with_receive(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.receive = schedule.intern();
+        self
+    }
+
+    /// Set the schedule in which the data is sent
+    pub fn with_send(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.send = schedule.intern();
+        self
+    }
+
+    /// Get the schedules configured in the [`App`]
+    pub(crate) fn get(app: &App) -> Self {
+        app.world
+            .get_resource::<NetworkScheduleConfig>()
+            .copied()
+            .unwrap_or_default()
+    }
 }
 
 // TODO: maybe the modes should just be
@@ -52,6 +108,7 @@ impl SharedConfig {
     /// We are in HostServer mode if the server is running and the mode is set to HostServer
     /// (checking if the mode is set to HostServer is not enough, it just means that the server plugin and client plugin are running
     /// in the same App)
+    #[cfg(feature = "server")]
     pub fn is_host_server_condition(
         config: Option<Res<ServerConfig>>,
         server: Option<Res<ServerConnections>>,
@@ -62,9 +119,21 @@ impl SharedConfig {
         })
     }
 
+    /// Without the `server` feature, the app cannot be a host-server
+    #[cfg(not(feature = "server"))]
+    pub fn is_host_server_condition() -> bool {
+        false
+    }
+
+    #[cfg(feature = "server")]
     pub fn is_mode_separate(config: Option<Res<ServerConfig>>) -> bool {
         config.map_or(true, |config| config.shared.mode == Mode::Separate)
     }
+
+    #[cfg(not(feature = "server"))]
+    pub fn is_mode_separate() -> bool {
+        true
+    }
 }
 
 impl Default for SharedConfig {
@@ -75,6 +144,7 @@ impl Default for SharedConfig {
             server_send_interval: Duration::from_millis(0),
             tick: TickConfig::new(Duration::from_millis(16)),
             mode: Mode::default(),
+            schedules: NetworkScheduleConfig::default(),
         }
     }
 }
@@ -4,7 +4,11 @@ use std::marker::PhantomData;
 
 use bevy::prelude::{Component, Entity, Event};
 
-use crate::packet::message::Message;
+use crate::channel::stream::TransferProgress;
+use crate::packet::message::{FragmentProgress, Message, MessageHandle, MessageId};
+use crate::prelude::Tick;
+use crate::protocol::channel::ChannelKind;
+use crate::shared::replication::DespawnReason;
 
 /// This event is emitted whenever we receive a message from the remote
 #[derive(Event)]
@@ -27,6 +31,156 @@ impl<M: Message, Ctx> MessageEvent<M, Ctx> {
     }
 }
 
+/// This event is emitted when the progress of a transfer on a
+/// [`ChannelMode::Stream`](crate::channel::builder::ChannelMode::Stream) channel is updated
+#[derive(Event, Debug)]
+pub struct TransferProgressEvent<Ctx = ()> {
+    channel: ChannelKind,
+    progress: TransferProgress,
+    context: Ctx,
+}
+
+impl<Ctx> TransferProgressEvent<Ctx> {
+    pub fn new(channel: ChannelKind, progress: TransferProgress, context: Ctx) -> Self {
+        Self {
+            channel,
+            progress,
+            context,
+        }
+    }
+
+    /// The stream channel of the transfer
+    pub fn channel(&self) -> ChannelKind {
+        self.channel
+    }
+
+    pub fn progress(&self) -> &TransferProgress {
+        &self.progress
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+/// This event is emitted when a fragment of a message sent on a reliable channel is acked by the remote
+/// (see [`ReliableSettings::max_fragments_per_send`](crate::channel::builder::ReliableSettings::max_fragments_per_send))
+#[derive(Event, Debug)]
+pub struct FragmentProgressEvent<Ctx = ()> {
+    channel: ChannelKind,
+    progress: FragmentProgress,
+    context: Ctx,
+}
+
+impl<Ctx> FragmentProgressEvent<Ctx> {
+    pub fn new(channel: ChannelKind, progress: FragmentProgress, context: Ctx) -> Self {
+        Self {
+            channel,
+            progress,
+            context,
+        }
+    }
+
+    /// The channel on which the message was sent
+    pub fn channel(&self) -> ChannelKind {
+        self.channel
+    }
+
+    pub fn progress(&self) -> &FragmentProgress {
+        &self.progress
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+/// This event is emitted when a message sent on a reliable channel expired before being acked
+/// (see [`ReliableSettings::max_age`](crate::channel::builder::ReliableSettings::max_age))
+#[derive(Event, Debug)]
+pub struct MessageExpiredEvent<Ctx = ()> {
+    channel: ChannelKind,
+    message_id: MessageId,
+    context: Ctx,
+}
+
+impl<Ctx> MessageExpiredEvent<Ctx> {
+    pub fn new(channel: ChannelKind, message_id: MessageId, context: Ctx) -> Self {
+        Self {
+            channel,
+            message_id,
+            context,
+        }
+    }
+
+    /// The channel on which the message was sent
+    pub fn channel(&self) -> ChannelKind {
+        self.channel
+    }
+
+    pub fn message_id(&self) -> MessageId {
+        self.message_id
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+/// This event is emitted when component updates are received for an entity that doesn't exist locally,
+/// if the [`UnknownEntityUpdatePolicy`](crate::shared::replication::UnknownEntityUpdatePolicy) is `Event`
+#[derive(Event, Debug)]
+pub struct UnknownEntityUpdateEvent<Ctx = ()> {
+    remote_entity: Entity,
+    tick: Tick,
+    context: Ctx,
+}
+
+impl<Ctx> UnknownEntityUpdateEvent<Ctx> {
+    pub fn new(remote_entity: Entity, tick: Tick, context: Ctx) -> Self {
+        Self {
+            remote_entity,
+            tick,
+            context,
+        }
+    }
+
+    /// The entity on the remote's side
+    pub fn remote_entity(&self) -> Entity {
+        self.remote_entity
+    }
+
+    /// The remote tick of the updates
+    pub fn tick(&self) -> Tick {
+        self.tick
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+/// This event is emitted when the remote received a message that was sent with a [`MessageHandle`]
+#[derive(Event, Debug)]
+pub struct MessageAckedEvent<Ctx = ()> {
+    handle: MessageHandle,
+    context: Ctx,
+}
+
+impl<Ctx> MessageAckedEvent<Ctx> {
+    pub fn new(handle: MessageHandle, context: Ctx) -> Self {
+        Self { handle, context }
+    }
+
+    pub fn handle(&self) -> MessageHandle {
+        self.handle
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
 #[derive(Event)]
 /// Event emitted on server every time we receive an event
 pub struct InputEvent<I: crate::inputs::native::UserAction, Ctx = ()> {
@@ -74,17 +228,39 @@ impl<Ctx> EntitySpawnEvent<Ctx> {
 pub struct EntityDespawnEvent<Ctx = ()> {
     entity: Entity,
     context: Ctx,
+    reason: Option<DespawnReason>,
 }
 
 impl<Ctx> EntityDespawnEvent<Ctx> {
     pub fn new(entity: Entity, context: Ctx) -> Self {
-        Self { entity, context }
+        Self {
+            entity,
+            context,
+            reason: None,
+        }
+    }
+
+    pub(crate) fn with_reason(mut self, reason: Option<DespawnReason>) -> Self {
+        self.reason = reason;
+        self
     }
 
     pub fn entity(&self) -> Entity {
         self.entity
     }
 
+    /// The reason code that the remote provided when despawning the entity
+    pub fn reason(&self) -> Option<DespawnReason> {
+        self.reason
+    }
+
+    /// The reason that the remote provided when despawning the entity, converted to the game's own reason type.
+    ///
+    /// Returns None if no reason was provided, or if the code doesn't match any variant of `R`
+    pub fn reason_as<R: TryFrom<DespawnReason>>(&self) -> Option<R> {
+        self.reason.and_then(|reason| R::try_from(reason).ok())
+    }
+
     pub fn context(&self) -> &Ctx {
         &self.context
     }
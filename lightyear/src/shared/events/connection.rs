@@ -12,13 +12,15 @@ use crate::protocol::channel::ChannelKind;
 use crate::protocol::component::ComponentNetId;
 use crate::protocol::message::MessageKind;
 use crate::protocol::EventContext;
+use crate::serialize::RawData;
+use crate::shared::replication::DespawnReason;
 
 // TODO: don't make fields pub but instead make accessors
 #[derive(Debug, Resource)]
 pub struct ConnectionEvents {
     // replication
     pub spawns: Vec<Entity>,
-    pub despawns: Vec<Entity>,
+    pub despawns: Vec<(Entity, Option<DespawnReason>)>,
 
     // TODO: [IMPORTANT]: add ticks as well?
     // - should we just return the latest update for a given component/entity, or all of them?
@@ -40,6 +42,9 @@ pub struct ConnectionEvents {
     // How can i easily get the events (inserts/adds/removes) for a given entity? add components on that entity
     // that track that?
     empty: bool,
+    /// Values of the components that were rejected or clamped by a [`ValidateFn`](crate::protocol::component::ValidateFn),
+    /// and that must be sent back to the remote so that it corrects its own value
+    pub(crate) component_corrections: Vec<(Entity, RawData)>,
 }
 
 pub(crate) trait ClearEvents {
@@ -53,6 +58,7 @@ impl ClearEvents for ConnectionEvents {
         self.component_inserts.clear();
         self.component_removes.clear();
         self.component_updates.clear();
+        self.component_corrections.clear();
         self.empty = true;
     }
 }
@@ -74,6 +80,7 @@ impl ConnectionEvents {
             component_updates: Default::default(),
             // bookkeeping
             empty: true,
+            component_corrections: Vec::new(),
         }
     }
 
@@ -81,6 +88,11 @@ impl ConnectionEvents {
         self.empty
     }
 
+    pub(crate) fn push_component_correction(&mut self, entity: Entity, component: RawData) {
+        trace!(?entity, "Component value must be corrected on the remote");
+        self.component_corrections.push((entity, component));
+    }
+
     pub(crate) fn push_spawn(&mut self, entity: Entity) {
         trace!(?entity, "Received entity spawn");
         #[cfg(feature = "metrics")]
@@ -91,13 +103,13 @@ impl ConnectionEvents {
         self.empty = false;
     }
 
-    pub(crate) fn push_despawn(&mut self, entity: Entity) {
-        trace!(?entity, "Received entity despawn");
+    pub(crate) fn push_despawn(&mut self, entity: Entity, reason: Option<DespawnReason>) {
+        trace!(?entity, ?reason, "Received entity despawn");
         #[cfg(feature = "metrics")]
         {
             metrics::counter!("entity_despawn").increment(1);
         }
-        self.despawns.push(entity);
+        self.despawns.push((entity, reason));
         self.empty = false;
     }
 
@@ -180,14 +192,22 @@ impl IterEntitySpawnEvent for ConnectionEvents {
 }
 
 pub trait IterEntityDespawnEvent<Ctx: EventContext = ()> {
-    fn into_iter_entity_despawn(&mut self) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>;
+    fn into_iter_entity_despawn(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, Option<DespawnReason>, Ctx)> + '_>;
     fn has_entity_despawn(&self) -> bool;
 }
 
 impl IterEntityDespawnEvent for ConnectionEvents {
-    fn into_iter_entity_despawn(&mut self) -> Box<dyn Iterator<Item = (Entity, ())> + '_> {
+    fn into_iter_entity_despawn(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, Option<DespawnReason>, ())> + '_> {
         let despawns = std::mem::take(&mut self.despawns);
-        Box::new(despawns.into_iter().map(|entity| (entity, ())))
+        Box::new(
+            despawns
+                .into_iter()
+                .map(|(entity, reason)| (entity, reason, ())),
+        )
     }
 
     fn has_entity_despawn(&self) -> bool {
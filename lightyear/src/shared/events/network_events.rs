@@ -0,0 +1,262 @@
+//! Bevy [`Event`]s that are sent over the network
+//!
+//! An event registered with [`add_network_event`](crate::prelude::AppMessageExt::add_network_event) is sent to the
+//! remote peer whenever it is written to an [`EventWriter`](bevy::prelude::EventWriter), and the remote peer emits it
+//! again as a normal bevy [`Event`].
+//!
+//! On the server, the events written as `E` are sent to all the clients. Write a [`ToClients<E>`] event
+//! instead to only send the event to some of the clients.
+use bevy::ecs::event::EventId;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use tracing::error;
+
+use crate::prelude::{Channel, Message};
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::events::components::MessageEvent;
+use crate::shared::message::MessageSend;
+use crate::shared::replication::network_target::NetworkTarget;
+use crate::shared::replication::ReplicationPeer;
+use crate::shared::sets::InternalMainSet;
+
+/// Event written on the server to send the event `E` only to the clients in `target`
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ToClients<E> {
+    pub event: E,
+    pub target: NetworkTarget,
+}
+
+impl<E> ToClients<E> {
+    pub fn new(event: E, target: NetworkTarget) -> Self {
+        Self { event, target }
+    }
+}
+
+/// Ids of the events `E` that were received from the remote peer during this frame.
+///
+/// This is only used when the event is sent in both directions, so that the received events
+/// are not sent back to the remote peer.
+#[derive(Resource)]
+struct ReceivedNetworkEvents<E: Event> {
+    ids: HashSet<EventId<E>>,
+}
+
+impl<E: Event> Default for ReceivedNetworkEvents<E> {
+    fn default() -> Self {
+        Self {
+            ids: HashSet::default(),
+        }
+    }
+}
+
+/// Add the systems to send the events `E` to the remote peers in `target`
+pub(crate) fn add_network_event_send_systems<
+    E: Event + Message,
+    C: Channel,
+    S: MessageSend + ReplicationPeer,
+>(
+    app: &mut App,
+    target: NetworkTarget,
+) {
+    app.add_event::<E>();
+    let schedules = NetworkScheduleConfig::get(app);
+    // the events are only kept for two frames, so we buffer them every frame
+    // even if the packets are only sent every send_interval
+    app.add_systems(
+        schedules.send,
+        (move |connection_manager: ResMut<S>,
+               events: EventReader<E>,
+               received: Option<ResMut<ReceivedNetworkEvents<E>>>| {
+            send_network_events::<E, C, S>(connection_manager, events, received, &target)
+        })
+        .before(InternalMainSet::<S::SetMarker>::Send),
+    );
+}
+
+/// Add the systems to send [`ToClients<E>`] events from the server
+pub(crate) fn add_network_event_target_systems<
+    E: Event + Message + Clone,
+    C: Channel,
+    S: MessageSend + ReplicationPeer,
+>(
+    app: &mut App,
+) {
+    app.add_event::<ToClients<E>>();
+    let schedules = NetworkScheduleConfig::get(app);
+    app.add_systems(
+        schedules.send,
+        send_targeted_network_events::<E, C, S>.before(InternalMainSet::<S::SetMarker>::Send),
+    );
+}
+
+pub(crate) fn add_network_event_receive_systems<
+    E: Event + Message + Clone,
+    S: MessageSend + ReplicationPeer,
+>(
+    app: &mut App,
+    is_bidirectional: bool,
+) {
+    app.add_event::<E>();
+    if is_bidirectional {
+        app.init_resource::<ReceivedNetworkEvents<E>>();
+    }
+    let schedules = NetworkScheduleConfig::get(app);
+    app.add_systems(
+        schedules.receive,
+        receive_network_events::<E, S::EventContext>
+            .after(InternalMainSet::<S::SetMarker>::EmitEvents),
+    );
+}
+
+/// Send the events `E` written during this frame to the remote peer
+fn send_network_events<E: Event + Message, C: Channel, S: MessageSend>(
+    mut connection_manager: ResMut<S>,
+    mut events: EventReader<E>,
+    mut received: Option<ResMut<ReceivedNetworkEvents<E>>>,
+    target: &NetworkTarget,
+) {
+    for (event, id) in events.read_with_id() {
+        // do not send back the events that we received from the remote peer
+        if received
+            .as_mut()
+            .is_some_and(|received| received.ids.remove(&id))
+        {
+            continue;
+        }
+        let _ = connection_manager
+            .send_message_to_target::<C, E>(event, target.clone())
+            .inspect_err(|e| error!("could not send network event: {:?}", e));
+    }
+    if let Some(received) = received.as_mut() {
+        received.ids.clear();
+    }
+}
+
+/// Send the [`ToClients<E>`] events written during this frame to the clients in their target
+fn send_targeted_network_events<E: Event + Message, C: Channel, S: MessageSend>(
+    mut connection_manager: ResMut<S>,
+    mut events: EventReader<ToClients<E>>,
+) {
+    for event in events.read() {
+        let _ = connection_manager
+            .send_message_to_target::<C, E>(&event.event, event.target.clone())
+            .inspect_err(|e| error!("could not send network event: {:?}", e));
+    }
+}
+
+/// Emit the events received from the remote peer as normal bevy events.
+///
+/// The [`MessageEvent<E>`] events are still available to know which peer sent the event.
+fn receive_network_events<E: Event + Message + Clone, Ctx: Send + Sync + 'static>(
+    mut messages: EventReader<MessageEvent<E, Ctx>>,
+    mut events: EventWriter<E>,
+    mut received: Option<ResMut<ReceivedNetworkEvents<E>>>,
+) {
+    for message in messages.read() {
+        let id = events.send(message.message.clone());
+        if let Some(received) = received.as_mut() {
+            received.ids.insert(id);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::utils::Duration;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+    use super::*;
+
+    #[derive(Event, Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct Chat(String);
+
+    /// The `Chat` events read by the app
+    #[derive(Resource, Default)]
+    struct ReadChats(Vec<Chat>);
+
+    fn read_chats(mut events: EventReader<Chat>, mut chats: ResMut<ReadChats>) {
+        chats.0.extend(events.read().cloned());
+    }
+
+    fn setup(direction: ChannelDirection) -> BevyStepper {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        for app in [&mut stepper.client_app, &mut stepper.server_app] {
+            app.add_network_event::<Chat, Channel1>(direction);
+            app.init_resource::<ReadChats>();
+            app.add_systems(Update, read_chats);
+        }
+        stepper.init();
+        stepper
+    }
+
+    #[test]
+    fn test_bidirectional_network_event() {
+        let mut stepper = setup(ChannelDirection::Bidirectional);
+        stepper
+            .client_app
+            .world
+            .send_event(Chat("from client".to_string()));
+        stepper
+            .server_app
+            .world
+            .send_event(Chat("from server".to_string()));
+
+        let mut client_messages = vec![];
+        for _ in 0..10 {
+            stepper.frame_step();
+            client_messages.extend(
+                stepper
+                    .client_app
+                    .world
+                    .resource_mut::<Events<MessageEvent<Chat>>>()
+                    .drain()
+                    .map(|event| event.message),
+            );
+        }
+        // the events received from the server are not sent back to the server
+        assert_eq!(client_messages, vec![Chat("from server".to_string())]);
+        let server_chats = &stepper.server_app.world.resource::<ReadChats>().0;
+        assert_eq!(server_chats.len(), 2);
+        assert!(server_chats.contains(&Chat("from client".to_string())));
+    }
+
+    #[test]
+    fn test_targeted_network_event() {
+        let mut stepper = setup(ChannelDirection::ServerToClient);
+        stepper.server_app.world.send_event(ToClients::new(
+            Chat("excluded".to_string()),
+            NetworkTarget::AllExceptSingle(ClientId::Netcode(TEST_CLIENT_ID)),
+        ));
+        stepper.server_app.world.send_event(ToClients::new(
+            Chat("included".to_string()),
+            NetworkTarget::Single(ClientId::Netcode(TEST_CLIENT_ID)),
+        ));
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper.client_app.world.resource::<ReadChats>().0,
+            vec![Chat("included".to_string())]
+        );
+    }
+}
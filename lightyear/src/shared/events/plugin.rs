@@ -1,8 +1,9 @@
 //! Create the bevy [`Plugin`]
 
-use bevy::app::{App, PreUpdate};
-use bevy::prelude::{IntoSystemConfigs, Plugin, PostUpdate};
+use bevy::app::App;
+use bevy::prelude::{IntoSystemConfigs, Plugin};
 
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::events::components::{EntityDespawnEvent, EntitySpawnEvent};
 use crate::shared::events::systems::{clear_events, push_entity_events};
 use crate::shared::replication::ReplicationReceive;
@@ -26,12 +27,13 @@ impl<R: ReplicationReceive> Plugin for EventsPlugin<R> {
         app.add_event::<EntitySpawnEvent<R::EventContext>>()
             .add_event::<EntityDespawnEvent<R::EventContext>>();
         // SYSTEMS
+        let schedules = NetworkScheduleConfig::get(app);
         app.add_systems(
-            PreUpdate,
+            schedules.receive,
             push_entity_events::<R>.in_set(InternalMainSet::<R::SetMarker>::EmitEvents),
         );
         app.add_systems(
-            PostUpdate,
+            schedules.send,
             // NOTE: we add this to the All system-set so that this system doesn't run if the host is disconnected
             clear_events::<R>.in_set(InternalReplicationSet::<R::SetMarker>::All),
         );
@@ -2,24 +2,46 @@
 
 use bevy::app::{App, Plugin};
 
+#[cfg(feature = "client")]
 use crate::client::config::ClientConfig;
 use crate::inputs::native::InputMessage;
 use crate::prelude::{MessageRegistry, UserAction};
 use crate::protocol::message::MessageType;
+#[cfg(feature = "server")]
 use crate::server::config::ServerConfig;
 
+/// Function that combines the inputs of the frames between two ticks into the input of the next tick.
+///
+/// It is called with the input combined so far and the input of the new frame.
+pub type CoalesceFn<A> = fn(A, A) -> A;
+
+/// Keep the input of the last frame
+pub(crate) fn latest_input<A>(_: A, input: A) -> A {
+    input
+}
+
 pub struct InputPlugin<A> {
-    _marker: std::marker::PhantomData<A>,
+    coalesce_fn: CoalesceFn<A>,
 }
 
 impl<A> Default for InputPlugin<A> {
     fn default() -> Self {
         Self {
-            _marker: std::marker::PhantomData,
+            coalesce_fn: latest_input,
         }
     }
 }
 
+impl<A> InputPlugin<A> {
+    /// Set the function that combines the inputs added on the client with
+    /// [`add_frame_input`](crate::prelude::client::InputManager::add_frame_input) during the frames between two ticks.
+    /// By default, the input of the last frame is used.
+    pub fn with_coalesce_fn(mut self, coalesce_fn: CoalesceFn<A>) -> Self {
+        self.coalesce_fn = coalesce_fn;
+        self
+    }
+}
+
 impl<A: UserAction> Plugin for InputPlugin<A> {
     fn build(&self, app: &mut App) {}
 
@@ -27,12 +49,15 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
         app.world
             .resource_mut::<MessageRegistry>()
             .add_message::<InputMessage<A>>(MessageType::NativeInput);
-        let is_client = app.world.get_resource::<ClientConfig>().is_some();
-        let is_server = app.world.get_resource::<ServerConfig>().is_some();
-        if is_client {
-            app.add_plugins(crate::client::input::InputPlugin::<A>::default());
+        #[cfg(feature = "client")]
+        if app.world.get_resource::<ClientConfig>().is_some() {
+            app.add_plugins(
+                crate::client::input::InputPlugin::<A>::default()
+                    .with_coalesce_fn(self.coalesce_fn),
+            );
         }
-        if is_server {
+        #[cfg(feature = "server")]
+        if app.world.get_resource::<ServerConfig>().is_some() {
             app.add_plugins(crate::server::input::InputPlugin::<A>::default());
         }
     }
@@ -5,7 +5,7 @@ use leafwing_input_manager::prelude::ActionState;
 
 use crate::client::config::ClientConfig;
 use crate::client::input_leafwing::LeafwingInputConfig;
-use crate::inputs::leafwing::InputMessage;
+use crate::inputs::leafwing::{InputMessage, InputStateRequest};
 use crate::prelude::{
     AppComponentExt, AppMessageExt, AppSerializeExt, ChannelDirection, LeafwingUserAction,
     MessageRegistry,
@@ -34,12 +34,13 @@ impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A> {
             .resource_mut::<MessageRegistry>()
             .add_message::<InputMessage<A>>(MessageType::LeafwingInput);
         app.add_map_entities::<InputMessage<A>>();
+        app.add_message::<InputStateRequest<A>>(ChannelDirection::ServerToClient);
         app.register_component::<ActionState<A>>(ChannelDirection::Bidirectional);
         let is_client = app.world.get_resource::<ClientConfig>().is_some();
         let is_server = app.world.get_resource::<ServerConfig>().is_some();
         if is_client {
             app.add_plugins(
-                crate::client::input_leafwing::LeafwingInputPlugin::<A>::new(self.config),
+                crate::client::input_leafwing::LeafwingInputPlugin::<A>::new(self.config.clone()),
             );
         }
         if is_server {
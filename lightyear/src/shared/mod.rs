@@ -12,10 +12,14 @@ pub mod plugin;
 
 pub mod replication;
 
+pub mod scheduled_events;
+
 pub mod sets;
 
 pub mod tick_manager;
 
+pub mod timings;
+
 pub mod input;
 
 #[cfg(feature = "leafwing")]
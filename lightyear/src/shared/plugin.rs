@@ -1,19 +1,28 @@
 //! Bevy [`Plugin`] used by both the server and the client
+use crate::client::components::ComponentSyncMode;
+#[cfg(feature = "client")]
 use crate::client::config::ClientConfig;
+#[cfg(feature = "server")]
 use crate::connection::server::ServerConnections;
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
 use crate::prelude::{
-    AppComponentExt, ChannelDirection, ChannelRegistry, ComponentRegistry, LinkConditionerConfig,
-    MessageRegistry, Mode, ParentSync, PingConfig, PrePredicted, PreSpawnedPlayerObject,
-    ShouldBePredicted, TickConfig,
+    AppComponentExt, AppMessageExt, ChannelDirection, ChannelRegistry, ComponentRegistry,
+    LinkConditionerConfig, MessageRegistry, Mode, ParentSync, PingConfig, PrePredicted,
+    PreSpawnedPlayerObject, ShouldBePredicted, TickConfig,
 };
+#[cfg(feature = "server")]
 use crate::server::config::ServerConfig;
+use crate::server::inspector::{InspectorRequest, InspectorSnapshot};
+#[cfg(feature = "server")]
+use crate::server::protocols::AdditionalProtocols;
+use crate::server::stats::ServerStats;
 use crate::shared::config::SharedConfig;
-use crate::shared::replication::components::{Controlled, ShouldBeInterpolated};
+use crate::shared::replication::components::{Controlled, ReplicateDisabled, ShouldBeInterpolated};
 use crate::shared::tick_manager::TickManagerPlugin;
 use crate::shared::time_manager::TimePlugin;
+use crate::shared::timings::TimingsPlugin;
 use crate::transport::middleware::compression::CompressionConfig;
 
 #[derive(Default, Debug)]
@@ -24,9 +33,11 @@ pub struct SharedPlugin {
 /// You can use this as a SystemParam to identify whether you're running on the client or the server
 #[derive(SystemParam)]
 pub struct NetworkIdentity<'w, 's> {
+    #[cfg(feature = "client")]
     client_config: Option<Res<'w, ClientConfig>>,
+    #[cfg(feature = "server")]
     server: Option<Res<'w, ServerConnections>>,
-    _marker: std::marker::PhantomData<&'s ()>,
+    _marker: std::marker::PhantomData<(&'w (), &'s ())>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -43,19 +54,24 @@ pub enum Identity {
 
 impl<'w, 's> NetworkIdentity<'w, 's> {
     pub fn identity(&self) -> Identity {
-        let Some(config) = &self.client_config else {
-            return Identity::Server;
-        };
-        if matches!(config.shared.mode, Mode::HostServer)
-            && self
-                .server
-                .as_ref()
-                .map_or(false, |server| server.is_listening())
+        #[cfg(feature = "client")]
         {
-            Identity::HostServer
-        } else {
+            let Some(config) = &self.client_config else {
+                return Identity::Server;
+            };
+            #[cfg(feature = "server")]
+            if matches!(config.shared.mode, Mode::HostServer)
+                && self
+                    .server
+                    .as_ref()
+                    .map_or(false, |server| server.is_listening())
+            {
+                return Identity::HostServer;
+            }
             Identity::Client
         }
+        #[cfg(not(feature = "client"))]
+        Identity::Server
     }
     pub fn is_client(&self) -> bool {
         self.identity() == Identity::Client
@@ -80,6 +96,7 @@ impl Plugin for SharedPlugin {
         app.insert_resource(ChannelRegistry::new());
         app.insert_resource(ComponentRegistry::default());
         app.insert_resource(MessageRegistry::default());
+        app.insert_resource(self.config.schedules);
         app.insert_resource(Time::<Fixed>::from_seconds(
             self.config.tick.tick_duration.as_secs_f64(),
         ));
@@ -93,6 +110,7 @@ impl Plugin for SharedPlugin {
             server_send_interval: self.config.server_send_interval,
             client_send_interval: self.config.client_send_interval,
         });
+        app.add_plugins(TimingsPlugin);
     }
 
     fn finish(&self, app: &mut App) {
@@ -102,14 +120,34 @@ impl Plugin for SharedPlugin {
         // (if we put this in the ReplicationPlugin, the components would get registered twice)
         // - we need to run this in `finish` so that all plugins have been built (so ClientPlugin and ServerPlugin
         // both exists)
-        app.register_component::<PreSpawnedPlayerObject>(ChannelDirection::Bidirectional);
-        app.register_component::<PrePredicted>(ChannelDirection::Bidirectional);
-        app.register_component::<ShouldBePredicted>(ChannelDirection::ServerToClient);
-        app.register_component::<ShouldBeInterpolated>(ChannelDirection::ServerToClient);
-        app.register_component::<ParentSync>(ChannelDirection::Bidirectional)
-            .add_map_entities();
-        app.register_component::<Controlled>(ChannelDirection::Bidirectional);
+        register_internal_protocol(app);
+        // the additional protocols of the server also contain the internal components and messages
+        #[cfg(feature = "server")]
+        if let Some(mut protocols) = app.world.remove_resource::<AdditionalProtocols>() {
+            protocols.register_internal();
+            app.insert_resource(protocols);
+        }
         // check that the protocol was built correctly
         app.world.resource::<ComponentRegistry>().check();
     }
 }
+
+/// Register the components and messages that lightyear uses internally
+pub(crate) fn register_internal_protocol(app: &mut App) {
+    app.register_component::<PreSpawnedPlayerObject>(ChannelDirection::Bidirectional);
+    app.register_component::<PrePredicted>(ChannelDirection::Bidirectional);
+    app.register_component::<ShouldBePredicted>(ChannelDirection::ServerToClient);
+    app.register_component::<ShouldBeInterpolated>(ChannelDirection::ServerToClient);
+    // the hierarchy of the predicted entities is synced separately (see `PredictionConfig::predict_hierarchy`),
+    // because the parent must be mapped to the predicted parent
+    app.register_component::<ParentSync>(ChannelDirection::Bidirectional)
+        .add_map_entities();
+    // the Controlled marker is synced to the predicted entities, to distinguish the predicted entities
+    // that are controlled by the local client from the remote ones
+    app.register_component::<Controlled>(ChannelDirection::Bidirectional)
+        .add_prediction(ComponentSyncMode::Simple);
+    app.register_component::<ReplicateDisabled>(ChannelDirection::ServerToClient);
+    app.register_resource::<ServerStats>(ChannelDirection::ServerToClient);
+    app.add_message::<InspectorRequest>(ChannelDirection::ClientToServer);
+    app.add_message::<InspectorSnapshot>(ChannelDirection::ServerToClient);
+}
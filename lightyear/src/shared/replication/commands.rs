@@ -0,0 +1,275 @@
+//! Commands to interact with the replication of components
+use bevy::ecs::system::Command;
+use bevy::prelude::{Commands, DetectChangesMut, Entity, World};
+
+use crate::client::components::{ComponentSyncMode, Confirmed, SyncComponent};
+#[cfg(feature = "client")]
+use crate::client::interpolation::resource::InterpolationManager;
+#[cfg(feature = "client")]
+use crate::client::prediction::resource::PredictionManager;
+use crate::protocol::component::ComponentRegistry;
+use crate::shared::replication::components::{RefreshComponent, Replicating};
+
+/// Extension trait to force a component to be synced again via [`Commands`].
+pub trait RefreshComponentExt {
+    /// Explicitly re-sync the component `C` of the entity, even if it is normally only synced once.
+    ///
+    /// - if the entity is being replicated, the component will be included in the next replication
+    ///   update, even if it is marked with [`ReplicateOnceComponent`](crate::prelude::ReplicateOnceComponent)
+    /// - if the entity is a [`Confirmed`] entity on the client, the component will be copied again to the
+    ///   Predicted and Interpolated entities if it was registered with [`ComponentSyncMode::Once`] or
+    ///   [`ComponentSyncMode::Simple`]
+    ///
+    /// This is useful for values that rarely change (for example a player color), for which
+    /// [`ComponentSyncMode::Full`] would add unnecessary change-detection costs every frame.
+    fn refresh_component<C: SyncComponent>(&mut self, entity: Entity);
+}
+
+impl RefreshComponentExt for Commands<'_, '_> {
+    fn refresh_component<C: SyncComponent>(&mut self, entity: Entity) {
+        self.add(RefreshComponentCommand::<C> {
+            entity,
+            _marker: std::marker::PhantomData,
+        });
+    }
+}
+
+struct RefreshComponentCommand<C> {
+    entity: Entity,
+    _marker: std::marker::PhantomData<C>,
+}
+
+impl<C: SyncComponent> Command for RefreshComponentCommand<C> {
+    fn apply(self, world: &mut World) {
+        let Some(mut entity_mut) = world.get_entity_mut(self.entity) else {
+            return;
+        };
+        let Some(mut component) = entity_mut.get_mut::<C>() else {
+            return;
+        };
+        // mark the component as changed so that it is included in the next replication update
+        component.set_changed();
+        #[cfg(feature = "client")]
+        let component = component.clone();
+        if entity_mut.contains::<Replicating>() {
+            entity_mut.insert(RefreshComponent::<C>::default());
+        }
+        #[cfg(feature = "client")]
+        refresh_synced_components(world, self.entity, component);
+    }
+}
+
+/// If the entity is a [`Confirmed`] entity, copy the component again to its Predicted and Interpolated entities
+#[cfg(feature = "client")]
+fn refresh_synced_components<C: SyncComponent>(world: &mut World, entity: Entity, component: C) {
+    let Some((predicted, interpolated)) = world
+        .get::<Confirmed>(entity)
+        .map(|confirmed| (confirmed.predicted, confirmed.interpolated))
+    else {
+        return;
+    };
+
+    // copy the confirmed value to the predicted and interpolated entities
+    let Some(registry) = world.get_resource::<ComponentRegistry>() else {
+        return;
+    };
+    let is_refreshable = |mode: ComponentSyncMode| {
+        matches!(mode, ComponentSyncMode::Once | ComponentSyncMode::Simple)
+    };
+    let predicted = predicted
+        .filter(|_| is_refreshable(registry.prediction_mode::<C>()))
+        .and_then(|predicted| {
+            let manager = world.get_resource::<PredictionManager>()?;
+            let mut component = component.clone();
+            manager.map_entities(&mut component, registry);
+            Some((predicted, component))
+        });
+    let interpolated = interpolated
+        .filter(|_| is_refreshable(registry.interpolation_mode::<C>()))
+        .and_then(|interpolated| {
+            let manager = world.get_resource::<InterpolationManager>()?;
+            let mut component = component;
+            manager.map_entities(&mut component, registry);
+            Some((interpolated, component))
+        });
+    for (entity, component) in predicted.into_iter().chain(interpolated) {
+        if let Some(mut entity_mut) = world.get_entity_mut(entity) {
+            entity_mut.insert(component);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{Commands, Entity, With};
+
+    use crate::client::components::Confirmed;
+    use crate::prelude::client::Predicted;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{client, ReplicateOnceComponent};
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    /// Check that refreshing a component that is marked as ReplicateOnce still replicates the update
+    #[test]
+    fn test_refresh_replicate_once_component() {
+        let mut stepper = BevyStepper::default();
+
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((
+                Replicate::default(),
+                Component1(1.0),
+                ReplicateOnceComponent::<Component1>::default(),
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = *stepper
+            .client_app
+            .world
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .entity(client_entity)
+                .get::<Component1>(),
+            Some(&Component1(1.0))
+        );
+
+        // updates are not replicated
+        stepper
+            .server_app
+            .world
+            .entity_mut(server_entity)
+            .insert(Component1(2.0));
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .entity(client_entity)
+                .get::<Component1>(),
+            Some(&Component1(1.0))
+        );
+
+        // unless the component is refreshed
+        stepper
+            .server_app
+            .world
+            .run_system_once(move |mut commands: Commands| {
+                commands.refresh_component::<Component1>(server_entity);
+            });
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .entity(client_entity)
+                .get::<Component1>(),
+            Some(&Component1(2.0))
+        );
+        // the marker has been removed
+        assert!(stepper
+            .server_app
+            .world
+            .entity(server_entity)
+            .get::<RefreshComponent<Component1>>()
+            .is_none());
+    }
+
+    /// Check that refreshing a ComponentSyncMode::Once component on the Confirmed entity
+    /// copies it again to the Predicted entity
+    #[test]
+    fn test_refresh_confirmed_component() {
+        let mut stepper = BevyStepper::default();
+
+        let confirmed = stepper
+            .client_app
+            .world
+            .spawn(Confirmed {
+                tick: stepper.client_tick(),
+                ..Default::default()
+            })
+            .id();
+        let predicted = stepper
+            .client_app
+            .world
+            .spawn(Predicted {
+                confirmed_entity: Some(confirmed),
+            })
+            .id();
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .get_mut::<Confirmed>()
+            .unwrap()
+            .predicted = Some(predicted);
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .insert(Component3(1.0));
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .entity(predicted)
+                .get::<Component3>(),
+            Some(&Component3(1.0))
+        );
+
+        // updates to a SyncMode::Once component are not synced
+        stepper
+            .client_app
+            .world
+            .entity_mut(confirmed)
+            .insert(Component3(2.0));
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .entity(predicted)
+                .get::<Component3>(),
+            Some(&Component3(1.0))
+        );
+
+        // unless the component is refreshed
+        stepper
+            .client_app
+            .world
+            .run_system_once(move |mut commands: Commands| {
+                commands.refresh_component::<Component3>(confirmed);
+            });
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .entity(predicted)
+                .get::<Component3>(),
+            Some(&Component3(2.0))
+        );
+        // the confirmed entity is not replicated, so no marker is added
+        assert!(stepper
+            .client_app
+            .world
+            .query_filtered::<Entity, With<RefreshComponent<Component3>>>()
+            .iter(&stepper.client_app.world)
+            .next()
+            .is_none());
+    }
+}
@@ -1,9 +1,10 @@
 //! Components used for replication
-use bevy::ecs::entity::MapEntities;
+use bevy::ecs::component::Tick as BevyTick;
+use bevy::ecs::entity::{EntityHashMap, MapEntities};
 use bevy::ecs::query::QueryFilter;
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::{Bundle, Component, Entity, EntityMapper, Or, Query, Reflect, With};
-use bevy::utils::{HashMap, HashSet};
+use bevy::utils::{Duration, HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use tracing::trace;
 
@@ -14,7 +15,9 @@ use crate::client::components::SyncComponent;
 use crate::connection::id::ClientId;
 use crate::prelude::ParentSync;
 use crate::protocol::component::{ComponentKind, ComponentNetId, ComponentRegistry};
-use crate::server::visibility::immediate::{ClientVisibility, VisibilityManager};
+use crate::protocol::macros::protocol_hash;
+#[cfg(feature = "server")]
+use crate::server::visibility::immediate::VisibilityManager;
 use crate::shared::replication::network_target::NetworkTarget;
 
 /// Marker component that indicates that the entity was spawned via replication
@@ -41,6 +44,29 @@ pub(crate) struct DespawnTracker;
 #[derive(Component, Clone, Copy, PartialEq, Debug, Reflect, Serialize, Deserialize)]
 pub struct Controlled;
 
+/// Marker component to disable an entity on the clients, without despawning it.
+///
+/// While this component is present on a replicated entity, the clients keep the entity (and any
+/// client-side state attached to it) but insert the marker registered with
+/// [`AppDisableExt::register_disabled_marker`](crate::client::disable::AppDisableExt::register_disabled_marker).
+/// Removing the component enables the entity again.
+#[derive(Component, Clone, Copy, Default, PartialEq, Debug, Reflect, Serialize, Deserialize)]
+pub struct ReplicateDisabled;
+
+/// Marker component to pause the replication of the updates of an entity, without despawning it on the clients.
+///
+/// While this component is present, the server does not send the inserts and updates of the components of
+/// the entity, so that the intermediate states (for example during a cutscene or a teleport) are not visible
+/// to the clients. The removals and the despawn of the entity are still replicated.
+/// When the component is removed, the current value of every replicated component of the entity is sent again.
+///
+/// The entities of a [`ReplicationGroup`] are updated together, so to pause a group the component should be
+/// added to all of its entities.
+///
+/// This has no effect for client to server replication.
+#[derive(Component, Clone, Copy, Default, PartialEq, Debug, Reflect, Serialize, Deserialize)]
+pub struct ReplicationPaused;
+
 /// Marker component to indicate that updates for this entity are being replicated.
 ///
 /// If this component gets removed, the replication will pause.
@@ -48,7 +74,7 @@ pub struct Controlled;
 pub struct Replicating;
 
 /// Component that indicates which clients the entity should be replicated to.
-#[derive(Component, Clone, Debug, PartialEq, Reflect)]
+#[derive(Component, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct ReplicationTarget {
     /// Which clients should this entity be replicated to
     pub target: NetworkTarget,
@@ -62,6 +88,50 @@ impl Default for ReplicationTarget {
     }
 }
 
+/// Component that indicates which clients should predict and interpolate the entity
+#[derive(Component, Default, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct SyncTarget {
+    /// Which clients should predict this entity (unused for client to server replication)
+    pub prediction: NetworkTarget,
+    /// Which clients should interpolate this entity (unused for client to server replication)
+    pub interpolation: NetworkTarget,
+}
+
+impl SyncTarget {
+    /// Predict the entity on all the clients that control it, and interpolate it on the other clients.
+    ///
+    /// This is useful for entities that are controlled by several clients at the same time
+    /// (for example two players steering the same vehicle).
+    pub fn controllers(controlled_by: &ControlledBy) -> Self {
+        let mut interpolation = NetworkTarget::All;
+        interpolation.exclude(&controlled_by.target);
+        Self {
+            prediction: controlled_by.target.clone(),
+            interpolation,
+        }
+    }
+}
+
+/// Component storing metadata about which clients have control over the entity
+///
+/// Several clients can control the same entity: each of them receives the [`Controlled`] marker, and on the
+/// server the leafwing inputs of all the controllers are merged (see `SharedActionStates`).
+/// The entity is only despawned when the last of its controllers disconnects.
+///
+/// This is only used for server to client replication.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ControlledBy {
+    /// Which client(s) control this entity?
+    pub target: NetworkTarget,
+}
+
+impl ControlledBy {
+    /// Returns true if the entity is controlled by the specified client
+    pub fn targets(&self, client_id: &ClientId) -> bool {
+        self.target.targets(client_id)
+    }
+}
+
 /// Defines the target entity for the replication.
 ///
 /// This can be used if you want to replicate this entity on an entity that already
@@ -78,10 +148,52 @@ pub enum TargetEntity {
     Preexisting(Entity),
 }
 
+/// Deterministic identifier of an entity that exists on both peers without being replicated,
+/// for example an entity placed in a scene or a level that is loaded by the server and by the clients.
+///
+/// Add the same [`NetworkId`] to the entity on both peers: instead of spawning a new entity, the remote
+/// links the replicated entity to its local entity with that [`NetworkId`], and the replication updates are
+/// applied to it. If the remote doesn't have an entity with that [`NetworkId`], a new entity is spawned (with the
+/// same [`NetworkId`], so that it is linked again if the server restarts and [loads](crate::server::persistence)
+/// its saved world).
+///
+/// The ids are split in two reserved ranges, so that they never collide:
+/// - [`NetworkId::new`] uses the ids below `2^32`, for ids assigned manually (the index of the entity in the level, etc.)
+/// - [`NetworkId::from_name`] uses the ids above `2^63`, for ids derived from a stable name (the path of the entity in the scene, etc.)
+///
+/// This is only used for server to client replication.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct NetworkId(u64);
+
+impl NetworkId {
+    /// First id of the range used by [`NetworkId::from_name`]
+    const NAMED_RANGE_START: u64 = 1 << 63;
+
+    /// Id assigned manually
+    pub const fn new(id: u32) -> Self {
+        Self(id as u64)
+    }
+
+    /// Id derived from the hash of a name, which must be unique among the pre-placed entities
+    pub const fn from_name(name: &str) -> Self {
+        Self(protocol_hash(name) | Self::NAMED_RANGE_START)
+    }
+
+    pub(crate) const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> u64 {
+        self.0
+    }
+}
+
 /// Component that defines how the hierarchy of an entity (parent/children) should be replicated
-#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct ReplicateHierarchy {
     /// If true, recursively add `Replicate` and `ParentSync` components to all children to make sure they are replicated
+    /// (including the children that are added to the hierarchy later). The whole hierarchy is replicated in the
+    /// [`ReplicationGroup`] of the root entity, and is despawned on the remote when the root is despawned.
     /// If false, you can still replicate hierarchies, but in a more fine-grained manner. You will have to add the `Replicate`
     /// and `ParentSync` components to the children yourself
     pub recursive: bool,
@@ -93,6 +205,12 @@ impl Default for ReplicateHierarchy {
     }
 }
 
+/// Marker component that excludes a child (and its own descendants) from the recursive [`ReplicateHierarchy`]
+/// of its root: the [`ReplicationTarget`] and [`ParentSync`](crate::shared::replication::hierarchy::ParentSync)
+/// components are not added to it automatically
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Reflect)]
+pub struct DisableReplicateHierarchy;
+
 // TODO: should these be sparse set or not?
 /// If this component is present, we won't replicate the component
 ///
@@ -127,6 +245,94 @@ impl<C> Default for ReplicateOnceComponent<C> {
     }
 }
 
+/// If this component is present, the updates of the component `C` are only replicated while the predicate
+/// returns true for the current value of the component.
+///
+/// This avoids sending updates for idle entities without having to toggle the replication manually:
+/// ```rust,ignore
+/// commands.spawn((
+///     Replicate::default(),
+///     Velocity::default(),
+///     ReplicateIf::<Velocity>::new(|velocity| velocity.0 != Vec2::ZERO),
+/// ));
+/// ```
+///
+/// The inserts of the component are always replicated. When the predicate starts returning false, the value
+/// that made it fail is still replicated once (so that the remote doesn't keep a stale value), but the
+/// following updates are not replicated until the predicate returns true again.
+///
+/// On the server, this is combined with the predicate of the [`ComponentRegistry`](crate::prelude::ComponentRegistry)
+/// (see [`add_replicate_if`](crate::prelude::ComponentRegistration::add_replicate_if)):
+/// the updates must pass both predicates.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ReplicateIf<C> {
+    predicate: fn(&C) -> bool,
+}
+
+impl<C> ReplicateIf<C> {
+    pub fn new(predicate: fn(&C) -> bool) -> Self {
+        Self { predicate }
+    }
+
+    pub(crate) fn should_replicate(&self, component: &C) -> bool {
+        (self.predicate)(component)
+    }
+}
+
+/// Keeps track of the entities whose component `C` was rejected by a replication predicate.
+///
+/// When the predicate starts rejecting the component, the value that made it fail is still replicated
+/// (otherwise the remote would keep the last value that passed the predicate, which can be very different);
+/// the following updates are not sent until the predicate accepts the component again.
+#[derive(Debug, Default)]
+pub(crate) struct RejectedUpdates {
+    /// Change tick of the first rejected value of the component, for each entity
+    transitions: EntityHashMap<BevyTick>,
+}
+
+impl RejectedUpdates {
+    /// Returns true if the update of the component can be sent.
+    ///
+    /// While the component is rejected, only the first rejected value is sent: it keeps being sent until it is acked,
+    /// as long as the component doesn't change again.
+    pub(crate) fn should_send(
+        &mut self,
+        entity: Entity,
+        rejected: bool,
+        change_tick: BevyTick,
+    ) -> bool {
+        if rejected {
+            *self.transitions.entry(entity).or_insert(change_tick) == change_tick
+        } else {
+            self.transitions.remove(&entity);
+            true
+        }
+    }
+
+    /// Only keep the entities for which `f` returns true (for example the entities that are still replicated)
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(Entity) -> bool) {
+        self.transitions.retain(|entity, _| f(*entity));
+    }
+}
+
+/// Marker component inserted by [`RefreshComponentExt::refresh_component`](crate::prelude::RefreshComponentExt::refresh_component)
+/// to force the component `C` to be replicated again, even if it is marked with [`ReplicateOnceComponent`].
+///
+/// It is removed automatically once the update has been buffered.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct RefreshComponent<C> {
+    _marker: std::marker::PhantomData<C>,
+}
+
+impl<C> Default for RefreshComponent<C> {
+    fn default() -> Self {
+        Self {
+            _marker: Default::default(),
+        }
+    }
+}
+
 // TODO: maybe have 3 fields:
 //  - target
 //  - override replication_target: bool (if true, we will completely override the replication target. If false, we do the intersection)
@@ -147,7 +353,7 @@ impl<C> OverrideTargetComponent<C> {
     }
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Reflect)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Reflect, Serialize, Deserialize)]
 pub enum ReplicationGroupIdBuilder {
     // the group id is the entity id
     #[default]
@@ -163,12 +369,15 @@ pub enum ReplicationGroupIdBuilder {
 ///
 /// If multiple entities are part of the same replication group, they will be sent together in the same message.
 /// It is guaranteed that these entities will be updated at the same time on the remote world.
-#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct ReplicationGroup {
     id_builder: ReplicationGroupIdBuilder,
     /// the priority of the accumulation group
     /// (priority will get reset to this value every time a message gets sent successfully)
     base_priority: f32,
+    /// If set, the updates of the group are sent at most once per interval instead of every
+    /// [`server_send_interval`](crate::prelude::SharedConfig::server_send_interval)
+    send_interval: Option<Duration>,
 }
 
 impl Default for ReplicationGroup {
@@ -176,6 +385,7 @@ impl Default for ReplicationGroup {
         Self {
             id_builder: ReplicationGroupIdBuilder::FromEntity,
             base_priority: 1.0,
+            send_interval: None,
         }
     }
 }
@@ -185,6 +395,7 @@ impl ReplicationGroup {
         Self {
             id_builder: ReplicationGroupIdBuilder::FromEntity,
             base_priority: 1.0,
+            send_interval: None,
         }
     }
 
@@ -192,6 +403,7 @@ impl ReplicationGroup {
         Self {
             id_builder: ReplicationGroupIdBuilder::Group(id),
             base_priority: 1.0,
+            send_interval: None,
         }
     }
 
@@ -217,6 +429,22 @@ impl ReplicationGroup {
         self.id_builder = ReplicationGroupIdBuilder::Group(id);
         self
     }
+
+    pub(crate) fn send_interval(&self) -> Option<Duration> {
+        self.send_interval
+    }
+
+    /// Send the updates of the group at most once per `send_interval`.
+    ///
+    /// The entity actions (spawns, inserts, removals) are still sent immediately.
+    ///
+    /// The clients are told the interval of the group, but their interpolation delay only depends on
+    /// the [`server_send_interval`](crate::prelude::SharedConfig::server_send_interval): the interpolated
+    /// entities of the group hold their last value until the next update if the delay is shorter than `send_interval`.
+    pub fn set_send_interval(mut self, send_interval: Duration) -> Self {
+        self.send_interval = Some(send_interval);
+        self
+    }
 }
 
 #[derive(
@@ -235,7 +463,7 @@ impl ReplicationGroup {
 )]
 pub struct ReplicationGroupId(pub u64);
 
-#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Reflect)]
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Reflect, Serialize, Deserialize)]
 pub enum VisibilityMode {
     /// We will replicate this entity to the clients specified in the `replication_target`.
     /// On top of that, we will apply interest management logic to determine which clients should receive the entity
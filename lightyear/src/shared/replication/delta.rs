@@ -0,0 +1,292 @@
+//! Delta compression of component updates
+//!
+//! For the components registered with
+//! [`ComponentRegistration::add_delta_compression`](crate::protocol::component::ComponentRegistration::add_delta_compression),
+//! the server keeps, for each client, the last value of the component that the client acked. The next updates
+//! only contain the difference with that value (the baseline), along with the tick of the baseline.
+//!
+//! The client keeps a short history of the values it received, so that it can find the baseline and apply the
+//! difference. If the updates are lost, the baseline doesn't move forward; once it is too old, the server falls
+//! back to sending the full value of the component.
+//!
+//! Inserts are always sent in full.
+use std::any::Any;
+use std::collections::VecDeque;
+
+use bevy::ecs::entity::EntityHash;
+use bevy::prelude::{Component, Entity};
+use bevy::utils::{hashbrown, HashMap};
+
+use crate::prelude::Tick;
+use crate::protocol::component::ComponentNetId;
+use crate::protocol::BitSerializable;
+use crate::shared::replication::components::ReplicationGroupId;
+
+type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
+
+/// Maximum number of ticks between an update and its baseline.
+///
+/// If the last value acked by the client is older than that, the full value of the component is sent.
+pub(crate) const MAX_DELTA_BASE_AGE: i16 = 64;
+
+/// A component that can be replicated as a difference with a previous value
+///
+/// ```rust,ignore
+/// #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+/// struct Inventory(Vec<u32>);
+///
+/// impl Diffable for Inventory {
+///     // the items that were added since the previous value
+///     type Delta = Vec<u32>;
+///
+///     fn diff(&self, new: &Self) -> Self::Delta {
+///         new.0[self.0.len()..].to_vec()
+///     }
+///
+///     fn apply_diff(&mut self, delta: &Self::Delta) {
+///         self.0.extend_from_slice(delta);
+///     }
+/// }
+/// ```
+pub trait Diffable: Clone {
+    /// The difference between two values of the component
+    type Delta: BitSerializable;
+
+    /// Compute the difference to go from `self` to `new`
+    fn diff(&self, new: &Self) -> Self::Delta;
+
+    /// Apply the difference computed by [`diff`](Self::diff) to `self`
+    fn apply_diff(&mut self, delta: &Self::Delta);
+}
+
+pub(crate) type DeltaValue = Box<dyn Any + Send + Sync>;
+
+#[derive(Default)]
+struct ComponentDeltaState {
+    /// Last value acked by the remote, with the tick at which it was sent
+    acked: Option<(Tick, DeltaValue)>,
+    /// Values that were sent in update messages that were not acked yet
+    pending: Vec<(Tick, DeltaValue)>,
+}
+
+/// Keeps track of the values of the delta-compressed components that were sent to a remote peer,
+/// to use them as baselines
+#[derive(Default)]
+pub(crate) struct DeltaSender {
+    states: EntityHashMap<
+        ReplicationGroupId,
+        EntityHashMap<Entity, HashMap<ComponentNetId, ComponentDeltaState>>,
+    >,
+}
+
+impl DeltaSender {
+    /// Returns the baseline to use for an update sent at `tick`, if the remote acked a value recently enough
+    pub(crate) fn base(
+        &self,
+        group_id: ReplicationGroupId,
+        entity: Entity,
+        kind: ComponentNetId,
+        tick: Tick,
+    ) -> Option<(Tick, &DeltaValue)> {
+        let (base_tick, base) = self
+            .states
+            .get(&group_id)?
+            .get(&entity)?
+            .get(&kind)?
+            .acked
+            .as_ref()?;
+        (tick - *base_tick < MAX_DELTA_BASE_AGE).then_some((*base_tick, base))
+    }
+
+    /// Record the value of the component that was included in the update message of the group sent at `tick`
+    pub(crate) fn record_sent(
+        &mut self,
+        group_id: ReplicationGroupId,
+        entity: Entity,
+        kind: ComponentNetId,
+        tick: Tick,
+        value: DeltaValue,
+    ) {
+        let state = self
+            .states
+            .entry(group_id)
+            .or_default()
+            .entry(entity)
+            .or_default()
+            .entry(kind)
+            .or_default();
+        // values that are too old would not be used as baselines even if they were acked
+        state
+            .pending
+            .retain(|(t, _)| *t != tick && tick - *t < MAX_DELTA_BASE_AGE);
+        state.pending.push((tick, value));
+    }
+
+    /// The update message of the group that was sent at `tick` was received by the remote: the values it
+    /// contained become the new baselines
+    pub(crate) fn ack(&mut self, group_id: ReplicationGroupId, tick: Tick) {
+        let Some(entities) = self.states.get_mut(&group_id) else {
+            return;
+        };
+        for state in entities
+            .values_mut()
+            .flat_map(|components| components.values_mut())
+        {
+            let Some(index) = state.pending.iter().position(|(t, _)| *t == tick) else {
+                continue;
+            };
+            let acked = state.pending.swap_remove(index);
+            // acks can arrive out of order
+            if state
+                .acked
+                .as_ref()
+                .map_or(true, |(acked_tick, _)| acked.0 > *acked_tick)
+            {
+                state.acked = Some(acked);
+            }
+            state.pending.retain(|(t, _)| *t > tick);
+        }
+    }
+
+    /// Stop using baselines for the component (for example because it was removed)
+    pub(crate) fn remove_component(&mut self, entity: Entity, kind: ComponentNetId) {
+        self.states.values_mut().for_each(|entities| {
+            if let Some(components) = entities.get_mut(&entity) {
+                components.remove(&kind);
+            }
+        });
+    }
+
+    pub(crate) fn remove_entity(&mut self, entity: Entity) {
+        self.states.values_mut().for_each(|entities| {
+            entities.remove(&entity);
+        });
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    /// Forget the values that are too old to be used as baselines
+    pub(crate) fn cleanup(&mut self, tick: Tick) {
+        for entities in self.states.values_mut() {
+            for components in entities.values_mut() {
+                for state in components.values_mut() {
+                    state
+                        .pending
+                        .retain(|(t, _)| tick - *t < MAX_DELTA_BASE_AGE);
+                    if state
+                        .acked
+                        .as_ref()
+                        .is_some_and(|(t, _)| tick - *t >= MAX_DELTA_BASE_AGE)
+                    {
+                        state.acked = None;
+                    }
+                }
+                components.retain(|_, state| state.acked.is_some() || !state.pending.is_empty());
+            }
+            entities.retain(|_, components| !components.is_empty());
+        }
+        self.states.retain(|_, entities| !entities.is_empty());
+    }
+}
+
+/// Values of a delta-compressed component received by the client, that can be used as baselines
+#[derive(Component, Debug)]
+pub(crate) struct DeltaHistory<C: Send + Sync + 'static> {
+    values: VecDeque<(Tick, C)>,
+}
+
+impl<C: Send + Sync + 'static> Default for DeltaHistory<C> {
+    fn default() -> Self {
+        Self {
+            values: VecDeque::new(),
+        }
+    }
+}
+
+impl<C: Clone + Send + Sync + 'static> DeltaHistory<C> {
+    /// Record the value of the component received at `tick`
+    pub(crate) fn record(&mut self, tick: Tick, value: C) {
+        if self.values.back().is_some_and(|(t, _)| *t >= tick) {
+            // the value is older than the latest one, it will never be used as a baseline
+            return;
+        }
+        self.values.push_back((tick, value));
+        while self
+            .values
+            .front()
+            .is_some_and(|(t, _)| tick - *t >= MAX_DELTA_BASE_AGE)
+        {
+            self.values.pop_front();
+        }
+    }
+
+    /// Returns the value received at `tick`.
+    ///
+    /// The values older than `tick` are dropped: the baselines used by the server only move forward.
+    pub(crate) fn base(&mut self, tick: Tick) -> Option<&C> {
+        while self.values.front().is_some_and(|(t, _)| *t < tick) {
+            self.values.pop_front();
+        }
+        self.values
+            .front()
+            .filter(|(t, _)| *t == tick)
+            .map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(base: Option<(Tick, &DeltaValue)>) -> Option<(Tick, u32)> {
+        base.map(|(tick, value)| (tick, *value.downcast_ref::<u32>().unwrap()))
+    }
+
+    #[test]
+    fn test_delta_sender() {
+        let mut sender = DeltaSender::default();
+        let entity = Entity::from_raw(1);
+        let group = ReplicationGroupId(0);
+        let kind = 2;
+        sender.record_sent(group, entity, kind, Tick(1), Box::new(10u32));
+        sender.record_sent(group, entity, kind, Tick(2), Box::new(20u32));
+        // nothing was acked yet
+        assert_eq!(value(sender.base(group, entity, kind, Tick(3))), None);
+
+        sender.ack(group, Tick(2));
+        assert_eq!(
+            value(sender.base(group, entity, kind, Tick(3))),
+            Some((Tick(2), 20))
+        );
+        // late acks don't move the baseline backwards
+        sender.ack(group, Tick(1));
+        assert_eq!(
+            value(sender.base(group, entity, kind, Tick(3))),
+            Some((Tick(2), 20))
+        );
+
+        // the baseline is too old
+        assert_eq!(
+            value(sender.base(group, entity, kind, Tick(2 + MAX_DELTA_BASE_AGE as u16))),
+            None
+        );
+        sender.cleanup(Tick(2 + MAX_DELTA_BASE_AGE as u16));
+        assert!(sender.states.is_empty());
+    }
+
+    #[test]
+    fn test_delta_history() {
+        let mut history = DeltaHistory::<u32>::default();
+        history.record(Tick(1), 10);
+        history.record(Tick(3), 30);
+        // out of order
+        history.record(Tick(2), 20);
+        assert_eq!(history.base(Tick(2)), None);
+        assert_eq!(history.base(Tick(3)), Some(&30));
+
+        history.record(Tick(3 + MAX_DELTA_BASE_AGE as u16), 40);
+        assert_eq!(history.base(Tick(3)), None);
+    }
+}
@@ -1,10 +1,14 @@
 //! Map between local and remote entities
 use anyhow::Context;
 use bevy::ecs::entity::{EntityHashMap, EntityMapper, MapEntities};
-use bevy::prelude::{Component, Deref, DerefMut, Entity, EntityWorldMut, World};
+use bevy::prelude::{Component, Deref, DerefMut, Entity, EntityWorldMut, Resource, World};
 use bevy::reflect::Reflect;
 use bevy::utils::hashbrown::hash_map::Entry;
+use bevy::utils::HashMap;
 use std::cell::UnsafeCell;
+use tracing::warn;
+
+use crate::shared::replication::components::NetworkId;
 
 #[derive(Default, Debug, Reflect, Deref, DerefMut)]
 pub struct EntityMap(pub(crate) EntityHashMap<Entity>);
@@ -114,13 +118,50 @@ impl RemoteEntityMap {
         self.remote_to_local.is_empty() && self.local_to_remote.is_empty()
     }
 
-    fn clear(&mut self) {
+    pub(super) fn clear(&mut self) {
         self.local_to_remote.clear();
         self.remote_to_local.clear();
     }
 }
 
-#[cfg(test)]
+/// The local entities that have a [`NetworkId`], so that the replicated entities with the same [`NetworkId`]
+/// can be linked to them
+#[derive(Resource, Default, Debug)]
+pub(crate) struct PrePlacedEntities {
+    entities: HashMap<NetworkId, Entity>,
+    network_ids: EntityHashMap<NetworkId>,
+}
+
+impl PrePlacedEntities {
+    pub(crate) fn insert(&mut self, network_id: NetworkId, entity: Entity) {
+        if let Some(previous) = self.network_ids.insert(entity, network_id) {
+            self.entities.remove(&previous);
+        }
+        if let Some(previous) = self.entities.insert(network_id, entity) {
+            if previous != entity {
+                warn!(
+                    ?network_id,
+                    ?previous,
+                    ?entity,
+                    "Several entities have the same NetworkId"
+                );
+                self.network_ids.remove(&previous);
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) {
+        if let Some(network_id) = self.network_ids.remove(&entity) {
+            self.entities.remove(&network_id);
+        }
+    }
+
+    pub(crate) fn get(&self, network_id: NetworkId) -> Option<Entity> {
+        self.entities.get(&network_id).copied()
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use bevy::utils::Duration;
 
@@ -1,12 +1,14 @@
 //! This module is responsible for making sure that parent-children hierarchies are replicated correctly.
-use bevy::ecs::entity::MapEntities;
+use bevy::ecs::entity::{EntityHashSet, MapEntities};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::prelude::server::ControlledBy;
 use crate::prelude::{Replicated, Replicating, ReplicationGroup, VisibilityMode};
-use crate::server::replication::send::SyncTarget;
-use crate::shared::replication::components::{ReplicateHierarchy, ReplicationTarget};
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::replication::components::{ControlledBy, SyncTarget};
+use crate::shared::replication::components::{
+    DisableReplicateHierarchy, ReplicateHierarchy, ReplicationTarget,
+};
 use crate::shared::replication::{ReplicationPeer, ReplicationSend};
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet};
 
@@ -16,10 +18,24 @@ use crate::shared::sets::{InternalMainSet, InternalReplicationSet};
 ///
 /// Updates entity's `Parent` component on change.
 /// Removes the parent if `None`.
+///
+/// With [`PredictionConfig::predict_hierarchy`], the hierarchy is also synced to the predicted entities on the client.
+/// If the children of a predicted entity are predicted as well (which is the case by default with
+/// [`ReplicateHierarchy::recursive`], since the children inherit the [`SyncTarget`] of the parent), the predicted
+/// children are attached to the predicted parent, so that their `Transform` is simulated and rolled back along with it.
+///
+/// [`PredictionConfig::predict_hierarchy`]: crate::client::prediction::plugin::PredictionConfig::predict_hierarchy
 #[derive(Component, Default, Reflect, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
 #[component(storage = "SparseSet")]
 pub struct ParentSync(Option<Entity>);
 
+impl ParentSync {
+    /// The parent of the entity on the sender side
+    pub(crate) fn parent(&self) -> Option<Entity> {
+        self.0
+    }
+}
+
 impl MapEntities for ParentSync {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
         if let Some(entity) = &mut self.0 {
@@ -42,6 +58,12 @@ impl<R> Default for HierarchySendPlugin<R> {
 
 impl<R: ReplicationSend> HierarchySendPlugin<R> {
     /// If `replicate.replicate_hierarchy` is true, replicate the entire hierarchy of the entity
+    ///
+    /// The children that are added to the hierarchy later are also replicated. The hierarchy is only
+    /// visited when the [`ReplicateHierarchy`] of the root changed, or when an entity of the hierarchy
+    /// got a new parent or new children. The children with [`DisableReplicateHierarchy`] (and their
+    /// descendants) are skipped.
+    #[allow(clippy::type_complexity)]
     fn propagate_replicate(
         mut commands: Commands,
         // query the root parent of the hierarchy
@@ -56,8 +78,22 @@ impl<R: ReplicationSend> HierarchySendPlugin<R> {
             ),
             (Without<Parent>, With<Children>),
         >,
+        changed_hierarchy_query: Query<Entity, Or<(Added<Parent>, Changed<Children>)>>,
+        ancestors_query: Query<&Parent>,
         children_query: Query<&Children>,
+        unreplicated_query: Query<(), Without<ReplicationTarget>>,
+        disabled_query: Query<(), With<DisableReplicateHierarchy>>,
     ) {
+        // the roots of the hierarchies where an entity got attached
+        let changed_roots: EntityHashSet = changed_hierarchy_query
+            .iter()
+            .map(|entity| {
+                ancestors_query
+                    .iter_ancestors(entity)
+                    .last()
+                    .unwrap_or(entity)
+            })
+            .collect();
         for (
             parent_entity,
             replicate_hierarchy,
@@ -67,29 +103,47 @@ impl<R: ReplicationSend> HierarchySendPlugin<R> {
             visibility_mode,
         ) in parent_query.iter()
         {
-            if replicate_hierarchy.is_changed() && replicate_hierarchy.recursive {
-                // iterate through all descendents of the entity
-                for child in children_query.iter_descendants(parent_entity) {
-                    trace!("Propagate Replicate through hierarchy: adding Replicate on child: {child:?}");
-                    // no need to set the correct parent as it will be set later in the `update_parent_sync` system
-                    commands.entity(child).insert((
-                        // TODO: should we add replicating?
-                        Replicating,
-                        replication_target.clone(),
-                        // the entire hierarchy is replicated as a single group, that uses the parent's entity as the group id
-                        ReplicationGroup::new_id(parent_entity.to_bits()),
-                        ReplicateHierarchy { recursive: true },
-                        ParentSync(None),
-                    ));
-                    if let Some(controlled_by) = controlled_by {
-                        commands.entity(child).insert(controlled_by.clone());
-                    }
-                    if let Some(sync_target) = sync_target {
-                        commands.entity(child).insert(sync_target.clone());
-                    }
-                    if let Some(vis) = visibility_mode {
-                        commands.entity(child).insert(*vis);
-                    }
+            if !replicate_hierarchy.recursive
+                || (!replicate_hierarchy.is_changed() && !changed_roots.contains(&parent_entity))
+            {
+                continue;
+            }
+            // iterate through all descendents of the entity, except the subtrees that opted out
+            let mut descendants: Vec<Entity> = children_query
+                .get(parent_entity)
+                .map(|children| children.to_vec())
+                .unwrap_or_default();
+            while let Some(child) = descendants.pop() {
+                if disabled_query.contains(child) {
+                    continue;
+                }
+                if let Ok(children) = children_query.get(child) {
+                    descendants.extend(children.iter().copied());
+                }
+                if !replicate_hierarchy.is_changed() && !unreplicated_query.contains(child) {
+                    continue;
+                }
+                trace!(
+                    "Propagate Replicate through hierarchy: adding Replicate on child: {child:?}"
+                );
+                // no need to set the correct parent as it will be set later in the `update_parent_sync` system
+                commands.entity(child).insert((
+                    // TODO: should we add replicating?
+                    Replicating,
+                    replication_target.clone(),
+                    // the entire hierarchy is replicated as a single group, that uses the parent's entity as the group id
+                    ReplicationGroup::new_id(parent_entity.to_bits()),
+                    ReplicateHierarchy { recursive: true },
+                    ParentSync(None),
+                ));
+                if let Some(controlled_by) = controlled_by {
+                    commands.entity(child).insert(controlled_by.clone());
+                }
+                if let Some(sync_target) = sync_target {
+                    commands.entity(child).insert(sync_target.clone());
+                }
+                if let Some(vis) = visibility_mode {
+                    commands.entity(child).insert(*vis);
                 }
             }
             // TODO: should we update the parent's replication group? we actually can't.. replication groups
@@ -133,8 +187,9 @@ impl<R: ReplicationSend> HierarchySendPlugin<R> {
 
 impl<R: ReplicationSend> Plugin for HierarchySendPlugin<R> {
     fn build(&self, app: &mut App) {
+        let schedules = NetworkScheduleConfig::get(app);
         app.add_systems(
-            PostUpdate,
+            schedules.send,
             (
                 (Self::propagate_replicate, Self::update_parent_sync).chain(),
                 Self::removal_system,
@@ -195,23 +250,29 @@ impl<R: ReplicationPeer> Plugin for HierarchyReceivePlugin<R> {
 
         // TODO: does this work for client replication? (client replicating to other clients via the server?)
         // when we receive a ParentSync update from the remote, update the hierarchy
+        let schedules = NetworkScheduleConfig::get(app);
         app.add_systems(
-            PreUpdate,
+            schedules.receive,
             Self::update_parent.after(InternalMainSet::<R::SetMarker>::Receive),
         );
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use std::ops::Deref;
 
-    use bevy::hierarchy::{BuildWorldChildren, Children, Parent};
-    use bevy::prelude::{default, Entity, With};
+    use bevy::hierarchy::{BuildWorldChildren, Children, DespawnRecursiveExt, Parent};
+    use bevy::prelude::{default, Entity, Or, With};
 
-    use crate::prelude::server::Replicate;
-    use crate::prelude::ReplicationGroup;
-    use crate::shared::replication::components::ReplicateHierarchy;
+    use crate::client::connection::ConnectionManager as ClientConnectionManager;
+
+    use crate::prelude::client::{ClientConfig, Confirmed, Predicted};
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{NetworkTarget, Replicated, ReplicationGroup};
+    use crate::shared::replication::components::{
+        DisableReplicateHierarchy, ReplicateHierarchy, ReplicationTarget,
+    };
     use crate::shared::replication::hierarchy::ParentSync;
     use crate::tests::protocol::*;
     use crate::tests::stepper::{BevyStepper, Step};
@@ -390,4 +451,204 @@ mod tests {
             Some(&ReplicationGroup::new_id(grandparent.to_bits()))
         );
     }
+
+    /// Children added to a replicated hierarchy are replicated in the group of the root
+    #[test]
+    fn test_propagate_hierarchy_new_child() {
+        let (mut stepper, grandparent, parent, _) = setup_hierarchy();
+        stepper
+            .server_app
+            .world
+            .entity_mut(grandparent)
+            .insert(Replicate::default());
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let new_child = stepper.server_app.world.spawn(Component5(0.0)).id();
+        stepper
+            .server_app
+            .world
+            .entity_mut(parent)
+            .add_child(new_child);
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert_eq!(
+            stepper.server_app.world.get::<ReplicationGroup>(new_child),
+            Some(&ReplicationGroup::new_id(grandparent.to_bits()))
+        );
+        let client_parent = stepper
+            .client_app
+            .world
+            .query_filtered::<Entity, With<Component2>>()
+            .get_single(&stepper.client_app.world)
+            .unwrap();
+        let client_new_child = stepper
+            .client_app
+            .world
+            .query_filtered::<&Parent, With<Component5>>()
+            .get_single(&stepper.client_app.world)
+            .unwrap();
+        assert_eq!(client_new_child.get(), client_parent);
+    }
+
+    /// Despawning the root of the hierarchy despawns the whole remote hierarchy
+    /// The children with `DisableReplicateHierarchy` are not replicated with the hierarchy, nor are their descendants
+    #[test]
+    fn test_propagate_hierarchy_disabled_child() {
+        let (mut stepper, grandparent, parent, child) = setup_hierarchy();
+        stepper
+            .server_app
+            .world
+            .entity_mut(parent)
+            .insert(DisableReplicateHierarchy);
+        stepper
+            .server_app
+            .world
+            .entity_mut(grandparent)
+            .insert(Replicate::default());
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert!(stepper
+            .server_app
+            .world
+            .get::<ReplicationTarget>(parent)
+            .is_none());
+        assert!(stepper
+            .server_app
+            .world
+            .get::<ReplicationTarget>(child)
+            .is_none());
+        assert!(stepper
+            .client_app
+            .world
+            .query_filtered::<Entity, With<Component1>>()
+            .get_single(&stepper.client_app.world)
+            .is_ok());
+        assert!(stepper
+            .client_app
+            .world
+            .query_filtered::<Entity, Or<(With<Component2>, With<Component3>)>>()
+            .iter(&stepper.client_app.world)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_despawn_hierarchy_root() {
+        let (mut stepper, grandparent, _, _) = setup_hierarchy();
+        stepper
+            .server_app
+            .world
+            .entity_mut(grandparent)
+            .insert(Replicate::default());
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .query::<&Replicated>()
+                .iter(&stepper.client_app.world)
+                .count(),
+            3
+        );
+
+        stepper
+            .server_app
+            .world
+            .entity_mut(grandparent)
+            .despawn_recursive();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .query::<&Replicated>()
+                .iter(&stepper.client_app.world)
+                .count(),
+            0
+        );
+        assert!(stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(grandparent)
+            .is_none());
+    }
+
+    /// The hierarchy of predicted entities is mapped to the predicted entities, if enabled
+    #[test]
+    fn test_propagate_hierarchy_prediction() {
+        let (mut stepper, grandparent, _, _) = setup_hierarchy();
+
+        stepper
+            .server_app
+            .world
+            .entity_mut(grandparent)
+            .insert(Replicate {
+                sync: SyncTarget {
+                    prediction: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            });
+        for _ in 0..4 {
+            stepper.frame_step();
+        }
+        // the hierarchy is not synced to the predicted entities by default
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .query_filtered::<&Parent, With<Predicted>>()
+                .iter(&stepper.client_app.world)
+                .count(),
+            0
+        );
+
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ClientConfig>()
+            .prediction
+            .predict_hierarchy = true;
+        stepper.frame_step();
+        let predicted_parent = stepper
+            .client_app
+            .world
+            .query_filtered::<&Confirmed, With<Component2>>()
+            .get_single(&stepper.client_app.world)
+            .unwrap()
+            .predicted
+            .unwrap();
+        let predicted_child = stepper
+            .client_app
+            .world
+            .query_filtered::<(Entity, &Parent), With<Predicted>>()
+            .iter(&stepper.client_app.world)
+            .find(|(_, parent)| parent.get() == predicted_parent)
+            .map(|(entity, _)| entity)
+            .expect("the predicted child should be attached to the predicted parent");
+        let predicted_grandparent = stepper
+            .client_app
+            .world
+            .get::<Parent>(predicted_parent)
+            .unwrap()
+            .get();
+        assert!(stepper
+            .client_app
+            .world
+            .get::<Predicted>(predicted_grandparent)
+            .is_some());
+        assert!(stepper
+            .client_app
+            .world
+            .get::<Predicted>(predicted_child)
+            .is_some());
+    }
 }
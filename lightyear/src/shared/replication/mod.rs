@@ -6,10 +6,11 @@ use anyhow::Result;
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::entity::EntityHashMap;
 use bevy::prelude::{Component, Entity, Resource};
-use bevy::reflect::Map;
-use bevy::utils::HashSet;
+use bevy::reflect::{Map, Reflect};
+use bevy::utils::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 
+use bitcode::encoding::Fixed;
 use bitcode::{Decode, Encode};
 use network_target::NetworkTarget;
 
@@ -20,7 +21,9 @@ use crate::prelude::{ReplicationGroup, Tick};
 use crate::protocol::component::{ComponentNetId, ComponentRegistry};
 use crate::protocol::registry::NetId;
 use crate::protocol::EventContext;
+use crate::serialize::bitcode::reader::BitcodeReader;
 use crate::serialize::bitcode::writer::BitcodeWriter;
+use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::serialize::RawData;
 use crate::shared::events::connection::{
@@ -29,7 +32,9 @@ use crate::shared::events::connection::{
 };
 use crate::shared::replication::components::{ReplicationGroupId, ReplicationTarget};
 
+pub(crate) mod commands;
 pub mod components;
+pub mod delta;
 
 pub mod entity_map;
 pub(crate) mod hierarchy;
@@ -55,9 +60,11 @@ pub struct EntityActions {
 pub(crate) enum SpawnAction {
     None,
     Spawn,
-    Despawn,
+    Despawn(Option<DespawnReason>),
     // the u64 is the entity's bits (we cannot use Entity directly because it doesn't implement Encode/Decode)
     Reuse(u64),
+    // link the entity to the remote entity that has this NetworkId (spawn a new entity if there is none)
+    Link(u64),
 }
 
 impl Default for EntityActions {
@@ -79,6 +86,130 @@ pub struct EntityActionMessage {
     #[bitcode(with_serde)]
     // we use vec but the order of entities should not matter
     pub(crate) actions: Vec<(Entity, EntityActions)>,
+    /// Entity spawns that are sent in a compact form, see [`EntityActionMessage::batch_spawns`]
+    #[bitcode(with_serde)]
+    pub(crate) spawn_batches: Vec<SpawnBatch>,
+}
+
+/// Spawns of several entities of the same group that have the same components.
+///
+/// The components are listed once in the layout of the batch instead of being repeated for every entity.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub(crate) struct SpawnBatch {
+    /// The components that all the entities of the batch have
+    layout: Vec<ComponentNetId>,
+    /// The spawned entities, along with the values of their components in the order of the layout
+    /// (without the [`ComponentNetId`] that starts every serialized component)
+    entities: Vec<(Entity, Vec<RawData>)>,
+}
+
+/// Minimum number of entities with the same components to send their spawns in a [`SpawnBatch`]
+const MIN_SPAWN_BATCH_SIZE: usize = 2;
+
+/// Number of bytes taken by the [`ComponentNetId`] at the start of a serialized component
+const COMPONENT_NET_ID_BYTES: usize = std::mem::size_of::<ComponentNetId>();
+
+impl EntityActionMessage {
+    pub(crate) fn new(sequence_id: MessageId, actions: Vec<(Entity, EntityActions)>) -> Self {
+        Self {
+            sequence_id,
+            actions,
+            spawn_batches: Vec::new(),
+        }
+    }
+
+    /// Move the spawns of the entities that only have component inserts into [`SpawnBatch`]es,
+    /// grouping the entities that have the same components.
+    pub(crate) fn batch_spawns(mut self) -> Self {
+        let mut layouts: HashMap<Vec<ComponentNetId>, Vec<usize>> = HashMap::default();
+        for (index, (_, actions)) in self.actions.iter().enumerate() {
+            if actions.spawn != SpawnAction::Spawn
+                || actions.insert.is_empty()
+                || !actions.remove.is_empty()
+                || !actions.updates.is_empty()
+            {
+                continue;
+            }
+            let Some(layout) = actions
+                .insert
+                .iter()
+                .map(component_net_id)
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            layouts.entry(layout).or_default().push(index);
+        }
+        let mut batched = vec![false; self.actions.len()];
+        for (layout, indices) in layouts {
+            if indices.len() < MIN_SPAWN_BATCH_SIZE {
+                continue;
+            }
+            let entities = indices
+                .into_iter()
+                .map(|index| {
+                    batched[index] = true;
+                    let (entity, actions) = &mut self.actions[index];
+                    let values = std::mem::take(&mut actions.insert)
+                        .into_iter()
+                        .map(|mut raw| {
+                            raw.drain(..COMPONENT_NET_ID_BYTES);
+                            raw
+                        })
+                        .collect();
+                    (*entity, values)
+                })
+                .collect();
+            self.spawn_batches.push(SpawnBatch { layout, entities });
+        }
+        let mut batched = batched.into_iter();
+        self.actions.retain(|_| !batched.next().unwrap());
+        self
+    }
+
+    /// Expand the [`SpawnBatch`]es back into regular entity spawns
+    pub(crate) fn unbatch_spawns(&mut self) {
+        for batch in std::mem::take(&mut self.spawn_batches) {
+            let headers: Vec<RawData> = batch
+                .layout
+                .iter()
+                .map(|net_id| component_net_id_header(*net_id))
+                .collect();
+            for (entity, values) in batch.entities {
+                let insert = headers
+                    .iter()
+                    .zip(values)
+                    .map(|(header, value)| {
+                        let mut raw = header.clone();
+                        raw.extend_from_slice(&value);
+                        raw
+                    })
+                    .collect();
+                self.actions.push((
+                    entity,
+                    EntityActions {
+                        spawn: SpawnAction::Spawn,
+                        insert,
+                        ..Default::default()
+                    },
+                ));
+            }
+        }
+    }
+}
+
+/// Read the [`ComponentNetId`] at the start of a serialized component
+fn component_net_id(raw: &RawData) -> Option<ComponentNetId> {
+    let header = raw.get(..COMPONENT_NET_ID_BYTES)?;
+    let mut reader = BitcodeReader::start_read(header);
+    reader.decode::<ComponentNetId>(Fixed).ok()
+}
+
+/// The bytes that start every serialized component with the given [`ComponentNetId`]
+fn component_net_id_header(net_id: ComponentNetId) -> RawData {
+    let mut writer = BitcodeWriter::with_capacity(COMPONENT_NET_ID_BYTES);
+    let _ = writer.encode(&net_id, Fixed);
+    writer.finish_write().to_vec()
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Encode, Decode)]
@@ -91,12 +222,184 @@ pub struct EntityUpdatesMessage {
     pub(crate) updates: Vec<(Entity, Vec<RawData>)>,
 }
 
+/// Code sent along with an entity despawn, to let the remote know why the entity was despawned
+/// (for example to play a different effect on the client). The meaning of the codes is up to the game,
+/// except for [`DespawnReason::LOST_VISIBILITY`].
+///
+/// Games usually define their own reason enum and convert it to and from a [`DespawnReason`]:
+/// ```rust,ignore
+/// enum MyDespawnReason {
+///     Killed,
+///     OutOfRange,
+///     RoundReset,
+/// }
+///
+/// impl From<MyDespawnReason> for DespawnReason {
+///     fn from(reason: MyDespawnReason) -> Self {
+///         DespawnReason(reason as u16)
+///     }
+/// }
+///
+/// impl TryFrom<DespawnReason> for MyDespawnReason {
+///     type Error = ();
+///     fn try_from(reason: DespawnReason) -> Result<Self, ()> {
+///         match reason.0 {
+///             0 => Ok(MyDespawnReason::Killed),
+///             1 => Ok(MyDespawnReason::OutOfRange),
+///             2 => Ok(MyDespawnReason::RoundReset),
+///             _ => Err(()),
+///         }
+///     }
+/// }
+///
+/// // on the server
+/// commands.entity(entity).despawn_with_reason(MyDespawnReason::Killed);
+/// // on the client
+/// if let Some(MyDespawnReason::Killed) = event.reason_as::<MyDespawnReason>() {
+///     // play the death animation
+/// }
+/// ```
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Encode, Decode, Reflect,
+)]
+pub struct DespawnReason(pub u16);
+
+impl DespawnReason {
+    /// Reason sent by the server when the entity still exists, but is not replicated to the client anymore
+    /// (for example because the client lost visibility of the entity)
+    pub const LOST_VISIBILITY: Self = Self(u16::MAX);
+}
+
+/// What to do with the component updates received for an entity that doesn't exist locally.
+///
+/// This can happen if the updates arrive before the spawn of the entity, or after its despawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub enum UnknownEntityUpdatePolicy {
+    /// Drop the updates
+    #[default]
+    Drop,
+    /// Keep the updates for up to `ticks` ticks, and apply them if the entity gets spawned in the meantime.
+    /// The updates are dropped after that.
+    Buffer { ticks: u16 },
+    /// Drop the updates and emit an `UnknownEntityUpdateEvent`
+    Event,
+    /// Drop the updates and ask the server to send the entity again (its spawn and the current value
+    /// of all its replicated components).
+    ///
+    /// Only the client can request a resend: on the server this behaves like [`UnknownEntityUpdatePolicy::Drop`]
+    RequestResend,
+}
+
+/// Number of component updates received for entities that didn't exist locally
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UnknownEntityUpdateStats {
+    /// Updates that were dropped (including the buffered updates whose entity was never spawned)
+    pub dropped: u64,
+    /// Updates that were buffered with [`UnknownEntityUpdatePolicy::Buffer`]
+    pub buffered: u64,
+    /// Buffered updates that were applied once their entity was spawned
+    pub applied: u64,
+    /// Entities that the server was asked to send again with [`UnknownEntityUpdatePolicy::RequestResend`]
+    pub resend_requested: u64,
+}
+
+/// Set of up to 64 despawned entities whose bits are close to each other
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Encode, Decode)]
+pub(crate) struct DespawnBlock {
+    /// The bits of the first entity of the block
+    start: u64,
+    /// Bit `i` is set if the entity with bits `start + i` is despawned
+    mask: u64,
+}
+
+/// Despawns of many entities that are each the only entity of their replication group,
+/// so that a mass despawn doesn't require one message per entity
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Encode, Decode)]
+pub struct EntityDespawnsMessage {
+    reason: Option<DespawnReason>,
+    /// The despawned entities. Entities that are spawned together usually have consecutive indices,
+    /// so they can be stored compactly in bitsets
+    blocks: Vec<DespawnBlock>,
+    /// The action sequence id of the replication group of each entity, in the same order as the entities in `blocks`
+    sequence_ids: Vec<MessageId>,
+}
+
+impl EntityDespawnsMessage {
+    pub(crate) fn new(
+        reason: Option<DespawnReason>,
+        mut despawns: Vec<(Entity, MessageId)>,
+    ) -> Self {
+        despawns.sort_unstable_by_key(|(entity, _)| entity.to_bits());
+        let mut blocks: Vec<DespawnBlock> = Vec::new();
+        let mut sequence_ids = Vec::with_capacity(despawns.len());
+        for (entity, sequence_id) in despawns {
+            let bits = entity.to_bits();
+            match blocks.last_mut() {
+                Some(block) if bits - block.start < u64::BITS as u64 => {
+                    block.mask |= 1 << (bits - block.start);
+                }
+                _ => blocks.push(DespawnBlock {
+                    start: bits,
+                    mask: 1,
+                }),
+            }
+            sequence_ids.push(sequence_id);
+        }
+        Self {
+            reason,
+            blocks,
+            sequence_ids,
+        }
+    }
+
+    /// Iterate through the despawned entities, along with the action sequence id of their replication group
+    pub(crate) fn despawns(&self) -> impl Iterator<Item = (Entity, MessageId)> + '_ {
+        self.blocks
+            .iter()
+            .flat_map(|block| {
+                (0..u64::BITS as u64)
+                    .filter(|i| block.mask & (1 << i) != 0)
+                    .map(|i| Entity::from_bits(block.start + i))
+            })
+            .zip(self.sequence_ids.iter().copied())
+    }
+}
+
+/// Sent when the server resets the replicated world: the remote despawns all the entities it received,
+/// then receives the entities that were kept again
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Encode, Decode)]
+pub struct WorldResetMessage {
+    /// The next action sequence id of every replication group at the time of the reset.
+    /// The actions with an older sequence id were sent before the reset and must be ignored
+    pub(crate) sequence_ids: Vec<(ReplicationGroupId, MessageId)>,
+}
+
+/// Full state of the replicated world, sent to a client that connects while entities are already replicated
+/// so that the client receives all of them at once instead of one replication group at a time.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Encode, Decode)]
+pub struct WorldSnapshotMessage {
+    /// The compressed [`WorldSnapshot`]
+    pub(crate) data: Vec<u8>,
+}
+
+/// The entity actions of every replication group contained in a [`WorldSnapshotMessage`]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Encode, Decode)]
+pub(crate) struct WorldSnapshot {
+    pub(crate) groups: Vec<(ReplicationGroupId, EntityActionMessage)>,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Encode, Decode)]
 pub enum ReplicationMessageData {
     /// All the entity actions (Spawn/despawn/inserts/removals) for a given group
     Actions(EntityActionMessage),
     /// All the entity updates for a given group
     Updates(EntityUpdatesMessage),
+    /// The despawns of entities from many groups (the group id of the message is not used)
+    Despawns(EntityDespawnsMessage),
+    /// Reset of the replicated world (the group id of the message is not used)
+    Reset(WorldResetMessage),
+    /// Snapshot of the replicated world for a client that just connected (the group id of the message is not used)
+    Snapshot(WorldSnapshotMessage),
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Encode, Decode)]
@@ -1,6 +1,7 @@
 //! This module contains the `ReplicationReceivePlugin` and `ReplicationSendPlugin` plugins, which control
 //! the replication of entities and resources.
 //!
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::replication::hierarchy::{HierarchyReceivePlugin, HierarchySendPlugin};
 use crate::shared::replication::resources::{
     receive::ResourceReceivePlugin, send::ResourceSendPlugin,
@@ -49,7 +50,6 @@ pub(crate) mod receive {
 
 pub(crate) mod send {
     use super::*;
-    use crate::prelude::server::ServerReplicationSet;
 
     pub(crate) struct ReplicationSendPlugin<R> {
         clean_interval: Duration,
@@ -75,15 +75,16 @@ pub(crate) mod send {
                 .add_plugins(HierarchySendPlugin::<R>::default());
 
             // SETS
+            let schedules = NetworkScheduleConfig::get(app);
             app.configure_sets(
-                PostUpdate,
+                schedules.send,
                 (
                     InternalMainSet::<R::SetMarker>::SendPackets.in_set(MainSet::SendPackets),
                     InternalMainSet::<R::SetMarker>::Send.in_set(MainSet::Send),
                 ),
             );
             app.configure_sets(
-                PostUpdate,
+                schedules.send,
                 (
                     (
                         InternalReplicationSet::<R::SetMarker>::BeforeBuffer,
@@ -134,13 +135,15 @@ pub(crate) mod send {
 }
 
 pub(crate) mod shared {
+    #[cfg(feature = "client")]
     use crate::client::replication::send::ReplicateToServer;
     use crate::prelude::{
         PrePredicted, RemoteEntityMap, ReplicationGroup, ShouldBePredicted, TargetEntity,
         VisibilityMode,
     };
     use crate::shared::replication::components::{
-        Replicating, ReplicationGroupId, ReplicationGroupIdBuilder, ShouldBeInterpolated,
+        NetworkId, Replicating, ReplicationGroupId, ReplicationGroupIdBuilder, ReplicationPaused,
+        ShouldBeInterpolated,
     };
     use crate::shared::replication::entity_map::{InterpolatedEntityMap, PredictedEntityMap};
     use crate::shared::replication::network_target::NetworkTarget;
@@ -152,8 +155,9 @@ pub(crate) mod shared {
         fn build(&self, app: &mut App) {
             // REFLECTION
             app.register_type::<TargetEntity>()
+                .register_type::<NetworkId>()
                 .register_type::<Replicating>()
-                .register_type::<ReplicateToServer>()
+                .register_type::<ReplicationPaused>()
                 .register_type::<ReplicationGroupIdBuilder>()
                 .register_type::<ReplicationGroup>()
                 .register_type::<ReplicationGroupId>()
@@ -165,6 +169,8 @@ pub(crate) mod shared {
                 .register_type::<RemoteEntityMap>()
                 .register_type::<PredictedEntityMap>()
                 .register_type::<InterpolatedEntityMap>();
+            #[cfg(feature = "client")]
+            app.register_type::<ReplicateToServer>();
         }
     }
 }
@@ -7,21 +7,25 @@ use bevy::ecs::entity::{EntityHash, MapEntities};
 use bevy::prelude::{DespawnRecursiveExt, Entity, World};
 use bevy::reflect::Reflect;
 use bevy::utils::HashSet;
+use bitcode::encoding::Fixed;
 use tracing::{debug, error, info, trace, trace_span, warn};
 
+use crate::client::components::Confirmed;
+use crate::packet::compression::PacketDecompressor;
 use crate::packet::message::MessageId;
-use crate::prelude::client::Confirmed;
 use crate::prelude::{ClientId, Tick};
-use crate::protocol::component::ComponentRegistry;
+use crate::protocol::component::{ComponentRegistry, ProtocolVersion};
 use crate::serialize::bitcode::reader::BitcodeReader;
 use crate::serialize::reader::ReadBuffer;
+use crate::serialize::RawData;
 use crate::shared::events::connection::ConnectionEvents;
-use crate::shared::replication::components::{Replicated, ReplicationGroupId};
+use crate::shared::replication::components::{NetworkId, Replicated, ReplicationGroupId};
 
-use super::entity_map::RemoteEntityMap;
+use super::entity_map::{PrePlacedEntities, RemoteEntityMap};
 use super::{
-    EntityActionMessage, EntityUpdatesMessage, ReplicationMessage, ReplicationMessageData,
-    SpawnAction,
+    EntityActionMessage, EntityActions, EntityUpdatesMessage, ReplicationMessage,
+    ReplicationMessageData, SpawnAction, UnknownEntityUpdatePolicy, UnknownEntityUpdateStats,
+    WorldResetMessage, WorldSnapshot, WorldSnapshotMessage,
 };
 
 type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
@@ -39,6 +43,54 @@ pub(crate) struct ReplicationReceiver {
     // BOTH
     /// Buffer to so that we have an ordered receiver per group
     pub group_channels: EntityHashMap<ReplicationGroupId, GroupChannel>,
+
+    /// Local entities that must be despawned because the remote reset the replicated world
+    pending_world_reset_despawns: Vec<Entity>,
+
+    /// Version of the protocol used by the remote, if it is older than ours.
+    /// The received components are upgraded to our version.
+    pub(crate) remote_protocol_version: Option<ProtocolVersion>,
+
+    /// What to do with the updates received for entities that don't exist locally
+    pub(crate) unknown_entity_policy: UnknownEntityUpdatePolicy,
+    /// Updates that are waiting for their entity to be spawned
+    unknown_entity_updates: Vec<UnknownEntityUpdate>,
+    /// Remote entity and tick of the updates that must be emitted as `UnknownEntityUpdateEvent`s
+    unknown_entity_events: Vec<(Entity, Tick)>,
+    pub(crate) unknown_entity_stats: UnknownEntityUpdateStats,
+    /// Remote entities that the remote must send again, with [`UnknownEntityUpdatePolicy::RequestResend`]
+    resend_requests: Vec<Entity>,
+    /// Remote entities for which a resend was requested, and that were not spawned since.
+    /// We only request each entity once, even if we keep receiving updates for it
+    requested_resends: EntityHashSet<Entity>,
+    /// Local tick of the latest call to `read_messages`
+    current_tick: Tick,
+    /// Decompresses the world snapshots sent by the remote
+    snapshot_decompressor: PacketDecompressor,
+    /// Remote entities that were kept from the previous connection and that we expect the remote to send again
+    /// in its world snapshot
+    stale_entities: EntityHashSet<Entity>,
+    /// Remote entities of the previous connection that were included in the world snapshot.
+    /// Their spawn re-attaches them to the existing local entity
+    reattached_entities: EntityHashSet<Entity>,
+    /// [`NetworkId`]s that the world snapshot links again after a reconnection. The local entities of the
+    /// previous connection that have one of them are linked again instead of being despawned (the remote
+    /// entity changed, for example because the server restarted and loaded its saved world)
+    relinked_network_ids: HashSet<NetworkId>,
+}
+
+/// Maximum size of a world snapshot once decompressed
+const MAX_DECOMPRESSED_SNAPSHOT_SIZE: usize = 64 * 1024 * 1024;
+
+/// Component updates received for a remote entity that doesn't exist locally
+#[derive(Debug)]
+struct UnknownEntityUpdate {
+    remote_entity: Entity,
+    /// Remote tick of the update
+    tick: Tick,
+    /// Local tick at which the update was buffered
+    buffered_at: Tick,
+    components: Vec<RawData>,
 }
 
 impl ReplicationReceiver {
@@ -50,20 +102,86 @@ impl ReplicationReceiver {
             remote_entity_to_group: Default::default(),
             // BOTH
             group_channels: Default::default(),
+            pending_world_reset_despawns: Vec::new(),
+            remote_protocol_version: None,
+            unknown_entity_policy: UnknownEntityUpdatePolicy::default(),
+            unknown_entity_updates: Vec::new(),
+            unknown_entity_events: Vec::new(),
+            unknown_entity_stats: UnknownEntityUpdateStats::default(),
+            resend_requests: Vec::new(),
+            requested_resends: EntityHashSet::default(),
+            current_tick: Tick(0),
+            snapshot_decompressor: PacketDecompressor::default(),
+            stale_entities: EntityHashSet::default(),
+            reattached_entities: EntityHashSet::default(),
+            relinked_network_ids: HashSet::default(),
         }
     }
 
+    /// Keep the entity mapping of a previous connection, so that the entities that the remote sends again
+    /// are re-attached to the existing local entities instead of being spawned again.
+    ///
+    /// The entities that are not part of the next world snapshot are despawned.
+    pub(crate) fn reattach(&mut self, previous: RemoteEntityMap) {
+        self.stale_entities = previous.remote_to_local.keys().copied().collect();
+        self.remote_entity_map = previous;
+    }
+
+    /// Take the remote entity and tick of the updates received for entities that don't exist locally,
+    /// when the policy is [`UnknownEntityUpdatePolicy::Event`]
+    pub(crate) fn drain_unknown_entity_events(&mut self) -> Vec<(Entity, Tick)> {
+        std::mem::take(&mut self.unknown_entity_events)
+    }
+
+    /// Take the remote entities that the remote must send again,
+    /// when the policy is [`UnknownEntityUpdatePolicy::RequestResend`]
+    pub(crate) fn drain_resend_requests(&mut self) -> Vec<Entity> {
+        std::mem::take(&mut self.resend_requests)
+    }
+
     /// Recv a new replication message and buffer it
     pub(crate) fn recv_message(&mut self, message: ReplicationMessage, remote_tick: Tick) {
         trace!(?message, ?remote_tick, "Received replication message");
+        if let ReplicationMessageData::Despawns(m) = message.data {
+            // split the batch into one action message per group, so that each despawn is applied
+            // in order with the other actions of its group
+            for (entity, sequence_id) in m.despawns() {
+                self.recv_message(
+                    ReplicationMessage {
+                        group_id: ReplicationGroupId(entity.to_bits()),
+                        data: ReplicationMessageData::Actions(EntityActionMessage::new(
+                            sequence_id,
+                            vec![(
+                                entity,
+                                EntityActions {
+                                    spawn: SpawnAction::Despawn(m.reason),
+                                    ..Default::default()
+                                },
+                            )],
+                        )),
+                    },
+                    remote_tick,
+                );
+            }
+            return;
+        }
+        if let ReplicationMessageData::Reset(m) = message.data {
+            self.recv_world_reset(m, remote_tick);
+            return;
+        }
+        if let ReplicationMessageData::Snapshot(m) = message.data {
+            self.recv_world_snapshot(m, remote_tick);
+            return;
+        }
         let channel = self.group_channels.entry(message.group_id).or_default();
         match message.data {
-            ReplicationMessageData::Actions(m) => {
+            ReplicationMessageData::Actions(mut m) => {
                 // if the message is too old, ignore it
                 if m.sequence_id < channel.actions_pending_recv_message_id {
                     trace!(message_id= ?m.sequence_id, pending_message_id = ?channel.actions_pending_recv_message_id, "message is too old, ignored");
                     return;
                 }
+                m.unbatch_spawns();
                 // update the list of entities in the group
                 m.actions
                     .iter()
@@ -106,10 +224,137 @@ impl ReplicationReceiver {
                     }
                 };
             }
+            // batched despawns, resets and snapshots were handled above
+            ReplicationMessageData::Despawns(_)
+            | ReplicationMessageData::Reset(_)
+            | ReplicationMessageData::Snapshot(_) => {}
         }
         trace!(?channel, "group channel after buffering");
     }
 
+    /// The remote reset the replicated world: forget about all the entities received so far,
+    /// and ignore the replication messages that were sent before the reset
+    fn recv_world_reset(&mut self, message: WorldResetMessage, remote_tick: Tick) {
+        debug!(?remote_tick, "Received world reset");
+        for (group_id, sequence_id) in message.sequence_ids {
+            let channel = self.group_channels.entry(group_id).or_default();
+            channel.actions_pending_recv_message_id = sequence_id;
+            channel
+                .actions_recv_message_buffer
+                .retain(|message_id, _| *message_id >= sequence_id);
+        }
+        for channel in self.group_channels.values_mut() {
+            channel.remote_entities.clear();
+            channel.buffered_updates_with_last_action_tick.clear();
+            channel.buffered_updates_without_last_action_tick.clear();
+            // updates that were sent before the reset will be ignored
+            channel.latest_tick = Some(remote_tick);
+        }
+        self.pending_world_reset_despawns
+            .extend(self.remote_entity_map.local_to_remote.keys().copied());
+        self.remote_entity_map.clear();
+        self.remote_entity_to_group.clear();
+        self.stale_entities.clear();
+        self.reattached_entities.clear();
+        self.relinked_network_ids.clear();
+    }
+
+    /// The remote sent the state of the whole replicated world.
+    ///
+    /// The snapshot is split into one action message per group. Since they all have the same remote tick,
+    /// they are all applied during the same frame.
+    fn recv_world_snapshot(&mut self, message: WorldSnapshotMessage, remote_tick: Tick) {
+        let snapshot = self
+            .snapshot_decompressor
+            .decompress_with_max_size(&message.data, MAX_DECOMPRESSED_SNAPSHOT_SIZE)
+            .and_then(|data| {
+                let mut reader = BitcodeReader::start_read(&data);
+                reader.decode::<WorldSnapshot>(Fixed)
+            });
+        let mut snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                error!("could not read the world snapshot: {e:?}");
+                return;
+            }
+        };
+        debug!(
+            ?remote_tick,
+            num_groups = snapshot.groups.len(),
+            "Received world snapshot"
+        );
+        snapshot
+            .groups
+            .iter_mut()
+            .for_each(|(_, actions)| actions.unbatch_spawns());
+        if !self.stale_entities.is_empty() {
+            let snapshot_entities: EntityHashSet<Entity> = snapshot
+                .groups
+                .iter()
+                .flat_map(|(_, actions)| actions.actions.iter())
+                .filter(|(_, actions)| {
+                    matches!(actions.spawn, SpawnAction::Spawn | SpawnAction::Link(_))
+                })
+                .map(|(remote_entity, _)| *remote_entity)
+                .collect();
+            self.relinked_network_ids = snapshot
+                .groups
+                .iter()
+                .flat_map(|(_, actions)| actions.actions.iter())
+                .filter_map(|(_, actions)| match actions.spawn {
+                    SpawnAction::Link(network_id) => Some(NetworkId::from_bits(network_id)),
+                    _ => None,
+                })
+                .collect();
+            for remote_entity in std::mem::take(&mut self.stale_entities) {
+                if snapshot_entities.contains(&remote_entity) {
+                    self.reattached_entities.insert(remote_entity);
+                } else if let Some(local_entity) =
+                    self.remote_entity_map.remove_by_remote(remote_entity)
+                {
+                    debug!(
+                        ?remote_entity,
+                        "entity of the previous connection is not in the world snapshot"
+                    );
+                    self.pending_world_reset_despawns.push(local_entity);
+                }
+            }
+        }
+        for (group_id, actions) in snapshot.groups {
+            self.recv_message(
+                ReplicationMessage {
+                    group_id,
+                    data: ReplicationMessageData::Actions(actions),
+                },
+                remote_tick,
+            );
+        }
+    }
+
+    /// Despawn the local entities that were received before the remote reset the replicated world,
+    /// or that were not re-attached after a reconnection
+    pub(crate) fn apply_world_reset(&mut self, world: &mut World, events: &mut ConnectionEvents) {
+        let relinked_network_ids = std::mem::take(&mut self.relinked_network_ids);
+        for local_entity in self.pending_world_reset_despawns.drain(..) {
+            let Some(entity_mut) = world.get_entity_mut(local_entity) else {
+                continue;
+            };
+            // the entity is linked again by the world snapshot
+            if entity_mut
+                .get::<NetworkId>()
+                .is_some_and(|network_id| relinked_network_ids.contains(network_id))
+            {
+                debug!(
+                    ?local_entity,
+                    "Keeping entity that is linked again by its NetworkId"
+                );
+                continue;
+            }
+            entity_mut.despawn_recursive();
+            events.push_despawn(local_entity, None);
+        }
+    }
+
     /// Return the list of replication messages that are ready to be applied to the World
     /// Also include the server_tick when that replication message was emitted
     ///
@@ -125,6 +370,19 @@ impl ReplicationReceiver {
         current_tick: Tick,
     ) -> Vec<(ReplicationGroupId, Vec<(Tick, ReplicationMessageData)>)> {
         trace!(?current_tick, ?self.group_channels, "reading replication messages");
+        self.current_tick = current_tick;
+        // drop the buffered updates whose entity was not spawned in time
+        if let UnknownEntityUpdatePolicy::Buffer { ticks } = self.unknown_entity_policy {
+            let stats = &mut self.unknown_entity_stats;
+            self.unknown_entity_updates.retain(|update| {
+                let expired = (current_tick - update.buffered_at) as i32 > ticks as i32;
+                if expired {
+                    debug!(remote_entity = ?update.remote_entity, "dropping buffered update for entity that was never spawned");
+                    stats.dropped += 1;
+                }
+                !expired
+            });
+        }
         self.group_channels
             .iter_mut()
             .filter_map(|(group_id, channel)| {
@@ -199,6 +457,35 @@ impl ReplicationReceiver {
     //  use a OnceCell that gets set with the channel name mapping when the protocol is finalized?
     //  the other option is to have wrappers in Connection, but that's pretty ugly
 
+    /// Apply to one of our own entities the value of a component that the remote corrected
+    /// (because our value didn't pass the validation of the remote)
+    pub(crate) fn apply_component_correction(
+        &mut self,
+        world: &mut World,
+        component_registry: &ComponentRegistry,
+        entity: Entity,
+        component: &RawData,
+        events: &mut ConnectionEvents,
+    ) {
+        let Some(mut entity_mut) = world.get_entity_mut(entity) else {
+            debug!(
+                ?entity,
+                "received a component correction for an entity that doesn't exist"
+            );
+            return;
+        };
+        self.reader.reset_read(component.as_slice());
+        let _ = component_registry
+            .raw_write(
+                &mut self.reader,
+                self.remote_protocol_version,
+                &mut entity_mut,
+                &mut self.remote_entity_map.remote_to_local,
+                events,
+            )
+            .inspect_err(|e| error!("could not apply the component correction: {:?}", e));
+    }
+
     /// Apply any replication messages to the world, and emit an event
     /// I think we don't need to emit a tick with the event anymore, because
     /// we can access the tick via the replication manager
@@ -227,6 +514,11 @@ impl ReplicationReceiver {
                     match actions.spawn {
                         SpawnAction::Spawn => {
                             self.remote_entity_to_group.insert(*remote_entity, group_id);
+                            self.requested_resends.remove(remote_entity);
+                            if self.reattached_entities.remove(remote_entity) {
+                                debug!(?remote_entity, "Re-attached entity after reconnection");
+                                continue;
+                            }
                             if let Some(local_entity) =
                                 self.remote_entity_map.get_local(*remote_entity)
                             {
@@ -270,6 +562,53 @@ impl ReplicationReceiver {
                             // update the entity mapping
                             self.remote_entity_map.insert(*remote_entity, local_entity);
                         }
+                        SpawnAction::Link(network_id) => {
+                            self.remote_entity_to_group.insert(*remote_entity, group_id);
+                            self.requested_resends.remove(remote_entity);
+                            if self.reattached_entities.remove(remote_entity) {
+                                debug!(?remote_entity, "Re-attached entity after reconnection");
+                                continue;
+                            }
+                            if self.remote_entity_map.get_local(*remote_entity).is_some() {
+                                warn!("Received link for an entity that is already in our entity mapping! Not linking");
+                                continue;
+                            }
+                            // only the server can link the entities of the remote to pre-placed entities,
+                            // otherwise a client could take over any entity of the server that has a NetworkId
+                            if remote.is_some() {
+                                warn!(?remote_entity, ?remote, "Received a link to a pre-placed entity from a client, spawning a new entity instead");
+                                let local_entity = world.spawn(Replicated { from: remote }).id();
+                                self.remote_entity_map.insert(*remote_entity, local_entity);
+                                events.push_spawn(local_entity);
+                                continue;
+                            }
+                            let network_id = NetworkId::from_bits(network_id);
+                            let pre_placed = world
+                                .get_resource::<PrePlacedEntities>()
+                                .and_then(|pre_placed| pre_placed.get(network_id))
+                                .filter(|local_entity| world.get_entity(*local_entity).is_some());
+                            let local_entity = match pre_placed {
+                                Some(local_entity) => {
+                                    debug!(
+                                        ?remote_entity,
+                                        ?local_entity,
+                                        ?network_id,
+                                        "Linked entity to the pre-placed entity"
+                                    );
+                                    world
+                                        .entity_mut(local_entity)
+                                        .insert(Replicated { from: remote });
+                                    local_entity
+                                }
+                                None => {
+                                    debug!(?remote_entity, ?network_id, "No pre-placed entity for the NetworkId, spawning a new entity");
+                                    // keep the NetworkId so that the entity can be linked again after a reconnection
+                                    world.spawn((Replicated { from: remote }, network_id)).id()
+                                }
+                            };
+                            self.remote_entity_map.insert(*remote_entity, local_entity);
+                            events.push_spawn(local_entity);
+                        }
                         _ => {}
                     }
                 }
@@ -278,7 +617,7 @@ impl ReplicationReceiver {
                     debug!(remote_entity = ?entity, "Received entity actions");
 
                     // despawn
-                    if actions.spawn == SpawnAction::Despawn {
+                    if let SpawnAction::Despawn(reason) = actions.spawn {
                         debug!(remote_entity = ?entity, "Received entity despawn");
                         if let Some(local_entity) = self.remote_entity_map.remove_by_remote(entity)
                         {
@@ -289,7 +628,7 @@ impl ReplicationReceiver {
                             if let Some(entity_mut) = world.get_entity_mut(local_entity) {
                                 entity_mut.despawn_recursive();
                             }
-                            events.push_despawn(local_entity);
+                            events.push_despawn(local_entity, reason);
                             self.remote_entity_to_group.remove(&entity);
                         } else {
                             error!("Received despawn for an entity that does not exist")
@@ -322,6 +661,7 @@ impl ReplicationReceiver {
                         let _ = component_registry
                             .raw_write(
                                 &mut self.reader,
+                                self.remote_protocol_version,
                                 &mut local_entity_mut,
                                 &mut self.remote_entity_map.remote_to_local,
                                 events,
@@ -360,6 +700,7 @@ impl ReplicationReceiver {
                         let _ = component_registry
                             .raw_write(
                                 &mut self.reader,
+                                self.remote_protocol_version,
                                 &mut local_entity_mut,
                                 &mut self.remote_entity_map.remote_to_local,
                                 events,
@@ -383,6 +724,7 @@ impl ReplicationReceiver {
                             let _ = component_registry
                                 .raw_write(
                                     &mut self.reader,
+                                    self.remote_protocol_version,
                                     &mut local_entity_mut,
                                     &mut self.remote_entity_map.remote_to_local,
                                     events,
@@ -394,11 +736,28 @@ impl ReplicationReceiver {
                     } else {
                         // we can get a few buffered updates after the entity has been despawned
                         // those are the updates that we received before the despawn action message, but with a tick
-                        // later than the despawn action message
-                        debug!("update for entity that doesn't exist?");
+                        // later than the despawn action message.
+                        // We can also get updates before the spawn of the entity.
+                        self.handle_unknown_entity_update(entity, tick, components);
                     }
                 }
             }
+            ReplicationMessageData::Despawns(_) => {
+                error!(
+                    "batched despawns should have been split into action messages when received"
+                );
+            }
+            ReplicationMessageData::Reset(_) => {
+                error!("world resets should have been handled when received");
+            }
+            ReplicationMessageData::Snapshot(_) => {
+                error!("world snapshots should have been split into action messages when received");
+            }
+        }
+
+        // apply the buffered updates whose entity was just spawned
+        if !self.unknown_entity_updates.is_empty() {
+            self.apply_unknown_entity_updates(world, component_registry, tick, events);
         }
 
         // update the Confirmed tick for all entities in the replication group
@@ -422,6 +781,91 @@ impl ReplicationReceiver {
     }
 }
 
+impl ReplicationReceiver {
+    /// Apply the [`UnknownEntityUpdatePolicy`] to updates received for an entity that doesn't exist locally
+    fn handle_unknown_entity_update(
+        &mut self,
+        remote_entity: Entity,
+        tick: Tick,
+        components: Vec<RawData>,
+    ) {
+        match self.unknown_entity_policy {
+            UnknownEntityUpdatePolicy::Drop => {
+                debug!(?remote_entity, "update for entity that doesn't exist?");
+                self.unknown_entity_stats.dropped += 1;
+            }
+            UnknownEntityUpdatePolicy::Buffer { .. } => {
+                debug!(
+                    ?remote_entity,
+                    "buffering update for entity that doesn't exist"
+                );
+                self.unknown_entity_stats.buffered += 1;
+                self.unknown_entity_updates.push(UnknownEntityUpdate {
+                    remote_entity,
+                    tick,
+                    buffered_at: self.current_tick,
+                    components,
+                });
+            }
+            UnknownEntityUpdatePolicy::Event => {
+                self.unknown_entity_stats.dropped += 1;
+                self.unknown_entity_events.push((remote_entity, tick));
+            }
+            UnknownEntityUpdatePolicy::RequestResend => {
+                self.unknown_entity_stats.dropped += 1;
+                if self.requested_resends.insert(remote_entity) {
+                    debug!(
+                        ?remote_entity,
+                        "requesting resend of entity that doesn't exist"
+                    );
+                    self.unknown_entity_stats.resend_requested += 1;
+                    self.resend_requests.push(remote_entity);
+                }
+            }
+        }
+    }
+
+    /// Apply the buffered updates of the entities that now exist
+    fn apply_unknown_entity_updates(
+        &mut self,
+        world: &mut World,
+        component_registry: &ComponentRegistry,
+        tick: Tick,
+        events: &mut ConnectionEvents,
+    ) {
+        for update in std::mem::take(&mut self.unknown_entity_updates) {
+            let Ok(mut local_entity_mut) = self
+                .remote_entity_map
+                .get_by_remote(world, update.remote_entity)
+            else {
+                self.unknown_entity_updates.push(update);
+                continue;
+            };
+            // the message that spawned the entity already contains a more recent state
+            if update.tick <= tick {
+                self.unknown_entity_stats.dropped += 1;
+                continue;
+            }
+            debug!(remote_entity = ?update.remote_entity, "applying buffered update");
+            for component in update.components {
+                self.reader.reset_read(component.as_slice());
+                let _ = component_registry
+                    .raw_write(
+                        &mut self.reader,
+                        self.remote_protocol_version,
+                        &mut local_entity_mut,
+                        &mut self.remote_entity_map.remote_to_local,
+                        events,
+                    )
+                    .inspect_err(|e| {
+                        error!("could not write the component to the entity: {:?}", e)
+                    });
+            }
+            self.unknown_entity_stats.applied += 1;
+        }
+    }
+}
+
 /// Channel to keep track of receiving/sending replication messages for a given Group
 #[derive(Debug)]
 pub struct GroupChannel {
@@ -558,10 +1002,10 @@ mod tests {
         manager.recv_message(
             ReplicationMessage {
                 group_id,
-                data: ReplicationMessageData::Actions(EntityActionMessage {
-                    sequence_id: MessageId(0) - 1,
-                    actions: Default::default(),
-                }),
+                data: ReplicationMessageData::Actions(EntityActionMessage::new(
+                    MessageId(0) - 1,
+                    Vec::new(),
+                )),
             },
             Tick(0),
         );
@@ -584,10 +1028,10 @@ mod tests {
         manager.recv_message(
             ReplicationMessage {
                 group_id: ReplicationGroupId(0),
-                data: ReplicationMessageData::Actions(EntityActionMessage {
-                    sequence_id: MessageId(0),
-                    actions: Default::default(),
-                }),
+                data: ReplicationMessageData::Actions(EntityActionMessage::new(
+                    MessageId(0),
+                    Vec::new(),
+                )),
             },
             Tick(0),
         );
@@ -650,10 +1094,10 @@ mod tests {
         manager.recv_message(
             ReplicationMessage {
                 group_id: ReplicationGroupId(0),
-                data: ReplicationMessageData::Actions(EntityActionMessage {
-                    sequence_id: MessageId(2),
-                    actions: Default::default(),
-                }),
+                data: ReplicationMessageData::Actions(EntityActionMessage::new(
+                    MessageId(2),
+                    Vec::new(),
+                )),
             },
             Tick(3),
         );
@@ -663,10 +1107,10 @@ mod tests {
         manager.recv_message(
             ReplicationMessage {
                 group_id: ReplicationGroupId(0),
-                data: ReplicationMessageData::Actions(EntityActionMessage {
-                    sequence_id: MessageId(1),
-                    actions: Default::default(),
-                }),
+                data: ReplicationMessageData::Actions(EntityActionMessage::new(
+                    MessageId(1),
+                    Vec::new(),
+                )),
             },
             Tick(2),
         );
@@ -687,9 +1131,9 @@ mod tests {
         let component_registry = ComponentRegistry::default();
         let mut events = ConnectionEvents::default();
         let group_id = ReplicationGroupId(0);
-        let replication = ReplicationMessageData::Actions(EntityActionMessage {
-            sequence_id: MessageId(0),
-            actions: vec![(
+        let replication = ReplicationMessageData::Actions(EntityActionMessage::new(
+            MessageId(0),
+            vec![(
                 remote_entity,
                 EntityActions {
                     spawn: SpawnAction::Reuse(local_entity.to_bits()),
@@ -698,7 +1142,7 @@ mod tests {
                     updates: vec![],
                 },
             )],
-        });
+        ));
         manager.apply_world(
             &mut world,
             None,
@@ -717,4 +1161,142 @@ mod tests {
             &local_entity
         );
     }
+
+    /// A client cannot link its entities to the pre-placed entities of the server
+    #[test]
+    fn test_recv_spawn_link_from_client() {
+        let mut manager = ReplicationReceiver::new();
+        let mut world = World::new();
+        let remote_entity = Entity::from_raw(1000);
+        let network_id = NetworkId::new(1);
+        let local_entity = world.spawn(network_id).id();
+        let mut pre_placed = PrePlacedEntities::default();
+        pre_placed.insert(network_id, local_entity);
+        world.insert_resource(pre_placed);
+        let component_registry = ComponentRegistry::default();
+        let mut events = ConnectionEvents::default();
+        let replication = ReplicationMessageData::Actions(EntityActionMessage::new(
+            MessageId(0),
+            vec![(
+                remote_entity,
+                EntityActions {
+                    spawn: SpawnAction::Link(network_id.to_bits()),
+                    insert: vec![],
+                    remove: Default::default(),
+                    updates: vec![],
+                },
+            )],
+        ));
+        manager.apply_world(
+            &mut world,
+            Some(ClientId::Netcode(0)),
+            &component_registry,
+            Tick(0),
+            replication,
+            ReplicationGroupId(0),
+            &mut events,
+        );
+
+        // a new entity was spawned instead of linking the pre-placed entity
+        let new_entity = *manager.remote_entity_map.get_local(remote_entity).unwrap();
+        assert_ne!(new_entity, local_entity);
+        assert_eq!(world.entities().len(), 2);
+        assert!(world.get::<Replicated>(local_entity).is_none());
+        assert!(world.get::<NetworkId>(new_entity).is_none());
+    }
+
+    #[test]
+    fn test_unknown_entity_updates() {
+        let mut manager = ReplicationReceiver::new();
+        manager.unknown_entity_policy = UnknownEntityUpdatePolicy::Buffer { ticks: 10 };
+        let mut world = World::new();
+        let component_registry = ComponentRegistry::default();
+        let mut events = ConnectionEvents::default();
+        let group_id = ReplicationGroupId(0);
+        let remote_entity = Entity::from_raw(1000);
+        let other_remote_entity = Entity::from_raw(1001);
+        let updates = |entity: Entity| {
+            ReplicationMessageData::Updates(EntityUpdatesMessage {
+                last_action_tick: None,
+                updates: vec![(entity, vec![])],
+            })
+        };
+
+        // the updates arrive before the spawns, so they are buffered
+        manager.read_messages(Tick(0));
+        for entity in [remote_entity, other_remote_entity] {
+            manager.apply_world(
+                &mut world,
+                None,
+                &component_registry,
+                Tick(5),
+                updates(entity),
+                group_id,
+                &mut events,
+            );
+        }
+        assert_eq!(manager.unknown_entity_stats.buffered, 2);
+
+        // the buffered update is applied once the entity is spawned
+        manager.apply_world(
+            &mut world,
+            None,
+            &component_registry,
+            Tick(3),
+            ReplicationMessageData::Actions(EntityActionMessage::new(
+                MessageId(0),
+                vec![(
+                    remote_entity,
+                    EntityActions {
+                        spawn: SpawnAction::Spawn,
+                        ..Default::default()
+                    },
+                )],
+            )),
+            group_id,
+            &mut events,
+        );
+        assert_eq!(manager.unknown_entity_stats.applied, 1);
+
+        // the other update is dropped once it waited for more than 10 ticks
+        manager.read_messages(Tick(10));
+        assert_eq!(manager.unknown_entity_stats.dropped, 0);
+        manager.read_messages(Tick(11));
+        assert_eq!(manager.unknown_entity_stats.dropped, 1);
+        assert!(manager.unknown_entity_updates.is_empty());
+
+        // with the Event policy, the update is dropped and reported
+        manager.unknown_entity_policy = UnknownEntityUpdatePolicy::Event;
+        manager.apply_world(
+            &mut world,
+            None,
+            &component_registry,
+            Tick(12),
+            updates(other_remote_entity),
+            group_id,
+            &mut events,
+        );
+        assert_eq!(
+            manager.drain_unknown_entity_events(),
+            vec![(other_remote_entity, Tick(12))]
+        );
+        assert_eq!(manager.unknown_entity_stats.dropped, 2);
+
+        // with the RequestResend policy, the entity is only requested once
+        manager.unknown_entity_policy = UnknownEntityUpdatePolicy::RequestResend;
+        for tick in [Tick(13), Tick(14)] {
+            manager.apply_world(
+                &mut world,
+                None,
+                &component_registry,
+                tick,
+                updates(other_remote_entity),
+                group_id,
+                &mut events,
+            );
+        }
+        assert_eq!(manager.drain_resend_requests(), vec![other_remote_entity]);
+        assert_eq!(manager.unknown_entity_stats.dropped, 4);
+        assert_eq!(manager.unknown_entity_stats.resend_requested, 1);
+    }
 }
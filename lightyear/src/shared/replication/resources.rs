@@ -8,7 +8,7 @@ use bevy::ecs::entity::MapEntities;
 use bevy::ecs::system::Command;
 use bevy::prelude::{
     Commands, Component, DetectChanges, EntityMapper, IntoSystemConfigs, IntoSystemSetConfigs,
-    Plugin, PostUpdate, PreUpdate, Res, ResMut, Resource, SystemSet,
+    Plugin, Res, ResMut, Resource, SystemSet,
 };
 pub use command::{ReplicateResourceExt, StopReplicateResourceExt};
 use serde::de::DeserializeOwned;
@@ -16,6 +16,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::prelude::{ChannelKind, Message};
 use crate::protocol::BitSerializable;
+use crate::shared::config::NetworkScheduleConfig;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::ReplicationSend;
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet};
@@ -71,6 +72,16 @@ pub struct ReplicateResourceMetadata<R> {
     _marker: PhantomData<R>,
 }
 
+impl<R> ReplicateResourceMetadata<R> {
+    pub(crate) fn new(target: NetworkTarget, channel: ChannelKind) -> Self {
+        Self {
+            target,
+            channel,
+            _marker: PhantomData,
+        }
+    }
+}
+
 /// Message that indicates that a resource should be despawned
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DespawnResource<R> {
@@ -114,8 +125,9 @@ pub(crate) mod send {
     >(
         app: &mut App,
     ) {
+        let schedules = NetworkScheduleConfig::get(app);
         app.add_systems(
-            PostUpdate,
+            schedules.send,
             (
                 send_resource_removal::<R, S>.run_if(resource_removed::<R>()),
                 send_resource_update::<R, S>,
@@ -211,8 +223,9 @@ pub(crate) mod receive {
 
     impl<R: ReplicationPeer> Plugin for ResourceReceivePlugin<R> {
         fn build(&self, app: &mut App) {
+            let schedules = NetworkScheduleConfig::get(app);
             app.configure_sets(
-                PreUpdate,
+                schedules.receive,
                 InternalReplicationSet::<R::SetMarker>::ReceiveResourceUpdates
                     .after(InternalMainSet::<R::SetMarker>::EmitEvents),
             );
@@ -231,15 +244,16 @@ pub(crate) mod receive {
     ) {
         // If `is_bidirectional` is  true, that means that the resource can be replicated in both directions.
         // In that case, we need to disable change detection or we would get an infinite loop of updates.
+        let schedules = NetworkScheduleConfig::get(app);
         if is_bidirectional {
             app.add_systems(
-                PreUpdate,
+                schedules.receive,
                 handle_resource_message_bidirectional::<R, S::EventContext>
                     .in_set(InternalReplicationSet::<S::SetMarker>::ReceiveResourceUpdates),
             );
         } else {
             app.add_systems(
-                PreUpdate,
+                schedules.receive,
                 handle_resource_message::<R, S::EventContext>
                     .in_set(InternalReplicationSet::<S::SetMarker>::ReceiveResourceUpdates),
             );
@@ -309,7 +323,7 @@ pub(crate) mod receive {
     // }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use crate::shared::replication::network_target::NetworkTarget;
     use crate::shared::replication::resources::ReplicateResourceExt;
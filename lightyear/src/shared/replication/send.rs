@@ -1,26 +1,32 @@
 //! General struct handling replication
 use std::iter::Extend;
 
-use crate::channel::builder::{EntityActionsChannel, EntityUpdatesChannel};
+use crate::channel::builder::{EntityActionsChannel, EntityUpdatesChannel, WorldSnapshotChannel};
 use anyhow::Context;
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::entity::EntityHash;
 use bevy::prelude::{Entity, Reflect};
 use bevy::utils::petgraph::data::ElementIterator;
 use bevy::utils::{hashbrown, HashMap, HashSet};
+use bitcode::encoding::Fixed;
 use crossbeam_channel::Receiver;
 use tracing::{debug, error, info, trace, warn};
 
+use crate::packet::compression::PacketCompressor;
 use crate::packet::message::MessageId;
 use crate::prelude::{ShouldBePredicted, Tick};
 use crate::protocol::channel::ChannelKind;
 use crate::protocol::component::ComponentNetId;
 use crate::protocol::registry::NetId;
+use crate::serialize::bitcode::writer::BitcodeWriter;
+use crate::serialize::writer::WriteBuffer;
 use crate::serialize::RawData;
-use crate::shared::replication::components::ReplicationGroupId;
+use crate::shared::replication::components::{NetworkId, ReplicationGroupId};
+use crate::shared::replication::delta::DeltaSender;
 
 use super::{
-    EntityActionMessage, EntityActions, EntityUpdatesMessage, ReplicationMessageData, SpawnAction,
+    DespawnReason, EntityActionMessage, EntityActions, EntityDespawnsMessage, EntityUpdatesMessage,
+    ReplicationMessageData, SpawnAction, WorldResetMessage, WorldSnapshot, WorldSnapshotMessage,
 };
 
 type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
@@ -33,8 +39,8 @@ pub(crate) struct ReplicationSender {
 
     /// Map from message-id to the corresponding group-id that sent this update message, as well as the bevy ChangeTick
     /// when we sent the message. (so that when it's acked, we know we only need to include updates that happened after that tick,
-    /// for that replication group)
-    pub updates_message_id_to_group_id: HashMap<MessageId, (ReplicationGroupId, BevyTick)>,
+    /// for that replication group) and the tick at which it was sent
+    pub updates_message_id_to_group_id: HashMap<MessageId, (ReplicationGroupId, BevyTick, Tick)>,
     /// messages that are being written. We need to hold a buffer of messages because components actions/updates
     /// are being buffered individually but we want to group them inside a message
     pub pending_actions: EntityHashMap<ReplicationGroupId, EntityHashMap<Entity, EntityActions>>,
@@ -46,10 +52,45 @@ pub(crate) struct ReplicationSender {
     /// Buffer to so that we have an ordered receiver per group
     pub group_channels: EntityHashMap<ReplicationGroupId, GroupChannel>,
 
+    // DELTA COMPRESSION
+    /// Baselines of the delta-compressed components
+    pub delta: DeltaSender,
+
     // PRIORITY
     /// Get notified whenever a message for a given ReplicationGroup was actually sent
     /// (sometimes they might not be sent because of bandwidth constraints
     pub message_send_receiver: Receiver<MessageId>,
+    /// The priority accumulated by each replication group.
+    ///
+    /// It is reset every time a message of the group is sent, and accumulated otherwise (because of the
+    /// bandwidth cap). The groups without an accumulated priority use their base priority.
+    ///
+    /// On the server the accumulators are stored on the client entities
+    /// (see [`ReplicationPriorities`](crate::prelude::server::ReplicationPriorities)), and are only
+    /// moved here while the replication messages are sent.
+    pub priorities: HashMap<ReplicationGroupId, f32>,
+
+    /// If set, when at least this many single-entity groups are despawned during the same frame,
+    /// the despawns are sent in a single [`EntityDespawnsMessage`] instead of one message per group
+    pub despawn_batch_threshold: Option<usize>,
+
+    // WORLD RESET
+    /// True while a world reset is in progress: no replication messages are sent until the remote
+    /// has received the reset message
+    resetting_world: bool,
+    /// Reset message that will be sent in the next [`finalize`](Self::finalize)
+    pending_world_reset: Option<WorldResetMessage>,
+    /// Message-id of the reset message that was sent but not acked yet
+    pub world_reset_message_id: Option<MessageId>,
+    /// Update messages sent before the world reset; their acks must be ignored
+    stale_update_message_ids: HashSet<MessageId>,
+
+    // SNAPSHOT
+    /// If set, the entity actions of the next [`finalize`](Self::finalize) after [`request_snapshot`](Self::request_snapshot)
+    /// are compressed with this compressor and sent in a single [`WorldSnapshotMessage`]
+    pub(crate) snapshot_compressor: Option<PacketCompressor>,
+    /// True if the next entity actions must be sent in a snapshot
+    snapshot_requested: bool,
 }
 
 impl ReplicationSender {
@@ -65,8 +106,20 @@ impl ReplicationSender {
             pending_updates: EntityHashMap::default(),
             pending_unique_components: EntityHashMap::default(),
             group_channels: Default::default(),
+            // DELTA COMPRESSION
+            delta: DeltaSender::default(),
             // PRIORITY
             message_send_receiver,
+            priorities: HashMap::default(),
+            despawn_batch_threshold: None,
+            // WORLD RESET
+            resetting_world: false,
+            pending_world_reset: None,
+            world_reset_message_id: None,
+            stale_update_message_ids: HashSet::default(),
+            // SNAPSHOT
+            snapshot_compressor: None,
+            snapshot_requested: false,
         }
     }
 
@@ -77,8 +130,8 @@ impl ReplicationSender {
     pub(crate) fn recv_send_notification(&mut self) {
         // TODO: handle errors that are not channel::isEmpty
         while let Ok(message_id) = self.message_send_receiver.try_recv() {
-            if let Some((group_id, _)) = self.updates_message_id_to_group_id.get(&message_id) {
-                if let Some(channel) = self.group_channels.get_mut(group_id) {
+            if let Some((group_id, _, _)) = self.updates_message_id_to_group_id.get(&message_id) {
+                if self.group_channels.contains_key(group_id) {
                     // TODO: think about we reset the priority, or how it should be accumulated
                     // reset the priority
                     debug!(
@@ -86,7 +139,7 @@ impl ReplicationSender {
                         ?group_id,
                         "successfully sent message for replication group! Resetting priority"
                     );
-                    channel.accumulated_priority = Some(0.0);
+                    self.priorities.insert(*group_id, 0.0);
                 } else {
                     error!(?message_id, ?group_id, "Received a send message-id notification but the corresponding group channel does not exist");
                 }
@@ -97,14 +150,15 @@ impl ReplicationSender {
             }
         }
 
+        // the priorities accumulated before a world reset are forgotten
+        if self.resetting_world {
+            self.priorities.clear();
+        }
         // TODO: don't accumulate priority if priority is not enabled
         // then accumulate the priority for all replication groups
-        self.group_channels.values_mut().for_each(|channel| {
-            channel.accumulated_priority = channel
-                .accumulated_priority
-                .map_or(Some(channel.base_priority), |acc| {
-                    Some(acc + channel.base_priority)
-                });
+        self.group_channels.iter().for_each(|(group_id, channel)| {
+            *self.priorities.entry(*group_id).or_insert(0.0) +=
+                channel.base_priority * channel.score;
         });
     }
 
@@ -113,10 +167,14 @@ impl ReplicationSender {
     pub(crate) fn recv_update_acks(&mut self) {
         // TODO: handle errors that are not channel::isEmpty
         while let Ok(message_id) = self.updates_ack_tracker.try_recv() {
+            if self.stale_update_message_ids.remove(&message_id) {
+                continue;
+            }
             // remember to remove the entry from the map to avoid memory leakage
-            if let Some((group_id, bevy_tick)) =
+            if let Some((group_id, bevy_tick, tick)) =
                 self.updates_message_id_to_group_id.remove(&message_id)
             {
+                self.delta.ack(group_id, tick);
                 if let Some(channel) = self.group_channels.get_mut(&group_id) {
                     channel.update_collect_changes_since_this_tick(bevy_tick)
                 } else {
@@ -130,7 +188,9 @@ impl ReplicationSender {
 
     /// Do some internal bookkeeping:
     /// - handle tick wrapping
+    /// - forget the delta baselines that are too old
     pub(crate) fn cleanup(&mut self, tick: Tick) {
+        self.delta.cleanup(tick);
         // if it's been enough time since we last any action for the group, we can set the last_action_tick to None
         // (meaning that there's no need when we receive the update to check if we have already received a previous action)
         for group_channel in self.group_channels.values_mut() {
@@ -157,12 +217,16 @@ impl ReplicationSender {
 impl ReplicationSender {
     /// Update the base priority for a given group
     pub(crate) fn update_base_priority(&mut self, group_id: ReplicationGroupId, priority: f32) {
-        let channel = self.group_channels.entry(group_id).or_default();
-        channel.base_priority = priority;
-        // if we already have an accumulated priority, don't override it
-        if channel.accumulated_priority.is_none() {
-            channel.accumulated_priority = Some(priority);
-        }
+        self.group_channels
+            .entry(group_id)
+            .or_default()
+            .base_priority = priority;
+    }
+
+    /// Update the score of a group, that multiplies the priority accumulated by the group every time
+    /// that messages are sent
+    pub(crate) fn update_score(&mut self, group_id: ReplicationGroupId, score: f32) {
+        self.group_channels.entry(group_id).or_default().score = score;
     }
 
     // TODO: how can I emit metrics here that contain the channel kind?
@@ -196,13 +260,35 @@ impl ReplicationSender {
             .spawn = SpawnAction::Reuse(remote_entity.to_bits());
     }
 
-    pub(crate) fn prepare_entity_despawn(&mut self, entity: Entity, group_id: ReplicationGroupId) {
+    /// Host wants to start replicating an entity that already exists on the remote, for example because
+    /// it was placed in a level loaded by both peers. The remote links it to its entity with the same [`NetworkId`].
+    pub(crate) fn prepare_entity_spawn_link(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        network_id: NetworkId,
+    ) {
+        self.pending_actions
+            .entry(group_id)
+            .or_default()
+            .entry(entity)
+            .or_default()
+            .spawn = SpawnAction::Link(network_id.to_bits());
+    }
+
+    pub(crate) fn prepare_entity_despawn(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        reason: Option<DespawnReason>,
+    ) {
+        self.delta.remove_entity(entity);
         self.pending_actions
             .entry(group_id)
             .or_default()
             .entry(entity)
             .or_default()
-            .spawn = SpawnAction::Despawn;
+            .spawn = SpawnAction::Despawn(reason);
     }
 
     // we want to send all component inserts that happen together for the same entity in a single message
@@ -268,6 +354,7 @@ impl ReplicationSender {
             );
             return;
         }
+        self.delta.remove_component(entity, kind);
         self.pending_actions
             .entry(group_id)
             .or_default()
@@ -277,13 +364,14 @@ impl ReplicationSender {
             .insert(kind);
     }
 
+    /// Returns false if the message already contains an update for the component
     pub(crate) fn prepare_entity_update(
         &mut self,
         entity: Entity,
         group_id: ReplicationGroupId,
         kind: ComponentNetId,
         component: RawData,
-    ) {
+    ) -> bool {
         if self
             .pending_unique_components
             .entry(group_id)
@@ -298,7 +386,7 @@ impl ReplicationSender {
                 ?kind,
                 "Trying to update a component that is already in the message"
             );
-            return;
+            return false;
         }
         trace!(?kind, "Inserting pending update!");
         self.pending_updates
@@ -313,14 +401,22 @@ impl ReplicationSender {
             .entry(entity)
             .or_default()
             .insert(kind);
+        true
     }
 
     /// Finalize the replication messages
+    ///
+    /// `bevy_tick` is the system tick at which the changes of the pending updates were collected.
     pub(crate) fn finalize(
         &mut self,
         tick: Tick,
+        bevy_tick: BevyTick,
     ) -> Vec<(ChannelKind, ReplicationGroupId, ReplicationMessageData, f32)> {
-        let mut messages = Vec::new();
+        let snapshot_requested = std::mem::take(&mut self.snapshot_requested);
+        if self.resetting_world {
+            return self.finalize_world_reset();
+        }
+        let mut messages = self.finalize_batched_despawns(tick);
 
         for (group_id, mut actions) in self.pending_actions.drain() {
             trace!(?group_id, "pending actions: {:?}", actions);
@@ -336,30 +432,41 @@ impl ReplicationSender {
                 }
             }
             let channel = self.group_channels.entry(group_id).or_default();
-            let priority = channel
-                .accumulated_priority
+            let priority = self
+                .priorities
+                .get(&group_id)
+                .copied()
                 .unwrap_or(channel.base_priority);
             let message_id = channel.actions_next_send_message_id;
             channel.actions_next_send_message_id += 1;
             channel.last_action_tick = Some(tick);
+            // the actions are sent reliably along with all the changes of the group, so there is no need
+            // to send these changes again as updates. In particular a remote that receives the full state
+            // of the group in a spawn doesn't receive it again in every update until it acks one of them
+            channel.update_collect_changes_since_this_tick(bevy_tick);
             messages.push((
                 ChannelKind::of::<EntityActionsChannel>(),
                 group_id,
-                ReplicationMessageData::Actions(EntityActionMessage {
-                    sequence_id: message_id,
+                ReplicationMessageData::Actions(
                     // TODO: maybe we can just send the HashMap directly?
-                    actions: Vec::from_iter(actions.into_iter()),
-                }),
+                    EntityActionMessage::new(message_id, Vec::from_iter(actions.into_iter()))
+                        .batch_spawns(),
+                ),
                 priority,
             ));
             debug!("final action messages to send: {:?}", messages);
         }
+        if snapshot_requested {
+            self.finalize_snapshot(&mut messages);
+        }
         // send the remaining updates
         for (group_id, updates) in self.pending_updates.drain() {
             trace!(?group_id, "pending updates: {:?}", updates);
             let channel = self.group_channels.entry(group_id).or_default();
-            let priority = channel
-                .accumulated_priority
+            let priority = self
+                .priorities
+                .get(&group_id)
+                .copied()
                 .unwrap_or(channel.base_priority);
             messages.push((
                 ChannelKind::of::<EntityUpdatesChannel>(),
@@ -384,6 +491,207 @@ impl ReplicationSender {
     }
 }
 
+impl ReplicationSender {
+    /// Single-entity groups where the only pending action is the despawn of the entity
+    fn pending_batchable_despawns(
+        &self,
+    ) -> Vec<(ReplicationGroupId, Entity, Option<DespawnReason>)> {
+        self.pending_actions
+            .iter()
+            .filter(|(group_id, _)| !self.pending_updates.contains_key(*group_id))
+            .filter(|(_, entities)| entities.len() == 1)
+            .filter_map(|(group_id, entities)| {
+                let (entity, actions) = entities.iter().next()?;
+                let SpawnAction::Despawn(reason) = actions.spawn else {
+                    return None;
+                };
+                // the receiver infers the group from the entity
+                (group_id.0 == entity.to_bits()
+                    && actions.insert.is_empty()
+                    && actions.remove.is_empty()
+                    && actions.updates.is_empty())
+                .then_some((*group_id, *entity, reason))
+            })
+            .collect()
+    }
+
+    /// If enough entities were despawned, send their despawns in batched messages (one per despawn reason)
+    fn finalize_batched_despawns(
+        &mut self,
+        tick: Tick,
+    ) -> Vec<(ChannelKind, ReplicationGroupId, ReplicationMessageData, f32)> {
+        let Some(threshold) = self.despawn_batch_threshold else {
+            return vec![];
+        };
+        let despawns = self.pending_batchable_despawns();
+        if despawns.len() < threshold {
+            return vec![];
+        }
+        let mut batches: HashMap<Option<DespawnReason>, (Vec<(Entity, MessageId)>, f32)> =
+            HashMap::default();
+        for (group_id, entity, reason) in despawns {
+            self.pending_actions.remove(&group_id);
+            let channel = self.group_channels.entry(group_id).or_default();
+            let priority = self
+                .priorities
+                .get(&group_id)
+                .copied()
+                .unwrap_or(channel.base_priority);
+            let message_id = channel.actions_next_send_message_id;
+            channel.actions_next_send_message_id += 1;
+            channel.last_action_tick = Some(tick);
+            let (batch, batch_priority) = batches.entry(reason).or_default();
+            batch.push((entity, message_id));
+            *batch_priority = batch_priority.max(priority);
+        }
+        batches
+            .into_iter()
+            .map(|(reason, (despawns, priority))| {
+                debug!(
+                    ?reason,
+                    num_despawns = despawns.len(),
+                    "batching entity despawns"
+                );
+                (
+                    ChannelKind::of::<EntityActionsChannel>(),
+                    ReplicationGroupId::default(),
+                    ReplicationMessageData::Despawns(EntityDespawnsMessage::new(reason, despawns)),
+                    priority,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Initial capacity of the buffer in which the world snapshots are written
+const SNAPSHOT_BUFFER_CAPACITY: usize = 64 * 1024;
+
+impl ReplicationSender {
+    /// Send the state of the world in a snapshot during the next [`finalize`](Self::finalize),
+    /// if the snapshots are enabled
+    pub(crate) fn request_snapshot(&mut self) {
+        self.snapshot_requested = self.snapshot_compressor.is_some();
+    }
+
+    /// Replace the entity actions messages with a single compressed [`WorldSnapshotMessage`].
+    ///
+    /// The messages are left untouched if the snapshot cannot be built.
+    fn finalize_snapshot(
+        &mut self,
+        messages: &mut Vec<(ChannelKind, ReplicationGroupId, ReplicationMessageData, f32)>,
+    ) {
+        let Some(compressor) = self.snapshot_compressor.as_mut() else {
+            return;
+        };
+        let (actions, others): (Vec<_>, Vec<_>) = std::mem::take(messages)
+            .into_iter()
+            .partition(|(_, _, data, _)| matches!(data, ReplicationMessageData::Actions(_)));
+        // the snapshot is sent even if the world is empty, so that a reconnecting client knows that
+        // none of its previous entities exist anymore
+        *messages = others;
+        let priority = actions
+            .iter()
+            .map(|(_, _, _, priority)| *priority)
+            .fold(0.0, f32::max);
+        let snapshot = WorldSnapshot {
+            groups: actions
+                .iter()
+                .filter_map(|(_, group_id, data, _)| match data {
+                    ReplicationMessageData::Actions(m) => Some((*group_id, m.clone())),
+                    _ => None,
+                })
+                .collect(),
+        };
+        let mut writer = BitcodeWriter::with_capacity(SNAPSHOT_BUFFER_CAPACITY);
+        let data = writer
+            .encode(&snapshot, Fixed)
+            .and_then(|_| compressor.compress(writer.finish_write()));
+        match data {
+            Ok(data) => {
+                debug!(
+                    num_groups = snapshot.groups.len(),
+                    size = data.len(),
+                    "sending world snapshot"
+                );
+                messages.push((
+                    ChannelKind::of::<WorldSnapshotChannel>(),
+                    ReplicationGroupId::default(),
+                    ReplicationMessageData::Snapshot(WorldSnapshotMessage { data }),
+                    priority,
+                ));
+            }
+            Err(e) => {
+                error!("could not build the world snapshot: {e:?}");
+                messages.extend(actions);
+            }
+        }
+    }
+}
+
+impl ReplicationSender {
+    /// Start resetting the replicated world on the remote.
+    ///
+    /// The remote will despawn all the entities it received, and nothing is replicated until
+    /// the reset message is acked. After that, the state of the world must be sent again from scratch.
+    pub(crate) fn reset_world(&mut self) {
+        self.pending_actions.clear();
+        self.pending_updates.clear();
+        self.pending_unique_components.clear();
+        self.delta.clear();
+        self.stale_update_message_ids.extend(
+            self.updates_message_id_to_group_id
+                .drain()
+                .map(|(message_id, _)| message_id),
+        );
+        let sequence_ids = self
+            .group_channels
+            .iter_mut()
+            .map(|(group_id, channel)| {
+                channel.collect_changes_since_this_tick = None;
+                channel.last_action_tick = None;
+                (*group_id, channel.actions_next_send_message_id)
+            })
+            .collect();
+        self.pending_world_reset = Some(WorldResetMessage { sequence_ids });
+        self.resetting_world = true;
+    }
+
+    /// Notify the sender that the remote received the entity actions message `message_id`.
+    ///
+    /// Returns true if it was the world reset message, in which case the reset is complete
+    pub(crate) fn recv_world_reset_ack(&mut self, message_id: MessageId) -> bool {
+        if self.pending_world_reset.is_some() || self.world_reset_message_id != Some(message_id) {
+            return false;
+        }
+        debug!(?message_id, "the remote received the world reset");
+        self.world_reset_message_id = None;
+        self.resetting_world = false;
+        self.stale_update_message_ids.clear();
+        true
+    }
+
+    /// While the world is being reset, drop all the replication messages and only send the reset message
+    fn finalize_world_reset(
+        &mut self,
+    ) -> Vec<(ChannelKind, ReplicationGroupId, ReplicationMessageData, f32)> {
+        self.pending_actions.clear();
+        self.pending_updates.clear();
+        self.pending_unique_components.clear();
+        self.pending_world_reset
+            .take()
+            .map(|message| {
+                (
+                    ChannelKind::of::<EntityActionsChannel>(),
+                    ReplicationGroupId::default(),
+                    ReplicationMessageData::Reset(message),
+                    1.0,
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
 /// Channel to keep track of sending replication messages for a given Group
 #[derive(Debug)]
 pub struct GroupChannel {
@@ -395,11 +703,12 @@ pub struct GroupChannel {
     // last tick for which we sent an action message
     pub last_action_tick: Option<Tick>,
 
-    /// The priority to send the replication group.
-    /// This will be reset to base_priority every time we send network updates, unless we couldn't send a message
-    /// for this group because of the bandwidth cap, in which case it will be accumulated.
-    pub accumulated_priority: Option<f32>,
+    /// The priority to send the replication group, which is accumulated every time that messages are sent
+    /// (see [`ReplicationSender::priorities`])
     pub base_priority: f32,
+    /// Multiplier of the priority accumulated by the group, computed by the
+    /// [`ReplicationPriorityScorer`](crate::server::priority::ReplicationPriorityScorer) on the server
+    pub score: f32,
 }
 
 impl Default for GroupChannel {
@@ -407,9 +716,9 @@ impl Default for GroupChannel {
         Self {
             actions_next_send_message_id: MessageId(0),
             last_action_tick: None,
-            accumulated_priority: None,
             collect_changes_since_this_tick: None,
             base_priority: 1.0,
+            score: 1.0,
         }
     }
 }
@@ -483,7 +792,7 @@ mod tests {
         manager.prepare_entity_update(entity_3, group_2, net_id_3, raw_4.clone());
 
         // the order of actions is not important if there are no relations between the entities
-        let message = manager.finalize(Tick(2));
+        let message = manager.finalize(Tick(2), BevyTick::new(0));
         let actions = message.first().unwrap();
         assert_eq!(actions.0, ChannelKind::of::<EntityActionsChannel>());
         assert_eq!(actions.1, group_1);
@@ -545,4 +854,129 @@ mod tests {
             Some(Tick(2))
         );
     }
+
+    /// The spawns of the entities of a group that have the same components are sent in a single batch
+    #[test]
+    fn test_batch_entity_spawns() {
+        let (_, receiver) = crossbeam_channel::unbounded();
+        let mut manager = ReplicationSender::new(receiver.clone(), receiver);
+        let group = ReplicationGroupId(0);
+        let net_id_1: ComponentNetId = 3;
+        let net_id_2: ComponentNetId = 7;
+        let component = |net_id: ComponentNetId, value: u8| {
+            let mut raw = super::super::component_net_id_header(net_id);
+            raw.extend_from_slice(&[value, value]);
+            raw
+        };
+
+        let entities: Vec<Entity> = (0..10).map(Entity::from_raw).collect();
+        for (i, entity) in entities.iter().enumerate() {
+            manager.prepare_entity_spawn(*entity, group);
+            manager.prepare_component_insert(
+                *entity,
+                group,
+                net_id_1,
+                component(net_id_1, i as u8),
+            );
+            manager.prepare_component_insert(
+                *entity,
+                group,
+                net_id_2,
+                component(net_id_2, i as u8),
+            );
+        }
+        // an entity with different components is not batched
+        let other = Entity::from_raw(100);
+        manager.prepare_entity_spawn(other, group);
+        manager.prepare_component_insert(other, group, net_id_1, component(net_id_1, 100));
+
+        let messages = manager.finalize(Tick(2), BevyTick::new(0));
+        let ReplicationMessageData::Actions(ref message) = messages.first().unwrap().2 else {
+            panic!()
+        };
+        assert_eq!(message.spawn_batches.len(), 1);
+        assert_eq!(message.spawn_batches[0].entities.len(), 10);
+        assert_eq!(message.actions.len(), 1);
+        assert_eq!(message.actions[0].0, other);
+
+        // the batch uses less bytes than the individual spawns
+        let mut unbatched = message.clone();
+        unbatched.unbatch_spawns();
+        let encoded_len = |message: &EntityActionMessage| {
+            let mut writer = BitcodeWriter::with_capacity(1024);
+            writer.encode(message, Fixed).unwrap();
+            writer.finish_write().len()
+        };
+        assert!(encoded_len(message) < encoded_len(&unbatched));
+
+        // the receiver gets back the original spawns
+        assert!(unbatched.spawn_batches.is_empty());
+        assert_eq!(unbatched.actions.len(), 11);
+        for (i, entity) in entities.iter().enumerate() {
+            let (_, actions) = unbatched.actions.iter().find(|(e, _)| e == entity).unwrap();
+            assert_eq!(actions.spawn, SpawnAction::Spawn);
+            assert_eq!(
+                actions.insert,
+                vec![component(net_id_1, i as u8), component(net_id_2, i as u8)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_batch_entity_despawns() {
+        let (_, receiver) = crossbeam_channel::unbounded();
+        let mut manager = ReplicationSender::new(receiver.clone(), receiver);
+        manager.despawn_batch_threshold = Some(3);
+
+        let entities: Vec<Entity> = [0, 1, 2, 70].into_iter().map(Entity::from_raw).collect();
+        let group = |entity: Entity| ReplicationGroupId(entity.to_bits());
+        manager.group_channels.insert(
+            group(entities[1]),
+            GroupChannel {
+                actions_next_send_message_id: MessageId(4),
+                ..Default::default()
+            },
+        );
+        let reason = Some(DespawnReason(1));
+        for entity in &entities {
+            manager.prepare_entity_despawn(*entity, group(*entity), reason);
+        }
+        // entities that share a group with other entities are not batched
+        let shared_group = ReplicationGroupId(100);
+        let entity_4 = Entity::from_raw(3);
+        let entity_5 = Entity::from_raw(4);
+        manager.prepare_entity_despawn(entity_4, shared_group, reason);
+        manager.prepare_entity_spawn(entity_5, shared_group);
+
+        let messages = manager.finalize(Tick(2), BevyTick::new(0));
+        assert_eq!(messages.len(), 2);
+        let ReplicationMessageData::Despawns(ref despawns) = messages[0].2 else {
+            panic!()
+        };
+        assert_eq!(despawns.reason, reason);
+        // the entities are stored in 2 blocks
+        assert_eq!(despawns.blocks.len(), 2);
+        assert_eq!(
+            despawns.despawns().collect::<Vec<_>>(),
+            vec![
+                (entities[0], MessageId(0)),
+                (entities[1], MessageId(4)),
+                (entities[2], MessageId(0)),
+                (entities[3], MessageId(0)),
+            ]
+        );
+        assert_eq!(
+            manager
+                .group_channels
+                .get(&group(entities[1]))
+                .unwrap()
+                .actions_next_send_message_id,
+            MessageId(5)
+        );
+        let ReplicationMessageData::Actions(ref actions) = messages[1].2 else {
+            panic!()
+        };
+        assert_eq!(messages[1].1, shared_group);
+        assert_eq!(actions.actions.len(), 2);
+    }
 }
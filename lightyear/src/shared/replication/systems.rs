@@ -13,13 +13,11 @@ use tracing::{debug, error, info, trace, warn};
 use crate::prelude::{ClientId, ReplicationGroup, ShouldBePredicted, TargetEntity, TickManager};
 use crate::protocol::component::{ComponentNetId, ComponentRegistry};
 use crate::serialize::RawData;
-use crate::server::replication::send::SyncTarget;
-use crate::server::replication::ServerReplicationSet;
-use crate::server::visibility::immediate::{ClientVisibility, ReplicateVisibility};
 use crate::shared::replication::components::{
-    DespawnTracker, DisabledComponent, OverrideTargetComponent, ReplicateOnceComponent,
-    ReplicationGroupId, ReplicationTarget, VisibilityMode,
+    DespawnTracker, DisabledComponent, NetworkId, OverrideTargetComponent, RefreshComponent,
+    ReplicateOnceComponent, ReplicationGroupId, ReplicationTarget, VisibilityMode,
 };
+use crate::shared::replication::entity_map::PrePlacedEntities;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::{ReplicationReceive, ReplicationSend};
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet};
@@ -34,6 +32,30 @@ pub(crate) fn send_cleanup<R: ReplicationSend>(
     sender.cleanup(tick);
 }
 
+/// Remove the [`RefreshComponent`] markers once the refreshed components have been buffered for replication
+pub(crate) fn remove_refresh_markers<C: Component>(
+    mut commands: Commands,
+    query: Query<Entity, With<RefreshComponent<C>>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<RefreshComponent<C>>();
+    }
+}
+
+/// Keep track of the local entities that have a [`NetworkId`], so that the replicated entities can be linked to them
+pub(crate) fn update_pre_placed_entities(
+    mut pre_placed: ResMut<PrePlacedEntities>,
+    query: Query<(Entity, &NetworkId), Changed<NetworkId>>,
+    mut removed: RemovedComponents<NetworkId>,
+) {
+    for entity in removed.read() {
+        pre_placed.remove(entity);
+    }
+    for (entity, network_id) in query.iter() {
+        pre_placed.insert(*network_id, entity);
+    }
+}
+
 /// Systems that runs internal clean-up on the ReplicationReceiver
 /// (handle tick wrapping, etc.)
 pub(crate) fn receive_cleanup<R: ReplicationReceive>(
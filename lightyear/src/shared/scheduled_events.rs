@@ -0,0 +1,387 @@
+/*! Trigger an event at the same moment on every client
+
+This is useful for cosmetic events that must be synchronized between all the players, for example
+a round countdown, a cutscene, or fireworks that start at a given time.
+
+The server schedules the event for a given server [`Tick`] with the [`EventScheduler`] resource.
+The event is sent to every client ahead of time, and each client emits a [`ScheduledEvent`] when its
+estimate of the current server tick reaches the scheduled tick. Since all the clients are synced
+with the server's clock, they trigger the event at (approximately) the same real-world moment.
+
+Clients that connect after the event was scheduled still receive it:
+- if the event has not been triggered yet, it is triggered at the scheduled tick like for the other clients
+- if the event was triggered less than [`EventScheduler::catch_up_window`] ago, it is triggered immediately
+  and [`ScheduledEvent::late_by`] indicates how late it is, so that the client can catch up
+  (for example by starting the cutscene in the middle)
+
+The event type must be registered on both the client and the server with the [`ScheduledEventPlugin`]:
+```rust,ignore
+app.add_plugins(ScheduledEventPlugin::<Fireworks>::default());
+
+fn start_fireworks(tick_manager: Res<TickManager>, mut scheduler: ResMut<EventScheduler<Fireworks>>) {
+    scheduler.schedule_in(&tick_manager, Duration::from_secs(3), Fireworks);
+}
+
+fn play_fireworks(mut events: EventReader<ScheduledEvent<Fireworks>>) {
+    for event in events.read() {
+        info!("Fireworks are late by {:?}", event.late_by);
+    }
+}
+```
+*/
+use std::marker::PhantomData;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{
+    not, Condition, Event, EventReader, EventWriter, IntoSystemConfigs, Res, ResMut, Resource,
+};
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::channel::builder::ScheduledEventChannel;
+#[cfg(feature = "client")]
+use crate::client::config::ClientConfig;
+#[cfg(feature = "client")]
+use crate::client::networking::is_connected;
+#[cfg(feature = "client")]
+use crate::prelude::client;
+#[cfg(feature = "server")]
+use crate::prelude::server;
+use crate::prelude::{
+    AppMessageExt, ChannelDirection, Message, NetworkTarget, SharedConfig, Tick, TickManager,
+    TimeManager,
+};
+#[cfg(feature = "server")]
+use crate::server::config::ServerConfig;
+#[cfg(feature = "server")]
+use crate::server::networking::is_started;
+use crate::shared::config::NetworkScheduleConfig;
+use crate::shared::sets::{ClientMarker, InternalMainSet, ServerMarker};
+
+/// Message used to send a scheduled event from the server to the clients
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScheduledEventMessage<E> {
+    pub tick: Tick,
+    pub event: E,
+}
+
+/// Bevy [`Event`] emitted when the server tick of a scheduled event is reached
+#[derive(Event, Debug)]
+pub struct ScheduledEvent<E> {
+    pub event: E,
+    /// Server tick at which the event was scheduled
+    pub tick: Tick,
+    /// How long after the scheduled moment the event was triggered.
+    ///
+    /// This is close to zero, except for clients that joined after the event was scheduled.
+    pub late_by: Duration,
+}
+
+#[derive(Debug)]
+struct ScheduledEntry<E> {
+    tick: Tick,
+    event: E,
+    /// Whether the event was already triggered for the local client in host-server mode
+    triggered: bool,
+}
+
+/// Resource used on the server to schedule events that will be triggered at the same time on every client
+#[derive(Resource, Debug)]
+pub struct EventScheduler<E> {
+    /// Events scheduled since the last time the events were sent
+    new_events: Vec<ScheduledEntry<E>>,
+    /// Events that were already sent, kept so that they can be sent to the clients that connect later
+    sent_events: Vec<ScheduledEntry<E>>,
+    /// How long after being triggered an event is still sent to the clients that connect
+    pub catch_up_window: Duration,
+}
+
+impl<E> Default for EventScheduler<E> {
+    fn default() -> Self {
+        Self {
+            new_events: Vec::new(),
+            sent_events: Vec::new(),
+            catch_up_window: Duration::default(),
+        }
+    }
+}
+
+impl<E> EventScheduler<E> {
+    pub fn with_catch_up_window(mut self, catch_up_window: Duration) -> Self {
+        self.catch_up_window = catch_up_window;
+        self
+    }
+
+    /// Schedule the event to be triggered on every client at the server tick `tick`
+    pub fn schedule(&mut self, tick: Tick, event: E) {
+        self.new_events.push(ScheduledEntry {
+            tick,
+            event,
+            triggered: false,
+        });
+    }
+
+    /// Schedule the event to be triggered on every client after `delay`.
+    ///
+    /// The delay should be longer than the latency of the clients, otherwise the event will
+    /// be triggered late.
+    pub fn schedule_in(&mut self, tick_manager: &TickManager, delay: Duration, event: E) {
+        let ticks = delay.as_secs_f32() / tick_manager.config.tick_duration.as_secs_f32();
+        let tick = tick_manager.tick() + (ticks.ceil() as i16);
+        self.schedule(tick, event);
+    }
+
+    /// Events that are scheduled (or were triggered less than `catch_up_window` ago)
+    pub fn scheduled_events(&self) -> impl Iterator<Item = (Tick, &E)> {
+        self.sent_events
+            .iter()
+            .chain(self.new_events.iter())
+            .map(|entry| (entry.tick, &entry.event))
+    }
+}
+
+/// Plugin that registers the event `E` so that it can be scheduled with the [`EventScheduler`].
+///
+/// It must be added to both the client and the server apps.
+pub struct ScheduledEventPlugin<E> {
+    _marker: PhantomData<E>,
+}
+
+impl<E> Default for ScheduledEventPlugin<E> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<E: Message + Clone> Plugin for ScheduledEventPlugin<E> {
+    fn build(&self, _app: &mut App) {}
+
+    fn finish(&self, app: &mut App) {
+        app.add_message::<ScheduledEventMessage<E>>(ChannelDirection::ServerToClient);
+        app.add_event::<ScheduledEvent<E>>();
+        let schedules = NetworkScheduleConfig::get(app);
+        #[cfg(feature = "client")]
+        if app.world.get_resource::<ClientConfig>().is_some() {
+            app.init_resource::<ReceivedEvents<E>>();
+            app.add_systems(
+                schedules.receive,
+                trigger_received_events::<E>
+                    .after(InternalMainSet::<ClientMarker>::EmitEvents)
+                    .run_if(not(SharedConfig::is_host_server_condition).and_then(is_connected)),
+            );
+        }
+        #[cfg(feature = "server")]
+        if app.world.get_resource::<ServerConfig>().is_some() {
+            app.init_resource::<EventScheduler<E>>();
+            app.add_systems(
+                schedules.send,
+                send_scheduled_events::<E>
+                    .before(InternalMainSet::<ServerMarker>::Send)
+                    .run_if(is_started),
+            );
+            // in host-server mode, the local client shares the server's tick
+            app.add_systems(
+                schedules.receive,
+                trigger_host_server_events::<E>
+                    .after(InternalMainSet::<ServerMarker>::EmitEvents)
+                    .run_if(SharedConfig::is_host_server_condition.and_then(is_started)),
+            );
+        }
+    }
+}
+
+/// Send the newly scheduled events to all the clients, and the pending events to the newly connected clients
+#[cfg(feature = "server")]
+fn send_scheduled_events<E: Message + Clone>(
+    tick_manager: Res<TickManager>,
+    mut scheduler: ResMut<EventScheduler<E>>,
+    mut connection_manager: ResMut<server::ConnectionManager>,
+) {
+    let current_tick = tick_manager.tick();
+    let tick_duration = tick_manager.config.tick_duration.as_secs_f32();
+    let window = (scheduler.catch_up_window.as_secs_f32() / tick_duration).ceil() as i16;
+    scheduler
+        .sent_events
+        .retain(|entry| current_tick - entry.tick <= window);
+
+    // the newly scheduled events are broadcast to every client below, including the new ones
+    if !connection_manager.new_clients.is_empty() {
+        let target = NetworkTarget::Only(connection_manager.new_clients.clone());
+        for entry in scheduler.sent_events.iter() {
+            let message = ScheduledEventMessage {
+                tick: entry.tick,
+                event: entry.event.clone(),
+            };
+            connection_manager
+                .send_message_to_target::<ScheduledEventChannel, _>(&message, target.clone())
+                .unwrap_or_else(|e| {
+                    tracing::error!("could not send scheduled event: {:?}", e);
+                });
+        }
+    }
+    let new_events = std::mem::take(&mut scheduler.new_events);
+    for entry in new_events {
+        let message = ScheduledEventMessage {
+            tick: entry.tick,
+            event: entry.event.clone(),
+        };
+        connection_manager
+            .send_message_to_target::<ScheduledEventChannel, _>(&message, NetworkTarget::All)
+            .unwrap_or_else(|e| {
+                tracing::error!("could not send scheduled event: {:?}", e);
+            });
+        scheduler.sent_events.push(entry);
+    }
+}
+
+/// Events received by the client that have not been triggered yet
+#[cfg(feature = "client")]
+#[derive(Resource, Debug)]
+struct ReceivedEvents<E> {
+    events: Vec<(Tick, E)>,
+}
+
+#[cfg(feature = "client")]
+impl<E> Default for ReceivedEvents<E> {
+    fn default() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+/// Trigger the received events once the estimated server tick reaches their scheduled tick
+#[cfg(feature = "client")]
+fn trigger_received_events<E: Message + Clone>(
+    tick_manager: Res<TickManager>,
+    time_manager: Res<TimeManager>,
+    connection_manager: Res<client::ConnectionManager>,
+    mut messages: EventReader<client::MessageEvent<ScheduledEventMessage<E>>>,
+    mut received: ResMut<ReceivedEvents<E>>,
+    mut writer: EventWriter<ScheduledEvent<E>>,
+) {
+    received.events.extend(
+        messages
+            .read()
+            .map(|message| (message.message.tick, message.message.event.clone())),
+    );
+    // we cannot know the server tick before the client is synced
+    let Some(offset) = connection_manager.prediction_tick_offset(&tick_manager, &time_manager)
+    else {
+        return;
+    };
+    let current_tick = tick_manager.tick();
+    let tick_duration = tick_manager.config.tick_duration;
+    // the server time estimate corresponds to the server packets that we just received,
+    // the server is ahead of it by the one-way latency
+    let latency =
+        connection_manager.ping_manager.rtt().as_secs_f32() / 2.0 / tick_duration.as_secs_f32();
+    received.events.retain(|(tick, event)| {
+        // number of ticks elapsed on the server since the scheduled tick
+        let elapsed = (current_tick - *tick) as f32 - offset + latency;
+        if elapsed < 0.0 {
+            return true;
+        }
+        writer.send(ScheduledEvent {
+            event: event.clone(),
+            tick: *tick,
+            late_by: tick_duration.mul_f32(elapsed),
+        });
+        false
+    });
+}
+
+/// In host-server mode, trigger the events on the server's tick directly
+#[cfg(feature = "server")]
+fn trigger_host_server_events<E: Message + Clone>(
+    tick_manager: Res<TickManager>,
+    mut scheduler: ResMut<EventScheduler<E>>,
+    mut writer: EventWriter<ScheduledEvent<E>>,
+) {
+    let current_tick = tick_manager.tick();
+    let tick_duration = tick_manager.config.tick_duration;
+    let scheduler = scheduler.as_mut();
+    for entry in scheduler
+        .sent_events
+        .iter_mut()
+        .chain(scheduler.new_events.iter_mut())
+    {
+        let elapsed = current_tick - entry.tick;
+        if elapsed < 0 || entry.triggered {
+            continue;
+        }
+        entry.triggered = true;
+        writer.send(ScheduledEvent {
+            event: entry.event.clone(),
+            tick: entry.tick,
+            late_by: tick_duration * elapsed as u32,
+        });
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::Events;
+
+    use super::*;
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::{LinkConditionerConfig, TickConfig};
+    use crate::tests::protocol::Message1;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    #[test]
+    fn test_scheduled_event() {
+        let frame_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(frame_duration),
+                ..Default::default()
+            },
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::from_millis(30),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            frame_duration,
+        );
+        stepper
+            .client_app
+            .add_plugins(ScheduledEventPlugin::<Message1>::default());
+        stepper
+            .server_app
+            .add_plugins(ScheduledEventPlugin::<Message1>::default());
+        stepper.init();
+
+        let scheduled_tick = stepper.server_tick() + 20;
+        stepper
+            .server_app
+            .world
+            .resource_mut::<EventScheduler<Message1>>()
+            .schedule(scheduled_tick, Message1("fireworks".to_string()));
+
+        let mut triggered = None;
+        for _ in 0..40 {
+            stepper.frame_step();
+            let events: Vec<_> = stepper
+                .client_app
+                .world
+                .resource_mut::<Events<ScheduledEvent<Message1>>>()
+                .drain()
+                .collect();
+            if !events.is_empty() {
+                assert_eq!(events.len(), 1);
+                assert!(triggered.is_none());
+                triggered = Some(stepper.server_tick());
+                assert_eq!(events[0].event, Message1("fireworks".to_string()));
+                assert_eq!(events[0].tick, scheduled_tick);
+                assert!(events[0].late_by < frame_duration * 2);
+            }
+        }
+        // the client triggered the event when the server reached the scheduled tick
+        let triggered = triggered.unwrap();
+        assert!((triggered - scheduled_tick).abs() <= 1);
+    }
+}
@@ -66,15 +66,15 @@ pub enum MainSet {
     /// Systems that receive data (buffer any data received from transport, and read
     /// data from the buffers)
     ///
-    /// Runs in `PreUpdate`.
+    /// Runs in `PreUpdate` by default (see [`NetworkScheduleConfig`](crate::prelude::NetworkScheduleConfig)).
     Receive,
     /// Systems that emit networking-related events
-    /// Runs in `PreUpdate`, after `Receive`
+    /// Runs in the same schedule as `Receive`, after `Receive`
     EmitEvents,
 
     /// Systems that send data (buffer any data to be sent, and send any buffered packets)
     ///
-    /// Runs in `PostUpdate`.
+    /// Runs in `PostUpdate` by default (see [`NetworkScheduleConfig`](crate::prelude::NetworkScheduleConfig)).
     SendPackets,
     /// System to encompass all send-related systems. Runs only every send_interval
     Send,
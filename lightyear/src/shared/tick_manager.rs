@@ -3,9 +3,10 @@ use bevy::prelude::*;
 use bevy::utils::Duration;
 use tracing::trace;
 
+#[cfg(feature = "client")]
 use crate::client::prediction::plugin::is_in_rollback;
+#[cfg(feature = "client")]
 use crate::client::prediction::rollback::Rollback;
-use crate::prelude::client::RollbackState;
 use crate::prelude::FixedUpdateSet;
 use crate::utils::wrapping_id::wrapping_id;
 
@@ -30,6 +31,11 @@ pub(crate) fn increment_tick(mut tick_manager: ResMut<TickManager>) {
 
 impl Plugin for TickManagerPlugin {
     fn build(&self, app: &mut App) {
+        let increment = increment_tick.in_set(FixedUpdateSet::TickUpdate);
+        // run if there is no rollback resource, or if we are not in rollback
+        #[cfg(feature = "client")]
+        let increment =
+            increment.run_if(not(resource_exists::<Rollback>).or_else(not(is_in_rollback)));
         app
             // EVENTS
             .add_event::<TickEvent>()
@@ -39,13 +45,7 @@ impl Plugin for TickManagerPlugin {
             // SYSTEM SETS
             .configure_sets(FixedFirst, FixedUpdateSet::TickUpdate)
             // SYSTEMS
-            .add_systems(
-                FixedFirst,
-                (increment_tick
-                    .in_set(FixedUpdateSet::TickUpdate)
-                    // run if there is no rollback resource, or if we are not in rollback
-                    .run_if(not(resource_exists::<Rollback>).or_else(not(is_in_rollback))),),
-            );
+            .add_systems(FixedFirst, increment);
     }
 }
 
@@ -100,6 +100,7 @@ impl TickManager {
     }
 
     /// Get the current tick of the app; works even if we are in rollback
+    #[cfg(feature = "client")]
     pub fn tick_or_rollback_tick(&self, rollback_state: &Rollback) -> Tick {
         rollback_state.get_rollback_tick().unwrap_or(self.tick)
     }
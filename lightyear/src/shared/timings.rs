@@ -0,0 +1,197 @@
+//! Measure how much time is spent every frame in the various networking [`SystemSet`]s
+//!
+//! The [`NetworkTimings`] resource is updated at the end of every frame (in the `Last` schedule),
+//! so any system reading it will see the timings of the latest complete frame.
+//!
+//! The timings are measured as the wall-clock time between the start and the end of each set, so they
+//! can include the time spent in other systems that are running in parallel.
+use bevy::app::{App, Last};
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::{IntoSystemConfigs, Plugin, Reflect, ResMut, Resource, SystemSet};
+use bevy::utils::{Duration, Instant};
+
+use crate::shared::sets::{ClientMarker, ServerMarker};
+
+/// The networking phases that are being timed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum TimedPhase {
+    /// Receiving packets from the transport and reading them into messages (`MainSet::Receive`)
+    Receive,
+    /// Writing the networking bevy events (`MainSet::EmitEvents`)
+    EmitEvents,
+    /// Gathering and buffering the replication messages. This is a subset of [`TimedPhase::Send`]
+    ReplicationBuffer,
+    /// Buffering and sending the packets (`MainSet::Send`)
+    Send,
+    /// Running the rollback schedule (client only)
+    Rollback,
+}
+
+impl TimedPhase {
+    const COUNT: usize = 5;
+
+    fn index(self) -> usize {
+        match self {
+            TimedPhase::Receive => 0,
+            TimedPhase::EmitEvents => 1,
+            TimedPhase::ReplicationBuffer => 2,
+            TimedPhase::Send => 3,
+            TimedPhase::Rollback => 4,
+        }
+    }
+}
+
+/// Time spent in each networking phase during the latest frame, for a single peer (client or server)
+#[derive(Debug, Default, Clone, PartialEq, Reflect)]
+pub struct PeerTimings {
+    pub receive: Duration,
+    pub emit_events: Duration,
+    pub replication_buffer: Duration,
+    pub send: Duration,
+    pub rollback: Duration,
+    /// Durations accumulated during the current frame
+    #[reflect(ignore)]
+    current: [Duration; TimedPhase::COUNT],
+    /// Start instant of the phases that are currently running
+    #[reflect(ignore)]
+    started: [Option<Instant>; TimedPhase::COUNT],
+}
+
+impl PeerTimings {
+    /// Get the time spent in the given phase during the latest frame
+    pub fn get(&self, phase: TimedPhase) -> Duration {
+        match phase {
+            TimedPhase::Receive => self.receive,
+            TimedPhase::EmitEvents => self.emit_events,
+            TimedPhase::ReplicationBuffer => self.replication_buffer,
+            TimedPhase::Send => self.send,
+            TimedPhase::Rollback => self.rollback,
+        }
+    }
+
+    /// Total time spent in networking phases during the latest frame.
+    ///
+    /// (`ReplicationBuffer` is not counted separately since it is included in `Send`)
+    pub fn total(&self) -> Duration {
+        self.receive + self.emit_events + self.send + self.rollback
+    }
+
+    fn start(&mut self, phase: TimedPhase) {
+        self.started[phase.index()] = Some(Instant::now());
+    }
+
+    fn stop(&mut self, phase: TimedPhase) {
+        if let Some(start) = self.started[phase.index()].take() {
+            self.current[phase.index()] += start.elapsed();
+        }
+    }
+
+    /// Publish the durations accumulated during the frame, and reset them for the next frame
+    fn finish_frame(&mut self) {
+        let current = std::mem::take(&mut self.current);
+        self.receive = current[TimedPhase::Receive.index()];
+        self.emit_events = current[TimedPhase::EmitEvents.index()];
+        self.replication_buffer = current[TimedPhase::ReplicationBuffer.index()];
+        self.send = current[TimedPhase::Send.index()];
+        self.rollback = current[TimedPhase::Rollback.index()];
+    }
+}
+
+/// Resource that records the time spent every frame in the networking system sets
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use lightyear::prelude::*;
+///
+/// fn log_timings(timings: Res<NetworkTimings>) {
+///     info!(send = ?timings.server.send, receive = ?timings.server.receive, "server networking timings");
+/// }
+/// ```
+#[derive(Resource, Debug, Default, Clone, PartialEq, Reflect)]
+pub struct NetworkTimings {
+    pub client: PeerTimings,
+    pub server: PeerTimings,
+}
+
+/// Trait to identify which [`PeerTimings`] of the [`NetworkTimings`] a marker corresponds to
+pub(crate) trait TimingsMarker: Send + Sync + 'static {
+    fn timings(timings: &mut NetworkTimings) -> &mut PeerTimings;
+}
+
+impl TimingsMarker for ClientMarker {
+    fn timings(timings: &mut NetworkTimings) -> &mut PeerTimings {
+        &mut timings.client
+    }
+}
+
+impl TimingsMarker for ServerMarker {
+    fn timings(timings: &mut NetworkTimings) -> &mut PeerTimings {
+        &mut timings.server
+    }
+}
+
+/// Add systems that record the time spent in the `set` as the given [`TimedPhase`]
+pub(crate) fn add_set_timing<M: TimingsMarker>(
+    app: &mut App,
+    schedule: impl ScheduleLabel,
+    set: impl SystemSet + Clone,
+    phase: TimedPhase,
+) {
+    app.add_systems(
+        schedule,
+        (
+            (move |mut timings: ResMut<NetworkTimings>| M::timings(&mut timings).start(phase))
+                .before(set.clone()),
+            (move |mut timings: ResMut<NetworkTimings>| M::timings(&mut timings).stop(phase))
+                .after(set),
+        ),
+    );
+}
+
+fn finish_frame(mut timings: ResMut<NetworkTimings>) {
+    timings.client.finish_frame();
+    timings.server.finish_frame();
+}
+
+pub(crate) struct TimingsPlugin;
+
+impl Plugin for TimingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<NetworkTimings>();
+        app.init_resource::<NetworkTimings>();
+        app.add_systems(Last, finish_frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Update;
+
+    use super::*;
+
+    #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+    struct SlowSet;
+
+    #[test]
+    fn test_set_timing() {
+        let mut app = App::new();
+        app.add_plugins(TimingsPlugin);
+        add_set_timing::<ServerMarker>(&mut app, Update, SlowSet, TimedPhase::Send);
+        app.add_systems(
+            Update,
+            (|| std::thread::sleep(Duration::from_millis(2))).in_set(SlowSet),
+        );
+
+        // the timings are only published at the end of the frame
+        assert_eq!(
+            app.world.resource::<NetworkTimings>().server.send,
+            Duration::ZERO
+        );
+        app.update();
+        let timings = app.world.resource::<NetworkTimings>();
+        assert!(timings.server.send >= Duration::from_millis(2));
+        assert_eq!(timings.server.get(TimedPhase::Send), timings.server.send);
+        assert_eq!(timings.server.receive, Duration::ZERO);
+        assert_eq!(timings.client, PeerTimings::default());
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use lightyear_macros::ChannelInternal;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+use crate::transport::middleware::chaos::ReorderConfig;
+
+#[derive(ChannelInternal)]
+struct ReliableChannel;
+
+/// Messages sent on an ordered reliable channel are delivered exactly once and in order,
+/// even if the packets are dropped, duplicated, corrupted, reordered or stalled
+#[test]
+fn test_ordered_reliable_with_chaos() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    for app in [&mut stepper.client_app, &mut stepper.server_app] {
+        app.add_channel::<ReliableChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        });
+    }
+    stepper.init();
+    let client_chaos = stepper.client_chaos(0);
+    let server_chaos = stepper.server_chaos(1);
+    client_chaos.drop_next(3);
+    client_chaos.duplicate_next(3);
+    client_chaos.corrupt_next(1);
+    client_chaos.reorder(Some(ReorderConfig {
+        probability: 0.5,
+        max_displacement: 3,
+    }));
+    // the acks sent by the client are disrupted as well
+    server_chaos.drop_next(2);
+    server_chaos.duplicate_next(2);
+
+    let sent = (0..10).map(|i| Message1(i.to_string())).collect::<Vec<_>>();
+    let mut received = vec![];
+    for (i, message) in sent.iter().enumerate() {
+        stepper
+            .server_app
+            .world
+            .resource_mut::<server::ConnectionManager>()
+            .send_message::<ReliableChannel, _>(ClientId::Netcode(TEST_CLIENT_ID), message)
+            .unwrap();
+        if i == 5 {
+            client_chaos.stall(Duration::from_millis(50));
+        }
+        stepper.frame_step();
+    }
+    client_chaos.reorder(None);
+    for _ in 0..100 {
+        stepper.frame_step();
+        received.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    let stats = client_chaos.stats();
+    assert_eq!(stats.dropped, 3);
+    assert_eq!(stats.duplicated, 3);
+    assert!(stats.reordered > 0);
+    assert_eq!(received, sent);
+}
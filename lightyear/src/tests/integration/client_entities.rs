@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::server::{
+    ConnectedClient, ReplicationPriorities, SendQueues, VisibilityManager, VisibleEntities,
+};
+use crate::prelude::*;
+use crate::shared::replication::components::ReplicationGroupId;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+/// The networking state of each client is stored on the client entity
+#[test]
+fn test_client_entity_components() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.init();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+    let client_entity = stepper
+        .server_app
+        .world
+        .resource::<server::ConnectionManager>()
+        .client_entity(client_id)
+        .unwrap();
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .get::<ConnectedClient>(client_entity),
+        Some(&ConnectedClient(client_id))
+    );
+
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn(server::Replicate {
+            visibility: VisibilityMode::InterestManagement,
+            ..default()
+        })
+        .id();
+    stepper
+        .server_app
+        .world
+        .resource_mut::<VisibilityManager>()
+        .gain_visibility(client_id, server_entity);
+    stepper.frame_step();
+
+    let world = &stepper.server_app.world;
+    assert!(world
+        .get::<VisibleEntities>(client_entity)
+        .unwrap()
+        .contains(&server_entity));
+    assert!(world
+        .get::<ReplicationPriorities>(client_entity)
+        .unwrap()
+        .contains_key(&ReplicationGroupId(server_entity.to_bits())));
+
+    // the visible entities are updated when the entity loses visibility
+    stepper
+        .server_app
+        .world
+        .resource_mut::<VisibilityManager>()
+        .lose_visibility(client_id, server_entity);
+    stepper.frame_step();
+    assert!(stepper
+        .server_app
+        .world
+        .get::<VisibleEntities>(client_entity)
+        .unwrap()
+        .is_empty());
+
+    // the client entities can be accessed with a regular query
+    let mut query = stepper
+        .server_app
+        .world
+        .query::<(&ConnectedClient, &SendQueues)>();
+    let (client, queues) = query.single(&stepper.server_app.world);
+    assert_eq!(client.0, client_id);
+    assert!(!queues.is_empty());
+}
+
+/// The send system uses the priorities stored on the client entity
+#[test]
+fn test_modified_replication_priorities() {
+    let mut stepper = BevyStepper::default();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+    let client_entity = stepper
+        .server_app
+        .world
+        .resource::<server::ConnectionManager>()
+        .client_entity(client_id)
+        .unwrap();
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), server::Replicate::default()))
+        .id();
+    for _ in 0..3 {
+        stepper.frame_step();
+    }
+    let group_id = ReplicationGroupId(server_entity.to_bits());
+    let priority = |stepper: &BevyStepper| {
+        stepper
+            .server_app
+            .world
+            .get::<ReplicationPriorities>(client_entity)
+            .unwrap()[&group_id]
+    };
+    // the entity doesn't change, so its priority keeps accumulating from the modified value
+    stepper
+        .server_app
+        .world
+        .get_mut::<ReplicationPriorities>(client_entity)
+        .unwrap()
+        .insert(group_id, 100.0);
+    stepper.frame_step();
+    assert!(priority(&stepper) > 100.0);
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+/// A client that uses an older version of the protocol receives the components
+/// in the representation of its own version
+#[test]
+fn test_component_migration() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    // in version 2, the value of Component1 was halved
+    stepper.client_app.set_protocol_version(1);
+    stepper.server_app.set_protocol_version(2);
+    stepper
+        .server_app
+        .add_component_migration::<Component1, Component1>(
+            2,
+            |component| Component1(component.0 * 2.0),
+            |old| Component1(old.0 / 2.0),
+        );
+    stepper.init();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .client_protocol_version(client_id),
+        Some(1)
+    );
+
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), server::Replicate::default()))
+        .id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .expect("entity was not replicated to client");
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .get::<Component1>(client_entity)
+            .unwrap(),
+        &Component1(2.0)
+    );
+
+    // updates are migrated as well
+    stepper
+        .server_app
+        .world
+        .get_mut::<Component1>(server_entity)
+        .unwrap()
+        .0 = 3.0;
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .get::<Component1>(client_entity)
+            .unwrap(),
+        &Component1(6.0)
+    );
+}
@@ -0,0 +1,143 @@
+//! The server can validate the values of the components that the clients replicate to it
+use bevy::prelude::*;
+
+use crate::prelude::server::ConnectionManager;
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+/// The clients cannot move by more than 1.0 per update, starting from 0.0
+fn validate_component6(
+    old: Option<&Component6>,
+    new: &Component6,
+    _: ClientId,
+) -> Validation<Component6> {
+    if new.0 < 0.0 {
+        return Validation::Reject { correct: true };
+    }
+    let old = old.map_or(0.0, |old| old.0);
+    if new.0 - old > 1.0 {
+        return Validation::Clamp {
+            value: Component6(old + 1.0),
+            correct: true,
+        };
+    }
+    Validation::Accept
+}
+
+/// Spawn an entity with `Component6` on the client and return the (client, server) entities
+fn setup(stepper: &mut BevyStepper) -> (Entity, Entity) {
+    stepper
+        .server_app
+        .world
+        .resource_mut::<ComponentRegistry>()
+        .set_validation::<Component6>(validate_component6);
+    let client_entity = stepper
+        .client_app
+        .world
+        .spawn((Component6(0.0), client::Replicate::default()))
+        .id();
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    let server_entity = *stepper
+        .server_app
+        .world
+        .resource::<ConnectionManager>()
+        .connection(ClientId::Netcode(TEST_CLIENT_ID))
+        .expect("client connection missing")
+        .replication_receiver
+        .remote_entity_map
+        .get_local(client_entity)
+        .expect("entity was not replicated to server");
+    (client_entity, server_entity)
+}
+
+fn update_client_value(stepper: &mut BevyStepper, client_entity: Entity, value: f32) {
+    stepper
+        .client_app
+        .world
+        .entity_mut(client_entity)
+        .insert(Component6(value));
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+}
+
+#[test]
+fn test_validation_accept() {
+    let mut stepper = BevyStepper::default();
+    let (client_entity, server_entity) = setup(&mut stepper);
+
+    update_client_value(&mut stepper, client_entity, 0.5);
+    assert_eq!(
+        stepper.server_app.world.get::<Component6>(server_entity),
+        Some(&Component6(0.5))
+    );
+    assert_eq!(
+        stepper.client_app.world.get::<Component6>(client_entity),
+        Some(&Component6(0.5))
+    );
+}
+
+#[test]
+fn test_validation_clamp() {
+    let mut stepper = BevyStepper::default();
+    let (client_entity, server_entity) = setup(&mut stepper);
+
+    update_client_value(&mut stepper, client_entity, 5.0);
+    assert_eq!(
+        stepper.server_app.world.get::<Component6>(server_entity),
+        Some(&Component6(1.0))
+    );
+    // the clamped value was sent back to the client
+    assert_eq!(
+        stepper.client_app.world.get::<Component6>(client_entity),
+        Some(&Component6(1.0))
+    );
+}
+
+#[test]
+fn test_validation_reject() {
+    let mut stepper = BevyStepper::default();
+    let (client_entity, server_entity) = setup(&mut stepper);
+
+    update_client_value(&mut stepper, client_entity, 0.5);
+    update_client_value(&mut stepper, client_entity, -3.0);
+    assert_eq!(
+        stepper.server_app.world.get::<Component6>(server_entity),
+        Some(&Component6(0.5))
+    );
+    // the client was corrected to the last accepted value
+    assert_eq!(
+        stepper.client_app.world.get::<Component6>(client_entity),
+        Some(&Component6(0.5))
+    );
+}
+
+/// The client cannot bypass the validation by removing the component and inserting it again
+#[test]
+fn test_validation_reinsert() {
+    let mut stepper = BevyStepper::default();
+    let (client_entity, server_entity) = setup(&mut stepper);
+
+    stepper
+        .client_app
+        .world
+        .entity_mut(client_entity)
+        .remove::<Component6>();
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    assert!(stepper
+        .server_app
+        .world
+        .get::<Component6>(server_entity)
+        .is_none());
+
+    update_client_value(&mut stepper, client_entity, 5.0);
+    assert_eq!(
+        stepper.server_app.world.get::<Component6>(server_entity),
+        Some(&Component6(1.0))
+    );
+}
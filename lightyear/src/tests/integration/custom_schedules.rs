@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::server::Replicate;
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+
+/// The networking system sets can run in other schedules than `PreUpdate`/`PostUpdate`
+#[test]
+fn test_custom_network_schedules() {
+    let frame_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        tick: TickConfig::new(frame_duration),
+        schedules: NetworkScheduleConfig::default()
+            .with_receive(First)
+            .with_send(Last),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.init();
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .is_synced());
+
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), Replicate::default()))
+        .id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .expect("entity was not replicated to client");
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .get::<Component1>(client_entity)
+            .unwrap(),
+        &Component1(1.0)
+    );
+}
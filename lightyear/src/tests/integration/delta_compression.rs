@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::protocol::component::ComponentRegistry;
+use crate::shared::replication::delta::{DeltaHistory, Diffable};
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+impl Diffable for Component1 {
+    type Delta = f32;
+
+    fn diff(&self, new: &Self) -> Self::Delta {
+        new.0 - self.0
+    }
+
+    fn apply_diff(&mut self, delta: &Self::Delta) {
+        self.0 += delta;
+    }
+}
+
+/// The updates are sent as a difference with the last value acked by the client
+#[test]
+fn test_delta_compression() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.client_app.add_delta_compression::<Component1>();
+    stepper.server_app.add_delta_compression::<Component1>();
+    stepper.init();
+
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), server::Replicate::default()))
+        .id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .expect("entity was not replicated to client");
+    for i in 2..6 {
+        stepper
+            .server_app
+            .world
+            .get_mut::<Component1>(server_entity)
+            .unwrap()
+            .0 = i as f32;
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .get::<Component1>(client_entity)
+                .unwrap(),
+            &Component1(i as f32)
+        );
+    }
+    // the client keeps the values it received to use them as baselines
+    assert!(stepper
+        .client_app
+        .world
+        .get::<DeltaHistory<Component1>>(client_entity)
+        .is_some());
+
+    // the server has a baseline for the next update, once the last update is acked
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    let net_id = stepper
+        .server_app
+        .world
+        .resource::<ComponentRegistry>()
+        .net_id::<Component1>();
+    let tick = stepper.server_tick();
+    let group_id = ReplicationGroup::default().group_id(Some(server_entity));
+    let base = stepper
+        .server_app
+        .world
+        .resource::<server::ConnectionManager>()
+        .connection(ClientId::Netcode(TEST_CLIENT_ID))
+        .unwrap()
+        .replication_sender
+        .delta
+        .base(group_id, server_entity, net_id, tick)
+        .map(|(_, value)| value.downcast_ref::<Component1>().unwrap().clone());
+    assert_eq!(base, Some(Component1(5.0)));
+}
@@ -0,0 +1,239 @@
+//! Components that are not part of the protocol can be registered at runtime by their type path
+//! and replicated with reflection
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+/// A component provided by a mod, that is only registered in the type registry
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component, PartialEq)]
+struct ModComponent {
+    value: f32,
+    name: String,
+}
+
+/// Another component provided by a mod, that is only registered on the client
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component, PartialEq)]
+struct ClientModComponent(f32);
+
+fn register_mod_component(app: &mut App) {
+    app.register_type::<ModComponent>();
+    app.world.run_system_once(|mut commands: Commands| {
+        commands.register_dynamic_component(ModComponent::type_path());
+    });
+}
+
+fn stepper() -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.init();
+    stepper
+}
+
+fn client_entity(stepper: &BevyStepper, server_entity: Entity) -> Entity {
+    *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .expect("entity was not replicated to client")
+}
+
+#[test]
+fn test_replicate_dynamic_component() {
+    let mut stepper = stepper();
+    register_mod_component(&mut stepper.server_app);
+    register_mod_component(&mut stepper.client_app);
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    assert!(stepper
+        .server_app
+        .world
+        .resource::<server::ConnectionManager>()
+        .has_dynamic_component(ClientId::Netcode(TEST_CLIENT_ID), ModComponent::type_path()));
+
+    let component = ModComponent {
+        value: 1.0,
+        name: "a".to_string(),
+    };
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((component.clone(), server::Replicate::default()))
+        .id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let client_entity = client_entity(&stepper, server_entity);
+    assert_eq!(
+        stepper.client_app.world.get::<ModComponent>(client_entity),
+        Some(&component)
+    );
+
+    // update
+    stepper
+        .server_app
+        .world
+        .get_mut::<ModComponent>(server_entity)
+        .unwrap()
+        .value = 2.0;
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .get::<ModComponent>(client_entity)
+            .map(|component| component.value),
+        Some(2.0)
+    );
+
+    // removal
+    stepper
+        .server_app
+        .world
+        .entity_mut(server_entity)
+        .remove::<ModComponent>();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    assert!(stepper
+        .client_app
+        .world
+        .get::<ModComponent>(client_entity)
+        .is_none());
+}
+
+/// The dynamic component is only replicated to a client once it registered the component too
+#[test]
+fn test_dynamic_component_registered_later_on_client() {
+    let mut stepper = stepper();
+    register_mod_component(&mut stepper.server_app);
+    let component = ModComponent {
+        value: 1.0,
+        name: "a".to_string(),
+    };
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((component.clone(), server::Replicate::default()))
+        .id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let client_entity = client_entity(&stepper, server_entity);
+    stepper.client_app.register_type::<ModComponent>();
+    assert!(stepper
+        .client_app
+        .world
+        .get::<ModComponent>(client_entity)
+        .is_none());
+
+    // once the client registers the component, it receives its current value
+    register_mod_component(&mut stepper.client_app);
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    assert_eq!(
+        stepper.client_app.world.get::<ModComponent>(client_entity),
+        Some(&component)
+    );
+}
+
+/// The network ids of the dynamic components depend on the order in which they were registered,
+/// so the client uses the ids assigned by the server
+#[test]
+fn test_dynamic_component_different_local_ids() {
+    let mut stepper = stepper();
+    register_mod_component(&mut stepper.server_app);
+    stepper.client_app.register_type::<ClientModComponent>();
+    stepper
+        .client_app
+        .world
+        .run_system_once(|mut commands: Commands| {
+            commands.register_dynamic_component(ClientModComponent::type_path());
+        });
+    register_mod_component(&mut stepper.client_app);
+    let registry = stepper.client_app.world.resource::<ComponentRegistry>();
+    assert_ne!(
+        registry.dynamic_net_id(ModComponent::type_path()),
+        stepper
+            .server_app
+            .world
+            .resource::<ComponentRegistry>()
+            .dynamic_net_id(ModComponent::type_path())
+    );
+
+    let component = ModComponent {
+        value: 1.0,
+        name: "a".to_string(),
+    };
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((component.clone(), server::Replicate::default()))
+        .id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let client_entity = client_entity(&stepper, server_entity);
+    assert_eq!(
+        stepper.client_app.world.get::<ModComponent>(client_entity),
+        Some(&component)
+    );
+    assert!(stepper
+        .client_app
+        .world
+        .get::<ClientModComponent>(client_entity)
+        .is_none());
+}
+
+#[test]
+fn test_register_dynamic_component_errors() {
+    let mut app = App::new();
+    app.register_type::<ModComponent>();
+    let type_registry = app.world.resource::<AppTypeRegistry>().clone();
+    let type_registry = type_registry.read();
+    let mut registry = ComponentRegistry::default();
+    assert!(registry
+        .register_dynamic_component("unknown::Component", &type_registry)
+        .is_err());
+    let net_id = registry
+        .register_dynamic_component(ModComponent::type_path(), &type_registry)
+        .unwrap();
+    assert_eq!(
+        registry.dynamic_net_id(ModComponent::type_path()),
+        Some(net_id)
+    );
+    assert!(registry.is_dynamic_component_registered(ModComponent::type_path()));
+    // registering the same component twice returns the same network id
+    assert_eq!(
+        registry
+            .register_dynamic_component(ModComponent::type_path(), &type_registry)
+            .unwrap(),
+        net_id
+    );
+}
@@ -0,0 +1,91 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use lightyear_macros::ChannelInternal;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+#[derive(ChannelInternal)]
+struct PacedChannel;
+
+fn register_paced_channel(app: &mut App) {
+    app.world.run_system_once(|mut commands: Commands| {
+        commands.register_channel::<PacedChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(
+                ReliableSettings::default().with_max_fragments_per_send(2),
+            ),
+            fragment_threshold: Some(200),
+            ..default()
+        });
+    });
+}
+
+/// A large message is split with the channel's fragmentation threshold and its fragments are paced,
+/// while the sender is notified of the progress of the message
+#[test]
+fn test_fragment_pacing() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.init();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+    register_paced_channel(&mut stepper.server_app);
+    register_paced_channel(&mut stepper.client_app);
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+
+    let message = Message1("a".repeat(2000));
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>()
+        .send_message::<PacedChannel, _>(client_id, &message)
+        .unwrap();
+    let mut progress = vec![];
+    let mut received = vec![];
+    for _ in 0..50 {
+        stepper.frame_step();
+        progress.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::FragmentProgressEvent>>()
+                .drain()
+                .map(|event| *event.progress()),
+        );
+        received.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    assert_eq!(received, vec![message]);
+    // the fragments are acked over several frames because at most 2 fragments are sent per frame
+    assert!(progress.len() > 2);
+    let last = progress.last().unwrap();
+    assert!(last.num_fragments >= 10);
+    assert!(last.is_complete());
+    assert!(progress
+        .windows(2)
+        .all(|p| p[0].fraction() < p[1].fraction()));
+}
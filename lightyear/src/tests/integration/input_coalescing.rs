@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::client::input::InputManager;
+use crate::prelude::client::SyncConfig;
+use crate::prelude::server::InputEvent;
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+
+/// The inputs received by the server
+#[derive(Resource, Default)]
+struct ReceivedInputs(Vec<MyInput>);
+
+fn receive_inputs(
+    mut events: EventReader<InputEvent<MyInput>>,
+    mut inputs: ResMut<ReceivedInputs>,
+) {
+    inputs
+        .0
+        .extend(events.read().filter_map(|event| event.input().clone()));
+}
+
+/// An input that only lasts for one frame between two ticks is still sent to the server
+#[test]
+fn test_coalesce_frame_inputs() {
+    // the ticks are 3 times longer than the frames
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(30);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        },
+        SyncConfig::default().speedup_factor(1.0),
+        client::PredictionConfig::default(),
+        client::InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.server_app.init_resource::<ReceivedInputs>();
+    stepper.server_app.add_systems(FixedUpdate, receive_inputs);
+    stepper.init();
+    // the input is a bitmask of the pressed buttons
+    stepper
+        .client_app
+        .world
+        .resource_mut::<InputManager<MyInput>>()
+        .coalesce_fn = |buttons, frame| MyInput(buttons.0 | frame.0);
+
+    // wait for a frame that runs a tick
+    let tick = stepper.client_tick();
+    while stepper.client_tick() == tick {
+        stepper.frame_step();
+    }
+    add_frame_input(&mut stepper, MyInput(1));
+    // the client is ahead of the server, so we wait until the server reaches the ticks of the inputs
+    for _ in 0..30 {
+        add_frame_input(&mut stepper, MyInput(2));
+    }
+    let inputs = &stepper.server_app.world.resource::<ReceivedInputs>().0;
+    // the input of the first frame is combined with the inputs of the next frames until the tick runs
+    assert!(inputs.first().is_some_and(|input| input.0 & 1 == 1));
+    assert!(inputs[1..].iter().all(|input| *input == MyInput(2)));
+}
+
+fn add_frame_input(stepper: &mut BevyStepper, input: MyInput) {
+    stepper
+        .client_app
+        .world
+        .resource_mut::<InputManager<MyInput>>()
+        .add_frame_input(input);
+    stepper.frame_step();
+}
+
+/// The inputs used by the ticks of the client
+#[derive(Resource, Default)]
+struct ClientInputs(Vec<Option<MyInput>>);
+
+fn record_client_inputs(
+    mut events: EventReader<client::InputEvent<MyInput>>,
+    mut inputs: ResMut<ClientInputs>,
+) {
+    inputs
+        .0
+        .extend(events.read().map(|event| event.input().clone()));
+}
+
+/// An input that is not added anymore is not repeated on the ticks of the next frames
+#[test]
+fn test_released_frame_input() {
+    let mut stepper = BevyStepper::default();
+    stepper.client_app.init_resource::<ClientInputs>();
+    stepper
+        .client_app
+        .add_systems(FixedUpdate, record_client_inputs);
+    // one tick runs in each frame
+    add_frame_input(&mut stepper, MyInput(1));
+    stepper.frame_step();
+    stepper.frame_step();
+    // (the events written before the input was added can also be read during the first frame)
+    let inputs = &stepper.client_app.world.resource::<ClientInputs>().0;
+    assert!(
+        inputs.ends_with(&[Some(MyInput(1)), None, None]),
+        "{inputs:?}"
+    );
+}
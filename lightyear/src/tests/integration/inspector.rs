@@ -0,0 +1,107 @@
+//! Tests related to the inspection of the server world by an admin client
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InspectorClientPlugin, SyncConfig};
+use crate::prelude::server::{InspectorConfig, Replicate};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+
+const ADMIN_TOKEN: &str = "admin-token";
+
+fn setup(client_token: &str) -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        client::PredictionConfig::default(),
+        client::InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ServerConfig>()
+        .inspector =
+        Some(InspectorConfig::new(ADMIN_TOKEN).with_update_interval(Duration::from_millis(50)));
+    stepper
+        .client_app
+        .add_plugins(InspectorClientPlugin::new(client_token));
+    stepper.init();
+    stepper
+}
+
+/// The client that presents the admin token receives the components of the server entities
+#[test]
+fn test_inspector_snapshot() {
+    let mut stepper = setup(ADMIN_TOKEN);
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), Replicate::default()))
+        .id();
+    for _ in 0..20 {
+        stepper.frame_step();
+    }
+    let snapshot = stepper.client_app.world.resource::<InspectorSnapshot>();
+    let registry = stepper.client_app.world.resource::<ComponentRegistry>();
+    assert!(snapshot.tick > Tick(0));
+    assert_eq!(snapshot.entities().collect::<Vec<_>>(), vec![server_entity]);
+    assert_eq!(
+        snapshot
+            .component::<Component1>(registry, server_entity)
+            .unwrap(),
+        Some(Component1(1.0))
+    );
+    assert_eq!(
+        snapshot
+            .component::<Component2>(registry, server_entity)
+            .unwrap(),
+        None
+    );
+
+    // the snapshots follow the changes of the server world
+    stepper
+        .server_app
+        .world
+        .entity_mut(server_entity)
+        .insert(Component1(2.0));
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    let snapshot = stepper.client_app.world.resource::<InspectorSnapshot>();
+    let registry = stepper.client_app.world.resource::<ComponentRegistry>();
+    assert_eq!(
+        snapshot
+            .component::<Component1>(registry, server_entity)
+            .unwrap(),
+        Some(Component1(2.0))
+    );
+}
+
+/// The client that presents an invalid token doesn't receive any snapshot
+#[test]
+fn test_inspector_invalid_token() {
+    let mut stepper = setup("invalid-token");
+    stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), Replicate::default()));
+    for _ in 0..20 {
+        stepper.frame_step();
+    }
+    assert!(stepper
+        .client_app
+        .world
+        .get_resource::<InspectorSnapshot>()
+        .is_none());
+}
@@ -0,0 +1,274 @@
+//! A client that connects to a server that has been running for a while receives the current state of the world,
+//! without the updates that happened before it connected
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::channel::builder::WorldSnapshotChannel;
+use crate::client::events::{ComponentInsertEvent, ComponentUpdateEvent};
+use crate::prelude::client::{ClientCommands, InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::server::{Replicate, ServerCommands};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+#[derive(Resource, Default)]
+struct ReceivedValues {
+    component1: Vec<Component1>,
+    updates: usize,
+}
+
+fn collect_received_values(
+    mut inserts: EventReader<ComponentInsertEvent<Component1>>,
+    mut updates: EventReader<ComponentUpdateEvent<Component1>>,
+    query: Query<&Component1>,
+    mut received: ResMut<ReceivedValues>,
+) {
+    for event in inserts.read() {
+        if let Ok(component) = query.get(event.entity()) {
+            received.component1.push(component.clone());
+        }
+    }
+    received.updates += updates.read().count();
+}
+
+/// Replicate the server world to a client that connects after the server has been running for a while
+fn late_join_stepper() -> BevyStepper {
+    late_join_stepper_with_replication(server::ReplicationConfig::default())
+}
+
+fn late_join_stepper_with_replication(replication: server::ReplicationConfig) -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        tick: TickConfig::new(frame_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        // the acks of the client take several frames to reach the server
+        LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(50),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ServerConfig>()
+        .replication = replication;
+    stepper.client_app.finish();
+    stepper.server_app.finish();
+    stepper.client_app.init_resource::<ReceivedValues>();
+    stepper
+        .client_app
+        .add_systems(Update, collect_received_values);
+    stepper
+        .server_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.start_server());
+    stepper
+}
+
+#[test]
+fn test_late_join_receives_latest_values() {
+    let mut stepper = late_join_stepper();
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((
+            Replicate::default(),
+            Component1(0.0),
+            Component2(0.0),
+            ReplicateOnceComponent::<Component2>::default(),
+        ))
+        .id();
+    // the server world keeps changing before the client connects
+    for i in 1..=20 {
+        stepper
+            .server_app
+            .world
+            .entity_mut(server_entity)
+            .insert((Component1(i as f32), Component2(i as f32)));
+        stepper.frame_step();
+    }
+
+    stepper
+        .client_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.connect_client());
+    for _ in 0..100 {
+        stepper.frame_step();
+        // the entity actions contain the latest values, so they are not sent again as updates
+        // while the server waits for the first ack of the client
+        if let Some(connection) = stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .connections
+            .get(&ClientId::Netcode(TEST_CLIENT_ID))
+        {
+            assert!(connection
+                .replication_sender
+                .updates_message_id_to_group_id
+                .is_empty());
+        }
+    }
+
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .expect("entity was not replicated to client");
+    // the client received exactly one value for each component: the latest one
+    let received = stepper.client_app.world.resource::<ReceivedValues>();
+    assert_eq!(received.component1, vec![Component1(20.0)]);
+    assert_eq!(received.updates, 0);
+    // including the components that are only replicated once
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .entity(client_entity)
+            .get::<Component2>()
+            .expect("component missing"),
+        &Component2(20.0)
+    );
+}
+
+#[test]
+fn test_late_join_ignores_replicate_if() {
+    let mut stepper = late_join_stepper();
+    stepper
+        .server_app
+        .world
+        .resource_mut::<ComponentRegistry>()
+        .set_replicate_if::<Component1>(|component, _| component.0 > 5.0);
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((Replicate::default(), Component1(10.0)))
+        .id();
+    stepper.frame_step();
+    stepper
+        .server_app
+        .world
+        .entity_mut(server_entity)
+        .insert(Component1(1.0));
+    stepper.frame_step();
+
+    stepper
+        .client_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.connect_client());
+    for _ in 0..100 {
+        stepper.frame_step();
+    }
+    // the predicate only filters the updates: the late joiner receives the current value
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .expect("entity was not replicated to client");
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .entity(client_entity)
+            .get::<Component1>()
+            .expect("component missing"),
+        &Component1(1.0)
+    );
+}
+
+#[test]
+fn test_late_join_snapshot() {
+    let mut stepper = late_join_stepper_with_replication(
+        server::ReplicationConfig::default().with_late_join_snapshot(PacketCompression::None),
+    );
+    let server_entities: Vec<Entity> = (0..50)
+        .map(|i| {
+            stepper
+                .server_app
+                .world
+                .spawn((Replicate::default(), Component1(i as f32)))
+                .id()
+        })
+        .collect();
+    stepper.frame_step();
+
+    stepper
+        .client_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.connect_client());
+    let num_client_entities = |stepper: &mut BevyStepper| {
+        stepper
+            .client_app
+            .world
+            .query::<&Component1>()
+            .iter(&stepper.client_app.world)
+            .count()
+    };
+    let mut snapshot_transfers = vec![];
+    for _ in 0..100 {
+        stepper.frame_step();
+        // the snapshot is applied atomically
+        let count = num_client_entities(&mut stepper);
+        assert!(
+            count == 0 || count == 50,
+            "{count} entities were replicated"
+        );
+        snapshot_transfers.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::TransferProgressEvent>>()
+                .drain()
+                .filter(|event| event.channel() == ChannelKind::of::<WorldSnapshotChannel>())
+                .map(|event| event.progress().status),
+        );
+    }
+    assert_eq!(num_client_entities(&mut stepper), 50);
+    // the entities were received in a single snapshot
+    assert_eq!(
+        snapshot_transfers
+            .iter()
+            .filter(|status| **status == TransferStatus::Completed)
+            .count(),
+        1
+    );
+
+    // the entities keep being replicated after the snapshot
+    stepper
+        .server_app
+        .world
+        .entity_mut(server_entities[0])
+        .insert(Component1(100.0));
+    stepper.server_app.world.despawn(server_entities[1]);
+    for _ in 0..20 {
+        stepper.frame_step();
+    }
+    let remote_entity_map = &stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map;
+    assert!(remote_entity_map.get_local(server_entities[1]).is_none());
+    let client_entity = *remote_entity_map.get_local(server_entities[0]).unwrap();
+    assert_eq!(
+        stepper.client_app.world.get::<Component1>(client_entity),
+        Some(&Component1(100.0))
+    );
+    assert_eq!(num_client_entities(&mut stepper), 49);
+}
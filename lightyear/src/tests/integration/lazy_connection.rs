@@ -0,0 +1,84 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{ClientCommands, InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::server::{Replicate, ServerCommands};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+
+/// The connection resources are only created once the client connects / the server starts,
+/// and the apps can run without them
+#[test]
+fn test_run_before_connecting() {
+    let frame_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        tick: TickConfig::new(frame_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.client_app.finish();
+    stepper.server_app.finish();
+
+    stepper
+        .client_app
+        .world
+        .spawn((Component1(0.0), client::Replicate::default()));
+    stepper
+        .server_app
+        .world
+        .spawn((Component1(0.0), Replicate::default()));
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    assert!(stepper
+        .client_app
+        .world
+        .get_resource::<client::ConnectionManager>()
+        .is_none());
+    assert!(stepper
+        .client_app
+        .world
+        .get_resource::<client::ClientConnection>()
+        .is_none());
+    assert!(stepper
+        .server_app
+        .world
+        .get_resource::<server::ConnectionManager>()
+        .is_none());
+    assert!(stepper
+        .server_app
+        .world
+        .get_resource::<server::ServerConnections>()
+        .is_none());
+
+    // the connection still works once it is requested
+    stepper
+        .server_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.start_server());
+    stepper
+        .client_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.connect_client());
+    for _ in 0..100 {
+        stepper.frame_step();
+    }
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .is_synced());
+}
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+/// The sender is notified when a message sent with a `MessageHandle` is received by the remote
+#[test]
+fn test_message_acks() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.init();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+    // the channel must track acks
+    assert!(stepper
+        .client_app
+        .world
+        .resource_mut::<client::ConnectionManager>()
+        .send_message_with_handle::<Channel1, _>(&Message1("a".to_string()))
+        .is_err());
+
+    let client_handle = stepper
+        .client_app
+        .world
+        .resource_mut::<client::ConnectionManager>()
+        .send_message_with_handle::<Channel2, _>(&Message1("a".to_string()))
+        .unwrap();
+    let server_handle = stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>()
+        .send_message_with_handle::<Channel2, _>(client_id, &Message1("b".to_string()))
+        .unwrap();
+    assert!(!stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .is_message_acked(client_handle));
+
+    let mut client_events = vec![];
+    let mut server_events = vec![];
+    for _ in 0..10 {
+        stepper.frame_step();
+        client_events.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageAckedEvent>>()
+                .drain()
+                .map(|event| event.handle()),
+        );
+        server_events.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::MessageAckedEvent>>()
+                .drain()
+                .map(|event| (event.handle(), *event.context())),
+        );
+    }
+    assert_eq!(client_events, vec![client_handle]);
+    assert_eq!(server_events, vec![(server_handle, client_id)]);
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .is_message_acked(client_handle));
+    assert!(stepper
+        .server_app
+        .world
+        .resource::<server::ConnectionManager>()
+        .is_message_acked(client_id, server_handle));
+}
@@ -0,0 +1,83 @@
+use bevy::ecs::entity::MapEntities;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct EntityMessage(Entity);
+
+impl MapEntities for EntityMessage {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.0 = entity_mapper.map_entity(self.0);
+    }
+}
+
+/// The entities contained in a message are mapped to the receiver's local entities
+#[test]
+fn test_message_map_entities() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    for app in [&mut stepper.client_app, &mut stepper.server_app] {
+        app.add_message_with_map_entities::<EntityMessage>(ChannelDirection::ServerToClient);
+    }
+    stepper.init();
+
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn(server::Replicate::default())
+        .id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .expect("entity was not replicated to client");
+
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>()
+        .send_message::<Channel1, _>(
+            ClientId::Netcode(TEST_CLIENT_ID),
+            &EntityMessage(server_entity),
+        )
+        .unwrap();
+    let mut received = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        received.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<EntityMessage>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    assert_eq!(received, vec![EntityMessage(client_entity)]);
+}
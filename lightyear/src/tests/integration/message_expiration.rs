@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use lightyear_macros::ChannelInternal;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+#[derive(ChannelInternal)]
+struct ReliableChannel;
+
+/// A message that is not acked within its ttl is not delivered, and the sender is notified
+#[test]
+fn test_message_expiration() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    for app in [&mut stepper.client_app, &mut stepper.server_app] {
+        app.add_channel::<ReliableChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        });
+    }
+    stepper.init();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+    let mut connection_manager = stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>();
+    // the first message expires before it can be sent
+    connection_manager
+        .send_message_with_ttl::<ReliableChannel, _>(
+            client_id,
+            &Message1("a".to_string()),
+            Duration::ZERO,
+        )
+        .unwrap();
+    connection_manager
+        .send_message::<ReliableChannel, _>(client_id, &Message1("b".to_string()))
+        .unwrap();
+
+    let mut client_messages = vec![];
+    let mut expired_events = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        client_messages.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+        expired_events.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::MessageExpiredEvent>>()
+                .drain(),
+        );
+    }
+    // the ordered channel is not blocked by the message that expired
+    assert_eq!(client_messages, vec![Message1("b".to_string())]);
+    assert_eq!(expired_events.len(), 1);
+    assert_eq!(
+        expired_events[0].channel(),
+        ChannelKind::of::<ReliableChannel>()
+    );
+    assert_eq!(expired_events[0].context(), &client_id);
+}
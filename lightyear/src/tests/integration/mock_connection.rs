@@ -0,0 +1,96 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+
+use crate::prelude::client::{ClientCommands, MockClientConnection, NetworkingState};
+use crate::prelude::server::{MockServerConnection, ServerCommands};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+
+fn drain<E: Event>(app: &mut App) -> usize {
+    app.world.resource_mut::<Events<E>>().drain().count()
+}
+
+fn client_state(app: &App) -> NetworkingState {
+    *app.world.resource::<State<NetworkingState>>().get()
+}
+
+fn update(app: &mut App) {
+    for _ in 0..3 {
+        app.update();
+    }
+}
+
+/// The client goes through the connection states scripted by the mock connection
+#[test]
+fn test_mock_client_connection() {
+    let mock = MockClientConnection::new(ClientId::Netcode(1)).with_manual_connect();
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        client::ClientPlugins::new(client::ClientConfig {
+            net: client::NetConfig::Mock {
+                connection: mock.clone(),
+            },
+            ..default()
+        }),
+        ProtocolPlugin,
+    ));
+    app.finish();
+    app.world
+        .run_system_once(|mut commands: Commands| commands.connect_client());
+    update(&mut app);
+    assert_eq!(client_state(&app), NetworkingState::Connecting);
+
+    mock.accept();
+    update(&mut app);
+    assert_eq!(client_state(&app), NetworkingState::Connected);
+    assert_eq!(drain::<client::ConnectEvent>(&mut app), 1);
+
+    // an io error disconnects the client
+    mock.fail_io("connection reset");
+    update(&mut app);
+    assert_eq!(client_state(&app), NetworkingState::Disconnected);
+    assert_eq!(drain::<client::DisconnectEvent>(&mut app), 1);
+    assert!(mock.elapsed() > bevy::utils::Duration::ZERO);
+}
+
+/// The server reports the connections and disconnections scripted by the mock connection
+#[test]
+fn test_mock_server_connection() {
+    let mock = MockServerConnection::new();
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        server::ServerPlugins::new(server::ServerConfig {
+            net: vec![server::NetConfig::Mock {
+                connection: mock.clone(),
+            }],
+            ..default()
+        }),
+        ProtocolPlugin,
+    ));
+    app.finish();
+    app.world
+        .run_system_once(|mut commands: Commands| commands.start_server());
+    update(&mut app);
+    assert!(mock.is_started());
+
+    let client_id = ClientId::Netcode(1);
+    mock.connect_client(client_id);
+    update(&mut app);
+    assert_eq!(drain::<server::ConnectEvent>(&mut app), 1);
+    assert!(app
+        .world
+        .resource::<server::ConnectionManager>()
+        .connection(client_id)
+        .is_ok());
+
+    mock.disconnect_client(client_id);
+    update(&mut app);
+    assert_eq!(drain::<server::DisconnectEvent>(&mut app), 1);
+    assert!(app
+        .world
+        .resource::<server::ConnectionManager>()
+        .connection(client_id)
+        .is_err());
+}
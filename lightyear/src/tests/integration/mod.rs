@@ -1,2 +1,30 @@
+mod chaos;
+mod client_entities;
+mod component_migration;
+mod component_validation;
+mod custom_schedules;
+mod delta_compression;
+mod dynamic_components;
+mod fragment_pacing;
+mod input_coalescing;
+mod inspector;
+mod late_join;
+mod lazy_connection;
+mod message_acks;
+mod message_entity_mapping;
+mod message_expiration;
+mod mock_connection;
+mod multi_protocol;
 mod multi_transport;
+#[cfg(feature = "lz4")]
+mod packet_compression;
+mod persistence;
+mod predicted_entity_mapping;
+mod protocol_evolution;
+mod protocol_hash;
+mod reconnect;
+mod runtime_channel;
+mod stream_channel;
+mod tick_buffered_channel;
 mod tick_wrapping;
+mod visibility_events;
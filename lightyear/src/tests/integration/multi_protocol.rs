@@ -0,0 +1,156 @@
+//! Tests related to the server serving an additional protocol on one of its transports
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use lightyear_macros::ChannelInternal;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::server::{AppProtocolExt, Replicate};
+use crate::prelude::*;
+use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
+use crate::tests::protocol::*;
+use crate::tests::stepper::Step;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct EditorCommand(String);
+
+#[derive(ChannelInternal)]
+struct EditorChannel;
+
+/// The protocol used by the editor, which has nothing in common with the game protocol
+fn editor_protocol(app: &mut App) {
+    app.add_channel::<EditorChannel>(ChannelSettings {
+        mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+        ..default()
+    });
+    app.add_message::<EditorCommand>(ChannelDirection::Bidirectional);
+}
+
+fn setup() -> MultiBevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = MultiBevyStepper::new_with_client_2_protocol(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        frame_duration,
+        editor_protocol,
+    );
+    // the second client connects through the second transport of the server
+    stepper.server_app.add_protocol(1, editor_protocol);
+    stepper.init();
+    stepper
+}
+
+/// A client that uses an additional protocol can connect and exchange its messages with the server
+#[test]
+fn test_additional_protocol_messages() {
+    let mut stepper = setup();
+    let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+    let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+    let mut connected = stepper
+        .server_app
+        .world
+        .resource::<server::ConnectionManager>()
+        .connected_clients()
+        .collect::<Vec<_>>();
+    connected.sort_by_key(|client_id| client_id.to_bits());
+    assert_eq!(connected, vec![client_1, client_2]);
+
+    stepper
+        .client_app_2
+        .world
+        .resource_mut::<client::ConnectionManager>()
+        .send_message::<EditorChannel, _>(&EditorCommand("client".to_string()))
+        .unwrap();
+    // the messages sent to all the clients only go to the clients whose protocol contains them
+    let mut server_manager = stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>();
+    server_manager
+        .send_message_to_target::<Channel1, _>(&Message1("game".to_string()), NetworkTarget::All)
+        .unwrap();
+    server_manager
+        .send_message_to_target::<EditorChannel, _>(
+            &EditorCommand("server".to_string()),
+            NetworkTarget::All,
+        )
+        .unwrap();
+    let mut server_received = vec![];
+    let mut client_1_received = vec![];
+    let mut client_2_received = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        server_received.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::MessageEvent<EditorCommand>>>()
+                .drain()
+                .map(|event| (*event.context(), event.message)),
+        );
+        client_1_received.extend(
+            stepper
+                .client_app_1
+                .world
+                .resource_mut::<Events<client::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+        client_2_received.extend(
+            stepper
+                .client_app_2
+                .world
+                .resource_mut::<Events<client::MessageEvent<EditorCommand>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    assert_eq!(
+        server_received,
+        vec![(client_2, EditorCommand("client".to_string()))]
+    );
+    assert_eq!(client_1_received, vec![Message1("game".to_string())]);
+    assert_eq!(client_2_received, vec![EditorCommand("server".to_string())]);
+}
+
+/// The entities are not replicated to the clients of an additional protocol
+#[test]
+fn test_additional_protocol_no_replication() {
+    let mut stepper = setup();
+    stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), Replicate::default()));
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    assert!(stepper
+        .client_app_1
+        .world
+        .query::<&Component1>()
+        .get_single(&stepper.client_app_1.world)
+        .is_ok());
+    assert!(stepper
+        .client_app_2
+        .world
+        .query::<&Replicated>()
+        .iter(&stepper.client_app_2.world)
+        .next()
+        .is_none());
+}
+
+/// The additional protocols cannot register components, since the entities are not replicated to their clients
+#[test]
+#[should_panic(expected = "can only contain channels and messages")]
+fn test_additional_protocol_rejects_components() {
+    let mut stepper = setup();
+    stepper.server_app.add_protocol(2, |app| {
+        editor_protocol(app);
+        app.register_component::<Component1>(ChannelDirection::ServerToClient);
+    });
+}
@@ -45,3 +45,64 @@ fn test_multi_transport() {
     // check that the entity got replicated to both clients
     // (even though they share the same client id)
 }
+
+/// A client id that is already connected on one transport cannot be used on another transport
+#[test]
+fn test_multi_transport_duplicate_client_id() {
+    use crate::connection::client::{NetClient, NetConfig};
+    use crate::connection::server::ServerConnections;
+    use crate::prelude::client::{Authentication, ClientConfig, ClientConnection};
+    use crate::prelude::{server, ClientId};
+    use crate::tests::multi_stepper::TEST_CLIENT_ID_1;
+
+    let mut stepper = MultiBevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(Duration::from_millis(10)),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        Duration::from_millis(10),
+    );
+    // the second client uses the same client id as the first one
+    if let NetConfig::Netcode {
+        auth: Authentication::Manual { client_id, .. },
+        ..
+    } = &mut stepper
+        .client_app_2
+        .world
+        .resource_mut::<ClientConfig>()
+        .net
+    {
+        *client_id = TEST_CLIENT_ID_1;
+    }
+    stepper.init();
+    for _ in 0..20 {
+        stepper.frame_step();
+    }
+
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID_1);
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .connected_clients()
+            .collect::<Vec<_>>(),
+        vec![client_id]
+    );
+    let server_idx = stepper
+        .server_app
+        .world
+        .resource::<ServerConnections>()
+        .client_server_index(client_id)
+        .unwrap();
+    // only the client that connected on that transport stays connected
+    let connected = |app: &App| {
+        app.world.resource::<ClientConnection>().state()
+            == crate::prelude::client::NetworkingState::Connected
+    };
+    assert_eq!(connected(&stepper.client_app_1), server_idx == 0);
+    assert_eq!(connected(&stepper.client_app_2), server_idx == 1);
+}
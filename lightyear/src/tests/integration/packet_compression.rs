@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+fn setup(client_compression: PacketCompression) -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ServerConfig>()
+        .packet = server::PacketConfig::default().with_compression(PacketCompression::Lz4);
+    stepper
+        .client_app
+        .world
+        .resource_mut::<client::ClientConfig>()
+        .packet = client::PacketConfig::default().with_compression(client_compression);
+    stepper.init();
+    stepper
+}
+
+/// The packets are compressed once both peers agreed on the algorithm, and the messages are still delivered
+#[test]
+fn test_packet_compression() {
+    let mut stepper = setup(PacketCompression::Lz4);
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .packet_compression(client_id),
+        Some(PacketCompression::Lz4)
+    );
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .resource::<client::ConnectionManager>()
+            .packet_compression(),
+        PacketCompression::Lz4
+    );
+
+    let message = Message1("a".repeat(500));
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>()
+        .send_message::<Channel1, _>(client_id, &message)
+        .unwrap();
+    stepper
+        .client_app
+        .world
+        .resource_mut::<client::ConnectionManager>()
+        .send_message::<Channel1, _>(&message)
+        .unwrap();
+    let mut client_messages = vec![];
+    let mut server_messages = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        client_messages.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+        server_messages.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    assert_eq!(client_messages, vec![message.clone()]);
+    assert_eq!(server_messages, vec![message]);
+}
+
+/// A peer that doesn't enable compression can still receive compressed packets
+#[test]
+fn test_packet_compression_one_side() {
+    let mut stepper = setup(PacketCompression::None);
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .packet_compression(client_id),
+        Some(PacketCompression::Lz4)
+    );
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .resource::<client::ConnectionManager>()
+            .packet_compression(),
+        PacketCompression::None
+    );
+
+    let message = Message1("a".repeat(500));
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>()
+        .send_message::<Channel1, _>(client_id, &message)
+        .unwrap();
+    let mut client_messages = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        client_messages.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    assert_eq!(client_messages, vec![message]);
+}
@@ -0,0 +1,217 @@
+//! The server saves its replicated world, restarts, and the clients that reconnect keep their entities
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::packet::compression::PacketCompression;
+use crate::prelude::client::{ClientCommands, InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::server::{Replicate, SavedWorld, ServerCommands, ServerReplication};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+
+fn persistence_stepper() -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(20),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ServerConfig>()
+        .replication =
+        server::ReplicationConfig::default().with_late_join_snapshot(PacketCompression::None);
+    stepper
+        .client_app
+        .world
+        .resource_mut::<client::ClientConfig>()
+        .replication = client::ReplicationConfig::default().with_reattach_entities(true);
+    stepper.init();
+    stepper
+}
+
+fn client_entity(stepper: &BevyStepper, server_entity: Entity) -> Option<Entity> {
+    stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .copied()
+}
+
+/// Save the server world, then restart the server with an empty world
+fn restart_server(stepper: &mut BevyStepper) -> SavedWorld {
+    stepper
+        .client_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.disconnect_client());
+    stepper
+        .server_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.stop_server());
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    let saved = ServerReplication::save(&stepper.server_app.world).unwrap();
+    let entities: Vec<Entity> = saved.entities.iter().map(|e| e.entity).collect();
+    for entity in entities {
+        stepper.server_app.world.despawn(entity);
+    }
+    saved
+}
+
+#[test]
+fn test_save_and_load() {
+    let mut stepper = persistence_stepper();
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((
+            Component1(1.0),
+            Component2(2.0),
+            NetworkId::new(1),
+            Replicate::default(),
+        ))
+        .id();
+    stepper.frame_step();
+    stepper.frame_step();
+    let saved = restart_server(&mut stepper);
+    assert_eq!(saved.entities.len(), 1);
+    assert_eq!(saved.entities[0].entity, server_entity);
+    assert_eq!(saved.entities[0].network_id, Some(NetworkId::new(1)));
+    assert_eq!(saved.entities[0].num_components(), 2);
+
+    // an unrelated entity reuses the index of the saved entity
+    stepper.server_app.world.spawn_empty();
+    let loaded = ServerReplication::load(&mut stepper.server_app.world, &saved).unwrap();
+    let entity_ref = stepper.server_app.world.entity(loaded[&server_entity]);
+    assert_eq!(entity_ref.get::<Component1>(), Some(&Component1(1.0)));
+    assert_eq!(entity_ref.get::<Component2>(), Some(&Component2(2.0)));
+    assert_eq!(entity_ref.get::<NetworkId>(), Some(&NetworkId::new(1)));
+    assert!(entity_ref.contains::<Replicating>());
+    // the NetworkId of the saved entity is already used
+    assert!(ServerReplication::load(&mut stepper.server_app.world, &saved).is_err());
+}
+
+/// A saved world that cannot be decoded is not loaded at all
+#[test]
+fn test_load_corrupted_world() {
+    let mut stepper = persistence_stepper();
+    stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), NetworkId::new(1), Replicate::default()));
+    stepper
+        .server_app
+        .world
+        .spawn((Component1(2.0), NetworkId::new(2), Replicate::default()));
+    stepper.frame_step();
+    let mut saved = restart_server(&mut stepper);
+    assert_eq!(saved.entities.len(), 2);
+    // only the component of the last entity is corrupted
+    saved.entities[1].components[0] = vec![0xFF; 4];
+
+    let num_entities = stepper.server_app.world.entities().len();
+    assert!(ServerReplication::load(&mut stepper.server_app.world, &saved).is_err());
+    assert_eq!(stepper.server_app.world.entities().len(), num_entities);
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .query::<&NetworkId>()
+            .iter(&stepper.server_app.world)
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn test_clients_reattach_after_server_restart() {
+    let mut stepper = persistence_stepper();
+    let kept = stepper
+        .server_app
+        .world
+        .spawn((Component1(1.0), NetworkId::new(1), Replicate::default()))
+        .id();
+    let removed = stepper
+        .server_app
+        .world
+        .spawn((Component1(2.0), NetworkId::new(2), Replicate::default()))
+        .id();
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    let kept_client = client_entity(&stepper, kept).expect("entity was not replicated");
+    let removed_client = client_entity(&stepper, removed).expect("entity was not replicated");
+
+    let saved = restart_server(&mut stepper);
+    // the entities are kept on the client while the server is down
+    assert!(stepper.client_app.world.get_entity(kept_client).is_some());
+
+    let loaded = ServerReplication::load(&mut stepper.server_app.world, &saved).unwrap();
+    let (kept, removed) = (loaded[&kept], loaded[&removed]);
+    // the entity is removed while the server is down
+    stepper.server_app.world.despawn(removed);
+    stepper
+        .server_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.start_server());
+    stepper
+        .client_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.connect_client());
+    for _ in 0..100 {
+        stepper.frame_step();
+    }
+
+    // the entity sent by the restarted server is linked to the existing client entity with the same NetworkId
+    assert_eq!(client_entity(&stepper, kept), Some(kept_client));
+    assert_eq!(
+        stepper.client_app.world.get::<Component1>(kept_client),
+        Some(&Component1(1.0))
+    );
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .query_filtered::<(), With<Replicated>>()
+            .iter(&stepper.client_app.world)
+            .count(),
+        1
+    );
+    // the entity that is not part of the server world anymore is despawned
+    assert!(client_entity(&stepper, removed).is_none());
+    assert!(stepper
+        .client_app
+        .world
+        .get_entity(removed_client)
+        .is_none());
+
+    // the re-attached entity still receives updates
+    stepper
+        .server_app
+        .world
+        .entity_mut(kept)
+        .insert(Component1(3.0));
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    assert_eq!(
+        stepper.client_app.world.get::<Component1>(kept_client),
+        Some(&Component1(3.0))
+    );
+}
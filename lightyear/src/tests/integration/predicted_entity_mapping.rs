@@ -0,0 +1,146 @@
+use bevy::ecs::entity::MapEntities;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::client::prediction::diagnostics::PredictionStats;
+use crate::prelude::client::{
+    ComponentSyncMode, Confirmed, InterpolationConfig, MapEntitiesTarget, PredictionConfig,
+    SyncConfig,
+};
+use crate::prelude::server::SyncTarget;
+use crate::prelude::*;
+use crate::tests::stepper::{BevyStepper, Step};
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Target(Entity);
+
+impl MapEntities for Target {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.0 = entity_mapper.map_entity(self.0);
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Owner(Entity);
+
+impl MapEntities for Owner {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.0 = entity_mapper.map_entity(self.0);
+    }
+}
+
+fn register_components(app: &mut App) {
+    app.register_component::<Target>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Full)
+        .add_map_entities();
+    app.register_component::<Owner>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Simple)
+        .add_map_entities()
+        .with_map_entities_target(MapEntitiesTarget::Confirmed);
+}
+
+fn predicted_replicate() -> server::Replicate {
+    server::Replicate {
+        sync: SyncTarget {
+            prediction: NetworkTarget::All,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// Returns the (confirmed, predicted) entities on the client for a server entity
+fn client_entities(stepper: &BevyStepper, server_entity: Entity) -> (Entity, Entity) {
+    let confirmed = *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .expect("entity was not replicated to client");
+    let predicted = stepper
+        .client_app
+        .world
+        .get::<Confirmed>(confirmed)
+        .unwrap()
+        .predicted
+        .expect("entity should be predicted");
+    (confirmed, predicted)
+}
+
+/// The entities referenced by predicted components are mapped to the predicted entities
+/// (or to the target chosen when registering the component)
+#[test]
+fn test_predicted_entity_mapping() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    register_components(&mut stepper.client_app);
+    register_components(&mut stepper.server_app);
+    stepper.init();
+
+    let server_target = stepper.server_app.world.spawn(predicted_replicate()).id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((
+            Target(server_target),
+            Owner(server_target),
+            predicted_replicate(),
+        ))
+        .id();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    let (confirmed_target, predicted_target) = client_entities(&stepper, server_target);
+    let (_, predicted_entity) = client_entities(&stepper, server_entity);
+    assert_eq!(
+        stepper.client_app.world.get::<Target>(predicted_entity),
+        Some(&Target(predicted_target))
+    );
+    assert_eq!(
+        stepper.client_app.world.get::<Owner>(predicted_entity),
+        Some(&Owner(confirmed_target))
+    );
+
+    // the mapped references match the predicted history, so the server updates don't cause rollbacks
+    for _ in 0..10 {
+        stepper
+            .server_app
+            .world
+            .get_mut::<Target>(server_entity)
+            .unwrap()
+            .set_changed();
+        stepper.frame_step();
+    }
+    let stats = stepper
+        .client_app
+        .world
+        .resource::<PredictionStats>()
+        .get::<Target>()
+        .unwrap();
+    assert!(stats.checks > 0);
+    assert_eq!(stats.mispredictions, 0);
+    assert_eq!(
+        stepper.client_app.world.get::<Target>(predicted_entity),
+        Some(&Target(predicted_target))
+    );
+}
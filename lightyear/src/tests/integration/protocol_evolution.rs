@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+/// Version of the message used by the client
+mod v1 {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct PlayerInfo {
+        pub name: String,
+    }
+}
+
+/// Newer version of the message used by the server
+mod v2 {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct PlayerInfo {
+        pub name: String,
+        #[serde(default)]
+        pub score: u32,
+    }
+}
+
+/// A server that added a field to a message can still talk to a client that uses the older version
+#[test]
+fn test_versioned_message() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper
+        .client_app
+        .add_message::<v1::PlayerInfo>(ChannelDirection::ServerToClient)
+        .with_stable_id(100)
+        .add_versioned_fields();
+    stepper
+        .server_app
+        .add_message::<v2::PlayerInfo>(ChannelDirection::ServerToClient)
+        .with_stable_id(100)
+        .add_versioned_fields();
+    stepper.init();
+    // the protocols are still considered identical
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .is_synced());
+
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>()
+        .send_message::<Channel1, _>(
+            ClientId::Netcode(TEST_CLIENT_ID),
+            &v2::PlayerInfo {
+                name: "a".to_string(),
+                score: 3,
+            },
+        )
+        .unwrap();
+    let mut received = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        received.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<v1::PlayerInfo>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    assert_eq!(
+        received,
+        vec![v1::PlayerInfo {
+            name: "a".to_string()
+        }]
+    );
+}
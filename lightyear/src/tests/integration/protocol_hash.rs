@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::stepper::{BevyStepper, Step};
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct ClientOnlyComponent(u32);
+
+#[derive(Resource, Default)]
+struct DisconnectReasons(Vec<Option<DeniedReason>>);
+
+fn record_disconnects(
+    mut events: EventReader<client::DisconnectEvent>,
+    mut reasons: ResMut<DisconnectReasons>,
+) {
+    reasons.0.extend(events.read().map(|event| event.reason));
+}
+
+fn stepper_with_client_only_component(verify_protocol_hash: bool) -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper
+        .client_app
+        .register_component::<ClientOnlyComponent>(ChannelDirection::ServerToClient);
+    stepper.client_app.init_resource::<DisconnectReasons>();
+    stepper.client_app.add_systems(Update, record_disconnects);
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ServerConfig>()
+        .verify_protocol_hash = verify_protocol_hash;
+    stepper.init();
+    stepper
+}
+
+/// A client whose protocol is different from the server's is refused
+#[test]
+fn test_protocol_mismatch() {
+    let stepper = stepper_with_client_only_component(true);
+
+    let reasons = &stepper.client_app.world.resource::<DisconnectReasons>().0;
+    assert_eq!(reasons.len(), 1);
+    assert!(matches!(
+        reasons[0],
+        Some(DeniedReason::ProtocolMismatch { client_hash, server_hash }) if client_hash != server_hash
+    ));
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .connected_clients()
+            .count(),
+        0
+    );
+}
+
+/// The protocol hash is only verified if the server opted in
+#[test]
+fn test_protocol_mismatch_not_verified() {
+    let stepper = stepper_with_client_only_component(false);
+
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<DisconnectReasons>()
+        .0
+        .is_empty());
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .resource::<server::ConnectionManager>()
+            .connected_clients()
+            .count(),
+        1
+    );
+}
@@ -0,0 +1,166 @@
+//! The client automatically reconnects to the server when the connection is lost
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::client::networking::NetworkingState;
+use crate::connection::server::ServerConnections;
+use crate::prelude::client::*;
+use crate::prelude::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+/// The phases of the reconnection, in the order in which they happened
+#[derive(Resource, Default)]
+struct ReconnectLog(Vec<String>);
+
+fn log_reconnect_events(
+    mut log: ResMut<ReconnectLog>,
+    mut reconnecting: EventReader<ReconnectingEvent>,
+    mut attempts: EventReader<ReconnectAttemptEvent>,
+    mut reconnected: EventReader<ReconnectedEvent>,
+    mut failed: EventReader<ReconnectFailedEvent>,
+) {
+    log.0.extend(
+        reconnecting
+            .read()
+            .map(|event| format!("reconnecting {}", event.attempt)),
+    );
+    log.0.extend(
+        attempts
+            .read()
+            .map(|event| format!("attempt {}", event.attempt)),
+    );
+    log.0.extend(
+        reconnected
+            .read()
+            .map(|event| format!("reconnected {}", event.attempts)),
+    );
+    log.0.extend(
+        failed
+            .read()
+            .map(|event| format!("failed {}", event.attempts)),
+    );
+}
+
+fn setup(max_attempts: Option<u32>) -> BevyStepper {
+    let tick_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        },
+        SyncConfig::default().speedup_factor(1.0),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(0),
+            incoming_jitter: Duration::from_millis(0),
+            incoming_loss: 0.0,
+        },
+        tick_duration,
+    );
+    let mut config = stepper.client_app.world.resource_mut::<ClientConfig>();
+    if let NetConfig::Netcode { config, .. } = &mut config.net {
+        config.client_timeout_secs = 1;
+    }
+    config.reconnect = Some(
+        ReconnectConfig::default()
+            .with_initial_delay(Duration::from_millis(100))
+            .with_max_attempts(max_attempts),
+    );
+    stepper.client_app.init_resource::<ReconnectLog>();
+    stepper.client_app.add_systems(Update, log_reconnect_events);
+    stepper.init();
+    assert_eq!(networking_state(&stepper), NetworkingState::Connected);
+    stepper
+}
+
+fn networking_state(stepper: &BevyStepper) -> NetworkingState {
+    *stepper
+        .client_app
+        .world
+        .resource::<State<NetworkingState>>()
+        .get()
+}
+
+fn log(stepper: &BevyStepper) -> &[String] {
+    &stepper.client_app.world.resource::<ReconnectLog>().0
+}
+
+/// The client and the server stop receiving packets from each other, so the connection times
+/// out on both sides
+fn lose_connection(stepper: &mut BevyStepper) {
+    for _ in 0..150 {
+        stepper.advance_time(stepper.frame_duration);
+        stepper.client_app.update();
+    }
+    for _ in 0..350 {
+        stepper.advance_time(stepper.frame_duration);
+        stepper.server_app.update();
+    }
+}
+
+#[test]
+fn test_reconnect_after_timeout() {
+    let mut stepper = setup(None);
+    lose_connection(&mut stepper);
+    for _ in 0..100 {
+        stepper.frame_step();
+    }
+    assert_eq!(networking_state(&stepper), NetworkingState::Connected);
+    assert_eq!(
+        log(&stepper),
+        &["reconnecting 1", "attempt 1", "reconnected 1"]
+    );
+}
+
+#[test]
+fn test_reconnect_gives_up() {
+    let mut stepper = setup(Some(2));
+    // the server is unreachable
+    for _ in 0..500 {
+        stepper.advance_time(stepper.frame_duration);
+        stepper.client_app.update();
+    }
+    assert_eq!(networking_state(&stepper), NetworkingState::Disconnected);
+    assert_eq!(
+        log(&stepper),
+        &[
+            "reconnecting 1",
+            "attempt 1",
+            "reconnecting 2",
+            "attempt 2",
+            "failed 2"
+        ]
+    );
+}
+
+#[test]
+fn test_no_reconnect_when_disconnect_is_requested() {
+    let mut stepper = setup(None);
+    stepper
+        .client_app
+        .world
+        .run_system_once(|mut commands: Commands| commands.disconnect_client());
+    for _ in 0..100 {
+        stepper.frame_step();
+    }
+    assert_eq!(networking_state(&stepper), NetworkingState::Disconnected);
+    assert!(log(&stepper).is_empty());
+}
+
+#[test]
+fn test_no_reconnect_when_server_disconnects() {
+    let mut stepper = setup(None);
+    stepper
+        .server_app
+        .world
+        .resource_mut::<ServerConnections>()
+        .disconnect(ClientId::Netcode(TEST_CLIENT_ID))
+        .unwrap();
+    for _ in 0..100 {
+        stepper.frame_step();
+    }
+    assert_eq!(networking_state(&stepper), NetworkingState::Disconnected);
+    assert!(log(&stepper).is_empty());
+}
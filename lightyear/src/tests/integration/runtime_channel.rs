@@ -0,0 +1,135 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use lightyear_macros::ChannelInternal;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::protocol::channel::MAX_PENDING_CHANNEL_MESSAGES;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+#[derive(ChannelInternal)]
+struct RuntimeChannel;
+
+fn runtime_channel_settings() -> ChannelSettings {
+    ChannelSettings {
+        mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+        ..default()
+    }
+}
+
+fn register_runtime_channel(app: &mut App) {
+    app.world.run_system_once(|mut commands: Commands| {
+        commands.register_channel::<RuntimeChannel>(runtime_channel_settings());
+    });
+}
+
+/// A channel registered after the client and server are connected can be used once both
+/// peers agreed on its network id, and the messages sent before that are buffered
+#[test]
+fn test_register_channel_at_runtime() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.init();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+    // the server registers the channel first
+    register_runtime_channel(&mut stepper.server_app);
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>()
+        .send_message::<RuntimeChannel, _>(client_id, &Message1("a".to_string()))
+        .unwrap();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    assert!(!stepper
+        .server_app
+        .world
+        .resource::<server::ConnectionManager>()
+        .is_channel_registered::<RuntimeChannel>(client_id));
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<Events<client::MessageEvent<Message1>>>()
+        .is_empty());
+
+    // once the client registers the channel, the buffered message is delivered
+    register_runtime_channel(&mut stepper.client_app);
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .is_channel_registered::<RuntimeChannel>());
+    stepper
+        .client_app
+        .world
+        .resource_mut::<client::ConnectionManager>()
+        .send_message::<RuntimeChannel, _>(&Message1("b".to_string()))
+        .unwrap();
+    let mut client_messages = vec![];
+    let mut server_messages = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        client_messages.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+        server_messages.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    assert!(stepper
+        .server_app
+        .world
+        .resource::<server::ConnectionManager>()
+        .is_channel_registered::<RuntimeChannel>(client_id));
+    assert_eq!(client_messages, vec![Message1("a".to_string())]);
+    assert_eq!(server_messages, vec![Message1("b".to_string())]);
+}
+
+/// The number of messages waiting for the client to acknowledge a channel is bounded
+#[test]
+fn test_pending_channel_messages_are_capped() {
+    let mut stepper = BevyStepper::default();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+    register_runtime_channel(&mut stepper.server_app);
+    let mut manager = stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>();
+    for _ in 0..MAX_PENDING_CHANNEL_MESSAGES {
+        manager
+            .send_message::<RuntimeChannel, _>(client_id, &Message1("a".to_string()))
+            .unwrap();
+    }
+    assert!(manager
+        .send_message::<RuntimeChannel, _>(client_id, &Message1("a".to_string()))
+        .is_err());
+}
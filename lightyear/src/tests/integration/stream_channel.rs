@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use lightyear_macros::ChannelInternal;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+#[derive(ChannelInternal)]
+struct StreamChannel;
+
+/// A message that is bigger than the send rate of the channel is sent over multiple frames,
+/// and both ends are notified of the progress of the transfer
+#[test]
+fn test_stream_channel_transfer() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    for app in [&mut stepper.client_app, &mut stepper.server_app] {
+        app.add_channel::<StreamChannel>(ChannelSettings {
+            mode: ChannelMode::Stream(StreamSettings::default().with_send_rate(500_000)),
+            ..default()
+        });
+    }
+    stepper.init();
+
+    // 50KB are sent in about 10 frames
+    let message = Message1("a".repeat(50_000));
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+    stepper
+        .server_app
+        .world
+        .resource_mut::<server::ConnectionManager>()
+        .send_message::<StreamChannel, _>(client_id, &message)
+        .unwrap();
+
+    let mut client_progress = vec![];
+    let mut server_progress = vec![];
+    let mut received = vec![];
+    for _ in 0..30 {
+        stepper.frame_step();
+        client_progress.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::TransferProgressEvent>>()
+                .drain()
+                .map(|event| *event.progress()),
+        );
+        server_progress.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::TransferProgressEvent>>()
+                .drain()
+                .map(|event| *event.progress()),
+        );
+        received.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| event.message),
+        );
+    }
+    assert_eq!(received, vec![message]);
+
+    // the transfer was received over multiple frames
+    assert!(client_progress.len() > 2);
+    assert!(client_progress
+        .iter()
+        .all(|p| p.direction == TransferDirection::Receive));
+    assert_eq!(
+        client_progress.last().unwrap().status,
+        TransferStatus::Completed
+    );
+    assert!(server_progress.len() > 2);
+    assert!(server_progress
+        .iter()
+        .all(|p| p.direction == TransferDirection::Send));
+    assert_eq!(
+        server_progress.last().unwrap().status,
+        TransferStatus::Completed
+    );
+    assert_eq!(
+        server_progress.last().unwrap().bytes_transferred,
+        server_progress.last().unwrap().total_bytes
+    );
+}
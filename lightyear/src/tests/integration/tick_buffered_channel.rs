@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use lightyear_macros::ChannelInternal;
+
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+
+#[derive(ChannelInternal)]
+struct CommandChannel;
+
+/// The messages sent by the client on a tick-buffered channel are read by the server
+/// on the tick at which they were sent
+#[test]
+fn test_tick_buffered_channel() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(20),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    let settings = ChannelSettings {
+        mode: ChannelMode::TickBuffered,
+        direction: ChannelDirection::ClientToServer,
+        ..default()
+    };
+    stepper
+        .client_app
+        .add_channel::<CommandChannel>(settings.clone());
+    stepper.server_app.add_channel::<CommandChannel>(settings);
+    stepper.init();
+    // let the client tick settle after the sync
+    stepper.frame_step();
+
+    let send_tick = stepper.client_tick();
+    // the client runs ahead of the server
+    assert!(send_tick > stepper.server_tick());
+    stepper
+        .client_app
+        .world
+        .resource_mut::<client::ConnectionManager>()
+        .send_message::<CommandChannel, _>(&Message1(format!("{send_tick:?}")))
+        .unwrap();
+
+    let mut received_ticks = vec![];
+    for _ in 0..50 {
+        // the messages are read before the server runs the next tick
+        let server_tick = stepper.server_tick();
+        stepper.frame_step();
+        received_ticks.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::MessageEvent<Message1>>>()
+                .drain()
+                .map(|event| (event.message.0, server_tick)),
+        );
+    }
+    assert_eq!(received_ticks, vec![(format!("{send_tick:?}"), send_tick)]);
+}
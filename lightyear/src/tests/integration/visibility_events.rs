@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::client::events::{EntityEnteredInterest, EntityLeftInterest};
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::server::VisibilityManager;
+use crate::prelude::*;
+use crate::server::events::{EntityHiddenFromClient, EntityVisibleToClient};
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step, TEST_CLIENT_ID};
+
+fn drain<E: Event>(app: &mut App) -> Vec<E> {
+    app.world.resource_mut::<Events<E>>().drain().collect()
+}
+
+/// Events are emitted on the server and the client when the visibility of an entity changes
+#[test]
+fn test_visibility_events() {
+    let frame_duration = Duration::from_millis(10);
+    let mut stepper = BevyStepper::new(
+        SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        },
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig {
+            incoming_latency: Duration::default(),
+            incoming_jitter: Duration::default(),
+            incoming_loss: 0.0,
+        },
+        frame_duration,
+    );
+    stepper.init();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((
+            Component1(1.0),
+            server::Replicate {
+                visibility: VisibilityMode::InterestManagement,
+                ..default()
+            },
+        ))
+        .id();
+
+    // gain visibility
+    stepper
+        .server_app
+        .world
+        .resource_mut::<VisibilityManager>()
+        .gain_visibility(client_id, server_entity);
+    let mut visible = vec![];
+    let mut entered = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        visible.extend(drain::<EntityVisibleToClient>(&mut stepper.server_app));
+        entered.extend(drain::<EntityEnteredInterest>(&mut stepper.client_app));
+    }
+    assert_eq!(
+        visible,
+        vec![EntityVisibleToClient {
+            entity: server_entity,
+            client_id,
+        }]
+    );
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<client::ConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .unwrap();
+    assert_eq!(
+        entered,
+        vec![EntityEnteredInterest {
+            entity: client_entity
+        }]
+    );
+
+    // lose visibility
+    stepper
+        .server_app
+        .world
+        .resource_mut::<VisibilityManager>()
+        .lose_visibility(client_id, server_entity);
+    let mut hidden = vec![];
+    let mut left = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        hidden.extend(drain::<EntityHiddenFromClient>(&mut stepper.server_app));
+        left.extend(drain::<EntityLeftInterest>(&mut stepper.client_app));
+    }
+    assert_eq!(
+        hidden,
+        vec![EntityHiddenFromClient {
+            entity: server_entity,
+            client_id,
+        }]
+    );
+    assert_eq!(
+        left,
+        vec![EntityLeftInterest {
+            entity: client_entity
+        }]
+    );
+    // the entity still exists on the server
+    assert!(stepper.server_app.world.get_entity(server_entity).is_some());
+    assert!(stepper.client_app.world.get_entity(client_entity).is_none());
+}
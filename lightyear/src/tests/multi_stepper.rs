@@ -67,6 +67,27 @@ impl MultiBevyStepper {
         prediction_config: PredictionConfig,
         interpolation_config: InterpolationConfig,
         frame_duration: Duration,
+    ) -> Self {
+        Self::new_with_client_2_protocol(
+            shared_config,
+            sync_config,
+            prediction_config,
+            interpolation_config,
+            frame_duration,
+            |app| {
+                app.add_plugins(ProtocolPlugin);
+            },
+        )
+    }
+
+    /// Build the stepper with a different protocol for the second client
+    pub fn new_with_client_2_protocol(
+        shared_config: SharedConfig,
+        sync_config: SyncConfig,
+        prediction_config: PredictionConfig,
+        interpolation_config: InterpolationConfig,
+        frame_duration: Duration,
+        client_2_protocol: fn(&mut App),
     ) -> Self {
         let now = bevy::utils::Instant::now();
 
@@ -169,7 +190,7 @@ impl MultiBevyStepper {
             .unwrap()
             .update_with_instant(now);
 
-        let build_client = |net_config: NetConfig| -> App {
+        let build_client = |net_config: NetConfig, protocol: fn(&mut App)| -> App {
             let mut client_app = App::new();
             client_app.add_plugins(
                 MinimalPlugins
@@ -195,7 +216,8 @@ impl MultiBevyStepper {
                 ..default()
             };
             let plugin = client::ClientPlugins::new(config);
-            client_app.add_plugins((plugin, ProtocolPlugin));
+            client_app.add_plugins(plugin);
+            protocol(&mut client_app);
             // Initialize Real time (needed only for the first TimeSystem run)
             client_app
                 .world
@@ -206,8 +228,10 @@ impl MultiBevyStepper {
         };
 
         Self {
-            client_app_1: build_client(net_config_1),
-            client_app_2: build_client(net_config_2),
+            client_app_1: build_client(net_config_1, |app| {
+                app.add_plugins(ProtocolPlugin);
+            }),
+            client_app_2: build_client(net_config_2, client_2_protocol),
             server_app,
             frame_duration,
             tick_duration: shared_config.tick.tick_duration,
@@ -234,13 +258,13 @@ impl MultiBevyStepper {
             if self
                 .client_app_1
                 .world
-                .resource::<client::ConnectionManager>()
-                .is_synced()
+                .get_resource::<client::ConnectionManager>()
+                .is_some_and(|manager| manager.is_synced())
                 && self
                     .client_app_2
                     .world
-                    .resource::<client::ConnectionManager>()
-                    .is_synced()
+                    .get_resource::<client::ConnectionManager>()
+                    .is_some_and(|manager| manager.is_synced())
             {
                 return;
             }
@@ -47,6 +47,9 @@ impl MapEntities for Component4 {
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Add, Mul, Reflect)]
 pub struct Component5(pub f32);
 
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct Component6(pub f32);
+
 impl Mul<f32> for &Component5 {
     type Output = Component5;
     fn mul(self, rhs: f32) -> Self::Output {
@@ -120,6 +123,8 @@ impl Plugin for ProtocolPlugin {
             .add_interpolation(ComponentSyncMode::Full)
             .add_linear_interpolation_fn();
 
+        app.register_component::<Component6>(ChannelDirection::ClientToServer);
+
         // resources
         app.register_resource::<Resource1>(ChannelDirection::ServerToClient);
         app.register_resource::<Resource2>(ChannelDirection::Bidirectional);
@@ -7,7 +7,9 @@ use bevy::time::TimeUpdateStrategy;
 use bevy::utils::Duration;
 use bevy::MinimalPlugins;
 
+use crate::connection::client::{ClientConnection, NetClient};
 use crate::connection::netcode::generate_key;
+use crate::connection::server::{NetServer, ServerConnections};
 use crate::prelude::client::{
     Authentication, ClientCommands, ClientConfig, ClientTransport, InterpolationConfig,
     PredictionConfig, SyncConfig,
@@ -15,6 +17,7 @@ use crate::prelude::client::{
 use crate::prelude::server::{NetcodeConfig, ServerCommands, ServerConfig, ServerTransport};
 use crate::prelude::*;
 use crate::tests::protocol::*;
+use crate::transport::middleware::chaos::ChaosHandle;
 use crate::transport::LOCAL_SOCKET;
 
 pub const TEST_CLIENT_ID: u64 = 111;
@@ -180,6 +183,27 @@ impl BevyStepper {
     pub(crate) fn server_tick(&self) -> Tick {
         self.server_app.world.resource::<TickManager>().tick()
     }
+    /// Inject faults in the packets received by the client. Must be called after [`init`](Self::init)
+    pub(crate) fn client_chaos(&mut self, seed: u64) -> ChaosHandle {
+        self.client_app
+            .world
+            .resource_mut::<ClientConnection>()
+            .io_mut()
+            .expect("the client is not connected")
+            .add_chaos(seed)
+    }
+
+    /// Inject faults in the packets received by the server. Must be called after [`init`](Self::init)
+    pub(crate) fn server_chaos(&mut self, seed: u64) -> ChaosHandle {
+        self.server_app
+            .world
+            .resource_mut::<ServerConnections>()
+            .servers[0]
+            .io_mut()
+            .expect("the server is not started")
+            .add_chaos(seed)
+    }
+
     pub(crate) fn init(&mut self) {
         self.server_app.finish();
         self.server_app
@@ -195,8 +219,8 @@ impl BevyStepper {
             if self
                 .client_app
                 .world
-                .resource::<client::ConnectionManager>()
-                .is_synced()
+                .get_resource::<client::ConnectionManager>()
+                .is_some_and(|manager| manager.is_synced())
             {
                 break;
             }
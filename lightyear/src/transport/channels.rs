@@ -7,9 +7,13 @@ use crossbeam_channel::{Receiver, Select, Sender};
 use self_cell::self_cell;
 use tracing::info;
 
+#[cfg(feature = "client")]
 use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
+#[cfg(feature = "client")]
 use crate::client::io::{ClientIoEventReceiver, ClientNetworkEventSender};
+#[cfg(feature = "server")]
 use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
+#[cfg(feature = "server")]
 use crate::server::io::{ServerIoEventReceiver, ServerNetworkEventSender};
 use crate::transport::io::IoState;
 use crate::transport::{
@@ -54,6 +58,7 @@ impl Channels {
     }
 }
 
+#[cfg(feature = "server")]
 impl ServerTransportBuilder for Channels {
     fn start(
         self,
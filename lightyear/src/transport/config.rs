@@ -1,6 +1,9 @@
 use crate::transport::middleware::compression::CompressionConfig;
 use crate::transport::middleware::conditioner::LinkConditionerConfig;
+use crate::transport::relay::RelayConfig;
 use bevy::prelude::Reflect;
+use bevy::utils::Duration;
+use std::net::SocketAddr;
 
 #[derive(Clone, Debug, Default, Reflect)]
 #[reflect(from_reflect = false)]
@@ -9,6 +12,14 @@ pub struct SharedIoConfig<T> {
     pub transport: T,
     pub conditioner: Option<LinkConditionerConfig>,
     pub compression: CompressionConfig,
+    /// If set, all packets go through a [`RelayServer`](crate::transport::relay::RelayServer)
+    #[reflect(ignore)]
+    pub relay: Option<RelayConfig>,
+    /// Options for the OS socket (only used by the `UdpSocket` transport)
+    pub socket: SocketConfig,
+    /// If set, the transports that can check the liveness of the connection natively use their own
+    /// keep-alives instead of the netcode keep-alives
+    pub liveness: Option<LivenessConfig>,
 }
 
 impl<T> SharedIoConfig<T> {
@@ -17,6 +28,9 @@ impl<T> SharedIoConfig<T> {
             transport,
             conditioner: None,
             compression: CompressionConfig::default(),
+            relay: None,
+            socket: SocketConfig::default(),
+            liveness: None,
         }
     }
     pub fn with_conditioner(mut self, conditioner_config: LinkConditionerConfig) -> Self {
@@ -24,8 +38,139 @@ impl<T> SharedIoConfig<T> {
         self
     }
 
+    /// Send and receive all packets through the [`RelayServer`](crate::transport::relay::RelayServer)
+    /// at `relay_addr`.
+    ///
+    /// The client and the server must use the same `session_id`.
+    pub fn with_relay(mut self, relay_addr: SocketAddr, session_id: u64) -> Self {
+        self.relay = Some(RelayConfig {
+            relay_addr,
+            session_id,
+        });
+        self
+    }
+
     pub fn with_compression(mut self, compression_config: CompressionConfig) -> Self {
         self.compression = compression_config;
         self
     }
+
+    pub fn with_socket_config(mut self, socket_config: SocketConfig) -> Self {
+        self.socket = socket_config;
+        self
+    }
+
+    pub fn with_liveness(mut self, liveness_config: LivenessConfig) -> Self {
+        self.liveness = Some(liveness_config);
+        self
+    }
+}
+
+/// How the liveness of a connection is checked by the transports that support it natively
+/// (WebTransport uses QUIC keep-alives, WebSocket uses ping frames; not available on wasm).
+///
+/// For these transports, the netcode keep-alive packets are not sent once the connection is established,
+/// and the connection is closed as soon as the transport detects that the peer is not responding anymore.
+/// The other transports keep relying on the netcode keep-alives and timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct LivenessConfig {
+    /// Interval at which the transport sends a keep-alive if nothing else was sent
+    pub keep_alive_interval: Duration,
+    /// The connection is considered dead if nothing was received from the peer for this duration
+    pub timeout: Duration,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl LivenessConfig {
+    pub fn with_keep_alive_interval(mut self, keep_alive_interval: Duration) -> Self {
+        self.keep_alive_interval = keep_alive_interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Options applied to the OS socket.
+///
+/// Servers with a high tick rate and many clients can overflow the default kernel buffers, in which
+/// case the packets are dropped silently by the OS.
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub struct SocketConfig {
+    /// Size in bytes of the kernel send buffer (`SO_SNDBUF`). If `None`, the OS default is used.
+    /// Only supported on Linux; it is ignored (with a warning) on the other platforms
+    pub send_buffer_size: Option<usize>,
+    /// Size in bytes of the kernel receive buffer (`SO_RCVBUF`). If `None`, the OS default is used.
+    /// Only supported on Linux; it is ignored (with a warning) on the other platforms
+    pub recv_buffer_size: Option<usize>,
+    /// [DSCP](https://en.wikipedia.org/wiki/Differentiated_services) value used to mark the packets sent
+    /// by the socket, so that routers can prioritize them. Must be between 0 and 63.
+    /// Only supported on Linux; it is ignored (with a warning) on the other platforms
+    pub dscp: Option<u8>,
+    /// If set, the server spreads its traffic across multiple sockets bound to consecutive ports,
+    /// to exceed the throughput of a single socket. The clients must use the same sharding
+    pub sharding: Option<SocketSharding>,
+    /// Set the "Don't Fragment" bit on the packets sent by the socket.
+    /// This is only enabled when path MTU discovery is enabled in the `PacketConfig`
+    pub(crate) dont_fragment: bool,
+}
+
+impl SocketConfig {
+    /// DSCP class 'Expedited Forwarding', recommended for latency-sensitive traffic
+    pub const DSCP_EXPEDITED_FORWARDING: u8 = 46;
+
+    pub fn with_send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    pub fn with_recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    pub fn with_dscp(mut self, dscp: u8) -> Self {
+        self.dscp = Some(dscp);
+        self
+    }
+
+    pub fn with_sharding(mut self, sharding: SocketSharding) -> Self {
+        self.sharding = Some(sharding);
+        self
+    }
+}
+
+/// How the traffic is spread across the sockets of a UDP server.
+///
+/// The server binds the sockets on consecutive ports, starting at the port of its address.
+/// The clients send their packets to one of these ports, and the server answers from the same port,
+/// so the traffic of a client always goes through the same port (which plays nicer with NATs).
+/// The clients keep using the address of the first socket as the server address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum SocketSharding {
+    /// The server opens `num_sockets` sockets, and each client uses one of them (picked from its local port)
+    PerClient { num_sockets: u16 },
+    /// The server opens two sockets: the packets of the [`Stream`](crate::channel::builder::ChannelMode::Stream)
+    /// channels go through the second one, so that bulk transfers don't delay the other packets
+    PerDelivery,
+}
+
+impl SocketSharding {
+    /// Number of sockets opened by the server
+    pub fn num_sockets(&self) -> u16 {
+        match self {
+            SocketSharding::PerClient { num_sockets } => (*num_sockets).max(1),
+            SocketSharding::PerDelivery => 2,
+        }
+    }
 }
@@ -1,7 +1,11 @@
 //! Dummy io for connections that provide their own way of sending and receiving raw bytes (for example steamworks).
+#[cfg(feature = "client")]
 use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
+#[cfg(feature = "client")]
 use crate::client::io::{ClientIoEventReceiver, ClientNetworkEventSender};
+#[cfg(feature = "server")]
 use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
+#[cfg(feature = "server")]
 use crate::server::io::{ServerIoEventReceiver, ServerNetworkEventSender};
 use crate::transport::io::IoState;
 use crate::transport::{
@@ -14,6 +18,7 @@ use super::error::Result;
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct DummyIo;
 
+#[cfg(feature = "client")]
 impl ClientTransportBuilder for DummyIo {
     fn connect(
         self,
@@ -32,6 +37,7 @@ impl ClientTransportBuilder for DummyIo {
     }
 }
 
+#[cfg(feature = "server")]
 impl ServerTransportBuilder for DummyIo {
     fn start(
         self,
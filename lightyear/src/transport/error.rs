@@ -16,6 +16,8 @@ pub enum Error {
     Channel(String),
     #[error("requested by user")]
     UserRequest,
+    #[error("invalid transport configuration: {0}")]
+    InvalidConfig(String),
 }
 
 #[allow(unused_qualifications)]
@@ -3,19 +3,24 @@
 use async_channel::Receiver;
 use std::fmt::{Debug, Formatter};
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 
 use bevy::app::{App, Plugin};
 use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
 use bevy::prelude::{Deref, DerefMut, Real, Res, Resource, Time};
+use bevy::reflect::Reflect;
 #[cfg(feature = "metrics")]
 use metrics;
 use tracing::info;
 
+use crate::packet::mtu::PacketSizing;
 use crate::transport::middleware::conditioner::{
     ConditionedPacketReceiver, LinkConditioner, LinkConditionerConfig, PacketLinkConditioner,
 };
 use crate::transport::middleware::PacketReceiverWrapper;
-use crate::transport::{PacketReceiver, PacketSender, Transport};
+#[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+use crate::transport::pcap::PacketCapture;
+use crate::transport::{Delivery, PacketReceiver, PacketSender, Transport};
 
 use super::error::{Error, Result};
 use super::{BoxedReceiver, BoxedSender};
@@ -28,6 +33,11 @@ pub struct BaseIo<T: Send + Sync> {
     pub(crate) receiver: BoxedReceiver,
     pub(crate) state: IoState,
     pub(crate) stats: IoStats,
+    /// How the size of the packets sent through this io should be determined
+    pub(crate) packet_sizing: PacketSizing,
+    /// True if the transport checks the liveness of the connection itself, in which case
+    /// netcode doesn't need to send keep-alives or to time out the connection
+    pub(crate) native_liveness: bool,
     pub(crate) context: T,
 }
 
@@ -81,6 +91,15 @@ impl<T: Send + Sync> PacketReceiver for BaseIo<T> {
 
 impl<T: Send + Sync> PacketSender for BaseIo<T> {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.send_with_delivery(payload, address, Delivery::Datagram)
+    }
+
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
         // todo: bandwidth monitoring
         #[cfg(feature = "metrics")]
         {
@@ -89,7 +108,46 @@ impl<T: Send + Sync> PacketSender for BaseIo<T> {
         }
         self.stats.bytes_sent += payload.len();
         self.stats.packets_sent += 1;
-        self.sender.as_mut().send(payload, address)
+        self.sender
+            .as_mut()
+            .send_with_delivery(payload, address, delivery)
+    }
+}
+
+/// Configuration of the diagnostics of the packets sent and received by a connection
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub struct IoDiagnosticsConfig {
+    /// If set, all the packets sent and received (before encryption) are written to this
+    /// [pcapng](https://www.ietf.org/archive/id/draft-tuexen-opsawg-pcapng-05.html) file,
+    /// annotated with the tick and the client id.
+    ///
+    /// Requires the `pcap` feature. Not available on wasm.
+    pub packet_capture: Option<PathBuf>,
+}
+
+impl IoDiagnosticsConfig {
+    /// Write all the packets sent and received to the pcapng file at `path`
+    pub fn with_packet_capture(mut self, path: impl Into<PathBuf>) -> Self {
+        self.packet_capture = Some(path.into());
+        self
+    }
+
+    /// Create the packet capture file, if packet capture is enabled
+    #[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+    pub(crate) fn build_packet_capture(&self) -> Option<PacketCapture> {
+        let path = self.packet_capture.as_ref()?;
+        PacketCapture::create(path)
+            .inspect_err(|e| tracing::error!("could not start packet capture: {:?}", e))
+            .ok()
+    }
+
+    #[cfg(not(all(feature = "pcap", not(target_family = "wasm"))))]
+    pub(crate) fn warn_if_packet_capture(&self) {
+        if self.packet_capture.is_some() {
+            tracing::warn!(
+                "packet capture requires the `pcap` feature and is not available on wasm"
+            );
+        }
     }
 }
 
@@ -4,9 +4,13 @@ use std::net::SocketAddr;
 
 use crossbeam_channel::{Receiver, Sender};
 
+#[cfg(feature = "client")]
 use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
+#[cfg(feature = "client")]
 use crate::client::io::{ClientIoEventReceiver, ClientNetworkEventSender};
+#[cfg(feature = "server")]
 use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
+#[cfg(feature = "server")]
 use crate::server::io::{ServerIoEventReceiver, ServerNetworkEventSender};
 use crate::transport::io::IoState;
 use crate::transport::{
@@ -33,6 +37,7 @@ impl LocalChannelBuilder {
     }
 }
 
+#[cfg(feature = "client")]
 impl ClientTransportBuilder for LocalChannelBuilder {
     fn connect(
         self,
@@ -0,0 +1,300 @@
+//! Test-only middleware to inject faults in the packets received by an [`Io`](crate::transport::io::BaseIo)
+//!
+//! The faults are controlled from the tests via a [`ChaosHandle`], and are deterministic: they apply to the next
+//! packets that are received, and the randomness (for corruption and reordering) comes from a seeded rng.
+//! Time is measured with `mock_instant`, so stalls follow the time advanced by the test steppers.
+//! ```rust,ignore
+//! let chaos = stepper.client_chaos(0);
+//! // drop the next 3 packets sent by the server
+//! chaos.drop_next(3);
+//! ```
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use bevy::utils::Duration;
+use mock_instant::Instant;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::transport::dummy::DummyIo;
+use crate::transport::error::Result;
+use crate::transport::io::BaseIo;
+use crate::transport::{BoxedReceiver, PacketReceiver};
+
+type Packet = (SocketAddr, Box<[u8]>);
+
+/// How the received packets are reordered
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ReorderConfig {
+    /// The % chance that a packet is held back. Represented as a value between 0 and 1
+    pub probability: f32,
+    /// A held back packet is released after a number of packets picked uniformly in `1..=max_displacement`
+    pub max_displacement: usize,
+}
+
+/// Number of packets that were affected by each fault
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct ChaosStats {
+    pub dropped: usize,
+    pub duplicated: usize,
+    pub corrupted: usize,
+    pub reordered: usize,
+    pub stalled: usize,
+}
+
+struct ChaosState {
+    rng: StdRng,
+    drop_remaining: usize,
+    duplicate_remaining: usize,
+    corrupt_remaining: usize,
+    reorder: Option<ReorderConfig>,
+    stall_until: Option<Instant>,
+    /// Packets that are held back, with the number of packets that must be received before they are released
+    held_back: Vec<(usize, Packet)>,
+    /// Packets that can be returned by the receiver
+    ready: VecDeque<Packet>,
+    stats: ChaosStats,
+}
+
+impl ChaosState {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            drop_remaining: 0,
+            duplicate_remaining: 0,
+            corrupt_remaining: 0,
+            reorder: None,
+            stall_until: None,
+            held_back: Vec::new(),
+            ready: VecDeque::new(),
+            stats: ChaosStats::default(),
+        }
+    }
+
+    /// Apply the faults to a packet received from the inner receiver
+    fn process(&mut self, mut packet: Packet) {
+        if self.drop_remaining > 0 {
+            self.drop_remaining -= 1;
+            self.stats.dropped += 1;
+            return;
+        }
+        if self.corrupt_remaining > 0 && !packet.1.is_empty() {
+            self.corrupt_remaining -= 1;
+            self.stats.corrupted += 1;
+            let index = self.rng.gen_range(0..packet.1.len());
+            packet.1[index] ^= 0xFF;
+        }
+        let copies = if self.duplicate_remaining > 0 {
+            self.duplicate_remaining -= 1;
+            self.stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            self.reorder_packet(packet.clone());
+        }
+    }
+
+    fn reorder_packet(&mut self, packet: Packet) {
+        // the packets that were held back are released after the packets that overtook them
+        let mut released = Vec::new();
+        self.held_back.retain_mut(|(remaining, held)| {
+            *remaining -= 1;
+            if *remaining == 0 {
+                released.push(held.clone());
+                return false;
+            }
+            true
+        });
+        match self.reorder {
+            Some(reorder) if self.rng.gen_range(0.0..1.0) < reorder.probability => {
+                let displacement = self.rng.gen_range(1..=reorder.max_displacement.max(1));
+                self.stats.reordered += 1;
+                self.held_back.push((displacement, packet));
+            }
+            _ => self.ready.push_back(packet),
+        }
+        self.ready.extend(released);
+    }
+
+    fn pop_packet(&mut self) -> Option<Packet> {
+        if let Some(stall_until) = self.stall_until {
+            if Instant::now() < stall_until {
+                return None;
+            }
+            self.stall_until = None;
+        }
+        self.ready.pop_front()
+    }
+}
+
+/// Handle used by the tests to control the faults of a [`ChaosPacketReceiver`]
+#[derive(Clone)]
+pub(crate) struct ChaosHandle(Arc<Mutex<ChaosState>>);
+
+impl ChaosHandle {
+    /// Drop the next `n` packets
+    pub fn drop_next(&self, n: usize) {
+        self.0.lock().unwrap().drop_remaining += n;
+    }
+
+    /// Receive the next `n` packets twice
+    pub fn duplicate_next(&self, n: usize) {
+        self.0.lock().unwrap().duplicate_remaining += n;
+    }
+
+    /// Flip the bits of a random byte of the next `n` packets
+    pub fn corrupt_next(&self, n: usize) {
+        self.0.lock().unwrap().corrupt_remaining += n;
+    }
+
+    /// Reorder the packets, or stop reordering them if `reorder` is `None`
+    ///
+    /// The packets that were already held back are still released after their displacement.
+    pub fn reorder(&self, reorder: Option<ReorderConfig>) {
+        self.0.lock().unwrap().reorder = reorder;
+    }
+
+    /// Don't return any packet for `duration`. The packets received in the meantime are buffered
+    /// and returned in order once the stall is over.
+    pub fn stall(&self, duration: Duration) {
+        let mut state = self.0.lock().unwrap();
+        state.stats.stalled += 1;
+        state.stall_until = Some(Instant::now() + duration);
+    }
+
+    pub fn stats(&self) -> ChaosStats {
+        self.0.lock().unwrap().stats
+    }
+}
+
+/// A wrapper around a packet receiver that injects the faults requested via a [`ChaosHandle`]
+pub(crate) struct ChaosPacketReceiver {
+    packet_receiver: BoxedReceiver,
+    state: ChaosHandle,
+    /// The last packet returned, to hand out a reference to its data
+    last_packet: Option<Packet>,
+}
+
+impl PacketReceiver for ChaosPacketReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let mut state = self.state.0.lock().unwrap();
+        while let Some((data, addr)) = self.packet_receiver.recv()? {
+            state.process((addr, data.to_vec().into_boxed_slice()));
+        }
+        self.last_packet = state.pop_packet();
+        Ok(self
+            .last_packet
+            .as_mut()
+            .map(|(addr, data)| (data.as_mut(), *addr)))
+    }
+}
+
+impl<T: Send + Sync> BaseIo<T> {
+    /// Wrap the receiver of the io so that faults can be injected in the received packets
+    pub(crate) fn add_chaos(&mut self, seed: u64) -> ChaosHandle {
+        let handle = ChaosHandle(Arc::new(Mutex::new(ChaosState::new(seed))));
+        let receiver = std::mem::replace(&mut self.receiver, Box::new(DummyIo));
+        self.receiver = Box::new(ChaosPacketReceiver {
+            packet_receiver: receiver,
+            state: handle.clone(),
+            last_packet: None,
+        });
+        handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Receiver that returns the packets of a queue
+    struct QueueReceiver {
+        packets: VecDeque<Vec<u8>>,
+        buffer: Vec<u8>,
+    }
+
+    impl PacketReceiver for QueueReceiver {
+        fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+            let Some(packet) = self.packets.pop_front() else {
+                return Ok(None);
+            };
+            self.buffer = packet;
+            Ok(Some((
+                self.buffer.as_mut_slice(),
+                crate::transport::LOCAL_SOCKET,
+            )))
+        }
+    }
+
+    fn chaos_receiver(packets: &[u8]) -> (ChaosPacketReceiver, ChaosHandle) {
+        let handle = ChaosHandle(Arc::new(Mutex::new(ChaosState::new(0))));
+        let receiver = ChaosPacketReceiver {
+            packet_receiver: Box::new(QueueReceiver {
+                packets: packets.iter().map(|p| vec![*p]).collect(),
+                buffer: vec![],
+            }),
+            state: handle.clone(),
+            last_packet: None,
+        };
+        (receiver, handle)
+    }
+
+    fn recv_all(receiver: &mut ChaosPacketReceiver) -> Vec<u8> {
+        let mut received = vec![];
+        while let Some((data, _)) = receiver.recv().unwrap() {
+            received.push(data[0]);
+        }
+        received
+    }
+
+    #[test]
+    fn test_drop_duplicate_corrupt() {
+        let (mut receiver, handle) = chaos_receiver(&[1, 2, 3, 4]);
+        handle.drop_next(1);
+        handle.duplicate_next(1);
+        handle.corrupt_next(1);
+        assert_eq!(recv_all(&mut receiver), vec![!2, !2, 3, 4]);
+        assert_eq!(
+            handle.stats(),
+            ChaosStats {
+                dropped: 1,
+                duplicated: 1,
+                corrupted: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_reorder() {
+        let packets = (0..20).collect::<Vec<u8>>();
+        let (mut receiver, handle) = chaos_receiver(&packets);
+        handle.reorder(Some(ReorderConfig {
+            probability: 0.3,
+            max_displacement: 3,
+        }));
+        let received = recv_all(&mut receiver);
+        assert!(handle.stats().reordered > 0);
+        assert_ne!(received, packets);
+        // the reordering is deterministic
+        let (mut other_receiver, other_handle) = chaos_receiver(&packets);
+        other_handle.reorder(Some(ReorderConfig {
+            probability: 0.3,
+            max_displacement: 3,
+        }));
+        assert_eq!(recv_all(&mut other_receiver), received);
+    }
+
+    #[test]
+    fn test_stall() {
+        let (mut receiver, handle) = chaos_receiver(&[1, 2]);
+        // the mock clock is shared with the other tests, which can advance it concurrently
+        handle.stall(Duration::from_secs(3600));
+        assert_eq!(recv_all(&mut receiver), Vec::<u8>::new());
+        handle.stall(Duration::ZERO);
+        assert_eq!(recv_all(&mut receiver), vec![1, 2]);
+    }
+}
@@ -7,7 +7,7 @@ use std::net::SocketAddr;
 pub(crate) mod compression {
     use super::*;
     use crate::transport::middleware::PacketSenderWrapper;
-    use crate::transport::PacketSender;
+    use crate::transport::{Delivery, PacketSender};
     use zstd::bulk::Compressor;
 
     pub(crate) struct ZstdCompressor {
@@ -41,6 +41,16 @@ pub(crate) mod compression {
             let compressed = self.compressor.compress(payload)?;
             self.inner.send(compressed, address)
         }
+
+        fn send_with_delivery(
+            &mut self,
+            payload: &[u8],
+            address: &SocketAddr,
+            delivery: Delivery,
+        ) -> Result<()> {
+            let compressed = self.compressor.compress(payload)?;
+            self.inner.send_with_delivery(compressed, address, delivery)
+        }
     }
 
     impl<T: PacketSender> PacketSenderWrapper<T> for ZstdCompressor {
@@ -121,6 +131,9 @@ mod tests {
             transport: config,
             conditioner: None,
             compression: CompressionConfig::Zstd { level: 0 },
+            relay: None,
+            socket: Default::default(),
+            liveness: None,
         };
         let mut io = io_config.connect().unwrap();
         let msg = b"hello world".as_slice();
@@ -9,6 +9,10 @@ pub(crate) mod conditioner;
 /// Middleware that compresses packets before sending them.
 pub(crate) mod compression;
 
+/// Test-only middleware that injects faults (drops, duplicates, corruption, reordering, stalls) in the received packets.
+#[cfg(test)]
+pub(crate) mod chaos;
+
 pub trait PacketReceiverWrapper<T: PacketReceiver> {
     fn wrap(self, receiver: T) -> impl PacketReceiver;
 }
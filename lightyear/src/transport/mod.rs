@@ -7,22 +7,29 @@ use enum_dispatch::enum_dispatch;
 use error::Result;
 
 // required import for enum dispatch to work
+#[cfg(feature = "client")]
 use crate::client::io::transport::ClientTransportEnum;
+use crate::packet::mtu::PacketSizing;
+#[cfg(feature = "server")]
 use crate::server::io::transport::ServerTransportEnum;
 use crate::transport::channels::Channels;
 use crate::transport::dummy::DummyIo;
 use crate::transport::io::IoState;
 use crate::transport::local::{LocalChannel, LocalChannelBuilder};
 use crate::transport::udp::{UdpSocket, UdpSocketBuilder};
-#[cfg(feature = "websocket")]
+#[cfg(all(feature = "client", feature = "websocket"))]
 use crate::transport::websocket::client::{WebSocketClientSocket, WebSocketClientSocketBuilder};
-#[cfg(all(feature = "websocket", not(target_family = "wasm")))]
+#[cfg(all(feature = "server", feature = "websocket", not(target_family = "wasm")))]
 use crate::transport::websocket::server::{WebSocketServerSocket, WebSocketServerSocketBuilder};
-#[cfg(feature = "webtransport")]
+#[cfg(all(feature = "client", feature = "webtransport"))]
 use crate::transport::webtransport::client::{
     WebTransportClientSocket, WebTransportClientSocketBuilder,
 };
-#[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
+#[cfg(all(
+    feature = "server",
+    feature = "webtransport",
+    not(target_family = "wasm")
+))]
 use crate::transport::webtransport::server::{
     WebTransportServerSocket, WebTransportServerSocketBuilder,
 };
@@ -46,9 +53,19 @@ pub(crate) mod webtransport;
 
 pub(crate) mod middleware;
 
+/// Forward packets through a relay server
+pub mod relay;
+
 pub mod config;
 pub(crate) mod dummy;
 pub(crate) mod error;
+/// Capture the packets to a pcapng file
+#[cfg_attr(docsrs, doc(cfg(feature = "pcap")))]
+#[cfg(all(feature = "pcap", not(target_family = "wasm")))]
+pub(crate) mod pcap;
+#[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+#[cfg(feature = "websocket")]
+pub(crate) mod proxy;
 #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
 #[cfg(feature = "websocket")]
 pub(crate) mod websocket;
@@ -73,18 +90,58 @@ pub(crate) trait Transport {
     ///
     /// This is useful to have parallel mutable access to the sender and the retriever
     fn split(self) -> (BoxedSender, BoxedReceiver);
+
+    /// How the size of the packets sent through this transport should be determined
+    fn packet_sizing(&self) -> PacketSizing {
+        PacketSizing::default()
+    }
+}
+
+/// How a packet should be delivered, for transports that support multiple delivery methods.
+///
+/// Only WebTransport currently makes the distinction; every other transport sends all packets the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Delivery {
+    /// Send the packet as an unreliable datagram (lowest latency, but packets can be lost)
+    #[default]
+    Datagram,
+    /// Send the packet on a reliable ordered stream. This is better suited for bulky reliable
+    /// channels since the packets never need to be resent, but a lost packet delays the ones after it.
+    Stream,
 }
 
 /// Send data to a remote address
 pub trait PacketSender: Send + Sync {
     /// Send data on the socket to the remote address
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()>;
+
+    /// Send data to the remote address using the given [`Delivery`] method.
+    ///
+    /// Transports that only support one delivery method ignore the `delivery` argument
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
+        let _ = delivery;
+        self.send(payload, address)
+    }
 }
 
 impl PacketSender for BoxedSender {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
         (**self).send(payload, address)
     }
+
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
+        (**self).send_with_delivery(payload, address, delivery)
+    }
 }
 
 /// Receive data from a remote address
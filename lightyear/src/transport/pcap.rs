@@ -0,0 +1,228 @@
+//! Write the packets sent and received by a connection to a [pcapng](https://www.ietf.org/archive/id/draft-tuexen-opsawg-pcapng-05.html) file
+//!
+//! The packets are captured before encryption (and after decryption), so their content can be
+//! inspected directly in a tool like Wireshark. Each packet is annotated with a comment containing
+//! the local tick and the id of the remote client.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use tracing::{error, info};
+
+use crate::connection::id::ClientId;
+use crate::packet::packet::Packet;
+use crate::prelude::Tick;
+use crate::serialize::bitcode::writer::BitcodeWriter;
+use crate::serialize::writer::WriteBuffer;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x00000001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+/// Link-layer type reserved for private use: the packets are lightyear packets, not ethernet frames
+const LINKTYPE_USER0: u16 = 147;
+
+const OPT_END_OF_OPT: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const OPT_IF_NAME: u16 = 2;
+const OPT_EPB_FLAGS: u16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Direction {
+    Sent,
+    Received,
+}
+
+impl Direction {
+    /// Value of the direction bits in the `epb_flags` option
+    fn flags(&self) -> u32 {
+        match self {
+            Direction::Received => 0b01,
+            Direction::Sent => 0b10,
+        }
+    }
+}
+
+/// Writes the captured packets to a pcapng file
+pub(crate) struct PacketCapture {
+    file: BufWriter<File>,
+    /// Buffer used to re-encode the received packets
+    writer: BitcodeWriter,
+}
+
+impl std::fmt::Debug for PacketCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketCapture").finish()
+    }
+}
+
+impl PacketCapture {
+    /// Create the capture file and write the pcapng headers
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("could not create the packet capture file {:?}", path))?;
+        let mut capture = Self {
+            file: BufWriter::new(file),
+            writer: BitcodeWriter::with_capacity(1500),
+        };
+        capture.write_headers()?;
+        info!(?path, "Capturing packets");
+        Ok(capture)
+    }
+
+    fn write_headers(&mut self) -> Result<()> {
+        // section header block
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        // version 1.0
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // unknown section length
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        self.write_block(SECTION_HEADER_BLOCK, &body)?;
+
+        // interface description block
+        let mut body = Vec::new();
+        body.extend_from_slice(&LINKTYPE_USER0.to_le_bytes());
+        // reserved
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // no snapshot length limit
+        body.extend_from_slice(&0u32.to_le_bytes());
+        write_option(&mut body, OPT_IF_NAME, b"lightyear");
+        write_option(&mut body, OPT_END_OF_OPT, &[]);
+        self.write_block(INTERFACE_DESCRIPTION_BLOCK, &body)
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> Result<()> {
+        // block type + 2 * block total length + body
+        let total_length = (12 + body.len()) as u32;
+        self.file.write_all(&block_type.to_le_bytes())?;
+        self.file.write_all(&total_length.to_le_bytes())?;
+        self.file.write_all(body)?;
+        self.file.write_all(&total_length.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn write_packet(
+        &mut self,
+        direction: Direction,
+        tick: Tick,
+        client_id: Option<ClientId>,
+        payload: &[u8],
+    ) -> Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let mut body = Vec::with_capacity(payload.len() + 64);
+        // interface id
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        // captured and original packet length
+        body.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        body.extend_from_slice(payload);
+        pad(&mut body);
+        let comment = match client_id {
+            Some(client_id) => format!("tick={} client_id={}", tick.0, client_id),
+            None => format!("tick={}", tick.0),
+        };
+        write_option(&mut body, OPT_COMMENT, comment.as_bytes());
+        write_option(&mut body, OPT_EPB_FLAGS, &direction.flags().to_le_bytes());
+        write_option(&mut body, OPT_END_OF_OPT, &[]);
+        self.write_block(ENHANCED_PACKET_BLOCK, &body)?;
+        // flush so that the capture can be inspected while the app is running
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Capture a packet that is about to be sent (before encryption)
+    pub(crate) fn capture_sent(&mut self, tick: Tick, client_id: Option<ClientId>, payload: &[u8]) {
+        let _ = self
+            .write_packet(Direction::Sent, tick, client_id, payload)
+            .inspect_err(|e| error!("could not capture sent packet: {:?}", e));
+    }
+
+    /// Capture a packet that was received (after decryption)
+    pub(crate) fn capture_received(
+        &mut self,
+        tick: Tick,
+        client_id: Option<ClientId>,
+        packet: &Packet,
+    ) {
+        // the netcode layer only gives us the decoded packet, so we encode it again to get the raw bytes
+        self.writer.start_write();
+        if let Err(e) = packet.encode(&mut self.writer) {
+            error!("could not encode received packet for capture: {:?}", e);
+            return;
+        }
+        let payload = self.writer.finish_write().to_vec();
+        let _ = self
+            .write_packet(Direction::Received, tick, client_id, &payload)
+            .inspect_err(|e| error!("could not capture received packet: {:?}", e));
+    }
+}
+
+/// Pad the buffer to a multiple of 4 bytes
+fn pad(buffer: &mut Vec<u8>) {
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+}
+
+fn write_option(buffer: &mut Vec<u8>, code: u16, value: &[u8]) {
+    buffer.extend_from_slice(&code.to_le_bytes());
+    buffer.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    buffer.extend_from_slice(value);
+    pad(buffer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_write_pcapng() {
+        let path = std::env::temp_dir().join(format!("lightyear-{}.pcapng", std::process::id()));
+        let mut capture = PacketCapture::create(&path).unwrap();
+        capture.capture_sent(Tick(3), Some(ClientId::Netcode(1)), &[1, 2, 3, 4, 5]);
+        drop(capture);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // iterate through the blocks
+        let mut offset = 0;
+        let mut blocks = vec![];
+        while offset < bytes.len() {
+            let block_type = read_u32(&bytes, offset);
+            let length = read_u32(&bytes, offset + 4) as usize;
+            assert_eq!(length % 4, 0);
+            assert_eq!(read_u32(&bytes, offset + length - 4) as usize, length);
+            blocks.push((block_type, offset));
+            offset += length;
+        }
+        assert_eq!(offset, bytes.len());
+        assert_eq!(
+            blocks.iter().map(|(t, _)| *t).collect::<Vec<_>>(),
+            vec![
+                SECTION_HEADER_BLOCK,
+                INTERFACE_DESCRIPTION_BLOCK,
+                ENHANCED_PACKET_BLOCK
+            ]
+        );
+        let packet_offset = blocks[2].1;
+        // captured length
+        assert_eq!(read_u32(&bytes, packet_offset + 20), 5);
+        assert_eq!(
+            &bytes[packet_offset + 28..packet_offset + 33],
+            &[1, 2, 3, 4, 5]
+        );
+        let comment = b"tick=3 client_id=Netcode(1)";
+        assert!(bytes.windows(comment.len()).any(|window| window == comment));
+    }
+}
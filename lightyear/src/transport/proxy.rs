@@ -0,0 +1,324 @@
+//! Tunnel the TCP connection of a client transport through a SOCKS5 or HTTP proxy
+//!
+//! This is used by the transports that are based on TCP (for example WebSocket) when the player can only
+//! reach the internet through a proxy.
+use std::net::SocketAddr;
+
+/// Username and password used to authenticate with the proxy
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProxyCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Proxy through which the connection to the server is tunneled
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProxyConfig {
+    /// [SOCKS5](https://datatracker.ietf.org/doc/html/rfc1928) proxy
+    Socks5 {
+        proxy_addr: SocketAddr,
+        credentials: Option<ProxyCredentials>,
+    },
+    /// HTTP proxy that supports the `CONNECT` method
+    HttpConnect {
+        proxy_addr: SocketAddr,
+        credentials: Option<ProxyCredentials>,
+    },
+}
+
+impl ProxyConfig {
+    pub fn socks5(proxy_addr: SocketAddr) -> Self {
+        Self::Socks5 {
+            proxy_addr,
+            credentials: None,
+        }
+    }
+
+    pub fn http_connect(proxy_addr: SocketAddr) -> Self {
+        Self::HttpConnect {
+            proxy_addr,
+            credentials: None,
+        }
+    }
+
+    /// Authenticate with the proxy using a username and a password
+    /// (SOCKS5 username/password authentication, or HTTP basic authentication)
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        let new_credentials = Some(ProxyCredentials {
+            username: username.into(),
+            password: password.into(),
+        });
+        match &mut self {
+            Self::Socks5 { credentials, .. } | Self::HttpConnect { credentials, .. } => {
+                *credentials = new_credentials;
+            }
+        }
+        self
+    }
+
+    /// Address of the proxy
+    pub fn proxy_addr(&self) -> SocketAddr {
+        match self {
+            Self::Socks5 { proxy_addr, .. } | Self::HttpConnect { proxy_addr, .. } => *proxy_addr,
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub(crate) use native::connect;
+
+#[cfg(not(target_family = "wasm"))]
+mod native {
+    use std::io::{Error, ErrorKind, Result};
+    use std::net::SocketAddr;
+
+    use base64::Engine;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tracing::debug;
+
+    use super::{ProxyConfig, ProxyCredentials};
+
+    const SOCKS_VERSION: u8 = 0x05;
+    const SOCKS_NO_AUTH: u8 = 0x00;
+    const SOCKS_USERNAME_PASSWORD: u8 = 0x02;
+    const SOCKS_CONNECT: u8 = 0x01;
+    const SOCKS_IPV4: u8 = 0x01;
+    const SOCKS_DOMAIN: u8 = 0x03;
+    const SOCKS_IPV6: u8 = 0x04;
+    /// Maximum size of the response headers of an HTTP proxy
+    const MAX_HTTP_RESPONSE_SIZE: usize = 8192;
+
+    /// Open a TCP connection to `target`; if a proxy is provided, the connection is tunneled through it
+    pub(crate) async fn connect(
+        target: SocketAddr,
+        proxy: Option<&ProxyConfig>,
+    ) -> Result<TcpStream> {
+        let Some(proxy) = proxy else {
+            return TcpStream::connect(target).await;
+        };
+        let mut stream = TcpStream::connect(proxy.proxy_addr()).await?;
+        match proxy {
+            ProxyConfig::Socks5 { credentials, .. } => {
+                socks5_handshake(&mut stream, target, credentials.as_ref()).await?
+            }
+            ProxyConfig::HttpConnect { credentials, .. } => {
+                http_connect_handshake(&mut stream, target, credentials.as_ref()).await?
+            }
+        }
+        debug!(?target, proxy = ?proxy.proxy_addr(), "Connected through proxy");
+        Ok(stream)
+    }
+
+    fn proxy_error(message: impl Into<String>) -> Error {
+        Error::new(ErrorKind::ConnectionRefused, message.into())
+    }
+
+    pub(super) async fn socks5_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+        stream: &mut S,
+        target: SocketAddr,
+        credentials: Option<&ProxyCredentials>,
+    ) -> Result<()> {
+        // method selection
+        let methods: &[u8] = match credentials {
+            None => &[SOCKS_NO_AUTH],
+            Some(_) => &[SOCKS_NO_AUTH, SOCKS_USERNAME_PASSWORD],
+        };
+        let mut request = vec![SOCKS_VERSION, methods.len() as u8];
+        request.extend_from_slice(methods);
+        stream.write_all(&request).await?;
+        let mut response = [0; 2];
+        stream.read_exact(&mut response).await?;
+        if response[0] != SOCKS_VERSION {
+            return Err(proxy_error("the proxy is not a SOCKS5 proxy"));
+        }
+        match (response[1], credentials) {
+            (SOCKS_NO_AUTH, _) => {}
+            (SOCKS_USERNAME_PASSWORD, Some(credentials)) => {
+                // username/password authentication (RFC 1929)
+                let username = credentials.username.as_bytes();
+                let password = credentials.password.as_bytes();
+                if username.len() > u8::MAX as usize || password.len() > u8::MAX as usize {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "the SOCKS5 username and password must be at most 255 bytes",
+                    ));
+                }
+                let mut request = vec![0x01, username.len() as u8];
+                request.extend_from_slice(username);
+                request.push(password.len() as u8);
+                request.extend_from_slice(password);
+                stream.write_all(&request).await?;
+                let mut response = [0; 2];
+                stream.read_exact(&mut response).await?;
+                if response[1] != 0 {
+                    return Err(proxy_error("the SOCKS5 proxy rejected the credentials"));
+                }
+            }
+            // the proxy answers 0xFF if it accepts none of the methods
+            _ => {
+                return Err(proxy_error(
+                    "the SOCKS5 proxy does not support any of the authentication methods",
+                ));
+            }
+        }
+
+        // connect request
+        let mut request = vec![SOCKS_VERSION, SOCKS_CONNECT, 0x00];
+        match target {
+            SocketAddr::V4(addr) => {
+                request.push(SOCKS_IPV4);
+                request.extend_from_slice(&addr.ip().octets());
+            }
+            SocketAddr::V6(addr) => {
+                request.push(SOCKS_IPV6);
+                request.extend_from_slice(&addr.ip().octets());
+            }
+        }
+        request.extend_from_slice(&target.port().to_be_bytes());
+        stream.write_all(&request).await?;
+        let mut response = [0; 4];
+        stream.read_exact(&mut response).await?;
+        if response[1] != 0 {
+            return Err(proxy_error(format!(
+                "the SOCKS5 proxy could not connect to the server (reply code {})",
+                response[1]
+            )));
+        }
+        // skip the address that the proxy bound
+        let address_len = match response[3] {
+            SOCKS_IPV4 => 4,
+            SOCKS_IPV6 => 16,
+            SOCKS_DOMAIN => stream.read_u8().await? as usize,
+            _ => return Err(proxy_error("invalid SOCKS5 address type")),
+        };
+        let mut bound_address = vec![0; address_len + 2];
+        stream.read_exact(&mut bound_address).await?;
+        Ok(())
+    }
+
+    pub(super) async fn http_connect_handshake<S: AsyncRead + AsyncWrite + Unpin>(
+        stream: &mut S,
+        target: SocketAddr,
+        credentials: Option<&ProxyCredentials>,
+    ) -> Result<()> {
+        let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+        if let Some(credentials) = credentials {
+            let token = base64::engine::general_purpose::STANDARD
+                .encode(format!("{}:{}", credentials.username, credentials.password));
+            request.push_str(&format!("Proxy-Authorization: Basic {token}\r\n"));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // read the response headers one byte at a time, so that we don't consume any of the
+        // bytes sent by the server after the tunnel is established
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() >= MAX_HTTP_RESPONSE_SIZE {
+                return Err(proxy_error("the HTTP proxy response is too long"));
+            }
+            response.push(stream.read_u8().await?);
+        }
+        let response = String::from_utf8_lossy(&response);
+        let status_line = response.lines().next().unwrap_or_default();
+        let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+        if !status.starts_with('2') {
+            return Err(proxy_error(format!(
+                "the HTTP proxy could not connect to the server: {status_line}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_family = "wasm")))]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::native::{http_connect_handshake, socks5_handshake};
+    use super::ProxyCredentials;
+
+    fn credentials() -> ProxyCredentials {
+        ProxyCredentials {
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socks5_handshake() {
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+        let target = "127.0.0.1:5000".parse().unwrap();
+        let proxy_task = tokio::spawn(async move {
+            let mut buf = [0; 4];
+            proxy.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [0x05, 2, 0x00, 0x02]);
+            proxy.write_all(&[0x05, 0x02]).await.unwrap();
+            let mut buf = [0; 11];
+            proxy.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"\x01\x04user\x04pass");
+            proxy.write_all(&[0x01, 0x00]).await.unwrap();
+            let mut buf = [0; 10];
+            proxy.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [0x05, 0x01, 0x00, 0x01, 127, 0, 0, 1, 0x13, 0x88]);
+            proxy
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            // the tunnel is established
+            proxy.write_all(b"hello").await.unwrap();
+        });
+        socks5_handshake(&mut client, target, Some(&credentials()))
+            .await
+            .unwrap();
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+        proxy_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_handshake() {
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+        let target = "127.0.0.1:5000".parse().unwrap();
+        let proxy_task = tokio::spawn(async move {
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(proxy.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("CONNECT 127.0.0.1:5000 HTTP/1.1\r\n"));
+            assert!(request.contains("Proxy-Authorization: Basic dXNlcjpwYXNz\r\n"));
+            proxy
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\nhello")
+                .await
+                .unwrap();
+        });
+        http_connect_handshake(&mut client, target, Some(&credentials()))
+            .await
+            .unwrap();
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+        proxy_task.await.unwrap();
+
+        // the proxy refuses the connection
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            let mut buf = [0; 1024];
+            let _ = proxy.read(&mut buf).await.unwrap();
+            proxy
+                .write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        assert!(http_connect_handshake(&mut client, target, None)
+            .await
+            .is_err());
+    }
+}
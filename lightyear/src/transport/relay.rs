@@ -0,0 +1,778 @@
+//! Relay that forwards packets between clients and a server that has no public IP.
+//!
+//! The server opens a connection to a publicly reachable [`RelayServer`] and registers a `session_id`.
+//! Clients then send their packets to the relay (tagged with the `session_id`), which forwards them to the server,
+//! and the server's responses are forwarded back to the clients.
+//!
+//! The relay only forwards the packets: it cannot read them since they are encrypted by
+//! the netcode layer, so the relay does not need to be trusted.
+//!
+//! Relaying is transparent for the connection layer:
+//! - on the client, the packets received from the relay are reported as coming from the server address
+//! - on the server, the packets received from the relay are reported as coming from the real address of the client
+//!
+//! Enable it with [`SharedIoConfig::with_relay`](crate::transport::config::SharedIoConfig::with_relay) on both the client and the server.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use bevy::utils::{Duration, HashMap, Instant};
+use tracing::{debug, trace};
+
+use crate::transport::error::Result;
+use crate::transport::{BoxedReceiver, BoxedSender, Delivery, PacketReceiver, PacketSender, MTU};
+
+/// First byte of every relay packet.
+///
+/// The lower 4 bits of the first byte of a netcode packet encode the packet type, which is always lower than 7,
+/// so relay packets can never be confused with netcode packets.
+const MAGIC: u8 = 0xFE;
+
+/// Maximum size of the relay header
+const MAX_HEADER_SIZE: usize = 2 + 19;
+
+/// Interval at which the server re-registers its session with the relay
+const REGISTER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the relay keeps a session or a client without receiving any packet from it
+const SESSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Clients that are connected through the relay, with the last time that we received a packet from them
+type RelayedClients = Arc<Mutex<HashMap<SocketAddr, Instant>>>;
+
+/// Configuration to connect through a [`RelayServer`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RelayConfig {
+    /// Address of the relay server
+    pub relay_addr: SocketAddr,
+    /// Identifies the server that the packets should be forwarded to
+    pub session_id: u64,
+}
+
+#[derive(Debug, PartialEq)]
+enum RelayPacket<'a> {
+    /// Server -> Relay: register (or keep alive) the session
+    RegisterHost { session_id: u64 },
+    /// Client -> Relay: forward the payload to the server of the session
+    ToHost { session_id: u64, payload: &'a [u8] },
+    /// Relay -> Server: payload sent by a client
+    FromClient {
+        client_addr: SocketAddr,
+        payload: &'a [u8],
+    },
+    /// Server -> Relay: forward the payload to the client
+    ToClient {
+        client_addr: SocketAddr,
+        payload: &'a [u8],
+    },
+    /// Relay -> Client: payload sent by the server
+    FromHost { payload: &'a [u8] },
+}
+
+impl<'a> RelayPacket<'a> {
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        buf.push(MAGIC);
+        match self {
+            RelayPacket::RegisterHost { session_id } => {
+                buf.push(0);
+                buf.extend_from_slice(&session_id.to_be_bytes());
+            }
+            RelayPacket::ToHost {
+                session_id,
+                payload,
+            } => {
+                buf.push(1);
+                buf.extend_from_slice(&session_id.to_be_bytes());
+                buf.extend_from_slice(payload);
+            }
+            RelayPacket::FromClient {
+                client_addr,
+                payload,
+            } => {
+                buf.push(2);
+                write_addr(buf, client_addr);
+                buf.extend_from_slice(payload);
+            }
+            RelayPacket::ToClient {
+                client_addr,
+                payload,
+            } => {
+                buf.push(3);
+                write_addr(buf, client_addr);
+                buf.extend_from_slice(payload);
+            }
+            RelayPacket::FromHost { payload } => {
+                buf.push(4);
+                buf.extend_from_slice(payload);
+            }
+        }
+    }
+
+    /// Returns None if the buffer is not a valid relay packet
+    fn read(buf: &'a [u8]) -> Option<Self> {
+        let [MAGIC, kind, rest @ ..] = buf else {
+            return None;
+        };
+        match kind {
+            0 => Some(RelayPacket::RegisterHost {
+                session_id: u64::from_be_bytes(rest.try_into().ok()?),
+            }),
+            1 => {
+                let (session_id, payload) = split_array::<8>(rest)?;
+                Some(RelayPacket::ToHost {
+                    session_id: u64::from_be_bytes(session_id),
+                    payload,
+                })
+            }
+            2 => {
+                let (client_addr, payload) = read_addr(rest)?;
+                Some(RelayPacket::FromClient {
+                    client_addr,
+                    payload,
+                })
+            }
+            3 => {
+                let (client_addr, payload) = read_addr(rest)?;
+                Some(RelayPacket::ToClient {
+                    client_addr,
+                    payload,
+                })
+            }
+            4 => Some(RelayPacket::FromHost { payload: rest }),
+            _ => None,
+        }
+    }
+}
+
+fn write_addr(buf: &mut Vec<u8>, addr: &SocketAddr) {
+    match addr.ip() {
+        IpAddr::V4(ip) => {
+            buf.push(4);
+            buf.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            buf.push(6);
+            buf.extend_from_slice(&ip.octets());
+        }
+    }
+    buf.extend_from_slice(&addr.port().to_be_bytes());
+}
+
+fn read_addr(buf: &[u8]) -> Option<(SocketAddr, &[u8])> {
+    let (version, rest) = buf.split_first()?;
+    let (ip, rest) = match version {
+        4 => {
+            let (ip, rest) = split_array::<4>(rest)?;
+            (IpAddr::V4(Ipv4Addr::from(ip)), rest)
+        }
+        6 => {
+            let (ip, rest) = split_array::<16>(rest)?;
+            (IpAddr::V6(Ipv6Addr::from(ip)), rest)
+        }
+        _ => return None,
+    };
+    let (port, rest) = split_array::<2>(rest)?;
+    Some((SocketAddr::new(ip, u16::from_be_bytes(port)), rest))
+}
+
+fn split_array<const N: usize>(buf: &[u8]) -> Option<([u8; N], &[u8])> {
+    if buf.len() < N {
+        return None;
+    }
+    let (head, rest) = buf.split_at(N);
+    Some((head.try_into().ok()?, rest))
+}
+
+/// Wrap the sender/receiver of a client io so that all packets go through the relay
+pub(crate) fn wrap_client(
+    config: RelayConfig,
+    sender: BoxedSender,
+    receiver: BoxedReceiver,
+) -> (BoxedSender, BoxedReceiver) {
+    let server_addr = Arc::new(Mutex::new(None));
+    (
+        Box::new(RelayClientSender {
+            inner: sender,
+            config,
+            server_addr: server_addr.clone(),
+            buffer: Vec::with_capacity(MTU + MAX_HEADER_SIZE),
+        }),
+        Box::new(RelayClientReceiver {
+            inner: receiver,
+            config,
+            server_addr,
+            buffer: [0; MTU],
+        }),
+    )
+}
+
+/// Wrap the sender/receiver of a server io so that it accepts connections arriving through the relay
+pub(crate) fn wrap_server(
+    config: RelayConfig,
+    sender: BoxedSender,
+    receiver: BoxedReceiver,
+) -> (BoxedSender, BoxedReceiver) {
+    let sender = Arc::new(Mutex::new(sender));
+    let relayed_clients = Arc::new(Mutex::new(HashMap::default()));
+    (
+        Box::new(RelayServerSender {
+            inner: sender.clone(),
+            config,
+            relayed_clients: relayed_clients.clone(),
+            buffer: Vec::with_capacity(MTU + MAX_HEADER_SIZE),
+        }),
+        Box::new(RelayServerReceiver {
+            inner: receiver,
+            sender,
+            config,
+            relayed_clients,
+            last_register: None,
+            buffer: [0; MTU],
+        }),
+    )
+}
+
+struct RelayClientSender {
+    inner: BoxedSender,
+    config: RelayConfig,
+    /// The address that the connection layer is sending packets to
+    server_addr: Arc<Mutex<Option<SocketAddr>>>,
+    buffer: Vec<u8>,
+}
+
+impl PacketSender for RelayClientSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.send_with_delivery(payload, address, Delivery::Datagram)
+    }
+
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
+        *self.server_addr.lock().unwrap() = Some(*address);
+        RelayPacket::ToHost {
+            session_id: self.config.session_id,
+            payload,
+        }
+        .write(&mut self.buffer);
+        self.inner
+            .send_with_delivery(&self.buffer, &self.config.relay_addr, delivery)
+    }
+}
+
+struct RelayClientReceiver {
+    inner: BoxedReceiver,
+    config: RelayConfig,
+    server_addr: Arc<Mutex<Option<SocketAddr>>>,
+    buffer: [u8; MTU],
+}
+
+impl PacketReceiver for RelayClientReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        loop {
+            let Some((buf, addr)) = self.inner.recv()? else {
+                return Ok(None);
+            };
+            let Some(RelayPacket::FromHost { payload }) =
+                RelayPacket::read(buf).filter(|_| addr == self.config.relay_addr)
+            else {
+                trace!(?addr, "dropping packet that was not sent by the relay");
+                continue;
+            };
+            // the packet is reported as coming from the server
+            let origin = self
+                .server_addr
+                .lock()
+                .unwrap()
+                .unwrap_or(self.config.relay_addr);
+            let len = payload.len();
+            self.buffer[..len].copy_from_slice(payload);
+            return Ok(Some((&mut self.buffer[..len], origin)));
+        }
+    }
+}
+
+struct RelayServerSender {
+    inner: Arc<Mutex<BoxedSender>>,
+    config: RelayConfig,
+    relayed_clients: RelayedClients,
+    buffer: Vec<u8>,
+}
+
+impl PacketSender for RelayServerSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.send_with_delivery(payload, address, Delivery::Datagram)
+    }
+
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if !self.relayed_clients.lock().unwrap().contains_key(address) {
+            return inner.send_with_delivery(payload, address, delivery);
+        }
+        RelayPacket::ToClient {
+            client_addr: *address,
+            payload,
+        }
+        .write(&mut self.buffer);
+        inner.send_with_delivery(&self.buffer, &self.config.relay_addr, delivery)
+    }
+}
+
+struct RelayServerReceiver {
+    inner: BoxedReceiver,
+    /// The sender is shared so that the receiver can keep the session alive
+    sender: Arc<Mutex<BoxedSender>>,
+    config: RelayConfig,
+    relayed_clients: RelayedClients,
+    last_register: Option<Instant>,
+    buffer: [u8; MTU],
+}
+
+impl RelayServerReceiver {
+    fn register(&mut self) -> Result<()> {
+        if self
+            .last_register
+            .is_some_and(|last| last.elapsed() < REGISTER_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_register = Some(Instant::now());
+        // forget the clients that stopped sending packets, the relay has also dropped them by now
+        self.relayed_clients
+            .lock()
+            .unwrap()
+            .retain(|_, last_seen| last_seen.elapsed() < SESSION_TIMEOUT);
+        let mut buf = Vec::with_capacity(MAX_HEADER_SIZE);
+        RelayPacket::RegisterHost {
+            session_id: self.config.session_id,
+        }
+        .write(&mut buf);
+        self.sender
+            .lock()
+            .unwrap()
+            .send(&buf, &self.config.relay_addr)
+    }
+}
+
+impl PacketReceiver for RelayServerReceiver {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        self.register()?;
+        loop {
+            let Some((buf, addr)) = self.inner.recv()? else {
+                return Ok(None);
+            };
+            if addr != self.config.relay_addr {
+                // direct connection
+                let len = buf.len();
+                self.buffer[..len].copy_from_slice(buf);
+                return Ok(Some((&mut self.buffer[..len], addr)));
+            }
+            let Some(RelayPacket::FromClient {
+                client_addr,
+                payload,
+            }) = RelayPacket::read(buf)
+            else {
+                trace!("dropping invalid relay packet");
+                continue;
+            };
+            self.relayed_clients
+                .lock()
+                .unwrap()
+                .insert(client_addr, Instant::now());
+            let len = payload.len();
+            self.buffer[..len].copy_from_slice(payload);
+            return Ok(Some((&mut self.buffer[..len], client_addr)));
+        }
+    }
+}
+
+/// Standalone server that forwards the packets between clients and servers that have no public IP.
+///
+/// It must be reachable by both the clients and the servers, for example by running it on a machine with a public IP:
+/// ```rust,no_run
+/// use lightyear::prelude::RelayServer;
+///
+/// let relay = RelayServer::bind("0.0.0.0:5001".parse().unwrap()).unwrap();
+/// relay.run().unwrap();
+/// ```
+pub struct RelayServer {
+    socket: std::net::UdpSocket,
+    /// Map from the session id to the address of the server
+    sessions: HashMap<u64, (SocketAddr, Instant)>,
+    /// Map from the address of a client to its session id
+    clients: HashMap<SocketAddr, (u64, Instant)>,
+    recv_buffer: [u8; MTU + MAX_HEADER_SIZE],
+    send_buffer: Vec<u8>,
+}
+
+impl RelayServer {
+    /// Create a new relay server listening on `addr`
+    pub fn bind(addr: SocketAddr) -> anyhow::Result<Self> {
+        let socket = std::net::UdpSocket::bind(addr).context("could not bind relay socket")?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            sessions: HashMap::default(),
+            clients: HashMap::default(),
+            recv_buffer: [0; MTU + MAX_HEADER_SIZE],
+            send_buffer: Vec::with_capacity(MTU + MAX_HEADER_SIZE),
+        })
+    }
+
+    /// The local address of the relay server
+    pub fn local_addr(&self) -> anyhow::Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Forward all the packets that were received since the last update, without blocking
+    pub fn update(&mut self) -> anyhow::Result<()> {
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut self.recv_buffer) {
+                Ok(res) => res,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            };
+            let now = Instant::now();
+            match RelayPacket::read(&self.recv_buffer[..len]) {
+                Some(RelayPacket::RegisterHost { session_id }) => {
+                    // a session can only be taken over by another host once it expired
+                    if let Some((host_addr, last_seen)) = self.sessions.get(&session_id) {
+                        if *host_addr != addr && last_seen.elapsed() < SESSION_TIMEOUT {
+                            debug!(
+                                ?addr,
+                                ?session_id,
+                                "the session is already registered by another host"
+                            );
+                            continue;
+                        }
+                    }
+                    trace!(?addr, ?session_id, "host registered");
+                    self.sessions.insert(session_id, (addr, now));
+                }
+                Some(RelayPacket::ToHost {
+                    session_id,
+                    payload,
+                }) => {
+                    let Some((host_addr, _)) = self.sessions.get(&session_id) else {
+                        debug!(?addr, ?session_id, "no host registered for this session");
+                        continue;
+                    };
+                    self.clients.insert(addr, (session_id, now));
+                    RelayPacket::FromClient {
+                        client_addr: addr,
+                        payload,
+                    }
+                    .write(&mut self.send_buffer);
+                    // one unreachable peer must not stop the relay
+                    if let Err(e) = self.socket.send_to(&self.send_buffer, host_addr) {
+                        debug!(?host_addr, "could not forward packet to the host: {e:?}");
+                    }
+                }
+                Some(RelayPacket::ToClient {
+                    client_addr,
+                    payload,
+                }) => {
+                    // only the host of the client's session can send packets to the client
+                    let is_host = self
+                        .clients
+                        .get(&client_addr)
+                        .and_then(|(session_id, _)| self.sessions.get(session_id))
+                        .is_some_and(|(host_addr, _)| *host_addr == addr);
+                    if !is_host {
+                        debug!(
+                            ?addr,
+                            ?client_addr,
+                            "dropping packet from a peer that is not the host"
+                        );
+                        continue;
+                    }
+                    RelayPacket::FromHost { payload }.write(&mut self.send_buffer);
+                    if let Err(e) = self.socket.send_to(&self.send_buffer, client_addr) {
+                        debug!(
+                            ?client_addr,
+                            "could not forward packet to the client: {e:?}"
+                        );
+                    }
+                }
+                _ => debug!(?addr, "invalid relay packet"),
+            }
+        }
+        self.sessions
+            .retain(|_, (_, last_seen)| last_seen.elapsed() < SESSION_TIMEOUT);
+        self.clients
+            .retain(|_, (_, last_seen)| last_seen.elapsed() < SESSION_TIMEOUT);
+        Ok(())
+    }
+
+    /// Run the relay server forever
+    pub fn run(mut self) -> anyhow::Result<()> {
+        loop {
+            self.update()?;
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use crate::prelude::client::{ClientTransport, IoConfig as ClientIoConfig};
+    use crate::prelude::server::{IoConfig as ServerIoConfig, ServerTransport};
+    use crate::transport::LOCAL_SOCKET;
+
+    use super::*;
+
+    #[test]
+    fn test_packet_serialization() {
+        let client_addr = SocketAddr::from(([1, 2, 3, 4], 5000));
+        let client_addr_v6 = SocketAddr::from(([1, 2, 3, 4, 5, 6, 7, 8], 5000));
+        let mut buf = vec![];
+        for packet in [
+            RelayPacket::RegisterHost { session_id: 7 },
+            RelayPacket::ToHost {
+                session_id: 7,
+                payload: &[1, 2, 3],
+            },
+            RelayPacket::FromClient {
+                client_addr,
+                payload: &[1, 2, 3],
+            },
+            RelayPacket::ToClient {
+                client_addr: client_addr_v6,
+                payload: &[],
+            },
+            RelayPacket::FromHost { payload: &[1] },
+        ] {
+            packet.write(&mut buf);
+            assert_eq!(RelayPacket::read(&buf), Some(packet));
+        }
+        assert_eq!(RelayPacket::read(&[0, 1, 2]), None);
+    }
+
+    struct NoopIo;
+
+    impl PacketSender for NoopIo {
+        fn send(&mut self, _: &[u8], _: &SocketAddr) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl PacketReceiver for NoopIo {
+        fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+            Ok(None)
+        }
+    }
+
+    /// Records the delivery method of the packets that are sent
+    #[derive(Clone, Default)]
+    struct DeliveryRecorder(Arc<Mutex<Vec<Delivery>>>);
+
+    impl PacketSender for DeliveryRecorder {
+        fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+            self.send_with_delivery(payload, address, Delivery::Datagram)
+        }
+
+        fn send_with_delivery(
+            &mut self,
+            _: &[u8],
+            _: &SocketAddr,
+            delivery: Delivery,
+        ) -> Result<()> {
+            self.0.lock().unwrap().push(delivery);
+            Ok(())
+        }
+    }
+
+    /// The relay senders forward the delivery method to the underlying transport
+    #[test]
+    fn test_relay_senders_forward_delivery() {
+        let config = RelayConfig {
+            relay_addr: SocketAddr::from(([1, 2, 3, 4], 5000)),
+            session_id: 1,
+        };
+        let server_addr = SocketAddr::from(([10, 0, 0, 1], 5000));
+        let client_addr = SocketAddr::from(([5, 6, 7, 8], 5000));
+
+        let client_deliveries = DeliveryRecorder::default();
+        let mut client_sender = RelayClientSender {
+            inner: Box::new(client_deliveries.clone()),
+            config,
+            server_addr: Arc::new(Mutex::new(None)),
+            buffer: vec![],
+        };
+        client_sender
+            .send_with_delivery(&[1], &server_addr, Delivery::Stream)
+            .unwrap();
+        client_sender.send(&[2], &server_addr).unwrap();
+        assert_eq!(
+            *client_deliveries.0.lock().unwrap(),
+            vec![Delivery::Stream, Delivery::Datagram]
+        );
+
+        let server_deliveries = DeliveryRecorder::default();
+        let relayed_clients: RelayedClients = Arc::new(Mutex::new(HashMap::default()));
+        relayed_clients
+            .lock()
+            .unwrap()
+            .insert(client_addr, Instant::now());
+        let mut server_sender = RelayServerSender {
+            inner: Arc::new(Mutex::new(Box::new(server_deliveries.clone()))),
+            config,
+            relayed_clients,
+            buffer: vec![],
+        };
+        // relayed client
+        server_sender
+            .send_with_delivery(&[3], &client_addr, Delivery::Stream)
+            .unwrap();
+        // direct client
+        server_sender
+            .send_with_delivery(&[4], &server_addr, Delivery::Stream)
+            .unwrap();
+        assert_eq!(
+            *server_deliveries.0.lock().unwrap(),
+            vec![Delivery::Stream, Delivery::Stream]
+        );
+    }
+
+    /// The clients that stopped sending packets through the relay are removed
+    #[test]
+    fn test_relayed_clients_expire() {
+        let config = RelayConfig {
+            relay_addr: SocketAddr::from(([1, 2, 3, 4], 5000)),
+            session_id: 1,
+        };
+        let relayed_clients: RelayedClients = Arc::new(Mutex::new(HashMap::default()));
+        let mut receiver = RelayServerReceiver {
+            inner: Box::new(NoopIo),
+            sender: Arc::new(Mutex::new(Box::new(NoopIo))),
+            config,
+            relayed_clients: relayed_clients.clone(),
+            last_register: None,
+            buffer: [0; MTU],
+        };
+        let active = SocketAddr::from(([5, 6, 7, 8], 5000));
+        let inactive = SocketAddr::from(([5, 6, 7, 9], 5000));
+        relayed_clients
+            .lock()
+            .unwrap()
+            .insert(active, Instant::now());
+        relayed_clients.lock().unwrap().insert(
+            inactive,
+            Instant::now().checked_sub(SESSION_TIMEOUT).unwrap(),
+        );
+
+        assert!(receiver.recv().unwrap().is_none());
+        let relayed_clients = relayed_clients.lock().unwrap();
+        assert!(relayed_clients.contains_key(&active));
+        assert!(!relayed_clients.contains_key(&inactive));
+    }
+
+    fn recv_with_retries(receiver: &mut impl PacketReceiver) -> Option<(Vec<u8>, SocketAddr)> {
+        for _ in 0..100 {
+            if let Some((buf, addr)) = receiver.recv().unwrap() {
+                return Some((buf.to_vec(), addr));
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        None
+    }
+
+    /// A session that is registered cannot be taken over by another host
+    #[test]
+    fn test_relay_rejects_session_hijack() {
+        let mut relay = RelayServer::bind(LOCAL_SOCKET).unwrap();
+        let relay_addr = relay.local_addr().unwrap();
+        let host = std::net::UdpSocket::bind(LOCAL_SOCKET).unwrap();
+        let attacker = std::net::UdpSocket::bind(LOCAL_SOCKET).unwrap();
+        let client = std::net::UdpSocket::bind(LOCAL_SOCKET).unwrap();
+        let mut buf = vec![];
+
+        RelayPacket::RegisterHost { session_id: 1 }.write(&mut buf);
+        host.send_to(&buf, relay_addr).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        relay.update().unwrap();
+        attacker.send_to(&buf, relay_addr).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        relay.update().unwrap();
+
+        // the packets of the session are still forwarded to the original host
+        RelayPacket::ToHost {
+            session_id: 1,
+            payload: &[1, 2, 3],
+        }
+        .write(&mut buf);
+        client.send_to(&buf, relay_addr).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        relay.update().unwrap();
+
+        let mut recv_buffer = [0; MTU + MAX_HEADER_SIZE];
+        host.set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let (len, _) = host.recv_from(&mut recv_buffer).unwrap();
+        assert_eq!(
+            RelayPacket::read(&recv_buffer[..len]),
+            Some(RelayPacket::FromClient {
+                client_addr: client.local_addr().unwrap(),
+                payload: &[1, 2, 3],
+            })
+        );
+        attacker.set_nonblocking(true).unwrap();
+        assert!(attacker.recv_from(&mut recv_buffer).is_err());
+    }
+
+    #[test]
+    fn test_relay() {
+        let mut relay = RelayServer::bind(LOCAL_SOCKET).unwrap();
+        let relay_addr = relay.local_addr().unwrap();
+        // this address is not reachable, the client only knows it from the ConnectToken
+        let server_public_addr = SocketAddr::from(([10, 0, 0, 1], 5000));
+
+        let mut server_io =
+            ServerIoConfig::from_transport(ServerTransport::UdpSocket(LOCAL_SOCKET))
+                .with_relay(relay_addr, 1)
+                .start()
+                .unwrap();
+        let mut client_io =
+            ClientIoConfig::from_transport(ClientTransport::UdpSocket(LOCAL_SOCKET))
+                .with_relay(relay_addr, 1)
+                .connect()
+                .unwrap();
+        let mut other_client_io =
+            ClientIoConfig::from_transport(ClientTransport::UdpSocket(LOCAL_SOCKET))
+                .with_relay(relay_addr, 2)
+                .connect()
+                .unwrap();
+
+        // the server registers its session
+        assert!(server_io.recv().unwrap().is_none());
+        std::thread::sleep(Duration::from_millis(10));
+        relay.update().unwrap();
+
+        // the client packet is forwarded to the server
+        client_io.send(&[1, 2, 3], &server_public_addr).unwrap();
+        // packets for unknown sessions are dropped
+        other_client_io.send(&[4], &server_public_addr).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        relay.update().unwrap();
+        let (payload, client_addr) = recv_with_retries(&mut server_io).unwrap();
+        assert_eq!(payload, vec![1, 2, 3]);
+        assert_eq!(client_addr, client_io.local_addr());
+        assert!(server_io.recv().unwrap().is_none());
+
+        // the server response is forwarded to the client, as if it came from the server
+        server_io.send(&[5, 6], &client_addr).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        relay.update().unwrap();
+        let (payload, origin) = recv_with_retries(&mut client_io).unwrap();
+        assert_eq!(payload, vec![5, 6]);
+        assert_eq!(origin, server_public_addr);
+    }
+}
@@ -2,41 +2,109 @@
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 
+#[cfg(feature = "client")]
 use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
+#[cfg(feature = "client")]
 use crate::client::io::{ClientIoEventReceiver, ClientNetworkEventSender};
+use crate::packet::mtu::PacketSizing;
+#[cfg(feature = "server")]
 use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
+#[cfg(feature = "server")]
 use crate::server::io::{ServerIoEventReceiver, ServerNetworkEventSender};
+use crate::transport::config::{SocketConfig, SocketSharding};
 use crate::transport::io::IoState;
-use crate::transport::{BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, MTU};
+use crate::transport::{
+    BoxedReceiver, BoxedSender, Delivery, PacketReceiver, PacketSender, Transport, MTU,
+};
 use anyhow::Context;
 use async_channel::Receiver;
+use bevy::utils::HashMap;
 
 use super::error::Result;
 
 pub struct UdpSocketBuilder {
     pub(crate) local_addr: SocketAddr,
+    pub(crate) config: SocketConfig,
 }
 
 impl UdpSocketBuilder {
+    fn bind(local_addr: SocketAddr, config: &SocketConfig) -> Result<std::net::UdpSocket> {
+        let udp_socket = std::net::UdpSocket::bind(local_addr)?;
+        let local_addr = udp_socket.local_addr()?;
+        if config.dont_fragment {
+            set_dont_fragment(&udp_socket, &local_addr)?;
+        }
+        apply_socket_config(&udp_socket, &local_addr, config)?;
+        udp_socket.set_nonblocking(true)?;
+        Ok(udp_socket)
+    }
+
+    /// Build the socket of a client, or of a server that doesn't use sharding
     fn build(self) -> Result<UdpSocket> {
-        let udp_socket = std::net::UdpSocket::bind(self.local_addr)?;
+        let udp_socket = Self::bind(self.local_addr, &self.config)?;
         let local_addr = udp_socket.local_addr()?;
         let socket = Arc::new(Mutex::new(udp_socket));
-        socket.as_ref().lock().unwrap().set_nonblocking(true)?;
         let sender = UdpSocketBuffer {
             socket: socket.clone(),
+            dont_fragment: self.config.dont_fragment,
             buffer: [0; MTU],
         };
-        let receiver = sender.clone();
+        let (sender, receiver): (BoxedSender, BoxedReceiver) = match self.config.sharding {
+            Some(sharding) => {
+                let sender = ShardedClientBuffer {
+                    inner: sender,
+                    sharding,
+                    shard: match sharding {
+                        SocketSharding::PerClient { .. } => {
+                            local_addr.port() % sharding.num_sockets()
+                        }
+                        SocketSharding::PerDelivery => 0,
+                    },
+                    server_addr: Arc::new(Mutex::new(None)),
+                };
+                (Box::new(sender.clone()), Box::new(sender))
+            }
+            None => (Box::new(sender.clone()), Box::new(sender)),
+        };
         Ok(UdpSocket {
             local_addr,
             sender,
             receiver,
         })
     }
+
+    /// Build the sockets of a server that spreads its traffic across multiple sockets
+    fn build_sharded_server(self, sharding: SocketSharding) -> Result<UdpSocket> {
+        let first_socket = Self::bind(self.local_addr, &self.config)?;
+        let local_addr = first_socket.local_addr()?;
+        let mut sockets = vec![Arc::new(Mutex::new(first_socket))];
+        for i in 1..sharding.num_sockets() {
+            let port = local_addr.port().checked_add(i).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "the ports of the sharded sockets overflow",
+                )
+            })?;
+            let socket = Self::bind(SocketAddr::new(local_addr.ip(), port), &self.config)?;
+            sockets.push(Arc::new(Mutex::new(socket)));
+        }
+        let sender = ShardedServerBuffer {
+            sockets,
+            sharding,
+            client_sockets: Arc::new(Mutex::new(HashMap::default())),
+            next_socket: 0,
+            dont_fragment: self.config.dont_fragment,
+            buffer: [0; MTU],
+        };
+        Ok(UdpSocket {
+            local_addr,
+            sender: Box::new(sender.clone()),
+            receiver: Box::new(sender),
+        })
+    }
 }
 
-#[cfg(not(target_family = "wasm"))]
+#[cfg(all(feature = "client", not(target_family = "wasm")))]
 impl ClientTransportBuilder for UdpSocketBuilder {
     fn connect(
         self,
@@ -55,6 +123,7 @@ impl ClientTransportBuilder for UdpSocketBuilder {
     }
 }
 
+#[cfg(feature = "server")]
 impl ServerTransportBuilder for UdpSocketBuilder {
     fn start(
         self,
@@ -64,8 +133,12 @@ impl ServerTransportBuilder for UdpSocketBuilder {
         Option<ServerIoEventReceiver>,
         Option<ServerNetworkEventSender>,
     )> {
+        let socket = match self.config.sharding {
+            Some(sharding) => self.build_sharded_server(sharding)?,
+            None => self.build()?,
+        };
         Ok((
-            ServerTransportEnum::UdpSocket(self.build()?),
+            ServerTransportEnum::UdpSocket(socket),
             IoState::Connected,
             None,
             None,
@@ -73,11 +146,158 @@ impl ServerTransportBuilder for UdpSocketBuilder {
     }
 }
 
+/// Set the "Don't Fragment" bit on the packets sent by the socket, so that packets that are too big
+/// for the network path get dropped instead of being fragmented at the IP level.
+/// This is required for path MTU discovery.
+#[cfg(target_os = "linux")]
+fn set_dont_fragment(socket: &std::net::UdpSocket, local_addr: &SocketAddr) -> Result<()> {
+    let (level, name, value) = match local_addr {
+        SocketAddr::V4(_) => (
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            libc::IP_PMTUDISC_DO,
+        ),
+        SocketAddr::V6(_) => (
+            libc::IPPROTO_IPV6,
+            libc::IPV6_MTU_DISCOVER,
+            libc::IPV6_PMTUDISC_DO,
+        ),
+    };
+    set_socket_option(socket, level, name, value)
+}
+
+/// On other platforms the probes used for path MTU discovery might get fragmented at the IP level,
+/// in which case the discovery converges to the maximum packet size
+#[cfg(not(target_os = "linux"))]
+fn set_dont_fragment(_socket: &std::net::UdpSocket, _local_addr: &SocketAddr) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn apply_socket_config(
+    socket: &std::net::UdpSocket,
+    local_addr: &SocketAddr,
+    config: &SocketConfig,
+) -> Result<()> {
+    for (name, size, label) in [
+        (libc::SO_SNDBUF, config.send_buffer_size, "send"),
+        (libc::SO_RCVBUF, config.recv_buffer_size, "receive"),
+    ] {
+        let Some(size) = size else {
+            continue;
+        };
+        let value = libc::c_int::try_from(size).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the socket buffer size is too big",
+            )
+        })?;
+        set_socket_option(socket, libc::SOL_SOCKET, name, value)?;
+        // the kernel silently caps the buffer size (`net.core.wmem_max` / `net.core.rmem_max`).
+        // It also doubles the requested size to leave room for its bookkeeping, and reports the doubled size
+        let actual = get_socket_option(socket, libc::SOL_SOCKET, name)?;
+        if (actual as usize) < size.saturating_mul(2) {
+            tracing::warn!(
+                requested = ?size,
+                ?actual,
+                "the OS capped the socket {} buffer size; increase net.core.wmem_max/net.core.rmem_max",
+                label
+            );
+        }
+    }
+    if let Some(dscp) = config.dscp {
+        if dscp > 63 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the DSCP value must be between 0 and 63",
+            )
+            .into());
+        }
+        // the DSCP is stored in the 6 upper bits of the TOS / traffic class field
+        let value = libc::c_int::from(dscp) << 2;
+        match local_addr {
+            SocketAddr::V4(_) => set_socket_option(socket, libc::IPPROTO_IP, libc::IP_TOS, value)?,
+            SocketAddr::V6(_) => {
+                set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_TCLASS, value)?
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn apply_socket_config(
+    _socket: &std::net::UdpSocket,
+    _local_addr: &SocketAddr,
+    config: &SocketConfig,
+) -> Result<()> {
+    if config.send_buffer_size.is_some()
+        || config.recv_buffer_size.is_some()
+        || config.dscp.is_some()
+    {
+        tracing::warn!("socket options are only supported on linux; the SocketConfig is ignored");
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_socket_option(
+    socket: &std::net::UdpSocket,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the file descriptor is valid for the lifetime of the socket, and all the options
+    // that we set expect a c_int value
+    let res = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if res != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn get_socket_option(
+    socket: &std::net::UdpSocket,
+    level: libc::c_int,
+    name: libc::c_int,
+) -> Result<libc::c_int> {
+    use std::os::fd::AsRawFd;
+
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    // SAFETY: the file descriptor is valid for the lifetime of the socket, and the buffer
+    // is big enough to hold a c_int
+    let res = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if res != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(value)
+}
+
 /// UDP Socket
 pub struct UdpSocket {
     local_addr: SocketAddr,
-    sender: UdpSocketBuffer,
-    receiver: UdpSocketBuffer,
+    sender: BoxedSender,
+    receiver: BoxedReceiver,
 }
 
 impl Transport for UdpSocket {
@@ -86,7 +306,11 @@ impl Transport for UdpSocket {
     }
 
     fn split(self) -> (BoxedSender, BoxedReceiver) {
-        (Box::new(self.sender), Box::new(self.receiver))
+        (self.sender, self.receiver)
+    }
+
+    fn packet_sizing(&self) -> PacketSizing {
+        PacketSizing::Discover
     }
 }
 
@@ -95,69 +319,214 @@ pub struct UdpSocketBuffer {
     /// The underlying UDP Socket. This is wrapped in an Arc<Mutex<>> so that it
     /// can be shared between threads
     socket: Arc<Mutex<std::net::UdpSocket>>,
+    /// The socket sets the "Don't Fragment" bit, for path MTU discovery
+    dont_fragment: bool,
     buffer: [u8; MTU],
 }
 
 impl PacketSender for UdpSocketBuffer {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
-        self.socket
-            .as_ref()
-            .lock()
-            .unwrap()
-            .send_to(payload, address)?;
-        Ok(())
+        send_to(&self.socket, payload, address, self.dont_fragment)
     }
 }
 
 impl PacketReceiver for UdpSocketBuffer {
     /// Receives a packet from the socket, and stores the results in the provided buffer
     fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
-        match self
-            .socket
-            .as_ref()
-            .lock()
-            .unwrap()
-            .recv_from(&mut self.buffer)
-        {
-            Ok((recv_len, address)) => Ok(Some((&mut self.buffer[..recv_len], address))),
-            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                // Nothing to receive on the socket
-                Ok(None)
+        Ok(recv_from(&self.socket, &mut self.buffer)?
+            .map(|(recv_len, address)| (&mut self.buffer[..recv_len], address)))
+    }
+}
+
+/// Maximum number of clients whose socket is tracked by a sharded server.
+/// The tracking is reset when there are more clients, and each client is tracked again on its next packet
+const MAX_TRACKED_CLIENTS: usize = 4096;
+
+/// Sockets of a server that uses [`SocketSharding`]
+#[derive(Clone)]
+pub struct ShardedServerBuffer {
+    sockets: Vec<Arc<Mutex<std::net::UdpSocket>>>,
+    sharding: SocketSharding,
+    /// Index of the socket on which we last received a packet from each client, so that we answer
+    /// from the same port
+    client_sockets: Arc<Mutex<HashMap<SocketAddr, usize>>>,
+    /// Index of the socket that is read first on the next `recv`, so that all the sockets are read fairly
+    next_socket: usize,
+    /// The sockets set the "Don't Fragment" bit, for path MTU discovery
+    dont_fragment: bool,
+    buffer: [u8; MTU],
+}
+
+impl PacketSender for ShardedServerBuffer {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.send_with_delivery(payload, address, Delivery::Datagram)
+    }
+
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
+        let index = match self.sharding {
+            SocketSharding::PerClient { .. } => self
+                .client_sockets
+                .lock()
+                .unwrap()
+                .get(address)
+                .copied()
+                .unwrap_or_default(),
+            SocketSharding::PerDelivery => (delivery == Delivery::Stream) as usize,
+        };
+        send_to(&self.sockets[index], payload, address, self.dont_fragment)
+    }
+}
+
+impl PacketReceiver for ShardedServerBuffer {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let num_sockets = self.sockets.len();
+        for i in 0..num_sockets {
+            let index = (self.next_socket + i) % num_sockets;
+            let Some((recv_len, address)) = recv_from(&self.sockets[index], &mut self.buffer)?
+            else {
+                continue;
+            };
+            self.next_socket = (index + 1) % num_sockets;
+            if let SocketSharding::PerClient { .. } = self.sharding {
+                let mut client_sockets = self.client_sockets.lock().unwrap();
+                if client_sockets.len() >= MAX_TRACKED_CLIENTS
+                    && !client_sockets.contains_key(&address)
+                {
+                    client_sockets.clear();
+                }
+                client_sockets.insert(address, index);
             }
-            // Err(e) => Err(anyhow!("error receiving packet")),
-            Err(e) => Err(e.into()),
+            return Ok(Some((&mut self.buffer[..recv_len], address)));
         }
+        Ok(None)
     }
 }
 
-#[cfg(test)]
+/// Socket of a client that connects to a server that uses [`SocketSharding`]
+#[derive(Clone)]
+pub struct ShardedClientBuffer {
+    inner: UdpSocketBuffer,
+    sharding: SocketSharding,
+    /// Index of the server socket that this client sends its datagrams to
+    shard: u16,
+    /// Address of the first socket of the server. The packets received from the other sockets
+    /// of the server appear to come from this address
+    server_addr: Arc<Mutex<Option<SocketAddr>>>,
+}
+
+impl PacketSender for ShardedClientBuffer {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.send_with_delivery(payload, address, Delivery::Datagram)
+    }
+
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
+        *self.server_addr.lock().unwrap() = Some(*address);
+        let shard = match self.sharding {
+            SocketSharding::PerClient { .. } => self.shard,
+            SocketSharding::PerDelivery => (delivery == Delivery::Stream) as u16,
+        };
+        let mut shard_addr = *address;
+        shard_addr.set_port(address.port().wrapping_add(shard));
+        self.inner.send(payload, &shard_addr)
+    }
+}
+
+impl PacketReceiver for ShardedClientBuffer {
+    fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let server_addr = *self.server_addr.lock().unwrap();
+        let num_sockets = self.sharding.num_sockets();
+        Ok(self.inner.recv()?.map(|(data, address)| {
+            let address = match server_addr {
+                Some(server_addr)
+                    if address.ip() == server_addr.ip()
+                        && address.port().wrapping_sub(server_addr.port()) < num_sockets =>
+                {
+                    server_addr
+                }
+                _ => address,
+            };
+            (data, address)
+        }))
+    }
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn send_to(
+    socket: &Mutex<std::net::UdpSocket>,
+    payload: &[u8],
+    address: &SocketAddr,
+    dont_fragment: bool,
+) -> Result<()> {
+    match socket.lock().unwrap().send_to(payload, address) {
+        Ok(_) => Ok(()),
+        // the packet is bigger than the known path MTU: treat it as if it was dropped
+        // by the network (it can happen for path MTU discovery probes)
+        #[cfg(target_os = "linux")]
+        Err(e) if dont_fragment && e.raw_os_error() == Some(libc::EMSGSIZE) => {
+            tracing::trace!(len = ?payload.len(), "packet too big for the network path");
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn recv_from(
+    socket: &Mutex<std::net::UdpSocket>,
+    buffer: &mut [u8],
+) -> Result<Option<(usize, SocketAddr)>> {
+    match socket.lock().unwrap().recv_from(buffer) {
+        Ok(res) => Ok(Some(res)),
+        // Nothing to receive on the socket
+        Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
 mod tests {
     use std::net::SocketAddr;
     use std::str::FromStr;
 
     use crate::client::io::transport::ClientTransportBuilder;
     use crate::server::io::transport::ServerTransportBuilder;
+    use crate::transport::config::{SocketConfig, SocketSharding};
     use anyhow::Context;
     use bevy::utils::Duration;
 
     use crate::transport::middleware::conditioner::{LinkConditioner, LinkConditionerConfig};
     use crate::transport::middleware::PacketReceiverWrapper;
     use crate::transport::udp::UdpSocketBuilder;
-    use crate::transport::{PacketReceiver, PacketSender, Transport};
+    use crate::transport::{Delivery, PacketReceiver, PacketSender, Transport};
 
     #[test]
     fn test_udp_socket() -> Result<(), anyhow::Error> {
         // let the OS assign a port
         let local_addr = SocketAddr::from_str("127.0.0.1:0")?;
-        let (client_socket, _, _, _) = UdpSocketBuilder { local_addr }
-            .connect()
-            .context("could not connect to socket")?;
+        let (client_socket, _, _, _) = UdpSocketBuilder {
+            local_addr,
+            config: SocketConfig::default(),
+        }
+        .connect()
+        .context("could not connect to socket")?;
         let client_addr = client_socket.local_addr();
         let (mut client_sender, _) = client_socket.split();
 
-        let (server_socket, _, _, _) = UdpSocketBuilder { local_addr }
-            .start()
-            .context("could not connect to socket")?;
+        let (server_socket, _, _, _) = UdpSocketBuilder {
+            local_addr,
+            config: SocketConfig::default(),
+        }
+        .start()
+        .context("could not connect to socket")?;
         let server_addr = server_socket.local_addr();
         let (_, mut server_receiver) = server_socket.split();
 
@@ -182,15 +551,21 @@ mod tests {
         // let the OS assign a port
         let local_addr = SocketAddr::from_str("127.0.0.1:0")?;
 
-        let (client_socket, _, _, _) = UdpSocketBuilder { local_addr }
-            .connect()
-            .context("could not connect to socket")?;
+        let (client_socket, _, _, _) = UdpSocketBuilder {
+            local_addr,
+            config: SocketConfig::default(),
+        }
+        .connect()
+        .context("could not connect to socket")?;
         let client_addr = client_socket.local_addr();
         let (mut client_sender, _) = client_socket.split();
 
-        let (server_socket, _, _, _) = UdpSocketBuilder { local_addr }
-            .start()
-            .context("could not connect to socket")?;
+        let (server_socket, _, _, _) = UdpSocketBuilder {
+            local_addr,
+            config: SocketConfig::default(),
+        }
+        .start()
+        .context("could not connect to socket")?;
         let server_addr = server_socket.local_addr();
         let (_, server_receiver) = server_socket.split();
 
@@ -230,4 +605,115 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_udp_socket_sharding() -> Result<(), anyhow::Error> {
+        let local_addr = SocketAddr::from_str("127.0.0.1:0")?;
+        let config = SocketConfig::default().with_sharding(SocketSharding::PerDelivery);
+        let (client_socket, _, _, _) = UdpSocketBuilder {
+            local_addr,
+            config: config.clone(),
+        }
+        .connect()
+        .context("could not connect to socket")?;
+        let client_addr = client_socket.local_addr();
+        let (mut client_sender, mut client_receiver) = client_socket.split();
+
+        let (server_socket, _, _, _) = UdpSocketBuilder { local_addr, config }
+            .start()
+            .context("could not connect to socket")?;
+        let server_addr = server_socket.local_addr();
+        let (mut server_sender, mut server_receiver) = server_socket.split();
+
+        // the packets are sent to different ports of the server depending on their delivery
+        client_sender.send_with_delivery(b"datagram", &server_addr, Delivery::Datagram)?;
+        client_sender.send_with_delivery(b"stream", &server_addr, Delivery::Stream)?;
+        std::thread::sleep(Duration::from_millis(10));
+
+        let mut received = vec![];
+        while let Some((recv_msg, address)) = server_receiver.recv()? {
+            assert_eq!(address, client_addr);
+            received.push(recv_msg.to_vec());
+        }
+        received.sort();
+        assert_eq!(received, vec![b"datagram".to_vec(), b"stream".to_vec()]);
+
+        // the packets sent from the second port of the server appear to come from its main address
+        server_sender.send_with_delivery(b"stream", &client_addr, Delivery::Stream)?;
+        std::thread::sleep(Duration::from_millis(10));
+        let Some((recv_msg, address)) = client_receiver.recv()? else {
+            panic!("expected to receive a packet");
+        };
+        assert_eq!(address, server_addr);
+        assert_eq!(recv_msg, b"stream");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_udp_socket_config() -> Result<(), anyhow::Error> {
+        use super::get_socket_option;
+
+        let local_addr = SocketAddr::from_str("127.0.0.1:0")?;
+        let udp_socket = UdpSocketBuilder::bind(
+            local_addr,
+            &SocketConfig::default()
+                .with_send_buffer_size(32768)
+                .with_recv_buffer_size(32768)
+                .with_dscp(SocketConfig::DSCP_EXPEDITED_FORWARDING),
+        )?;
+        // linux doubles the requested buffer size to account for its bookkeeping overhead
+        assert_eq!(
+            get_socket_option(&udp_socket, libc::SOL_SOCKET, libc::SO_SNDBUF)?,
+            2 * 32768
+        );
+        assert_eq!(
+            get_socket_option(&udp_socket, libc::SOL_SOCKET, libc::SO_RCVBUF)?,
+            2 * 32768
+        );
+        assert_eq!(
+            get_socket_option(&udp_socket, libc::IPPROTO_IP, libc::IP_TOS)?,
+            46 << 2
+        );
+
+        // invalid DSCP value
+        assert!(UdpSocketBuilder {
+            local_addr,
+            config: SocketConfig::default().with_dscp(64),
+        }
+        .build()
+        .is_err());
+        Ok(())
+    }
+
+    /// The "Don't Fragment" bit is only set when path MTU discovery is enabled, and the packets that are
+    /// too big are only ignored in that case
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_udp_socket_dont_fragment() -> Result<(), anyhow::Error> {
+        use super::get_socket_option;
+
+        let local_addr = SocketAddr::from_str("127.0.0.1:0")?;
+        let too_big = vec![0; 70_000];
+        for dont_fragment in [false, true] {
+            let config = SocketConfig {
+                dont_fragment,
+                ..Default::default()
+            };
+            let udp_socket = UdpSocketBuilder::bind(local_addr, &config)?;
+            assert_eq!(
+                get_socket_option(&udp_socket, libc::IPPROTO_IP, libc::IP_MTU_DISCOVER)?
+                    == libc::IP_PMTUDISC_DO,
+                dont_fragment
+            );
+            let server_addr = udp_socket.local_addr()?;
+
+            let (socket, _, _, _) = UdpSocketBuilder { local_addr, config }
+                .connect()
+                .context("could not connect to socket")?;
+            let (mut sender, _) = socket.split();
+            assert_eq!(sender.send(&too_big, &server_addr).is_ok(), dont_fragment);
+        }
+        Ok(())
+    }
 }
@@ -21,22 +21,68 @@ use tokio::{
         Mutex,
     },
 };
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
 use tokio_tungstenite::{
-    connect_async, connect_async_with_config, tungstenite::Message, MaybeTlsStream,
+    client_async_tls_with_config, connect_async, connect_async_with_config, tungstenite::Message,
+    Connector, MaybeTlsStream, WebSocketStream,
 };
 use tracing::{debug, info, trace};
 use tracing_log::log::error;
 
 use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
 use crate::client::io::{ClientIoEvent, ClientIoEventReceiver, ClientNetworkEventSender};
+use crate::transport::config::LivenessConfig;
 use crate::transport::error::{Error, Result};
 use crate::transport::io::IoState;
+use crate::transport::proxy::{self, ProxyConfig};
+use crate::transport::websocket::liveness::{next_message, send_pings};
+use crate::transport::websocket::WebSocketClientTls;
 use crate::transport::{
     BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, LOCAL_SOCKET, MTU,
 };
 
 pub(crate) struct WebSocketClientSocketBuilder {
     pub(crate) server_addr: SocketAddr,
+    pub(crate) tls: Option<WebSocketClientTls>,
+    pub(crate) proxy: Option<ProxyConfig>,
+    pub(crate) liveness: Option<LivenessConfig>,
+}
+
+impl WebSocketClientSocketBuilder {
+    async fn connect_websocket(
+        server_addr: SocketAddr,
+        tls: Option<WebSocketClientTls>,
+        proxy: Option<ProxyConfig>,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let stream = proxy::connect(server_addr, proxy.as_ref()).await?;
+        stream.set_nodelay(true)?;
+        let (url, connector) = match tls {
+            None => (format!("ws://{}/", server_addr), Some(Connector::Plain)),
+            Some(tls) => {
+                // the domain name in the url is used to validate the server's certificate
+                let url = format!("wss://{}:{}/", tls.server_name, server_addr.port());
+                // if no root certificates are provided, the platform's root certificates are used
+                let connector = if tls.root_certificates.is_empty() {
+                    None
+                } else {
+                    let mut root_store = RootCertStore::empty();
+                    for certificate in tls.root_certificates {
+                        root_store
+                            .add(CertificateDer::from(certificate))
+                            .map_err(std::io::Error::other)?;
+                    }
+                    let config = ClientConfig::builder()
+                        .with_root_certificates(root_store)
+                        .with_no_client_auth();
+                    Some(Connector::Rustls(Arc::new(config)))
+                };
+                (url, connector)
+            }
+        };
+        let (ws_stream, _) = client_async_tls_with_config(url, stream, None, connector).await?;
+        Ok(ws_stream)
+    }
 }
 
 impl ClientTransportBuilder for WebSocketClientSocketBuilder {
@@ -54,6 +100,7 @@ impl ClientTransportBuilder for WebSocketClientSocketBuilder {
         // channels used to check the status of the io task
         let (status_tx, status_rx) = async_channel::bounded(1);
 
+        let ping_tx = serverbound_tx.clone();
         let sender = WebSocketClientSocketSender { serverbound_tx };
         let receiver = WebSocketClientSocketReceiver {
             buffer: [0; MTU],
@@ -63,33 +110,33 @@ impl ClientTransportBuilder for WebSocketClientSocketBuilder {
 
         IoTaskPool::get()
             .spawn(Compat::new(async move {
-                let ws_stream = match connect_async_with_config(
-                    format!("ws://{}/", self.server_addr),
-                    None,
-                    true,
-                )
-                .await
-                {
-                    Ok((ws_stream, _)) => ws_stream,
-                    Err(e) => {
-                        status_tx
-                            .send(ClientIoEvent::Disconnected(e.into()))
-                            .await
-                            .unwrap();
-                        return;
-                    }
-                };
+                let ws_stream =
+                    match Self::connect_websocket(self.server_addr, self.tls, self.proxy).await {
+                        Ok(ws_stream) => ws_stream,
+                        Err(e) => {
+                            status_tx
+                                .send(ClientIoEvent::Disconnected(e))
+                                .await
+                                .unwrap();
+                            return;
+                        }
+                    };
                 info!("WebSocket handshake has been successfully completed");
                 status_tx.send(ClientIoEvent::Connected).await.unwrap();
                 let (mut write, mut read) = ws_stream.split();
 
-                let send_handle = IoTaskPool::get().spawn(Compat::new(async move {
-                    while let Some(msg) = read.next().await {
+                let liveness = self.liveness;
+                let mut send_handle = IoTaskPool::get().spawn(Compat::new(async move {
+                    while let Some(msg) = next_message(&mut read, liveness.as_ref()).await {
                         let msg = msg
                             .map_err(|e| {
                                 error!("Error while receiving websocket msg: {}", e);
                             })
                             .unwrap();
+                        // pings are answered automatically by tungstenite
+                        if matches!(msg, Message::Ping(_) | Message::Pong(_)) {
+                            continue;
+                        }
 
                         clientbound_tx.send(msg).expect(
                             "Unable to propagate the read websocket message to the receiver",
@@ -108,8 +155,18 @@ impl ClientTransportBuilder for WebSocketClientSocketBuilder {
                             .unwrap();
                     }
                 }));
-                // wait for a signal that the io should be closed
-                let _ = close_rx.recv().await;
+                // send pings regularly so that the server can detect that the connection is still alive
+                let ping_handle = liveness.map(|liveness| {
+                    IoTaskPool::get().spawn(Compat::new(send_pings(
+                        ping_tx,
+                        liveness.keep_alive_interval,
+                    )))
+                });
+                // wait for a signal that the io should be closed, or for the stream to be closed
+                tokio::select! {
+                    _ = close_rx.recv() => {}
+                    _ = &mut send_handle => {}
+                }
                 let _ = status_tx
                     .send(ClientIoEvent::Disconnected(
                         std::io::Error::other("websocket closed").into(),
@@ -118,6 +175,9 @@ impl ClientTransportBuilder for WebSocketClientSocketBuilder {
                 info!("Close websocket connection");
                 send_handle.cancel().await;
                 recv_handle.cancel().await;
+                if let Some(ping_handle) = ping_handle {
+                    ping_handle.cancel().await;
+                }
             }))
             .detach();
         Ok((
@@ -139,20 +199,6 @@ pub struct WebSocketClientSocket {
     receiver: WebSocketClientSocketReceiver,
 }
 
-impl WebSocketClientSocket {
-    /*fn get_tls_connector(&self) -> TlsConnector {
-        let root_store = RootCertStore {
-            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
-        };
-
-        let config = ClientConfig::builder()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
-
-        TlsConnector::from(Arc::new(config))
-    }*/
-}
-
 impl Transport for WebSocketClientSocket {
     fn local_addr(&self) -> SocketAddr {
         // TODO: get the local_addr
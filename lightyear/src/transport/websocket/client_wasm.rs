@@ -21,12 +21,16 @@ use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
 use crate::client::io::{ClientIoEventReceiver, ClientNetworkEventSender};
 use crate::transport::error::{Error, Result};
 use crate::transport::io::IoState;
+use crate::transport::proxy::ProxyConfig;
+use crate::transport::websocket::WebSocketClientTls;
 use crate::transport::{
     BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, LOCAL_SOCKET, MTU,
 };
 
 pub(crate) struct WebSocketClientSocketBuilder {
     pub(crate) server_addr: SocketAddr,
+    pub(crate) tls: Option<WebSocketClientTls>,
+    pub(crate) proxy: Option<ProxyConfig>,
 }
 
 impl ClientTransportBuilder for WebSocketClientSocketBuilder {
@@ -51,8 +55,16 @@ impl ClientTransportBuilder for WebSocketClientSocketBuilder {
         };
 
         info!("Starting client websocket task");
+        if self.proxy.is_some() {
+            warn!("The proxy configuration is ignored on wasm: the browser uses its own proxy settings");
+        }
 
-        let ws = WebSocket::new(&format!("ws://{}/", self.server_addr))
+        // the browser validates the certificate using the domain name of the server
+        let url = match &self.tls {
+            Some(tls) => format!("wss://{}:{}/", tls.server_name, self.server_addr.port()),
+            None => format!("ws://{}/", self.server_addr),
+        };
+        let ws = WebSocket::new(&url)
             .map_err(|e| Error::Io(std::io::Error::other("could not create websocket")))?;
 
         ws.set_binary_type(BinaryType::Arraybuffer);
@@ -0,0 +1,41 @@
+//! Liveness of the native websocket connections, shared by the client and the server
+use bevy::utils::Duration;
+use futures_util::StreamExt;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::info;
+
+use crate::transport::config::LivenessConfig;
+
+/// Send a ping to the remote peer at every `interval`, until the connection is closed
+pub(crate) async fn send_pings(tx: UnboundedSender<Message>, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        if tx.send(Message::Ping(vec![])).is_err() {
+            return;
+        }
+    }
+}
+
+/// Wait for the next message of the websocket stream.
+///
+/// If a [`LivenessConfig`] is provided, returns `None` (as if the stream was closed) when no message
+/// was received during the liveness timeout.
+pub(crate) async fn next_message<S: futures_util::Stream + Unpin>(
+    stream: &mut S,
+    liveness: Option<&LivenessConfig>,
+) -> Option<S::Item> {
+    let Some(liveness) = liveness else {
+        return stream.next().await;
+    };
+    tokio::time::timeout(liveness.timeout, stream.next())
+        .await
+        .unwrap_or_else(|_| {
+            info!(
+                "No websocket message received for {:?}, closing the connection",
+                liveness.timeout
+            );
+            None
+        })
+}
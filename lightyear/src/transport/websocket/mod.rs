@@ -1,11 +1,45 @@
 //! Transport using the WebSocket protocol (based on TCP, HTTP)
+
+/// TLS configuration of a WebSocket client, to connect to a secure WebSocket (`wss://`) server
+#[derive(Clone, Debug)]
+pub struct WebSocketClientTls {
+    /// Domain name of the server. It is used to validate the server's certificate, so it must
+    /// match the domain of the certificate.
+    pub server_name: String,
+    /// DER-encoded root certificates to trust (for example a self-signed certificate used during development).
+    /// If empty, the platform's root certificates are used.
+    ///
+    /// This is ignored in the browser, which always uses its own root certificates.
+    pub root_certificates: Vec<Vec<u8>>,
+}
+
+impl WebSocketClientTls {
+    pub fn new(server_name: impl Into<String>) -> Self {
+        Self {
+            server_name: server_name.into(),
+            root_certificates: vec![],
+        }
+    }
+
+    pub fn with_root_certificate(mut self, certificate: Vec<u8>) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+}
+
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "websocket", target_family = "wasm"))] {
+            #[cfg(feature = "client")]
             pub mod client_wasm;
+            #[cfg(feature = "client")]
             pub use client_wasm as client;
     } else if #[cfg(all(feature = "websocket", not(target_family = "wasm")))]{
+            mod liveness;
+            #[cfg(feature = "server")]
             pub mod server;
+            #[cfg(feature = "client")]
             pub mod client_native;
+            #[cfg(feature = "client")]
             pub use client_native as client;
     }
 }
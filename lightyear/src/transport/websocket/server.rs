@@ -1,36 +1,94 @@
 use std::{
+    io::BufReader,
     net::SocketAddr,
+    path::Path,
     sync::{Arc, Mutex},
 };
 
+use anyhow::Context;
 use async_compat::Compat;
 use bevy::tasks::{futures_lite, IoTaskPool};
-use bevy::utils::HashMap;
+use bevy::utils::{Duration, HashMap};
 use futures_util::{
     future, pin_mut,
     stream::{SplitSink, TryStreamExt},
     SinkExt, StreamExt, TryFutureExt,
 };
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc;
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::mpsc::{error::TryRecvError, unbounded_channel, UnboundedReceiver, UnboundedSender},
 };
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
 use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
 use tracing::{debug, info, trace};
 use tracing_log::log::error;
-use wtransport::datagram::Datagram;
-use wtransport::Connection;
 
 use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
 use crate::server::io::{ServerIoEvent, ServerIoEventReceiver, ServerNetworkEventSender};
+use crate::transport::config::LivenessConfig;
 use crate::transport::error::{Error, Result};
 use crate::transport::io::IoState;
-use crate::transport::webtransport::server::WebTransportServerSocket;
+use crate::transport::websocket::liveness::{next_message, send_pings};
 use crate::transport::{BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, MTU};
 
+/// TLS configuration of the WebSocket server, to accept secure WebSocket (`wss://`) connections.
+///
+/// Browsers refuse to open insecure WebSocket connections from pages that are served over HTTPS.
+#[derive(Clone, Debug)]
+pub struct WebSocketServerTls(Arc<ServerConfig>);
+
+impl WebSocketServerTls {
+    /// Load the certificate chain and the private key from PEM files.
+    ///
+    /// This can be used with the certificates provisioned by an ACME client (for example the `fullchain.pem` and
+    /// `privkey.pem` files generated by certbot for Let's Encrypt).
+    pub fn from_pem_files(
+        certificate_chain: impl AsRef<Path>,
+        private_key: impl AsRef<Path>,
+    ) -> anyhow::Result<Self> {
+        let certificate_chain = std::fs::read(certificate_chain.as_ref()).with_context(|| {
+            format!(
+                "could not read certificate file {:?}",
+                certificate_chain.as_ref()
+            )
+        })?;
+        let private_key = std::fs::read(private_key.as_ref()).with_context(|| {
+            format!("could not read private key file {:?}", private_key.as_ref())
+        })?;
+        Self::from_pem(&certificate_chain, &private_key)
+    }
+
+    /// Use a PEM-encoded certificate chain and private key
+    pub fn from_pem(certificate_chain: &[u8], private_key: &[u8]) -> anyhow::Result<Self> {
+        let certificate_chain = rustls_pemfile::certs(&mut BufReader::new(certificate_chain))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context("could not parse the certificate chain")?;
+        let private_key = rustls_pemfile::private_key(&mut BufReader::new(private_key))
+            .context("could not parse the private key")?
+            .context("no private key found")?;
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certificate_chain, private_key)
+            .context("invalid certificate")?;
+        Ok(Self(Arc::new(config)))
+    }
+
+    /// Use a custom rustls configuration.
+    ///
+    /// This is useful to provide a certificate resolver that renews the certificates automatically,
+    /// for example with an ACME client.
+    pub fn from_config(config: Arc<ServerConfig>) -> Self {
+        Self(config)
+    }
+}
+
 pub(crate) struct WebSocketServerSocketBuilder {
     pub(crate) server_addr: SocketAddr,
+    pub(crate) tls: Option<WebSocketServerTls>,
+    pub(crate) liveness: Option<LivenessConfig>,
 }
 
 impl ServerTransportBuilder for WebSocketServerSocketBuilder {
@@ -49,6 +107,8 @@ impl ServerTransportBuilder for WebSocketServerSocketBuilder {
         // channels used to check the status of the io task
         let (status_tx, status_rx) = async_channel::unbounded();
         let addr_to_task = Arc::new(Mutex::new(HashMap::new()));
+        let tls_acceptor = self.tls.map(|tls| TlsAcceptor::from(tls.0));
+        let liveness = self.liveness;
 
         let sender = WebSocketServerSocketSender {
             server_addr: self.server_addr,
@@ -100,7 +160,7 @@ impl ServerTransportBuilder for WebSocketServerSocketBuilder {
                             let clientbound_tx_map = clientbound_tx_map.clone();
                             let serverbound_tx = serverbound_tx.clone();
                             let task = IoTaskPool::get().spawn(Compat::new(
-                                WebSocketServerSocket::handle_client(addr, stream, serverbound_tx, clientbound_tx_map, status_tx.clone())
+                                WebSocketServerSocket::handle_client(addr, stream, tls_acceptor.clone(), serverbound_tx, clientbound_tx_map, status_tx.clone(), liveness)
                             ));
                             addr_to_task.lock().unwrap().insert(addr, task);
                         }
@@ -127,35 +187,52 @@ pub struct WebSocketServerSocket {
     receiver: WebSocketServerSocketReceiver,
 }
 
-impl WebSocketServerSocket {
-    /*fn get_tls_acceptor(&self) -> Option<TlsAcceptor> {
-        if let Some(config) = &self.tls_config {
-            let server_config = ServerConfig::builder()
-                .with_no_client_auth()
-                .with_single_cert(
-                    certs(&mut BufReader::new(&*config.certs))
-                        .map(|e| e.unwrap())
-                        .collect(),
-                    rsa_private_keys(&mut BufReader::new(&*config.keys))
-                        .map(|e| e.unwrap().into())
-                        .next()
-                        .unwrap(),
-                )
-                .unwrap();
-            Some(TlsAcceptor::from(Arc::new(server_config)))
-        } else {
-            None
-        }
-    }*/
-}
-
 impl WebSocketServerSocket {
     async fn handle_client(
         addr: SocketAddr,
         stream: TcpStream,
+        tls_acceptor: Option<TlsAcceptor>,
+        serverbound_tx: UnboundedSender<(SocketAddr, Message)>,
+        clientbound_tx_map: Arc<Mutex<HashMap<SocketAddr, UnboundedSender<Message>>>>,
+        status_tx: async_channel::Sender<ServerIoEvent>,
+        liveness: Option<LivenessConfig>,
+    ) {
+        let Some(tls_acceptor) = tls_acceptor else {
+            return Self::handle_websocket(
+                addr,
+                stream,
+                serverbound_tx,
+                clientbound_tx_map,
+                status_tx,
+                liveness,
+            )
+            .await;
+        };
+        let Ok(tls_stream) = tls_acceptor
+            .accept(stream)
+            .await
+            .inspect_err(|e| error!("An error occured during the TLS handshake: {e:?}"))
+        else {
+            return;
+        };
+        Self::handle_websocket(
+            addr,
+            tls_stream,
+            serverbound_tx,
+            clientbound_tx_map,
+            status_tx,
+            liveness,
+        )
+        .await
+    }
+
+    async fn handle_websocket<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+        addr: SocketAddr,
+        stream: S,
         serverbound_tx: UnboundedSender<(SocketAddr, Message)>,
         clientbound_tx_map: Arc<Mutex<HashMap<SocketAddr, UnboundedSender<Message>>>>,
         status_tx: async_channel::Sender<ServerIoEvent>,
+        liveness: Option<LivenessConfig>,
     ) {
         let Ok(ws_stream) = tokio_tungstenite::accept_async(stream)
             .await
@@ -167,6 +244,13 @@ impl WebSocketServerSocket {
 
         let (clientbound_tx, mut clientbound_rx) = unbounded_channel::<Message>();
         let (mut write, mut read) = ws_stream.split();
+        // send pings regularly so that the client can detect that the connection is still alive
+        let _ping_handle = liveness.map(|liveness| {
+            IoTaskPool::get().spawn(Compat::new(send_pings(
+                clientbound_tx.clone(),
+                liveness.keep_alive_interval,
+            )))
+        });
         clientbound_tx_map
             .lock()
             .unwrap()
@@ -186,9 +270,11 @@ impl WebSocketServerSocket {
                 error!("Error closing websocket: {:?}", e);
             });
         });
-        let serverbound_handle = IoTaskPool::get().spawn(async move {
-            while let Some(msg) = read.next().await {
+        let serverbound_handle = IoTaskPool::get().spawn(Compat::new(async move {
+            while let Some(msg) = next_message(&mut read, liveness.as_ref()).await {
                 match msg {
+                    // pings are answered automatically by tungstenite
+                    Ok(Message::Ping(_) | Message::Pong(_)) => {}
                     Ok(msg) => {
                         serverbound_tx
                             .send((addr, msg))
@@ -199,7 +285,7 @@ impl WebSocketServerSocket {
                     }
                 }
             }
-        });
+        }));
 
         let _closed = futures_lite::future::race(clientbound_handle, serverbound_handle).await;
 
@@ -6,23 +6,30 @@ use std::sync::Arc;
 
 use async_compat::Compat;
 use bevy::tasks::{futures_lite, IoTaskPool, TaskPool};
+use bytes::Bytes;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::error::TryRecvError;
 use tracing::{debug, error, info, trace, warn};
 use wtransport;
-use wtransport::datagram::Datagram;
 use wtransport::error::{ConnectingError, ConnectionError};
 use wtransport::ClientConfig;
 
 use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
 use crate::client::io::{ClientIoEvent, ClientIoEventReceiver, ClientNetworkEventSender};
+use crate::packet::mtu::PacketSizing;
+use crate::transport::config::LivenessConfig;
 use crate::transport::error::{Error, Result};
 use crate::transport::io::IoState;
-use crate::transport::{BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, MTU};
+use crate::transport::webtransport::stream::{read_packets, StreamWriter};
+use crate::transport::webtransport::WEBTRANSPORT_PACKET_SIZE;
+use crate::transport::{
+    BoxedReceiver, BoxedSender, Delivery, PacketReceiver, PacketSender, Transport, MTU,
+};
 
 pub(crate) struct WebTransportClientSocketBuilder {
     pub(crate) client_addr: SocketAddr,
     pub(crate) server_addr: SocketAddr,
+    pub(crate) liveness: Option<LivenessConfig>,
 }
 
 impl ClientTransportBuilder for WebTransportClientSocketBuilder {
@@ -34,18 +41,34 @@ impl ClientTransportBuilder for WebTransportClientSocketBuilder {
         Option<ClientIoEventReceiver>,
         Option<ClientNetworkEventSender>,
     )> {
-        let (to_server_sender, mut to_server_receiver) = mpsc::unbounded_channel();
+        let (to_server_sender, mut to_server_receiver) =
+            mpsc::unbounded_channel::<(Box<[u8]>, Delivery)>();
         let (from_server_sender, from_server_receiver) = mpsc::unbounded_channel();
         // channels used to cancel the task
         let (close_tx, close_rx) = async_channel::bounded(1);
         // channels used to check the status of the io task
         let (event_tx, event_rx) = async_channel::bounded(1);
 
+        let builder = ClientConfig::builder()
+            .with_bind_address(self.client_addr)
+            .with_no_cert_validation();
+        // use the QUIC keep-alives and idle timeout to detect dead connections
+        let config = match self.liveness {
+            Some(liveness) => builder
+                .keep_alive_interval(Some(liveness.keep_alive_interval))
+                // quinn only rejects idle timeouts larger than 2^62 milliseconds
+                .max_idle_timeout(Some(liveness.timeout))
+                .map_err(|_| {
+                    Error::InvalidConfig(format!(
+                        "the liveness timeout {:?} is too large",
+                        liveness.timeout
+                    ))
+                })?
+                .build(),
+            None => builder.build(),
+        };
+
         IoTaskPool::get().spawn(Compat::new(async move {
-            let config = ClientConfig::builder()
-                .with_bind_address(self.client_addr)
-                .with_no_cert_validation()
-                .build();
             let server_url = format!("https://{}", self.server_addr);
             info!(
                 "Connecting to server via webtransport at server url: {}",
@@ -90,12 +113,13 @@ impl ClientTransportBuilder for WebTransportClientSocketBuilder {
                     // - if you want to use tokio::Select, you have to first pin the Future, and then select on &mut Future. Only the reference gets
                     //   cancelled
                     let connection_recv = connection.clone();
+                    let from_server_stream_sender = from_server_sender.clone();
                     let recv_handle = IoTaskPool::get().spawn(Compat::new(async move {
                         loop {
                             match connection_recv.receive_datagram().await {
                                 Ok(data) => {
                                     trace!("receive datagram from server: {:?}", &data);
-                                    from_server_sender.send(data).unwrap();
+                                    from_server_sender.send(data.payload()).unwrap();
                                 }
                                 Err(e) => {
                                     // all the ConnectionErrors are related to the connection being close, so we can close the task
@@ -105,17 +129,57 @@ impl ClientTransportBuilder for WebTransportClientSocketBuilder {
                             }
                         }
                     }));
+                    // the server only opens one stream, on which it sends all the stream packets
+                    let connection_stream_recv = connection.clone();
+                    let stream_recv_handle = IoTaskPool::get().spawn(Compat::new(async move {
+                        loop {
+                            let stream = match connection_stream_recv.accept_uni().await {
+                                Ok(stream) => stream,
+                                Err(e) => {
+                                    error!("accept_uni connection error: {:?}", e);
+                                    return;
+                                }
+                            };
+                            let result = read_packets(stream, |data| {
+                                trace!("receive stream packet from server: {:?}", &data);
+                                let _ = from_server_stream_sender.send(data);
+                            }).await;
+                            if let Err(e) = result {
+                                error!("stream read error: {:?}", e);
+                            }
+                        }
+                    }));
+                    // the stream writes are awaited in their own task, so that a stream blocked by
+                    // flow control does not delay the datagrams
+                    let (to_server_stream_sender, mut to_server_stream_receiver) = mpsc::unbounded_channel::<Box<[u8]>>();
                     let connection_send = connection.clone();
                     let send_handle = IoTaskPool::get().spawn(Compat::new(async move {
                         loop {
-                            if let Some(msg) = to_server_receiver.recv().await {
-                                trace!("send datagram to server: {:?}", &msg);
-                                connection_send.send_datagram(msg).unwrap_or_else(|e| {
-                                    error!("send_datagram error: {:?}", e);
-                                });
+                            if let Some((msg, delivery)) = to_server_receiver.recv().await {
+                                match delivery {
+                                    Delivery::Datagram => {
+                                        trace!("send datagram to server: {:?}", &msg);
+                                        connection_send.send_datagram(msg).unwrap_or_else(|e| {
+                                            error!("send_datagram error: {:?}", e);
+                                        });
+                                    }
+                                    Delivery::Stream => {
+                                        let _ = to_server_stream_sender.send(msg);
+                                    }
+                                }
                             }
                         }
                     }));
+                    let connection_stream_send = connection.clone();
+                    let stream_send_handle = IoTaskPool::get().spawn(Compat::new(async move {
+                        let mut stream_writer = StreamWriter::new(connection_stream_send);
+                        while let Some(msg) = to_server_stream_receiver.recv().await {
+                            trace!("send stream packet to server: {:?}", &msg);
+                            stream_writer.write(&msg).await.unwrap_or_else(|e| {
+                                error!("stream write error: {:?}", e);
+                            });
+                        }
+                    }));
                     // Wait for a close signal from the close channel, or for the quic connection to be closed
                     tokio::select! {
                         reason = connection.closed() => {
@@ -133,7 +197,9 @@ impl ClientTransportBuilder for WebTransportClientSocketBuilder {
                     // drop(recv_handle);
                     // drop(send_handle);
                     recv_handle.cancel().await;
+                    stream_recv_handle.cancel().await;
                     send_handle.cancel().await;
+                    stream_send_handle.cancel().await;
                     debug!("WebTransport tasks shut down.");
                 }
             }
@@ -174,24 +240,37 @@ impl Transport for WebTransportClientSocket {
     fn split(self) -> (BoxedSender, BoxedReceiver) {
         (Box::new(self.sender), Box::new(self.receiver))
     }
+
+    fn packet_sizing(&self) -> PacketSizing {
+        PacketSizing::Fixed(WEBTRANSPORT_PACKET_SIZE)
+    }
 }
 
 struct WebTransportClientPacketSender {
-    to_server_sender: mpsc::UnboundedSender<Box<[u8]>>,
+    to_server_sender: mpsc::UnboundedSender<(Box<[u8]>, Delivery)>,
 }
 
 impl PacketSender for WebTransportClientPacketSender {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.send_with_delivery(payload, address, Delivery::Datagram)
+    }
+
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
         let data = payload.to_vec().into_boxed_slice();
         self.to_server_sender
-            .send(data)
+            .send((data, delivery))
             .map_err(|e| std::io::Error::other(format!("send_datagram error: {:?}", e)).into())
     }
 }
 
 struct WebTransportClientPacketReceiver {
     server_addr: SocketAddr,
-    from_server_receiver: mpsc::UnboundedReceiver<Datagram>,
+    from_server_receiver: mpsc::UnboundedReceiver<Bytes>,
     buffer: [u8; MTU],
 }
 
@@ -199,8 +278,7 @@ impl PacketReceiver for WebTransportClientPacketReceiver {
     fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
         match self.from_server_receiver.try_recv() {
             Ok(data) => {
-                // convert from datagram to payload via xwt
-                self.buffer[..data.len()].copy_from_slice(data.payload().as_ref());
+                self.buffer[..data.len()].copy_from_slice(data.as_ref());
                 Ok(Some((&mut self.buffer[..data.len()], self.server_addr)))
             }
             Err(e) => {
@@ -13,10 +13,10 @@ use xwt_core::prelude::*;
 
 use crate::client::io::transport::{ClientTransportBuilder, ClientTransportEnum};
 use crate::client::io::{ClientIoEvent, ClientIoEventReceiver, ClientNetworkEventSender};
-use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
-use crate::server::io::{ServerIoEventReceiver, ServerNetworkEventSender};
+use crate::packet::mtu::PacketSizing;
 use crate::transport::error::{Error, Result};
 use crate::transport::io::IoState;
+use crate::transport::webtransport::WEBTRANSPORT_PACKET_SIZE;
 use crate::transport::{BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, MTU};
 
 pub struct WebTransportClientSocketBuilder {
@@ -62,6 +62,7 @@ impl ClientTransportBuilder for WebTransportClientSocketBuilder {
         let (send, recv) = tokio::sync::oneshot::channel();
         let (send2, recv2) = tokio::sync::oneshot::channel();
         let (send3, recv3) = tokio::sync::oneshot::channel();
+        let (send4, recv4) = tokio::sync::oneshot::channel();
         let status_tx_clone = status_tx.clone();
         wasm_bindgen_futures::spawn_local(async move {
             info!("Starting webtransport io thread");
@@ -104,6 +105,7 @@ impl ClientTransportBuilder for WebTransportClientSocketBuilder {
             send.send(connection.clone()).unwrap();
             send2.send(connection.clone()).unwrap();
             send3.send(connection.clone()).unwrap();
+            send4.send(connection.clone()).unwrap();
         });
 
         // NOTE (IMPORTANT!):
@@ -134,6 +136,24 @@ impl ClientTransportBuilder for WebTransportClientSocketBuilder {
                 }
             }
         });
+        // the server only opens one stream, on which it sends all the stream packets
+        let from_server_stream_sender = from_server_sender.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let Ok(connection) = recv4.await else {
+                return;
+            };
+            tokio::select! {
+                _ = wasm_bindgen_futures::JsFuture::from(connection.transport.closed()) => {},
+                result = read_stream_packets(&connection, |data| {
+                    trace!("receive stream packet from server: {:?}", &data);
+                    let _ = from_server_stream_sender.send(data);
+                }) => {
+                    if let Err(e) = result {
+                        error!("stream read error: {:?}", e);
+                    }
+                }
+            }
+        });
         wasm_bindgen_futures::spawn_local(async move {
             let Ok(connection) = recv2.await else {
                 return;
@@ -188,6 +208,41 @@ impl ClientTransportBuilder for WebTransportClientSocketBuilder {
     }
 }
 
+/// Accept the stream opened by the server and read its packets until it is closed.
+///
+/// Every packet is prefixed with its length, encoded as a big-endian `u16`.
+async fn read_stream_packets(
+    connection: &xwt_web_sys::Session,
+    mut on_packet: impl FnMut(Vec<u8>),
+) -> anyhow::Result<()> {
+    let mut stream = connection
+        .accept_uni()
+        .await
+        .map_err(|e| anyhow::anyhow!("accept_uni error: {:?}", e))?;
+    let mut len = [0; 2];
+    loop {
+        read_exact(&mut stream, &mut len).await?;
+        let mut payload = vec![0; u16::from_be_bytes(len) as usize];
+        read_exact(&mut stream, &mut payload).await?;
+        on_packet(payload);
+    }
+}
+
+async fn read_exact(stream: &mut xwt_web_sys::RecvStream, buf: &mut [u8]) -> anyhow::Result<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match stream
+            .read(&mut buf[filled..])
+            .await
+            .map_err(|e| anyhow::anyhow!("stream read error: {:?}", e))?
+        {
+            Some(n) => filled += n,
+            None => anyhow::bail!("stream closed"),
+        }
+    }
+    Ok(())
+}
+
 /// WebTransport client socket
 pub struct WebTransportClientSocket {
     local_addr: SocketAddr,
@@ -203,8 +258,14 @@ impl Transport for WebTransportClientSocket {
     fn split(self) -> (BoxedSender, BoxedReceiver) {
         (Box::new(self.sender), Box::new(self.receiver))
     }
+
+    fn packet_sizing(&self) -> PacketSizing {
+        PacketSizing::Fixed(WEBTRANSPORT_PACKET_SIZE)
+    }
 }
 
+/// Streams are not supported yet in the browser, so packets that should be sent on a stream
+/// ([`Delivery::Stream`](crate::transport::Delivery::Stream)) are sent as datagrams instead.
 struct WebTransportClientPacketSender {
     to_server_sender: mpsc::UnboundedSender<Box<[u8]>>,
 }
@@ -1,11 +1,24 @@
 //! Transport using the WebTransport protocol (based on QUIC)
+
+/// Conservative packet size for WebTransport datagrams.
+///
+/// A QUIC datagram must fit in a single QUIC packet, and QUIC only guarantees packets of 1200 bytes,
+/// from which we need to remove the QUIC and WebTransport overhead.
+/// We cannot send probes with the Don't Fragment bit set through WebTransport, so we don't try to discover the path MTU.
+pub(crate) const WEBTRANSPORT_PACKET_SIZE: usize = 1100;
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "webtransport", target_family = "wasm"))] {
+            #[cfg(feature = "client")]
             pub mod client_wasm;
+            #[cfg(feature = "client")]
             pub use client_wasm as client;
     } else if #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]{
+            #[cfg(feature = "server")]
             pub mod server;
+            #[cfg(feature = "client")]
             pub mod client_native;
+            mod stream;
+            #[cfg(feature = "client")]
             pub use client_native as client;
     }
 }
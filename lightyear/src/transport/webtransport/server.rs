@@ -6,27 +6,60 @@ use anyhow::Context;
 use async_compat::Compat;
 use bevy::tasks::{futures_lite, IoTaskPool};
 use bevy::utils::HashMap;
+use bytes::Bytes;
 use tokio::sync::mpsc::error::TryRecvError;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, error, info, trace};
 use wtransport;
-use wtransport::datagram::Datagram;
 use wtransport::endpoint::endpoint_side::Server;
 use wtransport::endpoint::IncomingSession;
 use wtransport::tls::Certificate;
 use wtransport::{Connection, Endpoint};
 use wtransport::{Identity, ServerConfig};
 
+use crate::packet::mtu::PacketSizing;
 use crate::server::io::transport::{ServerTransportBuilder, ServerTransportEnum};
 use crate::server::io::{ServerIoEvent, ServerIoEventReceiver, ServerNetworkEventSender};
+use crate::transport::config::LivenessConfig;
 use crate::transport::error::{Error, Result};
 use crate::transport::io::IoState;
-use crate::transport::{BoxedReceiver, BoxedSender, PacketReceiver, PacketSender, Transport, MTU};
+use crate::transport::webtransport::stream::{read_packets, StreamWriter};
+use crate::transport::webtransport::WEBTRANSPORT_PACKET_SIZE;
+use crate::transport::{
+    BoxedReceiver, BoxedSender, Delivery, PacketReceiver, PacketSender, Transport, MTU,
+};
 
 pub(crate) struct WebTransportServerSocketBuilder {
     pub(crate) server_addr: SocketAddr,
     pub(crate) certificate: Identity,
+    pub(crate) liveness: Option<LivenessConfig>,
+}
+
+/// Build the config of the webtransport endpoint. If a [`LivenessConfig`] is provided,
+/// the QUIC keep-alives and idle timeout are used to detect dead connections
+fn server_config(
+    server_addr: SocketAddr,
+    certificate: &Identity,
+    liveness: Option<LivenessConfig>,
+) -> Result<ServerConfig> {
+    let builder = ServerConfig::builder()
+        .with_bind_address(server_addr)
+        .with_identity(certificate);
+    let Some(liveness) = liveness else {
+        return Ok(builder.build());
+    };
+    Ok(builder
+        .keep_alive_interval(Some(liveness.keep_alive_interval))
+        // quinn only rejects idle timeouts larger than 2^62 milliseconds
+        .max_idle_timeout(Some(liveness.timeout))
+        .map_err(|_| {
+            Error::InvalidConfig(format!(
+                "the liveness timeout {:?} is too large",
+                liveness.timeout
+            ))
+        })?
+        .build())
 }
 
 impl ServerTransportBuilder for WebTransportServerSocketBuilder {
@@ -58,10 +91,9 @@ impl ServerTransportBuilder for WebTransportServerSocketBuilder {
             from_client_receiver,
         };
 
-        let config = ServerConfig::builder()
-            .with_bind_address(self.server_addr)
-            .with_identity(&self.certificate)
-            .build();
+        let config = server_config(self.server_addr, &self.certificate, self.liveness)?;
+        let server_addr = self.server_addr;
+        let liveness = self.liveness;
         // need to run this with Compat because it requires the tokio reactor
         IoTaskPool::get()
             .spawn(Compat::new(async move {
@@ -91,6 +123,26 @@ impl ServerTransportBuilder for WebTransportServerSocketBuilder {
                                     debug!("Stopping webtransport io task associated with address: {:?} because we received a disconnection signal from netcode", addr);
                                     addr_to_task.lock().unwrap().remove(&addr);
                                 }
+                                ServerIoEvent::RotateCertificate(certificate) => {
+                                    let digest = certificate.certificate_chain().as_slice()[0].hash();
+                                    let config = match server_config(server_addr, &certificate, liveness) {
+                                        Ok(config) => config,
+                                        Err(e) => {
+                                            error!("could not rotate the webtransport server certificate: {:?}", e);
+                                            continue;
+                                        }
+                                    };
+                                    // do not rebind the socket, so that the existing connections are kept
+                                    match endpoint.reload_config(config, false) {
+                                        Ok(()) => {
+                                            info!(?digest, "Rotated the webtransport server certificate");
+                                            let _ = status_tx.send(ServerIoEvent::CertificateRotated(digest)).await;
+                                        }
+                                        Err(e) => {
+                                            error!("could not rotate the webtransport server certificate: {:?}", e);
+                                        }
+                                    }
+                                }
                                 _ => {}
                             }
                         }
@@ -152,8 +204,8 @@ pub struct WebTransportServerSocket {
 impl WebTransportServerSocket {
     pub async fn handle_client(
         connection: Arc<Connection>,
-        from_client_sender: UnboundedSender<(Datagram, SocketAddr)>,
-        to_client_channels: Arc<Mutex<HashMap<SocketAddr, UnboundedSender<Box<[u8]>>>>>,
+        from_client_sender: UnboundedSender<(Bytes, SocketAddr)>,
+        to_client_channels: Arc<Mutex<HashMap<SocketAddr, UnboundedSender<(Box<[u8]>, Delivery)>>>>,
         status_tx: async_channel::Sender<ServerIoEvent>,
     ) {
         let client_addr = connection.remote_address();
@@ -163,7 +215,8 @@ impl WebTransportServerSocket {
         );
 
         // add a new pair of channels for this client
-        let (to_client_sender, mut to_client_receiver) = mpsc::unbounded_channel::<Box<[u8]>>();
+        let (to_client_sender, mut to_client_receiver) =
+            mpsc::unbounded_channel::<(Box<[u8]>, Delivery)>();
         to_client_channels
             .lock()
             .unwrap()
@@ -171,6 +224,7 @@ impl WebTransportServerSocket {
 
         // connection established, waiting for data from client
         let connection_recv = connection.clone();
+        let from_client_stream_sender = from_client_sender.clone();
         let from_client_handle = IoTaskPool::get().spawn(async move {
             loop {
                 // receive messages from client
@@ -181,7 +235,9 @@ impl WebTransportServerSocket {
                             data.as_ref(),
                             data.len()
                         );
-                        from_client_sender.send((data, client_addr)).unwrap();
+                        from_client_sender
+                            .send((data.payload(), client_addr))
+                            .unwrap();
                     }
                     Err(e) => {
                         error!("receive_datagram connection error: {:?}", e);
@@ -191,19 +247,61 @@ impl WebTransportServerSocket {
                 }
             }
         });
+        // the client only opens one stream, on which it sends all the stream packets
+        let connection_stream_recv = connection.clone();
+        let from_client_stream_handle = IoTaskPool::get().spawn(async move {
+            loop {
+                let stream = match connection_stream_recv.accept_uni().await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        error!("accept_uni connection error: {:?}", e);
+                        break;
+                    }
+                };
+                let result = read_packets(stream, |data| {
+                    trace!("received stream packet from client!: {:?}", &data);
+                    let _ = from_client_stream_sender.send((data, client_addr));
+                })
+                .await;
+                if let Err(e) = result {
+                    error!("stream read error: {:?}", e);
+                }
+            }
+        });
+        // the stream writes are awaited in their own task, so that a stream blocked by
+        // flow control does not delay the datagrams
+        let (to_client_stream_sender, mut to_client_stream_receiver) =
+            mpsc::unbounded_channel::<Box<[u8]>>();
         let connection_send = connection.clone();
         let to_client_handle = IoTaskPool::get().spawn(async move {
             loop {
-                if let Some(msg) = to_client_receiver.recv().await {
-                    trace!("sending datagram to client!: {:?}", &msg);
-                    connection_send
-                        .send_datagram(msg.as_ref())
-                        .unwrap_or_else(|e| {
-                            error!("send_datagram error: {:?}", e);
-                        });
+                if let Some((msg, delivery)) = to_client_receiver.recv().await {
+                    match delivery {
+                        Delivery::Datagram => {
+                            trace!("sending datagram to client!: {:?}", &msg);
+                            connection_send
+                                .send_datagram(msg.as_ref())
+                                .unwrap_or_else(|e| {
+                                    error!("send_datagram error: {:?}", e);
+                                });
+                        }
+                        Delivery::Stream => {
+                            let _ = to_client_stream_sender.send(msg);
+                        }
+                    }
                 }
             }
         });
+        let connection_stream_send = connection.clone();
+        let to_client_stream_handle = IoTaskPool::get().spawn(async move {
+            let mut stream_writer = StreamWriter::new(connection_stream_send);
+            while let Some(msg) = to_client_stream_receiver.recv().await {
+                trace!("sending stream packet to client!: {:?}", &msg);
+                stream_writer.write(&msg).await.unwrap_or_else(|e| {
+                    error!("stream write error: {:?}", e);
+                });
+            }
+        });
 
         // await for the quic connection to be closed for any reason
         let reason = connection.closed().await;
@@ -229,19 +327,34 @@ impl Transport for WebTransportServerSocket {
     fn split(self) -> (BoxedSender, BoxedReceiver) {
         (Box::new(self.sender), Box::new(self.receiver))
     }
+
+    fn packet_sizing(&self) -> PacketSizing {
+        PacketSizing::Fixed(WEBTRANSPORT_PACKET_SIZE)
+    }
 }
 
 struct WebTransportServerSocketSender {
     server_addr: SocketAddr,
-    to_client_senders: Arc<Mutex<HashMap<SocketAddr, UnboundedSender<Box<[u8]>>>>>,
+    to_client_senders: Arc<Mutex<HashMap<SocketAddr, UnboundedSender<(Box<[u8]>, Delivery)>>>>,
 }
 
 impl PacketSender for WebTransportServerSocketSender {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.send_with_delivery(payload, address, Delivery::Datagram)
+    }
+
+    fn send_with_delivery(
+        &mut self,
+        payload: &[u8],
+        address: &SocketAddr,
+        delivery: Delivery,
+    ) -> Result<()> {
         if let Some(to_client_sender) = self.to_client_senders.lock().unwrap().get(address) {
-            to_client_sender.send(payload.into()).map_err(|e| {
-                std::io::Error::other(format!("unable to send message to client: {}", e)).into()
-            })
+            to_client_sender
+                .send((payload.into(), delivery))
+                .map_err(|e| {
+                    std::io::Error::other(format!("unable to send message to client: {}", e)).into()
+                })
         } else {
             // consider that if the channel doesn't exist, it's because the connection was closed
             Ok(())
@@ -256,13 +369,13 @@ impl PacketSender for WebTransportServerSocketSender {
 struct WebTransportServerSocketReceiver {
     buffer: [u8; MTU],
     server_addr: SocketAddr,
-    from_client_receiver: UnboundedReceiver<(Datagram, SocketAddr)>,
+    from_client_receiver: UnboundedReceiver<(Bytes, SocketAddr)>,
 }
 impl PacketReceiver for WebTransportServerSocketReceiver {
     fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
         match self.from_client_receiver.try_recv() {
             Ok((data, addr)) => {
-                self.buffer[..data.len()].copy_from_slice(data.payload().as_ref());
+                self.buffer[..data.len()].copy_from_slice(data.as_ref());
                 Ok(Some((&mut self.buffer[..data.len()], addr)))
             }
             Err(e) => {
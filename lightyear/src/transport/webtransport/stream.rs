@@ -0,0 +1,50 @@
+//! Send packets on a unidirectional WebTransport stream.
+//!
+//! A stream is a reliable and ordered sequence of bytes, so every packet is prefixed with its
+//! length (encoded as a big-endian `u16`) to split the bytes back into packets on the receiving side.
+use std::sync::Arc;
+
+use anyhow::Context;
+use bytes::Bytes;
+use wtransport::{Connection, RecvStream, SendStream};
+
+/// Writes packets on a single unidirectional stream, which is opened when the first packet is sent
+pub(crate) struct StreamWriter {
+    connection: Arc<Connection>,
+    stream: Option<SendStream>,
+}
+
+impl StreamWriter {
+    pub(crate) fn new(connection: Arc<Connection>) -> Self {
+        Self {
+            connection,
+            stream: None,
+        }
+    }
+
+    pub(crate) async fn write(&mut self, payload: &[u8]) -> anyhow::Result<()> {
+        let len = u16::try_from(payload.len()).context("packet is too big for a stream frame")?;
+        if self.stream.is_none() {
+            let stream = self.connection.open_uni().await?.await?;
+            self.stream = Some(stream);
+        }
+        let stream = self.stream.as_mut().unwrap();
+        stream.write_all(&len.to_be_bytes()).await?;
+        stream.write_all(payload).await?;
+        Ok(())
+    }
+}
+
+/// Read the packets from a stream until it is closed, calling `on_packet` for each packet
+pub(crate) async fn read_packets(
+    mut stream: RecvStream,
+    mut on_packet: impl FnMut(Bytes),
+) -> anyhow::Result<()> {
+    let mut len = [0; 2];
+    loop {
+        stream.read_exact(&mut len).await?;
+        let mut payload = vec![0; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut payload).await?;
+        on_packet(payload.into());
+    }
+}
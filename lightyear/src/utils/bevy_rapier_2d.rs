@@ -0,0 +1,237 @@
+//! Integration of `bevy_rapier2d` with lightyear's replication, prediction and rollback
+//!
+//! Provides the [`PhysicsReplicationPlugin`], which registers the `Transform` and [`Velocity`] components
+//! for replication and prediction, runs the rapier simulation in `FixedUpdate` so that it is re-run during
+//! rollback, and resets the internal state of rapier along with the predicted components.
+//!
+//! Unlike `bevy_xpbd_2d`, rapier stores the state of the rigid bodies in the [`RapierContext`] resource, and only
+//! reads the components back when they change: the position is read from the `GlobalTransform`, which is not
+//! updated before the ticks are re-simulated, and a sleeping body stays asleep. Restoring the `Transform` and
+//! [`Velocity`] components is not enough to get the same simulation when the ticks are re-simulated, so at the
+//! start of the rollback the plugin copies the corrected components (which hold the confirmed state) to the
+//! rapier bodies of the predicted entities, and wakes them up.
+//! The contacts between colliders are not restored: they are re-computed during the next physics step.
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::{
+    App, FixedUpdate, IntoSystemConfigs, IntoSystemSetConfigs, Plugin, PreUpdate, Query, Res,
+    ResMut, Startup, SystemSet, Transform, With,
+};
+use bevy_rapier2d::plugin::{
+    NoUserData, PhysicsSet, RapierConfiguration, RapierContext, RapierPhysicsPlugin, TimestepMode,
+};
+use bevy_rapier2d::prelude::{RapierRigidBodyHandle, Velocity};
+use bevy_rapier2d::rapier::dynamics::RigidBody;
+use bevy_rapier2d::rapier::math::{Isometry, Real};
+use tracing::trace;
+
+#[cfg(feature = "client")]
+use crate::client::components::Predicted;
+use crate::client::components::{ComponentSyncMode, LerpFn};
+#[cfg(feature = "client")]
+use crate::client::config::ClientConfig;
+#[cfg(feature = "client")]
+use crate::client::prediction::plugin::PredictionSet;
+#[cfg(feature = "client")]
+use crate::client::prediction::rollback::{run_rollback, Rollback, RollbackGroup};
+use crate::prelude::{AppComponentExt, ChannelDirection, TickManager};
+use crate::utils::bevy::TransformLinearInterpolation;
+
+/// SystemSets that run in the `FixedUpdate` schedule when using the [`PhysicsReplicationPlugin`]
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum PhysicsReplicationSet {
+    /// Systems that update the physics components (for example by applying the user's inputs).
+    /// Runs before [`PhysicsReplicationSet::Physics`]
+    Main,
+    /// Contains the physics simulation ([`PhysicsSet::SyncBackend`], [`PhysicsSet::StepSimulation`]
+    /// and [`PhysicsSet::Writeback`])
+    Physics,
+}
+
+/// Plugin that integrates `bevy_rapier2d` with lightyear's replication, prediction and rollback.
+/// It is only available with the `rapier_2d` feature.
+///
+/// - registers `Transform` and [`Velocity`] for replication and prediction (`Transform` is also
+///   interpolated and corrected)
+/// - adds the [`RapierPhysicsPlugin`] to the `FixedUpdate` schedule, with a timestep of exactly one tick,
+///   so that each tick (including the ticks re-simulated during rollback) runs exactly one physics step
+/// - on the client (with the `client` feature), resets the rapier state of the predicted rigid bodies to their
+///   corrected `Transform` and [`Velocity`] when a rollback is triggered
+///
+/// Systems that affect physics (e.g. movement from inputs) should be added to [`PhysicsReplicationSet::Main`].
+///
+/// This plugin must be added after the client or server plugins, like the rest of the protocol.
+pub struct PhysicsReplicationPlugin {
+    /// Direction in which the physics components are replicated
+    pub direction: ChannelDirection,
+}
+
+impl Default for PhysicsReplicationPlugin {
+    fn default() -> Self {
+        Self {
+            direction: ChannelDirection::ServerToClient,
+        }
+    }
+}
+
+impl PhysicsReplicationPlugin {
+    pub fn with_direction(mut self, direction: ChannelDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+impl Plugin for PhysicsReplicationPlugin {
+    fn build(&self, app: &mut App) {
+        // components
+        app.register_component::<Transform>(self.direction)
+            .add_prediction(ComponentSyncMode::Full)
+            .add_interpolation(ComponentSyncMode::Full)
+            .add_interpolation_fn(TransformLinearInterpolation::lerp)
+            .add_correction_fn(TransformLinearInterpolation::lerp);
+        // the velocities are not displayed, but they are needed to correctly predict the physics
+        app.register_component::<Velocity>(self.direction)
+            .add_prediction(ComponentSyncMode::Full);
+
+        // physics
+        // the simulation runs in FixedUpdate, which is the schedule that is re-run during rollback
+        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule());
+        app.configure_sets(
+            FixedUpdate,
+            (
+                (
+                    PhysicsSet::SyncBackend,
+                    PhysicsSet::StepSimulation,
+                    PhysicsSet::Writeback,
+                )
+                    .in_set(PhysicsReplicationSet::Physics),
+                (PhysicsReplicationSet::Main, PhysicsReplicationSet::Physics).chain(),
+            ),
+        );
+        app.add_systems(Startup, set_physics_timestep);
+
+        // rollback of the internal rapier state (only on the client)
+        #[cfg(feature = "client")]
+        if app.world.get_resource::<ClientConfig>().is_some() {
+            app.add_systems(
+                PreUpdate,
+                restore_rigid_bodies
+                    .in_set(PredictionSet::Rollback)
+                    .before(run_rollback),
+            );
+        }
+    }
+}
+
+/// Run exactly one physics step of one tick every time `FixedUpdate` runs, so that the
+/// simulation stays in sync with the tick (and is fully re-simulated during rollback)
+fn set_physics_timestep(tick_manager: Res<TickManager>, mut config: ResMut<RapierConfiguration>) {
+    config.timestep_mode = TimestepMode::Fixed {
+        dt: tick_manager.config.tick_duration.as_secs_f32(),
+        substeps: 1,
+    };
+}
+
+/// Reset the rapier state of the predicted rigid bodies that are rolled back to the state of their components,
+/// which were reset to the confirmed state in [`PredictionSet::PrepareRollback`].
+///
+/// The bodies are woken up, since the confirmed state might not be at rest.
+#[cfg(feature = "client")]
+fn restore_rigid_bodies(
+    mut context: ResMut<RapierContext>,
+    query: Query<
+        (
+            &RapierRigidBodyHandle,
+            &Transform,
+            &Velocity,
+            Option<&RollbackGroup>,
+        ),
+        With<Predicted>,
+    >,
+    rollback: Res<Rollback>,
+) {
+    let scale = context.physics_scale();
+    for (handle, transform, velocity, group) in query.iter() {
+        if !rollback.should_rollback_group(group) {
+            continue;
+        }
+        if let Some(body) = context.bodies.get_mut(handle.0) {
+            trace!(?handle, "Restoring the rigid body for rollback");
+            restore_rigid_body(body, transform, velocity, scale);
+        }
+    }
+}
+
+/// Copy the position and velocity of the components to the rapier rigid body, and wake it up
+#[cfg(feature = "client")]
+fn restore_rigid_body(
+    body: &mut RigidBody,
+    transform: &Transform,
+    velocity: &Velocity,
+    scale: Real,
+) {
+    let position = Isometry::new(
+        (transform.translation / scale).xy().into(),
+        transform.rotation.to_scaled_axis().z,
+    );
+    body.set_position(position, false);
+    body.set_linvel((velocity.linvel / scale).into(), false);
+    body.set_angvel(velocity.angvel, false);
+    body.wake_up(true);
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{Vec2, Vec3, World};
+    use bevy_rapier2d::rapier::dynamics::{RigidBodyBuilder, RigidBodyHandle};
+    use bevy_rapier2d::rapier::math::Vector;
+
+    use super::*;
+    use crate::client::prediction::rollback::RollbackState;
+    use crate::prelude::Tick;
+
+    /// Spawn a predicted entity whose rapier body is asleep at a mispredicted position,
+    /// while its components hold the corrected state
+    fn spawn_body(world: &mut World, group: RollbackGroup) -> RigidBodyHandle {
+        let mut body = RigidBodyBuilder::dynamic()
+            .translation([10.0, 10.0].into())
+            .build();
+        body.sleep();
+        let handle = world.resource_mut::<RapierContext>().bodies.insert(body);
+        world.spawn((
+            Predicted {
+                confirmed_entity: None,
+            },
+            RapierRigidBodyHandle(handle),
+            Transform::from_translation(Vec3::new(1.0, 2.0, 0.0)),
+            Velocity::linear(Vec2::new(3.0, 0.0)),
+            group,
+        ));
+        handle
+    }
+
+    #[test]
+    fn test_restore_rigid_bodies() {
+        let mut world = World::new();
+        world.insert_resource(RapierContext::default());
+        let rollback = Rollback::new(RollbackState::Default);
+        rollback.set_rollback_tick(Tick(10));
+        rollback.add_mispredicted_group(Some(RollbackGroup(0)));
+        world.insert_resource(rollback);
+        let rolled_back = spawn_body(&mut world, RollbackGroup(0));
+        let skipped = spawn_body(&mut world, RollbackGroup(1));
+
+        world.run_system_once(restore_rigid_bodies);
+
+        // the body of the rolled back group gets the corrected state, and is woken up
+        let context = world.resource::<RapierContext>();
+        let body = context.bodies.get(rolled_back).unwrap();
+        assert_eq!(body.translation(), &Vector::new(1.0, 2.0));
+        assert_eq!(body.linvel(), &Vector::new(3.0, 0.0));
+        assert!(!body.is_sleeping());
+        // the other groups are not rolled back
+        let body = context.bodies.get(skipped).unwrap();
+        assert_eq!(body.translation(), &Vector::new(10.0, 10.0));
+        assert!(body.is_sleeping());
+    }
+}
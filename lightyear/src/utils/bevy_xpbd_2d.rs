@@ -1,8 +1,111 @@
 //! Implement lightyear traits for some common bevy types
+//!
+//! Also provides the [`PhysicsReplicationPlugin`], which registers the `bevy_xpbd_2d` components
+//! for replication and prediction, and runs the physics simulation in `FixedUpdate` so that it is
+//! re-run during rollback.
+//!
+//! `avian` is the new name of `bevy_xpbd`, and its latest release that supports bevy 0.13 is `bevy_xpbd_2d` 0.4.
+//! The `avian` feature enables this integration, which is also re-exported in [`crate::utils::avian`].
+//! It will depend on the `avian2d` crate once lightyear upgrades to bevy 0.14.
+use bevy::prelude::{
+    App, FixedUpdate, IntoSystemSetConfigs, Plugin, Res, ResMut, Startup, SystemSet, Time,
+};
 use bevy_xpbd_2d::components::*;
 use bevy_xpbd_2d::math::Scalar;
+use bevy_xpbd_2d::prelude::{Physics, PhysicsPlugins, PhysicsSet, PhysicsTime, TimestepMode};
 use tracing::trace;
 
+use crate::client::components::ComponentSyncMode;
+use crate::prelude::{AppComponentExt, ChannelDirection, TickManager};
+
+/// SystemSets that run in the `FixedUpdate` schedule when using the [`PhysicsReplicationPlugin`]
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum PhysicsReplicationSet {
+    /// Systems that update the physics components (for example by applying the user's inputs).
+    /// Runs before [`PhysicsReplicationSet::Physics`]
+    Main,
+    /// Contains the physics simulation ([`PhysicsSet::Prepare`], [`PhysicsSet::StepSimulation`]
+    /// and [`PhysicsSet::Sync`])
+    Physics,
+}
+
+/// Plugin that integrates `bevy_xpbd_2d` with lightyear's replication, prediction and rollback.
+/// It is available with the `xpbd_2d` or `avian` features (see the module docs).
+///
+/// - registers [`Position`], [`Rotation`], [`LinearVelocity`] and [`AngularVelocity`] for
+///   replication and prediction ([`Position`] and [`Rotation`] are also interpolated and corrected)
+/// - adds the [`PhysicsPlugins`] to the `FixedUpdate` schedule, with a timestep of exactly one tick,
+///   so that each tick (including the ticks re-simulated during rollback) runs exactly one physics step
+///
+/// Systems that affect physics (e.g. movement from inputs) should be added to [`PhysicsReplicationSet::Main`].
+///
+/// This plugin must be added after the client or server plugins, like the rest of the protocol.
+pub struct PhysicsReplicationPlugin {
+    /// Direction in which the physics components are replicated
+    pub direction: ChannelDirection,
+}
+
+impl Default for PhysicsReplicationPlugin {
+    fn default() -> Self {
+        Self {
+            direction: ChannelDirection::ServerToClient,
+        }
+    }
+}
+
+impl PhysicsReplicationPlugin {
+    pub fn with_direction(mut self, direction: ChannelDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+impl Plugin for PhysicsReplicationPlugin {
+    fn build(&self, app: &mut App) {
+        // components
+        app.register_component::<Position>(self.direction)
+            .add_prediction(ComponentSyncMode::Full)
+            .add_interpolation(ComponentSyncMode::Full)
+            .add_interpolation_fn(position::lerp)
+            .add_correction_fn(position::lerp);
+        app.register_component::<Rotation>(self.direction)
+            .add_prediction(ComponentSyncMode::Full)
+            .add_interpolation(ComponentSyncMode::Full)
+            .add_interpolation_fn(rotation::lerp)
+            .add_correction_fn(rotation::lerp);
+        // the velocities are not displayed, but they are needed to correctly predict the physics
+        app.register_component::<LinearVelocity>(self.direction)
+            .add_prediction(ComponentSyncMode::Full);
+        app.register_component::<AngularVelocity>(self.direction)
+            .add_prediction(ComponentSyncMode::Full);
+
+        // physics
+        // the simulation runs in FixedUpdate, which is the schedule that is re-run during rollback
+        app.add_plugins(PhysicsPlugins::new(FixedUpdate));
+        app.configure_sets(
+            FixedUpdate,
+            (
+                (
+                    PhysicsSet::Prepare,
+                    PhysicsSet::StepSimulation,
+                    PhysicsSet::Sync,
+                )
+                    .in_set(PhysicsReplicationSet::Physics),
+                (PhysicsReplicationSet::Main, PhysicsReplicationSet::Physics).chain(),
+            ),
+        );
+        app.add_systems(Startup, set_physics_timestep);
+    }
+}
+
+/// Run exactly one physics step of one tick every time `FixedUpdate` runs, so that the
+/// simulation stays in sync with the tick (and is fully re-simulated during rollback)
+fn set_physics_timestep(tick_manager: Res<TickManager>, mut time: ResMut<Time<Physics>>) {
+    time.set_timestep_mode(TimestepMode::FixedOnce {
+        delta: tick_manager.config.tick_duration,
+    });
+}
+
 pub mod position {
 
     use super::*;
@@ -82,3 +185,117 @@ pub mod angular_velocity {
         res
     }
 }
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use bevy::prelude::{default, Vec2};
+    use bevy::utils::Duration;
+    use bevy_xpbd_2d::prelude::{Collisions, Gravity};
+
+    use super::*;
+    use crate::client::prediction::rollback::test_utils::received_confirmed_update;
+    use crate::prelude::client::{
+        Confirmed, InterpolationConfig, Predicted, PredictionConfig, SyncConfig,
+    };
+    use crate::prelude::{LinkConditionerConfig, SharedConfig, TickConfig};
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    /// A rollback re-runs the physics step of each re-simulated tick, starting from the confirmed state
+    #[test]
+    fn test_rollback_resimulates_physics() {
+        let tick_duration = Duration::from_millis(10);
+        let mut stepper = BevyStepper::new(
+            SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..default()
+            },
+            SyncConfig::default().speedup_factor(1.0),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig {
+                incoming_latency: Duration::default(),
+                incoming_jitter: Duration::default(),
+                incoming_loss: 0.0,
+            },
+            tick_duration,
+        );
+        for app in [&mut stepper.client_app, &mut stepper.server_app] {
+            app.add_plugins(PhysicsReplicationPlugin::default());
+            app.insert_resource(Gravity(Vec2::ZERO));
+            // the collision backend of bevy_xpbd_2d (which inserts this resource) is not enabled in the tests
+            app.init_resource::<Collisions>();
+        }
+        stepper.init();
+
+        // a predicted rigid body that is at rest
+        let confirmed = stepper
+            .client_app
+            .world
+            .spawn((
+                Confirmed::default(),
+                Position::default(),
+                Rotation::default(),
+                LinearVelocity::default(),
+                AngularVelocity::default(),
+            ))
+            .id();
+        let predicted = stepper
+            .client_app
+            .world
+            .spawn((
+                Predicted {
+                    confirmed_entity: Some(confirmed),
+                },
+                RigidBody::Dynamic,
+                MassPropertiesBundle {
+                    mass: Mass(1.0),
+                    inverse_mass: InverseMass(1.0),
+                    inertia: Inertia(1.0),
+                    inverse_inertia: InverseInertia(1.0),
+                    center_of_mass: CenterOfMass::default(),
+                },
+            ))
+            .id();
+        stepper
+            .client_app
+            .world
+            .get_mut::<Confirmed>(confirmed)
+            .unwrap()
+            .predicted = Some(predicted);
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper.client_app.world.get::<Position>(predicted),
+            Some(&Position::default())
+        );
+
+        // the server says that the body was moving 3 ticks ago
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world
+            .get_mut::<LinearVelocity>(confirmed)
+            .unwrap()
+            .0 = Vec2::new(100.0, 0.0);
+        received_confirmed_update(&mut stepper, confirmed, tick - 3);
+        stepper.frame_step();
+
+        // the 3 ticks since the confirmed tick are re-simulated, then the new tick runs
+        let position = stepper.client_app.world.get::<Position>(predicted).unwrap();
+        eprintln!(
+            "DBG {:?} {:?} {:?}",
+            stepper.client_app.world.get::<LinearVelocity>(predicted),
+            stepper.client_app.world.get::<RigidBody>(predicted),
+            stepper.client_app.world.resource::<Time<Physics>>()
+        );
+        assert!(
+            (position.0 - Vec2::new(4.0, 0.0)).length() < 1e-3,
+            "{position:?}"
+        );
+        assert_eq!(
+            stepper.client_app.world.get::<LinearVelocity>(predicted),
+            Some(&LinearVelocity(Vec2::new(100.0, 0.0)))
+        );
+    }
+}
@@ -12,5 +12,20 @@ pub mod bevy;
 #[cfg(feature = "xpbd_2d")]
 pub mod bevy_xpbd_2d;
 
+/// Integration of `avian`, the new name of `bevy_xpbd`.
+///
+/// The latest release of avian that supports bevy 0.13 is `bevy_xpbd_2d` 0.4, so this re-exports the
+/// [`bevy_xpbd_2d`] integration, which registers the same components (`Position`, `Rotation`,
+/// `LinearVelocity` and `AngularVelocity`).
+#[cfg_attr(docsrs, doc(cfg(feature = "avian")))]
+#[cfg(feature = "avian")]
+pub mod avian {
+    pub use super::bevy_xpbd_2d::{PhysicsReplicationPlugin, PhysicsReplicationSet};
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "rapier_2d")))]
+#[cfg(feature = "rapier_2d")]
+pub mod bevy_rapier_2d;
+
 pub(crate) mod pool;
 pub mod wrapping_id;